@@ -0,0 +1,70 @@
+/* Embeds an environment into a window owned by someone else.
+ *
+ * The window and the event loop in here stand in for the ones of a host
+ * application, like an editor, which hands the environment the raw handle to
+ * one of its windows and then keeps driving everything by itself. */
+use environment::EmbeddedEnvironment;
+use raw_window_handle::HasRawWindowHandle;
+use winit::event::{Event, WindowEvent};
+use winit::event_loop::{ControlFlow, EventLoop};
+use winit::window::WindowBuilder;
+use winit::dpi::PhysicalSize;
+use glow::HasContext;
+
+fn main() {
+	/* This is all done by the host application. */
+	let event_loop = EventLoop::new();
+	let window = WindowBuilder::new()
+		.with_title("Host Application")
+		.with_inner_size(PhysicalSize { width: 640, height: 480 })
+		.build(&event_loop)
+		.expect("could not create the host window");
+
+	/* The environment gets created on top of the window of the host. */
+	let EmbeddedEnvironment {
+		device,
+		mut swap_buffers,
+		mut resize,
+		surface_size,
+		mut delta_time,
+		..
+	} = unsafe {
+		environment::inner_start_with_window(
+			window.raw_window_handle(),
+			window.inner_size())
+	}.expect("could not embed into the host window");
+
+	let mut clock = 0.0f32;
+	event_loop.run(move |event, _, flow| {
+		*flow = ControlFlow::Poll;
+		match event {
+			Event::WindowEvent { event, window_id } if window_id == window.id() =>
+				match event {
+					WindowEvent::CloseRequested => *flow = ControlFlow::Exit,
+					WindowEvent::Resized(size) => resize(size),
+					_ => {}
+				},
+			Event::MainEventsCleared => {
+				clock += delta_time().as_secs_f32();
+
+				/* Just cycle through colors, to show that frames are going
+				 * through to the window of the host. */
+				let (width, height) = surface_size.get();
+				unsafe {
+					device.with_external_gl(|gl| {
+						gl.viewport(0, 0, width as i32, height as i32);
+						gl.clear_color(
+							clock.sin() * 0.5 + 0.5,
+							clock.cos() * 0.5 + 0.5,
+							0.5,
+							1.0);
+						gl.clear(glow::COLOR_BUFFER_BIT);
+					});
+				}
+
+				swap_buffers();
+			},
+			_ => {}
+		}
+	})
+}
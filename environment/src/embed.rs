@@ -0,0 +1,267 @@
+use std::time::Duration;
+use std::rc::Rc;
+use std::cell::RefCell;
+use winit::dpi::PhysicalSize;
+use raw_window_handle::RawWindowHandle;
+use glutin::{NotCurrent, RawContext};
+use gavle::Device;
+use crate::{FramePacer, FrameStats, SurfaceSize};
+
+/** Structures generated for an application embedded into a window owned by a
+ * host application, such as an editor.
+ *
+ * Unlike in [`Environment`], there is no window or event loop in here: both of
+ * them belong to the host, which is in charge of driving the application and
+ * of telling it about changes to the size of the window.
+ *
+ * [`Environment`]: crate::Environment */
+pub struct EmbeddedEnvironment {
+	/** The device used to render the game. */
+	pub device: Device,
+	/** A function used to swap buffers in the display device. */
+	pub swap_buffers: Box<dyn FnMut()>,
+	/** A function used to tell the context the window it renders to has been
+	 * resized, which the host must call every time that happens. */
+	pub resize: Box<dyn FnMut(PhysicalSize<u32>)>,
+	/** Current size of the window being rendered to, as last given to the
+	 * resize function. */
+	pub surface_size: SurfaceSize,
+	/** A function used to gather the time since since the last call to itself,
+	 * which also records that time in the frame statistics. */
+	pub delta_time: Box<dyn FnMut() -> Duration>,
+	/** Statistics about the times of the most recent frames. */
+	pub stats: Rc<RefCell<FrameStats>>,
+	/** Frame pacer used by the buffer swap function. */
+	pacer: Rc<RefCell<FramePacer<gavle::GpuFence>>>,
+	/** Connection to the display of the host the context was created on, if
+	 * it was created on an Xlib window. The context refers to it, so it comes
+	 * after every field holding on to the context, and goes away after them. */
+	_connection: Option<HostConnection>,
+}
+impl EmbeddedEnvironment {
+	/** Limits how many frames the device may fall behind the application by,
+	 * or lifts the limit, if none is given. This works the same way as it does
+	 * for [`Environment::set_max_frames_in_flight()`].
+	 *
+	 * [`Environment::set_max_frames_in_flight()`]: crate::Environment::set_max_frames_in_flight */
+	pub fn set_max_frames_in_flight(&self, max: Option<u32>) {
+		crate::limit_frames_in_flight(&self.device, &self.pacer, max)
+	}
+}
+
+/** Creates an environment that renders to the window behind the given handle,
+ * which currently has the given size, instead of creating a window of its own.
+ * No event loop gets created either, as the host owns that as well.
+ *
+ * The logger is only set up if the host hasn't set one up already.
+ *
+ * # Safety
+ * The handle must refer to a live window, which must outlive the environment
+ * and must not have an OpenGL context of its own made current on this thread.
+ *
+ * # Errors
+ * Only Win32, Xlib and Wayland windows are supported, on the platforms they
+ * belong to. */
+pub unsafe fn inner_start_with_window(
+	handle: RawWindowHandle,
+	size: PhysicalSize<u32>) -> Result<EmbeddedEnvironment, EmbedError> {
+
+	let _ = env_logger::try_init();
+
+	let (context, connection) = raw_context(handle, size)?;
+	let context = match context.make_current() {
+		Ok(context) => context,
+		Err((_, what)) => return Err(EmbedError::CreationFailed {
+			what: what.to_string()
+		})
+	};
+	context.resize(size);
+
+	let device = crate::device(|proc| {
+		context.get_proc_address(proc) as *const _
+	});
+
+	let context = Rc::new(context);
+	let pacer = Rc::new(RefCell::new(FramePacer::new(None)));
+	let swap_buffers = {
+		let context = context.clone();
+		crate::swap_buffers(
+			&device,
+			&pacer,
+			move || context.swap_buffers().unwrap())
+	};
+
+	let surface_size = SurfaceSize::new(size);
+	crate::resize_default_framebuffer(&device, size);
+	let resize = {
+		let surface_size = surface_size.clone();
+		let device = device.clone();
+		Box::new(move |size| {
+			context.resize(size);
+			surface_size.set(size);
+			crate::resize_default_framebuffer(&device, size);
+		})
+	};
+
+	let stats = Rc::new(RefCell::new(FrameStats::default()));
+	Ok(EmbeddedEnvironment {
+		device,
+		swap_buffers,
+		resize,
+		surface_size,
+		delta_time: crate::delta_time(&stats),
+		stats,
+		pacer,
+		_connection: connection
+	})
+}
+
+/** Builds a context on top of the window behind the given handle, along with
+ * the connection to the display of the host it was created on, for Xlib
+ * windows. Only the Wayland backend needs to be told the size of the window up
+ * front. */
+#[allow(unreachable_patterns, unused_variables, unused_mut)]
+unsafe fn raw_context(
+	handle: RawWindowHandle,
+	size: PhysicalSize<u32>)
+	-> Result<(RawContext<NotCurrent>, Option<HostConnection>), EmbedError> {
+
+	let mut connection = None;
+
+	let builder = crate::context_builder(&Default::default());
+	let context = match handle {
+		#[cfg(target_os = "windows")]
+		RawWindowHandle::Windows(handle) => {
+			use glutin::platform::windows::RawContextExt;
+			builder.build_raw_context(handle.hwnd)
+		},
+		#[cfg(any(
+			target_os = "linux",
+			target_os = "dragonfly",
+			target_os = "freebsd",
+			target_os = "netbsd",
+			target_os = "openbsd"))]
+		RawWindowHandle::Xlib(handle) => {
+			use glutin::platform::unix::RawContextExt;
+
+			let host = HostConnection::open(handle.display)?;
+			let context = builder.build_raw_x11_context(
+				host.connection.clone(),
+				handle.window);
+			connection = Some(host);
+
+			context
+		},
+		#[cfg(any(
+			target_os = "linux",
+			target_os = "dragonfly",
+			target_os = "freebsd",
+			target_os = "netbsd",
+			target_os = "openbsd"))]
+		RawWindowHandle::Wayland(handle) => {
+			use glutin::platform::unix::RawContextExt;
+			builder.build_raw_wayland_context(
+				handle.display as *mut _,
+				handle.surface,
+				size.width,
+				size.height)
+		},
+		other => return Err(EmbedError::UnsupportedWindow {
+			what: format!("{:?}", other)
+		})
+	};
+
+	let context = context.map_err(|what| EmbedError::CreationFailed {
+		what: what.to_string()
+	})?;
+	Ok((context, connection))
+}
+
+/** Connection to the X server through the display of a host application.
+ *
+ * Glutin only creates contexts on top of connections it gets handed, which
+ * can only be opened from scratch, on a display of their own, while the
+ * context has to be created on the display of the host, which the window
+ * belongs to. So the connection gets opened as usual, for the function tables
+ * of Xlib, and gets pointed at the display of the host for as long as the
+ * context is around. Our own display stays open all along, and gets put back
+ * once the context is gone, so that the connection only ever closes the
+ * display it opened, and never the one of the host. */
+#[cfg(any(
+	target_os = "linux",
+	target_os = "dragonfly",
+	target_os = "freebsd",
+	target_os = "netbsd",
+	target_os = "openbsd"))]
+struct HostConnection {
+	/** The connection, pointed at the display of the host. */
+	connection: std::sync::Arc<glutin::platform::unix::x11::XConnection>,
+	/** The display the connection was opened with. */
+	own: *mut std::os::raw::c_void,
+}
+#[cfg(any(
+	target_os = "linux",
+	target_os = "dragonfly",
+	target_os = "freebsd",
+	target_os = "netbsd",
+	target_os = "openbsd"))]
+impl HostConnection {
+	/** Opens a connection and points it at the given display of the host. */
+	unsafe fn open(host: *mut std::os::raw::c_void) -> Result<Self, EmbedError> {
+		use glutin::platform::unix::x11::XConnection;
+
+		let mut connection = XConnection::new(None)
+			.map_err(|what| EmbedError::CreationFailed {
+				what: format!("could not load xlib: {:?}", what)
+			})?;
+		let own = std::mem::replace(&mut connection.display, host as *mut _);
+
+		Ok(Self {
+			connection: std::sync::Arc::new(connection),
+			own: own as *mut _
+		})
+	}
+}
+#[cfg(any(
+	target_os = "linux",
+	target_os = "dragonfly",
+	target_os = "freebsd",
+	target_os = "netbsd",
+	target_os = "openbsd"))]
+impl Drop for HostConnection {
+	fn drop(&mut self) {
+		match std::sync::Arc::get_mut(&mut self.connection) {
+			Some(connection) => connection.display = self.own as *mut _,
+			None => {
+				/* Something still holds on to the connection, and it would
+				 * close the display of the host when it lets go of it. Better
+				 * to never let the connection go away. */
+				log::warn!(target: "environment::embed",
+					"The connection to the display of the host outlived the \
+					context created on it, and is being leaked");
+				std::mem::forget(self.connection.clone());
+			}
+		}
+	}
+}
+
+/** Contexts on windows other than Xlib ones aren't tied to a connection. */
+#[cfg(not(any(
+	target_os = "linux",
+	target_os = "dragonfly",
+	target_os = "freebsd",
+	target_os = "netbsd",
+	target_os = "openbsd")))]
+enum HostConnection {}
+
+#[derive(Debug, thiserror::Error)]
+pub enum EmbedError {
+	#[error("windows of this kind can't be embedded into: {what}")]
+	UnsupportedWindow {
+		what: String
+	},
+	#[error("could not create an opengl context for the window: {what}")]
+	CreationFailed {
+		what: String
+	},
+}
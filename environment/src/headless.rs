@@ -0,0 +1,192 @@
+use std::time::Duration;
+use std::rc::Rc;
+use std::cell::RefCell;
+use winit::dpi::PhysicalSize;
+use winit::event_loop::EventLoop;
+use winit::window::WindowBuilder;
+use gavle::Device;
+use crate::{EnvironmentOptions, FrameStats, SurfaceSize};
+
+/** Structures generated for an application rendering offscreen, such as a
+ * test checking the output of a render pass.
+ *
+ * Unlike in [`Environment`], there is no event loop to be run in here, and no
+ * window to be shown. Whatever gets rendered should go into framebuffers
+ * created by the application and be read back from them, as the default
+ * framebuffer may not be backed by anything at all.
+ *
+ * [`Environment`]: crate::Environment */
+pub struct HeadlessEnvironment {
+	/** The device used to render. */
+	pub device: Device,
+	/** A function used to mark the end of a frame, which doesn't present
+	 * anything, but lets the device retire resources like it normally would.
+	 * This also owns the context, so it must outlive the device. */
+	pub swap_buffers: Box<dyn FnMut()>,
+	/** Size the context was created with. */
+	pub surface_size: SurfaceSize,
+	/** A function used to gather the time since since the last call to itself,
+	 * which also records that time in the frame statistics. */
+	pub delta_time: Box<dyn FnMut() -> Duration>,
+	/** Statistics about the times of the most recent frames. */
+	pub stats: Rc<RefCell<FrameStats>>,
+}
+
+/** Creates an environment that renders offscreen, with a context whose default
+ * framebuffer, where there is one, has the given size.
+ *
+ * A headless context is tried first, which, depending on the platform, is
+ * either surfaceless or backed by a pixel buffer. Platforms that have neither
+ * get a context on a window that never gets shown instead.
+ *
+ * Unlike with the other environments, this may be called from any thread, and
+ * more than once, which lets tests each create an environment of their own.
+ * The logger is only set up if it hasn't been set up already.
+ *
+ * # Errors
+ * This fails if there is no display to connect to, as is the case on most
+ * machines running continuous integration, or if no context could be created
+ * for it. Tests depending on rendering should skip themselves in that case. */
+pub fn headless(width: u32, height: u32) -> Result<HeadlessEnvironment, HeadlessError> {
+	let _ = env_logger::try_init();
+
+	let size = PhysicalSize { width, height };
+	let event_loop = event_loop()?;
+	let context = match context(&event_loop, size) {
+		Ok(context) => context,
+		Err(what) => return Err(HeadlessError::CreationFailed { what })
+	};
+
+	let device = unsafe {
+		Device::new_from_loader_function(
+			|proc| context.get_proc_address(proc) as *const _,
+			Default::default())
+	}.map_err(|what| HeadlessError::CreationFailed {
+		what: format!("could not create a device: {}", what)
+	})?;
+	crate::sanitize_writes(&device);
+	gavle::log_startup_summary(device.information());
+	crate::resize_default_framebuffer(&device, size);
+
+	let swap_buffers = {
+		let device = device.clone();
+		Box::new(move || {
+			/* Nothing gets presented, this only keeps the context, and the
+			 * event loop it was created from, alive for as long as the
+			 * function is. */
+			let _ = (&context, &event_loop);
+			device.mark_frame()
+		})
+	};
+
+	let stats = Rc::new(RefCell::new(FrameStats::default()));
+	stats.borrow_mut().set_log_interval(None);
+
+	Ok(HeadlessEnvironment {
+		device,
+		swap_buffers,
+		surface_size: SurfaceSize::new(size),
+		delta_time: crate::delta_time(&stats),
+		stats
+	})
+}
+
+/** Creates an event loop that does not have to live on the main thread, on the
+ * platforms where that restriction may be lifted. */
+fn event_loop() -> Result<EventLoop<()>, HeadlessError> {
+	#[cfg(any(
+		target_os = "linux",
+		target_os = "dragonfly",
+		target_os = "freebsd",
+		target_os = "netbsd",
+		target_os = "openbsd"))]
+	fn new() -> EventLoop<()> {
+		use winit::platform::unix::EventLoopExtUnix;
+		EventLoop::new_any_thread()
+	}
+	#[cfg(target_os = "windows")]
+	fn new() -> EventLoop<()> {
+		use winit::platform::windows::EventLoopExtWindows;
+		EventLoop::new_any_thread()
+	}
+	#[cfg(not(any(
+		target_os = "linux",
+		target_os = "dragonfly",
+		target_os = "freebsd",
+		target_os = "netbsd",
+		target_os = "openbsd",
+		target_os = "windows")))]
+	fn new() -> EventLoop<()> {
+		EventLoop::new()
+	}
+
+	/* Winit panics rather than failing when there is no display to connect
+	 * to, which is exactly the case we want to report gracefully. */
+	std::panic::catch_unwind(new).map_err(|what| {
+		let what = what.downcast_ref::<String>().cloned()
+			.or_else(|| what.downcast_ref::<&str>().map(|what| what.to_string()))
+			.unwrap_or_else(|| "no reason given".to_owned());
+		HeadlessError::Unsupported { what }
+	})
+}
+
+/** A current context created with the given event loop, either headless or on
+ * a hidden window, whichever is available first. */
+enum Context {
+	Headless(glutin::Context<glutin::PossiblyCurrent>),
+	Hidden(glutin::WindowedContext<glutin::PossiblyCurrent>),
+}
+impl Context {
+	fn get_proc_address(&self, proc: &str) -> *const std::os::raw::c_void {
+		match self {
+			Self::Headless(context) => context.get_proc_address(proc),
+			Self::Hidden(context) => context.get_proc_address(proc),
+		}
+	}
+}
+
+/** Creates and makes current a context of the given size, falling back to one
+ * on a hidden window when a headless one can't be had. */
+fn context(event_loop: &EventLoop<()>, size: PhysicalSize<u32>) -> Result<Context, String> {
+	/* Multisampled default framebuffers can't be read back from. */
+	let options = EnvironmentOptions {
+		samples: 0,
+		..Default::default()
+	};
+
+	let headless = crate::context_builder(&options)
+		.build_headless(event_loop, size)
+		.map_err(|what| what.to_string())
+		.and_then(|context| unsafe { context.make_current() }
+			.map_err(|(_, what)| what.to_string()));
+	let what = match headless {
+		Ok(context) => return Ok(Context::Headless(context)),
+		Err(what) => what
+	};
+	log::debug!(target: "environment::headless",
+		"could not create a headless context, falling back to a hidden \
+		window: {}", what);
+
+	let window = WindowBuilder::new()
+		.with_visible(false)
+		.with_inner_size(size);
+	crate::context_builder(&options)
+		.build_windowed(window, event_loop)
+		.map_err(|hidden| format!("could not create a headless context: {}, \
+			nor one on a hidden window: {}", what, hidden))
+		.and_then(|context| unsafe { context.make_current() }
+			.map_err(|(_, what)| what.to_string()))
+		.map(Context::Hidden)
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum HeadlessError {
+	#[error("offscreen rendering is not supported here: {what}")]
+	Unsupported {
+		what: String
+	},
+	#[error("could not create an offscreen opengl context: {what}")]
+	CreationFailed {
+		what: String
+	},
+}
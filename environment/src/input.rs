@@ -0,0 +1,293 @@
+use std::collections::HashSet;
+use winit::dpi::PhysicalPosition;
+use winit::event::{ElementState, MouseButton, MouseScrollDelta, VirtualKeyCode, WindowEvent};
+use crate::SurfaceSize;
+
+/** Number of pixels worth of scrolling that count as scrolling a single line,
+ * for devices that scroll by pixels rather than by lines. */
+pub const PIXELS_PER_SCROLL_LINE: f64 = 20.0;
+
+/** State of the keyboard and the mouse, as seen through the events of a
+ * window, which it must be fed with through [`handle()`].
+ *
+ * Besides what is held down at the moment, the state keeps track of what has
+ * been pressed and released, and of how much has been scrolled, since the last
+ * call to [`end_frame()`], which should be made once all of those have been
+ * acted on, usually at the end of every frame.
+ *
+ * Keys are identified by the symbol they produce in the current layout, rather
+ * than by their scan code, so that the same key means the same thing across
+ * platforms and keyboard layouts.
+ *
+ * [`handle()`]: Self::handle
+ * [`end_frame()`]: Self::end_frame */
+#[derive(Debug, Clone)]
+pub struct Input {
+	/** Size of the surface the cursor moves over. */
+	surface_size: SurfaceSize,
+	/** Keys currently held down. */
+	keys_down: HashSet<VirtualKeyCode>,
+	/** Keys that went down since the end of the last frame. */
+	keys_pressed: HashSet<VirtualKeyCode>,
+	/** Keys that went up since the end of the last frame. */
+	keys_released: HashSet<VirtualKeyCode>,
+	/** Mouse buttons currently held down. */
+	buttons_down: HashSet<MouseButton>,
+	/** Mouse buttons that went down since the end of the last frame. */
+	buttons_pressed: HashSet<MouseButton>,
+	/** Mouse buttons that went up since the end of the last frame. */
+	buttons_released: HashSet<MouseButton>,
+	/** Position of the cursor, in pixels, if it is over the window. */
+	cursor: Option<PhysicalPosition<f64>>,
+	/** Lines scrolled since the end of the last frame. */
+	scroll: [f32; 2],
+}
+impl Input {
+	/** Creates a new input state with nothing held down, for a window whose
+	 * surface has the given size. */
+	pub fn new(surface_size: SurfaceSize) -> Self {
+		Self {
+			surface_size,
+			keys_down: Default::default(),
+			keys_pressed: Default::default(),
+			keys_released: Default::default(),
+			buttons_down: Default::default(),
+			buttons_pressed: Default::default(),
+			buttons_released: Default::default(),
+			cursor: None,
+			scroll: [0.0; 2]
+		}
+	}
+
+	/** Updates the state with the given event of the window. */
+	pub fn handle(&mut self, event: &WindowEvent) {
+		match event {
+			WindowEvent::KeyboardInput { input, .. } =>
+				if let Some(key) = input.virtual_keycode {
+					self.key(key, input.state)
+				},
+			WindowEvent::MouseInput { button, state, .. } =>
+				self.button(*button, *state),
+			WindowEvent::CursorMoved { position, .. } =>
+				self.cursor = Some(*position),
+			WindowEvent::CursorLeft { .. } =>
+				self.cursor = None,
+			WindowEvent::MouseWheel { delta, .. } =>
+				self.scroll(*delta),
+			/* Whatever is held down while the window is out of focus never
+			 * gets released as far as we can tell, so let go of everything. */
+			WindowEvent::Focused(false) =>
+				self.release_all(),
+			_ => {}
+		}
+	}
+
+	/** Forgets about what has been pressed, released and scrolled so far. */
+	pub fn end_frame(&mut self) {
+		self.keys_pressed.clear();
+		self.keys_released.clear();
+		self.buttons_pressed.clear();
+		self.buttons_released.clear();
+		self.scroll = [0.0; 2];
+	}
+
+	/** Whether the given key is held down. */
+	pub fn key_down(&self, key: VirtualKeyCode) -> bool {
+		self.keys_down.contains(&key)
+	}
+
+	/** Whether the given key went down during this frame. */
+	pub fn key_pressed(&self, key: VirtualKeyCode) -> bool {
+		self.keys_pressed.contains(&key)
+	}
+
+	/** Whether the given key went up during this frame. */
+	pub fn key_released(&self, key: VirtualKeyCode) -> bool {
+		self.keys_released.contains(&key)
+	}
+
+	/** Direction along an axis driven by a pair of keys, which is minus one
+	 * with only the negative key held down, one with only the positive key
+	 * held down, and zero otherwise. */
+	pub fn axis(&self, negative: VirtualKeyCode, positive: VirtualKeyCode) -> f32 {
+		match (self.key_down(negative), self.key_down(positive)) {
+			(true, false) => -1.0,
+			(false, true) => 1.0,
+			_ => 0.0
+		}
+	}
+
+	/** Whether the given mouse button is held down. */
+	pub fn button_down(&self, button: MouseButton) -> bool {
+		self.buttons_down.contains(&button)
+	}
+
+	/** Whether the given mouse button went down during this frame. */
+	pub fn button_pressed(&self, button: MouseButton) -> bool {
+		self.buttons_pressed.contains(&button)
+	}
+
+	/** Whether the given mouse button went up during this frame. */
+	pub fn button_released(&self, button: MouseButton) -> bool {
+		self.buttons_released.contains(&button)
+	}
+
+	/** Position of the cursor in pixels, from the top left corner of the
+	 * window, if it is over the window. */
+	pub fn cursor_position(&self) -> Option<[f64; 2]> {
+		self.cursor.map(|position| [position.x, position.y])
+	}
+
+	/** Position of the cursor in normalized device coordinates, going from
+	 * minus one to one, left to right and bottom to top, if it is over the
+	 * window, and the window is not empty. */
+	pub fn cursor_ndc(&self) -> Option<[f32; 2]> {
+		let (width, height) = self.surface_size.get();
+		if width == 0 || height == 0 { return None }
+
+		self.cursor.map(|position| [
+			(position.x / f64::from(width) * 2.0 - 1.0) as f32,
+			(1.0 - position.y / f64::from(height) * 2.0) as f32
+		])
+	}
+
+	/** Lines scrolled horizontally and vertically during this frame. */
+	pub fn scroll_delta(&self) -> [f32; 2] {
+		self.scroll
+	}
+
+	/** Records a key going up or down. Keys being repeated while held down do
+	 * not count as being pressed again. */
+	fn key(&mut self, key: VirtualKeyCode, state: ElementState) {
+		edge(&mut self.keys_down, &mut self.keys_pressed, &mut self.keys_released, key, state)
+	}
+
+	/** Records a mouse button going up or down. */
+	fn button(&mut self, button: MouseButton, state: ElementState) {
+		edge(&mut self.buttons_down, &mut self.buttons_pressed, &mut self.buttons_released, button, state)
+	}
+
+	/** Adds the given delta to the scrolling done during this frame. */
+	fn scroll(&mut self, delta: MouseScrollDelta) {
+		let [x, y] = match delta {
+			MouseScrollDelta::LineDelta(x, y) => [x, y],
+			MouseScrollDelta::PixelDelta(delta) => [
+				(delta.x / PIXELS_PER_SCROLL_LINE) as f32,
+				(delta.y / PIXELS_PER_SCROLL_LINE) as f32
+			]
+		};
+		self.scroll[0] += x;
+		self.scroll[1] += y;
+	}
+
+	/** Releases every key and button held down. */
+	fn release_all(&mut self) {
+		self.keys_released.extend(self.keys_down.drain());
+		self.buttons_released.extend(self.buttons_down.drain());
+	}
+}
+
+/** Records an element of a set going up or down, along with the edge, if the
+ * element is changing state. */
+fn edge<T>(
+	down: &mut HashSet<T>,
+	pressed: &mut HashSet<T>,
+	released: &mut HashSet<T>,
+	element: T,
+	state: ElementState)
+	where T: Copy + Eq + std::hash::Hash {
+
+	match state {
+		ElementState::Pressed => if down.insert(element) {
+			pressed.insert(element);
+		},
+		ElementState::Released => if down.remove(&element) {
+			released.insert(element);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use winit::dpi::PhysicalSize;
+
+	fn input() -> Input {
+		Input::new(SurfaceSize::new(PhysicalSize { width: 800, height: 600 }))
+	}
+
+	#[test]
+	fn edges_last_until_the_end_of_the_frame() {
+		let mut input = input();
+		input.key(VirtualKeyCode::W, ElementState::Pressed);
+		assert!(input.key_down(VirtualKeyCode::W));
+		assert!(input.key_pressed(VirtualKeyCode::W));
+
+		/* Repeats are not presses. */
+		input.end_frame();
+		input.key(VirtualKeyCode::W, ElementState::Pressed);
+		assert!(input.key_down(VirtualKeyCode::W));
+		assert!(!input.key_pressed(VirtualKeyCode::W));
+
+		/* Tapping a key within a single frame shows up as both edges. */
+		input.key(VirtualKeyCode::W, ElementState::Released);
+		input.key(VirtualKeyCode::W, ElementState::Pressed);
+		input.key(VirtualKeyCode::W, ElementState::Released);
+		assert!(!input.key_down(VirtualKeyCode::W));
+		assert!(input.key_pressed(VirtualKeyCode::W));
+		assert!(input.key_released(VirtualKeyCode::W));
+
+		input.end_frame();
+		assert!(!input.key_released(VirtualKeyCode::W));
+	}
+
+	#[test]
+	fn opposing_keys_cancel_out() {
+		let mut input = input();
+		assert_eq!(input.axis(VirtualKeyCode::A, VirtualKeyCode::W), 0.0);
+		input.key(VirtualKeyCode::A, ElementState::Pressed);
+		assert_eq!(input.axis(VirtualKeyCode::A, VirtualKeyCode::W), -1.0);
+		input.key(VirtualKeyCode::W, ElementState::Pressed);
+		assert_eq!(input.axis(VirtualKeyCode::A, VirtualKeyCode::W), 0.0);
+		input.key(VirtualKeyCode::A, ElementState::Released);
+		assert_eq!(input.axis(VirtualKeyCode::A, VirtualKeyCode::W), 1.0);
+	}
+
+	#[test]
+	fn losing_focus_releases_everything() {
+		let mut input = input();
+		input.key(VirtualKeyCode::A, ElementState::Pressed);
+		input.button(MouseButton::Left, ElementState::Pressed);
+		input.end_frame();
+
+		input.release_all();
+		assert!(!input.key_down(VirtualKeyCode::A));
+		assert!(input.key_released(VirtualKeyCode::A));
+		assert!(!input.button_down(MouseButton::Left));
+		assert!(input.button_released(MouseButton::Left));
+	}
+
+	#[test]
+	fn cursor_maps_to_normalized_coordinates() {
+		let mut input = input();
+		assert_eq!(input.cursor_ndc(), None);
+
+		input.cursor = Some(PhysicalPosition { x: 200.0, y: 150.0 });
+		assert_eq!(input.cursor_position(), Some([200.0, 150.0]));
+		assert_eq!(input.cursor_ndc(), Some([-0.5, 0.5]));
+
+		input.surface_size.set(PhysicalSize { width: 400, height: 300 });
+		assert_eq!(input.cursor_ndc(), Some([0.0, 0.0]));
+	}
+
+	#[test]
+	fn scrolling_accumulates_over_the_frame() {
+		let mut input = input();
+		input.scroll(MouseScrollDelta::LineDelta(0.0, 1.0));
+		input.scroll(MouseScrollDelta::PixelDelta(PhysicalPosition { x: 10.0, y: 40.0 }));
+		assert_eq!(input.scroll_delta(), [0.5, 3.0]);
+
+		input.end_frame();
+		assert_eq!(input.scroll_delta(), [0.0, 0.0]);
+	}
+}
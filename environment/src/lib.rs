@@ -3,6 +3,31 @@ use winit::dpi::PhysicalSize;
 use winit::window::{WindowBuilder, Window};
 use winit::event_loop::EventLoop;
 use gavle::Device;
+use std::rc::Rc;
+use std::cell::RefCell;
+
+mod pacer;
+pub use pacer::*;
+mod options;
+pub use options::*;
+mod surface;
+pub use surface::*;
+mod timestep;
+pub use timestep::*;
+mod input;
+pub use input::*;
+mod stats;
+pub use stats::*;
+
+#[cfg(all(feature = "embed", not(target_arch = "wasm32")))]
+mod embed;
+#[cfg(all(feature = "embed", not(target_arch = "wasm32")))]
+pub use embed::*;
+
+#[cfg(all(feature = "headless", not(target_arch = "wasm32")))]
+mod headless;
+#[cfg(all(feature = "headless", not(target_arch = "wasm32")))]
+pub use headless::*;
 
 /** Structures generated from the environment the application is running in. */
 pub struct Environment {
@@ -14,8 +39,58 @@ pub struct Environment {
 	pub device: Device,
 	/** A function used to swap buffers in the display device. */
 	pub swap_buffers: Box<dyn FnMut()>,
-	/** A function used to gather the time since since the last call to itself. */
-	pub delta_time: Box<dyn FnMut() -> Duration>
+	/** A function used to tell the environment the window has been resized,
+	 * which the application must call with the new size every time it gets a
+	 * [`WindowEvent::Resized`] event. This resizes the context, where that is
+	 * needed, and updates the size in [`surface_size`].
+	 *
+	 * [`WindowEvent::Resized`]: winit::event::WindowEvent::Resized
+	 * [`surface_size`]: Self::surface_size */
+	pub resize: Box<dyn FnMut(PhysicalSize<u32>)>,
+	/** Current size of the surface being rendered to. */
+	pub surface_size: SurfaceSize,
+	/** State of the keyboard and the mouse, which the application must feed
+	 * with the events of the window, through [`Input::handle()`]. */
+	pub input: Input,
+	/** A function used to gather the time since since the last call to itself,
+	 * which also records that time in [`stats`].
+	 *
+	 * [`stats`]: Self::stats */
+	pub delta_time: Box<dyn FnMut() -> Duration>,
+	/** Statistics about the times of the most recent frames. */
+	pub stats: Rc<RefCell<FrameStats>>,
+	/** Frame pacer used by the buffer swap function. */
+	pacer: Rc<RefCell<FramePacer<gavle::GpuFence>>>,
+}
+impl Environment {
+	/** Limits how many frames the device may fall behind the application by,
+	 * or lifts the limit, if none is given. The limit is off by default, and
+	 * [`DEFAULT_MAX_FRAMES_IN_FLIGHT`] is a good value to turn it on with.
+	 *
+	 * With the limit on, the buffer swap function waits for the device to
+	 * finish older frames before returning, trading some throughput for less
+	 * latency between input and the frames reflecting it. This has no effect
+	 * in browsers, which are in charge of presentation themselves.
+	 *
+	 * Resources retired through the device are held for as many frames as
+	 * the limit, or for [`DEFAULT_MAX_FRAMES_IN_FLIGHT`] with it lifted.
+	 *
+	 * [`DEFAULT_MAX_FRAMES_IN_FLIGHT`]: DEFAULT_MAX_FRAMES_IN_FLIGHT */
+	pub fn set_max_frames_in_flight(&self, max: Option<u32>) {
+		limit_frames_in_flight(&self.device, &self.pacer, max)
+	}
+}
+
+/** Changes the maximum number of frames in flight of the given pacer, along
+ * with the retirement latency of the device it paces. */
+fn limit_frames_in_flight(
+	device: &Device,
+	pacer: &RefCell<FramePacer<gavle::GpuFence>>,
+	max: Option<u32>) {
+
+	pacer.borrow_mut().set_max_frames_in_flight(max);
+	device.set_retirement_latency(
+		max.map(|max| max.max(1)).unwrap_or(DEFAULT_MAX_FRAMES_IN_FLIGHT));
 }
 
 /**
@@ -35,10 +110,62 @@ pub struct Environment {
   * control to the run function. */
  environment::main!(run);
  ```
+
+ The window and the context may also be set up differently from the defaults,
+ by giving the macro an expression for the [`EnvironmentOptions`] to use:
+
+ ```rust,norun
+ fn run(_: environment::Environment) {}
+
+ environment::main!(run, environment::EnvironmentOptions {
+ 	title: "Incredible Demo".to_owned(),
+ 	samples: 4,
+ 	..Default::default()
+ });
+ ```
+
+ Applications that would rather have their simulation run in fixed steps may
+ instead give the macro the duration of a step, along with a function setting
+ up a [`FixedApplication`], which then gets driven by [`run_fixed()`]. Options
+ may follow the duration, just like above:
+
+ ```rust,norun
+ use std::time::Duration;
+
+ struct Demo;
+ impl environment::FixedApplication for Demo {
+ 	fn update(&mut self, _: Duration, _: &environment::Input) {}
+ 	fn render(&mut self, _: f32) {}
+ }
+
+ fn setup(_: &environment::Environment) -> Demo { Demo }
+
+ environment::main!(setup, fixed = Duration::from_millis(16));
+ ```
  */
 #[macro_export]
 macro_rules! main {
 	($main:ident) => {
+		$crate::main!($main, $crate::EnvironmentOptions::default());
+	};
+	($setup:path, fixed = $tick:expr) => {
+		$crate::main!($setup, fixed = $tick, $crate::EnvironmentOptions::default());
+	};
+	($setup:path, fixed = $tick:expr, $options:expr) => {
+		#[cfg(target_arch = "wasm32")]
+		#[wasm_bindgen::prelude::wasm_bindgen(start)]
+		pub fn wasm_start() {
+			main()
+		}
+
+		fn main() {
+			use environment::{inner_start_with_options, run_fixed};
+			let env = inner_start_with_options(&$options);
+			let application = $setup(&env);
+			run_fixed(env, $tick, application);
+		}
+	};
+	($main:ident, $options:expr) => {
 		#[cfg(target_arch = "wasm32")]
 		#[wasm_bindgen::prelude::wasm_bindgen(start)]
 		pub fn wasm_start() {
@@ -46,39 +173,145 @@ macro_rules! main {
 		}
 
 		fn main() {
-			use environment::inner_start;
-			let env = inner_start();
+			use environment::inner_start_with_options;
+			let env = inner_start_with_options(&$options);
 			$main(env);
 		}
-	}
+	};
 }
 
 /** Creates a new window and event loop pair. */
-fn window() -> (EventLoop<()>, WindowBuilder) {
+fn window(options: &EnvironmentOptions) -> (EventLoop<()>, WindowBuilder) {
 	let event_loop = winit::event_loop::EventLoop::new();
 	let window = winit::window::WindowBuilder::default()
-		.with_title("Ricardo")
-		.with_resizable(true)
-		.with_inner_size(PhysicalSize {
-			width: 800,
-			height: 600
-		});
+		.with_title(options.title.clone())
+		.with_resizable(options.resizable)
+		.with_inner_size(options.size);
 
 	(event_loop, window)
 }
 
+/** Creates a builder for the kind of context the given options ask for. */
+#[cfg(not(target_arch = "wasm32"))]
+fn context_builder<'a>(options: &EnvironmentOptions)
+	-> glutin::ContextBuilder<'a, glutin::NotCurrent> {
+
+	let api = match options.gl.api {
+		GlApi::OpenGl => glutin::Api::OpenGl,
+		GlApi::OpenGlEs => glutin::Api::OpenGlEs,
+	};
+	glutin::ContextBuilder::new()
+		.with_gl(glutin::GlRequest::Specific(api, options.gl.version))
+		.with_gl_profile(glutin::GlProfile::Core)
+		.with_vsync(options.vsync)
+		.with_multisampling(options.samples)
+}
+
+/** Creates a new device, along with its context, from the given loader
+ * function, set up the same way for every kind of native environment. */
+#[cfg(not(target_arch = "wasm32"))]
+unsafe fn device<F>(loader: F) -> Device
+	where F: FnMut(&str) -> *const std::os::raw::c_void {
+
+	let device = Device::new_from_loader_function(loader, Default::default())
+		.unwrap();
+	sanitize_writes(&device);
+	gavle::log_startup_summary(device.information());
+
+	device
+}
+
+/** Turns on the buffer write sanitizer of the given device in debug builds, so
+ * that non-finite values making their way into buffers get caught as soon as
+ * they're written, rather than when the frame comes out wrong. */
+fn sanitize_writes(device: &Device) {
+	if cfg!(debug_assertions) {
+		device.set_write_sanitizer(gavle::SanitizeMode::Panic);
+	}
+}
+
+/** Tells the given device the default framebuffer has been resized to the
+ * given size, as it has no way of knowing on its own. */
+fn resize_default_framebuffer(device: &Device, size: PhysicalSize<u32>) {
+	device.resize_default_framebuffer(size.width, size.height)
+}
+
+/** Wraps the given function, which swaps the buffers of a native context, into
+ * one that also paces the frame and marks its end in the device. */
+#[cfg(not(target_arch = "wasm32"))]
+fn swap_buffers<F>(
+	device: &Device,
+	pacer: &Rc<RefCell<FramePacer<gavle::GpuFence>>>,
+	mut swap: F) -> Box<dyn FnMut()>
+	where F: FnMut() + 'static {
+
+	let device = device.clone();
+	let pacer = pacer.clone();
+	Box::new(move || {
+		swap();
+		pace(&device, &pacer);
+		device.mark_frame();
+	})
+}
+
+/** Creates a function that measures the time since the last call to itself,
+ * recording it in the given frame statistics. */
+#[cfg(not(target_arch = "wasm32"))]
+fn delta_time(stats: &Rc<RefCell<FrameStats>>) -> Box<dyn FnMut() -> Duration> {
+	use std::time::Instant;
+	let stats = stats.clone();
+	let mut last = Instant::now();
+
+	Box::new(move || {
+		let now = Instant::now();
+		let delta = now.duration_since(last);
+		last = now;
+
+		stats.borrow_mut().tick(delta);
+		delta
+	})
+}
+
+/** Paces the presentation of a frame on the given device, right after its
+ * buffers were swapped. */
+#[cfg(not(target_arch = "wasm32"))]
+fn pace(device: &Device, pacer: &RefCell<FramePacer<gavle::GpuFence>>) {
+	let mut pacer = pacer.borrow_mut();
+	if pacer.max_frames_in_flight().is_none() { return }
+
+	let fence = match device.fence() {
+		Ok(fence) => Some(fence),
+		Err(what) => {
+			log::debug!(target: "environment::pacer",
+				"could not create a fence for frame pacing, falling back to \
+				draining the device: {}", what);
+			None
+		}
+	};
+	pacer.frame_presented(
+		fence,
+		|fence| if let Err(what) = fence.wait(Duration::from_secs(1)) {
+			log::warn!(target: "environment::pacer",
+				"could not wait on a frame pacing fence: {}", what);
+		},
+		|| device.finish());
+}
+
+/** Inner part of the start function, with the default options. Clients
+ * should use [the main! macro] instead of this function in pretty much every
+ * case. */
+pub fn inner_start() -> Environment {
+	inner_start_with_options(&EnvironmentOptions::default())
+}
+
 /** Inner part of the start function. Clients should use [the main! macro]
  * instead of this function in pretty much every case. */
 #[cfg(not(target_arch = "wasm32"))]
-pub fn inner_start() -> Environment {
+pub fn inner_start_with_options(options: &EnvironmentOptions) -> Environment {
 	env_logger::init();
-	let (event_loop, window_builder) = window();
+	let (event_loop, window_builder) = window(options);
 
-	let windowed_context = glutin::ContextBuilder::new()
-		.with_gl(glutin::GlRequest::Specific(glutin::Api::OpenGlEs, (3, 0)))
-		.with_gl_profile(glutin::GlProfile::Core)
-		.with_vsync(false)
-		.with_multisampling(8)
+	let windowed_context = context_builder(options)
 		.build_windowed(window_builder, &event_loop)
 		.expect("could not initialize opengl context");
 
@@ -88,41 +321,45 @@ pub fn inner_start() -> Environment {
 			panic!("could not use the created opengl context: {}", what)
 	};
 
-	let device = gavle::Device::new_from_context(unsafe {
-		glow::Context::from_loader_function(|proc| {
-			context.get_proc_address(proc) as *const _
-		})
-	}).unwrap();
+	let device = unsafe {
+		device(|proc| context.get_proc_address(proc) as *const _)
+	};
 
 	let (context, window) = unsafe { context.split() };
+	let context = Rc::new(context);
+	let pacer = Rc::new(RefCell::new(FramePacer::new(None)));
+	let swap_buffers = {
+		let context = context.clone();
+		swap_buffers(
+			&device,
+			&pacer,
+			move || context.swap_buffers().unwrap())
+	};
 
-	use std::time::Instant;
-	let mut now = Instant::now();
-	let mut frames = 0u32;
-	let mut dnow = Instant::now();
+	let surface_size = SurfaceSize::new(window.inner_size());
+	resize_default_framebuffer(&device, window.inner_size());
+	let resize = {
+		let surface_size = surface_size.clone();
+		let device = device.clone();
+		Box::new(move |size| {
+			context.resize(size);
+			surface_size.set(size);
+			resize_default_framebuffer(&device, size);
+		})
+	};
 
+	let stats = Rc::new(RefCell::new(FrameStats::default()));
 	let environment = Environment {
 		window,
 		event_loop,
 		device,
-		swap_buffers: Box::new(move || context.swap_buffers().unwrap()),
-		delta_time: Box::new(move || {
-			let ndnow = Instant::now();
-			let delta = ndnow.duration_since(dnow);
-			dnow = ndnow;
-
-			frames += 1;
-			let elapsed = now.elapsed();
-			if elapsed >= Duration::from_secs(1) {
-				let fps = f64::from(frames) / elapsed.as_secs_f64();
-				log::info!("FPS: {:.02}", fps);
-
-				now = Instant::now();
-				frames = 0;
-			}
-
-			delta
-		})
+		swap_buffers,
+		resize,
+		input: Input::new(surface_size.clone()),
+		surface_size,
+		delta_time: delta_time(&stats),
+		stats,
+		pacer
 	};
 	environment
 }
@@ -130,40 +367,98 @@ pub fn inner_start() -> Environment {
 /** Inner part of the start function. Clients should use [the main! macro]
  * instead of this function in pretty much every case. */
 #[cfg(target_arch = "wasm32")]
-pub fn inner_start() -> Environment {
+pub fn inner_start_with_options(options: &EnvironmentOptions) -> Environment {
+	use wasm_bindgen::JsCast;
+	use winit::platform::web::{WindowBuilderExtWebSys, WindowExtWebSys};
+
 	std::panic::set_hook(Box::new(console_error_panic_hook::hook));
 
 	console_log::init_with_level(log::Level::Trace)
 		.expect("could not initialize logger");
 
-	let (event_loop, window_builder) = window();
-	let window = window_builder.build(&event_loop)
-		.expect("could not create window");
-
-	let canvas = winit::platform::web::WindowExtWebSys::canvas(&window);
-	web_sys::window()
+	let document = web_sys::window()
 		.expect("no window element")
 		.document()
-		.expect("no document element")
-		.body()
-		.expect("document has no body")
-		.append_child(&canvas)
-		.expect("could not append canvas to body");
+		.expect("no document element");
 
-	use wasm_bindgen::JsCast;
-	let context = canvas.get_context("webgl2")
+	/* Render to the canvas we were told to, if any, and to a new one at the
+	 * end of the page otherwise. */
+	let existing = options.canvas.as_ref().map(|id| document
+		.get_element_by_id(id)
+		.unwrap_or_else(|| panic!("no element with the id {:?}", id))
+		.dyn_into::<web_sys::HtmlCanvasElement>()
+		.unwrap_or_else(|_| panic!("the element with the id {:?} is not a \
+			canvas", id)));
+
+	let (event_loop, window_builder) = window(options);
+	let window = window_builder
+		.with_canvas(existing.clone())
+		.build(&event_loop)
+		.expect("could not create window");
+
+	let canvas = window.canvas();
+	if existing.is_none() {
+		document
+			.body()
+			.expect("document has no body")
+			.append_child(&canvas)
+			.expect("could not append canvas to body");
+	}
+
+	let mut attributes = web_sys::WebGlContextAttributes::new();
+	attributes.antialias(options.samples > 0);
+
+	let context = canvas.get_context_with_context_options("webgl2", &attributes)
 		.unwrap()
 		.unwrap()
 		.dyn_into::<web_sys::WebGl2RenderingContext>()
 		.unwrap();
 	let context = glow::Context::from_webgl2_context(context);
 
+	let device = Device::new_from_context(context).unwrap();
+	sanitize_writes(&device);
+	gavle::log_startup_summary(device.information());
+
+	let swap_buffers = {
+		let device = device.clone();
+		Box::new(move || device.mark_frame())
+	};
+
+	/* The canvas gets resized along with the window, so there's nothing to do
+	 * other than keeping track of its size. */
+	let surface_size = SurfaceSize::new(window.inner_size());
+	resize_default_framebuffer(&device, window.inner_size());
+	let resize = {
+		let surface_size = surface_size.clone();
+		let device = device.clone();
+		Box::new(move |size| {
+			surface_size.set(size);
+			resize_default_framebuffer(&device, size);
+		})
+	};
+
+	/* Frame times are made up in here, so there's no point in logging them. */
+	let stats = Rc::new(RefCell::new(FrameStats::default()));
+	stats.borrow_mut().set_log_interval(None);
+
 	let environment = Environment {
 		window,
 		event_loop,
-		device: Device::new_from_context(context).unwrap(),
-		swap_buffers: Box::new(move || {}),
-		delta_time: Box::new(move || Duration::from_secs_f64(0.01666666666))
+		device,
+		swap_buffers,
+		resize,
+		input: Input::new(surface_size.clone()),
+		surface_size,
+		delta_time: {
+			let stats = stats.clone();
+			Box::new(move || {
+				let delta = Duration::from_secs_f64(0.01666666666);
+				stats.borrow_mut().tick(delta);
+				delta
+			})
+		},
+		stats,
+		pacer: Rc::new(RefCell::new(FramePacer::new(None)))
 	};
 	environment
 }
\ No newline at end of file
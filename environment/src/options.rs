@@ -0,0 +1,72 @@
+use winit::dpi::PhysicalSize;
+
+/** Options for the window and the context an environment gets created with,
+ * as given to [`inner_start_with_options()`], or to [the main! macro] along
+ * with the function running the application.
+ *
+ * The default options create an 800×600 resizable window titled "Ricardo",
+ * with an OpenGL ES 3.0 context, vertical synchronization off and eight
+ * samples per pixel.
+ *
+ * [`inner_start_with_options()`]: crate::inner_start_with_options
+ * [the main! macro]: crate::main */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvironmentOptions {
+	/** Title of the window. */
+	pub title: String,
+	/** Size the window starts out with. */
+	pub size: PhysicalSize<u32>,
+	/** Whether the window may be resized by the user. */
+	pub resizable: bool,
+	/** Whether buffer swaps wait for the vertical blank of the display. This
+	 * has no effect in browsers, which are in charge of presentation. */
+	pub vsync: bool,
+	/** Number of samples per pixel of the default framebuffer, which must be
+	 * either zero, for no multisampling, or a power of two. Browsers only get
+	 * told whether to multisample at all, and pick the number themselves. */
+	pub samples: u16,
+	/** Version of OpenGL to ask for. Browsers always get a WebGL 2 context,
+	 * which matches OpenGL ES 3.0, regardless of this. */
+	pub gl: GlRequest,
+	/** Identifier of a canvas element already in the page that should be
+	 * rendered to, instead of a new one appended to the body of the page.
+	 * This has no effect outside of browsers. */
+	pub canvas: Option<String>,
+}
+impl Default for EnvironmentOptions {
+	fn default() -> Self {
+		Self {
+			title: "Ricardo".to_owned(),
+			size: PhysicalSize {
+				width: 800,
+				height: 600
+			},
+			resizable: true,
+			vsync: false,
+			samples: 8,
+			gl: GlRequest {
+				api: GlApi::OpenGlEs,
+				version: (3, 0)
+			},
+			canvas: None
+		}
+	}
+}
+
+/** Flavors of OpenGL a context may be asked for. */
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum GlApi {
+	/** Desktop OpenGL, with a core profile. */
+	OpenGl,
+	/** OpenGL ES. */
+	OpenGlEs,
+}
+
+/** Specific version of OpenGL to ask for a context of. */
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct GlRequest {
+	/** Flavor of OpenGL. */
+	pub api: GlApi,
+	/** Major and minor version numbers. */
+	pub version: (u8, u8),
+}
@@ -0,0 +1,155 @@
+use std::collections::VecDeque;
+
+/** The recommended maximum number of frames in flight when pacing is on.
+ *
+ * Two frames keep the device fed while the host works on the next one, without
+ * letting the driver queue up enough of them for input to feel sluggish. */
+pub const DEFAULT_MAX_FRAMES_IN_FLIGHT: u32 = 2;
+
+/** Bookkeeping for limiting how many frames the device may fall behind the
+ * host by, in order to cut down on the latency between input and presentation.
+ *
+ * Every presented frame gets a fence. Once as many fences are in flight as the
+ * limit allows, the oldest ones get waited on before control goes back to the
+ * application, so that a new frame is never started more than the given number
+ * of frames ahead of what the device has finished. Nothing is waited on while
+ * the queue is shorter than the limit. */
+#[derive(Debug)]
+pub struct FramePacer<F> {
+	/** Maximum number of frames in flight, if pacing is turned on. */
+	max_frames_in_flight: Option<u32>,
+	/** Fences of the frames still in flight, from oldest to newest. */
+	in_flight: VecDeque<F>,
+}
+impl<F> FramePacer<F> {
+	/** Creates a new frame pacer with the given maximum number of frames in
+	 * flight, or with pacing turned off, if none is given. */
+	pub fn new(max_frames_in_flight: Option<u32>) -> Self {
+		Self {
+			max_frames_in_flight: max_frames_in_flight.map(|max| max.max(1)),
+			in_flight: VecDeque::new()
+		}
+	}
+
+	/** The maximum number of frames in flight, if pacing is turned on. */
+	pub fn max_frames_in_flight(&self) -> Option<u32> {
+		self.max_frames_in_flight
+	}
+
+	/** Changes the maximum number of frames in flight, or turns pacing off, if
+	 * none is given. A limit of zero is treated as a limit of one. */
+	pub fn set_max_frames_in_flight(&mut self, max: Option<u32>) {
+		self.max_frames_in_flight = max.map(|max| max.max(1));
+		if self.max_frames_in_flight.is_none() {
+			self.in_flight.clear();
+		}
+	}
+
+	/** Number of frames whose fences have not been waited on yet. */
+	pub fn frames_in_flight(&self) -> usize {
+		self.in_flight.len()
+	}
+
+	/** Records the presentation of a frame and waits for as many of the older
+	 * frames as needed to bring the number of frames in flight under the limit.
+	 *
+	 * The given fence is the one inserted right after presenting the frame.
+	 * When no fence could be created for it, the whole device is drained with
+	 * the given finish function instead, which respects the limit, if more
+	 * strictly than needed. Neither function is called if pacing is off. */
+	pub fn frame_presented<W, D>(&mut self, fence: Option<F>, mut wait: W, finish: D)
+		where W: FnMut(F),
+			  D: FnOnce() {
+
+		let max = match self.max_frames_in_flight {
+			Some(max) => max as usize,
+			None => return
+		};
+
+		match fence {
+			Some(fence) => {
+				self.in_flight.push_back(fence);
+				while self.in_flight.len() >= max {
+					let oldest = self.in_flight.pop_front()
+						.expect("the queue cannot be empty here");
+					wait(oldest);
+				}
+			},
+			None => {
+				self.in_flight.clear();
+				finish();
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::cell::RefCell;
+
+	/** Presents a frame with the given scripted fence, returning the fences
+	 * that got waited on and whether the device was drained. */
+	fn present(
+		pacer: &mut FramePacer<u32>,
+		fence: Option<u32>) -> (Vec<u32>, bool) {
+
+		let waited = RefCell::new(Vec::new());
+		let finished = RefCell::new(false);
+		pacer.frame_presented(
+			fence,
+			|fence| waited.borrow_mut().push(fence),
+			|| *finished.borrow_mut() = true);
+
+		(waited.into_inner(), finished.into_inner())
+	}
+
+	#[test]
+	fn does_nothing_when_turned_off() {
+		let mut pacer = FramePacer::new(None);
+		assert_eq!(present(&mut pacer, Some(0)), (vec![], false));
+		assert_eq!(present(&mut pacer, None), (vec![], false));
+		assert_eq!(pacer.frames_in_flight(), 0);
+	}
+
+	#[test]
+	fn waits_on_the_oldest_frame_past_the_limit() {
+		let mut pacer = FramePacer::new(Some(3));
+		assert_eq!(present(&mut pacer, Some(0)), (vec![], false));
+		assert_eq!(present(&mut pacer, Some(1)), (vec![], false));
+		assert_eq!(present(&mut pacer, Some(2)), (vec![0], false));
+		assert_eq!(present(&mut pacer, Some(3)), (vec![1], false));
+		assert_eq!(pacer.frames_in_flight(), 2);
+	}
+
+	#[test]
+	fn waits_on_every_frame_with_a_limit_of_one() {
+		let mut pacer = FramePacer::new(Some(0));
+		assert_eq!(pacer.max_frames_in_flight(), Some(1));
+		assert_eq!(present(&mut pacer, Some(0)), (vec![0], false));
+		assert_eq!(present(&mut pacer, Some(1)), (vec![1], false));
+	}
+
+	#[test]
+	fn drains_the_device_without_a_fence() {
+		let mut pacer = FramePacer::new(Some(2));
+		assert_eq!(present(&mut pacer, Some(0)), (vec![], false));
+		assert_eq!(present(&mut pacer, None), (vec![], true));
+		assert_eq!(pacer.frames_in_flight(), 0);
+		assert_eq!(present(&mut pacer, Some(1)), (vec![], false));
+	}
+
+	#[test]
+	fn lowering_the_limit_catches_up_on_the_next_frame() {
+		let mut pacer = FramePacer::new(Some(4));
+		for fence in 0..3 {
+			assert_eq!(present(&mut pacer, Some(fence)), (vec![], false));
+		}
+
+		pacer.set_max_frames_in_flight(Some(2));
+		assert_eq!(present(&mut pacer, Some(3)), (vec![0, 1, 2], false));
+
+		pacer.set_max_frames_in_flight(None);
+		assert_eq!(pacer.frames_in_flight(), 0);
+	}
+}
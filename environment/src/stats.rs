@@ -0,0 +1,162 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/** The default number of frames whose times are kept around by [`FrameStats`].
+ *
+ * Two seconds' worth of frames at sixty frames per second, which is enough for
+ * the percentiles to settle, while still reacting quickly to changes. */
+pub const DEFAULT_FRAME_HISTORY: usize = 120;
+
+/** Statistics about the times of the most recent frames, such as the frame
+ * rate, for applications that want to show them, and for logging.
+ *
+ * Frame times go into a ring buffer through [`tick()`], which the delta time
+ * function of the environments calls on every frame, so all of this is kept up
+ * to date for as long as the application keeps calling that function.
+ *
+ * [`tick()`]: Self::tick */
+#[derive(Debug, Clone)]
+pub struct FrameStats {
+	/** Times of the most recent frames, from oldest to newest. */
+	times: VecDeque<Duration>,
+	/** Maximum number of frame times kept around. */
+	history: usize,
+	/** How often the frame rate gets logged, if it gets logged at all. */
+	log_interval: Option<Duration>,
+	/** Time since the frame rate was last logged. */
+	since_log: Duration,
+	/** Number of frames since the frame rate was last logged. */
+	frames_since_log: u32,
+}
+impl FrameStats {
+	/** Creates new statistics keeping the times of the given number of most
+	 * recent frames, which is at least one, and logging the frame rate once
+	 * every second. */
+	pub fn new(history: usize) -> Self {
+		let history = history.max(1);
+		Self {
+			times: VecDeque::with_capacity(history),
+			history,
+			log_interval: Some(Duration::from_secs(1)),
+			since_log: Duration::from_secs(0),
+			frames_since_log: 0
+		}
+	}
+
+	/** Changes how often the frame rate gets logged, or turns logging off, if
+	 * no interval is given. */
+	pub fn set_log_interval(&mut self, interval: Option<Duration>) {
+		self.log_interval = interval;
+		self.since_log = Duration::from_secs(0);
+		self.frames_since_log = 0;
+	}
+
+	/** Records a frame that took the given amount of time. */
+	pub fn tick(&mut self, frame_time: Duration) {
+		if self.times.len() == self.history {
+			self.times.pop_front();
+		}
+		self.times.push_back(frame_time);
+
+		let interval = match self.log_interval {
+			Some(interval) => interval,
+			None => return
+		};
+		self.since_log += frame_time;
+		self.frames_since_log += 1;
+		if self.since_log >= interval {
+			let fps = f64::from(self.frames_since_log) / self.since_log.as_secs_f64();
+			log::info!("FPS: {:.02}", fps);
+
+			self.since_log = Duration::from_secs(0);
+			self.frames_since_log = 0;
+		}
+	}
+
+	/** Times of the most recent frames, from oldest to newest. */
+	pub fn frame_times(&self) -> impl ExactSizeIterator<Item = Duration> + '_ {
+		self.times.iter().copied()
+	}
+
+	/** Average number of frames per second over the most recent frames, or
+	 * zero, if no time has been recorded. */
+	pub fn fps(&self) -> f32 {
+		let total = self.times.iter().sum::<Duration>().as_secs_f64();
+		if total == 0.0 {
+			0.0
+		} else {
+			(self.times.len() as f64 / total) as f32
+		}
+	}
+
+	/** Average time of the most recent frames. */
+	pub fn frame_time_avg(&self) -> Duration {
+		if self.times.is_empty() { return Duration::from_secs(0) }
+		self.times.iter().sum::<Duration>() / self.times.len() as u32
+	}
+
+	/** Time that ninety nine percent of the most recent frames took no longer
+	 * than, which tells of the hitches that the average smooths over. */
+	pub fn frame_time_p99(&self) -> Duration {
+		self.percentile(0.99)
+	}
+
+	/** Smallest of the most recent frame times no more than the given fraction
+	 * of them lie above. */
+	fn percentile(&self, fraction: f64) -> Duration {
+		if self.times.is_empty() { return Duration::from_secs(0) }
+
+		let mut times = self.times.iter().copied().collect::<Vec<_>>();
+		times.sort_unstable();
+
+		let rank = (fraction * times.len() as f64).ceil() as usize;
+		times[rank.clamp(1, times.len()) - 1]
+	}
+}
+impl Default for FrameStats {
+	fn default() -> Self {
+		Self::new(DEFAULT_FRAME_HISTORY)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn ms(millis: u64) -> Duration {
+		Duration::from_millis(millis)
+	}
+
+	#[test]
+	fn keeps_only_the_most_recent_frames() {
+		let mut stats = FrameStats::new(3);
+		stats.set_log_interval(None);
+		for millis in 1..=5 {
+			stats.tick(ms(millis));
+		}
+
+		assert_eq!(stats.frame_times().collect::<Vec<_>>(), vec![ms(3), ms(4), ms(5)]);
+		assert_eq!(stats.frame_time_avg(), ms(4));
+		assert!((stats.fps() - 250.0).abs() < 1e-3);
+	}
+
+	#[test]
+	fn p99_picks_up_hitches() {
+		let mut stats = FrameStats::new(200);
+		stats.set_log_interval(None);
+		assert_eq!(stats.frame_time_p99(), ms(0));
+		assert_eq!(stats.fps(), 0.0);
+
+		for _ in 0..197 {
+			stats.tick(ms(10));
+		}
+		for _ in 0..3 {
+			stats.tick(ms(100));
+		}
+
+		/* Three slow frames out of two hundred are more than one percent of
+		 * them, so they must show up. */
+		assert_eq!(stats.frame_time_p99(), ms(100));
+		assert!(stats.frame_time_avg() < ms(12));
+	}
+}
@@ -0,0 +1,80 @@
+use std::cell::Cell;
+use std::rc::Rc;
+use winit::dpi::PhysicalSize;
+use gavle::Viewport;
+
+/** Size of the surface an environment renders to, in physical pixels.
+ *
+ * This is a handle shared between the environment, which keeps it up to date
+ * every time its resize function gets called, and the application, which may
+ * read it whenever it needs to, such as when setting up the viewport for a
+ * frame. Clones of a handle all see the same size. */
+#[derive(Debug, Clone)]
+pub struct SurfaceSize(Rc<Cell<(u32, u32)>>);
+impl SurfaceSize {
+	/** Creates a new handle to a surface of the given size. */
+	pub(crate) fn new(size: PhysicalSize<u32>) -> Self {
+		Self(Rc::new(Cell::new((size.width, size.height))))
+	}
+
+	/** Changes the size seen through every handle to this surface. */
+	pub(crate) fn set(&self, size: PhysicalSize<u32>) {
+		self.0.set((size.width, size.height))
+	}
+
+	/** The current width and height of the surface. */
+	pub fn get(&self) -> (u32, u32) {
+		self.0.get()
+	}
+
+	/** The current width of the surface. */
+	pub fn width(&self) -> u32 {
+		self.get().0
+	}
+
+	/** The current height of the surface. */
+	pub fn height(&self) -> u32 {
+		self.get().1
+	}
+
+	/** Ratio between the width and the height of the surface, or one if it
+	 * has no height, as it may when its window is minimized. */
+	pub fn aspect_ratio(&self) -> f32 {
+		let (width, height) = self.get();
+		if height == 0 {
+			1.0
+		} else {
+			width as f32 / height as f32
+		}
+	}
+
+	/** A viewport covering the whole of the surface. */
+	pub fn viewport(&self) -> Viewport {
+		let (width, height) = self.get();
+		Viewport {
+			x: 0,
+			y: 0,
+			width,
+			height
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn clones_see_the_same_size() {
+		let size = SurfaceSize::new(PhysicalSize { width: 800, height: 600 });
+		let clone = size.clone();
+
+		size.set(PhysicalSize { width: 1024, height: 0 });
+		assert_eq!(clone.get(), (1024, 0));
+		assert_eq!(clone.aspect_ratio(), 1.0);
+
+		size.set(PhysicalSize { width: 640, height: 480 });
+		assert_eq!(clone.viewport(), Viewport { x: 0, y: 0, width: 640, height: 480 });
+		assert!((clone.aspect_ratio() - 4.0 / 3.0).abs() < 1e-6);
+	}
+}
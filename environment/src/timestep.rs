@@ -0,0 +1,198 @@
+use std::time::Duration;
+use winit::event::{Event, WindowEvent};
+use winit::event_loop::ControlFlow;
+use crate::{Environment, Input};
+
+/** The default maximum number of fixed updates a single frame may ask for.
+ *
+ * Any time past what this many updates cover gets thrown away, so that a long
+ * frame, such as one spent stopped in a debugger, doesn't ask for more updates
+ * than the next frame can run, which would only make it longer still. */
+pub const DEFAULT_MAX_TICKS_PER_FRAME: u32 = 8;
+
+/** Splits the time between frames into updates of a fixed duration, so that
+ * simulations step the same way regardless of the frame rate.
+ *
+ * Every frame feeds its delta time into the accumulator, which then gets
+ * drained one tick at a time. Whatever is left over, always shorter than a
+ * tick, carries over to the next frame, and tells how far along the current
+ * tick rendering should interpolate between the last two updates. */
+#[derive(Debug, Clone)]
+pub struct FixedTimestep {
+	/** Duration of a single update. */
+	tick: Duration,
+	/** Time that has passed but not been consumed by any update yet. */
+	accumulator: Duration,
+	/** Maximum number of updates a single frame may ask for. */
+	max_ticks: u32,
+}
+impl FixedTimestep {
+	/** Creates a new timestep with updates of the given duration, which must
+	 * not be zero, and the default maximum number of updates per frame. */
+	pub fn new(tick: Duration) -> Self {
+		assert!(tick > Duration::from_secs(0), "the duration of a tick must not be zero");
+		Self {
+			tick,
+			accumulator: Duration::from_secs(0),
+			max_ticks: DEFAULT_MAX_TICKS_PER_FRAME
+		}
+	}
+
+	/** The duration of a single update. */
+	pub fn tick(&self) -> Duration {
+		self.tick
+	}
+
+	/** The maximum number of updates a single frame may ask for. */
+	pub fn max_ticks(&self) -> u32 {
+		self.max_ticks
+	}
+
+	/** Changes the maximum number of updates a single frame may ask for. A
+	 * maximum of zero is treated as a maximum of one. */
+	pub fn set_max_ticks(&mut self, max: u32) {
+		self.max_ticks = max.max(1);
+	}
+
+	/** Accounts for a frame that took the given amount of time, returning how
+	 * many updates should be run for it, and how far along the next update
+	 * the frame should be rendered at. */
+	pub fn advance(&mut self, delta: Duration) -> Ticks {
+		let budget = self.tick * self.max_ticks;
+		let delta = if delta > budget {
+			log::debug!(target: "environment::timestep",
+				"dropping {:?} out of a {:?} frame, which is longer than {} \
+				ticks", delta - budget, delta, self.max_ticks);
+			budget
+		} else {
+			delta
+		};
+
+		/* The remainder is always shorter than a tick, so a delta clamped to
+		 * the budget never adds up to more than the maximum. */
+		self.accumulator += delta;
+		let count = (self.accumulator.as_nanos() / self.tick.as_nanos()) as u32;
+		let count = count.min(self.max_ticks);
+		self.accumulator -= self.tick * count;
+
+		Ticks {
+			count,
+			alpha: self.alpha()
+		}
+	}
+
+	/** How far along the next update the leftover time goes, between zero and
+	 * one, not inclusive. */
+	pub fn alpha(&self) -> f32 {
+		(self.accumulator.as_nanos() as f64 / self.tick.as_nanos() as f64) as f32
+	}
+}
+
+/** Updates to run for a frame, as given by [`FixedTimestep::advance()`]. */
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Ticks {
+	/** Number of fixed updates to run before rendering the frame. */
+	pub count: u32,
+	/** How far along the next update the frame should be rendered at, for
+	 * interpolating between the state before and after the last update. */
+	pub alpha: f32,
+}
+
+/** An application whose simulation runs in fixed steps, driven by
+ * [`run_fixed()`], apart from rendering, which happens once every frame. */
+pub trait FixedApplication: 'static {
+	/** Handles an event coming from the window. Resizing and closing the
+	 * window have already been taken care of by the time this gets called. */
+	fn event(&mut self, _event: &WindowEvent, _flow: &mut ControlFlow) {}
+
+	/** Advances the simulation by a single tick of the given duration, with
+	 * the given state of the input. Presses, releases and scrolling only show
+	 * up in the first update after they happen. */
+	fn update(&mut self, tick: Duration, input: &Input);
+
+	/** Renders a frame, which lies the given fraction of the way between the
+	 * state before and after the last update. Buffers get swapped right after
+	 * this returns. */
+	fn render(&mut self, alpha: f32);
+}
+
+/** Runs the given application in the given environment, with updates of the
+ * given duration, until its window gets closed. This is what [the main! macro]
+ * calls when it is given a `fixed` duration.
+ *
+ * [the main! macro]: crate::main */
+pub fn run_fixed<A>(env: Environment, tick: Duration, mut application: A) -> !
+	where A: FixedApplication {
+
+	let Environment {
+		window,
+		event_loop,
+		mut swap_buffers,
+		mut resize,
+		mut delta_time,
+		mut input,
+		..
+	} = env;
+
+	let mut timestep = FixedTimestep::new(tick);
+	let _ = delta_time();
+
+	event_loop.run(move |event, _, flow| {
+		*flow = ControlFlow::Poll;
+		match event {
+			Event::WindowEvent { event, window_id } if window_id == window.id() => {
+				match event {
+					WindowEvent::CloseRequested => *flow = ControlFlow::Exit,
+					WindowEvent::Resized(size) => resize(size),
+					_ => {}
+				}
+				input.handle(&event);
+				application.event(&event, flow);
+			},
+			Event::MainEventsCleared => {
+				let ticks = timestep.advance(delta_time());
+				for _ in 0..ticks.count {
+					application.update(tick, &input);
+					input.end_frame();
+				}
+				application.render(ticks.alpha);
+				swap_buffers();
+			},
+			_ => {}
+		}
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn ms(millis: u64) -> Duration {
+		Duration::from_millis(millis)
+	}
+
+	#[test]
+	fn carries_leftover_time_over() {
+		let mut timestep = FixedTimestep::new(ms(10));
+		assert_eq!(timestep.advance(ms(4)), Ticks { count: 0, alpha: 0.4 });
+		assert_eq!(timestep.advance(ms(4)).count, 0);
+		assert_eq!(timestep.advance(ms(4)).count, 1);
+		assert!((timestep.alpha() - 0.2).abs() < 1e-6);
+		assert_eq!(timestep.advance(ms(28)), Ticks { count: 3, alpha: 0.0 });
+	}
+
+	#[test]
+	fn long_frames_are_clamped() {
+		let mut timestep = FixedTimestep::new(ms(10));
+		timestep.set_max_ticks(4);
+		assert_eq!(timestep.advance(ms(5)).count, 0);
+
+		/* Only forty out of these ten seconds count, on top of the five left
+		 * over from before. */
+		let ticks = timestep.advance(Duration::from_secs(10));
+		assert_eq!(ticks.count, 4);
+		assert!((ticks.alpha - 0.5).abs() < 1e-6);
+
+		assert_eq!(timestep.advance(ms(5)), Ticks { count: 1, alpha: 0.0 });
+	}
+}
@@ -0,0 +1,20 @@
+/** Shaders used in the main render pass of the visitor. */
+pub mod visitor {
+	use gavle::ShaderSource;
+
+	/** Vertex program of this shader, given the skinning code for the skeleton
+	 * it is going to be used with. */
+	pub fn vertex(skinning: &str) -> ShaderSource<'static> {
+		ShaderSource::Glsl(format!("#version 300 es\n\
+			precision highp float;\n\
+			{}\n\
+			{}",
+			skinning,
+			include_str!("visitor/vert.glsl")).into())
+	}
+
+	/** Fragment program of this shader. */
+	pub fn fragment() -> ShaderSource<'static> {
+		ShaderSource::Glsl(include_str!("visitor/frag.glsl").into())
+	}
+}
@@ -0,0 +1,13 @@
+#version 300 es
+precision mediump float;
+
+in vec3 vs_normal;
+
+out vec4 color;
+
+void main()
+{
+	/* Simple directional lighting, so that the bend is easy to see. */
+	float light = max(dot(normalize(vs_normal), normalize(vec3(0.5, 1.0, -1.0))), 0.0);
+	color = vec4(vec3(0.2 + 0.8 * light), 1.0);
+}
@@ -0,0 +1,20 @@
+in vec3 tt_vert_position;
+in vec3 tt_vert_normal;
+in vec4 tt_vert_joints;
+in vec4 tt_vert_weights;
+
+out vec3 vs_normal;
+
+layout(std140) uniform rc_params
+{
+	mat4 view_projection;
+};
+
+void main()
+{
+	/* Move the vertex from the bind pose into the current pose. */
+	mat4 skin = rc_skin_matrix(tt_vert_joints, tt_vert_weights);
+
+	vs_normal = normalize(mat3(skin) * tt_vert_normal);
+	gl_Position = view_projection * skin * vec4(tt_vert_position, 1.0);
+}
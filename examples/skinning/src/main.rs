@@ -0,0 +1,376 @@
+use environment::Environment;
+use winit::event_loop::ControlFlow;
+use winit::event::{Event, WindowEvent};
+use gavle::*;
+use support::{Matrix4, SkinnedVertex, Joint, Skeleton};
+use std::convert::TryFrom;
+use bytemuck::Zeroable;
+
+/** Graphical assets used by this application. */
+mod assets;
+
+/** Function responsible for running the game inside of a given application
+ * environment, provided by the [`environment`] crate. */
+fn run(env: Environment) {
+	let Environment {
+		window,
+		event_loop,
+		device,
+		mut swap_buffers,
+		mut resize,
+		surface_size,
+		mut delta_time,
+		..
+	} = env;
+
+	/* Initialize the application state and create the visitor that will be
+	 * responsible for rendering the application state to the screen. */
+	let mut state = ApplicationRenderState::new();
+	let mut state_visitor = ApplicationRenderStateVisitor::new(&device);
+
+	/* Common parameters passed to the renderer. */
+	let framebuffer = device.default_framebuffer(
+		&DefaultFramebufferDescriptor {
+			color_load_op: LoadOp::Clear(Color {
+				red: 0.1,
+				green: 0.1,
+				blue: 0.1,
+				alpha: 1.0
+			}),
+			depth_load_op: LoadOp::Clear(f32::INFINITY),
+			stencil_load_op: LoadOp::Clear(1)
+		});
+
+	/* Run the main game loop. */
+	event_loop.run(move |event, _, flow| {
+		*flow = ControlFlow::Poll;
+		let viewport = surface_size.viewport();
+		let mut pass = false;
+
+		/* Process the events coming from the window. */
+		match event {
+			Event::WindowEvent { event, window_id }
+			if window_id == window.id() => {
+				match event {
+					WindowEvent::CloseRequested => *flow = ControlFlow::Exit,
+					WindowEvent::Resized(size) => resize(size),
+					_ => {}
+				}
+			},
+			Event::MainEventsCleared => pass = true,
+			_ => {}
+		}
+		if !pass { return }
+
+		/* Update the application. */
+		state.time += delta_time().as_secs_f32();
+
+		/* Render the application. */
+		state_visitor.visit(
+			&device,
+			&framebuffer,
+			&viewport,
+			&state);
+
+		swap_buffers();
+	})
+}
+
+/** All of the data that makes up a given state of the application. */
+#[derive(Debug, Copy, Clone, PartialEq, PartialOrd)]
+struct ApplicationRenderState {
+	/** Time elapsed since the start of the application, in seconds. */
+	pub time: f32,
+}
+impl ApplicationRenderState {
+	/** Create a new application state structure with default parameters. */
+	pub fn new() -> Self {
+		Self {
+			time: 0.0
+		}
+	}
+}
+
+/** A looping animation track, made up of keyframes holding a single value,
+ * which are linearly interpolated between. */
+struct Track {
+	/** Keyframes in this track, as pairs of time and value, in order. */
+	keys: &'static [(f32, f32)],
+}
+impl Track {
+	/** Value of the track at the given point in time. */
+	pub fn sample(&self, time: f32) -> f32 {
+		let duration = self.keys.last().map(|(time, _)| *time).unwrap_or(0.0);
+		if duration <= 0.0 {
+			return self.keys.first().map(|(_, value)| *value).unwrap_or(0.0)
+		}
+
+		let time = time.rem_euclid(duration);
+		for window in self.keys.windows(2) {
+			let (t0, v0) = window[0];
+			let (t1, v1) = window[1];
+
+			if time >= t0 && time <= t1 {
+				let factor = if t1 > t0 { (time - t0) / (t1 - t0) } else { 0.0 };
+				return v0 + (v1 - v0) * factor
+			}
+		}
+
+		self.keys.last().map(|(_, value)| *value).unwrap_or(0.0)
+	}
+}
+
+/** Angle the upper bone bends by relative to the lower one, over time. */
+const BEND: Track = Track {
+	keys: &[
+		(0.0,  0.0),
+		(1.0,  std::f32::consts::FRAC_PI_2),
+		(2.0,  0.0),
+		(3.0, -std::f32::consts::FRAC_PI_2),
+		(4.0,  0.0),
+	]
+};
+
+/** Angle the whole model turns by around the vertical axis, over time. */
+const SWAY: Track = Track {
+	keys: &[
+		(0.0, -std::f32::consts::FRAC_PI_4),
+		(3.0,  std::f32::consts::FRAC_PI_4),
+		(6.0, -std::f32::consts::FRAC_PI_4),
+	]
+};
+
+/** Uniform parameters passed on to the shader. */
+#[derive(Debug, Copy, Clone, PartialEq, PartialOrd, bytemuck::Zeroable, bytemuck::Pod)]
+#[repr(C)]
+struct ShaderParams {
+	/** World-View-Projection transformation matrix.
+	 *
+	 * The model transformation is left out of this matrix, as the joints in
+	 * the skeleton are already in world space. */
+	pub view_projection: Matrix4
+}
+
+/** Structure responsible for rendering information in the example pass directly
+ * into a target framebuffer, without any sort of processing. */
+struct ApplicationRenderStateVisitor {
+	/** The render pipeline used in the render pass. */
+	pipeline: RenderPipeline,
+	/** Vertex buffer containing data for the cylinder model. */
+	vertices: VertexBuffer,
+	/** Index buffer containing data for the cylinder model. */
+	indices: IndexBuffer,
+	/** Skeleton the cylinder model is bound to. */
+	skeleton: Skeleton,
+	/** Uniform data passed to the shaders in the render pass. */
+	params: UniformBuffer,
+	/** Uniform bind group passed on to the shader. */
+	bind: UniformGroup,
+	/** Number of indices in the current model. */
+	index_count: u32,
+}
+impl ApplicationRenderStateVisitor {
+	/** Create a new instance of this render pass. */
+	pub fn new(device: &Device) -> Self {
+		/* Generate a cylinder of height two, standing on the origin, made up of
+		 * two bones of equal length. The vertices around the joint between the
+		 * two bones are blended between both of them. */
+		let rings = 32_u16;
+		let segments = 24_u16;
+		let radius = 0.25;
+
+		let mut vertices = Vec::new();
+		let mut indices = Vec::new();
+		for ring in 0..=rings {
+			let y = f32::from(ring) / f32::from(rings) * 2.0;
+			let upper = ((y - 0.75) / 0.5).clamp(0.0, 1.0);
+
+			for segment in 0..=segments {
+				let angle = f32::from(segment) / f32::from(segments)
+					* 2.0 * std::f32::consts::PI;
+				let (sin, cos) = angle.sin_cos();
+
+				vertices.push(SkinnedVertex {
+					position: [cos * radius, y, sin * radius],
+					normal: [cos, 0.0, sin],
+					texture: [
+						f32::from(segment) / f32::from(segments),
+						f32::from(ring) / f32::from(rings)
+					],
+					joints: [0, 1, 0, 0],
+					weights: [1.0 - upper, upper, 0.0, 0.0]
+				});
+			}
+		}
+		for ring in 0..rings {
+			for segment in 0..segments {
+				let a = ring * (segments + 1) + segment;
+				let b = a + segments + 1;
+
+				indices.extend_from_slice(&[a, b, a + 1]);
+				indices.extend_from_slice(&[a + 1, b, b + 1]);
+			}
+		}
+		let index_count = indices.len() as u32;
+
+		let vertices = device.create_vertex_buffer_with_data(
+			&BufferDescriptor {
+				size: bytemuck::cast_slice::<_, u8>(&vertices[..]).len() as u32,
+				profile: BufferProfile::StaticUpload,
+				contents: BufferContents::Opaque,
+				..Default::default()
+			},
+			bytemuck::cast_slice(&vertices[..])).unwrap();
+		let indices = device.create_index_buffer_with_data(
+			&BufferDescriptor {
+				size: bytemuck::cast_slice::<_, u8>(&indices[..]).len() as u32,
+				profile: BufferProfile::StaticUpload,
+				contents: BufferContents::Opaque,
+				..Default::default()
+			},
+			bytemuck::cast_slice(&indices[..])).unwrap();
+
+		/* The lower bone starts at the origin and the upper bone starts one
+		 * unit above it. The inverse bind matrices take the vertices from model
+		 * space into the space of each joint. */
+		let skeleton = Skeleton::new(device, vec![
+			Joint {
+				parent: None,
+				inverse_bind: Matrix4::identity()
+			},
+			Joint {
+				parent: Some(0),
+				inverse_bind: Matrix4::translate(0.0, -1.0, 0.0)
+			},
+		]).unwrap();
+
+		let vertex = device.create_vertex_shader(
+			assets::visitor::vertex(&skeleton.glsl())).unwrap();
+		let fragment = device.create_fragment_shader(
+			assets::visitor::fragment()).unwrap();
+
+		let pipeline = device.create_render_pipeline(
+			&RenderPipelineDescriptor {
+				vertex: VertexState {
+					shader: &vertex,
+					buffer: &SkinnedVertex::LAYOUT,
+					instance: None
+				},
+				primitive_state: PrimitiveState {
+					topology: PrimitiveTopology::TriangleList,
+					index_format: IndexFormat::Uint16,
+					front_face: FrontFace::Ccw,
+					cull_mode: CullMode::None,
+					polygon_mode: PolygonMode::Fill,
+					line_width: 1.0,
+					program_point_size: false
+				},
+				fragment: Some(FragmentState {
+					shader: &fragment,
+					targets: &[ColorTargetState {
+						alpha_blend: BlendState::REPLACE,
+						color_blend: BlendState::REPLACE,
+						write_mask: ColorWrite::all(),
+					}]
+				}),
+				depth_stencil: Some(DepthStencilState::new(
+					true,
+					CompareFunction::Less,
+					StencilState::IGNORE))
+			}).unwrap();
+
+		let params = device.create_uniform_buffer(
+			&BufferDescriptor {
+				size: u32::try_from(bytemuck::bytes_of(
+					&ShaderParams::zeroed()).len()).unwrap(),
+				profile: BufferProfile::DynamicUpload,
+				contents: BufferContents::F32,
+				..Default::default()
+			}).unwrap();
+		let bind = device.create_uniform_bind_group(
+			&UniformGroupDescriptor {
+				entries: &[
+					UniformGroupEntry {
+						binding: "rc_params".into(),
+						kind: UniformBind::Buffer {
+							buffer: &params,
+							offset: 0,
+							size: None
+						}
+					},
+					skeleton.bind_entry(),
+				]
+			}).unwrap();
+
+		Self {
+			pipeline,
+			vertices,
+			indices,
+			skeleton,
+			params,
+			bind,
+			index_count
+		}
+	}
+
+	/** Dispatch this render pass with the given parameters. */
+	pub fn visit(
+		&mut self,
+		device: &Device,
+		framebuffer: &Framebuffer,
+		viewport: &Viewport,
+		state: &ApplicationRenderState) {
+
+		/* Pose the skeleton. Each transformation is relative to the parent of
+		 * the joint, and has to be uploaded before the pass starts. */
+		self.skeleton.set_pose(&[
+			Matrix4::rotate(0.0, 1.0, 0.0, SWAY.sample(state.time)),
+			Matrix4::translate(0.0, 1.0, 0.0)
+				* Matrix4::rotate(0.0, 0.0, 1.0, BEND.sample(state.time)),
+		]).unwrap();
+
+		/* Upload the application state to the buffer holding parameter data. */
+		let _ = {
+			let params = ShaderParams {
+				view_projection: {
+					let matrix = Matrix4::translate(0.0, -1.0, 4.0);
+					let matrix = Matrix4::rectilinear_projection(
+						std::f32::consts::FRAC_PI_2,
+						(f64::from(viewport.width) / f64::from(viewport.height)) as f32,
+						1.0,
+						100.0) * matrix;
+
+					matrix.transpose()
+				}
+			};
+
+			let slice = self.params.slice(..);
+			let mut map = slice.try_map_mut(BufferLoadOp::DontCare, MapMode::Orphan)
+				.unwrap();
+
+			let data = bytemuck::bytes_of(&params);
+			map[..data.len()].copy_from_slice(data);
+		};
+
+		/* Draw the cylinder. */
+		let mut pass = device.start_render_pass(
+			&RenderPassDescriptor {
+				pipeline: &self.pipeline,
+				framebuffer
+			});
+
+		pass.set_bind_group(&self.bind);
+		pass.set_index_buffer(&self.indices);
+		pass.set_vertex_buffer(&self.vertices);
+		pass.set_viewport(*viewport)
+			.expect("the viewport must fit in the limits of the context");
+
+		pass.draw_indexed(
+			0..self.index_count,
+			1)
+			.expect("the indices must fit in the index buffer");
+	}
+}
+
+/* Generate the main function. */
+environment::main!(run);
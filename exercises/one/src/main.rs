@@ -1,10 +1,9 @@
 use environment::Environment;
 use winit::event_loop::ControlFlow;
 use winit::event::{Event, WindowEvent};
-use gavle::*;
+use gavle::prelude::*;
 use std::collections::HashMap;
 use std::time::Duration;
-use winit::dpi::PhysicalSize;
 use std::borrow::Cow;
 
 /** Graphical assets used by this application. */
@@ -18,7 +17,10 @@ fn run(env: Environment) {
 		event_loop,
 		device,
 		mut swap_buffers,
-		mut delta_time
+		mut resize,
+		surface_size,
+		mut delta_time,
+		..
 	} = env;
 
 	/* Create the example render pass and some of the parameters we will be
@@ -26,12 +28,12 @@ fn run(env: Environment) {
 	let example_pass = ExamplePass::new(&device);
 
 	let mut top_index = 0usize;
-	let mut viewport = Viewport { x: 0, y: 0, width: 800, height: 600 };
 	let mut clock = Duration::from_secs(0);
 
 	/* Run the main game loop. */
 	event_loop.run(move |event, _, flow| {
 		*flow = ControlFlow::Poll;
+		let viewport = surface_size.viewport();
 		let mut pass = false;
 
 		/* Process the events coming from the window. */
@@ -40,11 +42,7 @@ fn run(env: Environment) {
 				if window_id == window.id() => {
 				match event {
 					WindowEvent::CloseRequested => *flow = ControlFlow::Exit,
-					WindowEvent::Resized(size) => {
-						let PhysicalSize { width, height } = size;
-						viewport.width  = width;
-						viewport.height = height;
-					}
+					WindowEvent::Resized(size) => resize(size),
 					_ => {}
 				}
 			},
@@ -114,13 +112,17 @@ impl ExamplePass {
 		let vertices = device.create_vertex_buffer_with_data(
 			&BufferDescriptor {
 				size: bytemuck::bytes_of(VERTICES).len() as u32,
-				profile: BufferProfile::StaticUpload
+				profile: BufferProfile::StaticUpload,
+				contents: BufferContents::Opaque,
+				..Default::default()
 			},
 			bytemuck::bytes_of(VERTICES)).unwrap();
 		let indices = device.create_index_buffer_with_data(
 			&BufferDescriptor {
 				size: bytemuck::bytes_of(INDICES).len() as u32,
-				profile: BufferProfile::StaticUpload
+				profile: BufferProfile::StaticUpload,
+				contents: BufferContents::Opaque,
+				..Default::default()
 			},
 			bytemuck::bytes_of(INDICES)).unwrap();
 
@@ -132,28 +134,9 @@ impl ExamplePass {
 		let pipelines = Self::TOPOLOGIES.iter()
 			.map(|topology| {
 				let device = device.create_render_pipeline(
-					&RenderPipelineDescriptor {
-						vertex: VertexState {
-							shader: &vertex,
-							buffer: Vertex::LAYOUT
-						},
-						primitive_state: PrimitiveState {
-							topology: *topology,
-							index_format: IndexFormat::Uint16,
-							front_face: FrontFace::Ccw,
-							cull_mode: CullMode::None,
-							polygon_mode: PolygonMode::Fill
-						},
-						fragment: Some(FragmentState {
-							shader: &fragment,
-							targets: ColorTargetState {
-								alpha_blend: BlendState::REPLACE,
-								color_blend: BlendState::REPLACE,
-								write_mask: ColorWrite::all(),
-							}
-						}),
-						depth_stencil: None
-					}).unwrap();
+					&RenderPipelineDescriptor::new(&vertex, Vertex::LAYOUT)
+						.with_topology(*topology)
+						.with_fragment(&fragment)).unwrap();
 
 				(*topology, device)
 			}).collect();
@@ -186,11 +169,13 @@ impl ExamplePass {
 
 		pass.set_index_buffer(&self.indices);
 		pass.set_vertex_buffer(&self.vertices);
-		pass.set_viewport(*viewport);
+		pass.set_viewport(*viewport)
+			.expect("the viewport must fit in the limits of the context");
 
 		pass.draw_indexed(
 			0..5,
-			1);
+			1)
+			.expect("the indices must fit in the index buffer");
 	}
 }
 
@@ -214,19 +199,22 @@ impl Vertex {
 				kind: VertexType::F32,
 				components: VertexComponents::Three,
 				offset: 0,
-				binding: Cow::Borrowed("tt_vert_position")
+				binding: Cow::Borrowed("tt_vert_position"),
+				location: None
 			},
 			VertexAttribute {
 				kind: VertexType::F32,
 				components: VertexComponents::Three,
 				offset: 12,
-				binding: Cow::Borrowed("tt_vert_normal")
+				binding: Cow::Borrowed("tt_vert_normal"),
+				location: None
 			},
 			VertexAttribute {
 				kind: VertexType::F32,
 				components: VertexComponents::Three,
 				offset: 24,
-				binding: Cow::Borrowed("tt_vert_texture")
+				binding: Cow::Borrowed("tt_vert_texture"),
+				location: None
 			},
 		]
 	};
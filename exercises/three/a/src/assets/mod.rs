@@ -14,3 +14,59 @@ pub mod visitor {
 	}
 }
 
+
+/** Shaders used to render textured materials. */
+pub mod textured {
+	use gavle::ShaderSource;
+
+	/** Vertex program of this shader. */
+	pub fn vertex() -> ShaderSource<'static> {
+		ShaderSource::Glsl(include_str!("textured/vert.glsl").into())
+	}
+
+	/** Fragment program of this shader. */
+	pub fn fragment() -> ShaderSource<'static> {
+		ShaderSource::Glsl(include_str!("textured/frag.glsl").into())
+	}
+}
+
+/** Scene rendered by the application, along with the assets it refers to. */
+pub mod scene {
+	use support::{SceneAssets, SceneImage};
+
+	/** Get the scene file describing what gets rendered. */
+	pub fn json() -> &'static str {
+		include_str!("scene.json")
+	}
+
+	/** Assets the scene can refer to, bundled in the executable, by the paths
+	 * the scene refers to them by. */
+	const BUNDLED: &[(&str, &[u8])] = &[
+		("dish/dish.obj", include_bytes!("../../../../two/e/src/assets/dish/dish.obj")),
+		("dish/albedo.png", include_bytes!("../../../../two/e/src/assets/dish/albedo.png")),
+	];
+
+	/** Source of the assets bundled in the executable. */
+	pub struct Bundled;
+	impl SceneAssets for Bundled {
+		fn read(&mut self, path: &str) -> Result<Vec<u8>, String> {
+			BUNDLED.iter()
+				.find(|(name, _)| *name == path)
+				.map(|(_, data)| data.to_vec())
+				.ok_or_else(|| format!("there is no bundled asset at {}", path))
+		}
+
+		fn image(&mut self, path: &str) -> Result<SceneImage, String> {
+			let data = self.read(path)?;
+			let image = image::load_from_memory(&data)
+				.map_err(|what| what.to_string())?
+				.into_rgba8();
+
+			Ok(SceneImage {
+				width: image.width(),
+				height: image.height(),
+				texels: image.into_raw()
+			})
+		}
+	}
+}
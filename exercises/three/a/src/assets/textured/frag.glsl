@@ -0,0 +1,24 @@
+#version 300 es
+precision mediump float;
+
+in struct VS_OUT
+{
+	vec3 position;
+	vec2 texture;
+
+	mat3 ntb;
+} vs_out;
+
+uniform sampler2D tt_tex_albedo;
+
+layout(std140) uniform rc_material
+{
+	vec3 tint;
+};
+
+out vec4 color;
+
+void main()
+{
+	color = vec4(texture(tt_tex_albedo, vs_out.texture).rgb * tint, 1.0);
+}
@@ -15,16 +15,17 @@ out struct VS_OUT
 	mat3 ntb;
 } vs_out;
 
-layout(std140) uniform rc_params
+layout(std140) uniform rc_object
 {
-	mat4 model_world_view;
+	mat4 world;
+	mat4 world_view_projection;
 };
 
 void main()
 {
 	/* Culculate initial position of the dish from the model transformation. */
 	vec4 dish = vec4(tt_vert_position, 1.0);
-	dish = model_world_view * dish;
+	dish = world_view_projection * dish;
 
 	/* Assemble the NTB matrix. */
 	mat3 ntb;
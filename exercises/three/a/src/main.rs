@@ -1,12 +1,12 @@
 
-use environment::Environment;
+use environment::{Environment, FrameStats, Input};
 use winit::event_loop::ControlFlow;
-use winit::event::{Event, WindowEvent, ElementState, MouseButton, MouseScrollDelta};
+use winit::event::{Event, WindowEvent, MouseButton, VirtualKeyCode};
 use gavle::*;
-use winit::dpi::PhysicalSize;
-use support::{Vertex, Matrix4};
-use std::convert::TryFrom;
-use bytemuck::Zeroable;
+use support::{Vertex, Camera, Projection, SceneFile, LoadedScene, RenderQueue, QueueMode, OrbitCamera, OrbitInput, CameraTrack, CameraRecorder};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
 
 /** Graphical assets used by this application. */
 mod assets;
@@ -19,17 +19,27 @@ fn run(env: Environment) {
 		event_loop,
 		device,
 		mut swap_buffers,
-		mut delta_time
+		mut resize,
+		surface_size,
+		mut delta_time,
+		mut input,
+		stats,
+		..
 	} = env;
 
 	/* Initialize the application state and create the visitor that will be
 	 * responsible for rendering the application state to the screen. */
-	let mut state = ApplicationRenderState::new();
+	let options = Options::from_args(std::env::args().skip(1));
+	let mut state = ApplicationRenderState::new(&options);
 	let mut state_visitor = ApplicationRenderStateVisitor::new(&device);
 
-	let mut dragging = false;
-	let mut cursor_x = 0.0_f32;
-	let mut cursor_y = 0.0_f32;
+	/* Setting everything up shouldn't count towards the first frame. Also
+	 * keep the times of every frame of a playback around, so that the
+	 * statistics at the end of it cover all of it. */
+	let _ = delta_time();
+	if let CameraSource::Play { .. } = state.source {
+		*stats.borrow_mut() = FrameStats::new(PLAYBACK_FRAME_HISTORY);
+	}
 
 	/* Common parameters passed to the renderer. */
 	let framebuffer = device.default_framebuffer(
@@ -43,61 +53,21 @@ fn run(env: Environment) {
 			depth_load_op: LoadOp::Clear(f32::INFINITY),
 			stencil_load_op: LoadOp::Clear(1)
 		});
-	let mut viewport = Viewport { x: 0, y: 0, width: 800, height: 600 };
 
 	/* Run the main game loop. */
 	event_loop.run(move |event, _, flow| {
 		*flow = ControlFlow::Poll;
+		let viewport = surface_size.viewport();
 		let mut pass = false;
 
 		/* Process the events coming from the window. */
 		match event {
 			Event::WindowEvent { event, window_id }
 			if window_id == window.id() => {
+				input.handle(&event);
 				match event {
 					WindowEvent::CloseRequested => *flow = ControlFlow::Exit,
-					WindowEvent::Resized(size) => {
-						let PhysicalSize { width, height } = size;
-						viewport.width  = width;
-						viewport.height = height;
-					},
-					WindowEvent::MouseInput { button, state, .. }
-						if MouseButton::Left == button => {
-
-						dragging = match state {
-							ElementState::Pressed => true,
-							ElementState::Released => false,
-						}
-					}
-					WindowEvent::CursorMoved { position, .. } => {
-						let x = (position.x / f64::from(viewport.width))  * 2.0 - 1.0;
-						let y = (position.y / f64::from(viewport.height)) * 2.0 - 1.0;
-
-						if dragging {
-							let dx = cursor_x - x as f32;
-							let dy = cursor_y - y as f32;
-
-							state.yaw   -= dx * std::f32::consts::PI;
-							state.pitch -= dy * std::f32::consts::PI;
-
-							state.pitch = state.pitch.clamp(
-								-std::f32::consts::FRAC_PI_2,
-								 std::f32::consts::FRAC_PI_2);
-						}
-
-						cursor_x = x as f32;
-						cursor_y = y as f32;
-					},
-					WindowEvent::MouseWheel { delta, .. } => {
-						let delta = match delta {
-							MouseScrollDelta::LineDelta(delta, _) => delta,
-							MouseScrollDelta::PixelDelta(delta) =>
-								((delta.y / f64::from(viewport.height))  * 2.0 - 1.0) as f32
-						};
-
-						state.distance += delta;
-						state.distance = state.distance.clamp(2.0, 20.0)
-					}
+					WindowEvent::Resized(size) => resize(size),
 					_ => {}
 				}
 			},
@@ -107,7 +77,23 @@ fn run(env: Environment) {
 		if !pass { return }
 
 		/* Update the application. */
-		let _ = delta_time();
+		let delta = delta_time();
+		state.update(&input, delta);
+
+		if options.exit && state.playback_finished() {
+			let stats = stats.borrow();
+			log::info!(target: "three_a::camtrack",
+				"played the camera track back over {} frames, at {:.02} FPS on \
+				average, with an average frame time of {:?} and a 99th \
+				percentile frame time of {:?}",
+				stats.frame_times().len(),
+				stats.fps(),
+				stats.frame_time_avg(),
+				stats.frame_time_p99());
+
+			*flow = ControlFlow::Exit;
+			return
+		}
 
 		/* Render the application. */
 		state_visitor.visit(
@@ -116,219 +102,263 @@ fn run(env: Environment) {
 			&viewport,
 			&state);
 
+		input.end_frame();
 		swap_buffers();
 	})
 }
 
+/** Projection applied by the camera. */
+const PROJECTION: Projection = Projection::Perspective {
+	field_of_view: std::f32::consts::FRAC_PI_2,
+	near: 1.0,
+	far: 100.0
+};
+
+/** Key taking a keyframe of the camera, while recording a camera track. */
+const KEYFRAME_KEY: VirtualKeyCode = VirtualKeyCode::F5;
+
+/** Key saving the camera track being recorded to its file. */
+const SAVE_KEY: VirtualKeyCode = VirtualKeyCode::F6;
+
+/** Number of frames whose times are kept around while playing a camera track
+ * back, which is over eighteen minutes' worth of frames at sixty frames per
+ * second. */
+const PLAYBACK_FRAME_HISTORY: usize = 1 << 16;
+
+/** Options given to this application on the command line.
+ *
+ * Passing `--camera-track <file>` records a camera track into the given file,
+ * with [`KEYFRAME_KEY`] taking a keyframe of the camera, and [`SAVE_KEY`]
+ * saving the track. Passing `--play` along with it plays the track in the file
+ * back instead, and passing `--exit` as well closes the application once the
+ * playback is over, logging the frame times it got, for benchmarking. */
+#[derive(Debug, Clone, Default)]
+struct Options {
+	/** File holding the camera track to record or to play back. */
+	camera_track: Option<PathBuf>,
+	/** Whether the camera track should be played back. */
+	play: bool,
+	/** Whether the application should close once the playback is over. */
+	exit: bool,
+}
+impl Options {
+	/** Parses the options out of the given command line arguments. */
+	pub fn from_args<I>(args: I) -> Self
+		where I: IntoIterator<Item = String> {
+
+		let mut options = Self::default();
+		let mut args = args.into_iter();
+		while let Some(arg) = args.next() {
+			match &arg[..] {
+				"--camera-track" => options.camera_track = Some(args.next()
+					.expect("--camera-track must be followed by a file")
+					.into()),
+				"--play" => options.play = true,
+				"--exit" => options.exit = true,
+				_ => log::warn!(target: "three_a::options",
+					"ignoring unknown argument {:?}", arg)
+			}
+		}
+
+		if options.play && options.camera_track.is_none() {
+			panic!("--play needs a camera track to play back")
+		}
+		if options.exit && !options.play {
+			panic!("--exit only makes sense when playing a camera track back")
+		}
+		options
+	}
+}
+
+/** What drives the camera of the application. */
+#[derive(Debug, Clone)]
+enum CameraSource {
+	/** The camera orbits around the object, following the mouse. */
+	Orbit,
+	/** The camera orbits around the object, following the mouse, while the
+	 * keyframes taken of it get recorded into a camera track. */
+	Record {
+		/** File the track gets saved to. */
+		path: PathBuf,
+		/** Recorder taking the keyframes. */
+		recorder: CameraRecorder,
+	},
+	/** The camera follows a camera track. */
+	Play {
+		/** Track being played back. */
+		track: CameraTrack,
+		/** Time into the track, in seconds. */
+		time: f32,
+	},
+}
+
 /** All of the data that makes up a given state of the application. */
-#[derive(Debug, Copy, Clone, PartialEq, PartialOrd)]
+#[derive(Debug, Clone)]
 struct ApplicationRenderState {
-	/** Angle of yaw of the object. */
-	pub yaw: f32,
-	/** Angle of pitch of the object. */
-	pub pitch: f32,
-	/** Distance to the object. */
-	pub distance: f32,
+	/** Camera orbiting around the object. */
+	pub orbit: OrbitCamera,
+	/** What drives the camera. */
+	pub source: CameraSource,
+	/** The camera the scene gets rendered with. */
+	pub camera: Camera,
 }
 impl ApplicationRenderState {
-	/** Create a new application state structure with default parameters. */
-	pub fn new() -> Self {
+	/** Create a new application state structure with default parameters, and
+	 * with the camera driven the way the given options ask for. */
+	pub fn new(options: &Options) -> Self {
+		let orbit = OrbitCamera::new(
+			[0.0; 3],
+			0.0,
+			std::f32::consts::FRAC_PI_6,
+			2.69);
+
+		let source = match (&options.camera_track, options.play) {
+			(Some(path), true) => {
+				let track = std::fs::read_to_string(path)
+					.map_err(|what| what.to_string())
+					.and_then(|text| CameraTrack::from_json(&text)
+						.map_err(|what| what.to_string()))
+					.unwrap_or_else(|what| panic!(
+						"could not load the camera track in {}: {}",
+						path.display(),
+						what));
+
+				CameraSource::Play { track, time: 0.0 }
+			},
+			(Some(path), false) => CameraSource::Record {
+				path: path.clone(),
+				recorder: CameraRecorder::new()
+			},
+			(None, _) => CameraSource::Orbit
+		};
+
 		Self {
-			yaw: 0.0,
-			pitch: std::f32::consts::FRAC_PI_6,
-			distance: 2.69
+			camera: orbit.camera(PROJECTION),
+			orbit,
+			source
 		}
 	}
-}
 
-/** Uniform parameters passed on to the shader. */
-#[derive(Debug, Copy, Clone, PartialEq, PartialOrd, bytemuck::Zeroable, bytemuck::Pod)]
-#[repr(C)]
-struct ShaderParams {
-	/** Model-World-View transformation matrix.
-	 *
-	 * This transformation maps a coordinate in model space into a coordinate
-	 * in screen space. Normally, having one single matrix for mapping model
-	 * space to screen space is incredibly wasteful. But, because we only really
-	 * have one model to display, this is a fine compromise to make, for the
-	 * sake of simplicity. */
-	pub model_world_view: Matrix4
+	/** Updates the camera with the input of a frame that took the given time. */
+	pub fn update(&mut self, input: &Input, delta: Duration) {
+		if let CameraSource::Play { track, time } = &mut self.source {
+			*time += delta.as_secs_f32();
+			if let Some(camera) = track.sample(*time) {
+				self.camera = camera;
+			}
+			return
+		}
+
+		self.orbit.update(
+			&OrbitInput {
+				cursor: input.cursor_ndc(),
+				rotating: input.button_down(MouseButton::Left),
+				panning: input.button_down(MouseButton::Middle),
+				zoom: input.scroll_delta()[1]
+			},
+			delta);
+		self.camera = self.orbit.camera(PROJECTION);
+
+		if let CameraSource::Record { path, recorder } = &mut self.source {
+			recorder.advance(delta);
+			if input.key_pressed(KEYFRAME_KEY) {
+				recorder.keyframe(&self.camera);
+				log::info!(target: "three_a::camtrack",
+					"took keyframe {} of the camera track",
+					recorder.track().keyframes().len());
+			}
+			if input.key_pressed(SAVE_KEY) {
+				match std::fs::write(path.as_path(), recorder.track().to_json()) {
+					Ok(()) => log::info!(target: "three_a::camtrack",
+						"saved the camera track to {}", path.display()),
+					Err(what) => log::warn!(target: "three_a::camtrack",
+						"could not save the camera track to {}: {}",
+						path.display(),
+						what)
+				}
+			}
+		}
+	}
+
+	/** Whether a camera track is being played back, and has reached its end. */
+	pub fn playback_finished(&self) -> bool {
+		match &self.source {
+			CameraSource::Play { track, time } => *time >= track.duration(),
+			_ => false
+		}
+	}
 }
 
-/** Structure responsible for rendering information in the example pass directly
- * into a target framebuffer, without any sort of processing. */
+/** Structure responsible for rendering the scene described by the scene file
+ * of the application directly into a target framebuffer. */
 struct ApplicationRenderStateVisitor {
-	/** The render pipeline used in the render pass. */
-	pipeline: RenderPipeline,
-	/** Vertex buffer containing data for the triangle model. */
-	vertices: VertexBuffer,
-	/** Index buffer containing data for the triangle model. */
-	indices: IndexBuffer,
-	/** Uniform data passed to the shaders in the render pass. */
-	params: UniformBuffer,
-	/** Uniform bind group passed on to the shader. */
-	bind: UniformGroup,
-	/** Number of indices in the current model. */
-	index_count: u32,
+	/** Render pipelines of the shaders materials in the scene can use. */
+	shaders: HashMap<String, RenderPipeline>,
+	/** The scene being rendered. */
+	scene: LoadedScene,
 }
 impl ApplicationRenderStateVisitor {
 	/** Create a new instance of this render pass. */
 	pub fn new(device: &Device) -> Self {
-		let steps = 64_u16;
-		let mut vertices = Vec::new();
-		let mut indices = Vec::new();
-
-		for height in 1..=steps / 2 {
-			let step = std::f32::consts::PI / f32::from(steps / 2);
-
-			let f0 = f32::from(height - 1) * step;
-			let f1 = f32::from(height) * step;
-
-			for angle in 0..=steps {
-				let step = 2.0 * std::f32::consts::PI / f32::from(steps);
-
-				let angle0 = f32::from(angle) * step;
-				let angle1 = f32::from((angle + 1) % steps) * step;
-
-				let color = |x, y, z| {
-					[
-						(x + 1.0) / 2.0,
-						(y + 1.0) / 2.0,
-						(z + 1.0) / 2.0
-					]
-				};
-
-				let x = f0.sin() * angle0.cos();
-				let y = f0.sin() * angle0.sin();
-				let z = f0.cos();
-				let v0 = Vertex::new_unchecked(
-					[x, y, z],
-					[0.0, 0.0],
-					color(x, y, z),
-					[1.0, 0.0, 0.0],
-					[0.0, 1.0, 0.0]);
-
-				let x = f1.sin() * angle0.cos();
-				let y = f1.sin() * angle0.sin();
-				let z = f1.cos();
-				let v1 = Vertex::new_unchecked(
-					[x, y, z],
-					[0.0, 0.0],
-					color(x, y, z),
-					[1.0, 0.0, 0.0],
-					[0.0, 1.0, 0.0]);
-
-				let x = f0.sin() * angle1.cos();
-				let y = f0.sin() * angle1.sin();
-				let z = f0.cos();
-				let v2 = Vertex::new_unchecked(
-					[x, y, z],
-					[0.0, 0.0],
-					color(x, y, z),
-					[1.0, 0.0, 0.0],
-					[0.0, 1.0, 0.0]);
-
-				let x = f1.sin() * angle1.cos();
-				let y = f1.sin() * angle1.sin();
-				let z = f1.cos();
-				let v3 = Vertex::new_unchecked(
-					[x, y, z],
-					[0.0, 0.0],
-					color(x, y, z),
-					[1.0, 0.0, 0.0],
-					[0.0, 1.0, 0.0]);
-
-				vertices.push(v0);
-				let v0 = (vertices.len() - 1) as u16;
-				vertices.push(v1);
-				let v1 = (vertices.len() - 1) as u16;
-				vertices.push(v2);
-				let v2 = (vertices.len() - 1) as u16;
-				vertices.push(v3);
-				let v3 = (vertices.len() - 1) as u16;
-
-				indices.push(v0);
-				indices.push(v1);
-				indices.push(v2);
-
-				indices.push(v1);
-				indices.push(v2);
-				indices.push(v3);
-			}
-		}
-		let index_count = indices.len() as u32;
-
-		let vertices = device.create_vertex_buffer_with_data(
-			&BufferDescriptor {
-				size: bytemuck::cast_slice::<_, u8>(&vertices[..]).len() as u32,
-				profile: BufferProfile::StaticUpload
-			},
-			bytemuck::cast_slice(&vertices[..])).unwrap();
-		let indices = device.create_index_buffer_with_data(
-			&BufferDescriptor {
-				size: bytemuck::cast_slice::<_, u8>(&indices[..]).len() as u32,
-				profile: BufferProfile::StaticUpload
-			},
-			bytemuck::cast_slice(&indices[..])).unwrap();
-
-		let vertex = device.create_vertex_shader(
-			assets::visitor::vertex()).unwrap();
-		let fragment = device.create_fragment_shader(
-			assets::visitor::fragment()).unwrap();
-
-		let pipeline = device.create_render_pipeline(
-			&RenderPipelineDescriptor {
-				vertex: VertexState {
-					shader: &vertex,
-					buffer: &Vertex::LAYOUT
-				},
-				primitive_state: PrimitiveState {
-					topology: PrimitiveTopology::TriangleList,
-					index_format: IndexFormat::Uint16,
-					front_face: FrontFace::Ccw,
-					cull_mode: CullMode::None,
-					polygon_mode: PolygonMode::Fill
-				},
-				fragment: Some(FragmentState {
-					shader: &fragment,
-					targets: ColorTargetState {
-						alpha_blend: BlendState::REPLACE,
-						color_blend: BlendState::REPLACE,
-						write_mask: ColorWrite::all(),
-					}
-				}),
-				depth_stencil: Some(DepthStencilState {
-					depth_write_enabled: true,
-					depth_compare: CompareFunction::Less,
-					stencil: StencilState::IGNORE
-				})
-			}).unwrap();
-
-		let params = device.create_uniform_buffer(
-			&BufferDescriptor {
-				size: u32::try_from(bytemuck::bytes_of(
-					&ShaderParams::zeroed()).len()).unwrap(),
-				profile: BufferProfile::DynamicUpload
-			}).unwrap();
-		let bind = device.create_uniform_bind_group(
-			&UniformGroupDescriptor {
-				entries: &[
-					UniformGroupEntry {
-						binding: "rc_params".into(),
-						kind: UniformBind::Buffer {
-							buffer: &params
-						}
+		let pipeline = |vertex, fragment| {
+			let vertex = device.create_vertex_shader(vertex).unwrap();
+			let fragment = device.create_fragment_shader(fragment).unwrap();
+
+			device.create_render_pipeline(
+				&RenderPipelineDescriptor {
+					vertex: VertexState {
+						shader: &vertex,
+						buffer: &Vertex::LAYOUT,
+						instance: None
 					},
-				]
-			});
+					primitive_state: PrimitiveState {
+						topology: PrimitiveTopology::TriangleList,
+						index_format: IndexFormat::Uint32,
+						front_face: FrontFace::Ccw,
+						cull_mode: CullMode::None,
+						polygon_mode: PolygonMode::Fill,
+						line_width: 1.0,
+						program_point_size: false
+					},
+					fragment: Some(FragmentState {
+						shader: &fragment,
+						targets: &[ColorTargetState {
+							alpha_blend: BlendState::REPLACE,
+							color_blend: BlendState::REPLACE,
+							write_mask: ColorWrite::all(),
+						}]
+					}),
+					depth_stencil: Some(DepthStencilState::new(
+						true,
+						CompareFunction::Less,
+						StencilState::IGNORE))
+				}).unwrap()
+		};
+
+		let mut shaders = HashMap::new();
+		shaders.insert(
+			"visitor".to_owned(),
+			pipeline(assets::visitor::vertex(), assets::visitor::fragment()));
+		shaders.insert(
+			"textured".to_owned(),
+			pipeline(assets::textured::vertex(), assets::textured::fragment()));
+
+		/* Items of the scene that fail to load are left out of it, which is
+		 * not worth stopping the application over. */
+		let file = SceneFile::from_json(assets::scene::json())
+			.expect("bundled scene file is invalid");
+		let (scene, issues) = LoadedScene::load(
+			device,
+			&file,
+			&mut assets::scene::Bundled,
+			&shaders);
+		for issue in issues {
+			log::warn!(target: "three_a::scene", "{}", issue);
+		}
 
 		Self {
-			pipeline,
-			vertices,
-			indices,
-			params,
-			bind,
-			index_count
+			shaders,
+			scene
 		}
 	}
 
@@ -340,56 +370,28 @@ impl ApplicationRenderStateVisitor {
 		viewport: &Viewport,
 		state: &ApplicationRenderState) {
 
-		/* Upload the application state to the buffer holding parameter data. */
-		let _ = {
-			let params = ShaderParams {
-				model_world_view: {
-					let matrix = Matrix4::rotate(
-						1.0,
-						0.0,
-						0.0,
-						state.pitch);
-					let matrix = Matrix4::rotate(
-						0.0,
-						1.0,
-						0.0,
-						state.yaw) * matrix;
-					let matrix = Matrix4::translate(
-						0.0,
-						0.0,
-						state.distance) * matrix;
-					let matrix = Matrix4::rectilinear_projection(
-						std::f32::consts::FRAC_PI_2,
-						(f64::from(viewport.width) / f64::from(viewport.height)) as f32,
-						1.0,
-						100.0) * matrix;
-
-					matrix.transpose()
-				}
-			};
+		/* Upload the application state to the buffers of the objects. */
+		let view_projection = state.camera.matrix(
+			(f64::from(viewport.width) / f64::from(viewport.height)) as f32);
+		self.scene.update(view_projection).unwrap();
 
-			let slice = self.params.slice(..);
-			let mut map = slice.try_map_mut(BufferLoadOp::DontCare).unwrap();
+		/* Draw the scene. */
+		let mut queue = RenderQueue::new(QueueMode::Unsorted3D);
+		self.scene.submit(&self.shaders, &mut queue);
 
-			let data = bytemuck::bytes_of(&params);
-			map[..data.len()].copy_from_slice(data);
+		let pipeline = match self.shaders.values().next() {
+			Some(pipeline) => pipeline,
+			None => return
 		};
-
-		/* Draw the triangle. */
 		let mut pass = device.start_render_pass(
 			&RenderPassDescriptor {
-				pipeline: &self.pipeline,
+				pipeline,
 				framebuffer
 			});
+		pass.set_viewport(*viewport)
+			.expect("the viewport must fit in the limits of the context");
 
-		pass.set_bind_group(&self.bind);
-		pass.set_index_buffer(&self.indices);
-		pass.set_vertex_buffer(&self.vertices);
-		pass.set_viewport(*viewport);
-
-		pass.draw_indexed(
-			0..self.index_count,
-			1);
+		queue.execute(&mut pass);
 	}
 }
 
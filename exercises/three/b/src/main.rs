@@ -1,10 +1,9 @@
 
 use environment::Environment;
 use winit::event_loop::ControlFlow;
-use winit::event::{Event, WindowEvent, ElementState, MouseButton, MouseScrollDelta};
+use winit::event::{Event, WindowEvent, MouseButton};
 use gavle::*;
-use winit::dpi::PhysicalSize;
-use support::{Vertex, Matrix4};
+use support::{Vertex, Matrix4, Mesh, OrbitCamera, OrbitInput};
 use std::convert::TryFrom;
 use bytemuck::Zeroable;
 
@@ -19,7 +18,11 @@ fn run(env: Environment) {
 		event_loop,
 		device,
 		mut swap_buffers,
-		mut delta_time
+		mut resize,
+		surface_size,
+		mut delta_time,
+		mut input,
+		..
 	} = env;
 
 	/* Initialize the application state and create the visitor that will be
@@ -27,10 +30,6 @@ fn run(env: Environment) {
 	let mut state = ApplicationRenderState::new();
 	let mut state_visitor = ApplicationRenderStateVisitor::new(&device);
 
-	let mut dragging = false;
-	let mut cursor_x = 0.0_f32;
-	let mut cursor_y = 0.0_f32;
-
 	/* Common parameters passed to the renderer. */
 	let framebuffer = device.default_framebuffer(
 		&DefaultFramebufferDescriptor {
@@ -43,61 +42,21 @@ fn run(env: Environment) {
 			depth_load_op: LoadOp::Clear(f32::INFINITY),
 			stencil_load_op: LoadOp::Clear(1)
 		});
-	let mut viewport = Viewport { x: 0, y: 0, width: 800, height: 600 };
 
 	/* Run the main game loop. */
 	event_loop.run(move |event, _, flow| {
 		*flow = ControlFlow::Poll;
+		let viewport = surface_size.viewport();
 		let mut pass = false;
 
 		/* Process the events coming from the window. */
 		match event {
 			Event::WindowEvent { event, window_id }
 			if window_id == window.id() => {
+				input.handle(&event);
 				match event {
 					WindowEvent::CloseRequested => *flow = ControlFlow::Exit,
-					WindowEvent::Resized(size) => {
-						let PhysicalSize { width, height } = size;
-						viewport.width  = width;
-						viewport.height = height;
-					},
-					WindowEvent::MouseInput { button, state, .. }
-						if MouseButton::Left == button => {
-
-						dragging = match state {
-							ElementState::Pressed => true,
-							ElementState::Released => false,
-						}
-					}
-					WindowEvent::CursorMoved { position, .. } => {
-						let x = (position.x / f64::from(viewport.width))  * 2.0 - 1.0;
-						let y = (position.y / f64::from(viewport.height)) * 2.0 - 1.0;
-
-						if dragging {
-							let dx = cursor_x - x as f32;
-							let dy = cursor_y - y as f32;
-
-							state.yaw   -= dx * std::f32::consts::PI;
-							state.pitch -= dy * std::f32::consts::PI;
-
-							state.pitch = state.pitch.clamp(
-								-std::f32::consts::FRAC_PI_2,
-								 std::f32::consts::FRAC_PI_2);
-						}
-
-						cursor_x = x as f32;
-						cursor_y = y as f32;
-					},
-					WindowEvent::MouseWheel { delta, .. } => {
-						let delta = match delta {
-							MouseScrollDelta::LineDelta(delta, _) => delta,
-							MouseScrollDelta::PixelDelta(delta) =>
-								((delta.y / f64::from(viewport.height))  * 2.0 - 1.0) as f32
-						};
-
-						state.distance += delta;
-						state.distance = state.distance.clamp(2.0, 20.0)
-					}
+					WindowEvent::Resized(size) => resize(size),
 					_ => {}
 				}
 			},
@@ -107,7 +66,16 @@ fn run(env: Environment) {
 		if !pass { return }
 
 		/* Update the application. */
-		let _ = delta_time();
+		let delta = delta_time();
+
+		state.orbit.update(
+			&OrbitInput {
+				cursor: input.cursor_ndc(),
+				rotating: input.button_down(MouseButton::Left),
+				panning: input.button_down(MouseButton::Middle),
+				zoom: input.scroll_delta()[1]
+			},
+			delta);
 
 		/* Render the application. */
 		state_visitor.visit(
@@ -116,27 +84,26 @@ fn run(env: Environment) {
 			&viewport,
 			&state);
 
+		input.end_frame();
 		swap_buffers();
 	})
 }
 
 /** All of the data that makes up a given state of the application. */
-#[derive(Debug, Copy, Clone, PartialEq, PartialOrd)]
+#[derive(Debug, Clone)]
 struct ApplicationRenderState {
-	/** Angle of yaw of the object. */
-	pub yaw: f32,
-	/** Angle of pitch of the object. */
-	pub pitch: f32,
-	/** Distance to the object. */
-	pub distance: f32,
+	/** Camera orbiting around the object. */
+	pub orbit: OrbitCamera,
 }
 impl ApplicationRenderState {
 	/** Create a new application state structure with default parameters. */
 	pub fn new() -> Self {
 		Self {
-			yaw: 0.0,
-			pitch: std::f32::consts::FRAC_PI_6,
-			distance: 2.69
+			orbit: OrbitCamera::new(
+				[0.0; 3],
+				0.0,
+				std::f32::consts::FRAC_PI_6,
+				2.69)
 		}
 	}
 }
@@ -160,9 +127,9 @@ struct ShaderParams {
 struct ApplicationRenderStateVisitor {
 	/** The render pipeline used in the render pass. */
 	pipeline: RenderPipeline,
-	/** Vertex buffer containing data for the triangle model. */
+	/** Vertex buffer containing data for the cylinder model. */
 	vertices: VertexBuffer,
-	/** Index buffer containing data for the triangle model. */
+	/** Index buffer containing data for the cylinder model. */
 	indices: IndexBuffer,
 	/** Uniform data passed to the shaders in the render pass. */
 	params: UniformBuffer,
@@ -174,73 +141,26 @@ struct ApplicationRenderStateVisitor {
 impl ApplicationRenderStateVisitor {
 	/** Create a new instance of this render pass. */
 	pub fn new(device: &Device) -> Self {
-		let steps = 64_u16;
-		let mut vertices = Vec::new();
-		let mut indices = Vec::new();
-
-		/* Generate the upper and lower discs. */
-		for height in 0..=1 {
-			let z = if height == 0 { -1.0 } else { 1.0 };
-			let local_vertices = (0..steps)
-				.into_iter()
-				.map(|step| {
-					let angle = 2.0 * std::f32::consts::PI / f32::from(steps - 1);
-					let angle = angle * f32::from(step);
-
-					let x = angle.cos();
-					let y = angle.sin();
-
-					Vertex::new_unchecked(
-						[x, y, z],
-						[x, y],
-						[(x + 1.0) / 2.0, (y + 1.0) / 2.0, 1.0],
-						[1.0, 0.0, 0.0],
-						[0.0, 1.0, 0.0])
-				});
-			vertices.extend(local_vertices);
-
-			let base = if height == 0 { 0 } else { steps };
-			let local_indices = (base..base + steps)
-				.skip(1)
-				.zip((base..base + steps).skip(2))
-				.flat_map(|(a, b)| {
-					std::array::IntoIter::new([base, a, b])
-				});
-			indices.extend(local_indices);
-		}
-
-		/* Weave between them. */
-		for triangle in 0..steps {
-			/* Bottom up triangle. */
-			let a0 = triangle;
-			let b0 = (triangle + 1) % steps;
-			let c0 = triangle + steps;
-
-			/* Bottom down triangle. */
-			let a1 = if triangle == 0 {
-				triangle + steps + steps - 1
-			} else {
-				triangle + steps - 1
-			};
-			let b1 = triangle + steps;
-			let c1 = triangle;
-
-			indices.extend(std::array::IntoIter::new([a0, b0, c0]));
-			indices.extend(std::array::IntoIter::new([a1, b1, c1]));
-		}
+		let mesh = Mesh::cylinder(64, true);
+		let vertices = mesh.vertices();
+		let indices = mesh.indices();
 
 		let index_count = indices.len() as u32;
 
 		let vertices = device.create_vertex_buffer_with_data(
 			&BufferDescriptor {
 				size: bytemuck::cast_slice::<_, u8>(&vertices[..]).len() as u32,
-				profile: BufferProfile::StaticUpload
+				profile: BufferProfile::StaticUpload,
+				contents: BufferContents::Opaque,
+				..Default::default()
 			},
 			bytemuck::cast_slice(&vertices[..])).unwrap();
 		let indices = device.create_index_buffer_with_data(
 			&BufferDescriptor {
 				size: bytemuck::cast_slice::<_, u8>(&indices[..]).len() as u32,
-				profile: BufferProfile::StaticUpload
+				profile: BufferProfile::StaticUpload,
+				contents: BufferContents::Opaque,
+				..Default::default()
 			},
 			bytemuck::cast_slice(&indices[..])).unwrap();
 
@@ -253,35 +173,39 @@ impl ApplicationRenderStateVisitor {
 			&RenderPipelineDescriptor {
 				vertex: VertexState {
 					shader: &vertex,
-					buffer: &Vertex::LAYOUT
+					buffer: &Vertex::LAYOUT,
+					instance: None
 				},
 				primitive_state: PrimitiveState {
 					topology: PrimitiveTopology::TriangleList,
-					index_format: IndexFormat::Uint16,
+					index_format: IndexFormat::Uint32,
 					front_face: FrontFace::Ccw,
 					cull_mode: CullMode::None,
-					polygon_mode: PolygonMode::Fill
+					polygon_mode: PolygonMode::Fill,
+					line_width: 1.0,
+					program_point_size: false
 				},
 				fragment: Some(FragmentState {
 					shader: &fragment,
-					targets: ColorTargetState {
+					targets: &[ColorTargetState {
 						alpha_blend: BlendState::REPLACE,
 						color_blend: BlendState::REPLACE,
 						write_mask: ColorWrite::all(),
-					}
+					}]
 				}),
-				depth_stencil: Some(DepthStencilState {
-					depth_write_enabled: true,
-					depth_compare: CompareFunction::Less,
-					stencil: StencilState::IGNORE
-				})
+				depth_stencil: Some(DepthStencilState::new(
+					true,
+					CompareFunction::Less,
+					StencilState::IGNORE))
 			}).unwrap();
 
 		let params = device.create_uniform_buffer(
 			&BufferDescriptor {
 				size: u32::try_from(bytemuck::bytes_of(
 					&ShaderParams::zeroed()).len()).unwrap(),
-				profile: BufferProfile::DynamicUpload
+				profile: BufferProfile::DynamicUpload,
+				contents: BufferContents::F32,
+				..Default::default()
 			}).unwrap();
 		let bind = device.create_uniform_bind_group(
 			&UniformGroupDescriptor {
@@ -289,11 +213,13 @@ impl ApplicationRenderStateVisitor {
 					UniformGroupEntry {
 						binding: "rc_params".into(),
 						kind: UniformBind::Buffer {
-							buffer: &params
+							buffer: &params,
+							offset: 0,
+							size: None
 						}
 					},
 				]
-			});
+			}).unwrap();
 
 		Self {
 			pipeline,
@@ -317,20 +243,7 @@ impl ApplicationRenderStateVisitor {
 		let _ = {
 			let params = ShaderParams {
 				model_world_view: {
-					let matrix = Matrix4::rotate(
-						1.0,
-						0.0,
-						0.0,
-						state.pitch);
-					let matrix = Matrix4::rotate(
-						0.0,
-						1.0,
-						0.0,
-						state.yaw) * matrix;
-					let matrix = Matrix4::translate(
-						0.0,
-						0.0,
-						state.distance) * matrix;
+					let matrix = state.orbit.view_matrix();
 					let matrix = Matrix4::rectilinear_projection(
 						std::f32::consts::FRAC_PI_2,
 						(f64::from(viewport.width) / f64::from(viewport.height)) as f32,
@@ -342,7 +255,8 @@ impl ApplicationRenderStateVisitor {
 			};
 
 			let slice = self.params.slice(..);
-			let mut map = slice.try_map_mut(BufferLoadOp::DontCare).unwrap();
+			let mut map = slice.try_map_mut(BufferLoadOp::DontCare, MapMode::Orphan)
+				.unwrap();
 
 			let data = bytemuck::bytes_of(&params);
 			map[..data.len()].copy_from_slice(data);
@@ -358,11 +272,13 @@ impl ApplicationRenderStateVisitor {
 		pass.set_bind_group(&self.bind);
 		pass.set_index_buffer(&self.indices);
 		pass.set_vertex_buffer(&self.vertices);
-		pass.set_viewport(*viewport);
+		pass.set_viewport(*viewport)
+			.expect("the viewport must fit in the limits of the context");
 
 		pass.draw_indexed(
 			0..self.index_count,
-			1);
+			1)
+			.expect("the indices must fit in the index buffer");
 	}
 }
 
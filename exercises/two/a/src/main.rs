@@ -4,7 +4,6 @@ use winit::event_loop::ControlFlow;
 use winit::event::{Event, WindowEvent, MouseButton, ElementState};
 use gavle::*;
 use std::time::Duration;
-use winit::dpi::PhysicalSize;
 use support::{Vertex, Matrix4};
 use std::convert::TryFrom;
 use bytemuck::Zeroable;
@@ -20,7 +19,10 @@ fn run(env: Environment) {
 		event_loop,
 		device,
 		mut swap_buffers,
-		mut delta_time
+		mut resize,
+		surface_size,
+		mut delta_time,
+		..
 	} = env;
 
 	/* Initialize the application state and create the visitor that will be
@@ -42,11 +44,11 @@ fn run(env: Environment) {
 			depth_load_op: LoadOp::Clear(f32::NEG_INFINITY),
 			stencil_load_op: LoadOp::Clear(1)
 		});
-	let mut viewport = Viewport { x: 0, y: 0, width: 800, height: 600 };
 
 	/* Run the main game loop. */
 	event_loop.run(move |event, _, flow| {
 		*flow = ControlFlow::Poll;
+		let viewport = surface_size.viewport();
 		let mut pass = false;
 
 		/* Process the events coming from the window. */
@@ -55,11 +57,7 @@ fn run(env: Environment) {
 			if window_id == window.id() => {
 				match event {
 					WindowEvent::CloseRequested => *flow = ControlFlow::Exit,
-					WindowEvent::Resized(size) => {
-						let PhysicalSize { width, height } = size;
-						viewport.width  = width;
-						viewport.height = height;
-					},
+					WindowEvent::Resized(size) => resize(size),
 					WindowEvent::MouseInput { button, state, .. } => {
 						match (button, state) {
 							(MouseButton::Left, ElementState::Pressed)   => grow_direction += 1.0,
@@ -154,13 +152,17 @@ impl ApplicationRenderStateVisitor {
 		let vertices = device.create_vertex_buffer_with_data(
 			&BufferDescriptor {
 				size: bytemuck::bytes_of(VERTICES).len() as u32,
-				profile: BufferProfile::StaticUpload
+				profile: BufferProfile::StaticUpload,
+				contents: BufferContents::Opaque,
+				..Default::default()
 			},
 			bytemuck::bytes_of(VERTICES)).unwrap();
 		let indices = device.create_index_buffer_with_data(
 			&BufferDescriptor {
 				size: bytemuck::bytes_of(INDICES).len() as u32,
-				profile: BufferProfile::StaticUpload
+				profile: BufferProfile::StaticUpload,
+				contents: BufferContents::Opaque,
+				..Default::default()
 			},
 			bytemuck::bytes_of(INDICES)).unwrap();
 
@@ -170,34 +172,16 @@ impl ApplicationRenderStateVisitor {
 			assets::visitor::fragment()).unwrap();
 
 		let pipeline = device.create_render_pipeline(
-			&RenderPipelineDescriptor {
-				vertex: VertexState {
-					shader: &vertex,
-					buffer: &Vertex::LAYOUT
-				},
-				primitive_state: PrimitiveState {
-					topology: PrimitiveTopology::TriangleList,
-					index_format: IndexFormat::Uint16,
-					front_face: FrontFace::Ccw,
-					cull_mode: CullMode::None,
-					polygon_mode: PolygonMode::Fill
-				},
-				fragment: Some(FragmentState {
-					shader: &fragment,
-					targets: ColorTargetState {
-						alpha_blend: BlendState::REPLACE,
-						color_blend: BlendState::REPLACE,
-						write_mask: ColorWrite::all(),
-					}
-				}),
-				depth_stencil: None
-			}).unwrap();
+			&RenderPipelineDescriptor::new(&vertex, &Vertex::LAYOUT)
+				.with_fragment(&fragment)).unwrap();
 
 		let params = device.create_uniform_buffer(
 			&BufferDescriptor {
 				size: u32::try_from(bytemuck::bytes_of(
 					&ShaderParams::zeroed()).len()).unwrap(),
-				profile: BufferProfile::DynamicUpload
+				profile: BufferProfile::DynamicUpload,
+				contents: BufferContents::F32,
+				..Default::default()
 			}).unwrap();
 		let bind = device.create_uniform_bind_group(
 			&UniformGroupDescriptor {
@@ -205,11 +189,13 @@ impl ApplicationRenderStateVisitor {
 					UniformGroupEntry {
 						binding: "rc_params".into(),
 						kind: UniformBind::Buffer {
-							buffer: &params
+							buffer: &params,
+							offset: 0,
+							size: None
 						}
 					}
 				]
-			});
+			}).unwrap();
 
 		Self {
 			pipeline,
@@ -238,7 +224,8 @@ impl ApplicationRenderStateVisitor {
 			};
 
 			let slice = self.params.slice(..);
-			let mut map = slice.try_map_mut(BufferLoadOp::DontCare).unwrap();
+			let mut map = slice.try_map_mut(BufferLoadOp::DontCare, MapMode::Orphan)
+				.unwrap();
 
 			let data = bytemuck::bytes_of(&params);
 			map[..data.len()].copy_from_slice(data);
@@ -254,11 +241,13 @@ impl ApplicationRenderStateVisitor {
 		pass.set_bind_group(&self.bind);
 		pass.set_index_buffer(&self.indices);
 		pass.set_vertex_buffer(&self.vertices);
-		pass.set_viewport(*viewport);
+		pass.set_viewport(*viewport)
+			.expect("the viewport must fit in the limits of the context");
 
 		pass.draw_indexed(
 			0..5,
-			1);
+			1)
+			.expect("the indices must fit in the index buffer");
 	}
 }
 
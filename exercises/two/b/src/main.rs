@@ -1,10 +1,9 @@
 
 use environment::Environment;
 use winit::event_loop::ControlFlow;
-use winit::event::{Event, WindowEvent, MouseButton, ElementState};
+use winit::event::{Event, WindowEvent, VirtualKeyCode};
 use gavle::*;
 use std::time::Duration;
-use winit::dpi::PhysicalSize;
 use support::{Vertex, Matrix4};
 use std::convert::TryFrom;
 use bytemuck::Zeroable;
@@ -20,7 +19,11 @@ fn run(env: Environment) {
 		event_loop,
 		device,
 		mut swap_buffers,
-		mut delta_time
+		mut resize,
+		surface_size,
+		mut delta_time,
+		mut input,
+		..
 	} = env;
 
 	/* Initialize the application state and create the visitor that will be
@@ -28,8 +31,6 @@ fn run(env: Environment) {
 	let mut state = ApplicationRenderState::new();
 	let mut state_visitor = ApplicationRenderStateVisitor::new(&device);
 
-	let mut grow_direction = 0.0f32;
-
 	/* Common parameters passed to the renderer. */
 	let framebuffer = device.default_framebuffer(
 		&DefaultFramebufferDescriptor {
@@ -42,35 +43,21 @@ fn run(env: Environment) {
 			depth_load_op: LoadOp::Clear(f32::NEG_INFINITY),
 			stencil_load_op: LoadOp::Clear(1)
 		});
-	let mut viewport = Viewport { x: 0, y: 0, width: 800, height: 600 };
 
 	/* Run the main game loop. */
 	event_loop.run(move |event, _, flow| {
 		*flow = ControlFlow::Poll;
+		let viewport = surface_size.viewport();
 		let mut pass = false;
 
 		/* Process the events coming from the window. */
 		match event {
 			Event::WindowEvent { event, window_id }
 			if window_id == window.id() => {
+				input.handle(&event);
 				match event {
 					WindowEvent::CloseRequested => *flow = ControlFlow::Exit,
-					WindowEvent::Resized(size) => {
-						let PhysicalSize { width, height } = size;
-						viewport.width  = width;
-						viewport.height = height;
-					},
-					WindowEvent::KeyboardInput { input, .. } => {
-						let (button, state) = (input.scancode, input.state);
-
-						match (button, state) {
-							(57419, ElementState::Pressed)                           => grow_direction = 1.0,
-							(57419, ElementState::Released) if grow_direction >= 0.0 => grow_direction = 0.0,
-							(57421, ElementState::Pressed)                           => grow_direction = -1.0,
-							(57421, ElementState::Released) if grow_direction <= 0.0 => grow_direction = 0.0,
-							_ => {}
-						}
-					},
+					WindowEvent::Resized(size) => resize(size),
 					_ => {}
 				}
 			},
@@ -82,11 +69,7 @@ fn run(env: Environment) {
 		/* Update the application. */
 		let delta = delta_time();
 
-		let grow_direction = if grow_direction == 0.0 {
-			0.0
-		} else {
-			grow_direction.signum()
-		};
+		let grow_direction = input.axis(VirtualKeyCode::Right, VirtualKeyCode::Left);
 		state.rotation += 0.5 * delta.as_secs_f32() * grow_direction;
 
 		/* Render the application. */
@@ -96,6 +79,7 @@ fn run(env: Environment) {
 			&viewport,
 			&state);
 
+		input.end_frame();
 		swap_buffers();
 	})
 }
@@ -157,13 +141,17 @@ impl ApplicationRenderStateVisitor {
 		let vertices = device.create_vertex_buffer_with_data(
 			&BufferDescriptor {
 				size: bytemuck::bytes_of(VERTICES).len() as u32,
-				profile: BufferProfile::StaticUpload
+				profile: BufferProfile::StaticUpload,
+				contents: BufferContents::Opaque,
+				..Default::default()
 			},
 			bytemuck::bytes_of(VERTICES)).unwrap();
 		let indices = device.create_index_buffer_with_data(
 			&BufferDescriptor {
 				size: bytemuck::bytes_of(INDICES).len() as u32,
-				profile: BufferProfile::StaticUpload
+				profile: BufferProfile::StaticUpload,
+				contents: BufferContents::Opaque,
+				..Default::default()
 			},
 			bytemuck::bytes_of(INDICES)).unwrap();
 
@@ -176,22 +164,25 @@ impl ApplicationRenderStateVisitor {
 			&RenderPipelineDescriptor {
 				vertex: VertexState {
 					shader: &vertex,
-					buffer: &Vertex::LAYOUT
+					buffer: &Vertex::LAYOUT,
+					instance: None
 				},
 				primitive_state: PrimitiveState {
 					topology: PrimitiveTopology::TriangleStrip,
 					index_format: IndexFormat::Uint16,
 					front_face: FrontFace::Ccw,
 					cull_mode: CullMode::None,
-					polygon_mode: PolygonMode::Fill
+					polygon_mode: PolygonMode::Fill,
+					line_width: 1.0,
+					program_point_size: false
 				},
 				fragment: Some(FragmentState {
 					shader: &fragment,
-					targets: ColorTargetState {
+					targets: &[ColorTargetState {
 						alpha_blend: BlendState::REPLACE,
 						color_blend: BlendState::REPLACE,
 						write_mask: ColorWrite::all(),
-					}
+					}]
 				}),
 				depth_stencil: None
 			}).unwrap();
@@ -200,7 +191,9 @@ impl ApplicationRenderStateVisitor {
 			&BufferDescriptor {
 				size: u32::try_from(bytemuck::bytes_of(
 					&ShaderParams::zeroed()).len()).unwrap(),
-				profile: BufferProfile::DynamicUpload
+				profile: BufferProfile::DynamicUpload,
+				contents: BufferContents::F32,
+				..Default::default()
 			}).unwrap();
 		let bind = device.create_uniform_bind_group(
 			&UniformGroupDescriptor {
@@ -208,11 +201,13 @@ impl ApplicationRenderStateVisitor {
 					UniformGroupEntry {
 						binding: "rc_params".into(),
 						kind: UniformBind::Buffer {
-							buffer: &params
+							buffer: &params,
+							offset: 0,
+							size: None
 						}
 					}
 				]
-			});
+			}).unwrap();
 
 		Self {
 			pipeline,
@@ -242,7 +237,8 @@ impl ApplicationRenderStateVisitor {
 			};
 
 			let slice = self.params.slice(..);
-			let mut map = slice.try_map_mut(BufferLoadOp::DontCare).unwrap();
+			let mut map = slice.try_map_mut(BufferLoadOp::DontCare, MapMode::Orphan)
+				.unwrap();
 
 			let data = bytemuck::bytes_of(&params);
 			map[..data.len()].copy_from_slice(data);
@@ -258,11 +254,13 @@ impl ApplicationRenderStateVisitor {
 		pass.set_bind_group(&self.bind);
 		pass.set_index_buffer(&self.indices);
 		pass.set_vertex_buffer(&self.vertices);
-		pass.set_viewport(*viewport);
+		pass.set_viewport(*viewport)
+			.expect("the viewport must fit in the limits of the context");
 
 		pass.draw_indexed(
 			0..5,
-			1);
+			1)
+			.expect("the indices must fit in the index buffer");
 	}
 }
 
@@ -1,9 +1,8 @@
 
 use environment::Environment;
 use winit::event_loop::ControlFlow;
-use winit::event::{Event, WindowEvent, ElementState};
+use winit::event::{Event, WindowEvent, VirtualKeyCode};
 use gavle::*;
-use winit::dpi::PhysicalSize;
 use support::{Vertex, Matrix4};
 use std::convert::TryFrom;
 use bytemuck::Zeroable;
@@ -19,7 +18,11 @@ fn run(env: Environment) {
 		event_loop,
 		device,
 		mut swap_buffers,
-		mut delta_time
+		mut resize,
+		surface_size,
+		mut delta_time,
+		mut input,
+		..
 	} = env;
 
 	/* Initialize the application state and create the visitor that will be
@@ -27,9 +30,6 @@ fn run(env: Environment) {
 	let mut state = ApplicationRenderState::new();
 	let mut state_visitor = ApplicationRenderStateVisitor::new(&device);
 
-	let mut direction_y = 0.0f32;
-	let mut direction_x = 0.0f32;
-
 	/* Common parameters passed to the renderer. */
 	let framebuffer = device.default_framebuffer(
 		&DefaultFramebufferDescriptor {
@@ -42,39 +42,21 @@ fn run(env: Environment) {
 			depth_load_op: LoadOp::Clear(f32::NEG_INFINITY),
 			stencil_load_op: LoadOp::Clear(1)
 		});
-	let mut viewport = Viewport { x: 0, y: 0, width: 800, height: 600 };
 
 	/* Run the main game loop. */
 	event_loop.run(move |event, _, flow| {
 		*flow = ControlFlow::Poll;
+		let viewport = surface_size.viewport();
 		let mut pass = false;
 
 		/* Process the events coming from the window. */
 		match event {
 			Event::WindowEvent { event, window_id }
 			if window_id == window.id() => {
+				input.handle(&event);
 				match event {
 					WindowEvent::CloseRequested => *flow = ControlFlow::Exit,
-					WindowEvent::Resized(size) => {
-						let PhysicalSize { width, height } = size;
-						viewport.width  = width;
-						viewport.height = height;
-					},
-					WindowEvent::KeyboardInput { input, .. } => {
-						let (button, state) = (input.scancode, input.state);
-
-						match (button, state) {
-							(17, ElementState::Pressed)                        => direction_y = 1.0,
-							(17, ElementState::Released) if direction_y >= 0.0 => direction_y = 0.0,
-							(30, ElementState::Pressed)                        => direction_x = -1.0,
-							(30, ElementState::Released) if direction_x <= 0.0 => direction_x = 0.0,
-							(31, ElementState::Pressed)                        => direction_y = -1.0,
-							(31, ElementState::Released) if direction_y <= 0.0 => direction_y = 0.0,
-							(32, ElementState::Pressed)                        => direction_x = 1.0,
-							(32, ElementState::Released) if direction_x >= 0.0 => direction_x = 0.0,
-							_ => {}
-						}
-					},
+					WindowEvent::Resized(size) => resize(size),
 					_ => {}
 				}
 			},
@@ -86,16 +68,8 @@ fn run(env: Environment) {
 		/* Update the application. */
 		let delta = delta_time();
 
-		let direction_x = if direction_x == 0.0 {
-			0.0
-		} else {
-			direction_x.signum()
-		};
-		let direction_y = if direction_y == 0.0 {
-			0.0
-		} else {
-			direction_y.signum()
-		};
+		let direction_x = input.axis(VirtualKeyCode::A, VirtualKeyCode::D);
+		let direction_y = input.axis(VirtualKeyCode::S, VirtualKeyCode::W);
 
 		state.position[0] += 0.5 * delta.as_secs_f32() * direction_x;
 		state.position[1] += 0.5 * delta.as_secs_f32() * direction_y;
@@ -107,6 +81,7 @@ fn run(env: Environment) {
 			&viewport,
 			&state);
 
+		input.end_frame();
 		swap_buffers();
 	})
 }
@@ -189,13 +164,17 @@ impl ApplicationRenderStateVisitor {
 		let vertices = device.create_vertex_buffer_with_data(
 			&BufferDescriptor {
 				size: bytemuck::cast_slice::<_, u8>(&vertices[..]).len() as u32,
-				profile: BufferProfile::StaticUpload
+				profile: BufferProfile::StaticUpload,
+				contents: BufferContents::Opaque,
+				..Default::default()
 			},
 			bytemuck::cast_slice(&vertices[..])).unwrap();
 		let indices = device.create_index_buffer_with_data(
 			&BufferDescriptor {
 				size: bytemuck::cast_slice::<_, u8>(&indices[..]).len() as u32,
-				profile: BufferProfile::StaticUpload
+				profile: BufferProfile::StaticUpload,
+				contents: BufferContents::Opaque,
+				..Default::default()
 			},
 			bytemuck::cast_slice(&indices[..])).unwrap();
 
@@ -208,22 +187,25 @@ impl ApplicationRenderStateVisitor {
 			&RenderPipelineDescriptor {
 				vertex: VertexState {
 					shader: &vertex,
-					buffer: &Vertex::LAYOUT
+					buffer: &Vertex::LAYOUT,
+					instance: None
 				},
 				primitive_state: PrimitiveState {
 					topology: PrimitiveTopology::TriangleStrip,
 					index_format: IndexFormat::Uint16,
 					front_face: FrontFace::Ccw,
 					cull_mode: CullMode::None,
-					polygon_mode: PolygonMode::Fill
+					polygon_mode: PolygonMode::Fill,
+					line_width: 1.0,
+					program_point_size: false
 				},
 				fragment: Some(FragmentState {
 					shader: &fragment,
-					targets: ColorTargetState {
+					targets: &[ColorTargetState {
 						alpha_blend: BlendState::REPLACE,
 						color_blend: BlendState::REPLACE,
 						write_mask: ColorWrite::all(),
-					}
+					}]
 				}),
 				depth_stencil: None
 			}).unwrap();
@@ -232,7 +214,9 @@ impl ApplicationRenderStateVisitor {
 			&BufferDescriptor {
 				size: u32::try_from(bytemuck::bytes_of(
 					&ShaderParams::zeroed()).len()).unwrap(),
-				profile: BufferProfile::DynamicUpload
+				profile: BufferProfile::DynamicUpload,
+				contents: BufferContents::F32,
+				..Default::default()
 			}).unwrap();
 		let bind = device.create_uniform_bind_group(
 			&UniformGroupDescriptor {
@@ -240,11 +224,13 @@ impl ApplicationRenderStateVisitor {
 					UniformGroupEntry {
 						binding: "rc_params".into(),
 						kind: UniformBind::Buffer {
-							buffer: &params
+							buffer: &params,
+							offset: 0,
+							size: None
 						}
 					}
 				]
-			});
+			}).unwrap();
 
 		Self {
 			pipeline,
@@ -279,7 +265,8 @@ impl ApplicationRenderStateVisitor {
 			};
 
 			let slice = self.params.slice(..);
-			let mut map = slice.try_map_mut(BufferLoadOp::DontCare).unwrap();
+			let mut map = slice.try_map_mut(BufferLoadOp::DontCare, MapMode::Orphan)
+				.unwrap();
 
 			let data = bytemuck::bytes_of(&params);
 			map[..data.len()].copy_from_slice(data);
@@ -295,11 +282,13 @@ impl ApplicationRenderStateVisitor {
 		pass.set_bind_group(&self.bind);
 		pass.set_index_buffer(&self.indices);
 		pass.set_vertex_buffer(&self.vertices);
-		pass.set_viewport(*viewport);
+		pass.set_viewport(*viewport)
+			.expect("the viewport must fit in the limits of the context");
 
 		pass.draw_indexed(
 			0..self.index_count,
-			1);
+			1)
+			.expect("the indices must fit in the index buffer");
 	}
 }
 
@@ -1,10 +1,9 @@
 
 use environment::Environment;
 use winit::event_loop::ControlFlow;
-use winit::event::{Event, WindowEvent, ElementState};
+use winit::event::{Event, WindowEvent, VirtualKeyCode};
 use gavle::*;
-use winit::dpi::PhysicalSize;
-use support::{Vertex, Matrix4};
+use support::{Vertex, Matrix4, RenderQueue, QueueMode, DrawItem, DrawBounds};
 use std::convert::TryFrom;
 use bytemuck::Zeroable;
 
@@ -19,7 +18,11 @@ fn run(env: Environment) {
 		event_loop,
 		device,
 		mut swap_buffers,
-		mut delta_time
+		mut resize,
+		surface_size,
+		mut delta_time,
+		mut input,
+		..
 	} = env;
 
 	/* Initialize the application state and create the visitor that will be
@@ -27,9 +30,6 @@ fn run(env: Environment) {
 	let mut state = ApplicationRenderState::new();
 	let mut state_visitor = ApplicationRenderStateVisitor::new(&device);
 
-	let mut direction_y = 0.0f32;
-	let mut direction_x = 0.0f32;
-
 	/* Common parameters passed to the renderer. */
 	let framebuffer = device.default_framebuffer(
 		&DefaultFramebufferDescriptor {
@@ -42,43 +42,21 @@ fn run(env: Environment) {
 			depth_load_op: LoadOp::Clear(f32::NEG_INFINITY),
 			stencil_load_op: LoadOp::Clear(1)
 		});
-	let mut viewport = Viewport { x: 0, y: 0, width: 800, height: 600 };
 
 	/* Run the main game loop. */
 	event_loop.run(move |event, _, flow| {
 		*flow = ControlFlow::Poll;
+		let viewport = surface_size.viewport();
 		let mut pass = false;
 
 		/* Process the events coming from the window. */
 		match event {
 			Event::WindowEvent { event, window_id }
 			if window_id == window.id() => {
+				input.handle(&event);
 				match event {
 					WindowEvent::CloseRequested => *flow = ControlFlow::Exit,
-					WindowEvent::Resized(size) => {
-						let PhysicalSize { width, height } = size;
-						viewport.width  = width;
-						viewport.height = height;
-					},
-					WindowEvent::KeyboardInput { input, .. } => {
-						let (button, state) = (input.scancode, input.state);
-
-						match (button, state) {
-							(17, ElementState::Pressed)                        => direction_y = 1.0,
-							(17, ElementState::Released) if direction_y >= 0.0 => direction_y = 0.0,
-							(30, ElementState::Pressed)                        => direction_x = -1.0,
-							(30, ElementState::Released) if direction_x <= 0.0 => direction_x = 0.0,
-							(31, ElementState::Pressed)                        => direction_y = -1.0,
-							(31, ElementState::Released) if direction_y <= 0.0 => direction_y = 0.0,
-							(32, ElementState::Pressed)                        => direction_x = 1.0,
-							(32, ElementState::Released) if direction_x >= 0.0 => direction_x = 0.0,
-							_ => {}
-						}
-					},
-					WindowEvent::CursorMoved { position, .. } => {
-						state.triangle_position[0] =  ((position.x / f64::from(viewport.width))  * 2.0 - 1.0) as f32;
-						state.triangle_position[1] = -((position.y / f64::from(viewport.height)) * 2.0 - 1.0) as f32;
-					}
+					WindowEvent::Resized(size) => resize(size),
 					_ => {}
 				}
 			},
@@ -90,20 +68,16 @@ fn run(env: Environment) {
 		/* Update the application. */
 		let delta = delta_time();
 
-		let direction_x = if direction_x == 0.0 {
-			0.0
-		} else {
-			direction_x.signum()
-		};
-		let direction_y = if direction_y == 0.0 {
-			0.0
-		} else {
-			direction_y.signum()
-		};
+		let direction_x = input.axis(VirtualKeyCode::A, VirtualKeyCode::D);
+		let direction_y = input.axis(VirtualKeyCode::S, VirtualKeyCode::W);
 
 		state.circle_position[0] += 0.5 * delta.as_secs_f32() * direction_x;
 		state.circle_position[1] += 0.5 * delta.as_secs_f32() * direction_y;
 
+		if let Some(cursor) = input.cursor_ndc() {
+			state.triangle_position = cursor;
+		}
+
 		/* Render the application. */
 		state_visitor.visit(
 			&device,
@@ -111,6 +85,7 @@ fn run(env: Environment) {
 			&viewport,
 			&state);
 
+		input.end_frame();
 		swap_buffers();
 	})
 }
@@ -210,13 +185,17 @@ impl ApplicationRenderStateVisitor {
 		let circle_vertices = device.create_vertex_buffer_with_data(
 			&BufferDescriptor {
 				size: bytemuck::cast_slice::<_, u8>(&circle_vertices[..]).len() as u32,
-				profile: BufferProfile::StaticUpload
+				profile: BufferProfile::StaticUpload,
+				contents: BufferContents::Opaque,
+				..Default::default()
 			},
 			bytemuck::cast_slice(&circle_vertices[..])).unwrap();
 		let circle_indices = device.create_index_buffer_with_data(
 			&BufferDescriptor {
 				size: bytemuck::cast_slice::<_, u8>(&circle_indices[..]).len() as u32,
-				profile: BufferProfile::StaticUpload
+				profile: BufferProfile::StaticUpload,
+				contents: BufferContents::Opaque,
+				..Default::default()
 			},
 			bytemuck::cast_slice(&circle_indices[..])).unwrap();
 
@@ -230,13 +209,17 @@ impl ApplicationRenderStateVisitor {
 		let triangle_vertices = device.create_vertex_buffer_with_data(
 			&BufferDescriptor {
 				size: bytemuck::bytes_of(TRIANGLE_VERTICES).len() as u32,
-				profile: BufferProfile::StaticUpload
+				profile: BufferProfile::StaticUpload,
+				contents: BufferContents::Opaque,
+				..Default::default()
 			},
 			bytemuck::bytes_of(TRIANGLE_VERTICES)).unwrap();
 		let triangle_indices = device.create_index_buffer_with_data(
 			&BufferDescriptor {
 				size: bytemuck::bytes_of(TRIANGLE_INDICES).len() as u32,
-				profile: BufferProfile::StaticUpload
+				profile: BufferProfile::StaticUpload,
+				contents: BufferContents::Opaque,
+				..Default::default()
 			},
 			bytemuck::bytes_of(TRIANGLE_INDICES)).unwrap();
 
@@ -249,22 +232,25 @@ impl ApplicationRenderStateVisitor {
 			&RenderPipelineDescriptor {
 				vertex: VertexState {
 					shader: &vertex,
-					buffer: &Vertex::LAYOUT
+					buffer: &Vertex::LAYOUT,
+					instance: None
 				},
 				primitive_state: PrimitiveState {
 					topology: PrimitiveTopology::TriangleStrip,
 					index_format: IndexFormat::Uint16,
 					front_face: FrontFace::Ccw,
 					cull_mode: CullMode::None,
-					polygon_mode: PolygonMode::Fill
+					polygon_mode: PolygonMode::Fill,
+					line_width: 1.0,
+					program_point_size: false
 				},
 				fragment: Some(FragmentState {
 					shader: &fragment,
-					targets: ColorTargetState {
+					targets: &[ColorTargetState {
 						alpha_blend: BlendState::REPLACE,
 						color_blend: BlendState::REPLACE,
 						write_mask: ColorWrite::all(),
-					}
+					}]
 				}),
 				depth_stencil: None
 			}).unwrap();
@@ -273,7 +259,9 @@ impl ApplicationRenderStateVisitor {
 			&BufferDescriptor {
 				size: u32::try_from(bytemuck::bytes_of(
 					&ShaderParams::zeroed()).len()).unwrap(),
-				profile: BufferProfile::DynamicUpload
+				profile: BufferProfile::DynamicUpload,
+				contents: BufferContents::F32,
+				..Default::default()
 			}).unwrap();
 		let circle_bind = device.create_uniform_bind_group(
 			&UniformGroupDescriptor {
@@ -281,17 +269,21 @@ impl ApplicationRenderStateVisitor {
 					UniformGroupEntry {
 						binding: "rc_params".into(),
 						kind: UniformBind::Buffer {
-							buffer: &circle_params
+							buffer: &circle_params,
+							offset: 0,
+							size: None
 						}
 					}
 				]
-			});
+			}).unwrap();
 
 		let triangle_params = device.create_uniform_buffer(
 			&BufferDescriptor {
 				size: u32::try_from(bytemuck::bytes_of(
 					&ShaderParams::zeroed()).len()).unwrap(),
-				profile: BufferProfile::DynamicUpload
+				profile: BufferProfile::DynamicUpload,
+				contents: BufferContents::F32,
+				..Default::default()
 			}).unwrap();
 		let triangle_bind = device.create_uniform_bind_group(
 			&UniformGroupDescriptor {
@@ -299,11 +291,13 @@ impl ApplicationRenderStateVisitor {
 					UniformGroupEntry {
 						binding: "rc_params".into(),
 						kind: UniformBind::Buffer {
-							buffer: &triangle_params
+							buffer: &triangle_params,
+							offset: 0,
+							size: None
 						}
 					}
 				]
-			});
+			}).unwrap();
 
 		Self {
 			pipeline,
@@ -344,7 +338,8 @@ impl ApplicationRenderStateVisitor {
 			};
 
 			let slice = self.circle_params.slice(..);
-			let mut map = slice.try_map_mut(BufferLoadOp::DontCare).unwrap();
+			let mut map = slice.try_map_mut(BufferLoadOp::DontCare, MapMode::Orphan)
+				.unwrap();
 
 			let data = bytemuck::bytes_of(&params);
 			map[..data.len()].copy_from_slice(data);
@@ -366,7 +361,8 @@ impl ApplicationRenderStateVisitor {
 			};
 
 			let slice = self.triangle_params.slice(..);
-			let mut map = slice.try_map_mut(BufferLoadOp::DontCare).unwrap();
+			let mut map = slice.try_map_mut(BufferLoadOp::DontCare, MapMode::Orphan)
+				.unwrap();
 
 			let data = bytemuck::bytes_of(&params);
 			map[..data.len()].copy_from_slice(data);
@@ -377,30 +373,38 @@ impl ApplicationRenderStateVisitor {
 				pipeline: &self.pipeline,
 				framebuffer
 			});
+		pass.set_viewport(*viewport)
+			.expect("the viewport must fit in the limits of the context");
+
+		/* There is no depth buffer in this pass, so let the queue sort the
+		 * draws instead, with the triangle always in front of the circle. */
+		let bounds = |position: [f32; 2], extent: f32| Some(DrawBounds {
+			min: [position[0] - extent, position[1] - extent],
+			max: [position[0] + extent, position[1] + extent]
+		});
 
-		/* Draw the circle. */
-		let _ = {
-			pass.set_bind_group(&self.circle_bind);
-			pass.set_index_buffer(&self.circle_indices);
-			pass.set_vertex_buffer(&self.circle_vertices);
-			pass.set_viewport(*viewport);
-
-			pass.draw_indexed(
-				0..self.circle_index_count,
-				1);
-		};
-
-		/* Draw the triangle. */
-		let _ = {
-			pass.set_bind_group(&self.triangle_bind);
-			pass.set_index_buffer(&self.triangle_indices);
-			pass.set_vertex_buffer(&self.triangle_vertices);
-			pass.set_viewport(*viewport);
-
-			pass.draw_indexed(
-				0..self.triangle_index_count,
-				1);
-		};
+		let mut queue = RenderQueue::new(QueueMode::Layer2D);
+		queue.submit(DrawItem {
+			pipeline: &self.pipeline,
+			bind: Some(&self.circle_bind),
+			vertices: &self.circle_vertices,
+			indices: &self.circle_indices,
+			elements: 0..self.circle_index_count,
+			instances: 1,
+			z: 1.0,
+			bounds: bounds(state.circle_position, 0.2)
+		});
+		queue.submit(DrawItem {
+			pipeline: &self.pipeline,
+			bind: Some(&self.triangle_bind),
+			vertices: &self.triangle_vertices,
+			indices: &self.triangle_indices,
+			elements: 0..self.triangle_index_count,
+			instances: 1,
+			z: 0.0,
+			bounds: bounds(state.triangle_position, 0.1)
+		});
+		queue.execute(&mut pass);
 	}
 }
 
@@ -52,39 +52,36 @@ pub mod dish {
 			.into_rgba8()
 	}
 
-	/** Decode the normal texture data for the dish into a raw image buffer.
+	/** Decode the normal texture data for the dish into an image.
 	 *
 	 * Every call to this function will perform the decoding process into a new raw
 	 * image allocation, given the data for the raw texture is too large to be
 	 * cached on  the web, where we'll be competing with Facebook and YouTube for
 	 * resources. */
-	pub fn normal() -> image::RgbaImage {
+	pub fn normal() -> image::DynamicImage {
 		image::load_from_memory(include_bytes!("dish/normal.jpg"))
 			.unwrap()
-			.into_rgba8()
 	}
 
-	/** Decode the roughness texture data for the dish into a raw image buffer.
+	/** Decode the roughness texture data for the dish into an image.
 	 *
 	 * Every call to this function will perform the decoding process into a new raw
 	 * image allocation, given the data for the raw texture is too large to be
 	 * cached on  the web, where we'll be competing with Facebook and YouTube for
 	 * resources. */
-	pub fn roughness() -> image::RgbaImage {
+	pub fn roughness() -> image::DynamicImage {
 		image::load_from_memory(include_bytes!("dish/roughness.jpg"))
 			.unwrap()
-			.into_rgba8()
 	}
 
-	/** Decode the metallic texture data for the dish into a raw image buffer.
+	/** Decode the metallic texture data for the dish into an image.
 	 *
 	 * Every call to this function will perform the decoding process into a new raw
 	 * image allocation, given the data for the raw texture is too large to be
 	 * cached on  the web, where we'll be competing with Facebook and YouTube for
 	 * resources. */
-	pub fn metallic() -> image::RgbaImage {
+	pub fn metallic() -> image::DynamicImage {
 		image::load_from_memory(include_bytes!("dish/metallic.jpg"))
 			.unwrap()
-			.into_rgba8()
 	}
 }
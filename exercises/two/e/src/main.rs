@@ -1,13 +1,13 @@
 
 use environment::Environment;
 use winit::event_loop::ControlFlow;
-use winit::event::{Event, WindowEvent, ElementState, MouseButton, MouseScrollDelta};
+use winit::event::{Event, WindowEvent, MouseButton};
 use gavle::*;
-use winit::dpi::PhysicalSize;
-use support::{Vertex, Matrix4};
+use support::{Vertex, Matrix4, OrbitCamera, OrbitInput};
 use std::convert::TryFrom;
 use bytemuck::Zeroable;
 use std::num::NonZeroU8;
+use std::time::Duration;
 
 /** Graphical assets used by this application. */
 mod assets;
@@ -15,12 +15,20 @@ mod assets;
 /** Function responsible for running the game inside of a given application
  * environment, provided by the [`environment`] crate. */
 fn run(env: Environment) {
+	/* Dragging the model around should follow the mouse as closely as it can,
+	 * so don't let the driver queue up frames ahead of it. */
+	env.set_max_frames_in_flight(Some(environment::DEFAULT_MAX_FRAMES_IN_FLIGHT));
+
 	let Environment {
 		window,
 		event_loop,
 		device,
 		mut swap_buffers,
-		mut delta_time
+		mut resize,
+		surface_size,
+		mut delta_time,
+		mut input,
+		..
 	} = env;
 
 	/* Initialize the application state and create the visitor that will be
@@ -28,10 +36,6 @@ fn run(env: Environment) {
 	let mut state = ApplicationRenderState::new();
 	let mut state_visitor = ApplicationRenderStateVisitor::new(&device);
 
-	let mut dragging = false;
-	let mut cursor_x = 0.0_f32;
-	let mut cursor_y = 0.0_f32;
-
 	/* Common parameters passed to the renderer. */
 	let framebuffer = device.default_framebuffer(
 		&DefaultFramebufferDescriptor {
@@ -44,61 +48,21 @@ fn run(env: Environment) {
 			depth_load_op: LoadOp::Clear(f32::INFINITY),
 			stencil_load_op: LoadOp::Clear(1)
 		});
-	let mut viewport = Viewport { x: 0, y: 0, width: 800, height: 600 };
 
 	/* Run the main game loop. */
 	event_loop.run(move |event, _, flow| {
 		*flow = ControlFlow::Poll;
+		let viewport = surface_size.viewport();
 		let mut pass = false;
 
 		/* Process the events coming from the window. */
 		match event {
 			Event::WindowEvent { event, window_id }
 			if window_id == window.id() => {
+				input.handle(&event);
 				match event {
 					WindowEvent::CloseRequested => *flow = ControlFlow::Exit,
-					WindowEvent::Resized(size) => {
-						let PhysicalSize { width, height } = size;
-						viewport.width  = width;
-						viewport.height = height;
-					},
-					WindowEvent::MouseInput { button, state, .. }
-						if MouseButton::Left == button => {
-
-						dragging = match state {
-							ElementState::Pressed => true,
-							ElementState::Released => false,
-						}
-					}
-					WindowEvent::CursorMoved { position, .. } => {
-						let x = (position.x / f64::from(viewport.width))  * 2.0 - 1.0;
-						let y = (position.y / f64::from(viewport.height)) * 2.0 - 1.0;
-
-						if dragging {
-							let dx = cursor_x - x as f32;
-							let dy = cursor_y - y as f32;
-
-							state.yaw   -= dx * std::f32::consts::PI;
-							state.pitch -= dy * std::f32::consts::PI;
-
-							state.pitch = state.pitch.clamp(
-								-std::f32::consts::FRAC_PI_2,
-								 std::f32::consts::FRAC_PI_2);
-						}
-
-						cursor_x = x as f32;
-						cursor_y = y as f32;
-					},
-					WindowEvent::MouseWheel { delta, .. } => {
-						let delta = match delta {
-							MouseScrollDelta::LineDelta(delta, _) => delta,
-							MouseScrollDelta::PixelDelta(delta) =>
-								((delta.y / f64::from(viewport.height))  * 2.0 - 1.0) as f32
-						};
-
-						state.distance += delta;
-						state.distance = state.distance.clamp(2.0, 20.0)
-					}
+					WindowEvent::Resized(size) => resize(size),
 					_ => {}
 				}
 			},
@@ -108,36 +72,45 @@ fn run(env: Environment) {
 		if !pass { return }
 
 		/* Update the application. */
-		let _ = delta_time();
+		let delta = delta_time();
+
+		state.orbit.update(
+			&OrbitInput {
+				cursor: input.cursor_ndc(),
+				rotating: input.button_down(MouseButton::Left),
+				panning: input.button_down(MouseButton::Middle),
+				zoom: input.scroll_delta()[1]
+			},
+			delta);
 
 		/* Render the application. */
 		state_visitor.visit(
 			&device,
 			&framebuffer,
 			&viewport,
-			&state);
+			&state,
+			delta);
 
+		input.end_frame();
 		swap_buffers();
 	})
 }
 
 /** All of the data that makes up a given state of the application. */
-#[derive(Debug, Copy, Clone, PartialEq, PartialOrd)]
+#[derive(Debug, Clone)]
 struct ApplicationRenderState {
-	/** Angle of yaw of the object. */
-	pub yaw: f32,
-	/** Angle of pitch of the object. */
-	pub pitch: f32,
-	/** Distance to the object. */
-	pub distance: f32,
+	/** Camera orbiting around the object. */
+	pub orbit: OrbitCamera,
 }
 impl ApplicationRenderState {
 	/** Create a new application state structure with default parameters. */
 	pub fn new() -> Self {
 		Self {
-			yaw: 0.0,
-			pitch: std::f32::consts::FRAC_PI_6,
-			distance: 2.69
+			orbit: OrbitCamera::new(
+				[0.0; 3],
+				0.0,
+				std::f32::consts::FRAC_PI_6,
+				2.69)
 		}
 	}
 }
@@ -156,6 +129,12 @@ struct ShaderParams {
 	pub model_world_view: Matrix4
 }
 
+/** Number of frames in the animated albedo texture of the dish. */
+const ALBEDO_FRAMES: usize = 12;
+
+/** Rate at which the frames of the animated albedo texture are shown. */
+const ALBEDO_FRAME_RATE: f32 = 12.0;
+
 /** Structure responsible for rendering information in the example pass directly
  * into a target framebuffer, without any sort of processing. */
 struct ApplicationRenderStateVisitor {
@@ -169,6 +148,8 @@ struct ApplicationRenderStateVisitor {
 	params: UniformBuffer,
 	/** Uniform bind group passed on to the shader. */
 	bind: UniformGroup,
+	/** Animated albedo texture of the model. */
+	albedo: support::FrameSequenceTexture,
 	/** Number of indices in the current model. */
 	index_count: u32,
 }
@@ -184,13 +165,17 @@ impl ApplicationRenderStateVisitor {
 		let vertices = device.create_vertex_buffer_with_data(
 			&BufferDescriptor {
 				size: bytemuck::cast_slice::<_, u8>(&vertices[..]).len() as u32,
-				profile: BufferProfile::StaticUpload
+				profile: BufferProfile::StaticUpload,
+				contents: BufferContents::Opaque,
+				..Default::default()
 			},
 			bytemuck::cast_slice(&vertices[..])).unwrap();
 		let indices = device.create_index_buffer_with_data(
 			&BufferDescriptor {
 				size: bytemuck::cast_slice::<_, u8>(&indices[..]).len() as u32,
-				profile: BufferProfile::StaticUpload
+				profile: BufferProfile::StaticUpload,
+				contents: BufferContents::Opaque,
+				..Default::default()
 			},
 			bytemuck::cast_slice(&indices[..])).unwrap();
 
@@ -203,88 +188,98 @@ impl ApplicationRenderStateVisitor {
 			&RenderPipelineDescriptor {
 				vertex: VertexState {
 					shader: &vertex,
-					buffer: &Vertex::LAYOUT
+					buffer: &Vertex::LAYOUT,
+					instance: None
 				},
 				primitive_state: PrimitiveState {
 					topology: PrimitiveTopology::TriangleList,
 					index_format: IndexFormat::Uint32,
 					front_face: FrontFace::Ccw,
 					cull_mode: CullMode::None,
-					polygon_mode: PolygonMode::Fill
+					polygon_mode: PolygonMode::Fill,
+					line_width: 1.0,
+					program_point_size: false
 				},
 				fragment: Some(FragmentState {
 					shader: &fragment,
-					targets: ColorTargetState {
+					targets: &[ColorTargetState {
 						alpha_blend: BlendState::REPLACE,
 						color_blend: BlendState::REPLACE,
 						write_mask: ColorWrite::all(),
-					}
+					}]
 				}),
-				depth_stencil: Some(DepthStencilState {
-					depth_write_enabled: true,
-					depth_compare: CompareFunction::Less,
-					stencil: StencilState::IGNORE
-				})
+				depth_stencil: Some(DepthStencilState::new(
+					true,
+					CompareFunction::Less,
+					StencilState::IGNORE))
 			}).unwrap();
 
 		let params = device.create_uniform_buffer(
 			&BufferDescriptor {
 				size: u32::try_from(bytemuck::bytes_of(
 					&ShaderParams::zeroed()).len()).unwrap(),
-				profile: BufferProfile::DynamicUpload
+				profile: BufferProfile::DynamicUpload,
+				contents: BufferContents::F32,
+				..Default::default()
 			}).unwrap();
 		let albedo = {
 			let image = assets::dish::albedo();
 			let width = image.width();
 			let height = image.height();
+			let base = image.into_raw();
+
+			/* Every frame of the animation is the albedo of the dish with its
+			 * brightness pulsing along a sine wave over the whole loop. */
+			let decode = move |frame: usize| {
+				let phase = frame as f32 / ALBEDO_FRAMES as f32
+					* 2.0 * std::f32::consts::PI;
+				let scale = 0.75 + 0.25 * phase.sin();
+
+				let mut data = base.clone();
+				for texel in data.chunks_exact_mut(4) {
+					for channel in &mut texel[..3] {
+						*channel = (f32::from(*channel) * scale) as u8;
+					}
+				}
+				data
+			};
 
-			device.create_texture_with_data(
-				&TextureDescriptor {
-					extent: TextureExtent::D2 { width, height },
-					format: TextureFormat::Rgba8Unorm,
-					mip: Mipmap::None
-				},
-				image.into_raw()).unwrap()
-		};
-		let normal = {
-			let image = assets::dish::normal();
-			let width = image.width();
-			let height = image.height();
-
-			device.create_texture_with_data(
-				&TextureDescriptor {
-					extent: TextureExtent::D2 { width, height },
-					format: TextureFormat::Rgba8Unorm,
-					mip: Mipmap::None
-				},
-				image.into_raw()).unwrap()
-		};
-		let roughness = {
-			let image = assets::dish::roughness();
-			let width = image.width();
-			let height = image.height();
-
-			device.create_texture_with_data(
-				&TextureDescriptor {
-					extent: TextureExtent::D2 { width, height },
-					format: TextureFormat::Rgba8Unorm,
-					mip: Mipmap::None
-				},
-				image.into_raw()).unwrap()
-		};
-		let metallic = {
-			let image = assets::dish::metallic();
-			let width = image.width();
-			let height = image.height();
-
-			device.create_texture_with_data(
-				&TextureDescriptor {
-					extent: TextureExtent::D2 { width, height },
-					format: TextureFormat::Rgba8Unorm,
-					mip: Mipmap::None
+			support::FrameSequenceTexture::new(
+				device,
+				&support::FrameSequenceDescriptor {
+					width,
+					height,
+					frame_rate: ALBEDO_FRAME_RATE,
+					mode: support::PlaybackMode::Loop,
+					decode_ahead: support::DEFAULT_DECODE_AHEAD,
+					decode_budget: 1
 				},
-				image.into_raw()).unwrap()
+				support::FrameSource::Decoder {
+					frames: ALBEDO_FRAMES,
+					decode: Box::new(decode)
+				}).unwrap()
 		};
+		let normal = device.create_texture_from_image(
+			&assets::dish::normal(),
+			&ImageTextureDescriptor {
+				usage: ImageUsage::Data,
+				flip_y: false,
+				mip: Mipmap::None
+			}).unwrap();
+		let roughness = device.create_texture_from_image(
+			&assets::dish::roughness(),
+			&ImageTextureDescriptor {
+				usage: ImageUsage::Data,
+				flip_y: false,
+				mip: Mipmap::None
+			}).unwrap();
+		let metallic = device.create_texture_from_image(
+			&assets::dish::metallic(),
+			&ImageTextureDescriptor {
+				usage: ImageUsage::Data,
+				flip_y: false,
+				mip: Mipmap::None
+			}).unwrap();
 
 		let bind = device.create_uniform_bind_group(
 			&UniformGroupDescriptor {
@@ -292,16 +287,20 @@ impl ApplicationRenderStateVisitor {
 					UniformGroupEntry {
 						binding: "rc_params".into(),
 						kind: UniformBind::Buffer {
-							buffer: &params
+							buffer: &params,
+							offset: 0,
+							size: None
 						}
 					},
 					UniformGroupEntry {
 						binding: "tt_tex_albedo".into(),
 						kind: UniformBind::Texture {
-							texture: &albedo,
+							texture: albedo.texture(),
 							far: TextureFilter::Nearest,
 							near: TextureFilter::Nearest,
+							address_mode: AddressMode::Repeat,
 							anisotropy_clamp: Some(NonZeroU8::new(16).unwrap()),
+							sampler: None,
 						}
 					},
 					UniformGroupEntry {
@@ -310,7 +309,9 @@ impl ApplicationRenderStateVisitor {
 							texture: &normal,
 							far: TextureFilter::Linear,
 							near: TextureFilter::Linear,
+							address_mode: AddressMode::Repeat,
 							anisotropy_clamp: Some(NonZeroU8::new(16).unwrap()),
+							sampler: None,
 						}
 					},
 					UniformGroupEntry {
@@ -319,7 +320,9 @@ impl ApplicationRenderStateVisitor {
 							texture: &roughness,
 							far: TextureFilter::Linear,
 							near: TextureFilter::Linear,
+							address_mode: AddressMode::Repeat,
 							anisotropy_clamp: Some(NonZeroU8::new(16).unwrap()),
+							sampler: None,
 						}
 					},
 					UniformGroupEntry {
@@ -328,11 +331,13 @@ impl ApplicationRenderStateVisitor {
 							texture: &metallic,
 							far: TextureFilter::Linear,
 							near: TextureFilter::Linear,
+							address_mode: AddressMode::Repeat,
 							anisotropy_clamp: Some(NonZeroU8::new(16).unwrap()),
+							sampler: None,
 						}
 					},
 				]
-			});
+			}).unwrap();
 
 		Self {
 			pipeline,
@@ -340,6 +345,7 @@ impl ApplicationRenderStateVisitor {
 			indices,
 			params,
 			bind,
+			albedo,
 			index_count
 		}
 	}
@@ -350,26 +356,18 @@ impl ApplicationRenderStateVisitor {
 		device: &Device,
 		framebuffer: &Framebuffer,
 		viewport: &Viewport,
-		state: &ApplicationRenderState) {
+		state: &ApplicationRenderState,
+		delta: Duration) {
+
+		/* Move the animation of the albedo texture along, which has to be done
+		 * before the render pass starts. */
+		self.albedo.tick(delta).unwrap();
 
 		/* Upload the application state to the buffer holding parameter data. */
 		let _ = {
 			let params = ShaderParams {
 				model_world_view: {
-					let matrix = Matrix4::rotate(
-						1.0,
-						0.0,
-						0.0,
-						state.pitch);
-					let matrix = Matrix4::rotate(
-						0.0,
-						1.0,
-						0.0,
-						state.yaw) * matrix;
-					let matrix = Matrix4::translate(
-						0.0,
-						0.0,
-						state.distance) * matrix;
+					let matrix = state.orbit.view_matrix();
 					let matrix = Matrix4::rectilinear_projection(
 						std::f32::consts::FRAC_PI_2,
 						(f64::from(viewport.width) / f64::from(viewport.height)) as f32,
@@ -381,7 +379,8 @@ impl ApplicationRenderStateVisitor {
 			};
 
 			let slice = self.params.slice(..);
-			let mut map = slice.try_map_mut(BufferLoadOp::DontCare).unwrap();
+			let mut map = slice.try_map_mut(BufferLoadOp::DontCare, MapMode::Orphan)
+				.unwrap();
 
 			let data = bytemuck::bytes_of(&params);
 			map[..data.len()].copy_from_slice(data);
@@ -397,11 +396,13 @@ impl ApplicationRenderStateVisitor {
 		pass.set_bind_group(&self.bind);
 		pass.set_index_buffer(&self.indices);
 		pass.set_vertex_buffer(&self.vertices);
-		pass.set_viewport(*viewport);
+		pass.set_viewport(*viewport)
+			.expect("the viewport must fit in the limits of the context");
 
 		pass.draw_indexed(
 			0..self.index_count,
-			1);
+			1)
+			.expect("the indices must fit in the index buffer");
 	}
 }
 
@@ -24,13 +24,15 @@ impl Vertex {
 				kind: VertexType::F32,
 				components: VertexComponents::Four,
 				offset: 0,
-				binding: Cow::Borrowed("position")
+				binding: Cow::Borrowed("position"),
+				location: None
 			},
 			VertexAttribute {
 				kind: VertexType::F32,
 				components: VertexComponents::Two,
 				offset: 16,
-				binding: Cow::Borrowed("texture_uv")
+				binding: Cow::Borrowed("texture_uv"),
+				location: None
 			}
 		]
 	};
@@ -106,7 +108,8 @@ fn run<F, G, W>(
 		&TextureDescriptor {
 			extent: TextureExtent::D2 { width: texture_width, height: texture_height },
 			format: TextureFormat::Rgba8Unorm,
-			mip: Mipmap::Automatic { filter: FilterType::Lanczos3 }
+			mip: Mipmap::Automatic { filter: FilterType::Lanczos3 },
+			swizzle: None
 		},
 		&texture)
 		.unwrap();
@@ -114,13 +117,17 @@ fn run<F, G, W>(
 	let vertices = device.create_vertex_buffer_with_data(
 		&BufferDescriptor {
 			size: u32::try_from(Vertex::cube_vertex_bytes().len()).unwrap(),
-			profile: BufferProfile::StaticUpload
+			profile: BufferProfile::StaticUpload,
+			contents: BufferContents::Opaque,
+			..Default::default()
 		},
 		&Vertex::cube_vertex_bytes()).unwrap();
 	let indices = device.create_index_buffer_with_data(
 		&BufferDescriptor {
 			size: u32::try_from(Vertex::cube_index_bytes().len()).unwrap(),
-			profile: BufferProfile::StaticUpload
+			profile: BufferProfile::StaticUpload,
+			contents: BufferContents::Opaque,
+			..Default::default()
 		},
 		&Vertex::cube_index_bytes()).unwrap();
 
@@ -131,7 +138,9 @@ fn run<F, G, W>(
 	let matrices = device.create_uniform_buffer_with_data(
 		&BufferDescriptor {
 			size: u32::try_from(bytemuck::bytes_of(&combined).len()).unwrap(),
-			profile: BufferProfile::DynamicUpload
+			profile: BufferProfile::DynamicUpload,
+			contents: BufferContents::Opaque,
+			..Default::default()
 		},
 		&bytemuck::bytes_of(&combined))
 		.unwrap();
@@ -145,44 +154,50 @@ fn run<F, G, W>(
 						texture: &texture,
 						far: TextureFilter::Linear,
 						near: TextureFilter::Linear,
+						address_mode: AddressMode::Repeat,
 						anisotropy_clamp: Some(NonZeroU8::new(16).unwrap()),
+						sampler: None,
 					}
 				},
 				UniformGroupEntry {
 					binding: "matrices".into(),
 					kind: UniformBind::Buffer {
-						buffer: &matrices
+						buffer: &matrices,
+						offset: 0,
+						size: None
 					}
 				}
 			]
-		});
+		}).unwrap();
 
 	let pipeline = device.create_render_pipeline(
 		&RenderPipelineDescriptor {
 			vertex: VertexState {
 				shader: &vertex_shader,
-				buffer: Vertex::LAYOUT
+				buffer: Vertex::LAYOUT,
+				instance: None
 			},
 			primitive_state: PrimitiveState {
 				topology: PrimitiveTopology::TriangleList,
 				index_format: IndexFormat::Uint32,
 				front_face: FrontFace::Ccw,
 				cull_mode: CullMode::None,
-				polygon_mode: PolygonMode::Fill
+				polygon_mode: PolygonMode::Fill,
+				line_width: 1.0,
+				program_point_size: false
 			},
 			fragment: Some(FragmentState {
 				shader: &fragment_shader,
-				targets: ColorTargetState {
+				targets: &[ColorTargetState {
 					alpha_blend: BlendState::REPLACE,
 					color_blend: BlendState::REPLACE,
 					write_mask: ColorWrite::all()
-				}
+				}]
 			}),
-			depth_stencil: Some(DepthStencilState {
-				depth_write_enabled: true,
-				depth_compare: CompareFunction::Less,
-				stencil: StencilState::IGNORE,
-			})
+			depth_stencil: Some(DepthStencilState::new(
+				true,
+				CompareFunction::Less,
+				StencilState::IGNORE))
 		}).unwrap();
 	let framebuffer = device.default_framebuffer(
 		&DefaultFramebufferDescriptor {
@@ -196,12 +211,7 @@ fn run<F, G, W>(
 			stencil_load_op: LoadOp::Clear(0xff),
 		});
 
-	let mut viewport = Viewport {
-		x: 0,
-		y: 0,
-		width: 800,
-		height: 600
-	};
+	device.resize_default_framebuffer(800, 600);
 	event_loop.run(move |event, _, control| {
 		let mut pass_off = false;
 		*control = ControlFlow::Poll;
@@ -212,14 +222,8 @@ fn run<F, G, W>(
 
 				match event {
 					WindowEvent::CloseRequested => *control = ControlFlow::Exit,
-					WindowEvent::Resized(PhysicalSize { width, height }) => {
-						viewport = Viewport {
-							x: 0,
-							y: 0,
-							width,
-							height
-						};
-					},
+					WindowEvent::Resized(PhysicalSize { width, height }) =>
+						device.resize_default_framebuffer(width, height),
 					_ => {}
 				}
 			},
@@ -238,7 +242,7 @@ fn run<F, G, W>(
 		{
 			let slice = matrices.slice(..);
 			let mut map = slice
-				.try_map_mut(BufferLoadOp::DontCare)
+				.try_map_mut(BufferLoadOp::DontCare, MapMode::Orphan)
 				.unwrap();
 
 			let combined = [modelview.transpose(), projection.transpose()];
@@ -248,13 +252,14 @@ fn run<F, G, W>(
 			pipeline: &pipeline,
 			framebuffer: &framebuffer
 		});
-		pass.set_viewport(viewport);
+		pass.set_full_viewport().unwrap();
 		pass.set_index_buffer(&indices);
 		pass.set_vertex_buffer(&vertices);
 		pass.set_bind_group(&uniforms);
 		pass.draw_indexed(
 			0..u32::try_from(Vertex::CUBE_INDICES.len()).unwrap(),
-			1);
+			1)
+			.expect("the indices must fit in the index buffer");
 
 		swap();
 	})
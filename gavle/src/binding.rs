@@ -1,17 +1,33 @@
 use std::borrow::Cow;
-use std::rc::Rc;
-use crate::texture::{TextureFilter, Texture};
-use crate::buffer::UniformBuffer;
+use std::rc::{Rc, Weak};
+use crate::texture::{TextureFilter, AddressMode, Texture, TextureView, TextureViewDescriptor, TextureExtent};
+use crate::buffer::{Buffer, BufferUsage};
+use crate::texel::TexelBuffer;
 use crate::access::AccessLock;
 use glow::{Context, HasContext};
 use std::convert::TryFrom;
-use crate::{RenderProgram, Features};
-use std::num::NonZeroU8;
+use crate::{RenderProgram, RenderPipeline, Information};
+use crate::pipeline::BlockBinding;
+use crate::blocks::BlockRegistry;
+use crate::sampler::Sampler;
+use crate::usage::UsageTracker;
+use crate::texture::InnerTexture;
+use std::num::{NonZeroU8, NonZeroU32};
+use std::cell::{Cell, RefCell};
+use crate::registry::ResourceKind;
 
 /**  */
 pub struct UniformGroup {
-	/** Uniform binds. */
-	pub(crate) entries: Rc<Vec<(String, OwnedUniformBind)>>
+	/** Uniform binds. These may be swapped for others of the same kind after
+	 * the group gets created, with [`UniformGroup::replace_texture()`] and
+	 * [`UniformGroup::replace_buffer()`].
+	 *
+	 * [`UniformGroup::replace_texture()`]: UniformGroup::replace_texture
+	 * [`UniformGroup::replace_buffer()`]: UniformGroup::replace_buffer */
+	pub(crate) entries: Rc<RefCell<Vec<(String, OwnedUniformBind)>>>,
+	/** Number of times an entry of this group has been replaced, so that
+	 * render passes know to bind the group again when it changes under them. */
+	pub(crate) revision: Rc<Cell<u64>>,
 }
 impl AccessLock for UniformGroup {
 	fn acquire_write(&self) {
@@ -23,49 +39,460 @@ impl AccessLock for UniformGroup {
 			uniforms are read-only objects");
 	}
 	fn acquire_read(&self) {
-		for (_, entry) in &*self.entries {
+		for (_, entry) in &*self.entries.borrow() {
 			match entry {
 				OwnedUniformBind::Texture { texture, .. } =>
 					texture.acquire_read(),
-				OwnedUniformBind::Buffer { buffer } =>
+				OwnedUniformBind::Buffer { buffer, .. } =>
+					buffer.acquire_read(),
+				OwnedUniformBind::TexelBuffer { buffer } =>
 					buffer.acquire_read()
 			}
 		}
 	}
 	fn release_read(&self) {
-		for (_, entry) in &*self.entries {
+		for (_, entry) in &*self.entries.borrow() {
 			match entry {
 				OwnedUniformBind::Texture { texture, .. } =>
 					texture.release_read(),
-				OwnedUniformBind::Buffer { buffer } =>
+				OwnedUniformBind::Buffer { buffer, .. } =>
+					buffer.release_read(),
+				OwnedUniformBind::TexelBuffer { buffer } =>
 					buffer.release_read()
 			}
 		}
 	}
 }
 impl UniformGroup {
-	/** Bind all of the elements of this uniform bind group.
+	/** Number of uniform buffer entries in this group, which is also the
+	 * number of dynamic offsets it takes. */
+	pub fn buffer_count(&self) -> usize {
+		self.entries.borrow().iter()
+			.filter(|(_, entry)| matches!(entry, OwnedUniformBind::Buffer { .. }))
+			.count()
+	}
+
+	/** Another handle to this same group, which shares its entries, and sees
+	 * every replacement made through either of them. */
+	pub(crate) fn share(&self) -> Self {
+		Self {
+			entries: self.entries.clone(),
+			revision: self.revision.clone()
+		}
+	}
+
+	/** Replaces the texture bound by the texture entry with the given binding,
+	 * keeping its filtering parameters, without having to create the group
+	 * all over again. This is useful for things like double buffered shadow
+	 * maps, whose groups get held by many renderers.
+	 *
+	 * The new texture must be of the same kind as the old one, so that it can
+	 * be sampled the same way. Entries created from a view keep viewing the
+	 * same levels and layers, which the new texture must have. The change
+	 * takes effect the next time the group gets bound, including by render
+	 * passes it is already bound to.
+	 *
+	 * # Errors
+	 * This function fails if there's no texture entry with the given binding,
+	 * or if the new texture can't stand in for the old one. */
+	pub fn replace_texture(&self, binding: &str, texture: &Texture)
+		-> Result<(), BindGroupError> {
+
+		let mut entries = self.entries.borrow_mut();
+		let entry = entries.iter_mut()
+			.find(|(name, _)| name == binding)
+			.map(|(_, entry)| entry)
+			.ok_or_else(|| BindGroupError::InvalidBinding {
+				what: format!("the group has no entry bound to \"{}\"", binding)
+			})?;
+
+		let (current, view, sliced) = match entry {
+			OwnedUniformBind::Texture { texture, view, sliced, .. } =>
+				(texture, view, *sliced),
+			other => return Err(BindGroupError::InvalidBinding {
+				what: format!("\"{}\" is bound to a {}, not to a texture",
+					binding,
+					other.kind())
+			})
+		};
+
+		let (old, new) = (current.extent(), texture.extent());
+		if old.target() != new.target() {
+			return Err(BindGroupError::InvalidBinding {
+				what: format!("\"{}\" is bound to a texture of extent {:?}, \
+					which can't be sampled the same way as one of extent {:?}",
+					binding,
+					old,
+					new)
+			})
+		}
+
+		let replacement = if sliced {
+			view.validate(texture.levels(), texture.layers())
+				.map_err(|what| BindGroupError::InvalidBinding {
+					what: format!("the view bound to \"{}\" doesn't fit in \
+						the new texture: {}", binding, what)
+				})?;
+			*view
+		} else {
+			TextureViewDescriptor {
+				base_mip: 0,
+				mip_count: texture.levels(),
+				base_layer: 0,
+				layer_count: texture.layers()
+			}
+		};
+
+		*current = Texture { inner: texture.inner.clone() };
+		*view = replacement;
+		self.revision.set(self.revision.get() + 1);
+
+		Ok(())
+	}
+
+	/** Replaces the uniform buffer bound by the buffer entry with the given
+	 * binding, keeping the range it binds, without having to create the group
+	 * all over again. The change takes effect the next time the group gets
+	 * bound, including by render passes it is already bound to.
+	 *
+	 * # Errors
+	 * This function fails if there's no buffer entry with the given binding,
+	 * if the new buffer can't be used for uniform data, or if the range it
+	 * binds doesn't fit in the new buffer. */
+	pub fn replace_buffer(&self, binding: &str, buffer: &Buffer)
+		-> Result<(), BindGroupError> {
+
+		let mut entries = self.entries.borrow_mut();
+		let entry = entries.iter_mut()
+			.find(|(name, _)| name == binding)
+			.map(|(_, entry)| entry)
+			.ok_or_else(|| BindGroupError::InvalidBinding {
+				what: format!("the group has no entry bound to \"{}\"", binding)
+			})?;
+
+		match entry {
+			OwnedUniformBind::Buffer { buffer: current, offset, size } => {
+				check_uniform_usage(binding, buffer)?;
+
+				/* The offset was checked to be aligned when the group was
+				 * created, so only the length of the new buffer matters. */
+				check_range(binding, buffer.len(), *offset, *size, 0, 1)?;
+				*current = buffer.share();
+			},
+			other => return Err(BindGroupError::InvalidBinding {
+				what: format!("\"{}\" is bound to a {}, not to a uniform buffer",
+					binding,
+					other.kind())
+			})
+		}
+		self.revision.set(self.revision.get() + 1);
+
+		Ok(())
+	}
+
+	/** A copy of this group with the texture bound by the entry with the given
+	 * binding replaced, as in [`replace_texture()`], leaving this group, and
+	 * every other handle to it, alone. This is how groups shared by many
+	 * users, such as the ones handed out by [group deduplication], should be
+	 * changed for a single one of them.
+	 *
+	 * # Errors
+	 * This function fails for the same reasons [`replace_texture()`] does.
+	 *
+	 * [`replace_texture()`]: Self::replace_texture
+	 * [group deduplication]: crate::Device::set_uniform_group_deduplication */
+	pub fn with_replaced_texture(&self, binding: &str, texture: &Texture)
+		-> Result<UniformGroup, BindGroupError> {
+
+		let copy = self.detached();
+		copy.replace_texture(binding, texture)?;
+
+		Ok(copy)
+	}
+
+	/** A copy of this group with the uniform buffer bound by the entry with
+	 * the given binding replaced, as in [`replace_buffer()`], leaving this
+	 * group, and every other handle to it, alone.
+	 *
+	 * # Errors
+	 * This function fails for the same reasons [`replace_buffer()`] does.
+	 *
+	 * [`replace_buffer()`]: Self::replace_buffer */
+	pub fn with_replaced_buffer(&self, binding: &str, buffer: &Buffer)
+		-> Result<UniformGroup, BindGroupError> {
+
+		let copy = self.detached();
+		copy.replace_buffer(binding, buffer)?;
+
+		Ok(copy)
+	}
+
+	/** A copy of this group with the texture bound by the entry with the given
+	 * binding filtered with the given filters, leaving this group, and every
+	 * other handle to it, alone.
+	 *
+	 * # Errors
+	 * This function fails if there's no texture entry with the given binding,
+	 * or if it samples through a sampler object, whose filters take the place
+	 * of its own. */
+	pub fn with_texture_filters(
+		&self,
+		binding: &str,
+		far: TextureFilter,
+		near: TextureFilter) -> Result<UniformGroup, BindGroupError> {
+
+		let copy = self.detached();
+		{
+			let mut entries = copy.entries.borrow_mut();
+			let entry = entries.iter_mut()
+				.find(|(name, _)| name == binding)
+				.map(|(_, entry)| entry)
+				.ok_or_else(|| BindGroupError::InvalidBinding {
+					what: format!("the group has no entry bound to \"{}\"", binding)
+				})?;
+
+			match entry {
+				OwnedUniformBind::Texture { sampler: Some(_), .. } =>
+					return Err(BindGroupError::InvalidBinding {
+						what: format!("\"{}\" is sampled through a sampler \
+							object, whose filters take the place of its own",
+							binding)
+					}),
+				OwnedUniformBind::Texture { far: current_far, near: current_near, .. } => {
+					*current_far = far;
+					*current_near = near;
+				},
+				other => return Err(BindGroupError::InvalidBinding {
+					what: format!("\"{}\" is bound to a {}, not to a texture",
+						binding,
+						other.kind())
+				})
+			}
+		}
+
+		Ok(copy)
+	}
+
+	/** A new group with the same entries as this one, which shares nothing
+	 * with it but the resources those entries bind. */
+	fn detached(&self) -> Self {
+		let entries = self.entries.borrow().iter()
+			.map(|(binding, entry)| (binding.clone(), entry.duplicate()))
+			.collect();
+
+		Self {
+			entries: Rc::new(RefCell::new(entries)),
+			revision: Rc::new(Cell::new(0))
+		}
+	}
+
+	/** Checks whether every entry in this group binds to something that is
+	 * active in the given pipeline. Binding a group to a pipeline normally
+	 * skips over inactive entries, leaving their data missing, which is most
+	 * often the sign of a typo or of a variable the shader compiler optimized
+	 * out. This turns that into an error, listing every such entry.
+	 *
+	 * Uniform buffers must bind to an active uniform block, and textures and
+	 * texel buffers to an active uniform, by the same name. Views of the
+	 * layers of array textures must bind to a `sampler2DArray`, as described
+	 * by [`TextureView::glsl()`], and get left out of binds otherwise.
+	 *
+	 * [`TextureView::glsl()`]: crate::TextureView::glsl */
+	pub fn validate(&self, pipeline: &RenderPipeline) -> Result<(), BindGroupError> {
+		let gl = &pipeline.inner.context;
+		let program = &pipeline.inner.program;
+
+		for (binding, entry) in &*self.entries.borrow() {
+			if let OwnedUniformBind::Texture { texture, sliced, .. } = entry {
+				if let Some(active) = program.uniforms.get(binding) {
+					check_view_sampler(
+						binding,
+						texture.inner.extent.get().target(),
+						*sliced,
+						active.kind)?;
+				}
+			}
+		}
+
+		let inactive = self.entries.borrow().iter()
+			.filter(|(binding, entry)| match entry {
+				OwnedUniformBind::Buffer { .. } => {
+					let reflected = program.reflection.uniform_blocks.iter()
+						.any(|block| &block.name == binding);
+					let active = reflected || unsafe {
+						gl.get_uniform_block_index(program.program, binding)
+							.is_some()
+					};
+
+					!active
+				},
+				_ => !program.uniforms.contains_key(binding)
+			})
+			.map(|(binding, entry)| format!("\"{}\" ({})", binding, entry.kind()))
+			.collect::<Vec<_>>();
+
+		if inactive.is_empty() {
+			Ok(())
+		} else {
+			Err(BindGroupError::InactiveBindings {
+				what: format!("{} are not active in the pipeline {}",
+					inactive.join(", "),
+					pipeline.name())
+			})
+		}
+	}
+
+	/** Checks whether the given dynamic offsets can be applied to the buffer
+	 * entries in this group, in order, given the alignment required of them. */
+	pub(crate) fn check_offsets(
+		&self,
+		offsets: &[u32],
+		alignment: u32) -> Result<(), BindGroupError> {
+
+		if offsets.len() != self.buffer_count() {
+			return Err(BindGroupError::InvalidOffset {
+				what: format!("{} dynamic offsets were given for a group with \
+					{} uniform buffers",
+					offsets.len(),
+					self.buffer_count())
+			})
+		}
+
+		let entries = self.entries.borrow();
+		let buffers = entries.iter()
+			.filter_map(|(binding, entry)| match entry {
+				OwnedUniformBind::Buffer { buffer, offset, size } =>
+					Some((binding, buffer, *offset, *size)),
+				_ => None
+			});
+		for ((binding, buffer, offset, size), dynamic) in buffers.zip(offsets) {
+			check_range(binding, buffer.len(), offset, size, *dynamic, alignment)?;
+		}
+
+		Ok(())
+	}
+
+	/** Records a bind of every texture in this group with the given tracker. */
+	pub(crate) fn record_usage(&self, usage: &mut UsageTracker<Weak<InnerTexture>>) {
+		for (_, entry) in &*self.entries.borrow() {
+			if let OwnedUniformBind::Texture { texture, .. } = entry {
+				usage.record(texture.inner.id, || Rc::downgrade(&texture.inner))
+			}
+		}
+	}
+
+	/** Bind all of the elements of this uniform bind group, shifting the range
+	 * of every buffer entry by the dynamic offset at the same position in the
+	 * given list, or by zero, if the list is empty.
 	 *
 	 * The correct shader program for this group must have already been bound
 	 * into the pipeline by this point. */
 	pub(crate) unsafe fn bind(
 		&self,
 		gl: &Context,
-		features: &Features,
-		program: &RenderProgram) {
+		information: &Information,
+		program: &RenderProgram,
+		offsets: &[u32]) {
 
-		let mut allocator = Default::default();
-		for (location, binder) in &*self.entries {
+		let mut allocator = Allocator::new(BlockRegistry::capacity(
+			information.limits.max_uniform_block_bindings));
+		let mut offsets = offsets.iter().copied();
+		for (location, binder) in &*self.entries.borrow() {
+			let dynamic = match binder {
+				OwnedUniformBind::Buffer { .. } => offsets.next().unwrap_or(0),
+				_ => 0
+			};
 			binder.bind(
 				gl,
-				features,
+				information,
 				location.as_str(),
 				program,
+				dynamic,
 				&mut allocator)
 		}
 	}
 }
 
+/** Checks whether a texture bound on the given target, as a view of a range of
+ * its layers or as a whole, may be sampled through the uniform of the given
+ * type bound to the given binding.
+ *
+ * Views over a range of layers can only be emulated when the shader samples
+ * the whole array and offsets the layer itself, and a plain two-dimensional
+ * sampler can't see into arrays. */
+pub(crate) fn check_view_sampler(
+	binding: &str,
+	target: u32,
+	sliced: bool,
+	sampler: u32) -> Result<(), BindGroupError> {
+
+	if !sliced
+		|| target != glow::TEXTURE_2D_ARRAY
+		|| sampler == glow::SAMPLER_2D_ARRAY {
+
+		return Ok(())
+	}
+	Err(BindGroupError::InvalidBinding {
+		what: format!("a view of the layers of an array texture is bound to \
+			\"{}\", which is not a sampler2DArray. sampling a range of layers \
+			through other sampler types is not supported",
+			binding)
+	})
+}
+
+/** Checks whether the given buffer may be bound to the given binding, which
+ * only buffers whose usage includes uniform data may be. */
+pub(crate) fn check_uniform_usage(binding: &str, buffer: &Buffer) -> Result<(), BindGroupError> {
+	if buffer.usage().contains(BufferUsage::UNIFORM) {
+		return Ok(())
+	}
+	Err(BindGroupError::InvalidBinding {
+		what: format!("buffer {} can't be bound to \"{}\", as its usage {:?} \
+			doesn't include uniform data",
+			buffer.inner.name(),
+			binding,
+			buffer.usage())
+	})
+}
+
+/** Checks whether the range of a uniform buffer starting at the given offset,
+ * shifted by the given dynamic offset, with the given size, is aligned and
+ * fits in a buffer of the given length. */
+pub(crate) fn check_range(
+	binding: &str,
+	len: u32,
+	offset: u32,
+	size: u32,
+	dynamic: u32,
+	alignment: u32) -> Result<(), BindGroupError> {
+
+	let range = offset.checked_add(dynamic)
+		.and_then(|start| start.checked_add(size).map(|end| (start, end)));
+	let start = match range {
+		Some((start, end)) if end <= len => start,
+		_ => return Err(BindGroupError::InvalidOffset {
+			what: format!("the range bound to \"{}\" starts at {} + {} and is \
+				{} bytes long, which does not fit in a buffer of {} bytes",
+				binding,
+				offset,
+				dynamic,
+				size,
+				len)
+		})
+	};
+	if alignment != 0 && start % alignment != 0 {
+		return Err(BindGroupError::InvalidOffset {
+			what: format!("the range bound to \"{}\" starts at {}, which is \
+				not a multiple of the required alignment of {}",
+				binding,
+				start,
+				alignment)
+		})
+	}
+
+	Ok(())
+}
+
 /** Structure that manages allocations in the uniform binding groups. */
 struct Allocator {
 	/** Simple texture bumper. */
@@ -74,11 +501,12 @@ struct Allocator {
 	ubo: u32,
 }
 impl Allocator {
-	/** Creates a new, empty allocator. */
-	pub fn new() -> Self {
+	/** Creates a new, empty allocator, which hands out UBO binding slots
+	 * starting at the given one. The slots below it belong to pinned blocks. */
+	pub fn new(ubo: u32) -> Self {
 		Self {
 			texture: 0,
-			ubo: 0
+			ubo
 		}
 	}
 
@@ -100,17 +528,16 @@ impl Allocator {
 		self.ubo - 1
 	}
 }
-impl Default for Allocator {
-	fn default() -> Self {
-		Self::new()
-	}
-}
-
 /** Owned internal version of the uniform bind specification structure. */
 pub(crate) enum OwnedUniformBind {
 	Buffer {
 		/** Buffer object to be bound to this group. */
-		buffer: UniformBuffer,
+		buffer: Buffer,
+		/** Offset of the bound range from the start of the buffer, in bytes,
+		 * not counting dynamic offsets. */
+		offset: u32,
+		/** Size of the bound range, in bytes. */
+		size: u32,
 	},
 	Texture {
 		/** Texture object to be bound to this group. */
@@ -119,57 +546,205 @@ pub(crate) enum OwnedUniformBind {
 		far: TextureFilter,
 		/** How this texture will be filtered when it needs to be upscaled. */
 		near: TextureFilter,
+		/** How coordinates outside of this texture will be handled. */
+		address_mode: AddressMode,
 		/** The level of anisotropic filtering to be applied to the texture. */
-		anisotropy_clamp: Option<NonZeroU8>
+		anisotropy_clamp: Option<NonZeroU8>,
+		/** Sampler object used instead of the parameters above, if any. */
+		sampler: Option<Sampler>,
+		/** The range of the texture that will be sampled. */
+		view: TextureViewDescriptor,
+		/** Whether this binding came from a texture view, in which case the
+		 * helper layer uniform will be set along with the texture. */
+		sliced: bool,
+	},
+	TexelBuffer {
+		/** Texel buffer to be bound to this group. */
+		buffer: TexelBuffer,
 	}
 }
 impl OwnedUniformBind {
+	/** Name of the kind of this binding, for diagnostics. */
+	fn kind(&self) -> &'static str {
+		match self {
+			OwnedUniformBind::Buffer { .. } => "uniform buffer",
+			OwnedUniformBind::Texture { .. } => "texture",
+			OwnedUniformBind::TexelBuffer { .. } => "texel buffer",
+		}
+	}
+
+	/** Another binding of the same resources, with the same parameters. */
+	fn duplicate(&self) -> Self {
+		match self {
+			OwnedUniformBind::Buffer { buffer, offset, size } =>
+				OwnedUniformBind::Buffer {
+					buffer: buffer.share(),
+					offset: *offset,
+					size: *size
+				},
+			OwnedUniformBind::Texture {
+				texture,
+				far,
+				near,
+				address_mode,
+				anisotropy_clamp,
+				sampler,
+				view,
+				sliced } =>
+
+				OwnedUniformBind::Texture {
+					texture: Texture { inner: texture.inner.clone() },
+					far: *far,
+					near: *near,
+					address_mode: *address_mode,
+					anisotropy_clamp: *anisotropy_clamp,
+					sampler: sampler.clone(),
+					view: *view,
+					sliced: *sliced
+				},
+			OwnedUniformBind::TexelBuffer { buffer } =>
+				OwnedUniformBind::TexelBuffer {
+					buffer: TexelBuffer { inner: buffer.inner.clone() }
+				}
+		}
+	}
+
+	/** Kind of resource this binding binds, for the interface registry. */
+	pub(crate) fn resource(&self) -> ResourceKind {
+		match self {
+			OwnedUniformBind::Buffer { size, .. } =>
+				ResourceKind::UniformBuffer { size: *size },
+			OwnedUniformBind::Texture { .. } => ResourceKind::Texture,
+			OwnedUniformBind::TexelBuffer { .. } => ResourceKind::TexelBuffer,
+		}
+	}
+
+	/** Assigns a binding point to a uniform block that could not be pinned
+	 * when its program was linked. Its index and the binding point it was last
+	 * assigned are cached in the program, so the context only gets asked for
+	 * the index once, and only gets told about the binding point when it
+	 * changes from what it was the last time around. */
+	unsafe fn assign_block(
+		gl: &Context,
+		information: &Information,
+		target: &str,
+		program: &RenderProgram,
+		allocator: &mut Allocator) -> Option<u32> {
+
+		let mut blocks = program.blocks.borrow_mut();
+		let block = blocks.entry(target.to_owned())
+			.or_insert_with(|| BlockBinding {
+				index: gl.get_uniform_block_index(program.program, target),
+				binding: None
+			});
+
+		let index = match block.index {
+			Some(index) => index,
+			None => {
+				rate_limited!(trace!(target: "gavle::bind",
+					"tried to bind to inactive uniform block at \
+					\"{}\". data for this uniform will be missing",
+					target));
+				return None
+			}
+		};
+
+		let binding = allocator.next_ubo_binding();
+		if binding >= information.limits.max_uniform_block_bindings {
+			rate_limited!(warn!(target: "gavle::bind",
+				"ran out of binding points for the uniform block at \
+				\"{}\". data for this uniform will be missing",
+				target));
+			return None
+		}
+
+		if block.binding != Some(binding) {
+			gl.uniform_block_binding(program.program, index, binding);
+			block.binding = Some(binding);
+		}
+
+		Some(binding)
+	}
+
 	unsafe fn bind(
 		&self,
 		gl: &Context,
-		features: &Features,
+		information: &Information,
 		target: &str,
 		program: &RenderProgram,
+		dynamic_offset: u32,
 		allocator: &mut Allocator) {
 
+		let features = &information.features;
 		match self {
-			OwnedUniformBind::Buffer { buffer } => {
-				let index = match gl.get_uniform_block_index(program.program, target) {
-					Some(location) => location,
-					None => {
-						trace!("tried to bind to inactive uniform block at \
-							\"{}\". data for this uniform will be missing",
-							target);
-						return
+			OwnedUniformBind::Buffer { buffer, offset, size } => {
+				let binding = match program.pinned.get(target) {
+					Some(binding) => *binding,
+					None => match Self::assign_block(
+						gl,
+						information,
+						target,
+						program,
+						allocator) {
+						Some(binding) => binding,
+						None => return
 					}
 				};
 
-				let binding = allocator.next_ubo_binding();
-				gl.uniform_block_binding(
-					program.program,
-					index,
-					binding);
-
 				gl.bind_buffer_range(
 					glow::UNIFORM_BUFFER,
 					binding,
 					Some(buffer.inner.buffer),
-					0,
-					i32::try_from(buffer.len()).expect("buffer is \
-						too big for shader use"));
+					i32::try_from(offset + dynamic_offset).expect("buffer \
+						offset is too big for shader use"),
+					i32::try_from(*size).expect("buffer is too big for \
+						shader use"));
+			},
+			OwnedUniformBind::TexelBuffer { buffer } => {
+				if !program.uniforms.contains_key(target) {
+					rate_limited!(trace!(target: "gavle::bind",
+						"tried to bind to the inactive uniform \"{}\". \
+						data for this uniform will be missing", target));
+					return
+				}
+
+				let location = match gl.get_uniform_location(program.program, target) {
+					Some(location) => location,
+					None => panic!("expected a uniform at \"{}\", found none",
+						target)
+				};
+
+				/* The filtering state of the texture was set up when the
+				 * buffer was created, and never changes after that. */
+				let slot = allocator.next_texture();
+				gl.active_texture(glow::TEXTURE0 + slot);
+				gl.bind_texture(
+					buffer.inner.storage.target(),
+					Some(buffer.inner.texture));
+				gl.uniform_1_i32(
+					Some(&location),
+					i32::try_from(slot).unwrap());
 			},
 			OwnedUniformBind::Texture {
 				texture,
 				far,
 				near,
-				anisotropy_clamp } => {
+				address_mode,
+				anisotropy_clamp,
+				sampler,
+				view,
+				sliced } => {
 
 				/* Check whether this target is active in the program. */
-				if let None = program.uniforms.get(target) {
-					trace!("tried to bind to the inactive uniform \"{}\". data \
-						for this uniform will be missing", target);
-					return
-				}
+				let active = match program.uniforms.get(target) {
+					Some(active) => active,
+					None => {
+						rate_limited!(trace!(target: "gavle::bind",
+							"tried to bind to the inactive uniform \"{}\". \
+							data for this uniform will be missing", target));
+						return
+					}
+				};
 
 				let location = match gl.get_uniform_location(program.program, target) {
 					Some(location) => location,
@@ -177,9 +752,67 @@ impl OwnedUniformBind {
 						target)
 				};
 
+				/* Groups with views that can't be sampled through this uniform
+				 * are turned down by `UniformGroup::validate()`, so, here, the
+				 * view just gets left out. */
+				let kind = texture.inner.extent.get().target();
+				if let Err(what) = check_view_sampler(target, kind, *sliced, active.kind) {
+					rate_limited!(warn!(target: "gavle::bind",
+						"{}. data for this uniform will be missing", what));
+					return
+				}
+
+				/* Views span every face of a cube map. */
+				let layers = match texture.inner.extent.get() {
+					TextureExtent::Cube { .. } => 0..6,
+					_ => view.base_layer..view.base_layer + view.layer_count
+				};
+				texture.inner.warn_uninitialized(
+					view.base_mip..view.base_mip + view.mip_count,
+					layers,
+					"sampled");
+
 				let slot = allocator.next_texture();
 				gl.active_texture(glow::TEXTURE0 + slot);
-				gl.bind_texture(glow::TEXTURE_2D, Some(texture.inner.texture));
+				gl.bind_texture(kind, Some(texture.inner.texture));
+
+				/* Restrict sampling to the mip levels in the view. This is
+				 * state of the texture itself, so it has to be set on every
+				 * bind, whether or not this is a view. */
+				gl.tex_parameter_i32(
+					kind,
+					glow::TEXTURE_BASE_LEVEL,
+					i32::try_from(view.base_mip).unwrap());
+				gl.tex_parameter_i32(
+					kind,
+					glow::TEXTURE_MAX_LEVEL,
+					i32::try_from(view.base_mip + view.mip_count - 1).unwrap());
+				gl.uniform_1_i32(
+					Some(&location),
+					i32::try_from(slot).unwrap());
+
+				/* Pass the first layer of the view on to the shader. */
+				if *sliced {
+					let layer = TextureView::layer_uniform(target);
+					match gl.get_uniform_location(program.program, &layer) {
+						Some(location) => gl.uniform_1_i32(
+							Some(&location),
+							i32::try_from(view.base_layer).unwrap()),
+						None => rate_limited!(trace!(target: "gavle::bind",
+							"the layer uniform \"{}\" for the view bound to \
+							\"{}\" is inactive", layer, target))
+					}
+				}
+
+				/* Sampler objects override the parameters of the texture, so
+				 * one left bound to this unit has to be cleared out for the
+				 * parameters set below to have any effect. */
+				if let Some(sampler) = sampler {
+					sampler.bind(gl, slot);
+					return
+				} else if information.capabilities.sampler_objects {
+					gl.bind_sampler(slot, None);
+				}
 
 				/* Enable or disable anisotropic filtering for this texture. */
 				match anisotropy_clamp {
@@ -191,14 +824,14 @@ impl OwnedUniformBind {
 					Some(clamp) if features.sampler_anisotropy => {
 						/* Enable anisotropic filtering. */
 						gl.tex_parameter_f32(
-							glow::TEXTURE_2D,
+							kind,
 							glow::TEXTURE_MAX_ANISOTROPY_EXT,
 							f32::from(clamp.get()))
 					},
 					None if features.sampler_anisotropy => {
 						/* Disable anisotropic filtering. */
 						gl.tex_parameter_f32(
-							glow::TEXTURE_2D,
+							kind,
 							glow::TEXTURE_MAX_ANISOTROPY_EXT,
 							1.0)
 					}
@@ -206,16 +839,20 @@ impl OwnedUniformBind {
 				}
 
 				gl.tex_parameter_i32(
-					glow::TEXTURE_2D,
+					kind,
 					glow::TEXTURE_MAG_FILTER,
 					i32::try_from(near.as_opengl(false)).unwrap());
+				/* Only pick a mipmapped filter if there are mips to pick from. */
+				let mipmapped = view.mip_count > 1;
 				gl.tex_parameter_i32(
-					glow::TEXTURE_2D,
+					kind,
 					glow::TEXTURE_MIN_FILTER,
-					i32::try_from(far.as_opengl(true)).unwrap());
-				gl.uniform_1_i32(
-					Some(&location),
-					i32::try_from(slot).unwrap());
+					i32::try_from(far.as_opengl(mipmapped)).unwrap());
+
+				let wrap = i32::try_from(address_mode.as_opengl()).unwrap();
+				gl.tex_parameter_i32(kind, glow::TEXTURE_WRAP_S, wrap);
+				gl.tex_parameter_i32(kind, glow::TEXTURE_WRAP_T, wrap);
+				gl.tex_parameter_i32(kind, glow::TEXTURE_WRAP_R, wrap);
 			}
 		}
 	}
@@ -238,8 +875,22 @@ pub struct UniformGroupEntry<'a> {
 #[derive(Debug, Copy, Clone)]
 pub enum UniformBind<'a> {
 	Buffer {
-		/** Buffer object to be bound to this group. */
-		buffer: &'a UniformBuffer,
+		/** Buffer object to be bound to this group, whose usage must include
+		 * [`BufferUsage::UNIFORM`]. */
+		buffer: &'a Buffer,
+		/** Offset of the bound range from the start of the buffer, in bytes.
+		 * This must be a multiple of the [`uniform_buffer_offset_alignment`]
+		 * limit of the context.
+		 *
+		 * [`uniform_buffer_offset_alignment`]: crate::Limits::uniform_buffer_offset_alignment */
+		offset: u32,
+		/** Size of the bound range, in bytes, or `None` for the rest of the
+		 * buffer past the offset. This is the size of the range every dynamic
+		 * offset gets applied to, and it can't be larger than the
+		 * [`max_uniform_block_size`] limit of the context.
+		 *
+		 * [`max_uniform_block_size`]: crate::Limits::max_uniform_block_size */
+		size: Option<NonZeroU32>,
 	},
 	Texture {
 		/** Texture object to be bound to this group. */
@@ -248,16 +899,114 @@ pub enum UniformBind<'a> {
 		far: TextureFilter,
 		/** How this texture will be filtered when it needs to be upscaled. */
 		near: TextureFilter,
+		/** How coordinates outside of this texture will be handled, on all of
+		 * its axes. Use a [`Sampler`] to set the axes apart.
+		 *
+		 * [`Sampler`]: crate::Sampler */
+		address_mode: AddressMode,
 		/** The level of anisotropic filtering to be applied to the texture.
 		 *
-		 * # Panic
+		 * # Errors
 		 * This is only available when the [`sampler_anisotropy`] feature is
 		 * present in the context. If this value is enabled for a context in
-		 * which that feature is not present and this uniform is bound, the
-		 * binding functions will panic.
+		 * which that feature is not present, or if it is higher than the
+		 * maximum allowed by the context, creating the bind group will fail.
 		 *
 		 * [`sampler_anisotropy`]: crate::Features::sampler_anisotropy
 		 */
-		anisotropy_clamp: Option<NonZeroU8>
+		anisotropy_clamp: Option<NonZeroU8>,
+		/** Sampler to sample the texture with. When one is given, the
+		 * filtering parameters above are ignored in favor of those of the
+		 * sampler, which lets the same texture be sampled in different ways
+		 * in the same frame. Otherwise, they are set on the texture itself
+		 * when the group gets bound. */
+		sampler: Option<&'a Sampler>,
+	},
+	/** A view over part of a texture, as created by [`Texture::view()`].
+	 *
+	 * See the documentation of that function for how views of array layers
+	 * must be sampled in shaders.
+	 *
+	 * [`Texture::view()`]: crate::Texture::view */
+	TextureView {
+		/** Texture view to be bound to this group. */
+		view: &'a TextureView,
+		/** How this texture will be filtered when it needs to be downscaled. */
+		far: TextureFilter,
+		/** How this texture will be filtered when it needs to be upscaled. */
+		near: TextureFilter,
+		/** How coordinates outside of this texture will be handled, on all of
+		 * its axes. */
+		address_mode: AddressMode,
+		/** The level of anisotropic filtering to be applied to the texture.
+		 *
+		 * This follows the same rules as the one in [`UniformBind::Texture`].
+		 *
+		 * [`UniformBind::Texture`]: UniformBind::Texture */
+		anisotropy_clamp: Option<NonZeroU8>,
+		/** Sampler to sample the texture with, as in [`UniformBind::Texture`].
+		 *
+		 * [`UniformBind::Texture`]: UniformBind::Texture */
+		sampler: Option<&'a Sampler>,
+	},
+	/** A texel buffer, which shaders read through the functions declared in
+	 * [`TexelBuffer::glsl()`]. This takes up a texture slot.
+	 *
+	 * [`TexelBuffer::glsl()`]: crate::TexelBuffer::glsl */
+	TexelBuffer {
+		/** Texel buffer to be bound to this group. */
+		buffer: &'a TexelBuffer,
+	}
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum BindGroupError {
+	#[error("the bind group exceeds the limits of the context: {what}")]
+	LimitExceeded {
+		what: String
+	},
+	#[error("the bind group uses a feature the context doesn't support: {what}")]
+	UnsupportedFeature {
+		what: String
+	},
+	#[error("the bind group has an invalid buffer range: {what}")]
+	InvalidOffset {
+		what: String
+	},
+	#[error("the bind group has no matching binding: {what}")]
+	InvalidBinding {
+		what: String
+	},
+	#[error("the bind group has bindings the pipeline doesn't use: {what}")]
+	InactiveBindings {
+		what: String
+	},
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn buffer_ranges_must_fit_and_be_aligned() {
+		assert!(check_range("params", 1024, 0, 1024, 0, 256).is_ok());
+		assert!(check_range("params", 1024, 256, 256, 512, 256).is_ok());
+
+		assert!(check_range("params", 1024, 256, 256, 768, 256).is_err());
+		assert!(check_range("params", 1024, 0, 256, 128, 256).is_err());
+		assert!(check_range("params", 1024, u32::MAX, 1, 1, 256).is_err());
+	}
+
+	#[test]
+	fn layer_views_need_array_samplers() {
+		let array = glow::TEXTURE_2D_ARRAY;
+		assert!(check_view_sampler("tex", array, true, glow::SAMPLER_2D_ARRAY).is_ok());
+		assert!(matches!(
+			check_view_sampler("tex", array, true, glow::SAMPLER_2D),
+			Err(BindGroupError::InvalidBinding { .. })));
+
+		/* Whole textures and views of other kinds of texture are left alone. */
+		assert!(check_view_sampler("tex", array, false, glow::SAMPLER_2D).is_ok());
+		assert!(check_view_sampler("tex", glow::TEXTURE_2D, true, glow::SAMPLER_2D).is_ok());
 	}
 }
@@ -0,0 +1,88 @@
+use std::convert::TryFrom;
+use std::collections::HashMap;
+
+/** Registry of the binding points uniform blocks get pinned to, by the name of
+ * the block, shared by all of the programs created by a device.
+ *
+ * Pinned blocks get their binding point assigned once, when their program is
+ * linked, so binding a buffer to them later on is all it takes to bind a group.
+ * Blocks sharing a name share their binding point across programs, which is
+ * fine, since the buffer bound to it gets replaced on every bind anyway.
+ *
+ * Only the lower half of the binding points are ever handed out here. Blocks
+ * that could not be pinned, because the registry filled up, get binding points
+ * from the upper half assigned to them whenever they get bound instead. */
+#[derive(Debug)]
+pub(crate) struct BlockRegistry {
+	/** Binding points handed out so far, by the name of their block. */
+	bindings: HashMap<String, u32>,
+	/** Number of binding points that may be handed out. */
+	capacity: u32,
+}
+impl BlockRegistry {
+	/** Creates a new, empty registry for a context with the given number of
+	 * uniform buffer binding points. */
+	pub(crate) fn new(max_bindings: u32) -> Self {
+		Self {
+			bindings: HashMap::new(),
+			capacity: Self::capacity(max_bindings)
+		}
+	}
+
+	/** Number of binding points that may be pinned in a context with the given
+	 * number of uniform buffer binding points. Binding points at or past this
+	 * one are left for the blocks that could not be pinned. */
+	pub(crate) fn capacity(max_bindings: u32) -> u32 {
+		max_bindings / 2
+	}
+
+	/** The binding point the block with the given name is pinned to, pinning
+	 * it to the next free one if it hasn't been pinned yet. Returns `None` once
+	 * the registry has run out of binding points for new names. */
+	pub(crate) fn pin(&mut self, name: &str) -> Option<u32> {
+		if let Some(binding) = self.bindings.get(name) {
+			return Some(*binding)
+		}
+
+		let binding = u32::try_from(self.bindings.len()).ok()?;
+		if binding >= self.capacity {
+			rate_limited!(debug!(target: "gavle::bind",
+				"ran out of binding points to pin the uniform block \"{}\" to. \
+				it will get one assigned every time it's bound instead",
+				name));
+			return None
+		}
+
+		self.bindings.insert(name.to_owned(), binding);
+		Some(binding)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn names_keep_their_binding_points() {
+		let mut registry = BlockRegistry::new(8);
+		assert_eq!(registry.pin("Camera"), Some(0));
+		assert_eq!(registry.pin("Lights"), Some(1));
+		assert_eq!(registry.pin("Camera"), Some(0));
+		assert_eq!(registry.pin("Skin"), Some(2));
+	}
+
+	#[test]
+	fn full_registries_leave_new_names_unpinned() {
+		let mut registry = BlockRegistry::new(5);
+		assert_eq!(BlockRegistry::capacity(5), 2);
+		assert_eq!(registry.pin("Camera"), Some(0));
+		assert_eq!(registry.pin("Lights"), Some(1));
+		assert_eq!(registry.pin("Skin"), None);
+		assert_eq!(registry.pin("Skin"), None);
+
+		/* Names pinned before it filled up are still there. */
+		assert_eq!(registry.pin("Lights"), Some(1));
+
+		assert_eq!(BlockRegistry::new(1).pin("Camera"), None);
+	}
+}
@@ -1,10 +1,15 @@
 use glow::{HasContext, Context};
+use crate::lock::{PipelineLock, LockOperation};
 use std::rc::Rc;
 use std::ops::{RangeBounds, Bound, Deref, DerefMut};
 use std::cell::RefCell;
 use crate::access::{AccessLock, UnitAccessLock};
 use std::convert::TryFrom;
-use crate::Information;
+use crate::{Information, Profile};
+use crate::sanitize::{Sanitizer, BufferContents};
+use crate::debug::Debugger;
+use crate::fence::GpuFence;
+use std::time::Duration;
 
 /** States the mapping of the buffer can take on. */
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
@@ -26,16 +31,68 @@ pub struct InnerBuffer {
 	/** Shared information on the context. */
 	pub(crate) information: Rc<Information>,
 	/** Shared OpenGL pipeline lock. */
-	pub(crate) pipeline: Rc<RefCell<()>>,
+	pub(crate) pipeline: Rc<PipelineLock>,
+	/** Shared write sanitizer state. */
+	pub(crate) sanitizer: Rc<Sanitizer>,
+	/** Shared error checking and labeling state. */
+	pub(crate) debug: Rc<Debugger>,
 	/** Name of the buffer, used to identify it to the implementation. */
 	pub(crate) buffer: <Context as HasContext>::Buffer,
+	/** Label used to refer to this buffer in diagnostics. */
+	pub(crate) label: RefCell<Option<String>>,
 	/** Access control structure. */
 	pub(crate) access: UnitAccessLock,
 	/** State of the buffer mapping. */
 	pub(crate) map: RefCell<MapState>,
+	/** Kind of data held by this buffer. */
+	pub(crate) contents: BufferContents,
+	/** Ways this buffer may be used in. */
+	pub(crate) usage: BufferUsage,
+	/** Usage profile the buffer was created with, which its storage gets
+	 * created with again when it gets orphaned. */
+	pub(crate) profile: BufferProfile,
 	/** Length of the buffer, in bytes. */
 	pub(crate) len: u32,
 }
+impl InnerBuffer {
+	/** Name of this buffer in diagnostics, which is either its label or the
+	 * name of the buffer object, if it has no label. */
+	pub(crate) fn name(&self) -> String {
+		self.lazy_name().to_string()
+	}
+
+	/** Name of this buffer in diagnostics, which only gets put together once
+	 * it actually gets formatted, for the paths that have to hand it over on
+	 * every write in case something has to be reported. */
+	pub(crate) fn lazy_name(&self) -> BufferName<'_> {
+		BufferName(self)
+	}
+
+	/** Checks for errors raised by the given operation on this buffer. */
+	pub(crate) unsafe fn check(&self, operation: &str) {
+		self.debug.check(
+			&self.context,
+			operation,
+			|| format!("buffer {}", self.name()))
+	}
+}
+
+/** Name of a buffer in diagnostics, as returned by [`InnerBuffer::lazy_name()`]. */
+pub(crate) struct BufferName<'a>(&'a InnerBuffer);
+impl std::fmt::Display for BufferName<'_> {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match self.0.label.borrow().as_deref() {
+			Some(label) => write!(f, "\"{}\"", label),
+			None => write!(f, "#{:?}", self.0.buffer)
+		}
+	}
+}
+impl std::fmt::Debug for BufferName<'_> {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		std::fmt::Display::fmt(self, f)
+	}
+}
+
 impl Drop for InnerBuffer {
 	fn drop(&mut self) {
 		unsafe {
@@ -66,102 +123,317 @@ impl AccessLock for InnerBuffer {
 	}
 }
 
-/** This macro instances buffers from a common buffer code given the buffer
- * type they belong to in OpenGL. */
+bitflags::bitflags! {
+	/** Ways a buffer may be used in. A single buffer may be used in more than
+	 * one way, such as both for vertex and for index data, and binding it in a
+	 * way its usage doesn't include is a bug.
+	 *
+	 * WebGL doesn't let index data share a buffer with anything else, so
+	 * [`INDEX`] may only be used by itself there.
+	 *
+	 * [`INDEX`]: Self::INDEX */
+	#[repr(transparent)]
+	pub struct BufferUsage: u32 {
+		/** The buffer may be used for vertex and instance data. */
+		const VERTEX = 1;
+		/** The buffer may be used for index data. */
+		const INDEX = 2;
+		/** The buffer may be used for uniform block data. */
+		const UNIFORM = 4;
+		/** The buffer may have the outputs of the vertex stage captured into
+		 * it, by a [`TransformFeedbackPass`]. Legacy contexts don't support
+		 * transform feedback, so buffers can't have this usage there.
+		 *
+		 * [`TransformFeedbackPass`]: crate::TransformFeedbackPass */
+		const TRANSFORM_FEEDBACK = 8;
+		/** The buffer may have texels copied from it into textures, as done
+		 * by [`UploadBuffer`]. This needs the [`pixel_unpack_buffers`]
+		 * capability.
+		 *
+		 * [`UploadBuffer`]: crate::UploadBuffer
+		 * [`pixel_unpack_buffers`]: crate::Capabilities::pixel_unpack_buffers */
+		const PIXEL_UNPACK = 16;
+	}
+}
+impl BufferUsage {
+	/** Binding slot buffers with this usage get bound to in order to be
+	 * created, written to and mapped. Buffers with index data have to go to
+	 * the index slot, since WebGL keeps them from ever being bound anywhere
+	 * else. */
+	pub(crate) fn target(self) -> u32 {
+		if self.contains(Self::INDEX) {
+			glow::ELEMENT_ARRAY_BUFFER
+		} else if self.contains(Self::VERTEX) {
+			glow::ARRAY_BUFFER
+		} else if self.contains(Self::UNIFORM) {
+			glow::UNIFORM_BUFFER
+		} else if self.contains(Self::TRANSFORM_FEEDBACK) {
+			glow::TRANSFORM_FEEDBACK_BUFFER
+		} else {
+			glow::PIXEL_UNPACK_BUFFER
+		}
+	}
+}
+
+/** Checks whether buffers with the given usage may be created in a context
+ * with the given profile. */
+pub(crate) fn check_usage(usage: BufferUsage, profile: Profile) -> Result<(), BufferError> {
+	if usage.is_empty() {
+		return Err(BufferError::InvalidUsage {
+			what: "buffers must have at least one usage".to_owned()
+		})
+	}
+	let web = matches!(profile, Profile::Web | Profile::WebLegacy);
+	if web
+		&& usage.contains(BufferUsage::INDEX)
+		&& usage != BufferUsage::INDEX {
+
+		return Err(BufferError::UnsupportedFeature {
+			what: format!("webgl can't use buffers with index data in any \
+				other way, as the usage {:?} would", usage)
+		})
+	}
+	if profile.is_legacy() && usage.contains(BufferUsage::TRANSFORM_FEEDBACK) {
+		return Err(BufferError::UnsupportedFeature {
+			what: "legacy contexts have no support for transform feedback"
+				.to_owned()
+		})
+	}
+	if profile.is_legacy() && usage.contains(BufferUsage::PIXEL_UNPACK) {
+		return Err(BufferError::UnsupportedFeature {
+			what: "legacy contexts have no support for pixel unpack buffers"
+				.to_owned()
+		})
+	}
+
+	Ok(())
+}
+
+/** A buffer that may be used in any of the ways allowed by its [usage].
+ *
+ * Buffers of the typed kinds, such as [`VertexBuffer`], dereference to this
+ * structure, so they may be passed along wherever a buffer is expected.
+ *
+ * [usage]: BufferUsage */
+#[derive(Debug)]
+pub struct Buffer {
+	/** Inner access-controlled version of this structure. */
+	pub(crate) inner: Rc<InnerBuffer>,
+}
+impl Buffer {
+	/** Length of this buffer, in bytes. */
+	pub fn len(&self) -> u32 {
+		self.inner.len
+	}
+
+	/** The ways this buffer may be used in. */
+	pub fn usage(&self) -> BufferUsage {
+		self.inner.usage
+	}
+
+	/** Another handle to this same buffer. */
+	pub(crate) fn share(&self) -> Self {
+		Self { inner: self.inner.clone() }
+	}
+
+	/** Panics if this buffer can't be bound for the given usage, with the
+	 * given operation in the message. */
+	pub(crate) fn assert_usage(&self, usage: BufferUsage, operation: &str) {
+		if !self.inner.usage.contains(usage) {
+			panic!("tried to {} buffer {}, whose usage {:?} doesn't include \
+				{:?}",
+				operation,
+				self.inner.name(),
+				self.inner.usage,
+				usage)
+		}
+	}
+
+	/** The label used to refer to this buffer in diagnostics, if any. */
+	pub fn label(&self) -> Option<String> {
+		self.inner.label.borrow().clone()
+	}
+
+	/** Changes the label used to refer to this buffer in diagnostics.
+	 * The label is also attached to the buffer object, for debuggers to
+	 * pick up, when the [`debug_labels`] capability is present.
+	 *
+	 * [`debug_labels`]: crate::Capabilities::debug_labels */
+	pub fn set_label(&self, label: &str) {
+		unsafe {
+			self.inner.debug.label(
+				&self.inner.context,
+				glow::BUFFER,
+				self.inner.buffer,
+				label);
+		}
+		*self.inner.label.borrow_mut() = Some(label.to_owned())
+	}
+
+	/** Get the raw handle of this buffer.
+	 *
+	 * # Safety
+	 * The handle may only be used from within
+	 * [`Device::with_external_gl`], and the buffer must not be deleted
+	 * or reallocated through it, as this structure keeps track of its
+	 * size.
+	 *
+	 * [`Device::with_external_gl`]: crate::Device::with_external_gl */
+	pub unsafe fn as_raw_handle(&self) -> <Context as HasContext>::Buffer {
+		self.inner.buffer
+	}
+
+	/** Writes the given data into this buffer, starting at the given
+	 * byte offset, without mapping it.
+	 *
+	 * This is the cheapest way to update a part of a buffer, as the
+	 * data goes straight to the implementation, and no part of the
+	 * buffer has to be mirrored in host memory.
+	 *
+	 * # Panic
+	 * This function will panic if the buffer can't be locked mutably
+	 * or if the pipeline can't be locked mutably. */
+	pub fn write(&self, offset: u32, data: &[u8]) -> Result<(), BufferError> {
+		write_bounds(offset, data.len(), self.len())?;
+
+		let _buffer_lock = self.inner.acquire_write_guarded();
+		let _pipeline_lock = self.inner.pipeline.acquire(LockOperation::BufferWrite);
+		if data.is_empty() { return Ok(()) }
+
+		/* Catch non-finite values before they make it to the device. */
+		self.inner.sanitizer.check(
+			&self.inner.lazy_name(),
+			self.inner.contents,
+			offset,
+			data);
+
+		let target = self.inner.usage.target();
+		let gl = self.inner.context.as_ref();
+		unsafe {
+			gl.bind_buffer(target, Some(self.inner.buffer));
+			gl.buffer_sub_data_u8_slice(
+				target,
+				i32::try_from(offset).unwrap(),
+				data);
+			gl.bind_buffer(target, None);
+			self.inner.check("writing to");
+		}
+
+		Ok(())
+	}
+
+	/** Get a range of this buffer. */
+	pub fn slice<R>(&self, range: R) -> BufferSlice
+		where R: RangeBounds<u32> {
+
+		let offset = match range.start_bound() {
+			Bound::Unbounded => 0,
+			Bound::Excluded(val) => val.checked_add(1)
+				.expect("lower range bound value would overflow u32 range"),
+			Bound::Included(val) => *val
+		};
+
+		let length = match range.end_bound() {
+			Bound::Unbounded => self.len() - offset,
+			Bound::Excluded(val) => {
+				let val = *val;
+
+				if val > self.len() {
+					panic!("upper range bound {} is greater than the \
+						length {} of the buffer",
+						val, self.len())
+				}
+				if offset > val {
+					panic!("lower range bound {} is greater than upper \
+						range bound {}",
+						offset, val)
+				}
+
+				val.checked_sub(offset).unwrap()
+			},
+			Bound::Included(val) => {
+				let val = *val;
+
+				if val >= self.len() {
+					panic!("upper range bound ={} is greater than the \
+						length {} of the buffer",
+						val, self.len())
+				}
+				if offset > val {
+					panic!("lower range bound {} is greater than upper \
+						range bound {}",
+						offset, val)
+				}
+
+				val.checked_sub(offset).unwrap()
+					.checked_add(1)
+					.expect("upper range bound overflows u32 range")
+			},
+		};
+
+		BufferSlice {
+			buffer: &self.inner,
+			target: self.inner.usage.target(),
+			offset,
+			length
+		}
+	}
+}
+impl AccessLock for Buffer {
+	fn acquire_write(&self) {
+		self.inner.acquire_write()
+	}
+	fn release_write(&self) {
+		self.inner.release_write()
+	}
+	fn acquire_read(&self) {
+		self.inner.acquire_read()
+	}
+	fn release_read(&self) {
+		self.inner.release_read()
+	}
+}
+
+/** This macro instances the typed buffers, which are buffers known to have a
+ * given usage, kept around so that code written before buffers had usages
+ * keeps on working. */
 macro_rules! instance_buffers {
 	($(
 		$(#[$outer:meta])*
-		pub struct $name:ident: $target:expr;
+		pub struct $name:ident: $usage:ident;
 	)+) => {$(
 		$(#[$outer])*
 		pub struct $name {
-			/** Inner access-controlled version of this structure. */
-			pub(crate) inner: Rc<InnerBuffer>,
+			/** The buffer, whose usage includes that of this type. */
+			pub(crate) buffer: Buffer,
 		}
 		impl $name {
-			/** Enum value of the binding slot that should be used for this type
-			 * of buffer. */
-			pub(crate) const GL_BIND: u32 = $target;
-
-			/** Length of this buffer, in bytes. */
-			pub fn len(&self) -> u32 {
-				self.inner.len
-			}
-
-			/** Get the raw handle of this buffer. */
-			pub unsafe fn as_raw_handle(&self) -> <Context as HasContext>::Buffer {
-				self.inner.buffer
+			/** Usage every buffer of this type has. */
+			pub const USAGE: BufferUsage = BufferUsage::$usage;
+		}
+		impl Deref for $name {
+			type Target = Buffer;
+			fn deref(&self) -> &Buffer {
+				&self.buffer
 			}
-
-			/** Get a range of this buffer. */
-			pub fn slice<R>(&self, range: R) -> BufferSlice
-				where R: RangeBounds<u32> {
-
-				let offset = match range.start_bound() {
-					Bound::Unbounded => 0,
-					Bound::Excluded(val) => val.checked_add(1)
-						.expect("lower range bound value would overflow u32 range"),
-					Bound::Included(val) => *val
-				};
-
-				let length = match range.end_bound() {
-					Bound::Unbounded => self.len() - offset,
-					Bound::Excluded(val) => {
-						let val = *val;
-
-						if val > self.len() {
-							panic!("upper range bound {} is greater than the \
-								length {} of the buffer",
-								val, self.len())
-						}
-						if offset > val {
-							panic!("lower range bound {} is greater than upper \
-								range bound {}",
-								offset, val)
-						}
-
-						val.checked_sub(offset).unwrap()
-					},
-					Bound::Included(val) => {
-						let val = *val;
-
-						if val >= self.len() {
-							panic!("upper range bound ={} is greater than the \
-								length {} of the buffer",
-								val, self.len())
-						}
-						if offset > val {
-							panic!("lower range bound {} is greater than upper \
-								range bound {}",
-								offset, val)
-						}
-
-						val.checked_sub(offset).unwrap()
-							.checked_add(1)
-							.expect("upper range bound overflows u32 range")
-					},
-				};
-
-				BufferSlice {
-					buffer: &self.inner,
-					target: $target,
-					offset,
-					length
-				}
+		}
+		impl From<$name> for Buffer {
+			fn from(buffer: $name) -> Self {
+				buffer.buffer
 			}
 		}
 		impl AccessLock for $name {
 			fn acquire_write(&self) {
-				self.inner.acquire_write()
+				self.buffer.acquire_write()
 			}
 			fn release_write(&self) {
-				self.inner.release_write()
+				self.buffer.release_write()
 			}
 			fn acquire_read(&self) {
-				self.inner.acquire_read()
+				self.buffer.acquire_read()
 			}
 			fn release_read(&self) {
-				self.inner.release_read()
+				self.buffer.release_read()
 			}
 		}
 	)+}
@@ -169,13 +441,79 @@ macro_rules! instance_buffers {
 instance_buffers! {
 	#[derive(Debug)]
 	#[doc = "A buffer type that may be used for vertex storage."]
-	pub struct VertexBuffer: glow::ARRAY_BUFFER;
+	pub struct VertexBuffer: VERTEX;
 	#[derive(Debug)]
 	#[doc = "A buffer type that may be used for index storage."]
-	pub struct IndexBuffer: glow::ELEMENT_ARRAY_BUFFER;
+	pub struct IndexBuffer: INDEX;
 	#[derive(Debug)]
 	#[doc = "A buffer that that may be used for uniform block storage."]
-	pub struct UniformBuffer: glow::UNIFORM_BUFFER;
+	pub struct UniformBuffer: UNIFORM;
+}
+
+/** Checks whether a copy of `size` bytes from `src_offset` in a buffer that is
+ * `src_len` bytes long to `dst_offset` in a buffer that is `dst_len` bytes
+ * long can be done, with both ranges in the same buffer if `same` is set. */
+pub(crate) fn check_copy(
+	src_offset: u32,
+	src_len: u32,
+	dst_offset: u32,
+	dst_len: u32,
+	size: u32,
+	same: bool) -> Result<(), BufferError> {
+
+	let src_end = src_offset.checked_add(size).filter(|end| *end <= src_len);
+	if src_end.is_none() {
+		return Err(BufferError::InvalidCopy {
+			what: format!("a copy of {} bytes from offset {} does not fit in \
+				a source buffer that is {} bytes long",
+				size,
+				src_offset,
+				src_len)
+		})
+	}
+	write_bounds(dst_offset, usize::try_from(size).unwrap(), dst_len)?;
+
+	let overlaps = src_offset < dst_offset + size && dst_offset < src_offset + size;
+	if same && overlaps {
+		return Err(BufferError::InvalidCopy {
+			what: format!("a copy of {} bytes from offset {} to offset {} of \
+				the same buffer has ranges that overlap",
+				size,
+				src_offset,
+				dst_offset)
+		})
+	}
+
+	Ok(())
+}
+
+/** Checks whether a write of `len` bytes starting at `offset` fits inside of a
+ * buffer that is `size` bytes long. */
+pub(crate) fn write_bounds(offset: u32, len: usize, size: u32) -> Result<(), BufferError> {
+	let end = u32::try_from(len).ok()
+		.and_then(|len| offset.checked_add(len));
+	match end {
+		Some(end) if end <= size => Ok(()),
+		_ => Err(BufferError::OutOfBounds {
+			offset,
+			len: u64::try_from(len).unwrap_or(u64::MAX),
+			size
+		})
+	}
+}
+
+/** Size, in bytes, of a buffer holding `count` values of type `T`, computed in
+ * 64 bits and failing if it doesn't fit in the `u32` buffers are sized with.
+ *
+ * This is what should be used to size buffers after the length of a slice,
+ * rather than multiplying its length by the size of its elements, which can
+ * silently wrap around on 32-bit targets. */
+pub fn buffer_size_of<T>(count: usize) -> Result<u32, BufferError> {
+	let size = u64::try_from(std::mem::size_of::<T>()).ok()
+		.zip(u64::try_from(count).ok())
+		.and_then(|(size, count)| size.checked_mul(count))
+		.unwrap_or(u64::MAX);
+	u32::try_from(size).map_err(|_| BufferError::TooLarge { computed_bytes: size })
 }
 
 /** Usage classes for buffers. This helps optimize the usage of the buffers. */
@@ -196,15 +534,9 @@ pub enum BufferProfile {
 	 */
 	DynamicDevice,
 }
-impl BufferProfile {
-	/** Get the OpenGL enum value for the current variant. */
-	pub(crate) fn as_opengl(&self) -> u32 {
-		match self {
-			Self::StaticUpload  => glow::STATIC_DRAW,
-			Self::DynamicUpload => glow::DYNAMIC_DRAW,
-			Self::StaticDevice  => glow::STATIC_COPY,
-			Self::DynamicDevice => glow::DYNAMIC_COPY,
-		}
+impl Default for BufferProfile {
+	fn default() -> Self {
+		Self::StaticUpload
 	}
 }
 
@@ -219,6 +551,36 @@ pub struct BufferDescriptor {
 	 * Uploading to a device-local buffer is still allowed, albeit it might be
 	 * slow compared to doing this to an upload buffer. */
 	pub profile: BufferProfile,
+	/** Kind of data the desired buffer is going to hold.
+	 *
+	 * This is only used to decide whether writes to the buffer should be
+	 * checked by the [write sanitizer], and [`BufferContents::Opaque`] is
+	 * always a valid choice.
+	 *
+	 * [write sanitizer]: crate::Device::set_write_sanitizer
+	 * [`BufferContents::Opaque`]: BufferContents::Opaque */
+	pub contents: BufferContents,
+	/** Ways the desired buffer may be used in.
+	 *
+	 * The functions creating typed buffers, such as
+	 * [`Device::create_vertex_buffer`], add the usage of their type to this,
+	 * so it only needs to list the other ways they may be used in, and is
+	 * empty by default.
+	 *
+	 * [`Device::create_vertex_buffer`]: crate::Device::create_vertex_buffer */
+	pub usage: BufferUsage,
+}
+impl Default for BufferDescriptor {
+	/** An empty static upload buffer, of opaque contents, with no usage other
+	 * than the one of its type. */
+	fn default() -> Self {
+		Self {
+			size: 0,
+			profile: Default::default(),
+			contents: Default::default(),
+			usage: BufferUsage::empty()
+		}
+	}
 }
 
 /** Read-only slice over the range of a buffer. */
@@ -242,7 +604,7 @@ impl<'a> BufferSlice<'a> {
 	 * the pipeline can't be locked mutably. */
 	pub fn try_map(&self) -> Result<BufferView, BufferRemap> {
 		let buffer_lock = self.buffer.acquire_read_guarded();
-		let pipeline_lock = self.buffer.pipeline.borrow_mut();
+		let pipeline_lock = self.buffer.pipeline.acquire(LockOperation::BufferMapping);
 
 		let mut map = self.buffer.map.borrow_mut();
 		*map = match *map {
@@ -270,8 +632,9 @@ impl<'a> BufferSlice<'a> {
 
 				/* Check for mapping errors. */
 				if ptr.is_null() {
-					panic!("opengl failed to map the buffer {:?} to memory: \
-					0x{:08x}", self.buffer.buffer, gl.get_error());
+					panic!("opengl failed to map the buffer {} to memory: {}",
+						self.buffer.name(),
+						crate::debug::error_name(gl.get_error()));
 				}
 
 				ptr
@@ -314,18 +677,31 @@ impl<'a> BufferSlice<'a> {
 	 * affects the initial state of the buffer and what the performance
 	 * characteristics of each operation are.
 	 *
+	 * The user may also choose how the mapping deals with the device still
+	 * reading from the buffer, from commands issued before it. See the
+	 * [documentation of the map modes] for details.
+	 *
 	 * # Panic
 	 * This function will panic if the buffer can't be locked mutable or if the
-	 * pipeline can't be locked mutably.
+	 * pipeline can't be locked mutably, or if the buffer is to be orphaned
+	 * while loading its contents, which orphaning throws away.
 	 *
 	 * [documentation of the load operations]: BufferLoadOp
+	 * [documentation of the map modes]: MapMode
 	 */
 	pub fn try_map_mut(
 		&self,
-		op: BufferLoadOp) -> Result<BufferViewMut, BufferRemap> {
+		op: BufferLoadOp,
+		mode: MapMode) -> Result<BufferViewMut, BufferRemap> {
+
+		let orphan = matches!(mode, MapMode::Orphan);
+		if orphan && op == BufferLoadOp::Load {
+			panic!("tried to load the contents of buffer {} while orphaning \
+				them", self.buffer.name());
+		}
 
 		let buffer_lock = self.buffer.acquire_write_guarded();
-		let pipeline_lock = self.buffer.pipeline.borrow_mut();
+		let pipeline_lock = self.buffer.pipeline.acquire(LockOperation::BufferMapping);
 
 		let mut map = self.buffer.map.borrow_mut();
 		*map = match *map {
@@ -333,8 +709,29 @@ impl<'a> BufferSlice<'a> {
 			MapState::Unmapped => MapState::Mapped,
 		};
 
+		/* Fences that fail to be waited on are as good as not signaled, as the
+		 * implementation can still synchronize the mapping by itself. */
+		let settled = match mode {
+			MapMode::AfterFence(fence, timeout) =>
+				fence.wait(timeout).unwrap_or(false),
+			_ => false
+		};
+
 		let len = self.length;
+		let whole = self.offset == 0 && len == self.buffer.len;
 		let gl = self.buffer.context.as_ref();
+		if orphan && whole && len > 0 {
+			/* Respecifying the storage of the buffer leaves the old one to
+			 * whatever commands are still reading from it. */
+			unsafe {
+				gl.bind_buffer(self.target, Some(self.buffer.buffer));
+				gl.buffer_data_size(
+					self.target,
+					i32::try_from(len).unwrap(),
+					self.buffer.profile.as_opengl());
+			}
+		}
+
 		let data = if len == 0 {
 			/* This is an empty buffer. */
 			BufferData::Empty { nothing: [] }
@@ -345,16 +742,13 @@ impl<'a> BufferSlice<'a> {
 					self.target,
 					i32::try_from(self.offset).unwrap(),
 					i32::try_from(len).unwrap(),
-					{
-						let access = glow::MAP_READ_BIT | glow::MAP_WRITE_BIT;
-						let access = access | glow::MAP_FLUSH_EXPLICIT_BIT;
-						access
-					});
+					map_access(orphan, whole, settled, op == BufferLoadOp::Load));
 
 				/* Check for mapping errors. */
 				if ptr.is_null() {
-					panic!("opengl failed to map the buffer {:?} to memory: \
-					0x{:08x}", self.buffer.buffer, gl.get_error());
+					panic!("opengl failed to map the buffer {} to memory: {}",
+						self.buffer.name(),
+						crate::debug::error_name(gl.get_error()));
 				}
 
 				ptr
@@ -450,6 +844,55 @@ pub enum BufferLoadOp {
 	Load,
 }
 
+/** How a mutable mapping of a buffer deals with the device still reading from
+ * the buffer, from commands issued before the mapping, such as the draws of the
+ * last frame reading from a uniform buffer that is updated every frame. */
+#[derive(Debug, Copy, Clone)]
+pub enum MapMode<'a> {
+	/** The implementation makes sure the device is done with the buffer before
+	 * handing it over, which may well stall until it is. */
+	Synchronized,
+	/** The contents of the mapped range get thrown away, and, when the whole
+	 * buffer is mapped, it gets new storage, with the old one being left to
+	 * the commands still reading from it. This never stalls, but the mapped
+	 * range has to be written to completely, and it can't be used with
+	 * [`BufferLoadOp::Load`].
+	 *
+	 * [`BufferLoadOp::Load`]: BufferLoadOp::Load */
+	Orphan,
+	/** The given fence gets waited on, for up to the given amount of time,
+	 * and the buffer gets mapped without synchronizing with the device if it
+	 * got signaled, or just like [`Synchronized`] if it didn't. Mappings that
+	 * load the contents of the buffer always synchronize, as implementations
+	 * don't allow reading from unsynchronized ones, but that has nothing left
+	 * to wait on once the fence has been signaled.
+	 *
+	 * The fence must have been inserted after the last command reading from
+	 * the buffer, otherwise the device may read what is being written. This
+	 * is what lets rings of buffers be reused once the frame that last read
+	 * from them is done, without stalling on the ones still in flight.
+	 *
+	 * [`Synchronized`]: Self::Synchronized */
+	AfterFence(&'a GpuFence, Duration),
+}
+
+/** Access bits buffers get mapped for writing with, when they are orphaned,
+ * with the whole buffer being mapped or not, when the device is known to be
+ * done with them, and when their contents get loaded. */
+fn map_access(orphan: bool, whole: bool, settled: bool, load: bool) -> u32 {
+	let access = glow::MAP_WRITE_BIT | glow::MAP_FLUSH_EXPLICIT_BIT;
+	match (orphan, whole, settled, load) {
+		/* Invalidated ranges can't be read from. */
+		(true, true, _, _) => access | glow::MAP_INVALIDATE_BUFFER_BIT,
+		(true, false, _, _) => access | glow::MAP_INVALIDATE_RANGE_BIT,
+		/* Reads can't be unsynchronized, but once the fence has signaled,
+		 * synchronizing doesn't have anything left to wait on anyway. */
+		(false, _, _, true) => access | glow::MAP_READ_BIT,
+		(false, _, true, false) => access | glow::MAP_UNSYNCHRONIZED_BIT,
+		(false, _, false, false) => access,
+	}
+}
+
 /** Error type for when buffer map requests are made to buffers which are
  * already mapped. */
 #[derive(Debug)]
@@ -467,6 +910,43 @@ pub enum BufferError {
 	CreationFailed {
 		what: String
 	},
+	#[error("a write of {len} bytes at offset {offset} does not fit in a buffer \
+		that is {size} bytes long")]
+	OutOfBounds {
+		offset: u32,
+		len: u64,
+		size: u32
+	},
+	#[error("a write of {len} bytes at offset {offset} is not aligned to \
+		{alignment} bytes")]
+	Misaligned {
+		offset: u32,
+		len: u64,
+		alignment: u32
+	},
+	#[error("the buffer exceeds the limits of the context: {what}")]
+	LimitExceeded {
+		what: String
+	},
+	#[error("invalid buffer usage: {what}")]
+	InvalidUsage {
+		what: String
+	},
+	#[error("invalid buffer copy: {what}")]
+	InvalidCopy {
+		what: String
+	},
+	#[error("the operation uses a feature the context doesn't support: {what}")]
+	UnsupportedFeature {
+		what: String
+	},
+	/** The buffer would take up more bytes than can be addressed. The
+	 * computed size saturates at [`u64::MAX`]. */
+	#[error("the buffer would take up {computed_bytes} bytes, which is more \
+		than can be addressed")]
+	TooLarge {
+		computed_bytes: u64
+	},
 }
 
 /** Depending on which implementation we're running, buffers may or may not be
@@ -509,6 +989,19 @@ impl BufferData {
 	fn finish(&mut self, slice: BufferSlice<'_>) {
 		let this = std::mem::replace(self, Self::Terminated);
 
+		/* Catch non-finite values before they make it to the device, but only
+		 * report them once the buffer is unmapped, as a panic would otherwise
+		 * leave it mapped for good. */
+		let hits = match this {
+			Self::Mapped { mutated: true, .. }
+				| Self::Mirrored { mutated: true, .. } =>
+				slice.buffer.sanitizer.find(
+					slice.buffer.contents,
+					slice.offset,
+					this.as_ref()),
+			_ => Vec::new()
+		};
+
 		match this {
 			Self::Mapped { len, mutated, .. } => unsafe {
 				let gl = slice.buffer.context.as_ref();
@@ -519,6 +1012,7 @@ impl BufferData {
 						i32::try_from(len).unwrap());
 				}
 				gl.unmap_buffer(slice.target);
+				slice.buffer.check("unmapping");
 			},
 			Self::Mirrored { storage, mutated } => unsafe {
 				let gl = slice.buffer.context.as_ref();
@@ -526,11 +1020,18 @@ impl BufferData {
 					gl.buffer_sub_data_u8_slice(
 						slice.target,
 						i32::try_from(slice.offset).unwrap(),
-						&*storage)
+						&*storage);
+					slice.buffer.check("writing the mirror back to");
 				}
 			},
 			Self::Terminated | Self::Empty { .. } => { /* No-op. */ }
 		}
+
+		if !hits.is_empty() {
+			slice.buffer.sanitizer.report(
+				&slice.buffer.lazy_name(),
+				&hits);
+		}
 	}
 }
 impl AsRef<[u8]> for BufferData {
@@ -584,7 +1085,7 @@ pub struct BufferView<'a> {
 	/** The mapped data in this buffer. */
 	data: BufferData,
 	/** The lock on the pipeline. */
-	_pipeline_lock: std::cell::RefMut<'a, ()>,
+	_pipeline_lock: crate::lock::PipelineGuard<'a>,
 	/** The lock on the buffer. */
 	_buffer_lock: crate::access::ReadGuard<'a>,
 }
@@ -620,7 +1121,7 @@ pub struct BufferViewMut<'a> {
 	/** The mapped data in this buffer. */
 	data: BufferData,
 	/** The lock on the pipeline. */
-	_pipeline_lock: std::cell::RefMut<'a, ()>,
+	_pipeline_lock: crate::lock::PipelineGuard<'a>,
 	/** The lock on the buffer. */
 	_buffer_lock: crate::access::WriteGuard<'a>,
 }
@@ -658,3 +1159,297 @@ impl<'a> Drop for BufferViewMut<'a> {
 		*map = MapState::Unmapped;
 	}
 }
+
+/** Writer over the storage of a buffer or texture being filled in, which
+ * lets data be written into it as it gets generated, rather than having to be
+ * put together in host memory beforehand.
+ *
+ * Values get written one after the other, in the order they were pushed in,
+ * and with no padding between them, unless asked for with [`align_to`]. The
+ * storage being written into has no alignment requirements, so values may be
+ * pushed at any offset, regardless of their own alignment.
+ *
+ * [`align_to`]: Self::align_to */
+#[derive(Debug)]
+pub struct BufferWriter<'a> {
+	/** Storage being written into, which is either mapped buffer memory or
+	 * a staging buffer in host memory. */
+	storage: &'a mut [u8],
+	/** Number of bytes written so far. */
+	written: usize,
+}
+impl<'a> BufferWriter<'a> {
+	/** Creates a new writer over the given storage. */
+	pub(crate) fn new(storage: &'a mut [u8]) -> Self {
+		Self {
+			storage,
+			written: 0
+		}
+	}
+
+	/** Writes the given value into the storage, right after the last one.
+	 *
+	 * # Panic
+	 * This function will panic if the value doesn't fit in the storage. */
+	pub fn push<T: bytemuck::Pod>(&mut self, value: &T) {
+		self.write(bytemuck::bytes_of(value))
+	}
+
+	/** Writes all of the given values into the storage, right after the last
+	 * one.
+	 *
+	 * # Panic
+	 * This function will panic if the values don't fit in the storage. */
+	pub fn extend_from_slice<T: bytemuck::Pod>(&mut self, values: &[T]) {
+		self.write(bytemuck::cast_slice(values))
+	}
+
+	/** Skips over to the next offset that is a multiple of the given
+	 * alignment, writing zeroes to the bytes in between.
+	 *
+	 * # Panic
+	 * This function will panic if the alignment is zero or if the padding
+	 * doesn't fit in the storage. */
+	pub fn align_to(&mut self, alignment: usize) {
+		assert_ne!(alignment, 0, "cannot align to zero bytes");
+
+		let padding = (alignment - self.written % alignment) % alignment;
+		let start = self.written;
+		self.reserve(padding).iter_mut().for_each(|byte| *byte = 0);
+		debug_assert_eq!(self.written, start + padding);
+	}
+
+	/** Number of bytes written so far. */
+	pub fn written(&self) -> usize {
+		self.written
+	}
+
+	/** Number of bytes that may still be written. */
+	pub fn remaining(&self) -> usize {
+		self.storage.len() - self.written
+	}
+
+	/** Writes the given bytes into the storage. */
+	fn write(&mut self, bytes: &[u8]) {
+		self.reserve(bytes.len()).copy_from_slice(bytes)
+	}
+
+	/** Takes the given number of bytes of the storage, right after the ones
+	 * written so far, counting them as written. */
+	fn reserve(&mut self, len: usize) -> &mut [u8] {
+		if len > self.remaining() {
+			panic!("tried to write {} bytes at offset {} of a storage that is \
+				{} bytes long", len, self.written, self.storage.len())
+		}
+
+		let start = self.written;
+		self.written += len;
+		&mut self.storage[start..self.written]
+	}
+
+	/** Finishes the writing, returning the storage that was written into.
+	 *
+	 * Writers must always fill their storage, which is checked for in debug
+	 * builds. In release builds, whatever wasn't written gets zeroed out, so
+	 * that staging buffers reused between writes never leak what was left in
+	 * them by the previous one. */
+	pub(crate) fn finish(self, what: &dyn std::fmt::Display) -> &'a mut [u8] {
+		debug_assert_eq!(self.written, self.storage.len(),
+			"{} was not written to completely, {} out of {} bytes were \
+			written", what, self.written, self.storage.len());
+
+		self.storage[self.written..].iter_mut().for_each(|byte| *byte = 0);
+		self.storage
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn writes_must_fit_in_the_buffer() {
+		assert!(write_bounds(0, 16, 16).is_ok());
+		assert!(write_bounds(12, 4, 16).is_ok());
+		assert!(write_bounds(16, 0, 16).is_ok());
+
+		assert!(matches!(write_bounds(13, 4, 16),
+			Err(BufferError::OutOfBounds { offset: 13, len: 4, size: 16 })));
+		assert!(write_bounds(17, 0, 16).is_err());
+		assert!(write_bounds(u32::MAX, 2, u32::MAX).is_err());
+	}
+
+	#[test]
+	fn copies_must_fit_and_not_overlap() {
+		assert!(check_copy(0, 64, 32, 64, 32, true).is_ok());
+		assert!(check_copy(32, 64, 0, 64, 32, true).is_ok());
+		assert!(check_copy(0, 64, 0, 64, 64, false).is_ok());
+		assert!(check_copy(8, 64, 8, 64, 0, true).is_ok());
+
+		/* Overlapping ranges only matter within the same buffer. */
+		assert!(matches!(check_copy(0, 64, 16, 64, 32, true),
+			Err(BufferError::InvalidCopy { .. })));
+		assert!(matches!(check_copy(16, 64, 0, 64, 32, true),
+			Err(BufferError::InvalidCopy { .. })));
+		assert!(check_copy(0, 64, 16, 64, 32, false).is_ok());
+
+		assert!(matches!(check_copy(48, 64, 0, 64, 32, false),
+			Err(BufferError::InvalidCopy { .. })));
+		assert!(matches!(check_copy(0, 64, 48, 64, 32, false),
+			Err(BufferError::OutOfBounds { offset: 48, len: 32, size: 64 })));
+		assert!(check_copy(u32::MAX, u32::MAX, 0, 64, 2, false).is_err());
+	}
+
+	#[test]
+	fn buffer_sizes_are_checked() {
+		assert_eq!(buffer_size_of::<[f32; 3]>(4).unwrap(), 48);
+		assert_eq!(buffer_size_of::<u8>(u32::MAX as usize).unwrap(), u32::MAX);
+		assert_eq!(buffer_size_of::<u32>(0).unwrap(), 0);
+
+		assert!(matches!(buffer_size_of::<u32>(0x4000_0000),
+			Err(BufferError::TooLarge { computed_bytes: 0x1_0000_0000 })));
+		assert!(matches!(buffer_size_of::<u64>(usize::MAX),
+			Err(BufferError::TooLarge { .. })));
+	}
+
+	#[test]
+	fn writers_write_values_in_order() {
+		let mut storage = [0xff; 12];
+		let mut writer = BufferWriter::new(&mut storage);
+		writer.push(&1u32);
+		writer.extend_from_slice(&[2u16, 3u16]);
+		writer.push(&4.0f32);
+		assert_eq!(writer.remaining(), 0);
+		writer.finish(&"storage");
+
+		let mut expected = Vec::new();
+		expected.extend_from_slice(&1u32.to_ne_bytes());
+		expected.extend_from_slice(&2u16.to_ne_bytes());
+		expected.extend_from_slice(&3u16.to_ne_bytes());
+		expected.extend_from_slice(&4.0f32.to_ne_bytes());
+		assert_eq!(&storage[..], &expected[..]);
+	}
+
+	#[test]
+	fn writers_push_values_at_unaligned_offsets() {
+		let mut storage = [0xff; 9];
+		let mut writer = BufferWriter::new(&mut storage);
+		writer.push(&7u8);
+		writer.push(&0x0102_0304_0506_0708u64);
+		writer.finish(&"storage");
+
+		assert_eq!(storage[0], 7);
+		assert_eq!(&storage[1..], &0x0102_0304_0506_0708u64.to_ne_bytes());
+	}
+
+	#[test]
+	fn writers_pad_to_alignment() {
+		let mut storage = [0xff; 8];
+		let mut writer = BufferWriter::new(&mut storage);
+		writer.push(&1u8);
+		writer.align_to(4);
+		assert_eq!(writer.written(), 4);
+		writer.align_to(4);
+		assert_eq!(writer.written(), 4);
+		writer.push(&2u32);
+		writer.finish(&"storage");
+
+		assert_eq!(&storage[..4], &[1, 0, 0, 0]);
+	}
+
+	#[test]
+	#[should_panic]
+	fn writers_reject_values_past_the_end() {
+		let mut storage = [0; 6];
+		let mut writer = BufferWriter::new(&mut storage);
+		writer.push(&1u32);
+		writer.push(&2u32);
+	}
+
+	#[test]
+	#[cfg(debug_assertions)]
+	#[should_panic]
+	fn writers_must_fill_their_storage() {
+		let mut storage = [0; 8];
+		let mut writer = BufferWriter::new(&mut storage);
+		writer.push(&1u32);
+		writer.finish(&"storage");
+	}
+
+	#[test]
+	#[cfg(not(debug_assertions))]
+	fn writers_zero_what_was_not_written() {
+		let mut storage = [0xff; 8];
+		let mut writer = BufferWriter::new(&mut storage);
+		writer.push(&1u32);
+		writer.finish(&"storage");
+
+		assert_eq!(&storage[4..], &[0, 0, 0, 0]);
+	}
+
+	#[test]
+	fn orphaned_mappings_are_never_read_from() {
+		let read = glow::MAP_READ_BIT;
+		let unsynchronized = glow::MAP_UNSYNCHRONIZED_BIT;
+
+		assert_eq!(map_access(true, true, false, false) & read, 0);
+		assert_eq!(map_access(true, false, true, false) & read, 0);
+		assert_ne!(map_access(true, true, false, false) & glow::MAP_INVALIDATE_BUFFER_BIT, 0);
+		assert_ne!(map_access(true, false, false, false) & glow::MAP_INVALIDATE_RANGE_BIT, 0);
+
+		assert_ne!(map_access(false, true, false, true) & read, 0);
+		assert_eq!(map_access(false, true, false, false) & read, 0);
+		assert_eq!(map_access(false, true, false, false) & unsynchronized, 0);
+		assert_ne!(map_access(false, false, true, false) & unsynchronized, 0);
+
+		/* Implementations reject reads from unsynchronized mappings. */
+		for &orphan in &[false, true] {
+			for &whole in &[false, true] {
+				for &settled in &[false, true] {
+					for &load in &[false, true] {
+						let access = map_access(orphan, whole, settled, load);
+						assert!(access & read == 0 || access & unsynchronized == 0);
+					}
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn usages_must_suit_the_context() {
+		let shared = BufferUsage::VERTEX | BufferUsage::INDEX;
+		assert_eq!(BufferUsage::VERTEX.target(), glow::ARRAY_BUFFER);
+		assert_eq!(BufferUsage::UNIFORM.target(), glow::UNIFORM_BUFFER);
+		assert_eq!(shared.target(), glow::ELEMENT_ARRAY_BUFFER);
+		assert_eq!(BufferUsage::TRANSFORM_FEEDBACK.target(), glow::TRANSFORM_FEEDBACK_BUFFER);
+		assert_eq!(BufferUsage::PIXEL_UNPACK.target(), glow::PIXEL_UNPACK_BUFFER);
+
+		assert!(matches!(
+			check_usage(BufferUsage::empty(), Profile::Core),
+			Err(BufferError::InvalidUsage { .. })));
+		assert!(check_usage(shared, Profile::Core).is_ok());
+		assert!(check_usage(shared, Profile::EsLegacy).is_ok());
+		assert!(check_usage(BufferUsage::INDEX, Profile::Web).is_ok());
+
+		/* Typed buffers created from the default descriptor only get the usage
+		 * of their type, which is fine even for index buffers on WebGL. */
+		let usage = BufferDescriptor::default().usage;
+		assert!(usage.is_empty());
+		assert!(check_usage(usage | BufferUsage::INDEX, Profile::Web).is_ok());
+		assert!(check_usage(BufferUsage::VERTEX | BufferUsage::UNIFORM, Profile::Web).is_ok());
+		for profile in [Profile::Web, Profile::WebLegacy] {
+			assert!(matches!(
+				check_usage(shared, profile),
+				Err(BufferError::UnsupportedFeature { .. })));
+		}
+
+		let captured = BufferUsage::VERTEX | BufferUsage::TRANSFORM_FEEDBACK;
+		assert!(check_usage(captured, Profile::Web).is_ok());
+		assert!(matches!(
+			check_usage(captured, Profile::EsLegacy),
+			Err(BufferError::UnsupportedFeature { .. })));
+		assert!(matches!(
+			check_usage(BufferUsage::PIXEL_UNPACK, Profile::WebLegacy),
+			Err(BufferError::UnsupportedFeature { .. })));
+	}
+}
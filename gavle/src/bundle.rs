@@ -0,0 +1,368 @@
+use crate::pipeline::RenderPipeline;
+use crate::buffer::{Buffer, BufferUsage};
+use crate::binding::{UniformGroup, BindGroupError};
+use crate::pass::{Viewport, ViewportError, DrawIndexedCommand, DrawError};
+use crate::pass::{check_draw_commands, check_viewport, vertex_draw_range, instance_count};
+use crate::{Information, Color};
+use std::ops::Range;
+use std::rc::Rc;
+
+/** List of draw commands recorded once, with [`Device::create_render_bundle()`],
+ * and replayed as many times as needed into render passes, with
+ * [`RenderPass::execute_bundle()`].
+ *
+ * Redundant state changes get dropped as the commands are recorded, and every
+ * draw gets checked against the state set up for it then, so replaying a
+ * bundle costs little more than the draw calls themselves. A bundle holds on
+ * to every pipeline, buffer and group it uses, so none of them can go away
+ * while it is still alive.
+ *
+ * [`Device::create_render_bundle()`]: crate::Device::create_render_bundle
+ * [`RenderPass::execute_bundle()`]: crate::RenderPass::execute_bundle */
+pub struct RenderBundle {
+	/** Commands in this bundle, in the order they were recorded in. */
+	pub(crate) commands: Vec<BundleCommand>,
+}
+impl RenderBundle {
+	/** Number of commands in this bundle, after redundant state changes have
+	 * been dropped. */
+	pub fn len(&self) -> usize {
+		self.commands.len()
+	}
+
+	/** Whether this bundle has no commands in it. */
+	pub fn is_empty(&self) -> bool {
+		self.commands.is_empty()
+	}
+}
+
+/** Descriptor for recording a new render bundle. */
+pub struct RenderBundleDescriptor<'a> {
+	/** The pipeline the bundle starts out using. */
+	pub pipeline: &'a RenderPipeline,
+}
+
+/** A single command recorded into a bundle. */
+pub(crate) enum BundleCommand {
+	SetPipeline(RenderPipeline),
+	SetVertexBuffer(Buffer),
+	SetInstanceBuffer(Buffer),
+	SetIndexBuffer(Buffer),
+	/** A group, along with its dynamic offsets, which are empty when the
+	 * group was set without any. */
+	SetBindGroup(UniformGroup, Vec<u32>),
+	SetViewport(Viewport),
+	SetScissor(Option<Viewport>),
+	SetBlendConstant(Color),
+	SetStencilReference(u8),
+	DrawIndexed {
+		indices: Range<u32>,
+		instances: u32,
+	},
+	MultiDrawIndexed(Vec<DrawIndexedCommand>),
+	Draw {
+		vertices: Range<u32>,
+		instances: u32,
+	},
+}
+
+/** Records commands into a [`RenderBundle`], through the same interface as
+ * the one used to issue them into a [`RenderPass`].
+ *
+ * Bundles start from a clean slate: no buffers and no group are set when
+ * recording starts, and the pipeline is the one given in the descriptor.
+ *
+ * [`RenderPass`]: crate::RenderPass */
+pub struct RenderBundleEncoder {
+	/** Shared context information. */
+	pub(crate) information: Rc<Information>,
+	/** Commands recorded so far. */
+	pub(crate) commands: Vec<BundleCommand>,
+	/** State as set by the commands recorded so far. */
+	pub(crate) state: BundleState,
+}
+impl RenderBundleEncoder {
+	/** Starts recording a new bundle using the given pipeline. */
+	pub(crate) fn new(
+		information: Rc<Information>,
+		pipeline: &RenderPipeline) -> Self {
+
+		let mut encoder = Self {
+			information,
+			commands: Vec::new(),
+			state: BundleState::default(),
+		};
+		encoder.set_pipeline(pipeline);
+
+		encoder
+	}
+
+	/** Finishes recording, turning the commands into a bundle. */
+	pub(crate) fn finish(self) -> RenderBundle {
+		RenderBundle { commands: self.commands }
+	}
+
+	/** Sets the pipeline used by the draws that follow. */
+	pub fn set_pipeline(&mut self, pipeline: &RenderPipeline) {
+		let key = Rc::as_ptr(&pipeline.inner) as usize;
+		if update(&mut self.state.pipeline, key) {
+			let pipeline = RenderPipeline { inner: pipeline.inner.clone() };
+			self.commands.push(BundleCommand::SetPipeline(pipeline));
+		}
+		self.state.instanced = pipeline.inner.instance_layout.as_ref()
+			.map(|layout| !layout.attributes.is_empty())
+			.unwrap_or(false);
+		self.state.vertex_attributes = !pipeline.inner.vertex_layout
+			.attributes.is_empty();
+		self.state.index_len = pipeline.index_len();
+	}
+
+	/** Sets the vertex buffer used by the draws that follow.
+	 *
+	 * # Panic
+	 * This function will panic if the usage of the buffer doesn't include
+	 * [`BufferUsage::VERTEX`]. */
+	pub fn set_vertex_buffer(&mut self, buffer: &Buffer) {
+		buffer.assert_usage(BufferUsage::VERTEX, "use vertex data from");
+		let key = Rc::as_ptr(&buffer.inner) as usize;
+		if update(&mut self.state.vertex, key) {
+			self.commands.push(BundleCommand::SetVertexBuffer(buffer.share()));
+		}
+	}
+
+	/** Sets the buffer the per-instance attributes of the pipeline are read
+	 * from in the draws that follow.
+	 *
+	 * # Panic
+	 * This function will panic if the usage of the buffer doesn't include
+	 * [`BufferUsage::VERTEX`]. */
+	pub fn set_instance_buffer(&mut self, buffer: &Buffer) {
+		buffer.assert_usage(BufferUsage::VERTEX, "use instance data from");
+		let key = Rc::as_ptr(&buffer.inner) as usize;
+		if update(&mut self.state.instance, key) {
+			self.commands.push(BundleCommand::SetInstanceBuffer(buffer.share()));
+		}
+	}
+
+	/** Sets the index buffer used by the draws that follow.
+	 *
+	 * # Panic
+	 * This function will panic if the usage of the buffer doesn't include
+	 * [`BufferUsage::INDEX`]. */
+	pub fn set_index_buffer(&mut self, buffer: &Buffer) {
+		buffer.assert_usage(BufferUsage::INDEX, "use index data from");
+		let key = Rc::as_ptr(&buffer.inner) as usize;
+		if update(&mut self.state.index, key) {
+			self.state.index_bytes = buffer.len();
+			self.commands.push(BundleCommand::SetIndexBuffer(buffer.share()));
+		}
+	}
+
+	/** Sets the uniform bind group used by the draws that follow. */
+	pub fn set_bind_group(&mut self, group: &UniformGroup) {
+		self.set_group(group, Vec::new())
+	}
+
+	/** Sets the uniform bind group used by the draws that follow, shifting the
+	 * ranges bound for its uniform buffers by the given offsets, in bytes,
+	 * just like [`RenderPass::set_bind_group_with_offsets()`] does. The
+	 * offsets get checked now, rather than when the bundle gets executed.
+	 *
+	 * [`RenderPass::set_bind_group_with_offsets()`]: crate::RenderPass::set_bind_group_with_offsets */
+	pub fn set_bind_group_with_offsets(
+		&mut self,
+		group: &UniformGroup,
+		offsets: &[u32]) -> Result<(), BindGroupError> {
+
+		group.check_offsets(
+			offsets,
+			self.information.limits.uniform_buffer_offset_alignment)?;
+		self.set_group(group, offsets.to_vec());
+
+		Ok(())
+	}
+
+	/** Records a change of group, unless it's the group and offsets already
+	 * set. Groups replace their entries in place, so they are compared by the
+	 * entries they share, rather than by the handles given to us. */
+	fn set_group(&mut self, group: &UniformGroup, offsets: Vec<u32>) {
+		let key = (Rc::as_ptr(&group.entries) as usize, offsets);
+		if update(&mut self.state.bind, key.clone()) {
+			self.commands.push(BundleCommand::SetBindGroup(group.share(), key.1));
+		}
+	}
+
+	/** Sets the viewport used by the draws that follow, failing if it
+	 * exceeds the limits of the context, just like its counterpart in
+	 * [`RenderPass`].
+	 *
+	 * [`RenderPass`]: crate::RenderPass */
+	pub fn set_viewport(&mut self, viewport: Viewport) -> Result<(), ViewportError> {
+		let limits = &self.information.limits;
+		check_viewport(&viewport, limits.max_viewport_width, limits.max_viewport_height)?;
+
+		if update(&mut self.state.viewport, viewport) {
+			self.commands.push(BundleCommand::SetViewport(viewport));
+		}
+		Ok(())
+	}
+
+	/** Sets the scissor rectangle used by the draws that follow, or disables
+	 * the scissor test, with `None`, failing if the rectangle exceeds the
+	 * limits of the context, just like its counterpart in [`RenderPass`].
+	 *
+	 * [`RenderPass`]: crate::RenderPass */
+	pub fn set_scissor(&mut self, rect: Option<Viewport>) -> Result<(), ViewportError> {
+		if let Some(rect) = &rect {
+			let limits = &self.information.limits;
+			check_viewport(rect, limits.max_viewport_width, limits.max_viewport_height)?;
+		}
+
+		if update(&mut self.state.scissor, rect) {
+			self.commands.push(BundleCommand::SetScissor(rect));
+		}
+		Ok(())
+	}
+
+	/** Sets the blend constant used by the draws that follow. */
+	pub fn set_blend_constant(&mut self, color: Color) {
+		if update(&mut self.state.blend_constant, color) {
+			self.commands.push(BundleCommand::SetBlendConstant(color));
+		}
+	}
+
+	/** Sets the reference value for the stencil operations of the draws that
+	 * follow. */
+	pub fn set_stencil_reference(&mut self, reference: u8) {
+		if update(&mut self.state.stencil_reference, reference) {
+			self.commands.push(BundleCommand::SetStencilReference(reference));
+		}
+	}
+
+	/** Records an indexed draw of the given range of indices.
+	 *
+	 * Unlike its counterpart in [`RenderPass`], this checks the range against
+	 * the index buffer, since doing it once now saves doing it every time the
+	 * bundle gets executed.
+	 *
+	 * [`RenderPass`]: crate::RenderPass */
+	pub fn draw_indexed(
+		&mut self,
+		indices: Range<u32>,
+		instances: u32) -> Result<(), DrawError> {
+
+		let draw = DrawIndexedCommand {
+			index_range: indices.clone(),
+			base_vertex: 0,
+			instances
+		};
+		self.check_indexed(std::slice::from_ref(&draw))?;
+
+		self.commands.push(BundleCommand::DrawIndexed { indices, instances });
+		Ok(())
+	}
+
+	/** Records all of the given indexed draws, one after the other. */
+	pub fn multi_draw_indexed(
+		&mut self,
+		draws: &[DrawIndexedCommand]) -> Result<(), DrawError> {
+
+		self.check_indexed(draws)?;
+		if draws.is_empty() { return Ok(()) }
+
+		self.commands.push(BundleCommand::MultiDrawIndexed(draws.to_vec()));
+		Ok(())
+	}
+
+	/** Records a non-indexed draw of the given range of vertices. */
+	pub fn draw(
+		&mut self,
+		vertices: Range<u32>,
+		instances: u32) -> Result<(), DrawError> {
+
+		if self.state.vertex.is_none() && self.state.vertex_attributes {
+			return Err(DrawError::MissingVertexBuffer)
+		}
+		if self.state.instance.is_none() && self.state.instanced {
+			return Err(DrawError::MissingInstanceBuffer)
+		}
+		vertex_draw_range(&vertices)?;
+		instance_count(instances)?;
+
+		self.commands.push(BundleCommand::Draw { vertices, instances });
+		Ok(())
+	}
+
+	/** Checks whether the given indexed draws can be made with the current
+	 * state. */
+	fn check_indexed(&self, draws: &[DrawIndexedCommand]) -> Result<(), DrawError> {
+		if self.state.index.is_none() {
+			return Err(DrawError::MissingIndexBuffer)
+		}
+		check_draw_commands(
+			draws,
+			self.state.index_bytes / self.state.index_len,
+			self.state.index_len,
+			self.information.capabilities.base_vertex)
+	}
+}
+
+/** State set by the commands recorded into a bundle so far. Resources are
+ * tracked by the address of their shared inner objects, which stay alive for
+ * as long as the bundle holds on to them. */
+#[derive(Debug, Default)]
+pub(crate) struct BundleState {
+	pipeline: Option<usize>,
+	vertex: Option<usize>,
+	instance: Option<usize>,
+	index: Option<usize>,
+	bind: Option<(usize, Vec<u32>)>,
+	viewport: Option<Viewport>,
+	scissor: Option<Option<Viewport>>,
+	blend_constant: Option<Color>,
+	stencil_reference: Option<u8>,
+	/** Length of the current index buffer, in bytes. */
+	index_bytes: u32,
+	/** Length of a single index of the current pipeline, in bytes. */
+	index_len: u32,
+	/** Whether the current pipeline reads from a vertex buffer. */
+	vertex_attributes: bool,
+	/** Whether the current pipeline reads from an instance buffer. */
+	instanced: bool,
+}
+
+/** Sets the given piece of state to the given value, returning whether that
+ * changed it, and so whether the change has to be recorded. */
+fn update<T: PartialEq>(current: &mut Option<T>, value: T) -> bool {
+	if current.as_ref() == Some(&value) {
+		false
+	} else {
+		*current = Some(value);
+		true
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn redundant_changes_are_dropped() {
+		let mut state = BundleState::default();
+		assert!(update(&mut state.vertex, 0x10));
+		assert!(!update(&mut state.vertex, 0x10));
+		assert!(update(&mut state.vertex, 0x20));
+		assert!(update(&mut state.vertex, 0x10));
+
+		/* Disabling the scissor test is a change from the initial state,
+		 * which has to be recorded for the bundle to start from it. */
+		assert!(update(&mut state.scissor, None));
+		assert!(!update(&mut state.scissor, None));
+
+		/* Changing only the offsets of a group is still a change. */
+		assert!(update(&mut state.bind, (0x30, vec![0, 256])));
+		assert!(!update(&mut state.bind, (0x30, vec![0, 256])));
+		assert!(update(&mut state.bind, (0x30, vec![256, 0])));
+		assert!(update(&mut state.bind, (0x30, vec![])));
+	}
+}
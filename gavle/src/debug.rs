@@ -0,0 +1,138 @@
+use glow::{Context, HasContext};
+use std::fmt::Debug;
+use crate::info::Capabilities;
+
+/** Options controlling how a device gets created. The default options are the
+ * ones used by [`Device::new_from_context()`].
+ *
+ * [`Device::new_from_context()`]: crate::Device::new_from_context */
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub struct DeviceOptions {
+	/** Whether the device should check for OpenGL errors after every call
+	 * that changes the state of a buffer, texture, framebuffer or render pass,
+	 * and log the operation that raised them, along with the label of the
+	 * object it was operating on.
+	 *
+	 * Querying for errors makes the implementation synchronize with the
+	 * device, which is very slow, so this is off by default, and should only
+	 * be turned on to track down errors. */
+	pub validation: bool,
+}
+
+/** Most errors read back from the context in a single check. Lost contexts
+ * may keep reporting errors forever, so we have to stop somewhere. */
+const MAX_ERRORS_PER_CHECK: usize = 8;
+
+/** Shared state of the debugging facilities of a device, used both to check
+ * for errors in validation mode and to attach labels to objects. */
+#[derive(Debug, Default)]
+pub(crate) struct Debugger {
+	/** Whether errors get checked for after state-changing calls. */
+	validation: bool,
+	/** Whether the context supports attaching labels to objects. */
+	labels: bool,
+}
+impl Debugger {
+	/** Creates the debugging state for a context with the given capabilities,
+	 * created with the given options. */
+	pub(crate) fn new(options: &DeviceOptions, capabilities: &Capabilities) -> Self {
+		Self {
+			validation: options.validation,
+			labels: capabilities.debug_labels
+		}
+	}
+
+	/** Checks the context for errors raised by the given operation, logging
+	 * every one of them along with the description of the object it was
+	 * operating on, which only gets worked out if there was an error. The
+	 * operation is followed by the object in messages, as in `writing to`
+	 * followed by `buffer "vertices"`. Does nothing outside of validation
+	 * mode. */
+	pub(crate) unsafe fn check<F>(&self, gl: &Context, operation: &str, object: F)
+		where F: FnOnce() -> String {
+
+		if !self.validation { return }
+
+		let mut errors = Vec::new();
+		loop {
+			let error = gl.get_error();
+			if error == glow::NO_ERROR || errors.len() >= MAX_ERRORS_PER_CHECK {
+				break
+			}
+			errors.push(error);
+		}
+		if errors.is_empty() { return }
+
+		let object = object();
+		for error in errors {
+			rate_limited!(error!(target: "gavle::validation",
+				"{} {} raised {}",
+				operation,
+				object,
+				error_name(error)));
+		}
+	}
+
+	/** Attaches the given label to the object with the given name, when the
+	 * context supports it. The identifier is the namespace of the object, such
+	 * as `glow::TEXTURE` or `glow::BUFFER`. */
+	#[cfg(not(target_arch = "wasm32"))]
+	pub(crate) unsafe fn label(
+		&self,
+		gl: &Context,
+		identifier: u32,
+		name: u32,
+		label: &str) {
+
+		if !self.labels { return }
+		gl.object_label(identifier, name, Some(label));
+	}
+
+	/** WebGL has no object labels, so there is nothing to attach them to. */
+	#[cfg(target_arch = "wasm32")]
+	pub(crate) unsafe fn label<N>(&self, _: &Context, _: u32, _: N, _: &str) {}
+}
+
+/** Name of an object in diagnostics, which is either its label, if it has
+ * one, as in `"albedo"`, or the given fallback identifier, as in `#12`. */
+pub(crate) fn name<D>(label: Option<&str>, fallback: D) -> String
+	where D: Debug {
+
+	match label {
+		Some(label) => format!("\"{}\"", label),
+		None => format!("#{:?}", fallback)
+	}
+}
+
+/** Name of the given OpenGL error code. */
+pub(crate) fn error_name(error: u32) -> String {
+	let name = match error {
+		glow::INVALID_ENUM => "GL_INVALID_ENUM",
+		glow::INVALID_VALUE => "GL_INVALID_VALUE",
+		glow::INVALID_OPERATION => "GL_INVALID_OPERATION",
+		glow::INVALID_FRAMEBUFFER_OPERATION => "GL_INVALID_FRAMEBUFFER_OPERATION",
+		glow::OUT_OF_MEMORY => "GL_OUT_OF_MEMORY",
+		glow::STACK_UNDERFLOW => "GL_STACK_UNDERFLOW",
+		glow::STACK_OVERFLOW => "GL_STACK_OVERFLOW",
+		glow::CONTEXT_LOST => "GL_CONTEXT_LOST",
+		_ => return format!("error 0x{:04x}", error)
+	};
+	name.to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn objects_are_named_by_their_labels() {
+		assert_eq!(name(Some("albedo"), 3), "\"albedo\"");
+		assert_eq!(name(None, 12u32), "#12");
+	}
+
+	#[test]
+	fn errors_are_named() {
+		assert_eq!(error_name(glow::INVALID_OPERATION), "GL_INVALID_OPERATION");
+		assert_eq!(error_name(0x1234), "error 0x1234");
+	}
+}
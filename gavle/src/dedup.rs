@@ -0,0 +1,784 @@
+use std::any::Any;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::num::{NonZeroU8, NonZeroU32};
+use std::rc::{Rc, Weak};
+use smallvec::SmallVec;
+use crate::binding::{OwnedUniformBind, UniformBind, UniformGroup, UniformGroupDescriptor};
+use crate::pipeline::{ColorTargetState, CullMode, DepthStencilState, FrontFace, IndexFormat, OwnedVertexBufferLayout, PolygonMode, PrimitiveTopology, RenderPipelineDescriptor};
+use crate::texture::{AddressMode, TextureFilter, TextureViewDescriptor};
+
+/** Counters kept by the uniform group cache of a device, as returned by
+ * [`Device::uniform_group_cache_statistics()`].
+ *
+ * [`Device::uniform_group_cache_statistics()`]: crate::Device::uniform_group_cache_statistics */
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub struct GroupCacheStatistics {
+	/** Number of groups that were handed out from the cache. */
+	pub hits: u64,
+	/** Number of groups that had to be created. */
+	pub misses: u64,
+	/** Number of groups dropped from the cache, because either them or one
+	 * of the resources they bind died, or because they had an entry
+	 * replaced. */
+	pub evictions: u64,
+	/** Number of groups in the cache right now. */
+	pub cached: usize,
+}
+
+/** Counters kept by the render pipeline cache of a device, as returned by
+ * [`Device::render_pipeline_cache_statistics()`].
+ *
+ * [`Device::render_pipeline_cache_statistics()`]: crate::Device::render_pipeline_cache_statistics */
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub struct PipelineCacheStatistics {
+	/** Number of pipelines that were handed out from the cache. */
+	pub hits: u64,
+	/** Number of pipelines that had to be linked. */
+	pub misses: u64,
+	/** Number of pipelines dropped from the cache. */
+	pub evictions: u64,
+	/** Number of pipelines in the cache right now. */
+	pub cached: usize,
+}
+
+/** Stable FNV-1a hasher, whose output only depends on what gets written to
+ * it, unlike that of the default hasher of the standard library. */
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct StableHasher(u64);
+impl StableHasher {
+	pub(crate) fn new() -> Self {
+		Self(0xcbf2_9ce4_8422_2325)
+	}
+}
+impl Hasher for StableHasher {
+	fn finish(&self) -> u64 {
+		self.0
+	}
+	fn write(&mut self, bytes: &[u8]) {
+		for byte in bytes {
+			self.0 ^= u64::from(*byte);
+			self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+		}
+	}
+}
+
+/** Everything about an entry of a group, other than the resources it binds,
+ * that has to match for two groups to be the same. */
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub(crate) enum EntryParameters {
+	Buffer {
+		offset: u32,
+		size: Option<NonZeroU32>,
+	},
+	Texture {
+		far: TextureFilter,
+		near: TextureFilter,
+		address_mode: AddressMode,
+		anisotropy_clamp: Option<NonZeroU8>,
+		/** Range of the texture, for entries created from a view. */
+		view: Option<TextureViewDescriptor>,
+	},
+	TexelBuffer,
+}
+
+/** Entry of a group, as seen by the cache. */
+#[derive(Debug)]
+pub(crate) struct KeyEntry {
+	/** Name of the binding of the entry. */
+	pub(crate) binding: String,
+	/** Parameters the entry binds its resources with. */
+	pub(crate) parameters: EntryParameters,
+	/** Resources bound by the entry, in order. These are held weakly, which
+	 * keeps their allocations from being reused for other resources for as
+	 * long as the key is around, so two keys are only ever equal when they
+	 * refer to the very same resources. */
+	pub(crate) resources: SmallVec<[Weak<dyn Any>; 2]>,
+}
+impl PartialEq for KeyEntry {
+	fn eq(&self, other: &Self) -> bool {
+		self.binding == other.binding
+			&& self.parameters == other.parameters
+			&& self.resources.len() == other.resources.len()
+			&& self.resources.iter()
+				.zip(&other.resources)
+				.all(|(a, b)| a.as_ptr() as *const () == b.as_ptr() as *const ())
+	}
+}
+
+/** Key of a group in the cache, made of its entries, in order, and of the
+ * options it was created with. There are no options for groups yet, but they
+ * have to be part of the key once there are, so that groups differing in
+ * nothing but their options never get merged. */
+#[derive(Debug)]
+pub(crate) struct GroupKey {
+	/** Stable hash of the entries. */
+	hash: u64,
+	/** Entries of the group. */
+	entries: Vec<KeyEntry>,
+}
+impl GroupKey {
+	/** Key for a group with the given entries. */
+	pub(crate) fn new(entries: Vec<KeyEntry>) -> Self {
+		let mut hasher = StableHasher::new();
+		entries.len().hash(&mut hasher);
+		for entry in &entries {
+			entry.binding.hash(&mut hasher);
+			entry.parameters.hash(&mut hasher);
+			for resource in &entry.resources {
+				(resource.as_ptr() as *const () as usize).hash(&mut hasher);
+			}
+		}
+
+		Self { hash: hasher.finish(), entries }
+	}
+
+	/** Key for a group created from the given descriptor. */
+	pub(crate) fn of(descriptor: &UniformGroupDescriptor) -> Self {
+		fn weak<T: Any>(resource: &Rc<T>) -> Weak<dyn Any> {
+			Rc::downgrade(resource) as Weak<dyn Any>
+		}
+
+		let entries = descriptor.entries.iter()
+			.map(|entry| {
+				let mut resources = SmallVec::new();
+				let parameters = match &entry.kind {
+					UniformBind::Buffer { buffer, offset, size } => {
+						resources.push(weak(&buffer.inner));
+						EntryParameters::Buffer { offset: *offset, size: *size }
+					},
+					UniformBind::Texture {
+						texture,
+						far,
+						near,
+						address_mode,
+						anisotropy_clamp,
+						sampler } => {
+
+						resources.push(weak(&texture.inner));
+						resources.extend(sampler.map(|sampler| weak(&sampler.inner)));
+						EntryParameters::Texture {
+							far: *far,
+							near: *near,
+							address_mode: *address_mode,
+							anisotropy_clamp: *anisotropy_clamp,
+							view: None
+						}
+					},
+					UniformBind::TextureView {
+						view,
+						far,
+						near,
+						address_mode,
+						anisotropy_clamp,
+						sampler } => {
+
+						resources.push(weak(&view.texture.inner));
+						resources.extend(sampler.map(|sampler| weak(&sampler.inner)));
+						EntryParameters::Texture {
+							far: *far,
+							near: *near,
+							address_mode: *address_mode,
+							anisotropy_clamp: *anisotropy_clamp,
+							view: Some(view.descriptor)
+						}
+					},
+					UniformBind::TexelBuffer { buffer } => {
+						resources.push(weak(&buffer.inner));
+						EntryParameters::TexelBuffer
+					}
+				};
+
+				KeyEntry {
+					binding: entry.binding.to_string(),
+					parameters,
+					resources
+				}
+			})
+			.collect();
+
+		Self::new(entries)
+	}
+
+	/** Whether any of the resources in this key has been dropped. */
+	fn is_stale(&self) -> bool {
+		self.entries.iter()
+			.flat_map(|entry| entry.resources.iter())
+			.any(|resource| resource.strong_count() == 0)
+	}
+}
+impl PartialEq for GroupKey {
+	fn eq(&self, other: &Self) -> bool {
+		self.hash == other.hash && self.entries == other.entries
+	}
+}
+
+/** Group held by the cache. The group is held weakly, so that the cache never
+ * keeps a group, or the resources it binds, alive on its own. */
+#[derive(Debug)]
+struct CachedGroup {
+	/** Key the group was created with. */
+	key: GroupKey,
+	/** Entries of the group. */
+	entries: Weak<RefCell<Vec<(String, OwnedUniformBind)>>>,
+	/** Revision counter of the group. */
+	revision: Weak<Cell<u64>>,
+}
+impl CachedGroup {
+	/** A handle to the group, unless it has died, or has had an entry
+	 * replaced, in which case it no longer matches its key. */
+	fn upgrade(&self) -> Option<UniformGroup> {
+		if self.key.is_stale() { return None }
+
+		let group = UniformGroup {
+			entries: self.entries.upgrade()?,
+			revision: self.revision.upgrade()?
+		};
+		if group.revision.get() != 0 { return None }
+
+		Some(group)
+	}
+}
+
+/** Cache of the uniform groups created by a device, which hands out another
+ * handle to an existing group when one gets created from an identical
+ * descriptor, rather than creating a new group. */
+#[derive(Debug, Default)]
+pub(crate) struct GroupCache {
+	/** Whether groups get looked up in and added to the cache. */
+	enabled: Cell<bool>,
+	/** Cached groups, by the hash of their keys. */
+	groups: RefCell<HashMap<u64, Vec<CachedGroup>>>,
+	/** Counters of the cache. */
+	statistics: Cell<GroupCacheStatistics>,
+}
+impl GroupCache {
+	/** Whether the cache is being used. */
+	pub(crate) fn enabled(&self) -> bool {
+		self.enabled.get()
+	}
+
+	/** Starts or stops using the cache, emptying it when it gets turned off. */
+	pub(crate) fn set_enabled(&self, enabled: bool) {
+		self.enabled.set(enabled);
+		if !enabled {
+			self.groups.borrow_mut().clear();
+			self.update(|statistics| statistics.cached = 0);
+		}
+	}
+
+	/** Counters of the cache so far. */
+	pub(crate) fn statistics(&self) -> GroupCacheStatistics {
+		self.statistics.get()
+	}
+
+	fn update<F>(&self, f: F)
+		where F: FnOnce(&mut GroupCacheStatistics) {
+
+		let mut statistics = self.statistics.get();
+		f(&mut statistics);
+		self.statistics.set(statistics);
+	}
+
+	/** Looks up a live group with the given key, evicting every group in the
+	 * same bucket that is no longer usable along the way. */
+	pub(crate) fn get(&self, key: &GroupKey) -> Option<UniformGroup> {
+		let mut groups = self.groups.borrow_mut();
+		let bucket = groups.get_mut(&key.hash)?;
+
+		let before = bucket.len();
+		let mut found = None;
+		bucket.retain(|cached| match cached.upgrade() {
+			Some(group) => {
+				if found.is_none() && &cached.key == key {
+					found = Some(group)
+				}
+				true
+			},
+			None => false
+		});
+		let evicted = before - bucket.len();
+		if bucket.is_empty() {
+			groups.remove(&key.hash);
+		}
+		drop(groups);
+
+		self.update(|statistics| {
+			statistics.evictions += evicted as u64;
+			statistics.cached -= evicted;
+			if found.is_some() {
+				statistics.hits += 1;
+			}
+		});
+
+		found
+	}
+
+	/** Adds a newly created group to the cache, under the given key. */
+	pub(crate) fn insert(&self, key: GroupKey, group: &UniformGroup) {
+		self.groups.borrow_mut()
+			.entry(key.hash)
+			.or_insert_with(Vec::new)
+			.push(CachedGroup {
+				key,
+				entries: Rc::downgrade(&group.entries),
+				revision: Rc::downgrade(&group.revision)
+			});
+		self.update(|statistics| {
+			statistics.misses += 1;
+			statistics.cached += 1;
+		});
+	}
+}
+
+/** Everything about a pipeline, other than its shaders, that has to match for
+ * two pipelines to be the same. */
+#[derive(Debug, Eq, PartialEq, Hash)]
+pub(crate) struct PipelineParameters {
+	vertex_layout: OwnedVertexBufferLayout,
+	instance_layout: Option<OwnedVertexBufferLayout>,
+	topology: PrimitiveTopology,
+	index_format: IndexFormat,
+	front_face: FrontFace,
+	cull_mode: CullMode,
+	polygon_mode: PolygonMode,
+	/** Bits of the line width, as it has been asked for, rather than as it
+	 * got clamped. */
+	line_width: u32,
+	program_point_size: bool,
+	/** Color target states, for pipelines with a fragment stage. */
+	targets: Option<SmallVec<[ColorTargetState; 4]>>,
+	depth_stencil: Option<DepthStencilState>,
+}
+
+/** Key of a pipeline in the cache, made of its shaders, by the identity of
+ * their shared inner objects, and of the rest of its description. */
+#[derive(Debug)]
+pub(crate) struct PipelineKey {
+	/** Stable hash of the shaders and the parameters. */
+	hash: u64,
+	/** Vertex shader, followed by the fragment shader, if any. These are held
+	 * weakly, as the pipelines in the cache keep them alive already. */
+	shaders: SmallVec<[Weak<dyn Any>; 2]>,
+	/** Everything else about the pipeline. */
+	parameters: PipelineParameters,
+}
+impl PipelineKey {
+	/** Key for a pipeline with the given shaders and parameters. */
+	pub(crate) fn new(
+		shaders: SmallVec<[Weak<dyn Any>; 2]>,
+		parameters: PipelineParameters) -> Self {
+
+		let mut hasher = StableHasher::new();
+		shaders.len().hash(&mut hasher);
+		for shader in &shaders {
+			(shader.as_ptr() as *const () as usize).hash(&mut hasher);
+		}
+		parameters.hash(&mut hasher);
+
+		Self { hash: hasher.finish(), shaders, parameters }
+	}
+
+	/** Key for a pipeline created from the given descriptor. */
+	pub(crate) fn of(descriptor: &RenderPipelineDescriptor) -> Self {
+		let mut shaders = SmallVec::new();
+		shaders.push(Rc::downgrade(&descriptor.vertex.shader.inner) as Weak<dyn Any>);
+		if let Some(fragment) = &descriptor.fragment {
+			shaders.push(Rc::downgrade(&fragment.shader.inner) as Weak<dyn Any>);
+		}
+
+		let primitive = &descriptor.primitive_state;
+		Self::new(shaders, PipelineParameters {
+			vertex_layout: From::from(descriptor.vertex.buffer),
+			instance_layout: descriptor.vertex.instance.map(From::from),
+			topology: primitive.topology,
+			index_format: primitive.index_format,
+			front_face: primitive.front_face,
+			cull_mode: primitive.cull_mode,
+			polygon_mode: primitive.polygon_mode,
+			line_width: primitive.line_width.to_bits(),
+			program_point_size: primitive.program_point_size,
+			targets: descriptor.fragment.as_ref()
+				.map(|fragment| fragment.targets.iter().copied().collect()),
+			depth_stencil: descriptor.depth_stencil
+		})
+	}
+}
+impl PartialEq for PipelineKey {
+	fn eq(&self, other: &Self) -> bool {
+		self.hash == other.hash
+			&& self.parameters == other.parameters
+			&& self.shaders.len() == other.shaders.len()
+			&& self.shaders.iter()
+				.zip(&other.shaders)
+				.all(|(a, b)| a.as_ptr() as *const () == b.as_ptr() as *const ())
+	}
+}
+
+/** Pipeline held by the cache, along with the key it was created with. */
+#[derive(Debug)]
+struct CachedPipeline<P> {
+	key: PipelineKey,
+	pipeline: Rc<P>,
+}
+
+/** Cache of the render pipelines created by a device, which hands out another
+ * handle to an existing pipeline when one gets created from an identical
+ * descriptor, rather than linking a new program.
+ *
+ * Unlike uniform groups, pipelines are held strongly, so that dropping every
+ * handle to a pipeline between creating them doesn't defeat the cache. They
+ * stay around until they get evicted. */
+#[derive(Debug)]
+pub(crate) struct PipelineCache<P> {
+	/** Whether pipelines get looked up in and added to the cache. */
+	enabled: Cell<bool>,
+	/** Cached pipelines, by the hash of their keys. */
+	pipelines: RefCell<HashMap<u64, Vec<CachedPipeline<P>>>>,
+	/** Counters of the cache. */
+	statistics: Cell<PipelineCacheStatistics>,
+}
+impl<P> Default for PipelineCache<P> {
+	fn default() -> Self {
+		Self {
+			enabled: Default::default(),
+			pipelines: Default::default(),
+			statistics: Default::default()
+		}
+	}
+}
+impl<P> PipelineCache<P> {
+	/** Whether the cache is being used. */
+	pub(crate) fn enabled(&self) -> bool {
+		self.enabled.get()
+	}
+
+	/** Starts or stops using the cache, emptying it when it gets turned off. */
+	pub(crate) fn set_enabled(&self, enabled: bool) {
+		self.enabled.set(enabled);
+		if !enabled {
+			self.pipelines.borrow_mut().clear();
+			self.update(|statistics| statistics.cached = 0);
+		}
+	}
+
+	/** Counters of the cache so far. */
+	pub(crate) fn statistics(&self) -> PipelineCacheStatistics {
+		self.statistics.get()
+	}
+
+	fn update<F>(&self, f: F)
+		where F: FnOnce(&mut PipelineCacheStatistics) {
+
+		let mut statistics = self.statistics.get();
+		f(&mut statistics);
+		self.statistics.set(statistics);
+	}
+
+	/** Looks up the pipeline with the given key. */
+	pub(crate) fn get(&self, key: &PipelineKey) -> Option<Rc<P>> {
+		let found = self.pipelines.borrow()
+			.get(&key.hash)?
+			.iter()
+			.find(|cached| &cached.key == key)
+			.map(|cached| cached.pipeline.clone())?;
+
+		self.update(|statistics| statistics.hits += 1);
+		Some(found)
+	}
+
+	/** Adds a newly linked pipeline to the cache, under the given key. */
+	pub(crate) fn insert(&self, key: PipelineKey, pipeline: &Rc<P>) {
+		self.pipelines.borrow_mut()
+			.entry(key.hash)
+			.or_default()
+			.push(CachedPipeline {
+				key,
+				pipeline: pipeline.clone()
+			});
+		self.update(|statistics| {
+			statistics.misses += 1;
+			statistics.cached += 1;
+		});
+	}
+
+	/** Drops every pipeline for which the given function returns true, and
+	 * returns how many of them got dropped. */
+	fn evict_where<F>(&self, mut f: F) -> usize
+		where F: FnMut(&Rc<P>) -> bool {
+
+		let mut evicted = 0;
+		self.pipelines.borrow_mut().retain(|_, bucket| {
+			let before = bucket.len();
+			bucket.retain(|cached| !f(&cached.pipeline));
+			evicted += before - bucket.len();
+
+			!bucket.is_empty()
+		});
+
+		self.update(|statistics| {
+			statistics.evictions += evicted as u64;
+			statistics.cached -= evicted;
+		});
+		evicted
+	}
+
+	/** Drops the given pipeline from the cache, returning whether it was in
+	 * it. Handles to it given out before stay valid. */
+	pub(crate) fn evict(&self, pipeline: &Rc<P>) -> bool {
+		self.evict_where(|cached| Rc::ptr_eq(cached, pipeline)) > 0
+	}
+
+	/** Drops every pipeline nothing but the cache holds a handle to, and
+	 * returns how many of them got dropped. */
+	pub(crate) fn evict_unused(&self) -> usize {
+		self.evict_where(|cached| Rc::strong_count(cached) == 1)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn entry(binding: &str, parameters: EntryParameters, resources: &[&Rc<u32>])
+		-> KeyEntry {
+
+		KeyEntry {
+			binding: binding.to_owned(),
+			parameters,
+			resources: resources.iter()
+				.map(|resource| Rc::downgrade(resource) as Weak<dyn Any>)
+				.collect()
+		}
+	}
+
+	fn texture(far: TextureFilter) -> EntryParameters {
+		EntryParameters::Texture {
+			far,
+			near: TextureFilter::Linear,
+			address_mode: AddressMode::Repeat,
+			anisotropy_clamp: None,
+			view: None
+		}
+	}
+
+	const BUFFER: EntryParameters = EntryParameters::Buffer {
+		offset: 0,
+		size: None
+	};
+
+	fn group() -> UniformGroup {
+		UniformGroup {
+			entries: Rc::new(RefCell::new(Vec::new())),
+			revision: Rc::new(Cell::new(0))
+		}
+	}
+
+	#[test]
+	fn hashing_is_stable() {
+		let mut hasher = StableHasher::new();
+		hasher.write(b"a");
+		assert_eq!(hasher.finish(), 0xaf63_dc4c_8601_ec8c);
+
+		let (a, b) = (Rc::new(0), Rc::new(1));
+		let key = || GroupKey::new(vec![
+			entry("albedo", texture(TextureFilter::Linear), &[&a]),
+			entry("Camera", BUFFER, &[&b]),
+		]);
+		assert_eq!(key().hash, key().hash);
+		assert_eq!(key(), key());
+
+		/* Names, parameters, resources and order all matter. */
+		let others = vec![
+			GroupKey::new(vec![
+				entry("albedo", texture(TextureFilter::Nearest), &[&a]),
+				entry("Camera", BUFFER, &[&b]),
+			]),
+			GroupKey::new(vec![
+				entry("normals", texture(TextureFilter::Linear), &[&a]),
+				entry("Camera", BUFFER, &[&b]),
+			]),
+			GroupKey::new(vec![
+				entry("albedo", texture(TextureFilter::Linear), &[&b]),
+				entry("Camera", BUFFER, &[&b]),
+			]),
+			GroupKey::new(vec![
+				entry("albedo", texture(TextureFilter::Linear), &[&a, &b]),
+				entry("Camera", BUFFER, &[&b]),
+			]),
+			GroupKey::new(vec![
+				entry("Camera", BUFFER, &[&b]),
+				entry("albedo", texture(TextureFilter::Linear), &[&a]),
+			]),
+		];
+		for other in &others {
+			assert_ne!(other.hash, key().hash);
+			assert_ne!(other, &key());
+		}
+	}
+
+	#[test]
+	fn identical_keys_share_a_group() {
+		let cache = GroupCache::default();
+		let buffer = Rc::new(0);
+		let key = || GroupKey::new(vec![entry("Camera", BUFFER, &[&buffer])]);
+
+		assert!(cache.get(&key()).is_none());
+		let created = group();
+		cache.insert(key(), &created);
+
+		let shared = cache.get(&key()).unwrap();
+		assert!(Rc::ptr_eq(&shared.entries, &created.entries));
+		assert_eq!(cache.statistics(), GroupCacheStatistics {
+			hits: 1,
+			misses: 1,
+			evictions: 0,
+			cached: 1
+		});
+	}
+
+	#[test]
+	fn dead_and_modified_groups_get_evicted() {
+		let cache = GroupCache::default();
+		let (a, b) = (Rc::new(0), Rc::new(1));
+		let key = |resource: &Rc<u32>| GroupKey::new(vec![
+			entry("Camera", BUFFER, &[resource])
+		]);
+
+		/* Dropping the group drops it from the cache. */
+		let dropped = group();
+		cache.insert(key(&a), &dropped);
+		drop(dropped);
+		assert!(cache.get(&key(&a)).is_none());
+
+		/* Replacing an entry makes the group stop matching its key. */
+		let replaced = group();
+		cache.insert(key(&a), &replaced);
+		replaced.revision.set(1);
+		assert!(cache.get(&key(&a)).is_none());
+
+		/* So does dropping one of its resources, which the group would
+		 * normally keep alive. */
+		let orphaned = group();
+		cache.insert(key(&b), &orphaned);
+		let stale = key(&b);
+		drop(b);
+		assert!(cache.get(&stale).is_none());
+
+		let statistics = cache.statistics();
+		assert_eq!(statistics.evictions, 3);
+		assert_eq!(statistics.cached, 0);
+		assert_eq!(statistics.hits, 0);
+	}
+
+	#[test]
+	fn turning_the_cache_off_empties_it() {
+		let cache = GroupCache::default();
+		cache.set_enabled(true);
+		let buffer = Rc::new(0);
+		let key = || GroupKey::new(vec![entry("Camera", BUFFER, &[&buffer])]);
+
+		let created = group();
+		cache.insert(key(), &created);
+		cache.set_enabled(false);
+		assert!(!cache.enabled());
+		assert!(cache.get(&key()).is_none());
+		assert_eq!(cache.statistics().cached, 0);
+	}
+
+	fn parameters(cull_mode: CullMode) -> PipelineParameters {
+		PipelineParameters {
+			vertex_layout: OwnedVertexBufferLayout {
+				array_stride: 12,
+				attributes: Vec::new()
+			},
+			instance_layout: None,
+			topology: PrimitiveTopology::TriangleList,
+			index_format: IndexFormat::Uint16,
+			front_face: FrontFace::Ccw,
+			cull_mode,
+			polygon_mode: PolygonMode::Fill,
+			line_width: 1.0f32.to_bits(),
+			program_point_size: false,
+			targets: Some(std::iter::once(ColorTargetState::default()).collect()),
+			depth_stencil: None
+		}
+	}
+
+	fn shaders(shaders: &[&Rc<u32>]) -> SmallVec<[Weak<dyn Any>; 2]> {
+		shaders.iter()
+			.map(|shader| Rc::downgrade(shader) as Weak<dyn Any>)
+			.collect()
+	}
+
+	#[test]
+	fn identical_descriptors_share_a_pipeline() {
+		let cache = PipelineCache::default();
+		let (vertex, fragment) = (Rc::new(0), Rc::new(1));
+		let key = || PipelineKey::new(
+			shaders(&[&vertex, &fragment]),
+			parameters(CullMode::None));
+
+		/* Only the first of many identical pipelines gets linked, even with
+		 * every handle to it dropped in between. */
+		let mut linked = 0;
+		for _ in 0..100 {
+			let key = key();
+			let pipeline = match cache.get(&key) {
+				Some(pipeline) => pipeline,
+				None => {
+					linked += 1;
+					let pipeline = Rc::new(linked);
+					cache.insert(key, &pipeline);
+					pipeline
+				}
+			};
+			assert_eq!(*pipeline, 1);
+		}
+		assert_eq!(linked, 1);
+		assert_eq!(cache.statistics(), PipelineCacheStatistics {
+			hits: 99,
+			misses: 1,
+			evictions: 0,
+			cached: 1
+		});
+
+		/* Shaders, their order and the rest of the state all matter. */
+		let others = vec![
+			PipelineKey::new(shaders(&[&fragment, &vertex]), parameters(CullMode::None)),
+			PipelineKey::new(shaders(&[&vertex]), parameters(CullMode::None)),
+			PipelineKey::new(shaders(&[&vertex, &fragment]), parameters(CullMode::Back)),
+		];
+		for other in &others {
+			assert_ne!(other, &key());
+			assert!(cache.get(other).is_none());
+		}
+	}
+
+	#[test]
+	fn pipelines_stay_until_evicted() {
+		let cache = PipelineCache::default();
+		let (a, b) = (Rc::new(0), Rc::new(1));
+		let key = |shader: &Rc<u32>| PipelineKey::new(
+			shaders(&[shader]),
+			parameters(CullMode::None));
+
+		let used = Rc::new(());
+		cache.insert(key(&a), &used);
+		cache.insert(key(&b), &Rc::new(()));
+
+		/* Only the pipeline nothing else holds gets dropped. */
+		assert_eq!(cache.evict_unused(), 1);
+		assert!(cache.get(&key(&a)).is_some());
+		assert!(cache.get(&key(&b)).is_none());
+
+		assert!(cache.evict(&used));
+		assert!(!cache.evict(&used));
+		assert!(cache.get(&key(&a)).is_none());
+
+		let statistics = cache.statistics();
+		assert_eq!(statistics.evictions, 2);
+		assert_eq!(statistics.cached, 0);
+	}
+}
@@ -0,0 +1,530 @@
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::convert::TryFrom;
+use std::ops::Range;
+use std::rc::{Rc, Weak};
+use glow::{Context, HasContext};
+use crate::{Device, Information};
+use crate::access::AccessLock;
+use crate::binding::UniformGroup;
+use crate::buffer::{Buffer, BufferDescriptor, BufferError, BufferUsage, VertexBuffer};
+use crate::debug::Debugger;
+use crate::lock::LockOperation;
+use crate::pass::{DrawError, instance_count, vertex_draw_range};
+use crate::pipeline::*;
+use crate::reflection::{shader_type, ShaderScalar};
+use crate::shader::ShaderSource;
+use crate::texture::InnerTexture;
+use crate::usage::UsageTracker;
+
+/** Fragment shader that does nothing at all. Fragments never make it out of
+ * the rasterizer during transform feedback, but OpenGL ES still won't link a
+ * program that has no fragment stage. */
+const DISCARD_FRAGMENT_SHADER: &str = "#version 300 es
+void main() {}
+";
+
+/** Mode transform feedback gets begun in for the given topology, along with
+ * the number of vertices making up each of its primitives. Only lists can have
+ * their outputs captured, since OpenGL ES has no way to capture strips. */
+fn feedback_primitive(topology: PrimitiveTopology) -> Option<(u32, u32)> {
+	match topology {
+		PrimitiveTopology::PointList => Some((glow::POINTS, 1)),
+		PrimitiveTopology::LineList => Some((glow::LINES, 2)),
+		PrimitiveTopology::TriangleList => Some((glow::TRIANGLES, 3)),
+		_ => None
+	}
+}
+
+/** Number of bytes taken up by every vertex captured from outputs of the given
+ * types and array sizes, interleaved in that order, or `None` if any of them
+ * can't be captured or the total doesn't fit in a `u32`. */
+fn feedback_stride<I>(outputs: I) -> Option<u32>
+	where I: IntoIterator<Item = (u32, i32)> {
+
+	outputs.into_iter().try_fold(0u32, |stride, (kind, size)| {
+		let (scalar, components, columns) = shader_type(kind)?;
+		if scalar == ShaderScalar::Bool { return None }
+		let size = u32::try_from(size).ok().filter(|size| *size > 0)?;
+
+		4u32.checked_mul(components.count())?
+			.checked_mul(columns)?
+			.checked_mul(size)
+			.and_then(|len| stride.checked_add(len))
+	})
+}
+
+/** Number of vertices captured by a draw of the given number of vertices for
+ * the given number of instances, with primitives of the given number of
+ * vertices. Vertices left over from an incomplete primitive don't get captured.
+ */
+fn captured_vertices(count: u32, per_primitive: u32, instances: u32) -> Option<u32> {
+	(count - count % per_primitive).checked_mul(instances)
+}
+
+/** Description of a pipeline that captures the outputs of its vertex stage
+ * into a buffer, rather than rasterizing them. */
+pub struct TransformFeedbackPipelineDescriptor<'a> {
+	/** Description of the vertex processing stage of this pipeline. */
+	pub vertex: VertexState<'a>,
+	/** Names of the outputs of the vertex stage to be captured, which get
+	 * written to the output buffer interleaved, in the order they are given
+	 * in here, with no padding between them. */
+	pub varyings: &'a [&'a str],
+	/** Topology of the primitives being drawn. This may only be one of
+	 * [`PrimitiveTopology::PointList`], [`PrimitiveTopology::LineList`] or
+	 * [`PrimitiveTopology::TriangleList`]. */
+	pub topology: PrimitiveTopology,
+}
+
+/** A pipeline that captures the outputs of its vertex stage into a buffer,
+ * created with [`Device::create_transform_feedback_pipeline`].
+ *
+ * [`Device::create_transform_feedback_pipeline`]: crate::Device::create_transform_feedback_pipeline */
+pub struct TransformFeedbackPipeline {
+	/** The render pipeline doing the actual work. */
+	pub(crate) pipeline: RenderPipeline,
+	/** Mode transform feedback gets begun in. */
+	mode: u32,
+	/** Number of vertices in each primitive being captured. */
+	per_primitive: u32,
+	/** Number of bytes taken up by every captured vertex. */
+	stride: u32,
+}
+impl TransformFeedbackPipeline {
+	/** Number of bytes taken up by every vertex captured by this pipeline. */
+	pub fn stride(&self) -> u32 {
+		self.stride
+	}
+
+	/** The label used to refer to this pipeline in diagnostics, if any. */
+	pub fn label(&self) -> Option<String> {
+		self.pipeline.label()
+	}
+
+	/** Changes the label used to refer to this pipeline in diagnostics. */
+	pub fn set_label(&self, label: &str) {
+		self.pipeline.set_label(label)
+	}
+}
+
+/** Parameters a [`TransformFeedbackPass`] gets started with. */
+pub struct TransformFeedbackPassDescriptor<'a> {
+	/** Pipeline used to capture outputs in this pass. */
+	pub pipeline: &'a TransformFeedbackPipeline,
+	/** Buffer the outputs get captured into, which must have the
+	 * [`BufferUsage::TRANSFORM_FEEDBACK`] usage. */
+	pub output: &'a Buffer,
+}
+
+/** A pass capturing the outputs of the vertex stage into a buffer. Draws in
+ * the same pass get written to the buffer one after the other, starting at
+ * its beginning, and nothing gets rasterized for as long as the pass is alive.
+ *
+ * Just like render passes, these hold the pipeline lock, so no other pass may
+ * be started until this one is dropped. */
+pub struct TransformFeedbackPass<'a> {
+	/** Shared graphics context. */
+	context: Rc<Context>,
+	/** Shared context information. */
+	information: Rc<Information>,
+	/** Record of the frames in which textures get bound. */
+	usage: Rc<RefCell<UsageTracker<Weak<InnerTexture>>>>,
+	/** Shared error checking state. */
+	debug: Rc<Debugger>,
+	/** Global graphics state lock. */
+	_lock: crate::lock::PipelineGuard<'a>,
+	/** Pipeline used in this pass. */
+	pipeline: &'a TransformFeedbackPipeline,
+	/** Buffer the outputs get captured into. */
+	output: &'a Buffer,
+	/** Reference to a vertex buffer, if any. */
+	vertex: Option<&'a Buffer>,
+	/** Reference to an instance buffer, if any. */
+	instance: Option<&'a Buffer>,
+	/** Reference to a uniform binding group, if any. */
+	bind: Option<&'a UniformGroup>,
+	/** Number of bytes captured into the output buffer so far. */
+	written: u32,
+}
+impl<'a> TransformFeedbackPass<'a> {
+	/** Sets the buffer the vertex attributes of the pipeline are read from.
+	 *
+	 * # Panic
+	 * This function will panic if the usage of the buffer doesn't include
+	 * [`BufferUsage::VERTEX`]. */
+	pub fn set_vertex_buffer(&mut self, buffer: &'a Buffer) {
+		buffer.assert_usage(BufferUsage::VERTEX, "use vertex data from");
+		self.vertex = Some(buffer);
+	}
+
+	/** Sets the buffer the per-instance attributes of the pipeline are read
+	 * from.
+	 *
+	 * # Panic
+	 * This function will panic if the usage of the buffer doesn't include
+	 * [`BufferUsage::VERTEX`]. */
+	pub fn set_instance_buffer(&mut self, buffer: &'a Buffer) {
+		buffer.assert_usage(BufferUsage::VERTEX, "use instance data from");
+		self.instance = Some(buffer);
+	}
+
+	/** Sets the uniform bind group to be used for the draws in this pass. */
+	pub fn set_bind_group(&mut self, group: &'a UniformGroup) {
+		self.bind = Some(group);
+	}
+
+	/** Number of bytes captured into the output buffer so far, which is also
+	 * where the outputs of the next draw will start at. */
+	pub fn written(&self) -> u32 {
+		self.written
+	}
+
+	/** Number of vertices captured into the output buffer so far. */
+	pub fn captured(&self) -> u32 {
+		self.written / self.pipeline.stride
+	}
+
+	/** Draws the given range of vertices for the given number of instances,
+	 * capturing their outputs into the output buffer, right after those of the
+	 * draws that came before it.
+	 *
+	 * # Errors
+	 * This function fails if the outputs don't fit in what is left of the
+	 * output buffer, or if the output buffer is also being read from. */
+	pub fn draw(&mut self, vertices: Range<u32>, instances: u32) -> Result<(), DrawError> {
+		let inner = &self.pipeline.pipeline.inner;
+		if self.vertex.is_none() && !inner.vertex_layout.attributes.is_empty() {
+			return Err(DrawError::MissingVertexBuffer)
+		}
+		let instanced = inner.instance_layout.as_ref()
+			.map(|layout| !layout.attributes.is_empty())
+			.unwrap_or(false);
+		if self.instance.is_none() && instanced {
+			return Err(DrawError::MissingInstanceBuffer)
+		}
+		let (first, count) = vertex_draw_range(&vertices)?;
+		let instance_total = instance_count(instances)?;
+
+		let read_back = self.vertex.iter()
+			.chain(self.instance.iter())
+			.any(|buffer| Rc::ptr_eq(&buffer.inner, &self.output.inner));
+		if read_back {
+			return Err(DrawError::InvalidFeedbackOutput {
+				what: "the output buffer is also being read from".to_owned()
+			})
+		}
+
+		/* Work out where the outputs of this draw go in the output buffer. */
+		let size = captured_vertices(
+				vertices.end - vertices.start,
+				self.pipeline.per_primitive,
+				instances)
+			.and_then(|captured| captured.checked_mul(self.pipeline.stride));
+		let end = size.and_then(|size| self.written.checked_add(size));
+		let range = match (size, end) {
+			(Some(size), Some(end)) if end <= self.output.len() =>
+				i32::try_from(self.written).ok()
+					.zip(i32::try_from(size).ok())
+					.map(|range| (range, end)),
+			_ => None
+		};
+		let ((offset, size), end) = range.ok_or_else(||
+			DrawError::InvalidFeedbackOutput {
+				what: format!("the outputs don't fit in the {} bytes left of \
+					the output buffer",
+					self.output.len() - self.written)
+			})?;
+		if size == 0 { return Ok(()) }
+
+		let _atoms = (
+			self.pipeline.pipeline.acquire_read_guarded(),
+			self.vertex.as_ref().map(|buffer| buffer.acquire_read_guarded()),
+			self.instance.as_ref().map(|buffer| buffer.acquire_read_guarded()),
+			self.bind.as_ref().map(|bind| bind.acquire_read_guarded()),
+			self.output.acquire_write_guarded());
+
+		unsafe {
+			let gl = self.context.as_ref();
+			self.pipeline.pipeline.bind(gl);
+
+			if let Some(binder) = &self.bind {
+				binder.bind(gl, &self.information, &inner.program, &[]);
+
+				let mut usage = self.usage.borrow_mut();
+				if usage.enabled() {
+					binder.record_usage(&mut usage);
+				}
+			}
+
+			let vertex = self.vertex.map(|vertex| vertex.as_raw_handle());
+			gl.bind_buffer(glow::ARRAY_BUFFER, vertex);
+			self.pipeline.pipeline.vertex_array_setup(
+				gl,
+				&self.information,
+				self.vertex,
+				self.instance,
+				None);
+			self.check("setting up");
+
+			gl.bind_buffer_range(
+				glow::TRANSFORM_FEEDBACK_BUFFER,
+				0,
+				Some(self.output.as_raw_handle()),
+				offset,
+				size);
+			gl.begin_transform_feedback(self.pipeline.mode);
+			gl.draw_arrays_instanced(
+				self.pipeline.pipeline.drawing_mode(),
+				first,
+				count,
+				instance_total);
+			gl.end_transform_feedback();
+			self.check("capturing outputs with");
+		}
+
+		self.written = end;
+		Ok(())
+	}
+
+	/** Checks for errors raised by the given operation in this pass. */
+	unsafe fn check(&self, operation: &str) {
+		self.debug.check(
+			&self.context,
+			operation,
+			|| format!("pipeline {} on buffer {}",
+				self.pipeline.pipeline.name(),
+				crate::debug::name(
+					self.output.label().as_deref(),
+					self.output.as_raw_handle())))
+	}
+}
+impl<'a> Drop for TransformFeedbackPass<'a> {
+	fn drop(&mut self) {
+		unsafe {
+			let gl = self.context.as_ref();
+			gl.bind_buffer_base(glow::TRANSFORM_FEEDBACK_BUFFER, 0, None);
+			gl.disable(glow::RASTERIZER_DISCARD);
+		}
+	}
+}
+
+/** A pair of vertex buffers that take turns at being read from and captured
+ * into, so that the outputs of one transform feedback pass become the inputs
+ * of the next, as in particle simulations. Both buffers have the
+ * [`BufferUsage::VERTEX`] and [`BufferUsage::TRANSFORM_FEEDBACK`] usages. */
+#[derive(Debug)]
+pub struct FeedbackBuffers {
+	/** The two buffers. */
+	buffers: [VertexBuffer; 2],
+	/** Index of the buffer currently being read from. */
+	input: usize,
+}
+impl FeedbackBuffers {
+	/** The buffer to be read from in the current pass. */
+	pub fn input(&self) -> &VertexBuffer {
+		&self.buffers[self.input]
+	}
+
+	/** The buffer to be captured into in the current pass. */
+	pub fn output(&self) -> &VertexBuffer {
+		&self.buffers[1 - self.input]
+	}
+
+	/** Swaps the buffers around, making the output of the last pass the input
+	 * of the next one. */
+	pub fn swap(&mut self) {
+		self.input = 1 - self.input
+	}
+}
+
+impl Device {
+	/** Creates a new pipeline that captures the given outputs of its vertex
+	 * stage into a buffer, for use in a [`TransformFeedbackPass`].
+	 *
+	 * # Errors
+	 * This function fails if the context lacks the [`transform_feedback`]
+	 * capability, if no outputs are given, if any of them is given twice or
+	 * doesn't exist, or if the topology isn't a list of primitives, along with
+	 * all of the ways [`create_render_pipeline()`] fails.
+	 *
+	 * [`transform_feedback`]: crate::Capabilities::transform_feedback
+	 * [`create_render_pipeline()`]: Self::create_render_pipeline */
+	pub fn create_transform_feedback_pipeline(
+		&self,
+		descriptor: &TransformFeedbackPipelineDescriptor)
+		-> Result<TransformFeedbackPipeline, RenderPipelineError> {
+
+		if !self.information.capabilities.transform_feedback {
+			return Err(RenderPipelineError::UnsupportedFeature {
+				what: format!("{} has no support for transform feedback",
+					self.information.version)
+			})
+		}
+		if descriptor.varyings.is_empty() {
+			return Err(RenderPipelineError::InvalidFeedback {
+				what: "at least one output must be captured".to_owned()
+			})
+		}
+		for (i, varying) in descriptor.varyings.iter().enumerate() {
+			if descriptor.varyings[..i].contains(varying) {
+				return Err(RenderPipelineError::InvalidFeedback {
+					what: format!("output \"{}\" is captured more than once",
+						varying)
+				})
+			}
+		}
+		let (mode, per_primitive) = feedback_primitive(descriptor.topology)
+			.ok_or_else(|| RenderPipelineError::InvalidFeedback {
+				what: format!("outputs can't be captured from primitives of \
+					the {:?} topology",
+					descriptor.topology)
+			})?;
+
+		let fragment = self.create_fragment_shader(
+			ShaderSource::Glsl(Cow::Borrowed(DISCARD_FRAGMENT_SHADER)))
+			.map_err(|what| RenderPipelineError::ProgramCreationFailed {
+				what: what.to_string()
+			})?;
+		let pipeline = self.create_pipeline(
+			&RenderPipelineDescriptor {
+				vertex: VertexState {
+					shader: descriptor.vertex.shader,
+					buffer: descriptor.vertex.buffer,
+					instance: descriptor.vertex.instance
+				},
+				primitive_state: PrimitiveState {
+					topology: descriptor.topology,
+					..Default::default()
+				},
+				fragment: Some(FragmentState::new(&fragment).with_targets(&[])),
+				depth_stencil: None
+			},
+			descriptor.varyings)?;
+
+		/* Linking already fails for outputs that don't exist, so all that is
+		 * left is to see how much room the ones that do take up. */
+		let outputs = unsafe {
+			let _atom = self.pipeline_lock.acquire(LockOperation::PipelineCreation);
+			let gl = self.context.as_ref();
+			(0..descriptor.varyings.len())
+				.map(|index| gl.get_transform_feedback_varying(
+					pipeline.inner.program.program,
+					index as u32))
+				.collect::<Option<Vec<_>>>()
+		};
+		let stride = outputs
+			.and_then(|outputs| feedback_stride(outputs.iter()
+				.map(|output| (output.tftype, output.size))))
+			.ok_or_else(|| RenderPipelineError::InvalidFeedback {
+				what: format!("the outputs {:?} can't all be captured",
+					descriptor.varyings)
+			})?;
+
+		Ok(TransformFeedbackPipeline {
+			pipeline,
+			mode,
+			per_primitive,
+			stride
+		})
+	}
+
+	/** Lock the render pipeline and start a new transform feedback pass from
+	 * the given parameters.
+	 *
+	 * # Panic
+	 * This function will panic if the usage of the output buffer doesn't
+	 * include [`BufferUsage::TRANSFORM_FEEDBACK`], or if the pipeline is
+	 * already locked, as is the case while a render pass is running. */
+	pub fn start_transform_feedback_pass<'a>(
+		&'a self,
+		descriptor: &TransformFeedbackPassDescriptor<'a>)
+		-> TransformFeedbackPass<'a> {
+
+		descriptor.output.assert_usage(
+			BufferUsage::TRANSFORM_FEEDBACK,
+			"capture outputs into");
+		let lock = self.pipeline_lock.acquire_labeled(
+			LockOperation::TransformFeedbackPass,
+			descriptor.pipeline.pipeline.shared_label());
+		unsafe {
+			self.context.enable(glow::RASTERIZER_DISCARD);
+		}
+
+		TransformFeedbackPass {
+			context: self.context.clone(),
+			information: self.information.clone(),
+			usage: self.usage.clone(),
+			debug: self.debug.clone(),
+			_lock: lock,
+			pipeline: descriptor.pipeline,
+			output: descriptor.output,
+			vertex: None,
+			instance: None,
+			bind: None,
+			written: 0
+		}
+	}
+
+	/** Tries to create a pair of buffers for use with transform feedback, as
+	 * described by the given descriptor, with the first input holding the
+	 * given data, which must be as long as the size in the descriptor.
+	 *
+	 * # Errors
+	 * This function fails in all of the ways [`create_buffer_with_data()`]
+	 * does, and in legacy contexts, which have no transform feedback.
+	 *
+	 * [`create_buffer_with_data()`]: Self::create_buffer_with_data */
+	pub fn create_feedback_buffers<A: AsRef<[u8]>>(
+		&self,
+		descriptor: &BufferDescriptor,
+		data: A)
+		-> Result<FeedbackBuffers, BufferError> {
+
+		let descriptor = BufferDescriptor {
+			usage: descriptor.usage | BufferUsage::TRANSFORM_FEEDBACK,
+			..*descriptor
+		};
+		let input = self.create_vertex_buffer_with_data(&descriptor, data)?;
+		let output = self.create_vertex_buffer(&descriptor)?;
+		if let Some(label) = input.label() {
+			output.set_label(&label);
+		}
+
+		Ok(FeedbackBuffers {
+			buffers: [input, output],
+			input: 0
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn strides_add_up_every_captured_output() {
+		assert_eq!(feedback_stride(vec![]), Some(0));
+		assert_eq!(feedback_stride(vec![(glow::FLOAT_VEC3, 1), (glow::FLOAT, 1)]), Some(16));
+		assert_eq!(feedback_stride(vec![(glow::FLOAT_MAT4, 1)]), Some(64));
+		assert_eq!(feedback_stride(vec![(glow::UNSIGNED_INT_VEC2, 3)]), Some(24));
+
+		assert_eq!(feedback_stride(vec![(glow::BOOL, 1)]), None);
+		assert_eq!(feedback_stride(vec![(glow::SAMPLER_2D, 1)]), None);
+		assert_eq!(feedback_stride(vec![(glow::FLOAT, 0)]), None);
+		assert_eq!(feedback_stride(vec![(glow::FLOAT_VEC4, i32::MAX)]), None);
+	}
+
+	#[test]
+	fn only_whole_primitives_get_captured() {
+		assert_eq!(feedback_primitive(PrimitiveTopology::PointList), Some((glow::POINTS, 1)));
+		assert_eq!(feedback_primitive(PrimitiveTopology::TriangleList), Some((glow::TRIANGLES, 3)));
+		assert_eq!(feedback_primitive(PrimitiveTopology::LineStrip), None);
+		assert_eq!(feedback_primitive(PrimitiveTopology::TriangleStrip), None);
+
+		assert_eq!(captured_vertices(7, 1, 2), Some(14));
+		assert_eq!(captured_vertices(7, 2, 1), Some(6));
+		assert_eq!(captured_vertices(7, 3, 2), Some(12));
+		assert_eq!(captured_vertices(u32::MAX, 1, 2), None);
+	}
+}
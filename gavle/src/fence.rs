@@ -0,0 +1,69 @@
+use glow::{Context, HasContext};
+use std::rc::Rc;
+use std::time::Duration;
+use std::convert::TryFrom;
+
+/** A synchronization point in the command stream of a device.
+ *
+ * The fence gets signaled once the device has finished executing all of the
+ * commands issued before it was created, which lets the host tell how far
+ * behind the device is without having to wait for it to go completely idle. */
+#[derive(Debug)]
+pub struct GpuFence {
+	/** Handle to the shared context. */
+	pub(crate) context: Rc<Context>,
+	/** The name of the underlying sync object. */
+	pub(crate) fence: <Context as HasContext>::Fence,
+}
+impl GpuFence {
+	/** Whether the device has already gone past this fence. Never blocks. */
+	pub fn poll(&self) -> bool {
+		unsafe {
+			self.context.get_sync_status(self.fence) == glow::SIGNALED
+		}
+	}
+
+	/** Blocks until either the device goes past this fence or the given amount
+	 * of time runs out, whichever comes first, returning whether the fence got
+	 * signaled.
+	 *
+	 * Implementations are free to cap the timeout at a value of their choosing,
+	 * with WebGL2 going as far as only allowing a timeout of zero. Timeouts
+	 * longer than what can be expressed are clamped. */
+	pub fn wait(&self, timeout: Duration) -> Result<bool, FenceError> {
+		let timeout = i32::try_from(timeout.as_nanos()).unwrap_or(i32::MAX);
+		let status = unsafe {
+			self.context.client_wait_sync(
+				self.fence,
+				glow::SYNC_FLUSH_COMMANDS_BIT,
+				timeout)
+		};
+
+		match status {
+			glow::ALREADY_SIGNALED | glow::CONDITION_SATISFIED => Ok(true),
+			glow::TIMEOUT_EXPIRED => Ok(false),
+			what => Err(FenceError::WaitFailed {
+				what: format!("glClientWaitSync returned 0x{:08x}", what)
+			})
+		}
+	}
+}
+impl Drop for GpuFence {
+	fn drop(&mut self) {
+		unsafe {
+			self.context.delete_sync(self.fence);
+		}
+	}
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum FenceError {
+	#[error("Failed to create a new fence: {what}")]
+	CreationFailed {
+		what: String
+	},
+	#[error("Failed to wait on fence: {what}")]
+	WaitFailed {
+		what: String
+	},
+}
@@ -1,8 +1,13 @@
 use smallvec::SmallVec;
-use crate::texture::Texture;
+use crate::lock::{PipelineLock, LockOperation};
+use crate::texture::{Texture, TextureFormat, TextureExtent};
 use crate::access::{UnitAccessLock, AccessLock};
+use crate::pass::Viewport;
 use glow::{HasContext, Context};
 use std::rc::Rc;
+use std::cell::{Cell, RefCell};
+use std::convert::TryFrom;
+use crate::debug::Debugger;
 
 /** The backing structure used for custom framebuffers. */
 #[derive(Debug)]
@@ -13,16 +18,64 @@ pub(crate) struct InnerFramebuffer {
 	pub(crate) access: UnitAccessLock,
 	/** Color attachments. */
 	pub(crate) color_attachments: SmallVec<[Texture; 32]>,
+	/** Layers of the color attachments, in the same order. */
+	pub(crate) color_layers: SmallVec<[u32; 32]>,
+	/** Mip levels of the color attachments, in the same order. */
+	pub(crate) color_levels: SmallVec<[u32; 32]>,
 	/** Depth stencil attachment. */
 	pub(crate) depth_stencil: Option<Texture>,
+	/** Layer of the depth stencil attachment. */
+	pub(crate) depth_stencil_layer: u32,
+	/** Mip level of the depth stencil attachment. */
+	pub(crate) depth_stencil_level: u32,
+	/** Multisampled storage of the framebuffer, which takes the place of the
+	 * attachments, since textures can't be multisampled, along with its size
+	 * and number of samples. This is `None` for single-sampled framebuffers. */
+	pub(crate) multisampled: Option<MultisampledStorage>,
 	/** Underlying named framebuffer object. */
 	pub(crate) framebuffer: <Context as HasContext>::Framebuffer,
-	/** The operation to perform on the color attachment when it is loaded. */
-	pub(crate) color_load_op: LoadOp<Color>,
+	/** The operations to perform on each of the color attachments when they
+	 * are loaded, in order. */
+	pub(crate) color_load_ops: SmallVec<[LoadOp<Color>; 32]>,
 	/** The operation to perform on the depth attachment when it is loaded. */
 	pub(crate) depth_load_op: LoadOp<f32>,
 	/** The operation to perform on the stencil attachment when it is loaded. */
 	pub(crate) stencil_load_op: LoadOp<u8>,
+	/** Whether the color attachments written to can be picked, which legacy
+	 * contexts can't do, as they only ever have the one attachment. */
+	pub(crate) draw_buffers: bool,
+}
+impl InnerFramebuffer {
+	/** Number of color attachments of this framebuffer, including the color
+	 * storage of multisampled framebuffers. */
+	pub(crate) fn color_attachment_count(&self) -> usize {
+		match &self.multisampled {
+			Some(_) => 1,
+			None => self.color_attachments.len()
+		}
+	}
+
+	/** Width and height of the attached levels of the color attachments, in
+	 * order. */
+	pub(crate) fn color_sizes(&self) -> impl Iterator<Item = (u32, u32)> + '_ {
+		self.color_attachments.iter()
+			.zip(&self.color_levels)
+			.map(|(texture, level)| level_size(&texture.extent(), *level))
+	}
+
+	/** Width and height of the attached level of the depth stencil
+	 * attachment, if any. */
+	pub(crate) fn depth_stencil_size(&self) -> Option<(u32, u32)> {
+		self.depth_stencil.as_ref()
+			.map(|texture| level_size(&texture.extent(), self.depth_stencil_level))
+	}
+}
+
+/** Width and height of the given mip level of a texture with the given
+ * extent, which is the same for all of its layers. */
+fn level_size(extent: &TextureExtent, level: u32) -> (u32, u32) {
+	let (width, height, _) = extent.mip(level).axes();
+	(width, height)
 }
 impl AccessLock for InnerFramebuffer {
 	fn acquire_write(&self) {
@@ -51,10 +104,28 @@ impl Drop for InnerFramebuffer {
 		unsafe {
 			let _atom = self.access.acquire_write_guarded();
 			self.context.delete_framebuffer(self.framebuffer);
+			if let Some(storage) = &self.multisampled {
+				for renderbuffer in &storage.renderbuffers {
+					self.context.delete_renderbuffer(*renderbuffer);
+				}
+			}
 		}
 	}
 }
 
+/** Renderbuffers backing a multisampled framebuffer. */
+#[derive(Debug)]
+pub(crate) struct MultisampledStorage {
+	/** Color renderbuffer, followed by the depth-stencil one, if any. */
+	pub(crate) renderbuffers: SmallVec<[<Context as HasContext>::Renderbuffer; 2]>,
+	/** Width of the renderbuffers, in pixels. */
+	pub(crate) width: u32,
+	/** Height of the renderbuffers, in pixels. */
+	pub(crate) height: u32,
+	/** Number of samples per pixel in the renderbuffers. */
+	pub(crate) samples: u32,
+}
+
 /** This type hides the fact that the framebuffer is an enum. Clients shouldn't
  * know this. */
 #[derive(Debug)]
@@ -74,6 +145,16 @@ pub(crate) enum FramebufferVariants {
 		depth_load_op: LoadOp<f32>,
 		/** The operation to perform on the stencil attachment when it is loaded. */
 		stencil_load_op: LoadOp<u8>,
+		/** Size of the drawable surface, shared with the device, as last given
+		 * to [`Device::resize_default_framebuffer`], if ever.
+		 *
+		 * [`Device::resize_default_framebuffer`]: crate::Device::resize_default_framebuffer */
+		size: Rc<Cell<Option<(u32, u32)>>>,
+		/** Whether colors written to it get encoded to sRGB, shared with the
+		 * device, as last given to [`Device::set_srgb_encoding`].
+		 *
+		 * [`Device::set_srgb_encoding`]: crate::Device::set_srgb_encoding */
+		srgb: Rc<Cell<bool>>,
 	},
 	/** This is a real framebuffer object. Because of the nature of the API, all
 	 * custom framebuffer objects are used exclusively for off-screen rendering.
@@ -87,10 +168,391 @@ pub(crate) enum FramebufferVariants {
 
 #[derive(Debug)]
 pub struct Framebuffer {
+	/** Shared context. */
+	pub(crate) context: Rc<Context>,
+	/** Shared OpenGL pipeline lock. */
+	pub(crate) pipeline: Rc<PipelineLock>,
+	/** Shared error checking and labeling state. */
+	pub(crate) debug: Rc<Debugger>,
+	/** Label used to refer to this framebuffer in diagnostics. */
+	pub(crate) label: RefCell<Option<String>>,
 	/** The actual framebuffer variants structure. */
 	pub(crate) variants: FramebufferVariants
 }
 impl Framebuffer {
+	/** The label used to refer to this framebuffer in diagnostics, if any. */
+	pub fn label(&self) -> Option<String> {
+		self.label.borrow().clone()
+	}
+
+	/** Changes the label used to refer to this framebuffer in diagnostics.
+	 * The label is also attached to the framebuffer object, for debuggers to
+	 * pick up, when the [`debug_labels`] capability is present. The default
+	 * framebuffer has no object for it to be attached to.
+	 *
+	 * [`debug_labels`]: crate::Capabilities::debug_labels */
+	pub fn set_label(&self, label: &str) {
+		if let FramebufferVariants::Custom { inner } = &self.variants {
+			unsafe {
+				self.debug.label(
+					&self.context,
+					glow::FRAMEBUFFER,
+					inner.framebuffer,
+					label);
+			}
+		}
+		*self.label.borrow_mut() = Some(label.to_owned())
+	}
+
+	/** Name of this framebuffer in diagnostics, which is either its label or
+	 * the name of the framebuffer object, if it has no label. */
+	pub(crate) fn name(&self) -> String {
+		let label = self.label.borrow();
+		match &self.variants {
+			FramebufferVariants::Default { .. } if label.is_none() =>
+				"(default)".into(),
+			FramebufferVariants::Default { .. } =>
+				crate::debug::name(label.as_deref(), ()),
+			FramebufferVariants::Custom { inner } =>
+				crate::debug::name(label.as_deref(), inner.framebuffer)
+		}
+	}
+
+	/** Checks for errors raised by the given operation on this framebuffer. */
+	pub(crate) unsafe fn check(&self, operation: &str) {
+		self.debug.check(
+			&self.context,
+			operation,
+			|| format!("framebuffer {}", self.name()))
+	}
+
+	/** Width and height of the surface this framebuffer renders to, in pixels.
+	 *
+	 * For custom framebuffers, this is the size of their smallest attachment,
+	 * as of the mip level that is attached of it.
+	 * OpenGL doesn't tell us how large the default framebuffer is, so, for it,
+	 * this is the size last given to [`Device::resize_default_framebuffer`],
+	 * or `None`, if it was never given one. Custom framebuffers without any
+	 * attachments have no size either.
+	 *
+	 * [`Device::resize_default_framebuffer`]: crate::Device::resize_default_framebuffer */
+	pub fn size(&self) -> Option<(u32, u32)> {
+		match &self.variants {
+			FramebufferVariants::Default { size, .. } => size.get(),
+			FramebufferVariants::Custom { inner } if inner.multisampled.is_some() => inner
+				.multisampled
+				.as_ref()
+				.map(|storage| (storage.width, storage.height)),
+			FramebufferVariants::Custom { inner } => inner.color_sizes()
+				.chain(inner.depth_stencil_size())
+				.fold(None, |smallest, (width, height)| match smallest {
+					Some((w, h)) => Some((u32::min(w, width), u32::min(h, height))),
+					None => Some((width, height))
+				})
+		}
+	}
+
+	/** Number of samples per pixel of this framebuffer, which is one for all
+	 * but the framebuffers created by [`Device::create_multisampled_framebuffer`].
+	 * OpenGL doesn't tell us about the default framebuffer, so it counts as
+	 * single-sampled, whatever the window was created with.
+	 *
+	 * [`Device::create_multisampled_framebuffer`]: crate::Device::create_multisampled_framebuffer */
+	pub fn samples(&self) -> u32 {
+		match &self.variants {
+			FramebufferVariants::Custom { inner } => inner.multisampled
+				.as_ref()
+				.map(|storage| storage.samples)
+				.unwrap_or(1),
+			FramebufferVariants::Default { .. } => 1
+		}
+	}
+
+	/** Returns the underlying handle to the framebuffer object, or `None` for
+	 * the default framebuffer.
+	 *
+	 * # Safety
+	 * The handle may only be used from within [`Device::with_external_gl`],
+	 * and the attachments of the framebuffer must not be changed through it.
+	 *
+	 * [`Device::with_external_gl`]: crate::Device::with_external_gl */
+	pub unsafe fn as_raw_handle(&self) -> Option<<Context as HasContext>::Framebuffer> {
+		match &self.variants {
+			FramebufferVariants::Default { .. } => None,
+			FramebufferVariants::Custom { inner } => Some(inner.framebuffer)
+		}
+	}
+
+	/** Reads back the pixels in the given region of the first color
+	 * attachment of this framebuffer, or of the screen, for the default
+	 * framebuffer.
+	 *
+	 * Pixels are tightly packed, in the given format, with rows going from the
+	 * bottom of the region to the top, as is the convention in OpenGL. The
+	 * format must match the format of the attachment being read, and the
+	 * default framebuffer may only be read as [`TextureFormat::Rgba8Unorm`].
+	 *
+	 * # Performance
+	 * This waits for all of the rendering commands targeting this framebuffer
+	 * to finish, so it is best used sparingly, for things like screenshots or
+	 * picking, rather than every frame.
+	 *
+	 * # Panic
+	 * This function will panic if the pipeline can't be locked mutably, as is
+	 * the case while a render pass is running.
+	 *
+	 * [`TextureFormat::Rgba8Unorm`]: TextureFormat::Rgba8Unorm */
+	pub fn read_pixels(&self, region: Viewport, format: TextureFormat)
+		-> Result<Vec<u8>, FramebufferError> {
+
+		match format {
+			TextureFormat::Rgba8Unorm
+				| TextureFormat::Srgb8Alpha8
+				| TextureFormat::Rgba32Float => {},
+			_ => return Err(FramebufferError::UnsupportedFormat {
+				what: format!("pixels can't be read back in the {:?} format",
+					format)
+			})
+		}
+
+		match &self.variants {
+			FramebufferVariants::Default { .. } => {
+				if format != TextureFormat::Rgba8Unorm {
+					return Err(FramebufferError::UnsupportedFormat {
+						what: format!("the default framebuffer can only be \
+							read back in the {:?} format, but {:?} was \
+							requested",
+							TextureFormat::Rgba8Unorm,
+							format)
+					})
+				}
+			},
+			FramebufferVariants::Custom { inner } if inner.multisampled.is_some() =>
+				return Err(FramebufferError::UnsupportedFormat {
+					what: "multisampled framebuffers can't be read back, and \
+						have to be resolved into a single-sampled one first"
+						.into()
+				}),
+			FramebufferVariants::Custom { inner } => {
+				let attachment = match inner.color_attachments.first() {
+					Some(attachment) => attachment,
+					None => return Err(FramebufferError::UnsupportedFormat {
+						what: "the framebuffer has no color attachments to \
+							read back from".into()
+					})
+				};
+				if attachment.format() != format {
+					return Err(FramebufferError::UnsupportedFormat {
+						what: format!("the color attachment is in the {:?} \
+							format, but it was requested in the {:?} format",
+							attachment.format(),
+							format)
+					})
+				}
+
+				let (width, height) = level_size(&attachment.extent(), inner.color_levels[0]);
+				check_region(&region, width, height)?;
+			}
+		}
+
+		let _pipeline = self.pipeline.acquire(LockOperation::FramebufferRead);
+		let _atom = self.acquire_read_guarded();
+
+		let gl = self.context.as_ref();
+		unsafe {
+			match &self.variants {
+				FramebufferVariants::Default { .. } =>
+					gl.bind_framebuffer(glow::READ_FRAMEBUFFER, None),
+				FramebufferVariants::Custom { inner } => {
+					gl.bind_framebuffer(
+						glow::READ_FRAMEBUFFER,
+						Some(inner.framebuffer));
+					gl.read_buffer(glow::COLOR_ATTACHMENT0);
+				}
+			}
+
+			let pixels = read_bound_pixels(gl, region, format);
+			gl.bind_framebuffer(glow::READ_FRAMEBUFFER, None);
+			self.check("reading pixels back from");
+
+			pixels
+		}
+	}
+
+	/** Resizes every attachment of this framebuffer to the given size, in
+	 * place, without having to recreate the framebuffer or its textures.
+	 *
+	 * The storage of every level of every attached texture is reallocated in
+	 * its own format, leaving its contents undefined. The textures keep their
+	 * identity, so every handle to them, and every bind group they are in,
+	 * sees the new size right away. This includes any other framebuffers the
+	 * textures happen to be attached to.
+	 *
+	 * # Errors
+	 * Only custom framebuffers whose attachments are all two-dimensional
+	 * textures may be resized, and their attachments must have no more mip
+	 * levels than a texture of the new size can have. The default framebuffer
+	 * follows the size of the window instead.
+	 *
+	 * # Panic
+	 * This function will panic if the pipeline can't be locked mutably, as is
+	 * the case while a render pass is running, or if any of the attachments
+	 * is in use. */
+	pub fn resize(&self, device: &crate::Device, width: u32, height: u32)
+		-> Result<(), FramebufferError> {
+
+		let inner = match &self.variants {
+			FramebufferVariants::Custom { inner } => inner,
+			FramebufferVariants::Default { .. } =>
+				return Err(FramebufferError::InvalidAttachment {
+					what: "the default framebuffer can't be resized, as it \
+						follows the size of the window".into()
+				})
+		};
+		if inner.multisampled.is_some() {
+			return Err(FramebufferError::InvalidAttachment {
+				what: "multisampled framebuffers can't be resized, and have to \
+					be created again at the new size".into()
+			})
+		}
+
+		let max = device.information.limits.max_texture_size;
+		let attachments = inner.color_attachments.iter().chain(&inner.depth_stencil);
+		for texture in attachments.clone() {
+			check_resize(
+				&texture.inner.extent.get(),
+				texture.inner.levels,
+				width,
+				height,
+				max)
+				.map_err(|what| FramebufferError::InvalidAttachment {
+					what: format!("texture {} can't be resized: {}",
+						texture.inner.name(),
+						what)
+				})?;
+		}
+
+		let _pipeline = self.pipeline.acquire(LockOperation::FramebufferCreation);
+		let _atom = self.acquire_write_guarded();
+
+		let gl = self.context.as_ref();
+		unsafe {
+			for texture in attachments {
+				texture.inner.reallocate(gl, width, height);
+			}
+
+			/* The attachments stay attached through the reallocation, but the
+			 * framebuffer could still end up incomplete with their new size. */
+			gl.bind_framebuffer(glow::FRAMEBUFFER, Some(inner.framebuffer));
+			let status = gl.check_framebuffer_status(glow::FRAMEBUFFER);
+			gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+			self.check("resizing");
+
+			if status != glow::FRAMEBUFFER_COMPLETE {
+				return Err(FramebufferError::CreationError {
+					what: format!("framebuffer {} is incomplete after being \
+						resized to {}x{}, status 0x{:08x}",
+						self.name(),
+						width,
+						height,
+						status)
+				})
+			}
+		}
+
+		Ok(())
+	}
+
+	/** Whether this and the given framebuffer are the same framebuffer. */
+	pub(crate) fn is_same(&self, other: &Framebuffer) -> bool {
+		match (&self.variants, &other.variants) {
+			(FramebufferVariants::Default { .. }, FramebufferVariants::Default { .. }) =>
+				true,
+			(FramebufferVariants::Custom { inner: a }, FramebufferVariants::Custom { inner: b }) =>
+				Rc::ptr_eq(a, b),
+			_ => false
+		}
+	}
+
+	/** Checks whether the given region can be blitted from or to this
+	 * framebuffer, returning its corners, as they are taken by OpenGL.
+	 *
+	 * Nothing is known about the size of the default framebuffer, so, for it,
+	 * this only checks whether the corners can be handed to OpenGL. Custom
+	 * framebuffers need every one of their color attachments to contain it. */
+	pub(crate) fn check_blit_region(&self, region: &Viewport)
+		-> Result<(i32, i32, i32, i32), FramebufferError> {
+
+		if let FramebufferVariants::Custom { inner } = &self.variants {
+			if let Some(storage) = &inner.multisampled {
+				check_region(region, storage.width, storage.height)?;
+				return blit_corners(region).ok_or_else(|| FramebufferError::InvalidRegion {
+					what: format!("the corners of the region {:?} don't fit in \
+						an i32", region)
+				})
+			}
+			if inner.color_attachments.is_empty() {
+				return Err(FramebufferError::InvalidAttachment {
+					what: "the framebuffer has no color attachments to blit \
+						from or to".into()
+				})
+			}
+			for (width, height) in inner.color_sizes() {
+				check_region(region, width, height)?;
+			}
+		}
+
+		blit_corners(region).ok_or_else(|| FramebufferError::InvalidRegion {
+			what: format!("the corners of the region {:?} don't fit in an i32",
+				region)
+		})
+	}
+
+	/** Binds this framebuffer as the source of a blit, which reads from its
+	 * first color attachment, or from the back buffer, for the default
+	 * framebuffer. */
+	pub(crate) unsafe fn bind_blit_source(&self, gl: &Context) {
+		match &self.variants {
+			FramebufferVariants::Default { .. } => {
+				gl.bind_framebuffer(glow::READ_FRAMEBUFFER, None);
+				gl.read_buffer(glow::BACK);
+			},
+			FramebufferVariants::Custom { inner } => {
+				gl.bind_framebuffer(glow::READ_FRAMEBUFFER, Some(inner.framebuffer));
+				gl.read_buffer(glow::COLOR_ATTACHMENT0);
+			}
+		}
+	}
+
+	/** Binds this framebuffer as the destination of a blit, which writes to
+	 * all of its color attachments. */
+	pub(crate) unsafe fn bind_blit_destination(&self, gl: &Context) {
+		match &self.variants {
+			FramebufferVariants::Default { .. } =>
+				gl.bind_framebuffer(glow::DRAW_FRAMEBUFFER, None),
+			FramebufferVariants::Custom { inner } =>
+				gl.bind_framebuffer(glow::DRAW_FRAMEBUFFER, Some(inner.framebuffer))
+		}
+		self.select_draw_buffers(gl, usize::MAX);
+	}
+
+	/** Lets the given number of color attachments of this framebuffer, from
+	 * the first one on, be written to, and keeps the rest from being written
+	 * to. This framebuffer must be bound for drawing.
+	 *
+	 * Fragment shader output `i` goes to the `i`-th attachment, no matter how
+	 * many of them there are, so pipelines with fewer color targets than the
+	 * framebuffer has attachments leave the ones past them untouched. */
+	pub(crate) unsafe fn select_draw_buffers(&self, gl: &Context, count: usize) {
+		let inner = match &self.variants {
+			FramebufferVariants::Custom { inner } if inner.draw_buffers => inner,
+			_ => return
+		};
+
+		let buffers = draw_buffers(inner.color_attachment_count(), count);
+		gl.draw_buffers(&buffers[..]);
+	}
+
 	/** Bind this framebuffer for use in OpenGL.
 	 *
 	 * This function does not perform any load or clear operations. Assuming
@@ -106,49 +568,200 @@ impl Framebuffer {
 		};
 	}
 
+	/** Whether colors written to this framebuffer should get encoded to sRGB,
+	 * which always is the case for the attachments of custom framebuffers that
+	 * use the sRGB encoding, the same way it is in OpenGL ES and WebGL. */
+	pub(crate) fn srgb_writes(&self) -> bool {
+		match &self.variants {
+			FramebufferVariants::Default { srgb, .. } => srgb.get(),
+			FramebufferVariants::Custom { .. } => true
+		}
+	}
+
+	/** The values the attachments of this framebuffer get cleared to when it
+	 * is loaded, as given by their load operations. */
+	pub(crate) fn clear_values(&self) -> ClearValues {
+		match &self.variants {
+			FramebufferVariants::Default {
+				color_load_op,
+				depth_load_op,
+				stencil_load_op,
+				.. } =>
+				ClearValues::new(&[*color_load_op], *depth_load_op, *stencil_load_op),
+			FramebufferVariants::Custom { inner } =>
+				ClearValues::new(
+					&inner.color_load_ops,
+					inner.depth_load_op,
+					inner.stencil_load_op)
+		}
+	}
+
 	/** Bind this framebuffer for use in OpenGL.
 	 *
 	 * This function also performs any required clear operations in all of the
-	 * attachments of the framebuffer, if needed. */
+	 * attachments of the framebuffer, if needed. The write masks are left open
+	 * afterwards, so the pipeline state has to be set up again. */
 	pub(crate) unsafe fn bind_and_load(&self, gl: &Context) {
-		let (color, depth, stencil) = match &self.variants {
-			FramebufferVariants::Default {
-				color_load_op,
-				depth_load_op,
-				stencil_load_op } => {
+		self.bind(gl);
+		self.select_draw_buffers(gl, usize::MAX);
+		self.clear_values().apply(gl);
+		self.check("loading");
+	}
 
-				gl.bind_framebuffer(glow::FRAMEBUFFER, None);
-				(*color_load_op, *depth_load_op, *stencil_load_op)
-			},
-			FramebufferVariants::Custom { inner } => {
-				gl.bind_framebuffer(glow::FRAMEBUFFER, Some(inner.framebuffer));
+	/** Keeps track of the attachments of this framebuffer being written to by
+	 * a render pass, warning about the ones that are loaded as they are before
+	 * anything was ever written to them. */
+	pub(crate) fn track_writes(&self) {
+		let inner = match &self.variants {
+			FramebufferVariants::Custom { inner } => inner,
+			FramebufferVariants::Default { .. } => return
+		};
 
-				(
-					inner.color_load_op,
-					inner.depth_load_op,
-					inner.stencil_load_op
-				)
+		let colors = inner.color_attachments.iter()
+			.zip(inner.color_layers.iter().zip(&inner.color_levels))
+			.zip(&inner.color_load_ops);
+		for ((texture, (layer, level)), load_op) in colors {
+			if let LoadOp::Load = load_op {
+				texture.inner.warn_uninitialized(
+					*level..*level + 1,
+					*layer..*layer + 1,
+					"loaded into a render pass");
 			}
-		};
+			texture.inner.mark_written(*level..*level + 1, *layer..*layer + 1);
+		}
+
+		if let Some(texture) = &inner.depth_stencil {
+			let layer = inner.depth_stencil_layer;
+			let level = inner.depth_stencil_level;
+			if inner.depth_load_op == LoadOp::Load || inner.stencil_load_op == LoadOp::Load {
+				texture.inner.warn_uninitialized(
+					level..level + 1,
+					layer..layer + 1,
+					"loaded into a render pass");
+			}
+			texture.inner.mark_written(level..level + 1, layer..layer + 1);
+		}
+	}
+}
+
+/** Values the attachments of a framebuffer get cleared to when it is loaded,
+ * where `None` means the attachment is loaded as it is. This is the one place
+ * load operations get turned into clears, for every kind of framebuffer. */
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ClearValues {
+	/** Values each of the color attachments get cleared to, in order. */
+	pub(crate) colors: SmallVec<[Option<Color>; 4]>,
+	/** Value the depth attachment gets cleared to. */
+	pub(crate) depth: Option<f32>,
+	/** Value the stencil attachment gets cleared to. */
+	pub(crate) stencil: Option<u8>,
+}
+impl ClearValues {
+	/** Resolves the clear values from the given load operations. */
+	pub(crate) fn new(
+		colors: &[LoadOp<Color>],
+		depth: LoadOp<f32>,
+		stencil: LoadOp<u8>) -> Self {
+
+		fn clear<T>(op: LoadOp<T>) -> Option<T> {
+			match op {
+				LoadOp::Clear(value) => Some(value),
+				LoadOp::Load => None
+			}
+		}
+		Self {
+			colors: colors.iter().copied().map(clear).collect(),
+			depth: clear(depth).map(clamp_depth),
+			stencil: clear(stencil),
+		}
+	}
 
+	/** Whether the color attachments get cleared one by one, each to its own
+	 * value, rather than all at once. Framebuffers with a single attachment,
+	 * which are all there are in legacy contexts, get cleared all at once. */
+	pub(crate) fn separate(&self) -> bool {
+		self.colors.len() > 1
+	}
+
+	/** Mask of the buffers that get cleared all at once. */
+	pub(crate) fn mask(&self) -> u32 {
 		let mut mask = 0;
-		if let LoadOp::Clear(color) = color {
+		if !self.separate() && self.colors.iter().any(Option::is_some) {
+			mask |= glow::COLOR_BUFFER_BIT
+		}
+		if self.depth.is_some()   { mask |= glow::DEPTH_BUFFER_BIT }
+		if self.stencil.is_some() { mask |= glow::STENCIL_BUFFER_BIT }
+
+		mask
+	}
+
+	/** Clears the attachments of the framebuffer currently bound, all of whose
+	 * color attachments must be selected for drawing.
+	 *
+	 * Clears are subject to the write masks, which are left behind by whatever
+	 * pipeline was used last, so they get opened up for the buffers being
+	 * cleared. Otherwise, a pipeline that doesn't write to depth would stop
+	 * the depth attachment of the next pass from being cleared at all. */
+	pub(crate) unsafe fn apply(&self, gl: &Context) {
+		if self.colors.iter().any(Option::is_some) {
+			gl.color_mask(true, true, true, true);
+		}
+		if self.separate() {
+			for (i, color) in (0u32..).zip(&self.colors) {
+				if let Some(color) = color {
+					let mut values = [color.red, color.green, color.blue, color.alpha];
+					gl.clear_buffer_f32_slice(glow::COLOR, i, &mut values);
+				}
+			}
+		} else if let Some(Some(color)) = self.colors.first() {
 			gl.clear_color(color.red, color.green, color.blue, color.alpha);
-			mask |= glow::COLOR_BUFFER_BIT;
 		}
-		if let LoadOp::Clear(depth) = depth {
+		if let Some(depth) = self.depth {
+			gl.depth_mask(true);
 			gl.clear_depth_f32(depth);
-			mask |= glow::DEPTH_BUFFER_BIT;
 		}
-		if let LoadOp::Clear(stencil) = stencil {
+		if let Some(stencil) = self.stencil {
+			gl.stencil_mask(0xff);
 			gl.clear_stencil(i32::from(stencil));
-			mask |= glow::STENCIL_BUFFER_BIT;
 		}
+
+		let mask = self.mask();
 		if mask != 0 {
 			gl.clear(mask);
 		}
 	}
 }
+
+/** Clamps the given depth value to the `[0; 1]` range of depth buffers. This
+ * is done by OpenGL itself for most values, but not by every implementation
+ * for the infinities, so it is better not to leave it up to them. */
+pub(crate) fn clamp_depth(value: f32) -> f32 {
+	value.clamp(0.0, 1.0)
+}
+
+/** Checks whether the given load operation of a depth attachment is one that
+ * can be carried out, which is any but clearing to NaN. */
+pub(crate) fn check_depth_load_op(op: LoadOp<f32>) -> Result<(), String> {
+	match op {
+		LoadOp::Clear(value) if value.is_nan() =>
+			Err("the depth attachment can't be cleared to NaN".to_owned()),
+		_ => Ok(())
+	}
+}
+
+/** The draw buffers of a framebuffer with the given number of color
+ * attachments, where only the given number of them, from the first one on,
+ * get written to. */
+fn draw_buffers(attachments: usize, count: usize) -> SmallVec<[u32; 8]> {
+	(0..attachments)
+		.map(|i| if i < count {
+			/* There are never more attachments than fit in a u32. */
+			glow::COLOR_ATTACHMENT0 + i as u32
+		} else {
+			glow::NONE
+		})
+		.collect()
+}
 impl AccessLock for Framebuffer {
 	fn acquire_write(&self) {
 		if let FramebufferVariants::Custom { inner } = &self.variants {
@@ -192,12 +805,56 @@ pub struct FramebufferDescriptor<'a> {
 	pub depth_stencil_attachment: Option<FramebufferDepthStencilAttachmentDescriptor<'a>>
 }
 
+/** Descriptor for a new, multisampled framebuffer, whose storage is created
+ * along with it, rather than given as textures, since textures can't be
+ * multisampled. Its contents can only be used by blitting them over to a
+ * single-sampled framebuffer, which resolves the samples of every pixel. */
+#[derive(Debug, Copy, Clone)]
+pub struct MultisampledFramebufferDescriptor {
+	/** Width of the framebuffer, in pixels. */
+	pub width: u32,
+	/** Height of the framebuffer, in pixels. */
+	pub height: u32,
+	/** Number of samples per pixel, which must be at least two and at most
+	 * the [`max_samples`] limit of the context. The implementation may use
+	 * more samples than requested.
+	 *
+	 * [`max_samples`]: crate::Limits::max_samples */
+	pub samples: u32,
+	/** Format of the color storage. */
+	pub color_format: TextureFormat,
+	/** Whether the framebuffer has depth and stencil storage, which is in the
+	 * [`TextureFormat::Depth24Stencil8`] format.
+	 *
+	 * [`TextureFormat::Depth24Stencil8`]: crate::TextureFormat::Depth24Stencil8 */
+	pub depth_stencil: bool,
+	/** The operation to perform on the color storage when it is loaded. */
+	pub color_load_op: LoadOp<Color>,
+	/** The operation to perform on the depth storage when it is loaded. */
+	pub depth_load_op: LoadOp<f32>,
+	/** The operation to perform on the stencil storage when it is loaded. */
+	pub stencil_load_op: LoadOp<u8>,
+}
+
 /** Descriptor for a color attachment in a custom framebuffer. */
 #[derive(Debug, Copy, Clone)]
 pub struct FramebufferColorAttachmentDescriptor<'a> {
 	/** Texture that will be used as the color attachment. */
 	pub attachment: &'a Texture,
-	/** The operation to perform on the attachment when it is loaded. */
+	/** Layer of the texture that will be attached, for array textures, or
+	 * index of the face that will be attached, for cube maps, in the order
+	 * given by [`TextureExtent::CUBE_FACES`]. Must be zero for every other
+	 * kind of texture.
+	 *
+	 * [`TextureExtent::CUBE_FACES`]: crate::TextureExtent::CUBE_FACES */
+	pub layer: u32,
+	/** Mip level of the texture that will be attached, which is zero for the
+	 * base level. Rendering to any other level requires a context that isn't a
+	 * legacy one. */
+	pub mip_level: u32,
+	/** The operation to perform on the attachment when it is loaded. Every
+	 * color attachment gets cleared to its own value, independently of the
+	 * other attachments in the framebuffer. */
 	pub load_op: LoadOp<Color>
 }
 
@@ -206,13 +863,30 @@ pub struct FramebufferColorAttachmentDescriptor<'a> {
 pub struct FramebufferDepthStencilAttachmentDescriptor<'a> {
 	/** Texture that will be used as the depth and stencil attachment. */
 	pub attachment: &'a Texture,
+	/** Layer or cube map face of the texture that will be attached, just like
+	 * in [`FramebufferColorAttachmentDescriptor`].
+	 *
+	 * [`FramebufferColorAttachmentDescriptor`]: FramebufferColorAttachmentDescriptor */
+	pub layer: u32,
+	/** Mip level of the texture that will be attached, just like in
+	 * [`FramebufferColorAttachmentDescriptor`].
+	 *
+	 * [`FramebufferColorAttachmentDescriptor`]: FramebufferColorAttachmentDescriptor */
+	pub mip_level: u32,
 	/** The operation to perform on the depth attachment when it is loaded. */
 	pub depth_load_op: LoadOp<f32>,
 	/** The operation to perform on the stencil attachment when it is loaded. */
 	pub stencil_load_op: LoadOp<u8>,
 }
 
-/** Operation to be performed on the loading of an attachment. */
+/** Operation to be performed on the loading of an attachment.
+ *
+ * Depth clear values get clamped to the `[0; 1]` range depth buffers hold
+ * before they're handed over to the context, the same way on every kind of
+ * context, so clearing to [`f32::INFINITY`] is the same as clearing to `1.0`,
+ * and clearing to [`f32::NEG_INFINITY`] is the same as clearing to `0.0`,
+ * which is what a reversed depth buffer needs. Clearing depth to NaN is an
+ * error. */
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub enum LoadOp<T> {
 	/** Clear the attachment with the given value. */
@@ -235,10 +909,268 @@ pub struct Color {
 	pub alpha: f32,
 }
 
+/** Checks that the given region lies within an attachment of the given size,
+ * and that its size can be handed to OpenGL. */
+fn check_region(region: &Viewport, width: u32, height: u32)
+	-> Result<(), FramebufferError> {
+
+	let fits = |offset: i32, len: u32, max: u32| u32::try_from(offset).ok()
+		.and_then(|offset| offset.checked_add(len))
+		.map(|end| end <= max)
+		.unwrap_or(false);
+
+	if !fits(region.x, region.width, width) || !fits(region.y, region.height, height) {
+		return Err(FramebufferError::InvalidRegion {
+			what: format!("the region {:?} is not contained in an attachment \
+				that is {}x{} texels big",
+				region,
+				width,
+				height)
+		})
+	}
+	Ok(())
+}
+
+/** Checks whether a texture of the given extent, with the given number of mip
+ * levels, can be resized to the given size in a context where textures can be
+ * at most `max` texels wide, describing the problem if it can't. */
+fn check_resize(
+	extent: &TextureExtent,
+	levels: u32,
+	width: u32,
+	height: u32,
+	max: u32) -> Result<(), String> {
+
+	if !matches!(extent, TextureExtent::D2 { .. }) {
+		return Err(format!("only two-dimensional textures can be resized, \
+			but it has extent {:?}", extent))
+	}
+	if width == 0 || height == 0 {
+		return Err(format!("textures can't be {}x{} texels big", width, height))
+	}
+	if width > max || height > max || i32::try_from(width.max(height)).is_err() {
+		return Err(format!("a size of {}x{} exceeds the maximum texture size \
+			of {}", width, height, max))
+	}
+
+	let available = TextureExtent::D2 { width, height }.max_levels();
+	if levels > available {
+		return Err(format!("it has {} mip levels, but a texture of {}x{} \
+			texels can only have {}", levels, width, height, available))
+	}
+
+	Ok(())
+}
+
+/** Corners of the given region, from its origin to its far end, as they are
+ * taken by `glBlitFramebuffer`, or `None` if they don't fit in an i32. */
+pub(crate) fn blit_corners(region: &Viewport) -> Option<(i32, i32, i32, i32)> {
+	let x1 = i32::try_from(region.width).ok()?.checked_add(region.x)?;
+	let y1 = i32::try_from(region.height).ok()?.checked_add(region.y)?;
+
+	Some((region.x, region.y, x1, y1))
+}
+
+/** Reads the pixels in the given region of the color buffer selected for
+ * reading in the framebuffer currently bound to `READ_FRAMEBUFFER`, as tightly
+ * packed data in the given format. */
+pub(crate) unsafe fn read_bound_pixels(
+	gl: &Context,
+	region: Viewport,
+	format: TextureFormat) -> Result<Vec<u8>, FramebufferError> {
+
+	let len = usize::try_from(region.width).ok()
+		.and_then(|width| width.checked_mul(usize::try_from(region.height).ok()?))
+		.and_then(|texels| texels.checked_mul(format.bytes_per_texel() as usize));
+	let (len, width, height) = match (
+		len,
+		i32::try_from(region.width),
+		i32::try_from(region.height)) {
+
+		(Some(len), Ok(width), Ok(height)) => (len, width, height),
+		_ => return Err(FramebufferError::InvalidRegion {
+			what: format!("the region {:?} is too big to be read back", region)
+		})
+	};
+
+	let mut pixels = vec![0; len];
+	if len == 0 { return Ok(pixels) }
+
+	/* Rows are tightly packed on our side, so they must be read back without
+	 * the default padding to four bytes. That only changes anything for the
+	 * formats that might not have rows aligned to four bytes already, but it
+	 * costs nothing to be explicit about it. */
+	let (pixel_format, _, kind) = format.as_opengl();
+	gl.pixel_store_i32(glow::PACK_ALIGNMENT, 1);
+	gl.read_pixels(
+		region.x,
+		region.y,
+		width,
+		height,
+		pixel_format,
+		kind,
+		glow::PixelPackData::Slice(&mut pixels[..]));
+	gl.pixel_store_i32(glow::PACK_ALIGNMENT, 4);
+
+	Ok(pixels)
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum FramebufferError {
 	#[error("could not create framebuffer object: {what}")]
 	CreationError {
 		what: String
 	},
+	#[error("the framebuffer exceeds the limits of the context: {what}")]
+	LimitExceeded {
+		what: String
+	},
+	#[error("the requested pixel format is not supported: {what}")]
+	UnsupportedFormat {
+		what: String
+	},
+	#[error("the requested region is invalid: {what}")]
+	InvalidRegion {
+		what: String
+	},
+	#[error("the attachment is invalid: {what}")]
+	InvalidAttachment {
+		what: String
+	},
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn only_two_dimensional_textures_get_resized() {
+		let texture = TextureExtent::D2 { width: 64, height: 64 };
+		assert!(check_resize(&texture, 1, 800, 600, 4096).is_ok());
+		assert!(check_resize(&texture, 7, 64, 32, 4096).is_ok());
+
+		/* Mip chains have to fit in the new size. */
+		assert!(check_resize(&texture, 7, 32, 32, 4096).is_err());
+
+		assert!(check_resize(&texture, 1, 0, 600, 4096).is_err());
+		assert!(check_resize(&texture, 1, 8192, 600, 4096).is_err());
+
+		let array = TextureExtent::D2Array { width: 64, height: 64, layers: 2 };
+		assert!(check_resize(&array, 1, 800, 600, 4096).is_err());
+	}
+
+	#[test]
+	fn clears_only_what_was_asked_for() {
+		let color = Color { red: 0.1, green: 0.2, blue: 0.3, alpha: 1.0 };
+
+		/* Default framebuffer, clearing only its color. */
+		let values = ClearValues::new(&[LoadOp::Clear(color)], LoadOp::Load, LoadOp::Load);
+		assert_eq!(&values.colors[..], &[Some(color)]);
+		assert_eq!(values.mask(), glow::COLOR_BUFFER_BIT);
+
+		/* Custom framebuffer with a reversed depth buffer. */
+		let values = ClearValues::new(&[LoadOp::Load], LoadOp::Clear(0.0), LoadOp::Load);
+		assert_eq!(values.depth, Some(0.0));
+		assert_eq!(values.mask(), glow::DEPTH_BUFFER_BIT);
+
+		/* Custom framebuffer loading its color and clearing its stencil. */
+		let values = ClearValues::new(&[LoadOp::Load], LoadOp::Load, LoadOp::Clear(0x80));
+		assert_eq!(values, ClearValues {
+			colors: std::iter::once(None).collect(),
+			depth: None,
+			stencil: Some(0x80)
+		});
+		assert_eq!(values.mask(), glow::STENCIL_BUFFER_BIT);
+	}
+
+	#[test]
+	fn g_buffer_attachments_clear_separately() {
+		let albedo = Color { red: 0.0, green: 0.0, blue: 0.0, alpha: 1.0 };
+		let normal = Color { red: 0.5, green: 0.5, blue: 1.0, alpha: 0.0 };
+		let material = Color { red: 0.0, green: 0.0, blue: 0.0, alpha: 0.0 };
+
+		/* Each attachment gets its own value, so none of them get cleared
+		 * along with the depth. */
+		let values = ClearValues::new(
+			&[LoadOp::Clear(albedo), LoadOp::Clear(normal), LoadOp::Clear(material)],
+			LoadOp::Clear(1.0),
+			LoadOp::Load);
+		assert!(values.separate());
+		assert_eq!(&values.colors[..], &[Some(albedo), Some(normal), Some(material)]);
+		assert_eq!(values.mask(), glow::DEPTH_BUFFER_BIT);
+
+		/* Attachments that get loaded are left alone. */
+		let values = ClearValues::new(
+			&[LoadOp::Clear(albedo), LoadOp::Load],
+			LoadOp::Load,
+			LoadOp::Load);
+		assert_eq!(&values.colors[..], &[Some(albedo), None]);
+		assert_eq!(values.mask(), 0);
+
+		/* A single attachment, as in the default framebuffer, still gets
+		 * cleared in one go with the depth and stencil. */
+		let values = ClearValues::new(&[LoadOp::Clear(normal)], LoadOp::Clear(1.0), LoadOp::Load);
+		assert!(!values.separate());
+		assert_eq!(values.mask(), glow::COLOR_BUFFER_BIT | glow::DEPTH_BUFFER_BIT);
+	}
+
+	#[test]
+	fn attached_levels_set_the_size() {
+		let atlas = TextureExtent::D2Array { width: 1024, height: 512, layers: 4 };
+		assert_eq!(level_size(&atlas, 0), (1024, 512));
+		assert_eq!(level_size(&atlas, 2), (256, 128));
+		assert_eq!(level_size(&atlas, 10), (1, 1));
+
+		let cube = TextureExtent::Cube { size: 64 };
+		assert_eq!(level_size(&cube, 3), (8, 8));
+	}
+
+	#[test]
+	fn depth_clears_stay_in_range() {
+		let depth = |value| ClearValues::new(&[], LoadOp::Clear(value), LoadOp::Load).depth;
+		assert_eq!(depth(f32::INFINITY), Some(1.0));
+		assert_eq!(depth(f32::NEG_INFINITY), Some(0.0));
+		assert_eq!(depth(-0.5), Some(0.0));
+		assert_eq!(depth(0.25), Some(0.25));
+
+		assert!(check_depth_load_op(LoadOp::Clear(f32::NAN)).is_err());
+		assert!(check_depth_load_op(LoadOp::Clear(f32::INFINITY)).is_ok());
+		assert!(check_depth_load_op(LoadOp::Load).is_ok());
+	}
+
+	#[test]
+	fn pipelines_write_to_the_first_attachments() {
+		assert_eq!(
+			&draw_buffers(2, usize::MAX)[..],
+			&[glow::COLOR_ATTACHMENT0, glow::COLOR_ATTACHMENT1]);
+		assert_eq!(
+			&draw_buffers(3, 1)[..],
+			&[glow::COLOR_ATTACHMENT0, glow::NONE, glow::NONE]);
+		assert_eq!(&draw_buffers(1, 0)[..], &[glow::NONE]);
+		assert!(draw_buffers(0, 4).is_empty());
+	}
+
+	#[test]
+	fn blit_corners_fit_in_an_i32() {
+		let region = |x, y, width, height| Viewport { x, y, width, height };
+
+		assert_eq!(blit_corners(&region(0, 0, 64, 32)), Some((0, 0, 64, 32)));
+		assert_eq!(blit_corners(&region(-8, 4, 16, 16)), Some((-8, 4, 8, 20)));
+		assert_eq!(blit_corners(&region(1, 0, i32::MAX as u32, 1)), None);
+		assert_eq!(blit_corners(&region(0, 0, u32::MAX, 1)), None);
+	}
+
+	#[test]
+	fn read_regions_must_fit_in_the_attachment() {
+		let region = |x, y, width, height| Viewport { x, y, width, height };
+
+		assert!(check_region(&region(0, 0, 64, 32), 64, 32).is_ok());
+		assert!(check_region(&region(32, 16, 32, 16), 64, 32).is_ok());
+		assert!(check_region(&region(64, 32, 0, 0), 64, 32).is_ok());
+
+		assert!(check_region(&region(33, 0, 32, 16), 64, 32).is_err());
+		assert!(check_region(&region(0, 0, 64, 33), 64, 32).is_err());
+		assert!(check_region(&region(-1, 0, 1, 1), 64, 32).is_err());
+		assert!(check_region(&region(1, 0, u32::MAX, 1), 64, 32).is_err());
+	}
 }
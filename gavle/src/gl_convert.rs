@@ -0,0 +1,566 @@
+use crate::buffer::BufferProfile;
+use crate::pipeline::{
+	BlendFactor,
+	BlendOperation,
+	CompareFunction,
+	CullMode,
+	FrontFace,
+	IndexFormat,
+	PrimitiveTopology,
+	StencilOperation,
+	VertexType
+};
+use crate::query::QueryKind;
+use crate::swizzle::ChannelSource;
+use crate::texel::TexelFormat;
+use crate::texture::{AddressMode, TextureExtent, TextureFilter, TextureFormat};
+
+/* Every mapping from the types of this crate to the enums OpenGL takes in
+ * their place lives here, rather than next to the types themselves, so that
+ * they can all be checked against each other in one place. */
+
+impl BufferProfile {
+	/** Get the OpenGL enum value for the current variant. */
+	pub(crate) fn as_opengl(&self) -> u32 {
+		match self {
+			Self::StaticUpload  => glow::STATIC_DRAW,
+			Self::DynamicUpload => glow::DYNAMIC_DRAW,
+			Self::StaticDevice  => glow::STATIC_COPY,
+			Self::DynamicDevice => glow::DYNAMIC_COPY,
+		}
+	}
+}
+
+impl StencilOperation {
+	/** Get the OpenGL enum value for the current variant. */
+	pub(crate) fn as_opengl(&self) -> u32 {
+		match self {
+			Self::Keep => glow::KEEP,
+			Self::Zero => glow::ZERO,
+			Self::Replace => glow::REPLACE,
+			Self::IncrementClamp => glow::INCR,
+			Self::IncrementWrap => glow::INCR_WRAP,
+			Self::DecrementClamp => glow::DECR,
+			Self::DecrementWrap => glow::DECR_WRAP,
+			Self::Invert => glow::INVERT
+		}
+	}
+}
+
+impl CompareFunction {
+	/** Get the OpenGL enum value for the current variant. */
+	pub(crate) fn as_opengl(&self) -> u32 {
+		match self {
+			Self::Equal => glow::EQUAL,
+			Self::Always => glow::ALWAYS,
+			Self::Greater => glow::GREATER,
+			Self::GreaterEqual => glow::GEQUAL,
+			Self::Less => glow::LESS,
+			Self::LessEqual => glow::LEQUAL,
+			Self::NotEqual => glow::NOTEQUAL,
+			Self::Never => glow::NEVER
+		}
+	}
+}
+
+impl BlendFactor {
+	/** Get the OpenGL enum value for the current variant. */
+	pub(crate) fn as_opengl(&self) -> u32 {
+		match self {
+			Self::Zero => glow::ZERO,
+			Self::One => glow::ONE,
+			Self::SrcColor => glow::SRC_COLOR,
+			Self::OneMinusSrcColor => glow::ONE_MINUS_SRC_COLOR,
+			Self::DstColor => glow::DST_COLOR,
+			Self::OneMinusDstColor => glow::ONE_MINUS_DST_COLOR,
+			Self::SrcAlpha => glow::SRC_ALPHA,
+			Self::OneMinusSrcAlpha => glow::ONE_MINUS_SRC_ALPHA,
+			Self::DstAlpha => glow::DST_ALPHA,
+			Self::OneMinusDstAlpha => glow::ONE_MINUS_DST_ALPHA,
+			Self::BlendColor => glow::CONSTANT_COLOR,
+			Self::OneMinusBlendColor => glow::ONE_MINUS_CONSTANT_COLOR,
+			Self::SrcAlphaSaturated =>
+				/* Use the same as SrcAlpha. */
+				glow::SRC_ALPHA
+		}
+	}
+}
+
+impl BlendOperation {
+	/** Get the OpenGL enum value for the current variant. */
+	pub(crate) fn as_opengl(&self) -> u32 {
+		match self {
+			Self::Add => glow::FUNC_ADD,
+			Self::Subtract => glow::FUNC_SUBTRACT,
+			Self::ReverseSubtract => glow::FUNC_REVERSE_SUBTRACT,
+			Self::Min => glow::MIN,
+			Self::Max => glow::MAX,
+		}
+	}
+}
+
+impl PrimitiveTopology {
+	/** Get the OpenGL drawing mode for the current variant. */
+	pub(crate) fn as_opengl(&self) -> u32 {
+		match self {
+			Self::PointList     => glow::POINTS,
+			Self::LineList      => glow::LINES,
+			Self::LineStrip     => glow::LINE_STRIP,
+			Self::TriangleList  => glow::TRIANGLES,
+			Self::TriangleStrip => glow::TRIANGLE_STRIP
+		}
+	}
+}
+
+impl IndexFormat {
+	/** Get the OpenGL type of indices in this format. */
+	pub(crate) fn as_opengl(&self) -> u32 {
+		match self {
+			Self::Uint16 => glow::UNSIGNED_SHORT,
+			Self::Uint32 => glow::UNSIGNED_INT
+		}
+	}
+
+	/** Number of bytes taken up by a single index in this format. */
+	pub(crate) fn len(&self) -> u32 {
+		match self {
+			Self::Uint16 => 2,
+			Self::Uint32 => 4
+		}
+	}
+}
+
+impl FrontFace {
+	/** Get the OpenGL winding order for the current variant. */
+	pub(crate) fn as_opengl(&self) -> u32 {
+		match self {
+			Self::Ccw => glow::CCW,
+			Self::Cw => glow::CW
+		}
+	}
+}
+
+impl CullMode {
+	/** Get the OpenGL enum for the faces culled by the current variant, or
+	 * `None` if culling has to be disabled altogether. */
+	pub(crate) fn as_opengl(&self) -> Option<u32> {
+		match self {
+			Self::None => None,
+			Self::Front => Some(glow::FRONT),
+			Self::Back => Some(glow::BACK)
+		}
+	}
+}
+
+impl VertexType {
+	/** Returns the OpenGL enum the current variant is equivalent to. */
+	pub fn as_opengl(&self) -> u32 {
+		match self {
+			Self::I8 | Self::Snorm8 | Self::Sint8 => glow::BYTE,
+			Self::U8 | Self::Unorm8 | Self::Uint8 => glow::UNSIGNED_BYTE,
+			Self::I16 | Self::Snorm16 | Self::Sint16 => glow::SHORT,
+			Self::U16 | Self::Unorm16 | Self::Uint16 => glow::UNSIGNED_SHORT,
+			Self::Sint32 => glow::INT,
+			Self::Uint32 => glow::UNSIGNED_INT,
+			Self::F16 => glow::HALF_FLOAT,
+			Self::F32 => glow::FLOAT
+		}
+	}
+
+	/** Number of bytes taken up by a single component of this type. */
+	pub fn size(&self) -> u32 {
+		match self {
+			Self::I8 | Self::U8
+				| Self::Unorm8 | Self::Snorm8
+				| Self::Uint8 | Self::Sint8 => 1,
+			Self::I16 | Self::U16 | Self::F16
+				| Self::Unorm16 | Self::Snorm16
+				| Self::Uint16 | Self::Sint16 => 2,
+			Self::F32 | Self::Uint32 | Self::Sint32 => 4,
+		}
+	}
+}
+
+impl QueryKind {
+	/** The OpenGL target queries of this kind are begun and ended on. */
+	pub(crate) fn as_opengl(&self) -> u32 {
+		match self {
+			Self::AnySamplesPassed => glow::ANY_SAMPLES_PASSED,
+			Self::TimeElapsed => glow::TIME_ELAPSED,
+		}
+	}
+}
+
+impl ChannelSource {
+	/** Get the OpenGL swizzle parameter value for this source. */
+	pub(crate) fn as_opengl(&self) -> i32 {
+		(match self {
+			Self::Red => glow::RED,
+			Self::Green => glow::GREEN,
+			Self::Blue => glow::BLUE,
+			Self::Alpha => glow::ALPHA,
+			Self::Zero => glow::ZERO,
+			Self::One => glow::ONE,
+		}) as i32
+	}
+}
+
+/** Internal formats of S3TC compressed textures, which are only ever available
+ * through extensions, so they are missing from the core enumerations. */
+const COMPRESSED_RGBA_S3TC_DXT1_EXT: u32 = 0x83f1;
+const COMPRESSED_RGBA_S3TC_DXT3_EXT: u32 = 0x83f2;
+const COMPRESSED_RGBA_S3TC_DXT5_EXT: u32 = 0x83f3;
+
+impl TextureFormat {
+	/** Get the OpenGL pixel format, internal format and pixel type for data in
+	 * this format, in that order.
+	 *
+	 * Compressed data gets uploaded with its internal format alone, so the
+	 * pixel format and type of compressed formats only describe the texels
+	 * they decompress to. */
+	pub(crate) fn as_opengl(&self) -> (u32, u32, u32) {
+		match self {
+			Self::Rgba8Unorm => (
+				glow::RGBA,
+				glow::RGBA8,
+				glow::UNSIGNED_BYTE),
+			Self::Srgb8Alpha8 => (
+				glow::RGBA,
+				glow::SRGB8_ALPHA8,
+				glow::UNSIGNED_BYTE),
+			Self::Rgba32Float => (
+				glow::RGBA,
+				glow::RGBA32F,
+				glow::FLOAT),
+			Self::Depth24Stencil8 => (
+				glow::DEPTH_STENCIL,
+				glow::DEPTH24_STENCIL8,
+				glow::UNSIGNED_INT_24_8),
+			Self::Etc2Rgb8Unorm => (
+				glow::RGB,
+				glow::COMPRESSED_RGB8_ETC2,
+				glow::UNSIGNED_BYTE),
+			Self::Etc2Rgba8Unorm => (
+				glow::RGBA,
+				glow::COMPRESSED_RGBA8_ETC2_EAC,
+				glow::UNSIGNED_BYTE),
+			Self::Bc1RgbaUnorm => (
+				glow::RGBA,
+				COMPRESSED_RGBA_S3TC_DXT1_EXT,
+				glow::UNSIGNED_BYTE),
+			Self::Bc2RgbaUnorm => (
+				glow::RGBA,
+				COMPRESSED_RGBA_S3TC_DXT3_EXT,
+				glow::UNSIGNED_BYTE),
+			Self::Bc3RgbaUnorm => (
+				glow::RGBA,
+				COMPRESSED_RGBA_S3TC_DXT5_EXT,
+				glow::UNSIGNED_BYTE),
+		}
+	}
+}
+
+impl TexelFormat {
+	/** Get the OpenGL pixel format, internal format and pixel type for data in
+	 * this format, in that order. */
+	pub(crate) fn as_opengl(&self) -> (u32, u32, u32) {
+		match self {
+			Self::Rgba32Float => (
+				glow::RGBA,
+				glow::RGBA32F,
+				glow::FLOAT),
+			Self::Rgba32Uint => (
+				glow::RGBA_INTEGER,
+				glow::RGBA32UI,
+				glow::UNSIGNED_INT),
+		}
+	}
+}
+
+impl TextureFilter {
+	/** Get the OpenGL enum value for the current variant, picking between
+	 * the mipmapped and the non-mipmapped versions of the filter. */
+	pub(crate) fn as_opengl(&self, mipmapped: bool) -> u32 {
+		match self {
+			Self::Nearest => if mipmapped { glow::NEAREST_MIPMAP_NEAREST } else { glow::NEAREST },
+			Self::Linear => if mipmapped { glow::LINEAR_MIPMAP_LINEAR } else { glow::LINEAR },
+		}
+	}
+}
+
+impl AddressMode {
+	/** Get the OpenGL enum value for the current variant. */
+	pub(crate) fn as_opengl(&self) -> u32 {
+		match self {
+			Self::Repeat => glow::REPEAT,
+			Self::MirrorRepeat => glow::MIRRORED_REPEAT,
+			Self::ClampToEdge => glow::CLAMP_TO_EDGE,
+		}
+	}
+}
+
+impl TextureExtent {
+	/** Get the OpenGL binding target for textures of this extent. */
+	pub(crate) fn target(&self) -> u32 {
+		match self {
+			Self::D1 { .. } => glow::TEXTURE_1D,
+			Self::D2 { .. } => glow::TEXTURE_2D,
+			Self::D2Array { .. } => glow::TEXTURE_2D_ARRAY,
+			Self::D3 { .. } => glow::TEXTURE_3D,
+			Self::Cube { .. } => glow::TEXTURE_CUBE_MAP,
+		}
+	}
+
+	/** Get the OpenGL image target for the cube map face with the given index,
+	 * following the order in [`TextureExtent::CUBE_FACES`], which is also the
+	 * order of the targets themselves.
+	 *
+	 * [`TextureExtent::CUBE_FACES`]: TextureExtent::CUBE_FACES */
+	pub(crate) fn cube_face_target(face: u32) -> u32 {
+		debug_assert!(face < 6, "cube maps only have six faces");
+		glow::TEXTURE_CUBE_MAP_POSITIVE_X + face
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::collections::HashSet;
+	use std::fmt::Debug;
+	use std::hash::Hash;
+
+	/** Checks every mapping in the given table, and that no two variants map
+	 * to the same value, unless they are meant to. */
+	fn check<T, U>(table: &[(T, U)], convert: impl Fn(&T) -> U, distinct: bool)
+		where T: Debug, U: Debug + Eq + Hash + Copy {
+
+		for (variant, expected) in table {
+			assert_eq!(convert(variant), *expected, "wrong mapping for {:?}", variant);
+		}
+		if distinct {
+			let values = table.iter().map(|(_, value)| *value).collect::<HashSet<_>>();
+			assert_eq!(values.len(), table.len(), "two variants share a mapping");
+		}
+	}
+
+	#[test]
+	fn pipeline_state() {
+		check(&[
+			(StencilOperation::Keep, glow::KEEP),
+			(StencilOperation::Zero, glow::ZERO),
+			(StencilOperation::Replace, glow::REPLACE),
+			(StencilOperation::Invert, glow::INVERT),
+			(StencilOperation::IncrementClamp, glow::INCR),
+			(StencilOperation::DecrementClamp, glow::DECR),
+			(StencilOperation::IncrementWrap, glow::INCR_WRAP),
+			(StencilOperation::DecrementWrap, glow::DECR_WRAP),
+		], StencilOperation::as_opengl, true);
+		check(&[
+			(CompareFunction::Never, glow::NEVER),
+			(CompareFunction::Less, glow::LESS),
+			(CompareFunction::Equal, glow::EQUAL),
+			(CompareFunction::LessEqual, glow::LEQUAL),
+			(CompareFunction::Greater, glow::GREATER),
+			(CompareFunction::NotEqual, glow::NOTEQUAL),
+			(CompareFunction::GreaterEqual, glow::GEQUAL),
+			(CompareFunction::Always, glow::ALWAYS),
+		], CompareFunction::as_opengl, true);
+		check(&[
+			(BlendOperation::Add, glow::FUNC_ADD),
+			(BlendOperation::Subtract, glow::FUNC_SUBTRACT),
+			(BlendOperation::ReverseSubtract, glow::FUNC_REVERSE_SUBTRACT),
+			(BlendOperation::Min, glow::MIN),
+			(BlendOperation::Max, glow::MAX),
+		], BlendOperation::as_opengl, true);
+		check(&[
+			(PrimitiveTopology::PointList, glow::POINTS),
+			(PrimitiveTopology::LineList, glow::LINES),
+			(PrimitiveTopology::LineStrip, glow::LINE_STRIP),
+			(PrimitiveTopology::TriangleList, glow::TRIANGLES),
+			(PrimitiveTopology::TriangleStrip, glow::TRIANGLE_STRIP),
+		], PrimitiveTopology::as_opengl, true);
+		check(&[
+			(FrontFace::Ccw, glow::CCW),
+			(FrontFace::Cw, glow::CW),
+		], FrontFace::as_opengl, true);
+		check(&[
+			(CullMode::None, None),
+			(CullMode::Front, Some(glow::FRONT)),
+			(CullMode::Back, Some(glow::BACK)),
+		], CullMode::as_opengl, true);
+	}
+
+	#[test]
+	fn blend_factors() {
+		/* Saturated source alpha has no equivalent, and falls back to plain
+		 * source alpha, so the factors can't all be distinct. */
+		check(&[
+			(BlendFactor::Zero, glow::ZERO),
+			(BlendFactor::One, glow::ONE),
+			(BlendFactor::SrcColor, glow::SRC_COLOR),
+			(BlendFactor::OneMinusSrcColor, glow::ONE_MINUS_SRC_COLOR),
+			(BlendFactor::DstColor, glow::DST_COLOR),
+			(BlendFactor::OneMinusDstColor, glow::ONE_MINUS_DST_COLOR),
+			(BlendFactor::SrcAlpha, glow::SRC_ALPHA),
+			(BlendFactor::OneMinusSrcAlpha, glow::ONE_MINUS_SRC_ALPHA),
+			(BlendFactor::DstAlpha, glow::DST_ALPHA),
+			(BlendFactor::OneMinusDstAlpha, glow::ONE_MINUS_DST_ALPHA),
+			(BlendFactor::SrcAlphaSaturated, glow::SRC_ALPHA),
+			(BlendFactor::BlendColor, glow::CONSTANT_COLOR),
+			(BlendFactor::OneMinusBlendColor, glow::ONE_MINUS_CONSTANT_COLOR),
+		], BlendFactor::as_opengl, false);
+	}
+
+	#[test]
+	fn vertex_and_index_types() {
+		/* Sizes have to agree with the OpenGL type, or strides and offsets
+		 * end up pointing at the wrong data. */
+		let size_of = |kind: u32| match kind {
+			glow::BYTE | glow::UNSIGNED_BYTE => 1,
+			glow::SHORT | glow::UNSIGNED_SHORT | glow::HALF_FLOAT => 2,
+			glow::INT | glow::UNSIGNED_INT | glow::FLOAT => 4,
+			_ => panic!("unexpected type 0x{:04x}", kind)
+		};
+
+		let table = [
+			(VertexType::I8, glow::BYTE),
+			(VertexType::U8, glow::UNSIGNED_BYTE),
+			(VertexType::I16, glow::SHORT),
+			(VertexType::U16, glow::UNSIGNED_SHORT),
+			(VertexType::F16, glow::HALF_FLOAT),
+			(VertexType::F32, glow::FLOAT),
+			(VertexType::Unorm8, glow::UNSIGNED_BYTE),
+			(VertexType::Unorm16, glow::UNSIGNED_SHORT),
+			(VertexType::Snorm8, glow::BYTE),
+			(VertexType::Snorm16, glow::SHORT),
+			(VertexType::Uint8, glow::UNSIGNED_BYTE),
+			(VertexType::Uint16, glow::UNSIGNED_SHORT),
+			(VertexType::Uint32, glow::UNSIGNED_INT),
+			(VertexType::Sint8, glow::BYTE),
+			(VertexType::Sint16, glow::SHORT),
+			(VertexType::Sint32, glow::INT),
+		];
+		check(&table, VertexType::as_opengl, false);
+		for (kind, gl) in &table {
+			assert_eq!(kind.size(), size_of(*gl), "wrong size for {:?}", kind);
+		}
+
+		let table = [
+			(IndexFormat::Uint16, glow::UNSIGNED_SHORT),
+			(IndexFormat::Uint32, glow::UNSIGNED_INT),
+		];
+		check(&table, IndexFormat::as_opengl, true);
+		for (format, gl) in &table {
+			assert_eq!(format.len(), size_of(*gl), "wrong size for {:?}", format);
+		}
+	}
+
+	#[test]
+	fn texture_formats() {
+		check(&[
+			(TextureFormat::Rgba8Unorm, (glow::RGBA, glow::RGBA8, glow::UNSIGNED_BYTE)),
+			(TextureFormat::Srgb8Alpha8, (glow::RGBA, glow::SRGB8_ALPHA8, glow::UNSIGNED_BYTE)),
+			(TextureFormat::Rgba32Float, (glow::RGBA, glow::RGBA32F, glow::FLOAT)),
+			(TextureFormat::Depth24Stencil8,
+				(glow::DEPTH_STENCIL, glow::DEPTH24_STENCIL8, glow::UNSIGNED_INT_24_8)),
+			(TextureFormat::Etc2Rgb8Unorm,
+				(glow::RGB, glow::COMPRESSED_RGB8_ETC2, glow::UNSIGNED_BYTE)),
+			(TextureFormat::Etc2Rgba8Unorm,
+				(glow::RGBA, glow::COMPRESSED_RGBA8_ETC2_EAC, glow::UNSIGNED_BYTE)),
+			(TextureFormat::Bc1RgbaUnorm, (glow::RGBA, 0x83f1, glow::UNSIGNED_BYTE)),
+			(TextureFormat::Bc2RgbaUnorm, (glow::RGBA, 0x83f2, glow::UNSIGNED_BYTE)),
+			(TextureFormat::Bc3RgbaUnorm, (glow::RGBA, 0x83f3, glow::UNSIGNED_BYTE)),
+		], TextureFormat::as_opengl, true);
+		check(&[
+			(TexelFormat::Rgba32Float, (glow::RGBA, glow::RGBA32F, glow::FLOAT)),
+			(TexelFormat::Rgba32Uint, (glow::RGBA_INTEGER, glow::RGBA32UI, glow::UNSIGNED_INT)),
+		], TexelFormat::as_opengl, true);
+
+		/* The pixel type and format have to describe texels as large as the
+		 * ones the crate sizes its uploads and downloads for. Compressed
+		 * formats are sized by blocks instead, so they are left out. */
+		let texel_size = |(format, _, kind): (u32, u32, u32)| {
+			let channels = match format {
+				glow::RGBA | glow::RGBA_INTEGER => 4,
+				glow::DEPTH_STENCIL => 1,
+				_ => panic!("unexpected format 0x{:04x}", format)
+			};
+			let size = match kind {
+				glow::UNSIGNED_BYTE => 1,
+				glow::FLOAT | glow::UNSIGNED_INT | glow::UNSIGNED_INT_24_8 => 4,
+				_ => panic!("unexpected type 0x{:04x}", kind)
+			};
+			channels * size
+		};
+		let formats = [
+			TextureFormat::Rgba8Unorm,
+			TextureFormat::Srgb8Alpha8,
+			TextureFormat::Rgba32Float,
+			TextureFormat::Depth24Stencil8
+		];
+		for format in &formats {
+			assert_eq!(format.bytes_per_texel(), texel_size(format.as_opengl()),
+				"wrong texel size for {:?}", format);
+		}
+		for format in &[TexelFormat::Rgba32Float, TexelFormat::Rgba32Uint] {
+			assert_eq!(format.bytes_per_texel(), texel_size(format.as_opengl()),
+				"wrong texel size for {:?}", format);
+		}
+	}
+
+	#[test]
+	fn texture_targets() {
+		check(&[
+			(TextureExtent::D1 { length: 4 }, glow::TEXTURE_1D),
+			(TextureExtent::D2 { width: 4, height: 4 }, glow::TEXTURE_2D),
+			(TextureExtent::D2Array { width: 4, height: 4, layers: 4 }, glow::TEXTURE_2D_ARRAY),
+			(TextureExtent::D3 { width: 4, height: 4, depth: 4 }, glow::TEXTURE_3D),
+			(TextureExtent::Cube { size: 4 }, glow::TEXTURE_CUBE_MAP),
+		], TextureExtent::target, true);
+		check(&[
+			(0, glow::TEXTURE_CUBE_MAP_POSITIVE_X),
+			(1, glow::TEXTURE_CUBE_MAP_NEGATIVE_X),
+			(2, glow::TEXTURE_CUBE_MAP_POSITIVE_Y),
+			(3, glow::TEXTURE_CUBE_MAP_NEGATIVE_Y),
+			(4, glow::TEXTURE_CUBE_MAP_POSITIVE_Z),
+			(5, glow::TEXTURE_CUBE_MAP_NEGATIVE_Z),
+		], |face: &u32| TextureExtent::cube_face_target(*face), true);
+	}
+
+	#[test]
+	fn sampling_state() {
+		check(&[
+			((TextureFilter::Nearest, false), glow::NEAREST),
+			((TextureFilter::Linear, false), glow::LINEAR),
+			((TextureFilter::Nearest, true), glow::NEAREST_MIPMAP_NEAREST),
+			((TextureFilter::Linear, true), glow::LINEAR_MIPMAP_LINEAR),
+		], |(filter, mipmapped)| filter.as_opengl(*mipmapped), true);
+		check(&[
+			(AddressMode::Repeat, glow::REPEAT),
+			(AddressMode::MirrorRepeat, glow::MIRRORED_REPEAT),
+			(AddressMode::ClampToEdge, glow::CLAMP_TO_EDGE),
+		], AddressMode::as_opengl, true);
+		check(&[
+			(ChannelSource::Red, glow::RED as i32),
+			(ChannelSource::Green, glow::GREEN as i32),
+			(ChannelSource::Blue, glow::BLUE as i32),
+			(ChannelSource::Alpha, glow::ALPHA as i32),
+			(ChannelSource::Zero, glow::ZERO as i32),
+			(ChannelSource::One, glow::ONE as i32),
+		], ChannelSource::as_opengl, true);
+	}
+
+	#[test]
+	fn buffers_and_queries() {
+		check(&[
+			(BufferProfile::StaticUpload, glow::STATIC_DRAW),
+			(BufferProfile::DynamicUpload, glow::DYNAMIC_DRAW),
+			(BufferProfile::StaticDevice, glow::STATIC_COPY),
+			(BufferProfile::DynamicDevice, glow::DYNAMIC_COPY),
+		], BufferProfile::as_opengl, true);
+		check(&[
+			(QueryKind::AnySamplesPassed, glow::ANY_SAMPLES_PASSED),
+			(QueryKind::TimeElapsed, glow::TIME_ELAPSED),
+		], QueryKind::as_opengl, true);
+	}
+}
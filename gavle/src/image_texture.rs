@@ -0,0 +1,138 @@
+use ::image::{DynamicImage, RgbaImage};
+use crate::Device;
+use crate::texture::{Texture, TextureDescriptor, TextureError, TextureExtent, TextureFormat, Mipmap};
+
+/** What the texels of an image stand for, which decides the format of the
+ * textures created from it. */
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum ImageUsage {
+	/** Colors, such as those of an albedo map, which images store encoded
+	 * with the sRGB transfer function. These get stored in the
+	 * [`TextureFormat::Srgb8Alpha8`] format, so that shaders see them as the
+	 * linear colors they need to do lighting with.
+	 *
+	 * [`TextureFormat::Srgb8Alpha8`]: TextureFormat::Srgb8Alpha8 */
+	Color,
+	/** Data other than colors, such as normals or roughness, which is already
+	 * linear. These get stored in the [`TextureFormat::Rgba8Unorm`] format,
+	 * and are seen by shaders just the way they are stored.
+	 *
+	 * [`TextureFormat::Rgba8Unorm`]: TextureFormat::Rgba8Unorm */
+	Data,
+}
+
+/** Descriptor specifying the parameters for a texture created from an image. */
+#[derive(Debug, Copy, Clone)]
+pub struct ImageTextureDescriptor {
+	/** What the texels of the image stand for. */
+	pub usage: ImageUsage,
+	/** Whether the rows of the image get flipped, so that its first row ends
+	 * up at the bottom of the texture, where texture coordinates start in
+	 * OpenGL. Images start with their top row, so this is needed for texture
+	 * coordinates made for APIs where coordinates start at the top. */
+	pub flip_y: bool,
+	/** Mipmap behavior of the texture. Mip levels may not be given manually
+	 * here, as images only have a single level. */
+	pub mip: Mipmap,
+}
+
+impl Device {
+	/** Creates a two-dimensional texture holding the given image, whose
+	 * pixels get converted to RGBA with eight bits for every channel.
+	 * Grayscale images have their luminance repeated on the color channels,
+	 * and images without alpha come out opaque.
+	 *
+	 * Color images are stored in the [`Rgba8Unorm`] format instead of the
+	 * [`Srgb8Alpha8`] format in legacy contexts, which don't support it, so
+	 * shaders sampling them there have to do the conversion themselves.
+	 *
+	 * [`Rgba8Unorm`]: TextureFormat::Rgba8Unorm
+	 * [`Srgb8Alpha8`]: TextureFormat::Srgb8Alpha8 */
+	pub fn create_texture_from_image(
+		&self,
+		image: &DynamicImage,
+		descriptor: &ImageTextureDescriptor)
+		-> Result<Texture, TextureError> {
+
+		let profile = self.information().version.profile;
+		let mut format = image_format(descriptor.usage);
+		if format.internal_format(profile).is_none() {
+			log::warn!(target: "gavle::device",
+				"textures can't be stored in the {:?} format in {:?} \
+				contexts, storing the image as {:?} instead",
+				format, profile, TextureFormat::Rgba8Unorm);
+			format = TextureFormat::Rgba8Unorm;
+		}
+
+		let texels = image_texels(image, descriptor.flip_y);
+		self.create_texture_with_data(
+			&TextureDescriptor {
+				extent: TextureExtent::D2 {
+					width: texels.width(),
+					height: texels.height()
+				},
+				format,
+				mip: descriptor.mip,
+				swizzle: None
+			},
+			texels.into_raw())
+	}
+}
+
+/** The format textures created from images used the given way are stored in. */
+fn image_format(usage: ImageUsage) -> TextureFormat {
+	match usage {
+		ImageUsage::Color => TextureFormat::Srgb8Alpha8,
+		ImageUsage::Data => TextureFormat::Rgba8Unorm,
+	}
+}
+
+/** Converts the given image to RGBA with eight bits for every channel, with
+ * its rows optionally flipped. */
+fn image_texels(image: &DynamicImage, flip_y: bool) -> RgbaImage {
+	let mut texels = image.to_rgba8();
+	if flip_y {
+		::image::imageops::flip_vertical_in_place(&mut texels);
+	}
+	texels
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use ::image::{GrayImage, RgbImage, Luma, Rgb};
+
+	#[test]
+	fn formats_follow_usage() {
+		assert_eq!(image_format(ImageUsage::Color), TextureFormat::Srgb8Alpha8);
+		assert_eq!(image_format(ImageUsage::Data), TextureFormat::Rgba8Unorm);
+	}
+
+	#[test]
+	fn grayscale_is_repeated_and_opaque() {
+		let image = GrayImage::from_fn(2, 1, |x, _| Luma([x as u8 * 100 + 7]));
+		let texels = image_texels(&DynamicImage::ImageLuma8(image), false);
+
+		assert_eq!(texels.into_raw(), vec![
+			7, 7, 7, 255,
+			107, 107, 107, 255
+		]);
+	}
+
+	#[test]
+	fn rows_get_flipped() {
+		let image = RgbImage::from_fn(1, 3, |_, y| Rgb([y as u8, 0, 10]));
+		let image = DynamicImage::ImageRgb8(image);
+
+		assert_eq!(image_texels(&image, false).into_raw(), vec![
+			0, 0, 10, 255,
+			1, 0, 10, 255,
+			2, 0, 10, 255
+		]);
+		assert_eq!(image_texels(&image, true).into_raw(), vec![
+			2, 0, 10, 255,
+			1, 0, 10, 255,
+			0, 0, 10, 255
+		]);
+	}
+}
@@ -3,6 +3,7 @@ use std::cmp::Ordering;
 use std::convert::{TryFrom, TryInto};
 use std::borrow::Cow;
 use std::collections::HashSet;
+use crate::texture::TextureFormat;
 
 /** Queries for a parameter with an `i32` result, checking whether it is
  * supported and, if it is not, returns `None`. */
@@ -27,6 +28,17 @@ unsafe fn checked_get_parameter_i32(
 pub struct Information {
 	/** Version and profile of the current context. */
 	pub version: Version,
+	/** Name of the renderer behind the current context, as reported by the
+	 * implementation. */
+	pub renderer: String,
+	/** Name of the company behind the implementation of the current context,
+	 * as reported by it. Along with the [`renderer`], this is what bug reports
+	 * should mention to tell implementations apart.
+	 *
+	 * [`renderer`]: Self::renderer */
+	pub vendor: String,
+	/** Extensions exposed by the current context. */
+	pub extensions: HashSet<Extension>,
 	/** Capabilities of this context. */
 	pub capabilities: Capabilities,
 	/** Limits of this context. */
@@ -44,17 +56,33 @@ impl Information {
 	/** Minimum supported version of the WebGL specification. */
 	const MIN_WEB: Release = Release { major: 2, minor: 0 };
 
+	/** Minimum supported version of the OpenGL ES specification, running with
+	 * the reduced feature set of [`Profile::EsLegacy`]. */
+	const MIN_ES_LEGACY: Release = Release { major: 2, minor: 0 };
+
+	/** Minimum supported version of the WebGL specification, running with
+	 * the reduced feature set of [`Profile::WebLegacy`]. */
+	const MIN_WEB_LEGACY: Release = Release { major: 1, minor: 0 };
+
 	/** Collect information on the given context and check whether it is
 	 * supported by the Gavle implementation or not. */
 	pub fn collect(context: &Context) -> Result<Self, UnsupportedContext> {
 		let gl = context;
-		let (version, major, minor) = unsafe {(
+		let (version, renderer, vendor, major, minor) = unsafe {(
 			gl.get_parameter_string(glow::VERSION),
+			gl.get_parameter_string(glow::RENDERER),
+			gl.get_parameter_string(glow::VENDOR),
 			checked_get_parameter_i32(gl, glow::MAJOR_VERSION),
 			checked_get_parameter_i32(gl, glow::MINOR_VERSION),
 		)};
-		debug!("Reported OpenGL Version String: {}", version);
-		debug!("Reported OpenGL Version: {:?}.{:?}", major, minor);
+		debug!(target: "gavle::device",
+			"Reported OpenGL Version String: {}", version);
+		debug!(target: "gavle::device",
+			"Reported OpenGL Version: {:?}.{:?}", major, minor);
+		debug!(target: "gavle::device",
+			"Reported OpenGL Renderer: {}", renderer);
+		debug!(target: "gavle::device",
+			"Reported OpenGL Vendor: {}", vendor);
 
 		/* Parse the version string. */
 		let version = Version::parse(&version)
@@ -75,7 +103,8 @@ impl Information {
 					})
 				}
 			},
-			(None, None) => warn!("implementation does not support dedicated \
+			(None, None) => warn!(target: "gavle::device",
+				"implementation does not support dedicated \
 				version query targets. we will rely solely on the version \
 				string, which may not be as accurate"),
 			_ => return Err(UnsupportedContext::InvalidRelease(major, minor))
@@ -86,6 +115,8 @@ impl Information {
 			Profile::Core if version.release >= Self::MIN_CORE => {},
 			Profile::Es   if version.release >= Self::MIN_ES   => {},
 			Profile::Web  if version.release >= Self::MIN_WEB  => {},
+			Profile::EsLegacy  if version.release >= Self::MIN_ES_LEGACY  => {},
+			Profile::WebLegacy if version.release >= Self::MIN_WEB_LEGACY => {},
 			_ => return Err(UnsupportedContext::UnsupportedRelease {
 				profile: version.profile,
 				release: (version.release.major, version.release.minor)
@@ -96,16 +127,22 @@ impl Information {
 		let mut extensions = HashSet::new();
 		let _ = unsafe { Extension::enumerate(gl, &mut extensions) }?;
 
-		debug!("Discovered {} extensions: ", extensions.len());
+		debug!(target: "gavle::device",
+			"Discovered {} extensions", extensions.len());
 		for extension in &extensions {
-			debug!("    - {}", extension)
+			trace!(target: "gavle::device", "    - {}", extension)
 		}
 
-		/* Gather capability information. */
-		let capabilities = Capabilities {
-			buffer_mapping: version.profile != Profile::Web,
-		};
-		let limits = Limits::collect(context)?;
+		if version.profile.is_legacy() {
+			warn!(target: "gavle::device",
+				"{} only supports a reduced feature set. uniform buffers and \
+				32-bit indices may be unavailable, and vertex arrays and \
+				instancing may be emulated",
+				version);
+		}
+
+		let capabilities = Capabilities::new(&version, &extensions);
+		let limits = Limits::collect(context, version.profile)?;
 		let features = Features {
 			sampler_anisotropy:
 				extensions.contains(&Extension::EXT_TEXTURE_FILTER_ANISOTROPIC),
@@ -119,11 +156,65 @@ impl Information {
 
 		Ok(Self {
 			version,
+			renderer,
+			vendor,
+			extensions,
 			capabilities,
 			limits,
 			features
 		})
 	}
+
+	/** Whether textures in the given format can be created in this context.
+	 * Compressed formats depend on the [`Capabilities`] of the context, and
+	 * legacy contexts only support a handful of formats to begin with. */
+	pub fn supports_texture_format(&self, format: TextureFormat) -> bool {
+		if format.internal_format(self.version.profile).is_none() {
+			return false
+		}
+		match format {
+			TextureFormat::Etc2Rgb8Unorm
+				| TextureFormat::Etc2Rgba8Unorm =>
+				self.capabilities.compressed_etc2,
+			TextureFormat::Bc1RgbaUnorm
+				| TextureFormat::Bc2RgbaUnorm
+				| TextureFormat::Bc3RgbaUnorm =>
+				self.capabilities.compressed_s3tc,
+			TextureFormat::Srgb8Alpha8 => self.capabilities.srgb_textures,
+			TextureFormat::Rgba32Float
+				| TextureFormat::Rgba8Unorm
+				| TextureFormat::Depth24Stencil8 => true,
+		}
+	}
+
+	/** Whether the context exposes the extension with the given name, exactly
+	 * as the implementation reports it. Keep in mind that WebGL names its
+	 * extensions without the `GL_` prefix OpenGL and OpenGL ES use. */
+	pub fn has_extension(&self, name: &str) -> bool {
+		self.extensions.contains(name)
+	}
+}
+
+/** Logs a short summary of the given context information at the `info` level.
+ *
+ * This is meant to be called once, right after a device has been created, so
+ * that logs always carry the basics of the context they were produced in. The
+ * full information is only ever logged at the `trace` level. */
+pub fn log_startup_summary(information: &Information) {
+	let limits = &information.limits;
+	info!(target: "gavle::device",
+		"Using {} on {} by {}",
+		information.version,
+		information.renderer,
+		information.vendor);
+	info!(target: "gavle::device",
+		"Limits: {} textures, {}px textures, {} uniform blocks of up to {} \
+		bytes, {} color attachments",
+		limits.max_textures,
+		limits.max_texture_size,
+		limits.max_uniform_block_bindings,
+		limits.max_uniform_block_size,
+		limits.max_framebuffer_color_attachments);
 }
 
 /** Named extension. */
@@ -137,6 +228,196 @@ impl Extension {
 	 */
 	pub const EXT_TEXTURE_FILTER_ANISOTROPIC: Self =
 		Self(Cow::Borrowed("GL_EXT_texture_filter_anisotropic"));
+
+	/** Support for timer queries in OpenGL ES.
+	 *
+	 * Registry entry:
+	 * https://www.khronos.org/registry/OpenGL/extensions/EXT/EXT_disjoint_timer_query.txt.
+	 */
+	pub const EXT_DISJOINT_TIMER_QUERY: Self =
+		Self(Cow::Borrowed("GL_EXT_disjoint_timer_query"));
+
+	/** Support for timer queries in WebGL2, which names its extensions
+	 * without the `GL_` prefix.
+	 *
+	 * Registry entry:
+	 * https://www.khronos.org/registry/webgl/extensions/EXT_disjoint_timer_query_webgl2/.
+	 */
+	pub const EXT_DISJOINT_TIMER_QUERY_WEBGL2: Self =
+		Self(Cow::Borrowed("EXT_disjoint_timer_query_webgl2"));
+
+	/** Support for debug output and object labels.
+	 *
+	 * Registry entry:
+	 * https://www.khronos.org/registry/OpenGL/extensions/KHR/KHR_debug.txt.
+	 */
+	pub const KHR_DEBUG: Self =
+		Self(Cow::Borrowed("GL_KHR_debug"));
+
+	/** Support for vertex array objects in OpenGL ES 2.0.
+	 *
+	 * Registry entry:
+	 * https://www.khronos.org/registry/OpenGL/extensions/OES/OES_vertex_array_object.txt.
+	 */
+	pub const OES_VERTEX_ARRAY_OBJECT: Self =
+		Self(Cow::Borrowed("GL_OES_vertex_array_object"));
+
+	/** Support for vertex array objects in WebGL1.
+	 *
+	 * Registry entry:
+	 * https://www.khronos.org/registry/webgl/extensions/OES_vertex_array_object/.
+	 */
+	pub const OES_VERTEX_ARRAY_OBJECT_WEBGL: Self =
+		Self(Cow::Borrowed("OES_vertex_array_object"));
+
+	/** Support for instanced draws and per-instance attributes in OpenGL ES
+	 * 2.0, as exposed by ANGLE.
+	 *
+	 * Registry entry:
+	 * https://www.khronos.org/registry/OpenGL/extensions/ANGLE/ANGLE_instanced_arrays.txt.
+	 */
+	pub const ANGLE_INSTANCED_ARRAYS: Self =
+		Self(Cow::Borrowed("GL_ANGLE_instanced_arrays"));
+
+	/** Support for instanced draws and per-instance attributes in OpenGL ES
+	 * 2.0.
+	 *
+	 * Registry entry:
+	 * https://www.khronos.org/registry/OpenGL/extensions/EXT/EXT_instanced_arrays.txt.
+	 */
+	pub const EXT_INSTANCED_ARRAYS: Self =
+		Self(Cow::Borrowed("GL_EXT_instanced_arrays"));
+
+	/** Support for instanced draws and per-instance attributes in WebGL1.
+	 *
+	 * Registry entry:
+	 * https://www.khronos.org/registry/webgl/extensions/ANGLE_instanced_arrays/.
+	 */
+	pub const ANGLE_INSTANCED_ARRAYS_WEBGL: Self =
+		Self(Cow::Borrowed("ANGLE_instanced_arrays"));
+
+	/** Support for 32-bit indices in OpenGL ES 2.0.
+	 *
+	 * Registry entry:
+	 * https://www.khronos.org/registry/OpenGL/extensions/OES/OES_element_index_uint.txt.
+	 */
+	pub const OES_ELEMENT_INDEX_UINT: Self =
+		Self(Cow::Borrowed("GL_OES_element_index_uint"));
+
+	/** Support for 32-bit indices in WebGL1.
+	 *
+	 * Registry entry:
+	 * https://www.khronos.org/registry/webgl/extensions/OES_element_index_uint/.
+	 */
+	pub const OES_ELEMENT_INDEX_UINT_WEBGL: Self =
+		Self(Cow::Borrowed("OES_element_index_uint"));
+
+	/** Support for creating shaders from SPIR-V modules in OpenGL.
+	 *
+	 * Registry entry:
+	 * https://www.khronos.org/registry/OpenGL/extensions/ARB/ARB_gl_spirv.txt.
+	 */
+	pub const ARB_GL_SPIRV: Self =
+		Self(Cow::Borrowed("GL_ARB_gl_spirv"));
+
+	/** Support for the features of OpenGL ES 3.0 in OpenGL, among which are
+	 * the ETC2 compressed texture formats.
+	 *
+	 * Registry entry:
+	 * https://www.khronos.org/registry/OpenGL/extensions/ARB/ARB_ES3_compatibility.txt.
+	 */
+	pub const ARB_ES3_COMPATIBILITY: Self =
+		Self(Cow::Borrowed("GL_ARB_ES3_compatibility"));
+
+	/** Support for linear filtering of floating point textures in OpenGL ES.
+	 *
+	 * Registry entry:
+	 * https://www.khronos.org/registry/OpenGL/extensions/OES/OES_texture_float_linear.txt.
+	 */
+	pub const OES_TEXTURE_FLOAT_LINEAR: Self =
+		Self(Cow::Borrowed("GL_OES_texture_float_linear"));
+
+	/** Support for linear filtering of floating point textures in WebGL.
+	 *
+	 * Registry entry:
+	 * https://www.khronos.org/registry/webgl/extensions/OES_texture_float_linear/.
+	 */
+	pub const OES_TEXTURE_FLOAT_LINEAR_WEBGL: Self =
+		Self(Cow::Borrowed("OES_texture_float_linear"));
+
+	/** Support for rendering to floating point color buffers in OpenGL ES.
+	 *
+	 * Registry entry:
+	 * https://www.khronos.org/registry/OpenGL/extensions/EXT/EXT_color_buffer_float.txt.
+	 */
+	pub const EXT_COLOR_BUFFER_FLOAT: Self =
+		Self(Cow::Borrowed("GL_EXT_color_buffer_float"));
+
+	/** Support for rendering to floating point color buffers in WebGL 2.
+	 *
+	 * Registry entry:
+	 * https://www.khronos.org/registry/webgl/extensions/EXT_color_buffer_float/.
+	 */
+	pub const EXT_COLOR_BUFFER_FLOAT_WEBGL: Self =
+		Self(Cow::Borrowed("EXT_color_buffer_float"));
+
+	/** Support for textures compressed with S3TC in OpenGL and OpenGL ES.
+	 *
+	 * Registry entry:
+	 * https://www.khronos.org/registry/OpenGL/extensions/EXT/EXT_texture_compression_s3tc.txt.
+	 */
+	pub const EXT_TEXTURE_COMPRESSION_S3TC: Self =
+		Self(Cow::Borrowed("GL_EXT_texture_compression_s3tc"));
+
+	/** Support for textures compressed with S3TC in WebGL.
+	 *
+	 * Registry entry:
+	 * https://www.khronos.org/registry/webgl/extensions/WEBGL_compressed_texture_s3tc/.
+	 */
+	pub const WEBGL_COMPRESSED_TEXTURE_S3TC: Self =
+		Self(Cow::Borrowed("WEBGL_compressed_texture_s3tc"));
+
+	/** Support for textures compressed with ETC2 and EAC in WebGL.
+	 *
+	 * Registry entry:
+	 * https://www.khronos.org/registry/webgl/extensions/WEBGL_compressed_texture_etc/.
+	 */
+	pub const WEBGL_COMPRESSED_TEXTURE_ETC: Self =
+		Self(Cow::Borrowed("WEBGL_compressed_texture_etc"));
+
+	/** Support for per draw buffer blend state and write masks in OpenGL ES.
+	 *
+	 * Registry entry:
+	 * https://www.khronos.org/registry/OpenGL/extensions/OES/OES_draw_buffers_indexed.txt.
+	 */
+	pub const OES_DRAW_BUFFERS_INDEXED: Self =
+		Self(Cow::Borrowed("GL_OES_draw_buffers_indexed"));
+
+	/** Support for per draw buffer blend state and write masks in OpenGL ES,
+	 * as an extension that predates the one from the OES.
+	 *
+	 * Registry entry:
+	 * https://www.khronos.org/registry/OpenGL/extensions/EXT/EXT_draw_buffers_indexed.txt.
+	 */
+	pub const EXT_DRAW_BUFFERS_INDEXED: Self =
+		Self(Cow::Borrowed("GL_EXT_draw_buffers_indexed"));
+
+	/** Support for per draw buffer blend state and write masks in WebGL2.
+	 *
+	 * Registry entry:
+	 * https://www.khronos.org/registry/webgl/extensions/OES_draw_buffers_indexed/.
+	 */
+	pub const OES_DRAW_BUFFERS_INDEXED_WEBGL: Self =
+		Self(Cow::Borrowed("OES_draw_buffers_indexed"));
+
+	/** Support for turning the encoding of colors written to sRGB attachments
+	 * on and off in OpenGL ES.
+	 *
+	 * Registry entry:
+	 * https://www.khronos.org/registry/OpenGL/extensions/EXT/EXT_sRGB_write_control.txt.
+	 */
+	pub const EXT_SRGB_WRITE_CONTROL: Self =
+		Self(Cow::Borrowed("GL_EXT_sRGB_write_control"));
 }
 impl Extension {
 	/** Enumerate all of the available extensions using the given context handle. */
@@ -183,7 +464,8 @@ impl Extension {
 				count += 1;
 			}
 		} else {
-			warn!("Probing the extension count with GL_NUM_EXTENSIONS is not \
+			warn!(target: "gavle::device",
+				"Probing the extension count with GL_NUM_EXTENSIONS is not \
 				supported. Falling back to pulling the combined extension \
 				string using glGetString(GL_EXTENSIONS)");
 			let combined = gl.get_parameter_string(glow::EXTENSIONS);
@@ -200,9 +482,7 @@ impl Extension {
 						what)
 			}
 
-			let iterator = combined.split_ascii_whitespace()
-				.map(|slice| slice.to_string())
-				.map(|string| Self(Cow::Owned(string)))
+			let iterator = Self::parse_combined(&combined)
 				.inspect(|_| count += 1);
 			target.extend(iterator);
 		}
@@ -210,6 +490,24 @@ impl Extension {
 		Ok(count)
 	}
 }
+impl Extension {
+	/** Name of this extension, as reported by the implementation. */
+	pub fn name(&self) -> &str {
+		&self.0
+	}
+
+	/** Parses the names in a combined extension string, as returned by
+	 * `glGetString(GL_EXTENSIONS)`, in which they are separated by spaces. */
+	fn parse_combined(combined: &str) -> impl Iterator<Item = Self> + '_ {
+		combined.split_ascii_whitespace()
+			.map(|name| Self(Cow::Owned(name.to_string())))
+	}
+}
+impl std::borrow::Borrow<str> for Extension {
+	fn borrow(&self) -> &str {
+		&self.0
+	}
+}
 impl std::fmt::Display for Extension {
 	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
 		write!(f, "{}", &self.0)
@@ -218,13 +516,311 @@ impl std::fmt::Display for Extension {
 
 /** Capabilities of a given context.
  *
- * None of these limit what the user may do with the API, instead, these
- * capabilities are meant to allow the library to internally select a faster
- * code path whenever the implementation supports it. */
+ * Most of these don't limit what the user may do with the API, instead, they
+ * are meant to allow the library to internally select a faster code path
+ * whenever the implementation supports it. The exceptions are
+ * [`uniform_buffers`] and [`index_uint32`], which are only ever missing from
+ * legacy contexts, and [`spirv_shaders`], without which the features they
+ * name can't be used.
+ *
+ * [`uniform_buffers`]: Self::uniform_buffers
+ * [`index_uint32`]: Self::index_uint32
+ * [`spirv_shaders`]: Self::spirv_shaders */
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub struct Capabilities {
 	/** Whether the context supports direct mapping of buffers to host memory. */
 	pub buffer_mapping: bool,
+	/** Whether the context supports sampler objects, which hold filtering
+	 * parameters apart from the textures they get used with. Without them,
+	 * those parameters are set on the textures themselves whenever they get
+	 * bound. */
+	pub sampler_objects: bool,
+	/** Whether the context can offset the indices of indexed draws by a base
+	 * vertex, as used by [`DrawIndexedCommand::base_vertex`].
+	 *
+	 * [`DrawIndexedCommand::base_vertex`]: crate::DrawIndexedCommand::base_vertex */
+	pub base_vertex: bool,
+	/** Whether the context supports measuring the time the device takes to get
+	 * through a series of commands, with [`QueryKind::TimeElapsed`] queries.
+	 *
+	 * [`QueryKind::TimeElapsed`]: crate::QueryKind::TimeElapsed */
+	pub timer_queries: bool,
+	/** Whether the context supports attaching debug labels to its objects,
+	 * which show up in debuggers and driver messages. Without it, labels set
+	 * on objects are only used in the messages of this crate. */
+	pub debug_labels: bool,
+	/** Whether the context supports uniform buffers. Without them, bind groups
+	 * with [`UniformBind::Buffer`] entries can't be created.
+	 *
+	 * [`UniformBind::Buffer`]: crate::UniformBind::Buffer */
+	pub uniform_buffers: bool,
+	/** Whether the context supports vertex array objects. Without them, the
+	 * vertex attributes of a pipeline are specified all over again every time
+	 * it gets bound. */
+	pub vertex_arrays: bool,
+	/** Whether the context supports instanced draws. Without them, draws of
+	 * more than one instance are done with one draw call per instance, and
+	 * pipelines with per-instance vertex buffers can't be created. */
+	pub instancing: bool,
+	/** Whether the context supports 32-bit indices. Without them, pipelines
+	 * using [`IndexFormat::Uint32`] can't be created.
+	 *
+	 * [`IndexFormat::Uint32`]: crate::IndexFormat::Uint32 */
+	pub index_uint32: bool,
+	/** Whether shaders can be created from SPIR-V modules, with
+	 * [`ShaderSource::SpirV`]. This is only ever available to devices created
+	 * with [`Device::new_from_loader_function`], as creating these shaders
+	 * takes entry points that have to be loaded separately.
+	 *
+	 * [`ShaderSource::SpirV`]: crate::ShaderSource::SpirV
+	 * [`Device::new_from_loader_function`]: crate::Device::new_from_loader_function */
+	pub spirv_shaders: bool,
+	/** Whether the context can swizzle the channels of textures as they get
+	 * sampled. Without it, the [`swizzle`] of a texture gets applied to its
+	 * data on the host, as it gets uploaded.
+	 *
+	 * [`swizzle`]: crate::TextureDescriptor::swizzle */
+	pub texture_swizzle: bool,
+	/** Whether textures in the [`Rgba32Float`] format may be sampled with
+	 * linear filtering. Without it, sampling them with anything other than
+	 * [`TextureFilter::Nearest`] leaves the texture incomplete, and every
+	 * sample comes back black.
+	 *
+	 * [`Rgba32Float`]: crate::TextureFormat::Rgba32Float
+	 * [`TextureFilter::Nearest`]: crate::TextureFilter::Nearest */
+	pub float_texture_filtering: bool,
+	/** Whether textures in the [`Rgba32Float`] format are color-renderable,
+	 * and may thus be attached to framebuffers as color targets. OpenGL also
+	 * needs this, along with [`float_texture_filtering`], in order to
+	 * generate mipmaps for them.
+	 *
+	 * [`Rgba32Float`]: crate::TextureFormat::Rgba32Float
+	 * [`float_texture_filtering`]: Self::float_texture_filtering */
+	pub float_color_buffers: bool,
+	/** Whether textures may be created in the [`Etc2Rgb8Unorm`] and
+	 * [`Etc2Rgba8Unorm`] compressed formats. These are part of OpenGL ES, and
+	 * of OpenGL 4.3 and contexts with `ARB_ES3_compatibility`, where they may
+	 * well be decompressed by the driver, but WebGL only has them through an
+	 * extension, which is mostly found on mobile.
+	 *
+	 * [`Etc2Rgb8Unorm`]: crate::TextureFormat::Etc2Rgb8Unorm
+	 * [`Etc2Rgba8Unorm`]: crate::TextureFormat::Etc2Rgba8Unorm */
+	pub compressed_etc2: bool,
+	/** Whether textures may be created in the [`Bc1RgbaUnorm`],
+	 * [`Bc2RgbaUnorm`] and [`Bc3RgbaUnorm`] compressed formats, which are
+	 * only ever available through extensions, mostly found on desktops.
+	 *
+	 * [`Bc1RgbaUnorm`]: crate::TextureFormat::Bc1RgbaUnorm
+	 * [`Bc2RgbaUnorm`]: crate::TextureFormat::Bc2RgbaUnorm
+	 * [`Bc3RgbaUnorm`]: crate::TextureFormat::Bc3RgbaUnorm */
+	pub compressed_s3tc: bool,
+	/** Whether the color [`targets`] of a pipeline may each have their own
+	 * blend state and write mask. Without it, pipelines writing to more than
+	 * one color attachment have to use the same state for all of them.
+	 *
+	 * [`targets`]: crate::FragmentState::targets */
+	pub indexed_draw_buffers: bool,
+	/** Whether data can be copied from one buffer to another on the device,
+	 * with [`Device::copy_buffer_to_buffer`]. This is only ever missing from
+	 * legacy contexts, which have no way to get data out of a buffer at all.
+	 *
+	 * [`Device::copy_buffer_to_buffer`]: crate::Device::copy_buffer_to_buffer */
+	pub buffer_copies: bool,
+	/** Whether the outputs of the vertex stage can be captured into buffers,
+	 * with the pipelines made by [`Device::create_transform_feedback_pipeline`].
+	 * This is also only ever missing from legacy contexts.
+	 *
+	 * [`Device::create_transform_feedback_pipeline`]: crate::Device::create_transform_feedback_pipeline */
+	pub transform_feedback: bool,
+	/** Whether texels can be staged in an [`UploadBuffer`] on the device, for
+	 * the implementation to copy them into textures asynchronously. WebGL2 has
+	 * pixel unpack buffers, but since it can't map them, filling one is just
+	 * as much of a copy as uploading the texels directly, so it goes without
+	 * them, along with legacy contexts. Upload buffers keep their texels in
+	 * host memory wherever this is missing.
+	 *
+	 * [`UploadBuffer`]: crate::UploadBuffer */
+	pub pixel_unpack_buffers: bool,
+	/** Whether textures may be created in the [`Srgb8Alpha8`] format, whose
+	 * texels get converted from sRGB to linear values as they get sampled.
+	 * Legacy contexts only have them through extensions, which are not
+	 * supported.
+	 *
+	 * [`Srgb8Alpha8`]: crate::TextureFormat::Srgb8Alpha8 */
+	pub srgb_textures: bool,
+	/** Whether the context can turn the encoding of colors written to
+	 * attachments that use the sRGB encoding on and off, which is done for the
+	 * default framebuffer with [`Device::set_srgb_encoding`]. OpenGL can, while
+	 * OpenGL ES needs an extension for it, and WebGL can't, so those always
+	 * encode colors written to sRGB attachments.
+	 *
+	 * [`Device::set_srgb_encoding`]: crate::Device::set_srgb_encoding */
+	pub srgb_write_control: bool,
+	/** Whether the default framebuffer stores its colors with the sRGB
+	 * encoding, as is needed for them to get encoded as they get written to
+	 * it. This can only be told by devices created with
+	 * [`Device::new_from_loader_function`], and is never the case in WebGL.
+	 *
+	 * [`Device::new_from_loader_function`]: crate::Device::new_from_loader_function */
+	pub srgb_default_framebuffer: bool,
+	/** Whether cube maps get filtered across the edges of their faces, rather
+	 * than each face getting clamped on its own, which shows up as seams
+	 * between them. OpenGL has to have this turned on, which devices do as
+	 * they get created, while OpenGL ES 3.0 and WebGL2 always filter cube
+	 * maps this way. */
+	pub seamless_cube_maps: bool,
+	/** Whether [`TexelBuffer`]s are kept in buffer textures, which shaders
+	 * read from with `texelFetch()` on a `samplerBuffer`, rather than in the
+	 * rows of two-dimensional textures. Buffer textures are part of OpenGL
+	 * and of OpenGL ES 3.2, but not of WebGL.
+	 *
+	 * Just like with [`spirv_shaders`], the bindings we use don't expose the
+	 * entry point they need, so only devices created with
+	 * [`Device::new_from_loader_function`] get to use them.
+	 *
+	 * [`TexelBuffer`]: crate::TexelBuffer
+	 * [`spirv_shaders`]: Self::spirv_shaders
+	 * [`Device::new_from_loader_function`]: crate::Device::new_from_loader_function */
+	pub texel_buffers: bool,
+}
+
+impl Capabilities {
+	/** Capabilities of a context of the given version, exposing the given
+	 * extensions. */
+	fn new(version: &Version, extensions: &HashSet<Extension>) -> Self {
+		Self {
+			buffer_mapping: match version.profile {
+				Profile::Core | Profile::Es => true,
+				Profile::Web | Profile::EsLegacy | Profile::WebLegacy => false,
+			},
+			base_vertex: match version.profile {
+				Profile::Core | Profile::Es =>
+					version.release >= Release { major: 3, minor: 2 },
+				Profile::Web | Profile::EsLegacy | Profile::WebLegacy => false,
+			},
+			sampler_objects: match version.profile {
+				Profile::Core => version.release >= Release { major: 3, minor: 3 },
+				Profile::Es | Profile::Web => true,
+				Profile::EsLegacy | Profile::WebLegacy => false,
+			},
+			timer_queries: match version.profile {
+				Profile::Core => version.release >= Release { major: 3, minor: 3 },
+				Profile::Es =>
+					extensions.contains(&Extension::EXT_DISJOINT_TIMER_QUERY),
+				Profile::Web =>
+					extensions.contains(&Extension::EXT_DISJOINT_TIMER_QUERY_WEBGL2),
+				Profile::EsLegacy | Profile::WebLegacy => false,
+			},
+			debug_labels: match version.profile {
+				Profile::Core =>
+					version.release >= Release { major: 4, minor: 3 }
+					|| extensions.contains(&Extension::KHR_DEBUG),
+				Profile::Es =>
+					version.release >= Release { major: 3, minor: 2 }
+					|| extensions.contains(&Extension::KHR_DEBUG),
+				Profile::EsLegacy => extensions.contains(&Extension::KHR_DEBUG),
+				Profile::Web | Profile::WebLegacy => false,
+			},
+			uniform_buffers: !version.profile.is_legacy(),
+			vertex_arrays: match version.profile {
+				Profile::Core | Profile::Es | Profile::Web => true,
+				Profile::EsLegacy =>
+					extensions.contains(&Extension::OES_VERTEX_ARRAY_OBJECT),
+				Profile::WebLegacy =>
+					extensions.contains(&Extension::OES_VERTEX_ARRAY_OBJECT_WEBGL),
+			},
+			instancing: match version.profile {
+				Profile::Core | Profile::Es | Profile::Web => true,
+				Profile::EsLegacy =>
+					extensions.contains(&Extension::ANGLE_INSTANCED_ARRAYS)
+					|| extensions.contains(&Extension::EXT_INSTANCED_ARRAYS),
+				Profile::WebLegacy =>
+					extensions.contains(&Extension::ANGLE_INSTANCED_ARRAYS_WEBGL),
+			},
+			index_uint32: match version.profile {
+				Profile::Core | Profile::Es | Profile::Web => true,
+				Profile::EsLegacy =>
+					extensions.contains(&Extension::OES_ELEMENT_INDEX_UINT),
+				Profile::WebLegacy =>
+					extensions.contains(&Extension::OES_ELEMENT_INDEX_UINT_WEBGL),
+			},
+			spirv_shaders: match version.profile {
+				Profile::Core =>
+					version.release >= Release { major: 4, minor: 6 }
+					|| extensions.contains(&Extension::ARB_GL_SPIRV),
+				Profile::Es | Profile::Web | Profile::EsLegacy
+					| Profile::WebLegacy => false,
+			},
+			texture_swizzle: match version.profile {
+				Profile::Core | Profile::Es => true,
+				Profile::Web | Profile::EsLegacy | Profile::WebLegacy => false,
+			},
+			float_texture_filtering: match version.profile {
+				Profile::Core => true,
+				Profile::Es =>
+					extensions.contains(&Extension::OES_TEXTURE_FLOAT_LINEAR),
+				Profile::Web =>
+					extensions.contains(&Extension::OES_TEXTURE_FLOAT_LINEAR_WEBGL),
+				Profile::EsLegacy | Profile::WebLegacy => false,
+			},
+			float_color_buffers: match version.profile {
+				Profile::Core => true,
+				Profile::Es =>
+					extensions.contains(&Extension::EXT_COLOR_BUFFER_FLOAT),
+				Profile::Web =>
+					extensions.contains(&Extension::EXT_COLOR_BUFFER_FLOAT_WEBGL),
+				Profile::EsLegacy | Profile::WebLegacy => false,
+			},
+			compressed_etc2: match version.profile {
+				Profile::Core =>
+					version.release >= Release { major: 4, minor: 3 }
+					|| extensions.contains(&Extension::ARB_ES3_COMPATIBILITY),
+				Profile::Es => true,
+				Profile::Web =>
+					extensions.contains(&Extension::WEBGL_COMPRESSED_TEXTURE_ETC),
+				Profile::EsLegacy | Profile::WebLegacy => false,
+			},
+			compressed_s3tc: match version.profile {
+				Profile::Core | Profile::Es =>
+					extensions.contains(&Extension::EXT_TEXTURE_COMPRESSION_S3TC),
+				Profile::Web =>
+					extensions.contains(&Extension::WEBGL_COMPRESSED_TEXTURE_S3TC),
+				Profile::EsLegacy | Profile::WebLegacy => false,
+			},
+			indexed_draw_buffers: match version.profile {
+				Profile::Core => version.release >= Release { major: 4, minor: 0 },
+				Profile::Es =>
+					version.release >= Release { major: 3, minor: 2 }
+					|| extensions.contains(&Extension::OES_DRAW_BUFFERS_INDEXED)
+					|| extensions.contains(&Extension::EXT_DRAW_BUFFERS_INDEXED),
+				Profile::Web =>
+					extensions.contains(&Extension::OES_DRAW_BUFFERS_INDEXED_WEBGL),
+				Profile::EsLegacy | Profile::WebLegacy => false,
+			},
+			buffer_copies: !version.profile.is_legacy(),
+			transform_feedback: !version.profile.is_legacy(),
+			pixel_unpack_buffers: match version.profile {
+				Profile::Core | Profile::Es => true,
+				Profile::Web | Profile::EsLegacy | Profile::WebLegacy => false,
+			},
+			srgb_textures: !version.profile.is_legacy(),
+			srgb_write_control: match version.profile {
+				Profile::Core => true,
+				Profile::Es =>
+					extensions.contains(&Extension::EXT_SRGB_WRITE_CONTROL),
+				Profile::Web | Profile::EsLegacy | Profile::WebLegacy => false,
+			},
+			/* This gets filled in by the device, as it takes an entry point
+			 * we have to load separately. */
+			srgb_default_framebuffer: false,
+			seamless_cube_maps: !version.profile.is_legacy(),
+			texel_buffers: match version.profile {
+				Profile::Core => true,
+				Profile::Es => version.release >= Release { major: 3, minor: 2 },
+				Profile::Web | Profile::EsLegacy | Profile::WebLegacy => false,
+			},
+		}
+	}
 }
 
 /** Features of a given context.
@@ -252,9 +848,15 @@ pub struct Limits {
 	/** The maximum extent of each of the axes in a 3D texture, measured in
 	 * pixels. */
 	pub max_texture_size_3d: u32,
+	/** The maximum number of texels in a buffer texture, in contexts that
+	 * have them. */
+	pub max_texture_buffer_size: Option<u32>,
 	/** The maximum number of layers allowed in a 2D array texture. The maximum
 	 * size of the individual layers is [`max_texture_size`]. */
 	pub max_texture_layers: u32,
+	/** The maximum extent of each of the axes of the faces of a cube map,
+	 * measured in pixels. */
+	pub max_cube_map_texture_size: u32,
 	/** Maximum number of uniform blocks available to the user for a given draw
 	 * command. This is the maximum number of uniform buffers a bind group
 	 * is allowed to have. */
@@ -262,6 +864,18 @@ pub struct Limits {
 	/** The maximum size of a single bound uniform block. Uniform blocks larger
 	 * than this number cannot be used in bind groups. */
 	pub max_uniform_block_size: u32,
+	/** The alignment, in bytes, required of the offsets at which ranges of
+	 * uniform buffers are bound. */
+	pub uniform_buffer_offset_alignment: u32,
+	/** The maximum number of vertex attributes available to a program. Every
+	 * attribute must be assigned a location lower than this. */
+	pub max_vertex_attribs: u32,
+	/** The maximum number of four-component vectors available for passing
+	 * data from the vertex stage on to the fragment stage. */
+	pub max_varying_vectors: u32,
+	/** The maximum number of individual components available for passing
+	 * data from the vertex stage on to the fragment stage. */
+	pub max_varying_components: u32,
 	/** The maximum number of color attachments a framebuffer is allowed to
 	 * have. */
 	pub max_framebuffer_color_attachments: u32,
@@ -269,15 +883,21 @@ pub struct Limits {
 	pub max_framebuffer_attachment_width: Option<u32>,
 	/** The maximum height of a framebuffer attachment, measured in pixels. */
 	pub max_framebuffer_attachment_height: Option<u32>,
+	/** The maximum number of samples per pixel of a multisampled
+	 * framebuffer. This is one in contexts that can't create them at all. */
+	pub max_samples: u32,
 	/** The maximum width of the viewport at any given time. */
 	pub max_viewport_width: Option<u32>,
 	/** The maximum height of the viewport at any given time. */
 	pub max_viewport_height: Option<u32>,
 	/** The maximum value of allowed for the anisotropy clamp. */
 	pub max_sampler_anisotropy: Option<f32>,
+	/** The smallest and largest widths of lines, measured in pixels. Line
+	 * widths given to pipelines are clamped to this range. */
+	pub aliased_line_width_range: [f32; 2],
 }
 impl Limits {
-	fn collect(gl: &Context) -> Result<Self, UnsupportedContext> {
+	fn collect(gl: &Context, profile: Profile) -> Result<Self, UnsupportedContext> {
 		let try_ensure_u32_indexed = |param: u32, index: u32| {
 			let value = unsafe {
 				let val = gl.get_parameter_indexed_i32(param, index);
@@ -359,25 +979,89 @@ impl Limits {
 			};
 			Ok(Some(value))
 		};
+		let ensure_f32_range = |param: u32| {
+			let mut range = [0.0; 2];
+			unsafe {
+				gl.get_parameter_f32_slice(param, &mut range);
+				match gl.get_error() {
+					glow::INVALID_ENUM => return Err(
+						UnsupportedContext::UnsupportedParameter {
+							parameter: param
+						}),
+					glow::NO_ERROR => {},
+					what =>
+						panic!("glGet(0x{:08x}) returned error code 0x{:08x}",
+							param,
+							what)
+				}
+			}
+			Ok(range)
+		};
+		/* Legacy contexts don't know about the limits of the features they
+		 * lack, which get the given value instead, low enough that the
+		 * features they limit can't be used. */
+		let ensure_u32_or_legacy = |param: u32, legacy: u32| {
+			if profile.is_legacy() {
+				Ok(legacy)
+			} else {
+				ensure_u32(param)
+			}
+		};
+
+		/* Desktop contexts before 4.1 only know about the component limit, while
+		 * core profiles after it may only know about the vector limit. Either
+		 * one of them is enough to derive the other. */
+		let max_varying_vectors = try_ensure_u32(glow::MAX_VARYING_VECTORS)?;
+		let max_varying_components = try_ensure_u32(glow::MAX_VARYING_COMPONENTS)?;
+		let (max_varying_vectors, max_varying_components) =
+			match (max_varying_vectors, max_varying_components) {
+				(Some(vectors), Some(components)) => (vectors, components),
+				(Some(vectors), None) => (vectors, vectors.saturating_mul(4)),
+				(None, Some(components)) => (components / 4, components),
+				(None, None) => return Err(
+					UnsupportedContext::UnsupportedParameter {
+						parameter: glow::MAX_VARYING_VECTORS
+					})
+			};
+
+		/* Indexed queries aren't there at all in legacy contexts. */
+		let max_viewport_dims = if profile.is_legacy() {
+			(None, None)
+		} else {(
+			try_ensure_u32_indexed(glow::MAX_VIEWPORT_DIMS, 0)?,
+			try_ensure_u32_indexed(glow::MAX_VIEWPORT_DIMS, 1)?,
+		)};
 
 		Ok(Self {
 			/* Texture limits block. */
 			max_textures: ensure_u32(glow::MAX_COMBINED_TEXTURE_IMAGE_UNITS)?,
 			max_texture_size: ensure_u32(glow::MAX_TEXTURE_SIZE)?,
-			max_texture_size_3d: ensure_u32(glow::MAX_3D_TEXTURE_SIZE)?,
-			max_texture_layers: ensure_u32(glow::MAX_ARRAY_TEXTURE_LAYERS)?,
+			max_texture_size_3d: ensure_u32_or_legacy(glow::MAX_3D_TEXTURE_SIZE, 0)?,
+			max_texture_buffer_size: try_ensure_u32(glow::MAX_TEXTURE_BUFFER_SIZE)?,
+			max_texture_layers: ensure_u32_or_legacy(glow::MAX_ARRAY_TEXTURE_LAYERS, 0)?,
+			max_cube_map_texture_size: ensure_u32(glow::MAX_CUBE_MAP_TEXTURE_SIZE)?,
 
 			/* Uniform buffer limits block. */
-			max_uniform_block_bindings: ensure_u32(glow::MAX_UNIFORM_BUFFER_BINDINGS)?,
-			max_uniform_block_size: ensure_u32(glow::MAX_UNIFORM_BLOCK_SIZE)?,
+			max_uniform_block_bindings: ensure_u32_or_legacy(glow::MAX_UNIFORM_BUFFER_BINDINGS, 0)?,
+			max_uniform_block_size: ensure_u32_or_legacy(glow::MAX_UNIFORM_BLOCK_SIZE, 0)?,
+			uniform_buffer_offset_alignment: ensure_u32_or_legacy(glow::UNIFORM_BUFFER_OFFSET_ALIGNMENT, 1)?,
+
+			/* Vertex interface limits block. */
+			max_vertex_attribs: ensure_u32(glow::MAX_VERTEX_ATTRIBS)?,
+			max_varying_vectors,
+			max_varying_components,
 
 			/* Framebuffer limits block. */
-			max_framebuffer_color_attachments: ensure_u32(glow::MAX_COLOR_ATTACHMENTS)?,
+			max_framebuffer_color_attachments: ensure_u32_or_legacy(glow::MAX_COLOR_ATTACHMENTS, 1)?,
 			max_framebuffer_attachment_width: try_ensure_u32(glow::MAX_FRAMEBUFFER_WIDTH)?,
 			max_framebuffer_attachment_height: try_ensure_u32(glow::MAX_FRAMEBUFFER_HEIGHT)?,
-			max_viewport_width: try_ensure_u32_indexed(glow::MAX_VIEWPORT_DIMS, 0)?,
-			max_viewport_height: try_ensure_u32_indexed(glow::MAX_VIEWPORT_DIMS, 1)?,
+			max_samples: ensure_u32_or_legacy(glow::MAX_SAMPLES, 1)?,
+			max_viewport_width: max_viewport_dims.0,
+			max_viewport_height: max_viewport_dims.1,
 			max_sampler_anisotropy: try_ensure_f32(glow::MAX_TEXTURE_MAX_ANISOTROPY_EXT)?,
+
+			/* Rasterization limits block. */
+			aliased_line_width_range: ensure_f32_range(glow::ALIASED_LINE_WIDTH_RANGE)?,
 		})
 	}
 }
@@ -392,6 +1076,15 @@ pub struct Version {
 	/** Vendor specific information included in the string, if any. */
 	pub vendor: String,
 }
+impl std::fmt::Display for Version {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(f, "{} {}", self.profile, self.release)?;
+		if !self.vendor.is_empty() {
+			write!(f, " ({})", self.vendor)?;
+		}
+		Ok(())
+	}
+}
 impl Version {
 	/** Try to parse version information from a version string. */
 	fn parse(string: &str) -> Result<Self, &str> {
@@ -399,6 +1092,14 @@ impl Version {
 		let (release, string) = Release::parse(string)?;
 		let vendor = string.trim().to_string();
 
+		/* Releases before the ones the full feature set is built on run with
+		 * the reduced one. */
+		let profile = match profile {
+			Profile::Es if release.major < 3 => Profile::EsLegacy,
+			Profile::Web if release.major < 2 => Profile::WebLegacy,
+			profile => profile
+		};
+
 		Ok(Self { profile, release, vendor })
 	}
 }
@@ -411,9 +1112,22 @@ pub enum Profile {
 	/** This is running an OpenGL ES implementation. */
 	Es,
 	/** This is running a WebGL implementation. */
-	Web
+	Web,
+	/** This is running an OpenGL ES 2.0 implementation, which only supports a
+	 * reduced feature set. */
+	EsLegacy,
+	/** This is running a WebGL1 implementation, which only supports a reduced
+	 * feature set. */
+	WebLegacy,
 }
 impl Profile {
+	/** Whether this profile only supports the reduced feature set of OpenGL
+	 * ES 2.0 and WebGL1. Which features are missing is told apart by the
+	 * [`Capabilities`] of the context. */
+	pub fn is_legacy(self) -> bool {
+		matches!(self, Self::EsLegacy | Self::WebLegacy)
+	}
+
 	/** Try to parse an implementation profile from a version string. */
 	fn parse(string: &str) -> Result<(Self, &str), &str> {
 		let string = string.trim_start();
@@ -451,9 +1165,9 @@ impl std::fmt::Display for Profile {
 		match self {
 			Profile::Core =>
 				write!(f, "OpenGL"),
-			Profile::Es =>
+			Profile::Es | Profile::EsLegacy =>
 				write!(f, "OpenGL ES"),
-			Profile::Web =>
+			Profile::Web | Profile::WebLegacy =>
 				write!(f, "WebGL")
 		}
 	}
@@ -516,6 +1230,11 @@ impl Release {
 		Ok((result, next))
 	}
 }
+impl std::fmt::Display for Release {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(f, "{}.{}", self.major, self.minor)
+	}
+}
 impl PartialOrd for Release {
 	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
 		match self.major.partial_cmp(&other.major) {
@@ -545,6 +1264,10 @@ impl Ord for Release {
  * - `OpenGL ES 3.0`
  * - `WebGL 2`
  *
+ * Or, with a reduced feature set.
+ * - `OpenGL ES 2.0`
+ * - `WebGL 1`
+ *
  */
 #[derive(Debug, thiserror::Error)]
 pub enum UnsupportedContext {
@@ -633,4 +1356,111 @@ mod tests {
 				vendor: "".to_string()
 			}));
 	}
+
+	#[test]
+	fn legacy_version() {
+		let version = Version::parse("OpenGL ES 2.0 ANGLE").unwrap();
+		assert_eq!(version.profile, Profile::EsLegacy);
+		assert_eq!(version.to_string(), "OpenGL ES 2.0 (ANGLE)");
+
+		let version = Version::parse("WebGL 1.0 (OpenGL ES 2.0 Chromium)").unwrap();
+		assert_eq!(version.profile, Profile::WebLegacy);
+		assert_eq!(version.release, Release { major: 1, minor: 0 });
+
+		assert!(!Version::parse("WebGL 2.0").unwrap().profile.is_legacy());
+		assert!(!Version::parse("2.1 Mesa").unwrap().profile.is_legacy());
+	}
+
+	#[test]
+	fn combined_extensions() {
+		let extensions = Extension::parse_combined(
+			" GL_KHR_debug  GL_OES_element_index_uint\nGL_EXT_sRGB ")
+			.collect::<HashSet<_>>();
+		assert_eq!(extensions.len(), 3);
+		assert!(extensions.contains("GL_KHR_debug"));
+		assert!(extensions.contains(&Extension::OES_ELEMENT_INDEX_UINT));
+		assert!(extensions.contains("GL_EXT_sRGB"));
+		assert!(!extensions.contains("KHR_debug"));
+
+		assert_eq!(Extension::parse_combined("").count(), 0);
+	}
+
+	#[test]
+	fn capabilities_from_extensions() {
+		let extensions = |combined| Extension::parse_combined(combined)
+			.collect::<HashSet<_>>();
+
+		let version = Version::parse("OpenGL ES 3.0 Mesa").unwrap();
+		let capabilities = Capabilities::new(&version, &extensions(""));
+		assert!(capabilities.sampler_objects);
+		assert!(capabilities.srgb_textures);
+		assert!(capabilities.seamless_cube_maps);
+		assert!(!capabilities.srgb_write_control);
+		assert!(!capabilities.timer_queries);
+		assert!(!capabilities.debug_labels);
+		assert!(!capabilities.compressed_s3tc);
+
+		let capabilities = Capabilities::new(&version, &extensions(
+			"GL_EXT_disjoint_timer_query GL_KHR_debug \
+			GL_EXT_texture_compression_s3tc GL_EXT_sRGB_write_control"));
+		assert!(capabilities.srgb_write_control);
+		assert!(capabilities.timer_queries);
+		assert!(capabilities.debug_labels);
+		assert!(capabilities.compressed_s3tc);
+
+		/* WebGL names its extensions differently, so the OpenGL ES names
+		 * don't count there. */
+		let version = Version::parse("WebGL 2.0").unwrap();
+		let capabilities = Capabilities::new(&version, &extensions(
+			"GL_EXT_disjoint_timer_query GL_EXT_texture_compression_s3tc"));
+		assert!(!capabilities.timer_queries);
+		assert!(!capabilities.compressed_s3tc);
+		let capabilities = Capabilities::new(&version, &extensions(
+			"EXT_disjoint_timer_query_webgl2 WEBGL_compressed_texture_s3tc"));
+		assert!(capabilities.timer_queries);
+		assert!(capabilities.compressed_s3tc);
+
+		/* Desktop OpenGL can always filter and render to floats, everything
+		 * else needs extensions for both. */
+		let version = Version::parse("3.3 Mesa").unwrap();
+		let capabilities = Capabilities::new(&version, &extensions(""));
+		assert!(capabilities.float_texture_filtering);
+		assert!(capabilities.float_color_buffers);
+		let version = Version::parse("OpenGL ES 3.0 Mesa").unwrap();
+		let capabilities = Capabilities::new(&version, &extensions(""));
+		assert!(!capabilities.float_texture_filtering);
+		assert!(!capabilities.float_color_buffers);
+		let capabilities = Capabilities::new(&version, &extensions(
+			"GL_OES_texture_float_linear GL_EXT_color_buffer_float"));
+		assert!(capabilities.float_texture_filtering);
+		assert!(capabilities.float_color_buffers);
+
+		/* Buffer textures came with OpenGL ES 3.2, and never made it to
+		 * WebGL. */
+		let capabilities = |version| Capabilities::new(
+			&Version::parse(version).unwrap(),
+			&extensions(""));
+		assert!(!capabilities("OpenGL ES 3.1 Mesa").texel_buffers);
+		assert!(capabilities("OpenGL ES 3.2 Mesa").texel_buffers);
+		assert!(capabilities("4.6 NVIDIA 457.51").texel_buffers);
+		assert!(!capabilities("WebGL 2.0").texel_buffers);
+
+		/* OpenGL only got ETC2 in 4.3. */
+		let version = Version::parse("3.3 Mesa").unwrap();
+		assert!(!Capabilities::new(&version, &extensions("")).compressed_etc2);
+		assert!(Capabilities::new(&version, &extensions(
+			"GL_ARB_ES3_compatibility")).compressed_etc2);
+		let version = Version::parse("4.3 Mesa").unwrap();
+		assert!(Capabilities::new(&version, &extensions("")).compressed_etc2);
+
+		let version = Version::parse("OpenGL ES 2.0 ANGLE").unwrap();
+		let capabilities = Capabilities::new(&version, &extensions(
+			"GL_OES_vertex_array_object GL_ANGLE_instanced_arrays GL_EXT_sRGB"));
+		assert!(capabilities.vertex_arrays);
+		assert!(capabilities.instancing);
+		assert!(!capabilities.index_uint32);
+		assert!(!capabilities.sampler_objects);
+		assert!(!capabilities.srgb_textures);
+		assert!(!capabilities.seamless_cube_maps);
+	}
 }
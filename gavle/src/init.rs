@@ -0,0 +1,183 @@
+use std::ops::Range;
+use crate::texture::TextureExtent;
+
+/** Number of subresources tracked per mip level of a texture of the given
+ * extent. Array textures are tracked per layer and cube maps per face, while
+ * every other kind of texture is tracked per level. */
+pub(crate) fn tracked_layers(extent: &TextureExtent) -> u32 {
+	match extent {
+		TextureExtent::D2Array { layers, .. } => *layers,
+		TextureExtent::Cube { .. } => 6,
+		_ => 1
+	}
+}
+
+/** The tracked layers touched by a region of a texture of the given extent,
+ * starting at the given layer, or face, and going over the given number of
+ * them. Regions of textures that aren't tracked per layer always touch their
+ * only one. */
+pub(crate) fn region_layers(extent: &TextureExtent, start: u32, count: u32) -> Range<u32> {
+	match extent {
+		TextureExtent::D2Array { .. } | TextureExtent::Cube { .. } =>
+			start..start.saturating_add(count),
+		_ => 0..1
+	}
+}
+
+/** Lists the given subresources for diagnostics, as in `level 0 layer 1`. */
+pub(crate) fn describe(subresources: &[(u32, u32)]) -> String {
+	subresources.iter()
+		.map(|(level, layer)| format!("level {} layer {}", level, layer))
+		.collect::<Vec<_>>()
+		.join(", ")
+}
+
+/** Record of which mip levels and layers of a texture have ever been written
+ * to, used to catch textures being sampled before anything was put in them.
+ *
+ * Tracking is coarse: writing to any part of a layer marks the whole layer as
+ * written. It only happens in debug builds and in builds with the `validation`
+ * feature enabled. Everywhere else, this structure takes up no space and all
+ * of its operations compile down to nothing. */
+#[cfg(any(debug_assertions, feature = "validation"))]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) struct InitTracker {
+	/** Number of mip levels in the texture. */
+	levels: u32,
+	/** Number of tracked layers in every mip level of the texture. */
+	layers: u32,
+	/** One bit per subresource, set once it has been written to, going over
+	 * every layer of a level before moving on to the next level. */
+	written: Vec<u64>,
+}
+#[cfg(any(debug_assertions, feature = "validation"))]
+impl InitTracker {
+	/** Creates a new tracker for a texture with the given number of mip levels
+	 * and tracked layers, none of which have been written to. */
+	pub(crate) fn new(levels: u32, layers: u32) -> Self {
+		let bits = levels as usize * layers as usize;
+		Self {
+			levels,
+			layers,
+			written: vec![0; bits / 64 + 1]
+		}
+	}
+
+	/** Position of the bit of the given subresource. */
+	fn bit(&self, level: u32, layer: u32) -> (usize, u64) {
+		let index = level as usize * self.layers as usize + layer as usize;
+		(index / 64, 1 << (index % 64))
+	}
+
+	/** Marks the given layers of the given levels as written. Anything out of
+	 * the bounds of the texture is ignored. */
+	pub(crate) fn mark(&mut self, levels: Range<u32>, layers: Range<u32>) {
+		for level in levels.start..levels.end.min(self.levels) {
+			for layer in layers.start..layers.end.min(self.layers) {
+				let (word, bit) = self.bit(level, layer);
+				self.written[word] |= bit;
+			}
+		}
+	}
+
+	/** Marks every subresource of the texture as written. */
+	pub(crate) fn mark_all(&mut self) {
+		self.mark(0..self.levels, 0..self.layers)
+	}
+
+	/** Whether the given subresource has been written to. Subresources out of
+	 * the bounds of the texture never have. */
+	pub(crate) fn is_written(&self, level: u32, layer: u32) -> bool {
+		if level >= self.levels || layer >= self.layers { return false }
+
+		let (word, bit) = self.bit(level, layer);
+		self.written[word] & bit != 0
+	}
+
+	/** The subresources in the given ranges that have never been written to,
+	 * as pairs of level and layer. */
+	pub(crate) fn missing(&self, levels: Range<u32>, layers: Range<u32>) -> Vec<(u32, u32)> {
+		let mut missing = Vec::new();
+		for level in levels.start..levels.end.min(self.levels) {
+			for layer in layers.start..layers.end.min(self.layers) {
+				if !self.is_written(level, layer) {
+					missing.push((level, layer));
+				}
+			}
+		}
+		missing
+	}
+}
+
+/** Stand-in for the initialization tracker in builds without validation. */
+#[cfg(not(any(debug_assertions, feature = "validation")))]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) struct InitTracker;
+#[cfg(not(any(debug_assertions, feature = "validation")))]
+impl InitTracker {
+	#[inline(always)]
+	pub(crate) fn new(_: u32, _: u32) -> Self { Self }
+	#[inline(always)]
+	pub(crate) fn mark(&mut self, _: Range<u32>, _: Range<u32>) {}
+	#[inline(always)]
+	pub(crate) fn mark_all(&mut self) {}
+	#[inline(always)]
+	pub(crate) fn missing(&self, _: Range<u32>, _: Range<u32>) -> Vec<(u32, u32)> {
+		Vec::new()
+	}
+}
+
+#[cfg(all(test, any(debug_assertions, feature = "validation")))]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn textures_start_out_unwritten() {
+		let tracker = InitTracker::new(3, 4);
+		assert_eq!(tracker.missing(0..3, 0..4).len(), 12);
+		assert!(!tracker.is_written(0, 0));
+	}
+
+	#[test]
+	fn marks_only_the_given_subresources() {
+		let mut tracker = InitTracker::new(3, 4);
+		tracker.mark(0..1, 1..3);
+		assert_eq!(tracker.missing(0..1, 0..4), vec![(0, 0), (0, 3)]);
+		assert_eq!(tracker.missing(1..3, 0..4).len(), 8);
+
+		/* Marks past the bounds of the texture are dropped. */
+		tracker.mark(2..5, 3..9);
+		assert_eq!(tracker.missing(2..3, 0..4), vec![(2, 0), (2, 1), (2, 2)]);
+		assert!(!tracker.is_written(5, 3));
+
+		tracker.mark_all();
+		assert!(tracker.missing(0..3, 0..4).is_empty());
+	}
+
+	#[test]
+	fn tracks_past_a_single_word() {
+		let mut tracker = InitTracker::new(1, 200);
+		tracker.mark(0..1, 130..131);
+		assert_eq!(tracker.missing(0..1, 128..132), vec![(0, 128), (0, 129), (0, 131)]);
+	}
+
+	#[test]
+	fn regions_touch_the_layers_they_span() {
+		let array = TextureExtent::D2Array { width: 4, height: 4, layers: 8 };
+		let cube = TextureExtent::Cube { size: 4 };
+		let volume = TextureExtent::D3 { width: 4, height: 4, depth: 8 };
+
+		assert_eq!(tracked_layers(&array), 8);
+		assert_eq!(tracked_layers(&cube), 6);
+		assert_eq!(tracked_layers(&volume), 1);
+
+		assert_eq!(region_layers(&array, 2, 3), 2..5);
+		assert_eq!(region_layers(&cube, 4, 2), 4..6);
+		assert_eq!(region_layers(&volume, 2, 3), 0..1);
+	}
+
+	#[test]
+	fn describes_subresources() {
+		assert_eq!(describe(&[(0, 1), (2, 0)]), "level 0 layer 1, level 2 layer 0");
+	}
+}
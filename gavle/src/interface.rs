@@ -0,0 +1,714 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/** Declarations in the interface of a shader, as found by scanning its source.
+ *
+ * The scanner only understands global declarations with the `in`, `out` and
+ * `varying` storage qualifiers, with or without interface blocks, which is
+ * all that's needed to estimate how much of the interface between stages a
+ * program uses. It is not a GLSL parser, and it never rejects a shader, as the
+ * compiler is in a much better position to do that. */
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct ShaderInterface {
+	/** Variables coming into the shader, along with the `varying` variables
+	 * of older shaders. */
+	pub(crate) inputs: Vec<Variable>,
+	/** Variables going out of the shader, along with the `varying` variables
+	 * of older shaders. */
+	pub(crate) outputs: Vec<Variable>,
+	/** Names of the uniform blocks declared by the shader, in order. */
+	pub(crate) uniform_blocks: Vec<String>,
+}
+impl ShaderInterface {
+	/** Scans the given GLSL source for the declarations in its interface. */
+	pub(crate) fn scan(source: &str) -> Self {
+		let (tokens, defines) = tokenize(source);
+		let mut interface = Self::default();
+
+		let mut statement = Vec::new();
+		let mut depth = 0usize;
+		let mut tokens = tokens.into_iter();
+		while let Some(token) = tokens.next() {
+			match token.as_str() {
+				"{" if depth == 0 && is_block(&statement) => {
+					/* Interface blocks are read as a whole, along with the
+					 * instance name after them. */
+					let mut block = Vec::new();
+					for token in &mut tokens {
+						if token == "}" { break }
+						block.push(token);
+					}
+					let mut tail = Vec::new();
+					for token in &mut tokens {
+						if token == ";" { break }
+						tail.push(token);
+					}
+
+					interface.declare_block(&statement, &block, &tail, &defines);
+					statement.clear();
+				},
+				"{" => {
+					if depth == 0 {
+						if let Some(name) = uniform_block(&statement) {
+							interface.uniform_blocks.push(name.clone());
+						}
+					}
+
+					depth += 1;
+					statement.clear();
+				},
+				"}" => {
+					depth = depth.saturating_sub(1);
+					statement.clear();
+				},
+				";" => {
+					if depth == 0 {
+						interface.declare(&statement, &defines);
+					}
+					statement.clear();
+				},
+				_ => statement.push(token)
+			}
+		}
+
+		interface
+	}
+
+	/** Records the variables in a global declaration, if it has any of the
+	 * storage qualifiers we're interested in. */
+	fn declare(&mut self, statement: &[String], defines: &HashMap<String, u32>) {
+		let statement = skip_layout(statement);
+		let (storage, rest) = match split_qualifiers(statement) {
+			Some(split) => split,
+			None => return
+		};
+
+		/* Function declarations may have `in` and `out` parameters, but they
+		 * are not part of the interface. */
+		if rest.iter().any(|token| token == "(") { return }
+
+		let (kind, declarators) = match rest.split_first() {
+			Some(split) => split,
+			None => return
+		};
+		for (name, array) in declarators_of(declarators, defines) {
+			self.push(storage, Variable {
+				name,
+				shape: Shape::of(kind),
+				array
+			});
+		}
+	}
+
+	/** Records the members of an interface block, if it has any of the
+	 * storage qualifiers we're interested in. */
+	fn declare_block(
+		&mut self,
+		head: &[String],
+		members: &[String],
+		tail: &[String],
+		defines: &HashMap<String, u32>) {
+
+		let head = skip_layout(head);
+		let (storage, rest) = match split_qualifiers(head) {
+			Some(split) => split,
+			None => return
+		};
+		let block = match rest.first() {
+			Some(block) => block,
+			None => return
+		};
+
+		/* Arrays of blocks repeat every member once per element. */
+		let instances = declarators_of(tail, defines)
+			.first()
+			.map(|(_, array)| *array)
+			.unwrap_or(1);
+
+		for member in members.split(|token| token == ";") {
+			let member = skip_layout(member);
+			let member = member.iter()
+				.position(|token| !is_qualifier(token))
+				.map(|index| &member[index..])
+				.unwrap_or(&[]);
+
+			let (kind, declarators) = match member.split_first() {
+				Some(split) => split,
+				None => continue
+			};
+			for (name, array) in declarators_of(declarators, defines) {
+				self.push(storage, Variable {
+					name: format!("{}.{}", block, name),
+					shape: Shape::of(kind),
+					array: array.saturating_mul(instances)
+				});
+			}
+		}
+	}
+
+	/** Adds a variable to the lists it belongs to, given its storage. */
+	fn push(&mut self, storage: Storage, variable: Variable) {
+		match storage {
+			Storage::In => self.inputs.push(variable),
+			Storage::Out => self.outputs.push(variable),
+			Storage::Varying => {
+				self.inputs.push(variable.clone());
+				self.outputs.push(variable);
+			}
+		}
+	}
+}
+
+/** A variable in the interface of a shader. */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Variable {
+	/** Name of the variable. Members of interface blocks are named after the
+	 * block, as in `Block.member`, since that's what stages are matched by. */
+	pub(crate) name: String,
+	/** Shape of a single element of the variable. */
+	pub(crate) shape: Shape,
+	/** Number of elements in the variable. This is one for variables that are
+	 * not arrays. */
+	pub(crate) array: u32,
+}
+impl Variable {
+	/** Number of rows taken up by all of the elements of this variable. */
+	pub(crate) fn rows(&self) -> u32 {
+		self.shape.rows.saturating_mul(self.array)
+	}
+}
+
+/** Space taken up by a variable in the grid of four-component vectors used for
+ * packing the interface between stages. */
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) struct Shape {
+	/** Number of rows taken up by the variable. */
+	pub(crate) rows: u32,
+	/** Number of components taken up in each of the rows. */
+	pub(crate) columns: u32,
+	/** Whether the type of the variable was recognized. Variables of unknown
+	 * types, such as structures, are assumed to take up a whole row. */
+	pub(crate) known: bool,
+}
+impl Shape {
+	/** Shape of a variable of the given GLSL type. Matrices take up one row
+	 * per column, each as wide as a column is tall. */
+	fn of(kind: &str) -> Self {
+		let shape = |rows, columns| Self { rows, columns, known: true };
+		let digit = |c: Option<char>| c
+			.and_then(|c| c.to_digit(10))
+			.filter(|c| (2..=4).contains(c));
+
+		match kind {
+			"float" | "int" | "uint" | "bool" => return shape(1, 1),
+			_ => {}
+		}
+
+		let vector = kind.strip_prefix("vec")
+			.or_else(|| kind.strip_prefix("ivec"))
+			.or_else(|| kind.strip_prefix("uvec"))
+			.or_else(|| kind.strip_prefix("bvec"));
+		if let Some(rest) = vector {
+			let mut chars = rest.chars();
+			if let (Some(n), None) = (digit(chars.next()), chars.next()) {
+				return shape(1, n)
+			}
+		}
+
+		if let Some(rest) = kind.strip_prefix("mat") {
+			let mut chars = rest.chars();
+			match (digit(chars.next()), chars.next(), digit(chars.next()), chars.next()) {
+				(Some(n), None, _, _) => return shape(n, n),
+				(Some(c), Some('x'), Some(r), None) => return shape(c, r),
+				_ => {}
+			}
+		}
+
+		Self { rows: 1, columns: 4, known: false }
+	}
+
+	/** Name of the GLSL type with this shape, for diagnostics. */
+	fn describe(&self) -> String {
+		match (self.known, self.rows, self.columns) {
+			(false, ..) => "unknown type, assumed vec4".into(),
+			(true, 1, 1) => "scalar".into(),
+			(true, 1, n) => format!("vec{}", n),
+			(true, c, r) if c == r => format!("mat{}", c),
+			(true, c, r) => format!("mat{}x{}", c, r),
+		}
+	}
+}
+
+/** Estimates the number of four-component vectors the given variables take
+ * up when packed by the algorithm in the GLSL ES specification.
+ *
+ * This is the smallest number of rows the variables can be packed into, which
+ * is conservative, in that implementations are allowed to pack more tightly,
+ * but never more loosely, than the specification does. */
+pub(crate) fn packed_rows(variables: &[Variable]) -> u32 {
+	let total = variables.iter()
+		.fold(0u32, |total, variable| total.saturating_add(variable.rows()));
+
+	(0..=total)
+		.find(|&rows| fits(variables, rows))
+		.unwrap_or(total)
+}
+
+/** Whether the given variables can be packed into a grid with the given number
+ * of four-component rows, following the GLSL ES packing algorithm.
+ *
+ * Variables are packed from the widest to the narrowest, and from the largest
+ * to the smallest within the same width. Four and three component variables
+ * take up whole rows from the top down, with the last column of the rows taken
+ * by three component variables left free. Two component variables are then
+ * packed from the top down into the first two columns of the rest of the rows
+ * and, once those run out, into the last two columns. Finally, every single
+ * component variable is packed into the column with the smallest contiguous
+ * range of free rows it fits in, at the topmost row of that range. */
+pub(crate) fn fits(variables: &[Variable], max_rows: u32) -> bool {
+	let mut sorted = variables.iter()
+		.map(|variable| (variable.shape.columns, variable.rows()))
+		.collect::<Vec<_>>();
+	sorted.sort_by(|a, b| b.cmp(a));
+
+	let width = |columns| sorted.iter()
+		.filter(move |(c, _)| *c == columns)
+		.map(|(_, rows)| *rows);
+	let sum = |columns| width(columns)
+		.fold(0u32, |sum, rows| sum.saturating_add(rows));
+
+	/* Four and three component variables take up whole rows. */
+	let four = sum(4);
+	let three = sum(3);
+	let top = match four.checked_add(three) {
+		Some(rows) if rows <= max_rows => rows,
+		_ => return false
+	};
+
+	let mut grid = vec![[false; 4]; max_rows as usize];
+	for row in &mut grid[..four as usize] {
+		*row = [true; 4];
+	}
+	for row in &mut grid[four as usize..top as usize] {
+		*row = [true, true, true, false];
+	}
+
+	/* Two component variables go into the first two columns of the rows
+	 * below, then into the last two, once the first two are full. */
+	let available = max_rows - top;
+	let mut left = 0u32;
+	let mut right = 0u32;
+	for rows in width(2) {
+		let (used, columns) = if rows <= available - left {
+			(&mut left, 0..2)
+		} else if rows <= available - right {
+			(&mut right, 2..4)
+		} else {
+			return false
+		};
+
+		let start = (top + *used) as usize;
+		for row in &mut grid[start..start + rows as usize] {
+			for column in columns.clone() { row[column] = true }
+		}
+		*used += rows;
+	}
+
+	/* Single component variables go into the tightest contiguous range of
+	 * free rows they fit in, in any of the columns. */
+	for rows in width(1) {
+		let mut best: Option<(u32, usize, usize)> = None;
+		for column in 0..4 {
+			let mut row = 0;
+			while row < grid.len() {
+				if grid[row][column] {
+					row += 1;
+					continue
+				}
+
+				let start = row;
+				while row < grid.len() && !grid[row][column] { row += 1 }
+
+				let free = (row - start) as u32;
+				if free >= rows && best.map(|(best, ..)| free < best).unwrap_or(true) {
+					best = Some((free, column, start));
+				}
+			}
+		}
+
+		let (_, column, start) = match best {
+			Some(best) => best,
+			None => return false
+		};
+		for row in &mut grid[start..start + rows as usize] {
+			row[column] = true;
+		}
+	}
+
+	true
+}
+
+/** Storage qualifiers the scanner looks for. */
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Storage {
+	In,
+	Out,
+	Varying,
+}
+
+/** Whether the given token is a qualifier other than a storage qualifier. */
+fn is_qualifier(token: &str) -> bool {
+	matches!(token,
+		"highp" | "mediump" | "lowp"
+		| "flat" | "smooth" | "noperspective" | "centroid" | "sample"
+		| "invariant" | "precise")
+}
+
+/** Removes a leading layout qualifier from the given declaration. */
+fn skip_layout(statement: &[String]) -> &[String] {
+	match statement.first().map(String::as_str) {
+		Some("layout") => {
+			let end = statement.iter()
+				.position(|token| token == ")")
+				.map(|index| index + 1)
+				.unwrap_or(statement.len());
+			skip_layout(&statement[end..])
+		},
+		_ => statement
+	}
+}
+
+/** Splits the qualifiers off of a declaration, returning its storage and the
+ * rest of it, if it has any of the storage qualifiers we're interested in. */
+fn split_qualifiers(statement: &[String]) -> Option<(Storage, &[String])> {
+	let mut storage = None;
+	for (index, token) in statement.iter().enumerate() {
+		match token.as_str() {
+			"in" => storage = Some(Storage::In),
+			"out" => storage = Some(Storage::Out),
+			"varying" => storage = Some(Storage::Varying),
+			token if is_qualifier(token) => {},
+			_ => return storage.map(|storage| (storage, &statement[index..]))
+		}
+	}
+	None
+}
+
+/** Whether the given tokens start an interface block with one of the storage
+ * qualifiers we're interested in. */
+fn is_block(statement: &[String]) -> bool {
+	match split_qualifiers(skip_layout(statement)) {
+		Some((_, rest)) => rest.len() == 1,
+		None => false
+	}
+}
+
+/** Name of the uniform block started by the given tokens, if they start one.
+ * The members of uniform blocks are of no interest to the scanner, only the
+ * names of the blocks themselves are. */
+fn uniform_block(statement: &[String]) -> Option<&String> {
+	let statement = skip_layout(statement);
+	let start = statement.iter().position(|token| !is_qualifier(token))?;
+	match &statement[start..] {
+		[uniform, name] if uniform == "uniform" => Some(name),
+		_ => None
+	}
+}
+
+/** Names and array sizes of the comma-separated declarators in the given
+ * tokens. Array sizes that are neither literals nor simple definitions are
+ * taken to be one, since there's no telling what they are. */
+fn declarators_of(tokens: &[String], defines: &HashMap<String, u32>) -> Vec<(String, u32)> {
+	tokens.split(|token| token == ",")
+		.filter_map(|declarator| {
+			let name = declarator.first()?;
+			let mut array = 1u32;
+			let mut rest = &declarator[1..];
+			while let [open, size, close, tail @ ..] = rest {
+				if open != "[" || close != "]" { break }
+
+				let size = size.parse::<u32>().ok()
+					.or_else(|| defines.get(size).copied())
+					.unwrap_or(1);
+				array = array.saturating_mul(size);
+				rest = tail;
+			}
+
+			Some((name.clone(), array))
+		})
+		.collect()
+}
+
+/** Splits the given GLSL source into tokens, leaving comments and preprocessor
+ * directives out, and collecting the object-like definitions of integer
+ * constants along the way. */
+fn tokenize(source: &str) -> (Vec<String>, HashMap<String, u32>) {
+	let mut tokens = Vec::new();
+	let mut defines = HashMap::new();
+
+	let source = strip_comments(source);
+	for line in source.lines() {
+		let line = line.trim();
+		if let Some(directive) = line.strip_prefix('#') {
+			let mut words = directive.split_whitespace();
+			if let (Some("define"), Some(name), Some(value), None) =
+				(words.next(), words.next(), words.next(), words.next()) {
+
+				if let Ok(value) = value.trim_end_matches(&['u', 'U'][..])
+					.parse::<u32>() {
+					defines.insert(name.to_string(), value);
+				}
+			}
+			continue
+		}
+
+		let mut word = String::new();
+		for c in line.chars() {
+			if c.is_alphanumeric() || c == '_' {
+				word.push(c);
+				continue
+			}
+			if !word.is_empty() {
+				tokens.push(std::mem::take(&mut word));
+			}
+			if !c.is_whitespace() {
+				tokens.push(c.to_string());
+			}
+		}
+		if !word.is_empty() {
+			tokens.push(word);
+		}
+	}
+
+	(tokens, defines)
+}
+
+/** Replaces all of the comments in the given GLSL source with whitespace,
+ * keeping line breaks where they were. */
+fn strip_comments(source: &str) -> String {
+	let mut stripped = String::with_capacity(source.len());
+	let mut chars = source.chars().peekable();
+	while let Some(c) = chars.next() {
+		match (c, chars.peek()) {
+			('/', Some('/')) => {
+				while let Some(&c) = chars.peek() {
+					if c == '\n' { break }
+					chars.next();
+				}
+				stripped.push(' ');
+			},
+			('/', Some('*')) => {
+				chars.next();
+				let mut last = ' ';
+				for c in &mut chars {
+					if c == '\n' { stripped.push('\n') }
+					if last == '*' && c == '/' { break }
+					last = c;
+				}
+				stripped.push(' ');
+			},
+			_ => stripped.push(c)
+		}
+	}
+	stripped
+}
+
+/** Lists the given variables along with how much space each of them takes up,
+ * one per line, for diagnostics. */
+pub(crate) fn itemize(variables: &[Variable]) -> String {
+	let mut list = String::new();
+	for variable in variables {
+		let _ = write!(list, "\n    - {} ({}", variable.name, variable.shape.describe());
+		if variable.array != 1 {
+			let _ = write!(list, "[{}]", variable.array);
+		}
+		let _ = write!(list, ", {} rows of {} components)",
+			variable.rows(),
+			variable.shape.columns);
+	}
+	list
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn variables(list: &[(&str, u32)]) -> Vec<Variable> {
+		list.iter()
+			.map(|(kind, array)| Variable {
+				name: kind.to_string(),
+				shape: Shape::of(kind),
+				array: *array
+			})
+			.collect()
+	}
+
+	fn names(variables: &[Variable]) -> Vec<(&str, u32)> {
+		variables.iter()
+			.map(|variable| (variable.name.as_str(), variable.array))
+			.collect()
+	}
+
+	#[test]
+	fn shapes_of_types() {
+		let shape = |kind| { let s = Shape::of(kind); (s.rows, s.columns, s.known) };
+		assert_eq!(shape("float"), (1, 1, true));
+		assert_eq!(shape("uint"), (1, 1, true));
+		assert_eq!(shape("vec3"), (1, 3, true));
+		assert_eq!(shape("ivec2"), (1, 2, true));
+		assert_eq!(shape("mat3"), (3, 3, true));
+		assert_eq!(shape("mat2x4"), (2, 4, true));
+		assert_eq!(shape("mat4x2"), (4, 2, true));
+		assert_eq!(shape("Light"), (1, 4, false));
+		assert_eq!(shape("vec5"), (1, 4, false));
+	}
+
+	#[test]
+	fn scans_interfaces() {
+		let interface = ShaderInterface::scan("
+			#version 300 es
+			#define LIGHTS 4
+			precision highp float;
+
+			in vec3 position; // out vec4 commented;
+			in vec2 texture;
+			/* out vec4 also_commented; */
+			layout(location = 2) in vec4 color;
+
+			flat out highp int id;
+			smooth centroid out vec3 normal, tangents[2];
+			out vec4 lights[LIGHTS];
+			out Data {
+				vec2 uv;
+				flat mat3 basis;
+			} data[2];
+
+			uniform Params { mat4 mvp; };
+			layout(std140) uniform Lighting { vec4 ambient; } lighting;
+			struct Light { vec3 direction; };
+
+			void helper(in vec4 a, out vec4 b);
+			void main()
+			{
+				vec4 out_color = vec4(0.0);
+				{ float nested; }
+			}
+		");
+
+		assert_eq!(names(&interface.inputs), vec![
+			("position", 1),
+			("texture", 1),
+			("color", 1)
+		]);
+		assert_eq!(names(&interface.outputs), vec![
+			("id", 1),
+			("normal", 1),
+			("tangents", 2),
+			("lights", 4),
+			("Data.uv", 2),
+			("Data.basis", 2)
+		]);
+		assert_eq!(interface.outputs[5].shape, Shape::of("mat3"));
+		assert_eq!(interface.uniform_blocks, vec!["Params", "Lighting"]);
+	}
+
+	#[test]
+	fn scans_legacy_varyings() {
+		let interface = ShaderInterface::scan("
+			attribute vec3 position;
+			varying vec2 uv;
+			void main() {}
+		");
+
+		assert_eq!(names(&interface.inputs), vec![("uv", 1)]);
+		assert_eq!(names(&interface.outputs), vec![("uv", 1)]);
+	}
+
+	#[test]
+	fn packs_whole_rows() {
+		assert_eq!(packed_rows(&[]), 0);
+		assert_eq!(packed_rows(&variables(&[("vec4", 8)])), 8);
+		assert_eq!(packed_rows(&variables(&[("mat4", 1), ("vec4", 1)])), 5);
+		assert_eq!(packed_rows(&variables(&[("mat3", 1), ("vec3", 2)])), 5);
+	}
+
+	#[test]
+	fn packs_scalars_next_to_three_component_vectors() {
+		/* Each float fits in the free column of a vec3 row. */
+		assert_eq!(packed_rows(&variables(&[("vec3", 1), ("float", 1)])), 1);
+		assert_eq!(packed_rows(&variables(&[
+			("vec3", 3),
+			("float", 1),
+			("float", 1),
+			("float", 1)
+		])), 3);
+
+		/* Arrays of floats need contiguous rows in a single column. */
+		assert_eq!(packed_rows(&variables(&[("vec3", 2), ("float", 3)])), 3);
+		assert_eq!(packed_rows(&variables(&[("float", 4)])), 4);
+		assert_eq!(packed_rows(&variables(&[
+			("float", 1),
+			("float", 1),
+			("float", 1),
+			("float", 1)
+		])), 1);
+	}
+
+	#[test]
+	fn packs_pairs_side_by_side() {
+		assert_eq!(packed_rows(&variables(&[("vec2", 1), ("vec2", 1)])), 1);
+		assert_eq!(packed_rows(&variables(&[("vec2", 3)])), 3);
+		assert_eq!(packed_rows(&variables(&[("vec2", 3), ("vec2", 3)])), 3);
+		assert_eq!(packed_rows(&variables(&[("mat2", 1), ("vec2", 2)])), 2);
+
+		/* Scalars go into whatever is left over by the pairs. */
+		assert_eq!(packed_rows(&variables(&[
+			("vec2", 2),
+			("vec2", 1),
+			("float", 1),
+			("float", 1)
+		])), 2);
+	}
+
+	#[test]
+	fn packs_known_interfaces() {
+		/* A typical lit interface: world position, normal, color, tangent and
+		 * bitangent, texture coordinates, and a shadow map coordinate for each
+		 * of four lights. The texture coordinates can't share the free column
+		 * of the three component rows, so they take a row of their own. */
+		let lit = variables(&[
+			("vec3", 1),
+			("vec3", 1),
+			("vec2", 1),
+			("vec3", 1),
+			("vec3", 1),
+			("vec3", 1),
+			("vec4", 4),
+		]);
+		assert_eq!(packed_rows(&lit), 10);
+		assert!(fits(&lit, 10));
+		assert!(!fits(&lit, 9));
+
+		/* A heavy interface going over the fifteen vectors WebGL allows on
+		 * some mobile devices. */
+		let heavy = variables(&[
+			("mat4", 1),
+			("vec4", 8),
+			("vec3", 3),
+			("float", 1),
+			("float", 1),
+			("float", 1),
+			("float", 1),
+		]);
+		assert_eq!(packed_rows(&heavy), 16);
+		assert!(!fits(&heavy, 15));
+		assert!(fits(&heavy, 16));
+	}
+
+	#[test]
+	fn itemizes_variables() {
+		let list = itemize(&variables(&[("vec3", 1), ("mat2", 3)]));
+		assert_eq!(list, "\n    - vec3 (vec3, 1 rows of 3 components)\
+			\n    - mat2 (mat2[3], 6 rows of 2 components)");
+	}
+}
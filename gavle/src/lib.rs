@@ -3,10 +3,31 @@ extern crate log;
 
 use glow::{HasContext, Context};
 use std::rc::Rc;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::convert::TryFrom;
 use crate::texture::InnerTexture;
+use crate::texel::{InnerTexelBuffer, TexBufferFunction, TexelLayout, TexelStorage};
+use crate::interface::ShaderInterface;
+use crate::retire::RetirementQueue;
+use crate::sampler::InnerSampler;
+use crate::usage::UsageTracker;
+use crate::blocks::BlockRegistry;
+use crate::locations::AttributeRegistry;
+use crate::init::InitTracker;
+use crate::debug::Debugger;
+use crate::registry::InterfaceRecorder;
+use crate::dedup::{GroupCache, GroupKey, PipelineCache, PipelineKey};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::spirv::SpirVFunctions;
+use crate::srgb::FramebufferQuery;
+use crate::lock::PipelineLock;
+use crate::vao::SharedVertexArrays;
+use crate::access::AccessLock;
+use std::rc::Weak;
+use std::time::Duration;
 
+#[macro_use]
+mod ratelimit;
 mod buffer;
 mod pipeline;
 mod shader;
@@ -16,6 +37,40 @@ mod texture;
 mod access;
 mod framebuffer;
 mod info;
+mod sanitize;
+mod texel;
+mod interface;
+mod fence;
+mod retire;
+mod sampler;
+mod usage;
+mod blocks;
+mod locations;
+mod query;
+mod init;
+mod debug;
+mod stencil;
+mod feedback;
+mod upload;
+#[cfg(not(target_arch = "wasm32"))]
+mod spirv;
+mod srgb;
+mod reflection;
+mod swizzle;
+mod lock;
+mod bundle;
+mod vao;
+mod registry;
+mod dedup;
+mod gl_convert;
+#[cfg(feature = "image")]
+mod image_texture;
+#[cfg(feature = "mipmap-generation")]
+mod mipgen;
+
+/** Re-exports of the types needed by most code rendering with gavle, meant to
+ * be glob imported with `use gavle::prelude::*`. */
+pub mod prelude;
 
 pub use buffer::*;
 pub use pipeline::*;
@@ -25,6 +80,25 @@ pub use binding::*;
 pub use texture::*;
 pub use framebuffer::*;
 pub use info::*;
+pub use sanitize::*;
+pub use texel::*;
+pub use fence::*;
+pub use retire::*;
+pub use sampler::*;
+pub use usage::*;
+pub use ratelimit::*;
+pub use query::*;
+pub use debug::*;
+pub use reflection::*;
+pub use swizzle::*;
+pub use registry::*;
+pub use dedup::{GroupCacheStatistics, PipelineCacheStatistics};
+pub use lock::{LockOperation, LockHolder};
+pub use bundle::{RenderBundle, RenderBundleDescriptor, RenderBundleEncoder};
+pub use feedback::*;
+pub use upload::*;
+#[cfg(feature = "image")]
+pub use image_texture::*;
 
 use smallvec::SmallVec;
 
@@ -39,18 +113,66 @@ macro_rules! instance_shader_creation_functions {
 			-> Result<$shader, ShaderError> {
 
 			let gl = self.context.as_ref();
+			#[cfg(not(target_arch = "wasm32"))]
+			let spirv = match (&source, &self.spirv) {
+				(ShaderSource::SpirV(_), Some(spirv))
+					if self.information.capabilities.spirv_shaders =>
+					Some(spirv),
+				(ShaderSource::SpirV(_), _) =>
+					return Err(ShaderError::UnsupportedSource {
+						what: format!("{} has no support for SPIR-V modules",
+							self.information.version)
+					}),
+				_ => None
+			};
+			/* Shader objects aren't plain names in WebGL, so SPIR-V modules
+			 * can't be loaded into them there. */
+			#[cfg(target_arch = "wasm32")]
+			let spirv = match &source {
+				ShaderSource::SpirV(_) =>
+					return Err(ShaderError::UnsupportedSource {
+						what: format!("{} has no support for SPIR-V modules",
+							self.information.version)
+					}),
+				_ => None::<std::convert::Infallible>
+			};
+			let interface = match &source {
+				ShaderSource::Glsl(source) => ShaderInterface::scan(source),
+				ShaderSource::Preprocessed(source) =>
+					ShaderInterface::scan(source.as_str()),
+				ShaderSource::SpirV(_) => ShaderInterface::default()
+			};
 			let shader = unsafe {
 				let shader = gl.create_shader(<$shader>::GL_TYPE)
 					.map_err(|what| ShaderError::CreationFailed { what })?;
 
-				match source {
-					ShaderSource::Glsl(source) =>
-						gl.shader_source(shader, &source)
+				match (&source, spirv) {
+					(ShaderSource::Glsl(source), _) => {
+						gl.shader_source(shader, source);
+						gl.compile_shader(shader);
+					},
+					(ShaderSource::Preprocessed(source), _) => {
+						gl.shader_source(shader, source.as_str());
+						gl.compile_shader(shader);
+					},
+					#[cfg(not(target_arch = "wasm32"))]
+					(ShaderSource::SpirV(module), Some(spirv)) =>
+						if let Err(what) = spirv.specialize(shader, module) {
+							gl.delete_shader(shader);
+							return Err(what)
+						},
+					#[cfg(target_arch = "wasm32")]
+					(ShaderSource::SpirV(_), Some(never)) => match never {},
+					(ShaderSource::SpirV(_), None) => unreachable!()
 				}
 
-				gl.compile_shader(shader);
 				if !gl.get_shader_compile_status(shader) {
 					let what = gl.get_shader_info_log(shader);
+					let what = match &source {
+						ShaderSource::Preprocessed(source) =>
+							source.map_log(&what),
+						_ => what
+					};
 					return Err(ShaderError::CompilationFailed { what })
 				}
 
@@ -61,88 +183,91 @@ macro_rules! instance_shader_creation_functions {
 				inner: Rc::new(InnerShader {
 					context: self.context.clone(),
 					access: Default::default(),
-					shader
+					shader,
+					interface,
+					spirv: spirv.is_some()
 				}),
 			})
 		}
 	)+}
 }
-/** This macro instances buffer creation functions from a common base. */
-macro_rules! instance_initialized_buffer_creation_functions {
+/** This macro instances the creation functions of the typed buffers, which
+ * create buffers with the usage of their type added to the one given in the
+ * descriptor. */
+macro_rules! instance_typed_buffer_creation_functions {
 	($(
-		$(#[$outer:meta])*
-		pub fn $name:ident: $buffer:ident;
+		$buffer:ident {
+			$(#[$data_outer:meta])*
+			pub fn $data:ident;
+			$(#[$written_outer:meta])*
+			pub fn $written:ident;
+			$(#[$zeroed_outer:meta])*
+			pub fn $zeroed:ident;
+		}
 	)+) => {$(
-		$(#[$outer])*
-		pub fn $name<A: AsRef<[u8]>>(
+		$(#[$data_outer])*
+		pub fn $data<A: AsRef<[u8]>>(
 			&self,
 			descriptor: &BufferDescriptor,
-			data_: A)
+			data: A)
 			-> Result<$buffer, BufferError> {
 
-			let	data = data_.as_ref();
-
-			let len = u32::try_from(data.len());
-			let len = match len {
-				Ok(len) if len != descriptor.size =>
-					panic!("the desired length of the uniform buffer ({}) and the \
-						size of the initialization buffer ({}) must have been the \
-						same", descriptor.size, len),
-				Ok(len) => len,
-				Err(what) =>
-					panic!("the length of the initialization buffer does not fit \
-						in a u32 value, as is required by opengl: {}", what),
+			let descriptor = BufferDescriptor {
+				usage: descriptor.usage | <$buffer>::USAGE,
+				..*descriptor
 			};
+			Ok($buffer { buffer: self.create_buffer_with_data(&descriptor, data)? })
+		}
 
-			let gl = self.context.as_ref();
-			let buffer = unsafe {
-				let buffer = gl.create_buffer()
-					.map_err(|what| BufferError::CreationFailed { what })?;
-
-				gl.bind_buffer(<$buffer>::GL_BIND, Some(buffer));
-				gl.buffer_data_u8_slice(
-					<$buffer>::GL_BIND,
-					data,
-					descriptor.profile.as_opengl());
-				gl.bind_buffer(<$buffer>::GL_BIND, None);
+		$(#[$written_outer])*
+		pub fn $written<F>(
+			&self,
+			descriptor: &BufferDescriptor,
+			f: F)
+			-> Result<$buffer, BufferError>
+			where F: FnOnce(&mut BufferWriter) {
 
-				buffer
+			let descriptor = BufferDescriptor {
+				usage: descriptor.usage | <$buffer>::USAGE,
+				..*descriptor
 			};
-
-			Ok($buffer {
-				inner: Rc::new(InnerBuffer {
-					context: self.context.clone(),
-					information: self.information.clone(),
-					pipeline: self.pipeline_lock.clone(),
-					buffer,
-					access: Default::default(),
-					map: Default::default(),
-					len
-				})
-			})
+			Ok($buffer { buffer: self.create_buffer_with(&descriptor, f)? })
 		}
-	)+}
-}
-/** This macro instances buffer creation functions from a common base. */
-macro_rules! instance_zero_initialized_buffer_creation_functions {
-	($(
-		$(#[$outer:meta])*
-		pub fn $name:ident: $base:ident -> $buffer:ident;
-	)+) => {$(
-		$(#[$outer])*
-		pub fn $name(
+
+		$(#[$zeroed_outer])*
+		pub fn $zeroed(
 			&self,
 			descriptor: &BufferDescriptor)
 			-> Result<$buffer, BufferError> {
 
-			let len  = usize::try_from(descriptor.size).unwrap();
-			let init = vec![0; len];
-
-			self.$base(descriptor, &init[..])
+			let descriptor = BufferDescriptor {
+				usage: descriptor.usage | <$buffer>::USAGE,
+				..*descriptor
+			};
+			Ok($buffer { buffer: self.create_buffer(&descriptor)? })
 		}
 	)+}
 }
 
+/** Entry points a device needs which the context doesn't give us access to,
+ * loaded separately through the loader function of the context, if any. */
+#[derive(Default)]
+struct LoadedFunctions {
+	/** Entry points for creating shaders from SPIR-V modules. */
+	#[cfg(not(target_arch = "wasm32"))]
+	spirv: Option<SpirVFunctions>,
+	/** Entry point for querying the attachments of framebuffers. */
+	framebuffer_query: Option<FramebufferQuery>,
+	/** Entry point for attaching buffers to buffer textures. */
+	tex_buffer: Option<TexBufferFunction>,
+}
+
+/** A device capable of rendering through an OpenGL context.
+ *
+ * Clones of a device are handles to the same context, sharing all of its
+ * state, and may be freely handed around to parts of the application that need
+ * to issue commands of their own. */
+#[derive(Clone)]
 pub struct Device {
 	/** Inner OpenGL context. */
 	context: Rc<Context>,
@@ -155,20 +280,163 @@ pub struct Device {
 	 * drawing commands as atomic transactions.
 	 *
 	 * This structure helps us support that behavior. */
-	pipeline_lock: Rc<RefCell<()>>,
+	pipeline_lock: Rc<PipelineLock>,
+	/** Shared state of the buffer write sanitizer. */
+	sanitizer: Rc<Sanitizer>,
+	/** Resources waiting to be deleted once no frame in flight may use them. */
+	retirement: Rc<RefCell<RetirementQueue<RetiredResource>>>,
+	/** Record of the frames in which textures get bound. */
+	usage: Rc<RefCell<UsageTracker<Weak<InnerTexture>>>>,
+	/** Binding points uniform blocks get pinned to when programs are linked. */
+	blocks: Rc<RefCell<BlockRegistry>>,
+	/** Locations vertex attributes get bound to when programs are linked. */
+	attribute_locations: Rc<RefCell<AttributeRegistry>>,
+	/** Shared state of the error checking and object labeling facilities. */
+	debug: Rc<Debugger>,
+	/** Entry points for creating shaders from SPIR-V modules, when they could
+	 * be loaded. */
+	#[cfg(not(target_arch = "wasm32"))]
+	spirv: Option<Rc<SpirVFunctions>>,
+	/** Entry point for attaching buffers to buffer textures, when it could be
+	 * loaded. */
+	tex_buffer: Option<Rc<TexBufferFunction>>,
+	/** Host memory buffers get written into before being uploaded, when they
+	 * can't be mapped. It only ever grows, so that it gets allocated once. */
+	staging: Rc<RefCell<Vec<u8>>>,
+	/** Size of the drawable surface of the default framebuffer, if known. */
+	default_size: Rc<Cell<Option<(u32, u32)>>>,
+	/** Whether colors written to the default framebuffer get encoded to sRGB,
+	 * where it can be told to. */
+	default_srgb: Rc<Cell<bool>>,
+	/** Interfaces of the pipelines and groups created while recording. */
+	interfaces: Rc<InterfaceRecorder>,
+	/** Uniform groups that may be handed out again when deduplicating. */
+	groups: Rc<GroupCache>,
+	/** Render pipelines that may be handed out again when caching. */
+	pipelines: Rc<PipelineCache<InnerRenderPipeline>>,
+	/** Vertex arrays shared between all of the pipelines of the device. */
+	vertex_arrays: Rc<SharedVertexArrays>,
 }
 impl Device {
 	/** Creates a new device from the given context, obtained externally to the
 	 * device itself. This is useful in contexts in which the device does not
 	 * or would not know how to properly create a context from scratch. */
 	pub fn new_from_context(context: Context) -> Result<Self, UnsupportedContext> {
-		let information = Information::collect(&context)?;
-		debug!("Collected information: {:#?}", information);
+		Self::new_from_context_with_options(context, DeviceOptions::default())
+	}
+
+	/** Creates a new device from the given context, obtained externally to the
+	 * device itself, with the given options. */
+	pub fn new_from_context_with_options(
+		context: Context,
+		options: DeviceOptions) -> Result<Self, UnsupportedContext> {
+
+		Self::new_with_functions(context, options, LoadedFunctions::default())
+	}
+
+	/** Creates a new device along with its context, loading all of the
+	 * OpenGL functions through the given loader function, with the given
+	 * options.
+	 *
+	 * Besides everything a device created with [`new_from_context`] can do,
+	 * devices created this way are able to create shaders from SPIR-V modules
+	 * and to keep texel buffers in buffer textures on contexts that support
+	 * them, which requires entry points the context alone does not give us
+	 * access to.
+	 *
+	 * # Safety
+	 * The loader function must return pointers to the functions of the
+	 * context that is current in the calling thread, or null for the ones it
+	 * doesn't have, just like with [`Context::from_loader_function`].
+	 *
+	 * [`new_from_context`]: Self::new_from_context */
+	#[cfg(not(target_arch = "wasm32"))]
+	pub unsafe fn new_from_loader_function<F>(
+		mut loader: F,
+		options: DeviceOptions) -> Result<Self, UnsupportedContext>
+		where F: FnMut(&str) -> *const std::os::raw::c_void {
+
+		let context = Context::from_loader_function(&mut loader);
+		let functions = LoadedFunctions {
+			spirv: SpirVFunctions::load(&mut loader),
+			framebuffer_query: FramebufferQuery::load(&mut loader),
+			tex_buffer: TexBufferFunction::load(&mut loader),
+		};
+
+		Self::new_with_functions(context, options, functions)
+	}
+
+	/** Creates a new device from the given context, with the given options
+	 * and the entry points loaded for it apart from the context, if any. */
+	fn new_with_functions(
+		context: Context,
+		options: DeviceOptions,
+		functions: LoadedFunctions) -> Result<Self, UnsupportedContext> {
+
+		let mut information = Information::collect(&context)?;
+		#[cfg(not(target_arch = "wasm32"))]
+		if information.capabilities.spirv_shaders && functions.spirv.is_none() {
+			debug!(target: "gavle::device",
+				"The context supports SPIR-V modules, but their entry points \
+				were not loaded, so they won't be available");
+			information.capabilities.spirv_shaders = false;
+		}
+		if information.capabilities.texel_buffers
+			&& (functions.tex_buffer.is_none()
+				|| information.limits.max_texture_buffer_size.is_none()) {
+			debug!(target: "gavle::device",
+				"The context supports buffer textures, but their entry point \
+				was not loaded, so texel buffers will be kept in textures");
+			information.capabilities.texel_buffers = false;
+		}
+		if let Some(query) = &functions.framebuffer_query {
+			information.capabilities.srgb_default_framebuffer = unsafe {
+				query.default_framebuffer_is_srgb(&context, information.version.profile)
+			};
+		}
+		debug!(target: "gavle::device",
+			"Created device for {} on {}",
+			information.version,
+			information.renderer);
+		trace!(target: "gavle::device",
+			"Collected information: {:#?}", information);
+		if options.validation {
+			info!(target: "gavle::device",
+				"Validation is enabled, so OpenGL errors will be checked for \
+				after every state-changing call. Expect it to run slowly");
+		}
+
+		if information.capabilities.seamless_cube_maps
+			&& information.version.profile == Profile::Core {
+
+			/* Unlike OpenGL ES and WebGL, OpenGL only filters cube maps across
+			 * the edges of their faces when asked to. */
+			unsafe { context.enable(glow::TEXTURE_CUBE_MAP_SEAMLESS) }
+		}
 
 		let context = Rc::new(context);
 		Ok(Self {
-			pipeline_lock: Rc::new(RefCell::new(())),
+			pipeline_lock: Default::default(),
+			sanitizer: Default::default(),
+			retirement: Rc::new(RefCell::new(
+				RetirementQueue::new(DEFAULT_RETIREMENT_LATENCY))),
+			usage: Rc::new(RefCell::new(UsageTracker::new())),
+			blocks: Rc::new(RefCell::new(BlockRegistry::new(
+				information.limits.max_uniform_block_bindings))),
+			attribute_locations: Rc::new(RefCell::new(AttributeRegistry::new(
+				information.limits.max_vertex_attribs))),
+			debug: Rc::new(Debugger::new(&options, &information.capabilities)),
 			information: Rc::new(information),
+			#[cfg(not(target_arch = "wasm32"))]
+			spirv: functions.spirv.map(Rc::new),
+			tex_buffer: functions.tex_buffer.map(Rc::new),
+			staging: Default::default(),
+			default_size: Default::default(),
+			default_srgb: Default::default(),
+			interfaces: Default::default(),
+			groups: Default::default(),
+			pipelines: Default::default(),
+			vertex_arrays: Rc::new(SharedVertexArrays::new(context.clone())),
 			context,
 		})
 	}
@@ -178,11 +446,364 @@ impl Device {
 		&*self.information
 	}
 
-	/** Creates a new uniform bind group from the given description. */
+	/** Inserts a new fence into the command stream, which will be signaled
+	 * once the device is done with every command issued before it. */
+	pub fn fence(&self) -> Result<GpuFence, FenceError> {
+		let _atom = self.pipeline_lock.acquire(LockOperation::Synchronization);
+
+		let fence = unsafe {
+			self.context.fence_sync(glow::SYNC_GPU_COMMANDS_COMPLETE, 0)
+				.map_err(|what| FenceError::CreationFailed { what })?
+		};
+
+		Ok(GpuFence {
+			context: self.context.clone(),
+			fence
+		})
+	}
+
+	/** Creates a new query of the given kind, to be run in render passes.
+	 *
+	 * # Errors
+	 * Time elapsed queries are only available when the [`timer_queries`]
+	 * capability is present in the context.
+	 *
+	 * [`timer_queries`]: Capabilities::timer_queries */
+	pub fn create_query(&self, kind: QueryKind) -> Result<Query, QueryError> {
+		if kind == QueryKind::TimeElapsed && !self.information.capabilities.timer_queries {
+			return Err(QueryError::UnsupportedFeature {
+				what: "time elapsed queries require the timer_queries \
+					capability".into()
+			})
+		}
+
+		let _atom = self.pipeline_lock.acquire(LockOperation::Synchronization);
+		let query = unsafe {
+			self.context.create_query()
+				.map_err(|what| QueryError::CreationFailed { what })?
+		};
+
+		Ok(Query {
+			context: self.context.clone(),
+			query,
+			kind,
+			access: Default::default(),
+			ended: Cell::new(false)
+		})
+	}
+
+	/** Blocks until the device is done with every command issued so far.
+	 *
+	 * This drains the whole command stream and should only be relied upon
+	 * where a [`GpuFence`] is not an option.
+	 *
+	 * [`GpuFence`]: GpuFence */
+	pub fn finish(&self) {
+		let _atom = self.pipeline_lock.acquire(LockOperation::Synchronization);
+		unsafe {
+			self.context.finish();
+		}
+	}
+
+	/** Hands the given resource over to the device, which drops it once none
+	 * of the frames that may still be in flight could be using it.
+	 *
+	 * This makes the release of video memory predictable, as opposed to it
+	 * happening whenever the last handle goes away. Keep in mind that the
+	 * resource only gets deleted if this was its last handle, as clones held
+	 * elsewhere, such as in uniform bind groups, keep it alive as usual. */
+	pub fn retire<R>(&self, resource: R)
+		where R: Into<RetiredResource> {
+
+		self.retirement.borrow_mut().retire(resource.into())
+	}
+
+	/** Marks the end of the current frame, dropping the retired resources that
+	 * have outlived the retirement latency. This should be called once per
+	 * frame, after its buffers have been swapped. */
+	pub fn mark_frame(&self) {
+		self.usage.borrow_mut().mark_frame(|texture| texture.strong_count() > 0);
+
+		let expired = self.retirement.borrow_mut().mark_frame();
+		if !expired.is_empty() {
+			trace!(target: "gavle::device",
+				"Dropping {} retired resources",
+				expired.len());
+		}
+	}
+
+	/** Changes the number of frames retired resources wait for before being
+	 * dropped, which should be at least the maximum number of frames the
+	 * device may be behind the host by. The default is
+	 * [`DEFAULT_RETIREMENT_LATENCY`].
+	 *
+	 * [`DEFAULT_RETIREMENT_LATENCY`]: DEFAULT_RETIREMENT_LATENCY */
+	pub fn set_retirement_latency(&self, frames: u32) {
+		self.retirement.borrow_mut().set_latency(frames)
+	}
+
+	/** A report on the bookkeeping this device does on its resources. */
+	pub fn resource_report(&self) -> ResourceReport {
+		let retirement = self.retirement.borrow();
+		ResourceReport {
+			frame: retirement.frame(),
+			retirement_latency: retirement.latency(),
+			pending_retirements: retirement.pending()
+		}
+	}
+
+	/** Turns the collection of statistics on how resources get used on or
+	 * off. Collection is off by default, and turning it off throws away all
+	 * of the statistics collected so far.
+	 *
+	 * While it's on, every bind of a texture in a render pass costs an extra
+	 * hash map update, and [`mark_frame()`] has to be called every frame for
+	 * the statistics to be of any use.
+	 *
+	 * [`mark_frame()`]: Self::mark_frame */
+	pub fn set_statistics(&self, enabled: bool) {
+		self.usage.borrow_mut().set_enabled(enabled)
+	}
+
+	/** A report on how recently every live texture was bound in a render pass,
+	 * sorted from the one that has gone for the longest without being bound to
+	 * the most recently bound one. Textures of the same staleness are sorted
+	 * from the largest to the smallest.
+	 *
+	 * Binding a texture doesn't mean it gets sampled, but it is a good enough
+	 * approximation for picking textures to evict. Only textures bound since
+	 * [statistics] were turned on show up in this report.
+	 *
+	 * [statistics]: Self::set_statistics */
+	pub fn texture_usage_report(&self) -> Vec<TextureUsage> {
+		self.usage.borrow().report(|texture| {
+			let texture = texture.upgrade()?;
+			let label = texture.label.borrow().clone();
+
+			Some((label, texture.bytes()))
+		})
+	}
+
+	/** Runs the given function with access to the raw context of this
+	 * device, for calling into functionality gavle doesn't wrap.
+	 *
+	 * # Safety
+	 * Object bindings may be changed freely, as gavle sets up the ones it needs
+	 * before every operation, but all other state changed in the function,
+	 * such as capabilities, pixel store parameters and the active texture
+	 * unit, must be put back the way it was found before returning. Objects
+	 * owned by gavle must not be deleted or reallocated.
+	 *
+	 * # Panic
+	 * This function will panic if the pipeline can't be locked mutably, which
+	 * is the case while a render pass is going on. */
+	pub unsafe fn with_external_gl<F, T>(&self, f: F) -> T
+		where F: FnOnce(&Context) -> T {
+
+		let _atom = self.pipeline_lock.acquire(LockOperation::ExternalGl);
+
+		/* Pipelines keep their vertex arrays set up between passes, so the
+		 * function must not get to change the attributes of whichever one
+		 * was left bound. */
+		if self.information.capabilities.vertex_arrays {
+			self.context.bind_vertex_array(None);
+		}
+		f(self.context.as_ref())
+	}
+
+	/** Who is currently holding the pipeline lock, if anyone.
+	 *
+	 * Render passes and buffer mappings hold the lock for as long as they're
+	 * alive, and most other operations on the device can't run while it is
+	 * being held, so this is the first place to look when one of them panics
+	 * because the pipeline is locked. Those panics name the holder too. */
+	pub fn pipeline_lock_holder(&self) -> Option<LockHolder> {
+		self.pipeline_lock.holder()
+	}
+
+	/** Makes every hold of the pipeline lock longer than the given duration
+	 * get logged as a warning when it ends, naming the operation that held it,
+	 * even when nothing else tried to take the lock in the meantime. This
+	 * catches things like a render pass being kept alive across a long
+	 * computation before that turns into a panic somewhere else.
+	 *
+	 * This is turned off by default, and has no effect in targets without a
+	 * monotonic clock, such as WebAssembly. */
+	pub fn lock_hold_warning_threshold(&self, threshold: Duration) {
+		self.pipeline_lock.set_threshold(Some(threshold))
+	}
+
+	/** Changes how writes of non-finite values into buffers created with
+	 * [`BufferContents::F32`] get handled.
+	 *
+	 * This is a debugging aid and is turned off by default. When turned on,
+	 * every write into such buffers gets scanned, which has a cost roughly
+	 * proportional to the amount of data being written.
+	 *
+	 * [`BufferContents::F32`]: BufferContents::F32 */
+	pub fn set_write_sanitizer(&self, mode: SanitizeMode) {
+		self.sanitizer.set_mode(mode)
+	}
+
+	/** Starts or stops recording the interfaces of the pipelines and uniform
+	 * groups created by this device into its [interface registry].
+	 *
+	 * This is a debugging aid and is turned off by default. Only objects
+	 * created while it is on get recorded, so it should be turned on before
+	 * anything gets created.
+	 *
+	 * [interface registry]: Self::interface_registry */
+	pub fn record_interfaces(&self, enabled: bool) {
+		self.interfaces.set_enabled(enabled)
+	}
+
+	/** The interfaces of every pipeline and uniform group recorded so far,
+	 * which is empty unless recording has been turned on with
+	 * [`record_interfaces()`].
+	 *
+	 * [`record_interfaces()`]: Self::record_interfaces */
+	pub fn interface_registry(&self) -> InterfaceRegistry {
+		self.interfaces.registry()
+	}
+
+	/** Turns the deduplication of uniform groups on or off. While it is on,
+	 * creating a group from a description identical to that of a group that
+	 * is still alive, down to the resources, the parameters and the order of
+	 * its entries, hands out another handle to that group instead.
+	 *
+	 * Handles to the same group share its entries, so replacing an entry
+	 * through any of them replaces it in all of them, and takes the group out
+	 * of the cache. Use [`UniformGroup::with_replaced_texture()`] and the like
+	 * to get a modified copy of a group instead.
+	 *
+	 * This is off by default. Turning it off empties the cache.
+	 *
+	 * [`UniformGroup::with_replaced_texture()`]: crate::UniformGroup::with_replaced_texture */
+	pub fn set_uniform_group_deduplication(&self, enabled: bool) {
+		self.groups.set_enabled(enabled)
+	}
+
+	/** Counters of the uniform group cache used for deduplication. */
+	pub fn uniform_group_cache_statistics(&self) -> GroupCacheStatistics {
+		self.groups.statistics()
+	}
+
+	/** Turns the caching of render pipelines on or off. While it is on,
+	 * creating a pipeline from a description identical to that of a pipeline
+	 * in the cache hands out another handle to that pipeline, rather than
+	 * linking a new program. Shaders are told apart by identity, so the same
+	 * source compiled twice gives two different shaders, and two different
+	 * pipelines.
+	 *
+	 * Handles to the same pipeline share everything, including its label.
+	 * The cache keeps the pipelines in it alive until they get evicted, with
+	 * [`evict_render_pipeline()`] or [`evict_unused_render_pipelines()`].
+	 *
+	 * This is off by default. Turning it off empties the cache.
+	 *
+	 * [`evict_render_pipeline()`]: Self::evict_render_pipeline
+	 * [`evict_unused_render_pipelines()`]: Self::evict_unused_render_pipelines */
+	pub fn set_render_pipeline_caching(&self, enabled: bool) {
+		self.pipelines.set_enabled(enabled)
+	}
+
+	/** Counters of the render pipeline cache. */
+	pub fn render_pipeline_cache_statistics(&self) -> PipelineCacheStatistics {
+		self.pipelines.statistics()
+	}
+
+	/** Drops the given pipeline from the render pipeline cache, so that it
+	 * gets deleted once the handles to it are gone, and returns whether it was
+	 * in the cache. */
+	pub fn evict_render_pipeline(&self, pipeline: &RenderPipeline) -> bool {
+		self.pipelines.evict(&pipeline.inner)
+	}
+
+	/** Drops every pipeline in the render pipeline cache that is not in use
+	 * anywhere else, deleting them, and returns how many of them there were. */
+	pub fn evict_unused_render_pipelines(&self) -> usize {
+		self.pipelines.evict_unused()
+	}
+
+	/** Creates a new sampler from the given description.
+	 *
+	 * # Errors
+	 * This function fails on contexts without support for [sampler objects],
+	 * in which case the filtering parameters of a texture have to be given
+	 * along with it in the bind group instead.
+	 *
+	 * [sampler objects]: crate::Capabilities::sampler_objects */
+	pub fn create_sampler(
+		&self,
+		descriptor: &SamplerDescriptor)
+		-> Result<Sampler, SamplerError> {
+
+		if !self.information.capabilities.sampler_objects {
+			return Err(SamplerError::UnsupportedFeature {
+				what: "the current context has no support for sampler \
+					objects".into()
+			})
+		}
+
+		let features = &self.information.features;
+		let limits = &self.information.limits;
+		match descriptor.anisotropy_clamp {
+			Some(_) if !features.sampler_anisotropy =>
+				return Err(SamplerError::UnsupportedFeature {
+					what: "anisotropic filtering is not supported by the \
+						current context".into()
+				}),
+			Some(anisotropy)
+				if f32::from(anisotropy.get()) >
+					limits.max_sampler_anisotropy.unwrap() =>
+				return Err(SamplerError::LimitExceeded {
+					what: format!("the anisotropy clamp factor ({}) is higher \
+						than the maximum allowed ({})",
+						anisotropy.get(),
+						limits.max_sampler_anisotropy.unwrap())
+				}),
+			_ => {}
+		}
+
+		let _atom = self.pipeline_lock.acquire(LockOperation::SamplerCreation);
+		let gl = self.context.as_ref();
+		let sampler = unsafe {
+			let sampler = gl.create_sampler()
+				.map_err(|what| SamplerError::CreationFailed { what })?;
+			Sampler::setup(gl, sampler, descriptor, features.sampler_anisotropy);
+
+			sampler
+		};
+
+		Ok(Sampler {
+			inner: Rc::new(InnerSampler {
+				context: self.context.clone(),
+				sampler
+			})
+		})
+	}
+
+	/** Creates a new uniform bind group from the given description.
+	 *
+	 * With [group deduplication] turned on, this hands out another handle to
+	 * a live group created from an identical description, if there is one,
+	 * rather than creating a new group.
+	 *
+	 * [group deduplication]: Self::set_uniform_group_deduplication */
 	pub fn create_uniform_bind_group(
 		&self,
 		description: &UniformGroupDescriptor)
-		-> UniformGroup {
+		-> Result<UniformGroup, BindGroupError> {
+
+		let key = if self.groups.enabled() {
+			let key = GroupKey::of(description);
+			if let Some(group) = self.groups.get(&key) {
+				return Ok(group)
+			}
+			Some(key)
+		} else {
+			None
+		};
 
 		let mut buffers = 0u32;
 		let mut textures = 0u32;
@@ -195,102 +816,244 @@ impl Device {
 					texture,
 					far,
 					near,
-					anisotropy_clamp } => {
+					address_mode,
+					anisotropy_clamp,
+					sampler } |
+				UniformBind::TextureView {
+					view: TextureView { texture, .. },
+					far,
+					near,
+					address_mode,
+					anisotropy_clamp,
+					sampler } => {
 
 					textures += 1;
 
 					/* Check whether the anisotropy parameters are valid. */
 					match anisotropy_clamp {
 						Some(_) if !self.information.features.sampler_anisotropy =>
-							panic!("Tried to create a uniform bind group in \
-								which a texture has anisotropic filtering, \
-								even though anisotropic filtering is not \
-								supported by the current context."),
+							return Err(BindGroupError::UnsupportedFeature {
+								what: format!("the texture bound to \"{}\" has \
+									anisotropic filtering, which is not \
+									supported by the current context",
+									bind)
+							}),
 						Some(anisotropy)
 							if f32::from(anisotropy.get()) >
 								self.information
 									.limits
 									.max_sampler_anisotropy
 									.unwrap() =>
-							panic!("Tried to create a uniform bind group in \
-								which a texture has an anisotropy clamp factor \
-								({}) higher than the maximum factor allowed by \
-								the current context ({}).",
-								anisotropy.get(),
-								self.information
-									.limits
-									.max_sampler_anisotropy
-									.unwrap()),
+							return Err(BindGroupError::LimitExceeded {
+								what: format!("the texture bound to \"{}\" has \
+									an anisotropy clamp factor ({}) higher than \
+									the maximum allowed ({})",
+									bind,
+									anisotropy.get(),
+									self.information
+										.limits
+										.max_sampler_anisotropy
+										.unwrap())
+							}),
 						_ =>
 							/* All good. */
 							{}
 					}
 
+					/* Plain textures are bound as a view of all of their
+					 * levels and layers. */
+					let (view, sliced) = match entry.kind {
+						UniformBind::TextureView { view, .. } =>
+							(view.descriptor, true),
+						_ => (TextureViewDescriptor {
+							base_mip: 0,
+							mip_count: texture.levels(),
+							base_layer: 0,
+							layer_count: texture.layers()
+						}, false)
+					};
+
 					OwnedUniformBind::Texture {
 						texture: Texture { inner: texture.inner.clone() },
 						far,
 						near,
-						anisotropy_clamp
+						address_mode,
+						anisotropy_clamp,
+						sampler: sampler.cloned(),
+						view,
+						sliced
 					}
 				},
-				UniformBind::Buffer { buffer } => {
+				UniformBind::TexelBuffer { buffer } => {
+					textures += 1;
+
+					OwnedUniformBind::TexelBuffer {
+						buffer: TexelBuffer { inner: buffer.inner.clone() }
+					}
+				},
+				UniformBind::Buffer { buffer, offset, size } => {
+					if !self.information.capabilities.uniform_buffers {
+						return Err(BindGroupError::UnsupportedFeature {
+							what: format!("uniform buffers can't be bound to \
+								\"{}\", as {} has no support for them",
+								bind,
+								self.information.version)
+						})
+					}
+					check_uniform_usage(&bind, buffer)?;
 					buffers += 1;
 
-					if buffer.len() > self.information
+					let size = match size {
+						Some(size) => size.get(),
+						None => buffer.len().checked_sub(offset)
+							.ok_or_else(|| BindGroupError::InvalidOffset {
+								what: format!("the range bound to \"{}\" \
+									starts at {}, past the end of a buffer of \
+									{} bytes",
+									bind,
+									offset,
+									buffer.len())
+							})?
+					};
+					if size > self.information
 						.limits
 						.max_uniform_block_size {
 
-						panic!("tried to use a uniform buffer larger than the \
-							maximum size allowed for a single uniform binding: \
-							len = {} > max = {}",
-							buffer.len(),
-							self.information
-								.limits
-								.max_uniform_block_size)
+						return Err(BindGroupError::LimitExceeded {
+							what: format!("the uniform buffer range bound to \
+								\"{}\" is larger than the maximum size allowed \
+								for a single uniform binding: len = {} > max = {}",
+								bind,
+								size,
+								self.information
+									.limits
+									.max_uniform_block_size)
+						})
 					}
+					check_range(
+						&bind,
+						buffer.len(),
+						offset,
+						size,
+						0,
+						self.information.limits.uniform_buffer_offset_alignment)?;
 
 					OwnedUniformBind::Buffer {
-						buffer: UniformBuffer { inner: buffer.inner.clone() }
+						buffer: buffer.share(),
+						offset,
+						size
 					}
 				},
 			};
 
 			/* Make sure we haven't used bound resources than is allowed. */
 			if buffers > self.information.limits.max_uniform_block_bindings {
-				panic!("tried to use more uniform buffer bindings than is \
-					allowed by the implementation. the maximum number of \
-					uniform buffer bindings is {}",
-					self.information.limits.max_uniform_block_bindings)
+				return Err(BindGroupError::LimitExceeded {
+					what: format!("the maximum number of uniform buffer \
+						bindings is {}",
+						self.information.limits.max_uniform_block_bindings)
+				})
 			}
 			if textures > self.information.limits.max_textures {
-				panic!("tried to use more texture bindings than is allowed by \
-					the implementation. the maximum number of texture bindings \
-					is {}",
-					self.information.limits.max_textures)
+				return Err(BindGroupError::LimitExceeded {
+					what: format!("the maximum number of texture bindings is {}",
+						self.information.limits.max_textures)
+				})
 			}
 
 			entries.push((bind, kind));
 		}
-
-		UniformGroup {
-			entries: Rc::new(entries)
+		self.interfaces.record_group(entries.iter()
+			.map(|(binding, entry)| RegisteredEntry {
+				binding: binding.clone(),
+				kind: entry.resource()
+			}));
+
+		let group = UniformGroup {
+			entries: Rc::new(RefCell::new(entries)),
+			revision: Rc::new(Cell::new(0))
+		};
+		if let Some(key) = key {
+			self.groups.insert(key, &group);
 		}
+
+		Ok(group)
 	}
 
 	/** Get a handle to the default framebuffer, used to render to the screen
-	 * and completely managed by OpenGL. */
+	 * and completely managed by OpenGL.
+	 *
+	 * # Panics
+	 * This function panics if the depth load operation clears to NaN. */
 	pub fn default_framebuffer(&self,
 		descriptor: &DefaultFramebufferDescriptor) -> Framebuffer {
 
+		if let Err(what) = check_depth_load_op(descriptor.depth_load_op) {
+			panic!("invalid default framebuffer descriptor: {}", what)
+		}
 		Framebuffer {
+			context: self.context.clone(),
+			pipeline: self.pipeline_lock.clone(),
+			debug: self.debug.clone(),
+			label: Default::default(),
 			variants: FramebufferVariants::Default {
 				color_load_op: descriptor.color_load_op,
 				depth_load_op: descriptor.depth_load_op,
-				stencil_load_op: descriptor.stencil_load_op
+				stencil_load_op: descriptor.stencil_load_op,
+				size: self.default_size.clone(),
+				srgb: self.default_srgb.clone()
 			}
 		}
 	}
 
+	/** Tells the device the size of the drawable surface of the default
+	 * framebuffer, in pixels, which should be done whenever the window gets
+	 * resized, and once before rendering to it for the first time.
+	 *
+	 * OpenGL has no way of telling how large the default framebuffer is, so
+	 * this is what [`Framebuffer::size`] reports for it, and what
+	 * [`RenderPass::set_full_viewport`] covers, for every handle to it, both
+	 * past and future ones.
+	 *
+	 * [`Framebuffer::size`]: Framebuffer::size
+	 * [`RenderPass::set_full_viewport`]: RenderPass::set_full_viewport */
+	pub fn resize_default_framebuffer(&self, width: u32, height: u32) {
+		self.default_size.set(Some((width, height)))
+	}
+
+	/** Whether colors written to the default framebuffer get encoded to sRGB,
+	 * as set by [`set_srgb_encoding()`].
+	 *
+	 * [`set_srgb_encoding()`]: Self::set_srgb_encoding */
+	pub fn srgb_encoding(&self) -> bool {
+		self.default_srgb.get()
+	}
+
+	/** Tells the device whether colors written to the default framebuffer
+	 * should get encoded to sRGB, for every handle to it, both past and future
+	 * ones, starting with the next render pass drawing to it. This is off by
+	 * default.
+	 *
+	 * The intended way to go about color is for shaders to work with linear
+	 * values, and for them to get encoded to sRGB as they get written to the
+	 * framebuffer, which is what displays expect. Framebuffers created with
+	 * [`TextureFormat::Srgb8Alpha8`] attachments always get written to this
+	 * way, and sampling textures in that format decodes them back to linear.
+	 * The default framebuffer only gets written to this way if it stores its
+	 * colors with the sRGB encoding, as told by the
+	 * [`srgb_default_framebuffer`] capability, and if the context can turn
+	 * the encoding on and off, as told by [`srgb_write_control`], this is
+	 * what does it. Contexts that can't turn it off always encode, while
+	 * default framebuffers without the sRGB encoding never do, and have to be
+	 * drawn to with a pass that encodes the colors in the shader, such as
+	 * the one in the support crate, instead.
+	 *
+	 * [`srgb_default_framebuffer`]: Capabilities::srgb_default_framebuffer
+	 * [`srgb_write_control`]: Capabilities::srgb_write_control */
+	pub fn set_srgb_encoding(&self, enabled: bool) {
+		self.default_srgb.set(enabled)
+	}
+
 	/** Tries to create a new framebuffer. Keep in mind that framebuffers
 	 * created with this function can only be used for off-screen rendering.
 	 *
@@ -301,11 +1064,11 @@ impl Device {
 		descriptor: &FramebufferDescriptor)
 		-> Result<Framebuffer, FramebufferError> {
 
-		let _atom = self.pipeline_lock.borrow_mut();
+		let _atom = self.pipeline_lock.acquire(LockOperation::FramebufferCreation);
 
 		/* This function checks the extents of an attachment if that kind of
 		 * information is available to us. */
-		let check_extent = |width, height| {
+		let check_extent = |texture: &Texture| {
 			let max_attachment_width = self.information
 				.limits
 				.max_framebuffer_attachment_width;
@@ -313,26 +1076,100 @@ impl Device {
 				.limits
 				.max_framebuffer_attachment_height;
 
+			let (width, height, _) = texture.extent().axes();
 			let extent = (
 				max_attachment_width,
 				max_attachment_height);
 			if let (Some(max_width), Some(max_height)) = extent {
 				if width > max_width {
-					panic!("cannot use texture with width of {} as a \
-							framebuffer attachment. the maximum width allowed \
-							for framebuffer attachments is {}",
-						width,
-						max_width)
+					return Err(FramebufferError::LimitExceeded {
+						what: format!("cannot use texture with width of {} as \
+							a framebuffer attachment. the maximum width \
+							allowed for framebuffer attachments is {}",
+							width,
+							max_width)
+					})
 				}
 				if height > max_height {
-					panic!("cannot use texture with height of {} as a \
-							framebuffer attachment. the maximum height allowed \
-							for framebuffer attachments is {}",
-						height,
-						max_height)
+					return Err(FramebufferError::LimitExceeded {
+						what: format!("cannot use texture with height of {} as \
+							a framebuffer attachment. the maximum height \
+							allowed for framebuffer attachments is {}",
+							height,
+							max_height)
+					})
 				}
 			}
+			Ok(())
+		};
+
+		/* Check all of the attachments against the limits before we create
+		 * anything, so that we don't have to clean up after a failure. */
+		let max_color_attachments = self.information
+			.limits
+			.max_framebuffer_color_attachments;
+		if descriptor.color_attachments.len() > max_color_attachments as usize {
+			return Err(FramebufferError::LimitExceeded {
+				what: format!("{} color attachments were given, but the \
+					maximum number of color attachments is {}",
+					descriptor.color_attachments.len(),
+					max_color_attachments)
+			})
+		}
+		/* Only textures with layers or faces have anything but their first
+		 * layer to attach. */
+		let check_layer = |texture: &Texture, layer: u32| {
+			let (_, _, layers) = texture.extent().axes();
+			let layers = match texture.extent() {
+				TextureExtent::D2Array { .. } | TextureExtent::Cube { .. } => layers,
+				_ => 1
+			};
+			if layer >= layers {
+				return Err(FramebufferError::InvalidAttachment {
+					what: format!("tried to attach layer {} of a texture of \
+						extent {:?}, which only has {}",
+						layer,
+						texture.extent(),
+						layers)
+				})
+			}
+			Ok(())
+		};
+		/* Any level a texture has can be attached, except in legacy contexts,
+		 * which can only render to the base level. */
+		let legacy = self.information.version.profile.is_legacy();
+		let check_level = |texture: &Texture, level: u32| {
+			if level >= texture.levels() {
+				return Err(FramebufferError::InvalidAttachment {
+					what: format!("tried to attach mip level {} of texture {}, \
+						which only has {}",
+						level,
+						texture.inner.name(),
+						texture.levels())
+				})
+			}
+			if level != 0 && legacy {
+				return Err(FramebufferError::InvalidAttachment {
+					what: format!("tried to attach mip level {} of texture {}, \
+						but legacy contexts can only render to the base level",
+						level,
+						texture.inner.name())
+				})
+			}
+			Ok(())
 		};
+		for attachment in descriptor.color_attachments {
+			check_extent(attachment.attachment)?;
+			check_layer(attachment.attachment, attachment.layer)?;
+			check_level(attachment.attachment, attachment.mip_level)?;
+		}
+		if let Some(attachment) = &descriptor.depth_stencil_attachment {
+			check_extent(attachment.attachment)?;
+			check_layer(attachment.attachment, attachment.layer)?;
+			check_level(attachment.attachment, attachment.mip_level)?;
+			check_depth_load_op(attachment.depth_load_op)
+				.map_err(|what| FramebufferError::InvalidAttachment { what })?;
+		}
 
 		let gl = self.context.as_ref();
 		let (framebuffer, color_attachments, depth_stencil) = unsafe {
@@ -340,33 +1177,40 @@ impl Device {
 				.map_err(|what| FramebufferError::CreationError { what })?;
 
 			gl.bind_framebuffer(glow::FRAMEBUFFER, Some(framebuffer));
+			/* Levels are no more than 32, so they always fit in an i32. */
 			let bind_texture = |
 				texture: &Texture,
-				attachment: u32| match texture.inner.extent {
+				layer: u32,
+				level: u32,
+				attachment: u32| match texture.inner.extent.get() {
 				TextureExtent::D1 { .. } | TextureExtent::D3 { .. } =>
 					panic!("cannot bind a one-dimensional or three-dimensional \
 						texture to a framebuffer"),
-				TextureExtent::D2 { width, height } => {
-					check_extent(width, height);
-
+				TextureExtent::D2 { .. } => {
 					gl.framebuffer_texture_2d(
 						glow::FRAMEBUFFER,
 						attachment,
 						glow::TEXTURE_2D,
 						Some(texture.inner.texture),
-						0)
+						i32::try_from(level).unwrap())
 				},
-				TextureExtent::D2Array { width, height, .. } => {
-					warn!("using the first layer of the array texture for the \
-						framebuffer attachment");
-					check_extent(width, height);
-
+				TextureExtent::D2Array { .. } => {
+					/* The number of layers was checked to fit in an i32 when
+					 * the texture was created. */
 					gl.framebuffer_texture_layer(
 						glow::FRAMEBUFFER,
 						attachment,
 						Some(texture.inner.texture),
-						0,
-						0)
+						i32::try_from(level).unwrap(),
+						i32::try_from(layer).unwrap())
+				},
+				TextureExtent::Cube { .. } => {
+					gl.framebuffer_texture_2d(
+						glow::FRAMEBUFFER,
+						attachment,
+						TextureExtent::cube_face_target(layer),
+						Some(texture.inner.texture),
+						i32::try_from(level).unwrap())
 				}
 			};
 
@@ -378,16 +1222,12 @@ impl Device {
 
 			let attachments = (0u32..).zip(descriptor.color_attachments);
 			for (i, texture) in attachments {
-				if i >= self.information
-					.limits
-					.max_framebuffer_color_attachments {
-
-					panic!("the total number of color attachments would be \
-						more than the maximum number of allowed attachments");
-				}
-
 				let attachment = glow::COLOR_ATTACHMENT0 + i;
-				bind_texture(texture.attachment, attachment);
+				bind_texture(
+					texture.attachment,
+					texture.layer,
+					texture.mip_level,
+					attachment);
 
 				color_attachments.push(Texture {
 					inner: texture.attachment.inner.clone()
@@ -403,7 +1243,11 @@ impl Device {
 						a texture whose format is not a depth-stencil format: \
 						{:?}", texture.attachment.format())
 				}
-				bind_texture(texture.attachment, glow::DEPTH_STENCIL_ATTACHMENT);
+				bind_texture(
+					texture.attachment,
+					texture.layer,
+					texture.mip_level,
+					glow::DEPTH_STENCIL_ATTACHMENT);
 				depth_stencil = Some(Texture {
 					inner: texture.attachment.inner.clone(),
 				});
@@ -413,7 +1257,13 @@ impl Device {
 			match gl.check_framebuffer_status(glow::FRAMEBUFFER) {
 				glow::FRAMEBUFFER_COMPLETE => { /* Okay. */ },
 				glow::FRAMEBUFFER_INCOMPLETE_ATTACHMENT =>
-					panic!("the given attachments are framebuffer incomplete"),
+					panic!("the given attachments are framebuffer incomplete: \
+						{}",
+						color_attachments.iter()
+							.chain(&depth_stencil)
+							.map(|texture| texture.inner.name())
+							.collect::<Vec<_>>()
+							.join(", ")),
 				glow::FRAMEBUFFER_INCOMPLETE_MISSING_ATTACHMENT =>
 					panic!("no attachments were given to the framebuffer"),
 				other =>
@@ -422,55 +1272,549 @@ impl Device {
 
 			/* Tell OpenGL to enable all of the targets in the framebuffer for
 			 * drawing by the fragment shader. If we don't do this, the shader
-			 * will only ever output to the first color attachment. */
-			gl.draw_buffers(&draw_buffers[..]);
-
-
+			 * will only ever output to the first color attachment. Legacy
+			 * contexts only ever have the one, and no way to pick it. */
+			if !self.information.version.profile.is_legacy() {
+				gl.draw_buffers(&draw_buffers[..]);
+			}
 			gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+			self.debug.check(gl, "creating", || format!("framebuffer #{:?}",
+				framebuffer));
+
 			(framebuffer, color_attachments, depth_stencil)
 		};
 
 		Ok(Framebuffer {
+			context: self.context.clone(),
+			pipeline: self.pipeline_lock.clone(),
+			debug: self.debug.clone(),
+			label: Default::default(),
 			variants: FramebufferVariants::Custom {
 				inner: Rc::new(InnerFramebuffer {
 					context: self.context.clone(),
 					access: Default::default(),
 					color_attachments,
+					color_layers: descriptor.color_attachments.iter()
+						.map(|attachment| attachment.layer)
+						.collect(),
+					color_levels: descriptor.color_attachments.iter()
+						.map(|attachment| attachment.mip_level)
+						.collect(),
 					depth_stencil,
+					depth_stencil_layer: descriptor.depth_stencil_attachment
+						.map(|attachment| attachment.layer)
+						.unwrap_or(0),
+					depth_stencil_level: descriptor.depth_stencil_attachment
+						.map(|attachment| attachment.mip_level)
+						.unwrap_or(0),
+					multisampled: None,
 					framebuffer,
-					color_load_op: descriptor.color_attachments.get(0)
+					color_load_ops: descriptor.color_attachments.iter()
 						.map(|attachment| attachment.load_op)
-						.unwrap_or(LoadOp::Load),
+						.collect(),
 					depth_load_op: descriptor.depth_stencil_attachment
 						.map(|attachment| attachment.depth_load_op)
-						.unwrap_or(LoadOp::Clear(f32::INFINITY)),
+						.unwrap_or(LoadOp::Load),
 					stencil_load_op: descriptor.depth_stencil_attachment
 						.map(|attachment| attachment.stencil_load_op)
-						.unwrap_or(LoadOp::Clear(0xff)),
+						.unwrap_or(LoadOp::Load),
+					draw_buffers: !self.information.version.profile.is_legacy(),
 				})
 			}
 		})
 	}
 
-	/** Lock the render pipeline and start a new render pass from the given
-	 * parameters. */
-	pub fn start_render_pass<'a>(
-		&'a self,
-		descriptor: &RenderPassDescriptor<'a>)
-		-> RenderPass<'a> {
+	/** Tries to create a new multisampled framebuffer, along with its storage.
+	 * Like other custom framebuffers, these can only be used for off-screen
+	 * rendering, and their contents get to the screen, or anywhere else, by
+	 * being blitted over to a single-sampled framebuffer with
+	 * [`blit_framebuffer()`], which resolves them.
+	 *
+	 * # Errors
+	 * The number of samples must be at least two and at most the
+	 * [`max_samples`] limit of the context, and only
+	 * [`TextureFormat::Rgba8Unorm`] can be multisampled everywhere.
+	 *
+	 * [`blit_framebuffer()`]: Device::blit_framebuffer
+	 * [`max_samples`]: Limits::max_samples
+	 * [`TextureFormat::Rgba8Unorm`]: TextureFormat::Rgba8Unorm */
+	pub fn create_multisampled_framebuffer(
+		&self,
+		descriptor: &MultisampledFramebufferDescriptor)
+		-> Result<Framebuffer, FramebufferError> {
 
-		RenderPass {
+		let limits = &self.information.limits;
+		if descriptor.samples < 2 || descriptor.samples > limits.max_samples {
+			return Err(FramebufferError::LimitExceeded {
+				what: format!("{} samples were requested, but multisampled \
+					framebuffers must have between 2 and {} samples",
+					descriptor.samples,
+					limits.max_samples)
+			})
+		}
+		if descriptor.color_format != TextureFormat::Rgba8Unorm {
+			return Err(FramebufferError::UnsupportedFormat {
+				what: format!("the {:?} format can't be multisampled",
+					descriptor.color_format)
+			})
+		}
+		check_depth_load_op(descriptor.depth_load_op)
+			.map_err(|what| FramebufferError::InvalidAttachment { what })?;
+		let max_width = limits.max_framebuffer_attachment_width
+			.unwrap_or(limits.max_texture_size);
+		let max_height = limits.max_framebuffer_attachment_height
+			.unwrap_or(limits.max_texture_size);
+		let size = (
+			i32::try_from(descriptor.width).ok().filter(|_| descriptor.width <= max_width),
+			i32::try_from(descriptor.height).ok().filter(|_| descriptor.height <= max_height));
+		let (width, height) = match size {
+			(Some(width), Some(height)) if width > 0 && height > 0 => (width, height),
+			_ => return Err(FramebufferError::LimitExceeded {
+				what: format!("a multisampled framebuffer can't be {}x{} \
+					pixels big, the maximum is {}x{}",
+					descriptor.width,
+					descriptor.height,
+					max_width,
+					max_height)
+			})
+		};
+		/* This is no larger than a limit given to us as an i32. */
+		let samples = i32::try_from(descriptor.samples).unwrap();
+
+		let _atom = self.pipeline_lock.acquire(LockOperation::FramebufferCreation);
+
+		let gl = self.context.as_ref();
+		let (framebuffer, renderbuffers) = unsafe {
+			let mut formats = SmallVec::<[(u32, u32); 2]>::new();
+			formats.push((
+				descriptor.color_format.as_opengl().1,
+				glow::COLOR_ATTACHMENT0));
+			if descriptor.depth_stencil {
+				formats.push((
+					TextureFormat::Depth24Stencil8.as_opengl().1,
+					glow::DEPTH_STENCIL_ATTACHMENT));
+			}
+
+			let framebuffer = gl.create_framebuffer()
+				.map_err(|what| FramebufferError::CreationError { what })?;
+			gl.bind_framebuffer(glow::FRAMEBUFFER, Some(framebuffer));
+
+			let mut renderbuffers = SmallVec::<[_; 2]>::new();
+			for (internal_format, attachment) in formats {
+				let renderbuffer = match gl.create_renderbuffer() {
+					Ok(renderbuffer) => renderbuffer,
+					Err(what) => {
+						gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+						gl.delete_framebuffer(framebuffer);
+						for renderbuffer in renderbuffers {
+							gl.delete_renderbuffer(renderbuffer);
+						}
+						return Err(FramebufferError::CreationError { what })
+					}
+				};
+				gl.bind_renderbuffer(glow::RENDERBUFFER, Some(renderbuffer));
+				gl.renderbuffer_storage_multisample(
+					glow::RENDERBUFFER,
+					samples,
+					internal_format,
+					width,
+					height);
+				gl.framebuffer_renderbuffer(
+					glow::FRAMEBUFFER,
+					attachment,
+					glow::RENDERBUFFER,
+					Some(renderbuffer));
+				renderbuffers.push(renderbuffer);
+			}
+			gl.bind_renderbuffer(glow::RENDERBUFFER, None);
+
+			let status = gl.check_framebuffer_status(glow::FRAMEBUFFER);
+			gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+			self.debug.check(gl, "creating", || format!("framebuffer #{:?}",
+				framebuffer));
+
+			if status != glow::FRAMEBUFFER_COMPLETE {
+				gl.delete_framebuffer(framebuffer);
+				for renderbuffer in renderbuffers {
+					gl.delete_renderbuffer(renderbuffer);
+				}
+				return Err(FramebufferError::CreationError {
+					what: format!("the multisampled framebuffer is incomplete, \
+						status 0x{:08x}", status)
+				})
+			}
+
+			(framebuffer, renderbuffers)
+		};
+
+		Ok(Framebuffer {
+			context: self.context.clone(),
+			pipeline: self.pipeline_lock.clone(),
+			debug: self.debug.clone(),
+			label: Default::default(),
+			variants: FramebufferVariants::Custom {
+				inner: Rc::new(InnerFramebuffer {
+					context: self.context.clone(),
+					access: Default::default(),
+					color_attachments: Default::default(),
+					color_layers: Default::default(),
+					color_levels: Default::default(),
+					depth_stencil: None,
+					depth_stencil_layer: 0,
+					depth_stencil_level: 0,
+					multisampled: Some(MultisampledStorage {
+						renderbuffers,
+						width: descriptor.width,
+						height: descriptor.height,
+						samples: descriptor.samples
+					}),
+					framebuffer,
+					color_load_ops: std::iter::once(descriptor.color_load_op).collect(),
+					depth_load_op: descriptor.depth_load_op,
+					stencil_load_op: descriptor.stencil_load_op,
+					draw_buffers: !self.information.version.profile.is_legacy(),
+				})
+			}
+		})
+	}
+
+	/** Copies the given number of bytes from the given offset into the source
+	 * buffer over to the given offset into the destination buffer, entirely
+	 * on the device. Buffers may be copied between no matter their usage,
+	 * and data may be moved around inside of the same buffer, as long as the
+	 * ranges it is read from and written to don't overlap.
+	 *
+	 * # Errors
+	 * This function fails if the context lacks the [`buffer_copies`]
+	 * capability, if either range doesn't fit in its buffer, or if the ranges
+	 * overlap in the same buffer. WebGL also keeps buffers with index data
+	 * from being copied from or to buffers without it.
+	 *
+	 * # Panic
+	 * This function will panic if the pipeline can't be locked mutably, as is
+	 * the case while a render pass is running, or if either of the buffers
+	 * is in use.
+	 *
+	 * [`buffer_copies`]: Capabilities::buffer_copies */
+	pub fn copy_buffer_to_buffer(
+		&self,
+		src: &Buffer,
+		src_offset: u32,
+		dst: &Buffer,
+		dst_offset: u32,
+		size: u32) -> Result<(), BufferError> {
+
+		if !self.information.capabilities.buffer_copies {
+			return Err(BufferError::UnsupportedFeature {
+				what: format!("{} can't copy between buffers",
+					self.information.version)
+			})
+		}
+
+		let (src, dst) = (&*src.inner, &*dst.inner);
+		let same = std::ptr::eq(src, dst);
+		check_copy(src_offset, src.len, dst_offset, dst.len, size, same)?;
+
+		let index = |buffer: &InnerBuffer| buffer.usage.contains(BufferUsage::INDEX);
+		let web = self.information.version.profile == Profile::Web;
+		if web && index(src) != index(dst) {
+			return Err(BufferError::UnsupportedFeature {
+				what: format!("{} can't copy between buffers with index data \
+					and buffers without it, as is the case from buffer {} to \
+					buffer {}",
+					self.information.version,
+					src.name(),
+					dst.name())
+			})
+		}
+		if size == 0 { return Ok(()) }
+
+		let _pipeline = self.pipeline_lock.acquire(LockOperation::Copy);
+		let _atoms = if same {
+			(src.acquire_write_guarded(), None)
+		} else {
+			(dst.acquire_write_guarded(), Some(src.acquire_read_guarded()))
+		};
+
+		/* Both ranges were checked to fit in their buffers, which are no
+		 * larger than an i32. */
+		let check_i32 = |val: u32| i32::try_from(val).unwrap();
+
+		let gl = self.context.as_ref();
+		unsafe {
+			gl.bind_buffer(glow::COPY_READ_BUFFER, Some(src.buffer));
+			gl.bind_buffer(glow::COPY_WRITE_BUFFER, Some(dst.buffer));
+			gl.copy_buffer_sub_data(
+				glow::COPY_READ_BUFFER,
+				glow::COPY_WRITE_BUFFER,
+				check_i32(src_offset),
+				check_i32(dst_offset),
+				check_i32(size));
+			gl.bind_buffer(glow::COPY_READ_BUFFER, None);
+			gl.bind_buffer(glow::COPY_WRITE_BUFFER, None);
+			dst.check("copying to");
+		}
+
+		Ok(())
+	}
+
+	/** Copies the given region of the source framebuffer over the given region
+	 * of the destination framebuffer, scaling it with the given filter when
+	 * the two regions differ in size.
+	 *
+	 * Color is read from the first color attachment of the source, or from the
+	 * back buffer, for the default framebuffer, and written to every color
+	 * attachment of the destination. Either one may be the default
+	 * framebuffer, which makes this the way to get the results of off-screen
+	 * rendering to the screen without drawing a full screen quad. The load
+	 * operations of neither framebuffer are performed.
+	 *
+	 * # Errors
+	 * A framebuffer can't be blitted onto itself, and both regions have to be
+	 * contained in all of the color attachments of their framebuffers. OpenGL
+	 * knows the size of the default framebuffer, but we don't, so regions
+	 * outside of it are just clipped.
+	 *
+	 * # Panic
+	 * This function will panic if the pipeline can't be locked mutably, as is
+	 * the case while a render pass is running. */
+	pub fn blit_framebuffer(
+		&self,
+		src: &Framebuffer,
+		dst: &Framebuffer,
+		src_rect: Viewport,
+		dst_rect: Viewport,
+		filter: TextureFilter) -> Result<(), FramebufferError> {
+
+		if src.is_same(dst) {
+			return Err(FramebufferError::InvalidRegion {
+				what: "a framebuffer can't be blitted onto itself".into()
+			})
+		}
+		if dst.samples() > 1 {
+			return Err(FramebufferError::InvalidAttachment {
+				what: "a multisampled framebuffer can't be blitted onto".into()
+			})
+		}
+		if src.samples() > 1
+			&& (src_rect.width, src_rect.height) != (dst_rect.width, dst_rect.height) {
+
+			return Err(FramebufferError::InvalidRegion {
+				what: format!("multisampled framebuffers can only be blitted \
+					to regions of the same size, but {}x{} pixels were \
+					blitted to a region of {}x{}",
+					src_rect.width,
+					src_rect.height,
+					dst_rect.width,
+					dst_rect.height)
+			})
+		}
+		let (sx0, sy0, sx1, sy1) = src.check_blit_region(&src_rect)?;
+		let (dx0, dy0, dx1, dy1) = dst.check_blit_region(&dst_rect)?;
+
+		let _pipeline = self.pipeline_lock.acquire(LockOperation::Copy);
+		let _atoms = (src.acquire_read_guarded(), dst.acquire_write_guarded());
+
+		let gl = self.context.as_ref();
+		unsafe {
+			src.bind_blit_source(gl);
+			dst.bind_blit_destination(gl);
+
+			/* Blits skip most of the fragment pipeline, but not the scissor
+			 * test, which may have been left on by a render pass. */
+			gl.disable(glow::SCISSOR_TEST);
+			gl.blit_framebuffer(
+				sx0, sy0, sx1, sy1,
+				dx0, dy0, dx1, dy1,
+				glow::COLOR_BUFFER_BIT,
+				filter.as_opengl(false));
+
+			gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+		}
+
+		Ok(())
+	}
+
+	/** Copies a region of the given extent between two textures, starting at
+	 * the given views.
+	 *
+	 * Both textures must be in the same format, and the region covers the
+	 * same number of layers, faces or slices in both of them, as given by the
+	 * last of the [axes] of the extent. Depth-stencil textures get both their
+	 * depth and their stencil copied.
+	 *
+	 * # Implementation
+	 * Every layer in the region gets attached to a pair of temporary
+	 * framebuffers and blitted from one to the other, which works the same
+	 * way everywhere, for every format that can be attached to a framebuffer.
+	 *
+	 * # Errors
+	 * One-dimensional textures can't be copied, as they can't be attached to
+	 * a framebuffer, and neither can a texture be copied onto itself.
+	 *
+	 * # Panic
+	 * This function will panic if the pipeline can't be locked mutably, as is
+	 * the case while a render pass is running.
+	 *
+	 * [axes]: TextureExtent::axes */
+	pub fn copy_texture_to_texture(
+		&self,
+		src: TextureCopyView,
+		dst: TextureCopyView,
+		extent: TextureExtent) -> Result<(), TextureError> {
+
+		if Rc::ptr_eq(&src.texture.inner, &dst.texture.inner) {
+			return Err(TextureError::InvalidCopy {
+				what: "a texture can't be copied onto itself".into()
+			})
+		}
+		if src.texture.format() != dst.texture.format() {
+			return Err(TextureError::InvalidCopy {
+				what: format!("a texture in the {:?} format can't be copied \
+					to one in the {:?} format",
+					src.texture.format(),
+					dst.texture.format())
+			})
+		}
+		if src.texture.format().is_compressed() {
+			return Err(TextureError::InvalidCopy {
+				what: format!("textures in the {:?} compressed format can't \
+					be attached to a framebuffer to be copied",
+					src.texture.format())
+			})
+		}
+		src.validate(&extent)?;
+		dst.validate(&extent)?;
+
+		let (width, height, layers) = extent.axes();
+		if width == 0 || height == 0 { return Ok(()) }
+
+		let (attachment, mask) = match src.texture.format() {
+			TextureFormat::Depth24Stencil8 => (
+				glow::DEPTH_STENCIL_ATTACHMENT,
+				glow::DEPTH_BUFFER_BIT | glow::STENCIL_BUFFER_BIT),
+			_ => (
+				glow::COLOR_ATTACHMENT0,
+				glow::COLOR_BUFFER_BIT)
+		};
+
+		/* The region was checked to fit in both textures, whose axes were
+		 * checked to fit in an i32 when they were created. */
+		let check_i32 = |val: u32| i32::try_from(val).unwrap();
+		let (sx, sy, sz) = src.origin;
+		let (dx, dy, dz) = dst.origin;
+		let (sx, sy) = (check_i32(sx), check_i32(sy));
+		let (dx, dy) = (check_i32(dx), check_i32(dy));
+		let (width, height) = (check_i32(width), check_i32(height));
+
+		let _pipeline = self.pipeline_lock.acquire(LockOperation::Copy);
+		let _atoms = (
+			src.texture.acquire_read_guarded(),
+			dst.texture.acquire_write_guarded());
+		src.texture.inner.warn_uninitialized(
+			src.mip_level..src.mip_level + 1,
+			init::region_layers(&src.texture.inner.extent.get(), sz, layers),
+			"copied from");
+
+		let gl = self.context.as_ref();
+		unsafe {
+			let read = gl.create_framebuffer()
+				.map_err(|what| TextureError::InvalidCopy { what })?;
+			let draw = match gl.create_framebuffer() {
+				Ok(draw) => draw,
+				Err(what) => {
+					gl.delete_framebuffer(read);
+					return Err(TextureError::InvalidCopy { what })
+				}
+			};
+			gl.bind_framebuffer(glow::READ_FRAMEBUFFER, Some(read));
+			gl.bind_framebuffer(glow::DRAW_FRAMEBUFFER, Some(draw));
+			gl.disable(glow::SCISSOR_TEST);
+
+			let copy_layers = || {
+				for layer in 0..layers {
+					src.texture.attach(
+						gl,
+						glow::READ_FRAMEBUFFER,
+						attachment,
+						src.mip_level,
+						sz + layer);
+					dst.texture.attach(
+						gl,
+						glow::DRAW_FRAMEBUFFER,
+						attachment,
+						dst.mip_level,
+						dz + layer);
+
+					for target in &[glow::READ_FRAMEBUFFER, glow::DRAW_FRAMEBUFFER] {
+						let status = gl.check_framebuffer_status(*target);
+						if status != glow::FRAMEBUFFER_COMPLETE {
+							return Err(TextureError::InvalidCopy {
+								what: format!("the textures {} and {} can't \
+									be attached to a framebuffer for copying, \
+									status 0x{:08x}",
+									src.texture.inner.name(),
+									dst.texture.inner.name(),
+									status)
+							})
+						}
+					}
+
+					gl.blit_framebuffer(
+						sx, sy, sx + width, sy + height,
+						dx, dy, dx + width, dy + height,
+						mask,
+						glow::NEAREST);
+					self.debug.check(gl, "copying to", || format!(
+						"texture {} from texture {}",
+						dst.texture.inner.name(),
+						src.texture.inner.name()));
+				}
+				Ok(())
+			};
+			let result = copy_layers();
+			if result.is_ok() {
+				dst.texture.inner.mark_written(
+					dst.mip_level..dst.mip_level + 1,
+					init::region_layers(&dst.texture.inner.extent.get(), dz, layers));
+			}
+
+			gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+			gl.delete_framebuffer(read);
+			gl.delete_framebuffer(draw);
+
+			result
+		}
+	}
+
+	/** Lock the render pipeline and start a new render pass from the given
+	 * parameters. */
+	pub fn start_render_pass<'a>(
+		&'a self,
+		descriptor: &RenderPassDescriptor<'a>)
+		-> RenderPass<'a> {
+
+		RenderPass {
 			context: self.context.clone(),
 			information: self.information.clone(),
-			_lock: self.pipeline_lock.borrow_mut(),
+			usage: self.usage.clone(),
+			debug: self.debug.clone(),
+			_lock: self.pipeline_lock.acquire_labeled(
+				LockOperation::RenderPass,
+				descriptor.pipeline.shared_label()),
 			general_setup: false,
 			pipeline: descriptor.pipeline,
 			vertex: None,
+			instance: None,
 			index: None,
 			bind: None,
+			bind_offsets: Vec::new(),
+			bind_revision: 0,
 			framebuffer: descriptor.framebuffer,
 			stencil_reference: 0,
 			stencil_setup: false,
+			depth_range: (0.0, 1.0),
+			depth_range_setup: false,
 			draw_buffers_setup: false,
 			blending_setup: false,
 			color_blend_constant: Color {
@@ -479,10 +1823,37 @@ impl Device {
 				blue: 0.0,
 				alpha: 1.0
 			},
-			framebuffer_loaded: false
+			framebuffer_loaded: false,
+			scissor: None,
+			scissor_setup: false,
+			scissored_load: false,
+			query: None
 		}
 	}
 
+	/** Record a new render bundle, starting from the given parameters, with
+	 * the commands issued by the given function, which may fail with errors
+	 * of its own. Recording doesn't touch the context, so this doesn't lock
+	 * the render pipeline, and may be done from inside of a render pass.
+	 *
+	 * The bundle may then be executed in any number of render passes, with
+	 * [`RenderPass::execute_bundle()`].
+	 *
+	 * [`RenderPass::execute_bundle()`]: RenderPass::execute_bundle */
+	pub fn create_render_bundle<F, E>(
+		&self,
+		descriptor: &RenderBundleDescriptor<'_>,
+		record: F) -> Result<RenderBundle, E>
+		where F: FnOnce(&mut RenderBundleEncoder) -> Result<(), E> {
+
+		let mut encoder = RenderBundleEncoder::new(
+			self.information.clone(),
+			descriptor.pipeline);
+		record(&mut encoder)?;
+
+		Ok(encoder.finish())
+	}
+
 	/** Internal implementation of the texture creation function, supporting
 	 * creation of both user-initialized textures and default-initialized ones. */
 	fn create_texture_generic(
@@ -491,424 +1862,390 @@ impl Device {
 		data: Option<&[u8]>)
 		-> Result<Texture, TextureError> {
 
-		let _atom = self.pipeline_lock.borrow_mut();
+		let _atom = self.pipeline_lock.acquire(LockOperation::TextureCreation);
+		let id = InnerTexture::next_id();
 
-		#[cfg(feature = "mipmap-generation")]
-		let mut mip_buffer: Option<Vec<u8>> = None;
-
-		#[cfg(feature = "mipmap-generation")]
-		use std::convert::TryInto;
-
-		/* Determine the number of bytes per pixel. */
-		#[cfg(feature = "mipmap-generation")]
-		let bytes_per_pixel = match descriptor.format {
-			TextureFormat::Rgba8Unorm => 4 * 1,
-			TextureFormat::Rgba32Float => 4 * 4,
-			TextureFormat::Depth24Stencil8 => 4,
-		};
-
-		/* Party rockers in the house tonight. */
-		let (mips, data) = match descriptor.mip {
-			Mipmap::None => (1, data),
-			Mipmap::Manual { levels } =>
-				(levels.get(), data),
-			#[cfg(feature = "mipmap-generation")]
-			Mipmap::Automatic { filter } => {
-				/* Generate the mipmaps and store them in new buffer. */
-				let (axis, layers) = match descriptor.extent {
-					TextureExtent::D2 { width, height } =>
-						(u32::min(width, height), 1),
-					TextureExtent::D2Array { width, height, layers } =>
-						(u32::min(width, height), layers),
-					_ => panic!("Mipmap generation is only supported for 2D and \
-						2D array textures. For textures of type {:?} mip maps, \
-						if supported, have to be specified manually.",
-						descriptor.extent)
-				};
-
-				let mips_per_layer = f64::from(axis)
-					.log2()
-					.floor() as u32;
+		/* Only desktop OpenGL has one-dimensional textures. */
+		if let TextureExtent::D1 { .. } = descriptor.extent {
+			if self.information.version.profile != Profile::Core {
+				return Err(TextureError::UnsupportedExtent {
+					what: format!("one-dimensional textures can't be created \
+						in {}", self.information.version.profile)
+				})
+			}
+		}
 
-				let data = match data {
-					Some(data) => data,
-					None => panic!("Mipmap generation is only supported for \
-						textures which are to be initialized with data.")
-				};
-				if let TextureFormat::Depth24Stencil8 = descriptor.format {
-					panic!("Mipmap generation is only supported for color \
-						textures")
+		/* Figure out how many levels the texture is going to have, and how
+		 * many of those we'll be uploading from the initialization data. */
+		let chain = descriptor.extent.max_levels();
+		let (levels, mips) = match descriptor.mip {
+			Mipmap::None => (1, 1),
+			Mipmap::Manual { levels } => {
+				if levels.get() > chain {
+					return Err(TextureError::UnsupportedMipmap {
+						what: format!("{} mip levels were requested, but the \
+							full chain for a texture of extent {:?} only has {}",
+							levels,
+							descriptor.extent,
+							chain)
+					})
 				}
-
-				let (width, height, bytes_per_pixel, stride) = {
-					let pixels_per_page = match descriptor.extent {
-						TextureExtent::D2Array { width, height, .. } =>
-							(width, height, width * height),
-						TextureExtent::D2 { width, height } =>
-							(width, height, width * height),
-						_ => unreachable!()
-					};
-
-					(
-						pixels_per_page.0,
-						pixels_per_page.1,
-						bytes_per_pixel,
-						pixels_per_page.2 * bytes_per_pixel
-					)
-				};
-
-				/* Generate the target buffer for the mips. */
-				let mut buffer = Vec::with_capacity(
-					(width * height * bytes_per_pixel * 2) as usize);
-				match descriptor.format {
-					TextureFormat::Rgba8Unorm =>
-						/* Since the length of the data type is the same as the
-						 * backing pixel storage, we can just process it
-						 * directly. */
-						for layer in 0..layers {
-							let offset = layer * stride;
-							let image = image::ImageBuffer
-								::<image::Rgba<u8>, &[u8]>
-								::from_raw(
-									width,
-									height,
-									&data[offset as usize..(offset + stride) as usize])
-									.expect("This must have already fit by this point");
-
-							buffer.extend_from_slice(image.as_raw());
-							for mip in 1..mips_per_layer {
-								let width = u32::max(width >> mip, 1);
-								let height = u32::max(height >> mip, 1);
-								let mip = image::imageops::resize(
-									&image,
-									width,
-									height,
-									filter);
-								buffer.extend_from_slice(mip.as_raw());
-							}
-						},
-					TextureFormat::Rgba32Float => {
-						/* We have to process it all as f32's, which means we
-						 * may have to convert back and forth between bytes and
-						 * floats. */
-
-						let mut back = None;
-						let data = bytemuck
-							::try_cast_slice
-							::<u8, f32>(data)
-							.unwrap_or_else(|_| {
-								let temp = data
-									.chunks_exact(std::mem::size_of::<f32>())
-									.map(|chunk| {
-										let bytes = chunk.try_into().unwrap();
-										f32::from_ne_bytes(bytes)
-									})
-									.collect::<Vec<_>>();
-								&*back.insert(temp)
-							});
-
-						let capacity = buffer.capacity() / std::mem::size_of::<f32>();
-						let mut local_buffer = Vec::with_capacity(capacity);
-
-						for layer in 0..layers {
-							let offset = layer * stride;
-							let image = image::ImageBuffer
-								::<image::Rgba<f32>, &[f32]>
-								::from_raw(
-									width,
-									height,
-									&data[offset as usize..(offset + stride) as usize])
-									.expect("This must have already fit by this point");
-
-							local_buffer.extend_from_slice(image.as_raw());
-							for mip in 1..mips_per_layer {
-								let width = u32::max(width >> mip, 1);
-								let height = u32::max(height >> mip, 1);
-								let mip = image::imageops::resize(
-									&image,
-									width,
-									height,
-									filter);
-								local_buffer.extend_from_slice(mip.as_raw());
-							}
-						}
-
-						/* Copy from the local buffer to the target buffer. */
-						local_buffer.into_iter()
-							.map(|value| f32::to_ne_bytes(value))
-							.for_each(|bytes| {
-								let iter = std::array::IntoIter::new(bytes);
-								buffer.extend(iter)
-							})
-					},
-					_ => unreachable!()
+				(levels.get(), levels.get())
+			},
+			Mipmap::Automatic { .. } => {
+				if descriptor.format == TextureFormat::Depth24Stencil8
+					|| descriptor.format.is_compressed() {
+					return Err(TextureError::UnsupportedMipmap {
+						what: format!("mipmaps can't be generated for textures \
+							in the {:?} format", descriptor.format)
+					})
 				}
 
-				(
-					mips_per_layer,
-					Some(&mip_buffer.insert(buffer)[..])
-				)
+				/* Generation goes all the way down to a single texel on the
+				 * largest axis, which is the full chain for the texture. */
+				(chain, 1)
 			}
 		};
 
-		match descriptor.extent {
-			TextureExtent::D2 { .. } => { },
-			_ if mips > 1 => {
-				warn!("Mipmaps for textures other than 2D textures are \
-					currently not supported");
-			},
-			_ => {}
-		}
-
-		let gl = self.context.as_ref();
-		let texture = unsafe {
-			let texture = gl.create_texture()
-				.map_err(|what| TextureError::CreationError {what})?;
-
-			let (format, internal_format, kind) = match descriptor.format {
-				TextureFormat::Rgba8Unorm => (
-					glow::RGBA,
-					glow::RGBA8,
-					glow::UNSIGNED_BYTE),
-				TextureFormat::Rgba32Float => (
-					glow::RGBA,
-					glow::RGBA32F,
-					glow::FLOAT),
-				TextureFormat::Depth24Stencil8 => (
-					glow::DEPTH_STENCIL,
-					glow::DEPTH24_STENCIL8,
-					glow::UNSIGNED_INT_24_8)
+		/* Check the the requested texture size against the limits imposed
+		 * by the implementation. */
+		{
+			let (
+				max_width,
+				max_height,
+				max_depth
+			) = match descriptor.extent {
+				TextureExtent::D1 { .. } => (
+					self.information.limits.max_texture_size,
+					1,
+					1),
+				TextureExtent::D2 { .. } => (
+					self.information.limits.max_texture_size,
+					self.information.limits.max_texture_size,
+					1),
+				TextureExtent::D2Array { .. } => (
+					self.information.limits.max_texture_size,
+					self.information.limits.max_texture_size,
+					self.information.limits.max_texture_layers,
+				),
+				TextureExtent::D3 { .. } => (
+					self.information.limits.max_texture_size_3d,
+					self.information.limits.max_texture_size_3d,
+					self.information.limits.max_texture_size_3d),
+				TextureExtent::Cube { .. } => (
+					self.information.limits.max_cube_map_texture_size,
+					self.information.limits.max_cube_map_texture_size,
+					6)
 			};
 
-			/* Check the the requested texture size against the limits imposed
-			 * by the implementation. */
-			{
-				let (
-					max_width,
-					max_height,
-					max_depth
-				) = match descriptor.extent {
-					TextureExtent::D1 { .. } => (
-						self.information.limits.max_texture_size,
-						1,
-						1),
-					TextureExtent::D2 { .. } => (
-						self.information.limits.max_texture_size,
-						self.information.limits.max_texture_size,
-						1),
-					TextureExtent::D2Array { .. } => (
-						self.information.limits.max_texture_size,
-						self.information.limits.max_texture_size,
-						self.information.limits.max_texture_layers,
-					),
-					TextureExtent::D3 { .. } => (
-						self.information.limits.max_texture_size_3d,
-						self.information.limits.max_texture_size_3d,
-						self.information.limits.max_texture_size_3d)
-				};
+			let (width, height, depth) = descriptor.extent.axes();
 
-				let (width, height, depth) = match descriptor.extent {
-					TextureExtent::D1 { length } => (length, 1, 1),
-					TextureExtent::D2 { width, height } => (width, height, 1),
-					TextureExtent::D2Array { width, height, layers } =>
-						(width, height, layers),
-					TextureExtent::D3 { width, height, depth } =>
-						(width, height, depth)
-				};
-
-				if width > max_width {
-					panic!("tried to created texture with width ({}) greater \
-						than the maximum width allowed by the implementation \
-						({})",
+			if width > max_width {
+				return Err(TextureError::LimitExceeded {
+					what: format!("tried to create a texture with width ({}) \
+						greater than the maximum width allowed by the \
+						implementation ({})",
 						width,
 						max_width)
-				}
-				if height > max_height {
-					panic!("tried to created texture with height ({}) greater \
-						than the maximum height allowed by the implementation \
-						({})",
+				})
+			}
+			if height > max_height {
+				return Err(TextureError::LimitExceeded {
+					what: format!("tried to create a texture with height ({}) \
+						greater than the maximum height allowed by the \
+						implementation ({})",
 						height,
 						max_height)
-				}
-				if depth > max_depth {
-					panic!("tried to created texture with depth ({}) greater \
-						than the maximum depth allowed by the implementation \
-						({})",
+				})
+			}
+			if depth > max_depth {
+				return Err(TextureError::LimitExceeded {
+					what: format!("tried to create a texture with depth ({}) \
+						greater than the maximum depth allowed by the \
+						implementation ({})",
 						depth,
 						max_depth)
-				}
+				})
 			}
+		}
 
-			/* Check the size of the initialization data, if there is any. */
-			if let Some(data) = data {
-				let (columns, rows, pages) = match descriptor.extent {
-					TextureExtent::D1 { length } => (length, 1, 1),
-					TextureExtent::D2 { width, height } => (width, height, 1),
-					TextureExtent::D2Array { width, height, layers } =>
-						(width, height, layers),
-					TextureExtent::D3 { width, height, depth } =>
-						(width, height, depth)
-				};
-
-				let bytes_per_pixel = match descriptor.format {
-					TextureFormat::Rgba32Float => 4 * 4,
-					TextureFormat::Rgba8Unorm  => 4 * 1,
-					TextureFormat::Depth24Stencil8 => 1 * 4
-				};
-
-				let bytes_per_page: u32 = (0..mips).into_iter()
-					.map(|mip| {
-						let width = u32::max(columns >> mip, 1);
-						let height = u32::max(rows >> mip, 1);
-
-						width * height * bytes_per_pixel
-					})
-					.sum();
-				let len = bytes_per_page * pages;
-
-				if data.len() < usize::try_from(len).unwrap() {
-					panic!("length of the intialization buffer ({}) is less \
-						than the minimum required length for the texture that \
-						would be created ({})",
-						data.len(),
-						len);
-				}
+		let internal_format = descriptor.format
+			.internal_format(self.information.version.profile)
+			.ok_or_else(|| TextureError::UnsupportedFormat {
+				what: format!("textures in the {:?} format can't be created \
+					in {}",
+					descriptor.format,
+					self.information.version)
+			})?;
+
+		/* Compressed textures can only be uploaded whole, as they get created,
+		 * and only in the formats the context has the capabilities for. */
+		let compressed = descriptor.format.is_compressed();
+		if compressed {
+			if !self.information.supports_texture_format(descriptor.format) {
+				return Err(TextureError::UnsupportedFormat {
+					what: format!("textures in the {:?} compressed format \
+						are not supported by {}",
+						descriptor.format,
+						self.information.version)
+				})
 			}
+			if data.is_none() {
+				return Err(TextureError::UnsupportedFormat {
+					what: format!("textures in the {:?} compressed format \
+						must be created with data", descriptor.format)
+				})
+			}
+			if !matches!(descriptor.extent, TextureExtent::D2 { .. }) {
+				return Err(TextureError::UnsupportedFormat {
+					what: format!("textures in the {:?} compressed format \
+						must be two-dimensional, not {:?}",
+						descriptor.format,
+						descriptor.extent)
+				})
+			}
+		}
 
-			/* Check whether a value is valid for the OpenGL FFI. */
-			let check_i32 = |val: u32|
-				i32::try_from(val).map_err(|what| TextureError::InvalidBounds {
-					what: format!("the bounds must have fit in an i32: {:?}", what)
-				});
-
-			match descriptor.extent {
-				TextureExtent::D1 { length } => {
-					let length = check_i32(length)?;
-
-					gl.bind_texture(glow::TEXTURE_1D, Some(texture));
-					gl.tex_image_1d(
-						glow::TEXTURE_1D,
-						0,
-						i32::try_from(internal_format).unwrap(),
-						length,
-						0,
-						format,
-						kind,
-						data);
+		/* Swizzles the context can't apply while sampling get applied to the
+		 * data as it gets uploaded. */
+		let swizzle = descriptor.swizzle
+			.filter(|swizzle| !swizzle.is_identity());
+		if swizzle.is_some() {
+			if let TextureFormat::Depth24Stencil8 = descriptor.format {
+				return Err(TextureError::UnsupportedFormat {
+					what: format!("textures in the {:?} format can't be \
+						swizzled", descriptor.format)
+				})
+			}
+		}
+		let swizzle_on_upload = swizzle.is_some()
+			&& !self.information.capabilities.texture_swizzle;
+		if swizzle_on_upload && compressed {
+			return Err(TextureError::UnsupportedFormat {
+				what: format!("textures in the {:?} compressed format can't \
+					be swizzled in {}, which can't swizzle them while \
+					sampling", descriptor.format, self.information.version)
+			})
+		}
 
-					gl.tex_parameter_i32(
-						glow::TEXTURE_2D,
-						glow::TEXTURE_MAX_LEVEL,
-						0);
-					gl.tex_parameter_i32(
-						glow::TEXTURE_2D,
-						glow::TEXTURE_BASE_LEVEL,
-						0);
-				},
-				TextureExtent::D2 { width, height } => {
-					let width = check_i32(width)?;
-					let height = check_i32(height)?;
-					let mips = check_i32(mips)?;
+		/* Check whether a value is valid for the OpenGL FFI. */
+		let check_i32 = |val: u32|
+			i32::try_from(val).map_err(|what| TextureError::InvalidBounds {
+				what: format!("the bounds must have fit in an i32: {:?}", what)
+			});
+
+		/* Every level is at most as large as the base level, so checking the
+		 * base level is enough to cover all of them. */
+		let (width, height, depth) = descriptor.extent.axes();
+		check_i32(width)?;
+		check_i32(height)?;
+		check_i32(depth)?;
+
+		/* With the `mipmap-generation` feature, the levels of the textures it
+		 * knows how to scale down get generated here, rather than by OpenGL,
+		 * and get uploaded just like manual mipmaps. */
+		#[cfg(feature = "mipmap-generation")]
+		let generated = match (descriptor.mip, data) {
+			(Mipmap::Automatic { filter }, Some(data)) => mipgen::generate(
+				descriptor.format,
+				&descriptor.extent,
+				levels,
+				data,
+				filter),
+			_ => None
+		};
+		#[cfg(not(feature = "mipmap-generation"))]
+		let generated: Option<Vec<u8>> = None;
+		let (data, mips) = match &generated {
+			Some(generated) => (Some(&generated[..]), levels),
+			None => (data, mips)
+		};
 
-					gl.bind_texture(glow::TEXTURE_2D, Some(texture));
+		/* Figure out where each of the levels we'll be uploading starts in the
+		 * initialization data, which packs them one after the other. */
+		let ranges = descriptor.format.level_ranges(&descriptor.extent, mips)?;
+		let end = ranges.last().map(|range| range.end).unwrap_or(0);
+		if let Some(data) = data {
+			if data.len() != end {
+				return Err(TextureError::InvalidDataLength {
+					expected: end as u64,
+					provided: data.len() as u64
+				})
+			}
+		}
+		let converted = match (data, swizzle) {
+			(Some(data), Some(swizzle)) if swizzle_on_upload => {
+				let mut data = data.to_vec();
+				swizzle.convert(descriptor.format, &mut data)
+					.expect("the format was already checked to be swizzlable");
+				Some(data)
+			},
+			_ => None
+		};
+		let data = converted.as_deref().or(data);
+
+		/* OpenGL can only generate mipmaps for formats that are both filterable
+		 * and color-renderable, which 32-bit floats aren't everywhere. */
+		let generate_on_device = generated.is_none()
+			&& matches!(descriptor.mip, Mipmap::Automatic { .. });
+		let capabilities = &self.information.capabilities;
+		let float_mipmaps = capabilities.float_texture_filtering
+			&& capabilities.float_color_buffers;
+		if generate_on_device
+			&& descriptor.format == TextureFormat::Rgba32Float
+			&& !float_mipmaps {
+			return Err(TextureError::UnsupportedMipmap {
+				what: format!("mipmaps can't be generated by OpenGL for textures \
+					in the {:?} format in {}, which can't both filter and render \
+					to them. they can only be generated on the host, with the \
+					mipmap-generation feature, for two-dimensional textures \
+					created with data",
+					descriptor.format,
+					self.information.version)
+			})
+		}
 
-					let mut offset = 0i32;
-					for i in 0..mips {
-						let width = i32::max(width >> i, 1);
-						let height = i32::max(height >> i, 1);
-						let length = width * height * 4;
+		let gl = self.context.as_ref();
+		let texture = unsafe {
+			let texture = gl.create_texture()
+				.map_err(|what| TextureError::CreationError {what})?;
 
-						let next_offset = offset.saturating_add(length);
+			let (format, _, kind) = descriptor.format.as_opengl();
+			let internal_format = i32::try_from(internal_format).unwrap();
+
+			let target = descriptor.extent.target();
+			gl.bind_texture(target, Some(texture));
+
+			for (mip, range) in ranges.into_iter().enumerate() {
+				let level = i32::try_from(mip).unwrap();
+				let data = data.map(|data| &data[range]);
+
+				match descriptor.extent.mip(level as u32) {
+					TextureExtent::D2 { width, height } if compressed => {
+						/* Compressed textures were checked to have data. */
+						let data = data.unwrap();
+						gl.compressed_tex_image_2d(
+							target,
+							level,
+							internal_format,
+							check_i32(width)?,
+							check_i32(height)?,
+							0,
+							i32::try_from(data.len()).map_err(|_|
+								TextureError::TooLarge {
+									computed_bytes: data.len() as u64
+								})?,
+							data)
+					},
+					TextureExtent::D1 { length } =>
+						gl.tex_image_1d(
+							target,
+							level,
+							internal_format,
+							check_i32(length)?,
+							0,
+							format,
+							kind,
+							data),
+					TextureExtent::D2 { width, height } =>
 						gl.tex_image_2d(
-							glow::TEXTURE_2D,
-							i,
-							i32::try_from(internal_format).unwrap(),
-							width,
-							height,
+							target,
+							level,
+							internal_format,
+							check_i32(width)?,
+							check_i32(height)?,
 							0,
 							format,
 							kind,
-							data.map(|data| &data[offset as usize..next_offset as usize]));
-
-						offset = next_offset;
-					}
-
-					gl.tex_parameter_i32(
-						glow::TEXTURE_2D,
-						glow::TEXTURE_MAX_LEVEL,
-						(mips - 1).max(0));
-					gl.tex_parameter_i32(
-						glow::TEXTURE_2D,
-						glow::TEXTURE_BASE_LEVEL,
-						0);
-				},
-				TextureExtent::D2Array { width, height, layers } => {
-					let width = check_i32(width)?;
-					let height = check_i32(height)?;
-					let layers = check_i32(layers)?;
-
-					gl.bind_texture(glow::TEXTURE_2D_ARRAY, Some(texture));
-					gl.tex_image_3d(
-						glow::TEXTURE_2D_ARRAY,
-						0,
-						i32::try_from(internal_format).unwrap(),
-						width,
-						height,
-						layers,
-						0,
-						format,
-						kind,
-						data);
-
-					gl.tex_parameter_i32(
-						glow::TEXTURE_2D,
-						glow::TEXTURE_MAX_LEVEL,
-						0);
-					gl.tex_parameter_i32(
-						glow::TEXTURE_2D,
-						glow::TEXTURE_BASE_LEVEL,
-						0);
-				},
-				TextureExtent::D3 { width, height, depth } => {
-					let width = check_i32(width)?;
-					let height = check_i32(height)?;
-					let depth = check_i32(depth)?;
-
-					gl.bind_texture(glow::TEXTURE_3D, Some(texture));
-					gl.tex_image_3d(
-						glow::TEXTURE_3D,
-						0,
-						i32::try_from(internal_format).unwrap(),
-						width,
-						height,
-						depth,
-						0,
-						format,
-						kind,
-						data);
-
+							data),
+					TextureExtent::D2Array { width, height, layers: depth }
+						| TextureExtent::D3 { width, height, depth } =>
+						gl.tex_image_3d(
+							target,
+							level,
+							internal_format,
+							check_i32(width)?,
+							check_i32(height)?,
+							check_i32(depth)?,
+							0,
+							format,
+							kind,
+							data),
+					TextureExtent::Cube { size } => {
+						/* Every face takes up the same share of the level. */
+						let faces = (0..6).map(|face| data.map(|data| {
+							let len = data.len() / 6;
+							&data[face * len..(face + 1) * len]
+						}));
+						for (face, data) in (0..).zip(faces) {
+							gl.tex_image_2d(
+								TextureExtent::cube_face_target(face),
+								level,
+								internal_format,
+								check_i32(size)?,
+								check_i32(size)?,
+								0,
+								format,
+								kind,
+								data)
+						}
+					},
+				}
+			}
 
-					gl.tex_parameter_i32(
-						glow::TEXTURE_2D,
-						glow::TEXTURE_MAX_LEVEL,
-						0);
-					gl.tex_parameter_i32(
-						glow::TEXTURE_2D,
-						glow::TEXTURE_BASE_LEVEL,
-						0);
+			/* Tell OpenGL which levels it should expect to be available, and
+			 * generate them from the base level if we were asked to. */
+			gl.tex_parameter_i32(
+				target,
+				glow::TEXTURE_MAX_LEVEL,
+				check_i32(levels - 1)?);
+			gl.tex_parameter_i32(
+				target,
+				glow::TEXTURE_BASE_LEVEL,
+				0);
+			if let (Some(swizzle), false) = (swizzle, swizzle_on_upload) {
+				for (parameter, value) in &swizzle.as_opengl() {
+					gl.tex_parameter_i32(target, *parameter, *value);
 				}
 			}
 
+			if generate_on_device {
+				gl.generate_mipmap(target);
+			}
+			gl.bind_texture(target, None);
+			self.debug.check(gl, "allocating", || format!("texture #{}", id));
+
 			texture
 		};
 
+		/* Only the levels that got uploaded, or generated from the base level,
+		 * have anything in them. */
+		let mut init = InitTracker::new(levels, init::tracked_layers(&descriptor.extent));
+		if data.is_some() {
+			let written = match descriptor.mip {
+				Mipmap::Automatic { .. } => levels,
+				_ => mips
+			};
+			init.mark(0..written, 0..u32::MAX);
+		}
+
 		Ok(Texture {
 			inner: Rc::new(InnerTexture {
 				context: self.context.clone(),
+				pipeline: self.pipeline_lock.clone(),
+				debug: self.debug.clone(),
 				texture,
 				access: Default::default(),
 				format: descriptor.format,
-				extent: descriptor.extent
+				internal_format,
+				extent: Cell::new(descriptor.extent),
+				levels,
+				ownership: RawOwnership::Owned,
+				id,
+				label: Default::default(),
+				init: RefCell::new(init),
+				swizzle: swizzle.unwrap_or(TextureSwizzle::IDENTITY),
+				swizzle_on_upload,
 			})
 		})
 	}
@@ -937,16 +2274,351 @@ impl Device {
 			None)
 	}
 
-	/** Tries to create a new render pipeline from the given description. */
+	/** Tries to create a new, zero-initialized texel buffer from the given
+	 * description, holding texels in the given format.
+	 *
+	 * The size of the buffer must be a multiple of the size of a texel in that
+	 * format. See the documentation of [`TexelBuffer`] for how these buffers
+	 * are stored and how shaders read from them.
+	 *
+	 * [`TexelBuffer`]: TexelBuffer */
+	pub fn create_texel_buffer(
+		&self,
+		descriptor: &BufferDescriptor,
+		format: TexelFormat)
+		-> Result<TexelBuffer, BufferError> {
+
+		let texel = format.bytes_per_texel();
+		if descriptor.size % texel != 0 {
+			return Err(BufferError::Misaligned {
+				offset: 0,
+				len: u64::from(descriptor.size),
+				alignment: texel
+			})
+		}
+		let texels = descriptor.size / texel;
+
+		let (texture, storage) = match (&self.tex_buffer, self.information.limits.max_texture_buffer_size) {
+			(Some(function), Some(max_texels)) if self.information.capabilities.texel_buffers => {
+				if texels > max_texels {
+					return Err(BufferError::LimitExceeded {
+						what: format!("{} texels don't fit in a buffer texture \
+							that holds at most {} texels",
+							texels,
+							max_texels)
+					})
+				}
+				self.create_texel_storage_buffer(descriptor, format, function)?
+			},
+			_ => self.create_texel_storage_rows(texels, format)?
+		};
+
+		Ok(TexelBuffer {
+			inner: Rc::new(InnerTexelBuffer {
+				context: self.context.clone(),
+				pipeline: self.pipeline_lock.clone(),
+				sanitizer: self.sanitizer.clone(),
+				texture,
+				access: Default::default(),
+				contents: descriptor.contents,
+				format,
+				storage,
+				texels,
+				len: descriptor.size
+			})
+		})
+	}
+
+	/** Creates a zero-initialized buffer of the size in the given descriptor,
+	 * along with a buffer texture reading texels in the given format from it.
+	 */
+	fn create_texel_storage_buffer(
+		&self,
+		descriptor: &BufferDescriptor,
+		format: TexelFormat,
+		function: &TexBufferFunction)
+		-> Result<(<Context as HasContext>::Texture, TexelStorage), BufferError> {
+
+		let init = vec![0u8; descriptor.size as usize];
+		let gl = self.context.as_ref();
+		unsafe {
+			let buffer = gl.create_buffer()
+				.map_err(|what| BufferError::CreationFailed { what })?;
+			gl.bind_buffer(glow::TEXTURE_BUFFER, Some(buffer));
+			gl.buffer_data_u8_slice(
+				glow::TEXTURE_BUFFER,
+				&init[..],
+				descriptor.profile.as_opengl());
+			gl.bind_buffer(glow::TEXTURE_BUFFER, None);
+
+			let texture = match gl.create_texture() {
+				Ok(texture) => texture,
+				Err(what) => {
+					gl.delete_buffer(buffer);
+					return Err(BufferError::CreationFailed { what })
+				}
+			};
+
+			/* Buffer textures have no filtering state, nor mipmaps, so there
+			 * is nothing else to set up. */
+			let (_, internal_format, _) = format.as_opengl();
+			gl.bind_texture(glow::TEXTURE_BUFFER, Some(texture));
+			function.attach(internal_format, buffer);
+			gl.bind_texture(glow::TEXTURE_BUFFER, None);
+			self.debug.check(gl, "allocating", || format!("texel buffer #{:?}", buffer));
+
+			Ok((texture, TexelStorage::Buffer(buffer)))
+		}
+	}
+
+	/** Creates a zero-initialized two-dimensional texture of texels in the
+	 * given format, with enough rows for the given number of texels. */
+	fn create_texel_storage_rows(
+		&self,
+		texels: u32,
+		format: TexelFormat)
+		-> Result<(<Context as HasContext>::Texture, TexelStorage), BufferError> {
+
+		let max_size = self.information.limits.max_texture_size;
+		let layout = TexelLayout::new(texels, max_size)
+			.ok_or_else(|| BufferError::LimitExceeded {
+				what: format!("{} texels don't fit in a texture that is at \
+					most {}x{} texels big",
+					texels,
+					max_size,
+					max_size)
+			})?;
+		let width = i32::try_from(layout.width).map_err(|_| BufferError::LimitExceeded {
+			what: format!("the texel buffer is {} texels wide", layout.width)
+		})?;
+		let height = i32::try_from(layout.height).map_err(|_| BufferError::LimitExceeded {
+			what: format!("the texel buffer is {} texels tall", layout.height)
+		})?;
+
+		/* Texels past the end of the buffer are part of the texture too, so
+		 * the initialization data has to cover all of the rows. */
+		let init = u64::from(layout.width)
+			.checked_mul(u64::from(layout.height))
+			.and_then(|len| len.checked_mul(u64::from(format.bytes_per_texel())))
+			.unwrap_or(u64::MAX);
+		let init = usize::try_from(init)
+			.map(|len| vec![0u8; len])
+			.map_err(|_| BufferError::TooLarge { computed_bytes: init })?;
+
+		let gl = self.context.as_ref();
+		let texture = unsafe {
+			let texture = gl.create_texture()
+				.map_err(|what| BufferError::CreationFailed { what })?;
+
+			let (format, internal_format, kind) = format.as_opengl();
+			gl.bind_texture(glow::TEXTURE_2D, Some(texture));
+			gl.tex_image_2d(
+				glow::TEXTURE_2D,
+				0,
+				i32::try_from(internal_format).unwrap(),
+				width,
+				height,
+				0,
+				format,
+				kind,
+				Some(&init[..]));
+
+			/* Texel fetches ignore filtering, but the texture still has to be
+			 * complete for them to return anything, and integer textures are
+			 * only complete with nearest filtering and no mipmaps. */
+			gl.tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MAX_LEVEL, 0);
+			gl.tex_parameter_i32(
+				glow::TEXTURE_2D,
+				glow::TEXTURE_MIN_FILTER,
+				glow::NEAREST as i32);
+			gl.tex_parameter_i32(
+				glow::TEXTURE_2D,
+				glow::TEXTURE_MAG_FILTER,
+				glow::NEAREST as i32);
+			gl.bind_texture(glow::TEXTURE_2D, None);
+
+			texture
+		};
+
+		Ok((texture, TexelStorage::Rows(layout)))
+	}
+
+	/** Tries to create a new render pipeline from the given description.
+	 *
+	 * With [pipeline caching] turned on, this hands out another handle to a
+	 * cached pipeline created from an identical description, if there is one,
+	 * rather than linking a new program.
+	 *
+	 * [pipeline caching]: Self::set_render_pipeline_caching */
 	pub fn create_render_pipeline(
 		&self,
 		descriptor: &RenderPipelineDescriptor)
 		-> Result<RenderPipeline, RenderPipelineError> {
 
-		let _atom = self.pipeline_lock.borrow_mut();
+		self.create_pipeline(descriptor, &[])
+	}
+
+	/** Creates a new render pipeline from the given description, whose program
+	 * captures the given outputs of the vertex stage with transform feedback,
+	 * if there are any. Pipelines capturing outputs are never cached, since
+	 * the keys of the cache don't account for them. */
+	pub(crate) fn create_pipeline(
+		&self,
+		descriptor: &RenderPipelineDescriptor,
+		feedback: &[&str])
+		-> Result<RenderPipeline, RenderPipelineError> {
+
+		let key = if self.pipelines.enabled() && feedback.is_empty() {
+			let key = PipelineKey::of(descriptor);
+			if let Some(inner) = self.pipelines.get(&key) {
+				return Ok(RenderPipeline { inner })
+			}
+			Some(key)
+		} else {
+			None
+		};
+
+		let _atom = self.pipeline_lock.acquire(LockOperation::PipelineCreation);
+		let limits = &self.information.limits;
+
+		/* WebGL2 refuses to link programs with aliased attributes, even when
+		 * other implementations would happily go along with them. */
+		let attributes = descriptor.vertex.buffer.attributes.iter()
+			.chain(descriptor.vertex.instance.iter()
+				.flat_map(|layout| layout.attributes.iter()))
+			.collect::<Vec<_>>();
+		for (i, attribute) in attributes.iter().enumerate() {
+			let aliased = attributes[..i].iter()
+				.any(|other| other.binding == attribute.binding);
+			if aliased {
+				return Err(RenderPipelineError::AttributeAliasing {
+					binding: attribute.binding.to_string()
+				})
+			}
+		}
+		pipeline::check_vertex_layout(&descriptor.vertex.buffer)?;
+		if let Some(instance) = &descriptor.vertex.instance {
+			pipeline::check_vertex_layout(instance)?;
+		}
+
+		let count = u32::try_from(attributes.len()).unwrap_or(u32::MAX);
+		if count > limits.max_vertex_attribs {
+			return Err(RenderPipelineError::TooManyAttributes {
+				count,
+				max: limits.max_vertex_attribs
+			})
+		}
+
+		/* Legacy contexts may be missing features pipelines would otherwise
+		 * take for granted. */
+		let capabilities = &self.information.capabilities;
+		let instanced = descriptor.vertex.instance
+			.map(|layout| !layout.attributes.is_empty())
+			.unwrap_or(false);
+		if instanced && !capabilities.instancing {
+			return Err(RenderPipelineError::UnsupportedFeature {
+				what: format!("per-instance vertex attributes need instancing, \
+					which {} has no support for",
+					self.information.version)
+			})
+		}
+		pipeline::check_integer_attributes(&attributes, &self.information.version)?;
+		if let Some(fragment) = &descriptor.fragment {
+			let max = limits.max_framebuffer_color_attachments;
+			if fragment.targets.len() > max as usize {
+				return Err(RenderPipelineError::TooManyColorTargets {
+					count: fragment.targets.len(),
+					max
+				})
+			}
+			if pipeline::shared_target_state(fragment.targets).is_none()
+				&& !capabilities.indexed_draw_buffers {
+
+				return Err(RenderPipelineError::UnsupportedFeature {
+					what: format!("the color targets have different states, \
+						but {} has no support for setting them up for each \
+						draw buffer",
+						self.information.version)
+				})
+			}
+		}
+		if descriptor.primitive_state.index_format == IndexFormat::Uint32
+			&& !capabilities.index_uint32 {
+
+			return Err(RenderPipelineError::UnsupportedFeature {
+				what: format!("{} has no support for 32-bit indices, use \
+					{:?} instead",
+					self.information.version,
+					IndexFormat::Uint16)
+			})
+		}
+
+		/* Estimate the size of the interface between the stages before handing
+		 * it off to the implementation, so that we can both catch it on all of
+		 * them and say exactly what went into it. Only the outputs consumed by
+		 * the fragment stage are counted, as unused ones get optimized out. */
+		let varyings = {
+			let outputs = &descriptor.vertex.shader.inner.interface.outputs;
+			match &descriptor.fragment {
+				Some(fragment) => {
+					let inputs = &fragment.shader.inner.interface.inputs;
+					outputs.iter()
+						.filter(|output| inputs.iter()
+							.any(|input| input.name == output.name))
+						.cloned()
+						.collect::<Vec<_>>()
+				},
+				None => outputs.clone()
+			}
+		};
+		let max_varyings = u32::min(
+			limits.max_varying_vectors,
+			limits.max_varying_components / 4);
+		if !interface::fits(&varyings, max_varyings) {
+			return Err(RenderPipelineError::VaryingLimitExceeded {
+				what: format!("the vertex stage passes on at least {} vectors, \
+					more than the limit of {}:{}",
+					interface::packed_rows(&varyings),
+					max_varyings,
+					interface::itemize(&varyings))
+			})
+		}
+
+		/* Attributes get bound to the locations the device has for their names,
+		 * so that pipelines with the same vertex layout share them. */
+		let requests = attributes.iter()
+			.map(|attribute| locations::Request {
+				name: attribute.binding.as_ref(),
+				explicit: attribute.location,
+				span: descriptor.vertex.shader.inner.interface.inputs.iter()
+					.find(|input| input.name == attribute.binding)
+					.map(|input| input.rows().max(1))
+					.unwrap_or(1)
+			})
+			.collect::<Vec<_>>();
+		let locations = locations::resolve(
+			&mut self.attribute_locations.borrow_mut(),
+			&requests,
+			limits.max_vertex_attribs)?;
+
+		/* Lines wider than a single pixel are optional, so rather than having
+		 * draws fail, widths get clamped to what the implementation supports. */
+		let mut primitive_state = descriptor.primitive_state;
+		primitive_state.line_width = pipeline::clamp_line_width(
+			primitive_state.line_width,
+			limits.aliased_line_width_range);
+		if primitive_state.line_width != descriptor.primitive_state.line_width {
+			warn!(target: "gavle::pipeline",
+				"Clamped the line width of {} to {}, as the implementation \
+				only supports widths in the [{}; {}] range",
+				descriptor.primitive_state.line_width,
+				primitive_state.line_width,
+				limits.aliased_line_width_range[0],
+				limits.aliased_line_width_range[1]);
+		}
 
 		let gl = self.context.as_ref();
-		let (program, vertex_shader, fragment_shader, color_target_state) = unsafe {
+		let (program, vertex_shader, fragment_shader, color_targets) = unsafe {
 			let program = gl.create_program()
 				.map_err(|what|
 					RenderPipelineError::ProgramCreationFailed { what })?;
@@ -954,7 +2626,7 @@ impl Device {
 			let vertex_shader = descriptor.vertex.shader.clone();
 			gl.attach_shader(program, vertex_shader.as_raw_handle());
 
-			let (fragment_shader, color_target_state) = match &descriptor.fragment {
+			let (fragment_shader, color_targets) = match &descriptor.fragment {
 				Some(fragment_state) => {
 					let fragment_shader = fragment_state.shader.clone();
 					gl.attach_shader(program, fragment_shader.as_raw_handle());
@@ -962,18 +2634,28 @@ impl Device {
 
 					(
 						Some(fragment_shader),
-						fragment_state.targets
+						fragment_state.targets.iter().copied().collect()
 					)
 				},
 				None => (
 					None,
-					ColorTargetState {
+					std::iter::once(ColorTargetState {
 						alpha_blend: BlendState::REPLACE,
 						color_blend: BlendState::REPLACE,
 						write_mask: ColorWrite::all()
-					})
+					}).collect())
 			};
 
+			for (attribute, location) in attributes.iter().zip(&locations) {
+				gl.bind_attrib_location(program, *location, &attribute.binding);
+			}
+			if !feedback.is_empty() {
+				gl.transform_feedback_varyings(
+					program,
+					feedback,
+					glow::INTERLEAVED_ATTRIBS);
+			}
+
 			gl.link_program(program);
 			if !gl.get_program_link_status(program) {
 				let what = gl.get_program_info_log(program);
@@ -981,30 +2663,114 @@ impl Device {
 			} else if log_enabled!(log::Level::Debug) {
 				let what = gl.get_program_info_log(program);
 				if !what.is_empty() {
-					debug!("Program linkage log: {}", what);
+					debug!(target: "gavle::pipeline",
+						"Program linkage log: {}", what);
 				}
 			}
 
-			(program, vertex_shader, fragment_shader, color_target_state)
-		};
+			/* Uniform blocks get pinned to their binding points once, here,
+			 * rather than every time a group gets bound to them. */
+			let mut program = RenderProgram::new(gl, program);
+			if self.information.capabilities.uniform_buffers {
+				let vertex = &descriptor.vertex.shader.inner.interface;
+				let fragment = descriptor.fragment.as_ref()
+					.map(|fragment| &fragment.shader.inner.interface);
+
+				program.pin_blocks(
+					gl,
+					vertex.uniform_blocks.iter()
+						.chain(fragment.into_iter()
+							.flat_map(|fragment| fragment.uniform_blocks.iter())),
+					&mut self.blocks.borrow_mut());
+			}
 
-		Ok(RenderPipeline {
+			/* Programs made from SPIR-V modules can't be queried for their
+			 * attributes by name, so the ones with explicit locations are
+			 * taken to be at those locations. */
+			if descriptor.vertex.shader.inner.spirv {
+				program.assume_locations(attributes.iter()
+					.filter_map(|attribute| attribute.location
+						.map(|location| (attribute.binding.as_ref(), location))));
+			}
+
+			/* Locations declared in the shader take precedence over the ones
+			 * bound here, which works all the same, as vertex arrays get set up
+			 * with the locations the program ended up with. */
+			for (attribute, location) in attributes.iter().zip(&locations) {
+				match program.locations.get(attribute.binding.as_ref()) {
+					Some(linked) if linked != location =>
+						debug!(target: "gavle::pipeline",
+							"Attribute \"{}\" is at location {}, as declared \
+							in the shader, rather than at location {}",
+							attribute.binding,
+							linked,
+							location),
+					_ => {}
+				}
+			}
+
+			/* Matrix attributes take up one location per column, all of which
+			 * have to fit under the limit. */
+			for (binding, active) in &program.attributes {
+				let location = match program.locations.get(binding) {
+					Some(location) => *location,
+					None => continue
+				};
+				let last = location + active.locations() - 1;
+
+				if last >= limits.max_vertex_attribs {
+					gl.delete_program(program.program);
+					return Err(RenderPipelineError::AttributeLocationOutOfRange {
+						binding: binding.clone(),
+						location: last,
+						max: limits.max_vertex_attribs
+					})
+				}
+			}
+
+			(program, vertex_shader, fragment_shader, color_targets)
+		};
+		let registered = self.interfaces.record_pipeline(
+			crate::debug::name(None, program.program),
+			&program.reflection);
+
+		let vertex_layout = pipeline::OwnedVertexBufferLayout::from(descriptor.vertex.buffer);
+		let instance_layout = descriptor.vertex.instance
+			.map(pipeline::OwnedVertexBufferLayout::from);
+		let attribute_pointers = pipeline::attribute_pointers(
+			&program.locations,
+			&vertex_layout,
+			instance_layout.as_ref());
+
+		let pipeline = RenderPipeline {
 			inner: Rc::new(InnerRenderPipeline {
 				context: self.context.clone(),
+				debug: self.debug.clone(),
+				label: Default::default(),
 				access: Default::default(),
-				program: unsafe { RenderProgram::new(gl, program) },
-				vao: Default::default(),
-				vertex_layout: From::from(descriptor.vertex.buffer),
+				program,
+				vertex_arrays: self.vertex_arrays.clone(),
+				attribute_pointers: Rc::from(attribute_pointers),
+				vertex_layout,
+				instance_layout,
 				vertex_shader: VertexShader { inner: vertex_shader.inner.clone() },
 				fragment_shader: fragment_shader.map(|fragment_shader|
 					FragmentShader {
 						inner: fragment_shader.inner.clone()
 					}),
-				primitive_state: descriptor.primitive_state,
+				primitive_state,
+				point_size_switch: self.information.version.profile == Profile::Core,
 				depth_stencil: descriptor.depth_stencil,
-				color_target_state
+				color_targets,
+				registration: registered.map(|index|
+					(self.interfaces.clone(), index))
 			})
-		})
+		};
+		if let Some(key) = key {
+			self.pipelines.insert(key, &pipeline.inner);
+		}
+
+		Ok(pipeline)
 	}
 
 	instance_shader_creation_functions! {
@@ -1014,36 +2780,272 @@ impl Device {
 		pub fn create_fragment_shader: FragmentShader;
 	}
 
-	instance_initialized_buffer_creation_functions! {
-		#[doc = "Tries to create a new vertex buffer with the given data."]
-		pub fn create_vertex_buffer_with_data: VertexBuffer;
-		#[doc = "Tries to create a new index buffer with the given data."]
-		pub fn create_index_buffer_with_data: IndexBuffer;
-		#[doc = "Tries to create a new uniform buffer with the given data."]
-		pub fn create_uniform_buffer_with_data: UniformBuffer;
+	/** Tries to create a new buffer with the given data, which must be as long
+	 * as the size in the descriptor.
+	 *
+	 * # Errors
+	 * This function fails if the descriptor has no usage, or if WebGL is asked
+	 * for a buffer that holds index data along with data of any other kind.
+	 *
+	 * # Panic
+	 * This function will panic if the length of the data differs from the size
+	 * in the descriptor. */
+	pub fn create_buffer_with_data<A: AsRef<[u8]>>(
+		&self,
+		descriptor: &BufferDescriptor,
+		data_: A)
+		-> Result<Buffer, BufferError> {
+
+		check_usage(descriptor.usage, self.information.version.profile)?;
+		let	data = data_.as_ref();
+
+		let len = u32::try_from(data.len());
+		let len = match len {
+			Ok(len) if len != descriptor.size =>
+				panic!("the desired length of the buffer ({}) and the size of \
+					the initialization buffer ({}) must have been the same",
+					descriptor.size, len),
+			Ok(len) => len,
+			Err(what) =>
+				panic!("the length of the initialization buffer does not fit \
+					in a u32 value, as is required by opengl: {}", what),
+		};
+		self.sanitizer.check(
+			&format_args!("(new buffer)"),
+			descriptor.contents,
+			0,
+			data);
+
+		let target = descriptor.usage.target();
+		let gl = self.context.as_ref();
+		let buffer = unsafe {
+			let buffer = gl.create_buffer()
+				.map_err(|what| BufferError::CreationFailed { what })?;
+
+			gl.bind_buffer(target, Some(buffer));
+			gl.buffer_data_u8_slice(
+				target,
+				data,
+				descriptor.profile.as_opengl());
+			gl.bind_buffer(target, None);
+			self.debug.check(gl, "allocating", || format!("buffer #{:?}", buffer));
+
+			buffer
+		};
+
+		Ok(Buffer {
+			inner: Rc::new(InnerBuffer {
+				context: self.context.clone(),
+				information: self.information.clone(),
+				pipeline: self.pipeline_lock.clone(),
+				sanitizer: self.sanitizer.clone(),
+				debug: self.debug.clone(),
+				buffer,
+				label: Default::default(),
+				access: Default::default(),
+				map: Default::default(),
+				contents: descriptor.contents,
+				usage: descriptor.usage,
+				profile: descriptor.profile,
+				len
+			})
+		})
 	}
 
-	instance_zero_initialized_buffer_creation_functions! {
-		#[doc = "Tries to create a new zero-initialized vertex buffer."]
-		#[doc = "# Performance"]
-		#[doc = "Creating zero-initialized buffers may involve an extra, "]
-		#[doc = "zero-initialized allocation in host memory, as big as the "]
-		#[doc = "target buffer on the device. Users should only sparringly "]
-		#[doc = "rely on this function."]
-		pub fn create_vertex_buffer: create_vertex_buffer_with_data -> VertexBuffer;
-		#[doc = "Tries to create a new zero-initialized vertex buffer."]
-		#[doc = "# Performance"]
-		#[doc = "Creating zero-initialized buffers may involve an extra, "]
-		#[doc = "zero-initialized allocation in host memory, as big as the "]
-		#[doc = "target buffer on the device. Users should only sparringly "]
-		#[doc = "rely on this function."]
-		pub fn create_index_buffer: create_index_buffer_with_data -> IndexBuffer;
-		#[doc = "Tries to create a new zero-initialized vertex buffer."]
-		#[doc = "# Performance"]
-		#[doc = "Creating zero-initialized buffers may involve an extra, "]
-		#[doc = "zero-initialized allocation in host memory, as big as the "]
-		#[doc = "target buffer on the device. Users should only sparringly "]
-		#[doc = "rely on this function."]
-		pub fn create_uniform_buffer: create_uniform_buffer_with_data -> UniformBuffer;
+	/** Tries to create a new buffer, filled in by the given function through
+	 * a writer over its storage, which must be written to completely.
+	 *
+	 * # Errors
+	 * This function fails for the same reasons [`create_buffer_with_data()`]
+	 * does, and if the buffer is too large for OpenGL.
+	 *
+	 * # Performance
+	 * The data goes straight into the buffer when it can be mapped. When it
+	 * can't, it goes through a staging buffer owned by the device, which is
+	 * only ever allocated again to grow.
+	 *
+	 * [`create_buffer_with_data()`]: Self::create_buffer_with_data */
+	pub fn create_buffer_with<F>(
+		&self,
+		descriptor: &BufferDescriptor,
+		f: F) -> Result<Buffer, BufferError>
+		where F: FnOnce(&mut BufferWriter) {
+
+		check_usage(descriptor.usage, self.information.version.profile)?;
+
+		let target = descriptor.usage.target();
+		let len = usize::try_from(descriptor.size).unwrap();
+		let size = i32::try_from(descriptor.size)
+			.map_err(|_| BufferError::LimitExceeded {
+				what: format!("a buffer of {} bytes is too large for opengl",
+					descriptor.size)
+			})?;
+
+		let gl = self.context.as_ref();
+		let buffer = unsafe {
+			gl.create_buffer()
+				.map_err(|what| BufferError::CreationFailed { what })?
+		};
+
+		/* The buffer is owned by its structure from here on, so that it gets
+		 * deleted even if the function panics. */
+		let inner = Rc::new(InnerBuffer {
+			context: self.context.clone(),
+			information: self.information.clone(),
+			pipeline: self.pipeline_lock.clone(),
+			sanitizer: self.sanitizer.clone(),
+			debug: self.debug.clone(),
+			buffer,
+			label: Default::default(),
+			access: Default::default(),
+			map: Default::default(),
+			contents: descriptor.contents,
+			usage: descriptor.usage,
+			profile: descriptor.profile,
+			len: descriptor.size
+		});
+
+		/* The function may well use the device to create buffers of its own,
+		 * so the buffer has to be bound again once it returns. */
+		if self.information.capabilities.buffer_mapping && len > 0 {
+			let storage = unsafe {
+				gl.bind_buffer(target, Some(buffer));
+				gl.buffer_data_size(target, size, descriptor.profile.as_opengl());
+				let ptr = gl.map_buffer_range(
+					target,
+					0,
+					size,
+					glow::MAP_WRITE_BIT | glow::MAP_INVALIDATE_BUFFER_BIT);
+				if ptr.is_null() {
+					panic!("opengl failed to map the buffer {} to memory: {}",
+						inner.name(),
+						crate::debug::error_name(gl.get_error()));
+				}
+				gl.bind_buffer(target, None);
+
+				std::slice::from_raw_parts_mut(ptr, len)
+			};
+
+			let mut writer = BufferWriter::new(storage);
+			f(&mut writer);
+			let data = writer.finish(&format_args!("(new buffer)"));
+			self.sanitizer.check(
+				&format_args!("(new buffer)"),
+				descriptor.contents,
+				0,
+				data);
+
+			unsafe {
+				gl.bind_buffer(target, Some(buffer));
+				gl.unmap_buffer(target);
+				gl.bind_buffer(target, None);
+				inner.check("allocating");
+			}
+		} else {
+			/* Functions creating buffers of their own would find the staging
+			 * buffer taken, in which case they get one of their own. */
+			let mut shared = self.staging.try_borrow_mut();
+			let mut fresh = Vec::new();
+			let staging = match &mut shared {
+				Ok(shared) => &mut **shared,
+				Err(_) => &mut fresh
+			};
+			staging.clear();
+			staging.resize(len, 0);
+
+			let mut writer = BufferWriter::new(&mut staging[..]);
+			f(&mut writer);
+			let data = writer.finish(&format_args!("(new buffer)"));
+			self.sanitizer.check(
+				&format_args!("(new buffer)"),
+				descriptor.contents,
+				0,
+				data);
+
+			unsafe {
+				gl.bind_buffer(target, Some(buffer));
+				gl.buffer_data_u8_slice(
+					target,
+					data,
+					descriptor.profile.as_opengl());
+				gl.bind_buffer(target, None);
+				inner.check("allocating");
+			}
+		}
+
+		Ok(Buffer { inner })
+	}
+
+	/** Tries to create a new zero-initialized buffer.
+	 *
+	 * # Errors
+	 * This function fails for the same reasons [`create_buffer_with_data()`]
+	 * does.
+	 *
+	 * # Performance
+	 * Creating zero-initialized buffers may involve an extra, zero-initialized
+	 * allocation in host memory, as big as the target buffer on the device.
+	 * Users should only sparringly rely on this function.
+	 *
+	 * [`create_buffer_with_data()`]: Self::create_buffer_with_data */
+	pub fn create_buffer(&self, descriptor: &BufferDescriptor) -> Result<Buffer, BufferError> {
+		let len  = usize::try_from(descriptor.size).unwrap();
+		let init = vec![0; len];
+
+		self.create_buffer_with_data(descriptor, &init[..])
+	}
+
+	instance_typed_buffer_creation_functions! {
+		VertexBuffer {
+			#[doc = "Tries to create a new vertex buffer with the given data, "]
+			#[doc = "as in [`create_buffer_with_data()`]."]
+			#[doc = ""]
+			#[doc = "[`create_buffer_with_data()`]: Self::create_buffer_with_data"]
+			pub fn create_vertex_buffer_with_data;
+			#[doc = "Tries to create a new vertex buffer, filled in by the "]
+			#[doc = "given function, as in [`create_buffer_with()`]."]
+			#[doc = ""]
+			#[doc = "[`create_buffer_with()`]: Self::create_buffer_with"]
+			pub fn create_vertex_buffer_with;
+			#[doc = "Tries to create a new zero-initialized vertex buffer, as "]
+			#[doc = "in [`create_buffer()`]."]
+			#[doc = ""]
+			#[doc = "[`create_buffer()`]: Self::create_buffer"]
+			pub fn create_vertex_buffer;
+		}
+		IndexBuffer {
+			#[doc = "Tries to create a new index buffer with the given data, "]
+			#[doc = "as in [`create_buffer_with_data()`]."]
+			#[doc = ""]
+			#[doc = "[`create_buffer_with_data()`]: Self::create_buffer_with_data"]
+			pub fn create_index_buffer_with_data;
+			#[doc = "Tries to create a new index buffer, filled in by the "]
+			#[doc = "given function, as in [`create_buffer_with()`]."]
+			#[doc = ""]
+			#[doc = "[`create_buffer_with()`]: Self::create_buffer_with"]
+			pub fn create_index_buffer_with;
+			#[doc = "Tries to create a new zero-initialized index buffer, as "]
+			#[doc = "in [`create_buffer()`]."]
+			#[doc = ""]
+			#[doc = "[`create_buffer()`]: Self::create_buffer"]
+			pub fn create_index_buffer;
+		}
+		UniformBuffer {
+			#[doc = "Tries to create a new uniform buffer with the given data, "]
+			#[doc = "as in [`create_buffer_with_data()`]."]
+			#[doc = ""]
+			#[doc = "[`create_buffer_with_data()`]: Self::create_buffer_with_data"]
+			pub fn create_uniform_buffer_with_data;
+			#[doc = "Tries to create a new uniform buffer, filled in by the "]
+			#[doc = "given function, as in [`create_buffer_with()`]."]
+			#[doc = ""]
+			#[doc = "[`create_buffer_with()`]: Self::create_buffer_with"]
+			pub fn create_uniform_buffer_with;
+			#[doc = "Tries to create a new zero-initialized uniform buffer, "]
+			#[doc = "as in [`create_buffer()`]."]
+			#[doc = ""]
+			#[doc = "[`create_buffer()`]: Self::create_buffer"]
+			pub fn create_uniform_buffer;
+		}
 	}
 }
@@ -0,0 +1,245 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::ops::Range;
+use crate::pipeline::RenderPipelineError;
+
+/** Registry of the locations vertex attributes get bound to, by the name of
+ * the attribute, shared by all of the programs created by a device.
+ *
+ * Attributes get their locations bound right before their program is linked,
+ * and attributes sharing a name share their locations across programs. This
+ * way, pipelines with the same vertex layout lay their attributes out the same
+ * way, and the locations never have to be looked up when setting up vertex
+ * arrays. Attributes taking up more than one location, such as matrices, get
+ * a run of consecutive locations.
+ *
+ * Once the registry runs out of locations, new names get whatever location is
+ * free in the program they're in, which still works, but isn't shared. */
+#[derive(Debug)]
+pub(crate) struct AttributeRegistry {
+	/** Locations handed out so far, by the name of their attribute. */
+	locations: HashMap<String, Range<u32>>,
+	/** Next location that has never been handed out. */
+	next: u32,
+	/** Number of locations that may be handed out. */
+	capacity: u32,
+}
+impl AttributeRegistry {
+	/** Creates a new, empty registry for a context with the given number of
+	 * vertex attribute locations. */
+	pub(crate) fn new(max_attributes: u32) -> Self {
+		Self {
+			locations: HashMap::new(),
+			next: 0,
+			capacity: max_attributes
+		}
+	}
+
+	/** The first of the locations the attribute with the given name is bound
+	 * to, handing out the given number of new ones if it hasn't been seen yet.
+	 * Returns `None` once the registry has run out of locations for new names,
+	 * or if the attribute has been seen before, taking up fewer locations. */
+	pub(crate) fn assign(&mut self, name: &str, span: u32) -> Option<u32> {
+		if let Some(locations) = self.locations.get(name) {
+			if locations.end - locations.start >= span {
+				return Some(locations.start)
+			}
+
+			rate_limited!(debug!(target: "gavle::pipeline",
+				"attribute \"{}\" takes up {} locations, but was registered \
+				with {}. it will be given locations of its own instead",
+				name,
+				span,
+				locations.end - locations.start));
+			return None
+		}
+
+		let end = self.next.checked_add(span)?;
+		if end > self.capacity {
+			rate_limited!(debug!(target: "gavle::pipeline",
+				"ran out of locations to register the attribute \"{}\" to. \
+				it will be given locations of its own instead",
+				name));
+			return None
+		}
+
+		self.locations.insert(name.to_owned(), self.next..end);
+		self.next = end;
+		Some(end - span)
+	}
+}
+
+/** An attribute in need of a location, as seen by [`resolve()`]. */
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct Request<'a> {
+	/** Name of the attribute. */
+	pub(crate) name: &'a str,
+	/** Location the attribute has to be bound to, if any. */
+	pub(crate) explicit: Option<u32>,
+	/** Number of consecutive locations taken up by the attribute. */
+	pub(crate) span: u32,
+}
+
+/** Works out the locations the given attributes of a single program get bound
+ * to, in order, out of a context with `capacity` attribute locations.
+ *
+ * Attributes with explicit locations get them, and must neither overlap nor
+ * go past the limit. Every other attribute gets its location from the
+ * registry, unless the registry has none to give or that location is already
+ * taken in this program, in which case it gets the first free one. */
+pub(crate) fn resolve(
+	registry: &mut AttributeRegistry,
+	requests: &[Request],
+	capacity: u32) -> Result<Vec<u32>, RenderPipelineError> {
+
+	let mut taken: Vec<(Range<u32>, &str)> = Vec::new();
+	let overlap = |taken: &[(Range<u32>, &str)], range: &Range<u32>| taken.iter()
+		.find(|(other, _)| other.start < range.end && range.start < other.end)
+		.map(|(_, name)| name.to_string());
+
+	let mut locations = vec![0; requests.len()];
+	for (i, request) in requests.iter().enumerate() {
+		let location = match request.explicit {
+			Some(location) => location,
+			None => continue
+		};
+
+		let range = location..location.saturating_add(request.span);
+		if range.end > capacity {
+			return Err(RenderPipelineError::AttributeLocationOutOfRange {
+				binding: request.name.to_owned(),
+				location: range.end - 1,
+				max: capacity
+			})
+		}
+		if let Some(other) = overlap(&taken, &range) {
+			return Err(RenderPipelineError::AttributeLocationConflict {
+				first: other,
+				second: request.name.to_owned(),
+				location
+			})
+		}
+
+		taken.push((range, request.name));
+		locations[i] = location;
+	}
+
+	for (i, request) in requests.iter().enumerate() {
+		if request.explicit.is_some() { continue }
+
+		let shared = registry.assign(request.name, request.span)
+			.filter(|location| {
+				let range = *location..location + request.span;
+				overlap(&taken, &range).is_none()
+			});
+		let location = match shared {
+			Some(location) => location,
+			None => (0..capacity.saturating_sub(request.span.saturating_sub(1)))
+				.find(|location| {
+					let range = *location..location + request.span;
+					overlap(&taken, &range).is_none()
+				})
+				.ok_or_else(|| RenderPipelineError::TooManyAttributes {
+					count: u32::try_from(requests.len()).unwrap_or(u32::MAX),
+					max: capacity
+				})?
+		};
+
+		taken.push((location..location + request.span, request.name));
+		locations[i] = location;
+	}
+
+	Ok(locations)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn request(name: &str, explicit: Option<u32>, span: u32) -> Request<'_> {
+		Request { name, explicit, span }
+	}
+
+	#[test]
+	fn names_keep_their_locations() {
+		let mut registry = AttributeRegistry::new(16);
+		assert_eq!(registry.assign("tt_vert_position", 1), Some(0));
+		assert_eq!(registry.assign("tt_inst_model", 4), Some(1));
+		assert_eq!(registry.assign("tt_vert_normal", 1), Some(5));
+		assert_eq!(registry.assign("tt_vert_position", 1), Some(0));
+		assert_eq!(registry.assign("tt_inst_model", 4), Some(1));
+
+		/* Names can't grow past the locations they were registered with. */
+		assert_eq!(registry.assign("tt_vert_position", 2), None);
+	}
+
+	#[test]
+	fn full_registries_leave_new_names_unregistered() {
+		let mut registry = AttributeRegistry::new(4);
+		assert_eq!(registry.assign("tt_inst_model", 3), Some(0));
+		assert_eq!(registry.assign("tt_inst_normal", 3), None);
+		assert_eq!(registry.assign("tt_vert_position", 1), Some(3));
+		assert_eq!(registry.assign("tt_vert_normal", 1), None);
+	}
+
+	#[test]
+	fn layouts_resolve_the_same_across_programs() {
+		let mut registry = AttributeRegistry::new(16);
+		let first = resolve(&mut registry, &[
+			request("tt_vert_position", None, 1),
+			request("tt_vert_normal", None, 1),
+		], 16).unwrap();
+		let second = resolve(&mut registry, &[
+			request("tt_vert_normal", None, 1),
+			request("tt_vert_position", None, 1),
+		], 16).unwrap();
+
+		assert_eq!(first, vec![0, 1]);
+		assert_eq!(second, vec![1, 0]);
+	}
+
+	#[test]
+	fn explicit_locations_take_precedence() {
+		let mut registry = AttributeRegistry::new(16);
+		registry.assign("tt_vert_position", 1);
+
+		/* The registry location of the position is taken, so it gets moved to
+		 * the first free location instead. */
+		let locations = resolve(&mut registry, &[
+			request("tt_vert_position", None, 1),
+			request("tt_inst_model", Some(0), 4),
+		], 16).unwrap();
+		assert_eq!(locations, vec![4, 0]);
+
+		/* Which doesn't change what the registry has for it. */
+		assert_eq!(registry.assign("tt_vert_position", 1), Some(0));
+	}
+
+	#[test]
+	fn conflicting_explicit_locations_are_rejected() {
+		let mut registry = AttributeRegistry::new(16);
+		let result = resolve(&mut registry, &[
+			request("tt_inst_model", Some(2), 4),
+			request("tt_vert_position", Some(5), 1),
+		], 16);
+		assert!(matches!(result,
+			Err(RenderPipelineError::AttributeLocationConflict { location: 5, .. })));
+
+		let result = resolve(&mut registry, &[
+			request("tt_inst_model", Some(14), 4),
+		], 16);
+		assert!(matches!(result,
+			Err(RenderPipelineError::AttributeLocationOutOfRange { location: 17, .. })));
+	}
+
+	#[test]
+	fn programs_run_out_of_locations() {
+		let mut registry = AttributeRegistry::new(4);
+		let result = resolve(&mut registry, &[
+			request("tt_inst_model", None, 4),
+			request("tt_vert_position", None, 1),
+		], 4);
+		assert!(matches!(result,
+			Err(RenderPipelineError::TooManyAttributes { count: 2, max: 4 })));
+	}
+}
@@ -0,0 +1,269 @@
+use std::cell::{Cell, RefCell, RefMut};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+use std::fmt::{Display, Formatter};
+
+/** Operations that lock the pipeline, as reported when the lock is held for
+ * too long, or when it can't be taken because something else is holding it. */
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum LockOperation {
+	/** A render pass, for as long as it is alive. */
+	RenderPass,
+	/** A transform feedback pass, for as long as it is alive. */
+	TransformFeedbackPass,
+	/** A mapping of a buffer into host memory, for as long as it is alive. */
+	BufferMapping,
+	/** Writing data into a buffer. */
+	BufferWrite,
+	/** Writing data into a texture. */
+	TextureWrite,
+	/** Reading data back from a texture. */
+	TextureRead,
+	/** Creating a texture. */
+	TextureCreation,
+	/** Reading pixels back from a framebuffer. */
+	FramebufferRead,
+	/** Creating or resizing a framebuffer. */
+	FramebufferCreation,
+	/** Copying between framebuffers, textures or buffers. */
+	Copy,
+	/** Creating a render pipeline. */
+	PipelineCreation,
+	/** Creating a sampler. */
+	SamplerCreation,
+	/** Creating or waiting on fences and queries. */
+	Synchronization,
+	/** Running code outside of gavle, through [`Device::with_external_gl`].
+	 *
+	 * [`Device::with_external_gl`]: crate::Device::with_external_gl */
+	ExternalGl,
+}
+impl Display for LockOperation {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		f.write_str(match self {
+			Self::RenderPass => "a render pass",
+			Self::TransformFeedbackPass => "a transform feedback pass",
+			Self::BufferMapping => "a buffer mapping",
+			Self::BufferWrite => "a buffer write",
+			Self::TextureWrite => "a texture write",
+			Self::TextureRead => "a texture read",
+			Self::TextureCreation => "a texture creation",
+			Self::FramebufferRead => "a framebuffer read",
+			Self::FramebufferCreation => "a framebuffer creation",
+			Self::Copy => "a copy",
+			Self::PipelineCreation => "a pipeline creation",
+			Self::SamplerCreation => "a sampler creation",
+			Self::Synchronization => "a synchronization operation",
+			Self::ExternalGl => "external OpenGL code",
+		})
+	}
+}
+
+/** Who is currently holding the pipeline lock, as given by
+ * [`Device::pipeline_lock_holder()`].
+ *
+ * [`Device::pipeline_lock_holder()`]: crate::Device::pipeline_lock_holder */
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct LockHolder {
+	/** The operation holding the lock. */
+	pub operation: LockOperation,
+	/** The label of whatever is holding the lock, if it has one. For render
+	 * passes, this is the label of their pipeline. */
+	pub label: Option<String>,
+	/** For how long the lock has been held. This is always `None` in targets
+	 * without a monotonic clock. */
+	pub held_for: Option<Duration>,
+}
+impl Display for LockHolder {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}", self.operation)?;
+		if let Some(label) = &self.label {
+			write!(f, " (\"{}\")", label)?;
+		}
+		if let Some(held_for) = self.held_for {
+			write!(f, ", held for {:?}", held_for)?;
+		}
+		Ok(())
+	}
+}
+
+/** Lock serializing the operations that change the state of the context.
+ *
+ * Because of the way the pipeline is managed through an internal state
+ * machine in OpenGL, in order to avoid state corruption, we have to treat
+ * drawing commands as atomic transactions. On top of the lock itself, this
+ * keeps track of which operation is holding it and since when, so that
+ * whatever fails to take it can say what it was up against. */
+#[derive(Debug, Default)]
+pub(crate) struct PipelineLock {
+	/** The lock itself. */
+	lock: RefCell<()>,
+	/** The operation holding the lock and when it took it, if any. */
+	holder: Cell<Option<(LockOperation, Option<Instant>)>>,
+	/** Label of whatever is holding the lock, if it has one. This is shared
+	 * with its owner, so that taking the lock doesn't copy it. */
+	label: RefCell<Option<Rc<str>>>,
+	/** Holds longer than this get logged when they end. */
+	threshold: Cell<Option<Duration>>,
+}
+impl PipelineLock {
+	/** Locks the pipeline for the given operation.
+	 *
+	 * # Panic
+	 * This function will panic if the pipeline is already locked, naming the
+	 * operation holding it. */
+	pub(crate) fn acquire(&self, operation: LockOperation) -> PipelineGuard<'_> {
+		self.acquire_labeled(operation, None)
+	}
+
+	/** Locks the pipeline for the given operation, on behalf of something
+	 * with the given label.
+	 *
+	 * # Panic
+	 * This function will panic if the pipeline is already locked, naming the
+	 * operation holding it. */
+	pub(crate) fn acquire_labeled(
+		&self,
+		operation: LockOperation,
+		label: Option<Rc<str>>) -> PipelineGuard<'_> {
+
+		match self.try_acquire(operation, label) {
+			Ok(guard) => guard,
+			Err(holder) => panic!("tried to lock the pipeline for {}, but it \
+				is already locked by {}", operation, holder)
+		}
+	}
+
+	/** Tries to lock the pipeline for the given operation, on behalf of
+	 * something with the given label, returning who's holding it if that
+	 * fails. */
+	pub(crate) fn try_acquire(
+		&self,
+		operation: LockOperation,
+		label: Option<Rc<str>>) -> Result<PipelineGuard<'_>, LockHolder> {
+
+		let lock = match self.lock.try_borrow_mut() {
+			Ok(lock) => lock,
+			Err(_) => return Err(self.holder().expect("the pipeline lock is \
+				taken, but nothing is holding it"))
+		};
+
+		self.holder.set(Some((operation, now())));
+		*self.label.borrow_mut() = label;
+		Ok(PipelineGuard {
+			owner: self,
+			_lock: lock
+		})
+	}
+
+	/** Who is currently holding the lock, if anyone. */
+	pub(crate) fn holder(&self) -> Option<LockHolder> {
+		let (operation, since) = self.holder.get()?;
+		Some(LockHolder {
+			operation,
+			label: self.label.borrow().as_deref().map(str::to_owned),
+			held_for: since.and_then(|since| Some(now()?.duration_since(since))),
+		})
+	}
+
+	/** Changes the longest the lock may be held for before the hold gets
+	 * logged when it ends, or turns that off, for `None`. */
+	pub(crate) fn set_threshold(&self, threshold: Option<Duration>) {
+		self.threshold.set(threshold)
+	}
+
+	/** The current holder of the lock, if it has been holding it for longer
+	 * than the threshold. */
+	fn overheld(&self) -> Option<LockHolder> {
+		let threshold = self.threshold.get()?;
+		self.holder()
+			.filter(|holder| holder.held_for
+				.map(|held_for| held_for > threshold)
+				.unwrap_or(false))
+	}
+}
+
+/** Lock on the pipeline, which gets released when dropped. */
+pub(crate) struct PipelineGuard<'a> {
+	/** The lock this guard was taken from. */
+	owner: &'a PipelineLock,
+	/** The borrow that is the lock itself. */
+	_lock: RefMut<'a, ()>,
+}
+impl<'a> Drop for PipelineGuard<'a> {
+	fn drop(&mut self) {
+		if let Some(holder) = self.owner.overheld() {
+			warn!(target: "gavle::lock",
+				"the pipeline was locked by {}, longer than the threshold \
+				of {:?}. nothing else can use the device while it is locked",
+				holder,
+				self.owner.threshold.get().unwrap_or_default());
+		}
+
+		self.owner.holder.set(None);
+		self.owner.label.borrow_mut().take();
+	}
+}
+
+/** The current time, in targets with a monotonic clock we can rely on. */
+fn now() -> Option<Instant> {
+	#[cfg(not(target_arch = "wasm32"))]
+	{ Some(Instant::now()) }
+
+	#[cfg(target_arch = "wasm32")]
+	{ None }
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn contention_names_the_holder() {
+		let lock = PipelineLock::default();
+		let guard = lock.acquire_labeled(
+			LockOperation::RenderPass,
+			Some("shadows".into()));
+
+		let holder = lock.try_acquire(LockOperation::TextureWrite, None)
+			.err()
+			.expect("the lock must not be taken twice");
+		assert_eq!(holder.operation, LockOperation::RenderPass);
+		assert_eq!(holder.label.as_deref(), Some("shadows"));
+		assert!(holder.held_for.is_some());
+
+		let message = holder.to_string();
+		assert!(message.contains("a render pass"));
+		assert!(message.contains("\"shadows\""));
+
+		drop(guard);
+		assert!(lock.holder().is_none());
+		assert!(lock.try_acquire(LockOperation::TextureWrite, None).is_ok());
+	}
+
+	#[test]
+	#[should_panic(expected = "already locked by a buffer mapping")]
+	fn contention_panics_with_the_holder() {
+		let lock = PipelineLock::default();
+		let _mapping = lock.acquire(LockOperation::BufferMapping);
+		let _write = lock.acquire(LockOperation::BufferWrite);
+	}
+
+	#[test]
+	fn long_holds_are_caught() {
+		let lock = PipelineLock::default();
+		let guard = lock.acquire(LockOperation::ExternalGl);
+		assert!(lock.overheld().is_none());
+
+		lock.set_threshold(Some(Duration::from_secs(3600)));
+		assert!(lock.overheld().is_none());
+
+		lock.set_threshold(Some(Duration::from_millis(1)));
+		std::thread::sleep(Duration::from_millis(5));
+		let holder = lock.overheld().expect("the hold must be over the threshold");
+		assert_eq!(holder.operation, LockOperation::ExternalGl);
+
+		drop(guard);
+		assert!(lock.overheld().is_none());
+	}
+}
@@ -0,0 +1,134 @@
+use ::image::{ImageBuffer, Primitive, Rgba};
+use ::image::imageops;
+use crate::texture::{FilterType, TextureExtent, TextureFormat};
+
+impl From<FilterType> for imageops::FilterType {
+	fn from(filter: FilterType) -> Self {
+		match filter {
+			FilterType::Nearest => Self::Nearest,
+			FilterType::Triangle => Self::Triangle,
+			FilterType::CatmullRom => Self::CatmullRom,
+			FilterType::Gaussian => Self::Gaussian,
+			FilterType::Lanczos3 => Self::Lanczos3,
+		}
+	}
+}
+
+/** Generates the given number of mip levels for a texture of the given format
+ * and extent on the host, scaling the given data of its base level down with
+ * the given filter. The data of every level gets packed one after the other,
+ * starting with the base level, just like for [`Mipmap::Manual`].
+ *
+ * Only two-dimensional textures in the [`TextureFormat::Rgba8Unorm`] and
+ * [`TextureFormat::Rgba32Float`] formats are supported. This returns `None`
+ * for all other textures, whose mipmaps get generated by the device instead.
+ *
+ * [`Mipmap::Manual`]: crate::Mipmap::Manual */
+pub(crate) fn generate(
+	format: TextureFormat,
+	extent: &TextureExtent,
+	levels: u32,
+	base: &[u8],
+	filter: FilterType) -> Option<Vec<u8>> {
+
+	match format {
+		TextureFormat::Rgba8Unorm =>
+			generate_chain(extent, levels, base.to_vec(), filter),
+		TextureFormat::Rgba32Float => {
+			/* The data may not be aligned to floats, so it gets copied. */
+			let base = base.chunks_exact(4)
+				.map(|bytes| f32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+				.collect::<Vec<_>>();
+			let chain = generate_chain(extent, levels, base, filter)?;
+
+			Some(chain.into_iter()
+				.flat_map(|value| value.to_ne_bytes().to_vec())
+				.collect())
+		},
+		_ => None
+	}
+}
+
+/** Generates the given number of mip levels from the given RGBA texels of the
+ * base level of a two-dimensional texture with the given extent, packing them
+ * one after the other. */
+fn generate_chain<T>(
+	extent: &TextureExtent,
+	levels: u32,
+	base: Vec<T>,
+	filter: FilterType) -> Option<Vec<T>>
+	where T: Primitive + 'static {
+
+	let (width, height) = match *extent {
+		TextureExtent::D2 { width, height } => (width, height),
+		_ => return None
+	};
+
+	let len = width as usize * height as usize * 4;
+	if len == 0 || base.len() != len {
+		return None
+	}
+	let image = ImageBuffer::<Rgba<T>, _>::from_raw(width, height, base.clone())?;
+
+	let mut chain = base;
+	for level in 1..levels {
+		let level = imageops::resize(
+			&image,
+			u32::max(width >> level, 1),
+			u32::max(height >> level, 1),
+			filter.into());
+		chain.extend_from_slice(level.as_raw());
+	}
+
+	Some(chain)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn generates_every_level() {
+		let extent = TextureExtent::D2 { width: 4, height: 2 };
+		let base = vec![255u8; 4 * 2 * 4];
+
+		let chain = generate(
+			TextureFormat::Rgba8Unorm,
+			&extent,
+			3,
+			&base,
+			FilterType::Triangle).unwrap();
+
+		/* 4x2, then 2x1, then 1x1. */
+		assert_eq!(chain.len(), (8 + 2 + 1) * 4);
+		assert_eq!(&chain[..base.len()], &base[..]);
+		assert!(chain[base.len()..].iter().all(|texel| *texel == 255));
+	}
+
+	#[test]
+	fn generates_float_levels() {
+		let extent = TextureExtent::D2 { width: 2, height: 2 };
+		let base = [0.5f32; 16].iter()
+			.flat_map(|value| value.to_ne_bytes().to_vec())
+			.collect::<Vec<_>>();
+
+		let chain = generate(
+			TextureFormat::Rgba32Float,
+			&extent,
+			2,
+			&base,
+			FilterType::Nearest).unwrap();
+		assert_eq!(chain.len(), (4 + 1) * 16);
+		assert_eq!(&chain[64..68], &0.5f32.to_ne_bytes());
+	}
+
+	#[test]
+	fn leaves_other_textures_to_the_device() {
+		let volume = TextureExtent::D3 { width: 2, height: 2, depth: 2 };
+		assert!(generate(TextureFormat::Rgba8Unorm, &volume, 2, &[0; 32], FilterType::Nearest).is_none());
+
+		let plane = TextureExtent::D2 { width: 2, height: 2 };
+		assert!(generate(TextureFormat::Depth24Stencil8, &plane, 2, &[0; 16], FilterType::Nearest).is_none());
+		assert!(generate(TextureFormat::Rgba8Unorm, &plane, 2, &[0; 15], FilterType::Nearest).is_none());
+	}
+}
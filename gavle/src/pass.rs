@@ -1,21 +1,31 @@
 use crate::pipeline::RenderPipeline;
-use std::rc::Rc;
+use std::rc::{Rc, Weak};
+use std::cell::RefCell;
 use glow::{Context, HasContext};
-use crate::buffer::{VertexBuffer, IndexBuffer};
+use crate::buffer::{Buffer, BufferUsage};
 use std::ops::Range;
-use crate::binding::UniformGroup;
+use crate::binding::{UniformGroup, BindGroupError};
 use crate::access::AccessLock;
-use crate::framebuffer::Framebuffer;
+use crate::framebuffer::{Framebuffer, clamp_depth};
 use std::convert::TryFrom;
 use crate::{Information, Color};
+use crate::usage::UsageTracker;
+use crate::texture::InnerTexture;
+use crate::query::Query;
+use crate::debug::Debugger;
+use crate::bundle::{RenderBundle, BundleCommand};
 
 pub struct RenderPass<'a> {
 	/** Shared graphics context. */
 	pub(crate) context: Rc<Context>,
 	/** Shared context information. */
 	pub(crate) information: Rc<Information>,
+	/** Record of the frames in which textures get bound. */
+	pub(crate) usage: Rc<RefCell<UsageTracker<Weak<InnerTexture>>>>,
+	/** Shared error checking state. */
+	pub(crate) debug: Rc<Debugger>,
 	/** Global graphics state lock. */
-	pub(crate) _lock: std::cell::RefMut<'a, ()>,
+	pub(crate) _lock: crate::lock::PipelineGuard<'a>,
 	/** Whether the pipeline has already been set up for calls.
 	 *
 	 * Because for the hole lifetime of this render pass we own a lock to the
@@ -33,21 +43,48 @@ pub struct RenderPass<'a> {
 	/** Reference to the pipeline object used in this pass. */
 	pub(crate) pipeline: &'a RenderPipeline,
 	/** Reference to a vertex buffer, if any. */
-	pub(crate) vertex: Option<&'a VertexBuffer>,
+	pub(crate) vertex: Option<&'a Buffer>,
+	/** Reference to an instance buffer, if any. */
+	pub(crate) instance: Option<&'a Buffer>,
 	/** Reference to an index buffer, if any. */
-	pub(crate) index: Option<&'a IndexBuffer>,
+	pub(crate) index: Option<&'a Buffer>,
 	/** Reference to a uniform binding group, if any. */
 	pub(crate) bind: Option<&'a UniformGroup>,
+	/** Dynamic offsets applied to the buffers in the uniform binding group.
+	 * Empty when no offsets are applied. */
+	pub(crate) bind_offsets: Vec<u32>,
+	/** Revision of the uniform binding group as of when it was last bound,
+	 * which changes when its entries get replaced. */
+	pub(crate) bind_revision: u64,
 	/** Framebuffer connected to the attachments. */
 	pub(crate) framebuffer: &'a Framebuffer,
 	/** Stencil reference value to be used during render operations. */
 	pub(crate) stencil_reference: u8,
+	/** Window depth values the near and far planes get mapped to. */
+	pub(crate) depth_range: (f32, f32),
+	/** Whether the depth range has been set up. */
+	pub(crate) depth_range_setup: bool,
 	/** Color blend constant value to be used during render operations. */
 	pub(crate) color_blend_constant: Color,
+	/** Scissor rectangle, already checked against the limits of the context,
+	 * or `None` if the scissor test is disabled. */
+	pub(crate) scissor: Option<Viewport>,
+	/** Whether the scissor state has been set up. */
+	pub(crate) scissor_setup: bool,
+	/** Whether the load operations of the framebuffer are restricted to the
+	 * scissor rectangle. */
+	pub(crate) scissored_load: bool,
+	/** The query currently active in this pass, if any. */
+	pub(crate) query: Option<&'a Query>,
 }
 impl<'a> RenderPass<'a> {
-	/** Sets the vertex buffer to be used for this dispatch. */
-	pub fn set_vertex_buffer(&mut self, buffer: &'a VertexBuffer) {
+	/** Sets the vertex buffer to be used for this dispatch.
+	 *
+	 * # Panic
+	 * This function will panic if the usage of the buffer doesn't include
+	 * [`BufferUsage::VERTEX`]. */
+	pub fn set_vertex_buffer(&mut self, buffer: &'a Buffer) {
+		buffer.assert_usage(BufferUsage::VERTEX, "use vertex data from");
 		let old = self.vertex.replace(buffer);
 
 		/* We can compare inner buffers to check whether the buffer is the
@@ -57,11 +94,37 @@ impl<'a> RenderPass<'a> {
 			Some(_) => false,
 			None => true,
 		};
-		self.draw_buffers_setup = !updated;
+		if updated {
+			self.draw_buffers_setup = false;
+		}
 	}
 
-	/** Sets the index buffer to be used for this dispatch. */
-	pub fn set_index_buffer(&mut self, buffer: &'a IndexBuffer) {
+	/** Sets the buffer the per-instance attributes of the pipeline are read
+	 * from, as described by the instance layout given to it at creation.
+	 *
+	 * # Panic
+	 * This function will panic if the usage of the buffer doesn't include
+	 * [`BufferUsage::VERTEX`]. */
+	pub fn set_instance_buffer(&mut self, buffer: &'a Buffer) {
+		buffer.assert_usage(BufferUsage::VERTEX, "use instance data from");
+		let old = self.instance.replace(buffer);
+
+		let updated = match old {
+			Some(old) => !Rc::ptr_eq(&buffer.inner, &old.inner),
+			None => true,
+		};
+		if updated {
+			self.draw_buffers_setup = false;
+		}
+	}
+
+	/** Sets the index buffer to be used for this dispatch.
+	 *
+	 * # Panic
+	 * This function will panic if the usage of the buffer doesn't include
+	 * [`BufferUsage::INDEX`]. */
+	pub fn set_index_buffer(&mut self, buffer: &'a Buffer) {
+		buffer.assert_usage(BufferUsage::INDEX, "use index data from");
 		let old = self.index.replace(buffer);
 
 		/* We can compare inner buffers to check whether the buffer is the
@@ -71,7 +134,9 @@ impl<'a> RenderPass<'a> {
 			Some(_) => false,
 			None => true,
 		};
-		self.draw_buffers_setup = !updated;
+		if updated {
+			self.draw_buffers_setup = false;
+		}
 	}
 
 	/** Sets the uniform bind group to be used for this dispatch. */
@@ -84,61 +149,238 @@ impl<'a> RenderPass<'a> {
 			Some(_) => false,
 			None => true,
 		};
-		self.general_setup = !updated;
+		let offsets = !self.bind_offsets.is_empty();
+		self.bind_offsets.clear();
+		if updated || offsets {
+			self.general_setup = false;
+		}
 	}
 
-	/** Set the viewport to be used for all subsequent draw commands. */
-	pub fn set_viewport(&mut self, viewport: Viewport) {
-		/* Clamp both the width and the height to the maximum value allowed by
-		 * the context before we actually pass this call on to OpenGL. */
+	/** Sets the uniform bind group to be used for this dispatch, shifting the
+	 * ranges bound for its uniform buffers by the given offsets, in bytes.
+	 *
+	 * There must be exactly one offset per uniform buffer entry in the group,
+	 * in the order they were given in when the group was created, and every
+	 * shifted range must be aligned to the [`uniform_buffer_offset_alignment`]
+	 * limit and still fit in its buffer. Changing only the offsets between
+	 * draws is much cheaper than creating new groups.
+	 *
+	 * [`uniform_buffer_offset_alignment`]: crate::Limits::uniform_buffer_offset_alignment */
+	pub fn set_bind_group_with_offsets(
+		&mut self,
+		group: &'a UniformGroup,
+		offsets: &[u32]) -> Result<(), BindGroupError> {
 
-		let mut width = viewport.width;
-		if let Some(max_width) = self.information.limits.max_viewport_width {
-			if viewport.width > max_width {
-				warn!("Clamping requested viewport width ({}) to the maximum ({})",
-					viewport.width,
-					max_width);
-				width = max_width
-			}
-		}
-		let mut height = viewport.height;
-		if let Some(max_height) = self.information.limits.max_viewport_height {
-			if viewport.height > max_height {
-				warn!("Clamping requested viewport height ({}) to the maximum ({})",
-					viewport.height,
-					max_height);
-				height = max_height
-			}
+		group.check_offsets(
+			offsets,
+			self.information.limits.uniform_buffer_offset_alignment)?;
+
+		let old = self.bind.replace(group);
+		let updated = match old {
+			Some(old) => !std::ptr::eq(old as *const _, group as *const _),
+			None => true,
+		};
+		if updated || self.bind_offsets != offsets {
+			self.bind_offsets.clear();
+			self.bind_offsets.extend_from_slice(offsets);
+			self.general_setup = false;
 		}
 
-		/* Now that we've made sure the parameters are valid, pass them on. */
+		Ok(())
+	}
+
+	/** Set the viewport to be used for all subsequent draw commands.
+	 *
+	 * # Errors
+	 * This function fails, leaving the viewport as it was, if the viewport is
+	 * wider or taller than the [`max_viewport_width`] and
+	 * [`max_viewport_height`] limits allow, or than fits in an `i32`.
+	 *
+	 * [`max_viewport_width`]: crate::Limits::max_viewport_width
+	 * [`max_viewport_height`]: crate::Limits::max_viewport_height */
+	pub fn set_viewport(&mut self, viewport: Viewport) -> Result<(), ViewportError> {
+		let limits = &self.information.limits;
+		let (width, height) = check_viewport(
+			&viewport,
+			limits.max_viewport_width,
+			limits.max_viewport_height)?;
+
 		let gl = self.context.as_ref();
 		unsafe {
-			gl.viewport(
-				viewport.x,
-				viewport.y,
-				i32::try_from(width)
-					.expect("the viewport width must fit in an i32"),
-				i32::try_from(height)
-					.expect("the viewport height must fit in an i32"))
+			gl.viewport(viewport.x, viewport.y, width, height)
 		}
+
+		Ok(())
 	}
 
-	/** Sets the blend color as used by some of the blending modes. */
-	pub fn set_blend_color(&mut self, color: Color) {
+	/** Set the viewport to cover the whole of the framebuffer this pass renders
+	 * to, as given by [`Framebuffer::size`].
+	 *
+	 * # Errors
+	 * This function fails if the size of the framebuffer isn't known, as is
+	 * the case for the default framebuffer before it is first given one with
+	 * [`Device::resize_default_framebuffer`], or for the same reasons
+	 * [`set_viewport()`] would.
+	 *
+	 * [`Framebuffer::size`]: crate::Framebuffer::size
+	 * [`Device::resize_default_framebuffer`]: crate::Device::resize_default_framebuffer
+	 * [`set_viewport()`]: Self::set_viewport */
+	pub fn set_full_viewport(&mut self) -> Result<(), ViewportError> {
+		let (width, height) = self.framebuffer.size()
+			.ok_or(ViewportError::UnknownSize)?;
+		self.set_viewport(Viewport { x: 0, y: 0, width, height })
+	}
+
+	/** Set the scissor rectangle for all subsequent draw commands, or disable
+	 * the scissor test, with `None`. Fragments outside of the rectangle are
+	 * discarded.
+	 *
+	 * Every pass starts with the scissor test disabled.
+	 *
+	 * # Errors
+	 * This function fails, leaving the scissor rectangle as it was, for the
+	 * same reasons [`set_viewport()`] would, as the rectangle follows the same
+	 * limits as the viewport.
+	 *
+	 * [`set_viewport()`]: Self::set_viewport */
+	pub fn set_scissor(&mut self, rect: Option<Viewport>) -> Result<(), ViewportError> {
+		if let Some(rect) = &rect {
+			let limits = &self.information.limits;
+			check_viewport(rect, limits.max_viewport_width, limits.max_viewport_height)?;
+		}
+
+		self.scissor = rect;
+		self.scissor_setup = false;
+
+		Ok(())
+	}
+
+	/** Set whether the load operations of the framebuffer, which clear its
+	 * attachments before the first draw command in the pass, are restricted to
+	 * the scissor rectangle set at that point.
+	 *
+	 * By default, they are not, and clear the whole framebuffer regardless of
+	 * the scissor rectangle. */
+	pub fn set_scissored_load(&mut self, scissored: bool) {
+		self.scissored_load = scissored;
+	}
+
+	/** Sets the blend constant used by the [`BlendColor`] and
+	 * [`OneMinusBlendColor`] blend factors in the draws that follow. Every
+	 * pass starts out with opaque black, and pipelines whose blend factors
+	 * don't reference the constant are unaffected by it.
+	 *
+	 * [`BlendColor`]: crate::BlendFactor::BlendColor
+	 * [`OneMinusBlendColor`]: crate::BlendFactor::OneMinusBlendColor */
+	pub fn set_blend_constant(&mut self, color: Color) {
 		self.color_blend_constant = color;
 		self.blending_setup = false;
 	}
 
+	/** Sets the blend color as used by some of the blending modes. */
+	#[deprecated(note = "use set_blend_constant() instead")]
+	pub fn set_blend_color(&mut self, color: Color) {
+		self.set_blend_constant(color)
+	}
+
 	/** Set the reference value for stencil operations. */
 	pub fn set_stencil_reference(&mut self, reference: u8) {
 		self.stencil_reference = reference;
 		self.stencil_setup = false;
 	}
 
+	/** Set the window depth values the near and far planes of normalized
+	 * device coordinates get mapped to, for all subsequent draw commands.
+	 * Every pass starts out with the whole of the depth buffer, from `0.0` at
+	 * the near plane to `1.0` at the far plane.
+	 *
+	 * Both values get clamped to the `[0; 1]` range, and the near value may be
+	 * greater than the far one, which flips the depth of everything drawn
+	 * around. The range doesn't affect the values attachments are cleared to.
+	 *
+	 * # Panics
+	 * This function panics if either of the values is NaN. */
+	pub fn set_depth_range(&mut self, near: f32, far: f32) {
+		assert!(!near.is_nan() && !far.is_nan(),
+			"the depth range can't start or end at NaN");
+
+		self.depth_range = (clamp_depth(near), clamp_depth(far));
+		self.depth_range_setup = false;
+	}
+
+	/** Clears the stencil aspect of the framebuffer to the given value, in
+	 * between the draws of this pass, such as to reset the masks built up for
+	 * a portal before moving on to the next one.
+	 *
+	 * Like draws, the clear is restricted to the scissor rectangle, and only
+	 * touches the bits in the write mask of the current pipeline, the one of
+	 * its front facing stencil state, or all of them, for pipelines without
+	 * any depth and stencil state. */
+	pub fn clear_stencil(&mut self, value: u8) {
+		let _atoms = (
+			self.pipeline.acquire_read_guarded(),
+			self.framebuffer.acquire_write_guarded());
+
+		unsafe {
+			self.ensure_setup();
+
+			let gl = self.context.as_ref();
+			if self.pipeline.inner.depth_stencil.is_none() {
+				gl.stencil_mask(0xff);
+			}
+			gl.clear_stencil(i32::from(value));
+			gl.clear(glow::STENCIL_BUFFER_BIT);
+			self.check("clearing the stencil of");
+		}
+	}
+
+	/** Begins running the given query, which measures the commands issued
+	 * in this pass from this point on until [`end_query()`] gets called, or
+	 * until the pass ends, whichever comes first.
+	 *
+	 * # Panic
+	 * Only one query may be active in a pass at a time. This function panics
+	 * if another query, or this same one, is already active.
+	 *
+	 * [`end_query()`]: Self::end_query */
+	pub fn begin_query(&mut self, query: &'a Query) {
+		if let Some(active) = self.query {
+			panic!("tried to begin a {:?} query while a {:?} query is still \
+				active in the same render pass", query.kind, active.kind)
+		}
+
+		query.acquire_write();
+		unsafe {
+			self.context.begin_query(query.kind.as_opengl(), query.query);
+		}
+		self.query = Some(query);
+	}
+
+	/** Ends the query that is currently active in this pass, if any, after
+	 * which its result may be polled for. */
+	pub fn end_query(&mut self) {
+		let query = match self.query.take() {
+			Some(query) => query,
+			None => return
+		};
+
+		unsafe {
+			self.context.end_query(query.kind.as_opengl());
+		}
+		query.ended.set(true);
+		query.release_write();
+	}
+
 	pub fn set_pipeline(&mut self, pipeline: &'a RenderPipeline) {
 		self.pipeline = pipeline;
 		self.general_setup = false;
+
+		/* Vertex arrays belong to the pipeline they were set up for. */
+		self.draw_buffers_setup = false;
+
+		/* Both of these are part of the state of the pipeline. */
+		self.stencil_setup = false;
+		self.blending_setup = false;
 	}
 
 	/** Perform the setup of the pipeline for subsequent render command, if
@@ -147,12 +389,43 @@ impl<'a> RenderPass<'a> {
 	unsafe fn ensure_setup(&mut self) {
 		let gl = self.context.as_ref();
 		if !self.framebuffer_loaded {
+			/* The scissor state may have been left behind by a previous pass,
+			 * and it affects clears, so it has to be set up before loading. */
+			let scissor = if self.scissored_load { self.scissor } else { None };
+			apply_scissor(gl, scissor);
+			self.scissor_setup = scissor == self.scissor;
+
+			/* Clears get encoded to sRGB just like draws do. */
+			crate::srgb::set_srgb_writes(
+				gl,
+				&self.information.capabilities,
+				self.framebuffer.srgb_writes());
+
 			self.framebuffer.bind_and_load(gl);
+			self.framebuffer.track_writes();
 			self.framebuffer_loaded = true;
+
+			/* Loading opens up the write masks, which are part of the state
+			 * of the pipeline. */
+			self.general_setup = false;
+			self.stencil_setup = false;
+		}
+
+		if !self.scissor_setup {
+			apply_scissor(gl, self.scissor);
+			self.scissor_setup = true;
+		}
+
+		/* Entries of the group may have been replaced since it was bound. */
+		if let Some(group) = self.bind {
+			if group.revision.get() != self.bind_revision {
+				self.general_setup = false;
+			}
 		}
 
 		if !self.general_setup {
 			self.framebuffer.bind(gl);
+			self.framebuffer.select_draw_buffers(gl, self.pipeline.color_target_count());
 			self.pipeline.bind(gl);
 
 			let vertex = self.vertex.map(|vertex| vertex.as_raw_handle());
@@ -160,8 +433,15 @@ impl<'a> RenderPass<'a> {
 			if let Some(binder) = &self.bind {
 				binder.bind(
 					gl,
-					&self.information.features,
-					&self.pipeline.inner.program)
+					&self.information,
+					&self.pipeline.inner.program,
+					&self.bind_offsets);
+				self.bind_revision = binder.revision.get();
+
+				let mut usage = self.usage.borrow_mut();
+				if usage.enabled() {
+					binder.record_usage(&mut usage);
+				}
 			}
 
 			gl.bind_buffer(glow::ARRAY_BUFFER, vertex);
@@ -173,11 +453,19 @@ impl<'a> RenderPass<'a> {
 		if !self.draw_buffers_setup {
 			self.pipeline.vertex_array_setup(
 				gl,
+				&self.information,
 				self.vertex,
+				self.instance,
 				self.index);
 			self.draw_buffers_setup = true;
 		}
 
+		if !self.depth_range_setup {
+			let (near, far) = self.depth_range;
+			gl.depth_range_f32(near, far);
+			self.depth_range_setup = true;
+		}
+
 		if !self.stencil_setup {
 			self.pipeline.stencil_setup(gl, self.stencil_reference);
 			self.stencil_setup = true;
@@ -187,13 +475,88 @@ impl<'a> RenderPass<'a> {
 			self.pipeline.blending_setup(gl, self.color_blend_constant);
 			self.blending_setup = true;
 		}
+		self.check("setting up");
+	}
+
+	/** Issues a single indexed draw call for the given number of instances.
+	 * Contexts without instancing get one call per instance instead, which
+	 * only works because pipelines there can't have per-instance attributes,
+	 * leaving `gl_InstanceID` as the only thing the calls can't reproduce. */
+	unsafe fn draw_elements(
+		&self,
+		mode: u32,
+		count: i32,
+		kind: u32,
+		offset: i32,
+		instances: i32) {
+
+		let gl = self.context.as_ref();
+		if self.information.capabilities.instancing {
+			gl.draw_elements_instanced(mode, count, kind, offset, instances)
+		} else {
+			for _ in 0..instances {
+				gl.draw_elements(mode, count, kind, offset)
+			}
+		}
+	}
+
+	/** Issues a single non-indexed draw call for the given number of
+	 * instances, the same way [`draw_elements()`] does.
+	 *
+	 * [`draw_elements()`]: Self::draw_elements */
+	unsafe fn draw_arrays(
+		&self,
+		mode: u32,
+		first: i32,
+		count: i32,
+		instances: i32) {
+
+		let gl = self.context.as_ref();
+		if self.information.capabilities.instancing {
+			gl.draw_arrays_instanced(mode, first, count, instances)
+		} else {
+			for _ in 0..instances {
+				gl.draw_arrays(mode, first, count)
+			}
+		}
 	}
 
-	/** Actually performs the dispatch set up in this structure. */
+	/** Checks for errors raised by the given operation in this pass. */
+	unsafe fn check(&self, operation: &str) {
+		self.debug.check(
+			&self.context,
+			operation,
+			|| format!("pipeline {} on framebuffer {}",
+				self.pipeline.name(),
+				self.framebuffer.name()))
+	}
+
+	/** Actually performs the dispatch set up in this structure.
+	 *
+	 * # Errors
+	 * This function fails if no index buffer has been set, if the range is
+	 * reversed or runs past the end of the index buffer, or if either it, its
+	 * offset into the index buffer in bytes or the number of instances don't
+	 * fit in the signed 32-bit integers taken by OpenGL. Nothing gets drawn
+	 * when it does. */
 	pub fn draw_indexed(
 		&mut self,
 		indices: Range<u32>,
-		instances: u32) {
+		instances: u32) -> Result<(), DrawError> {
+
+		let index = self.index.ok_or(DrawError::MissingIndexBuffer)?;
+		let (count, offset) = index_draw_range(&indices, self.pipeline.index_len())?;
+		let instances = instance_count(instances)?;
+
+		let available = index.len() / self.pipeline.index_len();
+		if indices.end > available {
+			return Err(DrawError::InvalidRange {
+				what: format!("indices up to {} were requested, but the index \
+					buffer only holds {}",
+					indices.end,
+					available)
+			})
+		}
 
 		/* Lock the pipeline.
 		 *
@@ -204,30 +567,399 @@ impl<'a> RenderPass<'a> {
 		let _atoms = (
 			self.pipeline.acquire_read_guarded(),
 			self.vertex.as_ref().map(|buffer| buffer.acquire_read_guarded()),
-			self.index.as_ref().map(|buffer| buffer.acquire_read_guarded()),
+			self.instance.as_ref().map(|buffer| buffer.acquire_read_guarded()),
+			index.acquire_read_guarded(),
 			self.bind.as_ref().map(|bind| bind.acquire_read_guarded()));
 		self.pipeline.framebuffer_acquire_write(&self.framebuffer);
 
-		let check_i32 = |val|
-			i32::try_from(val).expect("value does not fit in an i32, as is \
-				required by the opengl interface");
-
 		/* Make sure the pipeline is setup correctly. */
 		unsafe {
 			self.ensure_setup();
 		}
 
-		let gl = self.context.as_ref();
 		unsafe {
-			gl.draw_elements_instanced(
+			self.draw_elements(
 				self.pipeline.drawing_mode(),
-				check_i32(indices.end) - check_i32(indices.start),
+				count,
 				self.pipeline.index_type(),
-				check_i32(indices.start * self.pipeline.index_len()),
-				check_i32(instances))
+				offset,
+				instances);
+			self.check("drawing with");
 		}
 
 		self.pipeline.framebuffer_release_write(&self.framebuffer);
+		Ok(())
+	}
+
+	/** Performs all of the given indexed dispatches, one after the other,
+	 * sharing the state set up in this structure.
+	 *
+	 * This is meant for issuing large numbers of small draws that only differ
+	 * in their ranges. The state of the pass is set up and checked only once
+	 * for the whole list, as opposed to once per call to [`draw_indexed()`],
+	 * and all of the index ranges get checked against the index buffer up
+	 * front, failing before anything gets drawn.
+	 *
+	 * # Implementation
+	 * The bindings we use don't expose `glMultiDrawElements` or the
+	 * `WEBGL_multi_draw` extension, so every command still turns into its own
+	 * draw call, issued in a tight loop with nothing else in between.
+	 *
+	 * [`draw_indexed()`]: Self::draw_indexed */
+	pub fn multi_draw_indexed(
+		&mut self,
+		draws: &[DrawIndexedCommand]) -> Result<(), DrawError> {
+
+		let index = self.index.ok_or(DrawError::MissingIndexBuffer)?;
+		check_draw_commands(
+			draws,
+			index.len() / self.pipeline.index_len(),
+			self.pipeline.index_len(),
+			self.information.capabilities.base_vertex)?;
+		if draws.is_empty() { return Ok(()) }
+
+		let _atoms = (
+			self.pipeline.acquire_read_guarded(),
+			self.vertex.as_ref().map(|buffer| buffer.acquire_read_guarded()),
+			self.instance.as_ref().map(|buffer| buffer.acquire_read_guarded()),
+			index.acquire_read_guarded(),
+			self.bind.as_ref().map(|bind| bind.acquire_read_guarded()));
+		self.pipeline.framebuffer_acquire_write(self.framebuffer);
+
+		unsafe {
+			self.ensure_setup();
+		}
+
+		let gl = self.context.as_ref();
+		let mode = self.pipeline.drawing_mode();
+		let kind = self.pipeline.index_type();
+		let len = self.pipeline.index_len();
+		for draw in draws {
+			let (count, offset) = index_draw_range(&draw.index_range, len)
+				.expect("the draws were checked before anything was drawn");
+			let instances = instance_count(draw.instances)
+				.expect("the draws were checked before anything was drawn");
+			unsafe {
+				if draw.base_vertex == 0 {
+					self.draw_elements(mode, count, kind, offset, instances)
+				} else {
+					gl.draw_elements_instanced_base_vertex(
+						mode,
+						count,
+						kind,
+						offset,
+						instances,
+						draw.base_vertex)
+				}
+			}
+		}
+		unsafe {
+			self.check("drawing with");
+		}
+
+		self.pipeline.framebuffer_release_write(self.framebuffer);
+		Ok(())
+	}
+
+	/** Performs a non-indexed dispatch of the given range of vertices, taken
+	 * in order from the vertex buffer, without touching the index buffer.
+	 *
+	 * Pipelines with an empty vertex buffer layout may be used to draw without
+	 * a vertex buffer at all, relying on `gl_VertexID` instead. Otherwise, this
+	 * function fails if no vertex buffer has been set, or if no instance buffer
+	 * has been set for a pipeline that expects one. */
+	pub fn draw(
+		&mut self,
+		vertices: Range<u32>,
+		instances: u32) -> Result<(), DrawError> {
+
+		let inner = &self.pipeline.inner;
+		if self.vertex.is_none() && !inner.vertex_layout.attributes.is_empty() {
+			return Err(DrawError::MissingVertexBuffer)
+		}
+		let instanced = inner.instance_layout.as_ref()
+			.map(|layout| !layout.attributes.is_empty())
+			.unwrap_or(false);
+		if self.instance.is_none() && instanced {
+			return Err(DrawError::MissingInstanceBuffer)
+		}
+		let (first, count) = vertex_draw_range(&vertices)?;
+		let instances = instance_count(instances)?;
+
+		/* Lock the pipeline. The index buffer is left out, since we're not
+		 * going to be using it. */
+		let _atoms = (
+			self.pipeline.acquire_read_guarded(),
+			self.vertex.as_ref().map(|buffer| buffer.acquire_read_guarded()),
+			self.instance.as_ref().map(|buffer| buffer.acquire_read_guarded()),
+			self.bind.as_ref().map(|bind| bind.acquire_read_guarded()));
+		self.pipeline.framebuffer_acquire_write(self.framebuffer);
+
+		/* Make sure the pipeline is setup correctly. */
+		unsafe {
+			self.ensure_setup();
+		}
+
+		unsafe {
+			self.draw_arrays(
+				self.pipeline.drawing_mode(),
+				first,
+				count,
+				instances);
+			self.check("drawing with");
+		}
+
+		self.pipeline.framebuffer_release_write(self.framebuffer);
+		Ok(())
+	}
+
+	/** Replays all of the commands recorded into the given bundle in this
+	 * pass, in order.
+	 *
+	 * Bundles start from a clean slate, so the buffers and group set in this
+	 * pass are unset before the bundle runs. Afterwards, the pass is left with
+	 * whatever state the bundle ended up setting, with its viewport, scissor
+	 * rectangle, blend constant and stencil reference carrying over to the
+	 * commands that follow. */
+	pub fn execute_bundle(&mut self, bundle: &'a RenderBundle) -> Result<(), DrawError> {
+		self.vertex = None;
+		self.instance = None;
+		self.index = None;
+		self.bind = None;
+		self.bind_offsets.clear();
+		self.general_setup = false;
+		self.draw_buffers_setup = false;
+
+		for command in &bundle.commands {
+			match command {
+				BundleCommand::SetPipeline(pipeline) =>
+					self.set_pipeline(pipeline),
+				BundleCommand::SetVertexBuffer(buffer) =>
+					self.set_vertex_buffer(buffer),
+				BundleCommand::SetInstanceBuffer(buffer) =>
+					self.set_instance_buffer(buffer),
+				BundleCommand::SetIndexBuffer(buffer) =>
+					self.set_index_buffer(buffer),
+				BundleCommand::SetBindGroup(group, offsets) if offsets.is_empty() =>
+					self.set_bind_group(group),
+				BundleCommand::SetBindGroup(group, offsets) =>
+					self.set_bind_group_with_offsets(group, offsets)
+						.expect("the offsets were checked when the bundle was \
+							recorded"),
+				BundleCommand::SetViewport(viewport) =>
+					self.set_viewport(*viewport)
+						.expect("the viewport was checked when the bundle was \
+							recorded"),
+				BundleCommand::SetScissor(rect) =>
+					self.set_scissor(*rect)
+						.expect("the scissor rectangle was checked when the \
+							bundle was recorded"),
+				BundleCommand::SetBlendConstant(color) =>
+					self.set_blend_constant(*color),
+				BundleCommand::SetStencilReference(reference) =>
+					self.set_stencil_reference(*reference),
+				BundleCommand::DrawIndexed { indices, instances } =>
+					self.draw_indexed(indices.clone(), *instances)?,
+				BundleCommand::MultiDrawIndexed(draws) =>
+					self.multi_draw_indexed(draws)?,
+				BundleCommand::Draw { vertices, instances } =>
+					self.draw(vertices.clone(), *instances)?,
+			}
+		}
+
+		Ok(())
+	}
+}
+impl<'a> Drop for RenderPass<'a> {
+	fn drop(&mut self) {
+		/* Queries can't outlive the pass they were begun in. */
+		self.end_query();
+
+		/* Blits outside of passes copy texels over as they are. */
+		if self.framebuffer_loaded {
+			unsafe {
+				crate::srgb::set_srgb_writes(
+					self.context.as_ref(),
+					&self.information.capabilities,
+					false);
+			}
+		}
+	}
+}
+
+/** A single indexed draw in a call to [`RenderPass::multi_draw_indexed`].
+ *
+ * [`RenderPass::multi_draw_indexed`]: RenderPass::multi_draw_indexed */
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct DrawIndexedCommand {
+	/** Range of indices to be drawn, counted in indices, not bytes. */
+	pub index_range: Range<u32>,
+	/** Value added to every index before the vertex gets fetched. Any value
+	 * other than zero requires the [`base_vertex`] capability.
+	 *
+	 * [`base_vertex`]: crate::Capabilities::base_vertex */
+	pub base_vertex: i32,
+	/** Number of instances to be drawn. */
+	pub instances: u32,
+}
+
+/** Count of indices and offset into the index buffer in bytes, as taken by
+ * OpenGL, of a draw of the given range of indices, each of which is `index_len`
+ * bytes long. */
+pub(crate) fn index_draw_range(indices: &Range<u32>, index_len: u32)
+	-> Result<(i32, i32), DrawError> {
+
+	if indices.start > indices.end {
+		return Err(DrawError::InvalidRange {
+			what: format!("the index range {:?} is reversed", indices)
+		})
+	}
+	let count = i32::try_from(indices.end - indices.start)
+		.map_err(|_| DrawError::InvalidRange {
+			what: format!("the index range {:?} holds more indices than fit \
+				in an i32", indices)
+		})?;
+	let offset = u64::from(indices.start) * u64::from(index_len);
+	let offset = i32::try_from(offset)
+		.map_err(|_| DrawError::InvalidRange {
+			what: format!("the index range {:?} starts {} bytes into the index \
+				buffer, which doesn't fit in an i32", indices, offset)
+		})?;
+
+	Ok((count, offset))
+}
+
+/** First vertex and count of vertices, as taken by OpenGL, of a draw of the
+ * given range of vertices. */
+pub(crate) fn vertex_draw_range(vertices: &Range<u32>) -> Result<(i32, i32), DrawError> {
+	if vertices.start > vertices.end {
+		return Err(DrawError::InvalidRange {
+			what: format!("the vertex range {:?} is reversed", vertices)
+		})
+	}
+	let first = i32::try_from(vertices.start);
+	let count = i32::try_from(vertices.end - vertices.start);
+	match (first, count) {
+		(Ok(first), Ok(count)) => Ok((first, count)),
+		_ => Err(DrawError::InvalidRange {
+			what: format!("the vertex range {:?} doesn't fit in an i32",
+				vertices)
+		})
+	}
+}
+
+/** Number of instances, as taken by OpenGL, of a draw of the given number of
+ * instances. */
+pub(crate) fn instance_count(instances: u32) -> Result<i32, DrawError> {
+	i32::try_from(instances).map_err(|_| DrawError::InvalidRange {
+		what: format!("{} instances don't fit in an i32", instances)
+	})
+}
+
+/** Checks whether all of the given commands can be drawn from an index buffer
+ * holding the given number of indices, each of which is `index_len` bytes
+ * long. */
+pub(crate) fn check_draw_commands(
+	draws: &[DrawIndexedCommand],
+	indices: u32,
+	index_len: u32,
+	base_vertex: bool) -> Result<(), DrawError> {
+
+	let mut end = 0;
+	for draw in draws {
+		index_draw_range(&draw.index_range, index_len)?;
+		instance_count(draw.instances)?;
+		if draw.base_vertex != 0 && !base_vertex {
+			return Err(DrawError::UnsupportedFeature {
+				what: "the current context can't offset indices by a base \
+					vertex".into()
+			})
+		}
+		end = u32::max(end, draw.index_range.end);
+	}
+	if end > indices {
+		return Err(DrawError::InvalidRange {
+			what: format!("indices up to {} were requested, but the index \
+				buffer only holds {}",
+				end,
+				indices)
+		})
+	}
+
+	Ok(())
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum DrawError {
+	#[error("The pipeline reads from a vertex buffer, but none has been set")]
+	MissingVertexBuffer,
+	#[error("The pipeline reads from an instance buffer, but none has been set")]
+	MissingInstanceBuffer,
+	#[error("The draw reads from an index buffer, but none has been set")]
+	MissingIndexBuffer,
+	#[error("The draw range is invalid: {what}")]
+	InvalidRange {
+		what: String
+	},
+	#[error("The draw uses a feature the context doesn't support: {what}")]
+	UnsupportedFeature {
+		what: String
+	},
+	#[error("The draw can't capture its outputs into the buffer: {what}")]
+	InvalidFeedbackOutput {
+		what: String
+	},
+}
+
+/** Width and height of the given viewport, as taken by OpenGL, failing if it
+ * is larger than the given maximum width and height, when they're known, or
+ * than fits in an `i32`. */
+pub(crate) fn check_viewport(
+	viewport: &Viewport,
+	max_width: Option<u32>,
+	max_height: Option<u32>) -> Result<(i32, i32), ViewportError> {
+
+	let check = |what: &str, value: u32, max: Option<u32>| {
+		let max = max.unwrap_or(i32::MAX as u32).min(i32::MAX as u32);
+		if value > max {
+			return Err(ViewportError::LimitExceeded {
+				what: format!("the viewport is {} {}, but it can be at most {}",
+					value,
+					what,
+					max)
+			})
+		}
+		Ok(value as i32)
+	};
+
+	Ok((
+		check("pixels wide", viewport.width, max_width)?,
+		check("pixels tall", viewport.height, max_height)?
+	))
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ViewportError {
+	#[error("The viewport exceeds the limits of the context: {what}")]
+	LimitExceeded {
+		what: String
+	},
+	#[error("The size of the framebuffer isn't known")]
+	UnknownSize,
+}
+
+/** Enables the scissor test with the given rectangle, or disables it. */
+unsafe fn apply_scissor(gl: &Context, rect: Option<Viewport>) {
+	match rect {
+		Some(rect) => {
+			gl.enable(glow::SCISSOR_TEST);
+			gl.scissor(
+				rect.x,
+				rect.y,
+				i32::try_from(rect.width).unwrap(),
+				i32::try_from(rect.height).unwrap());
+		},
+		None => gl.disable(glow::SCISSOR_TEST)
 	}
 }
 
@@ -260,3 +992,82 @@ pub struct RenderPassDescriptor<'a> {
 	pub framebuffer: &'a Framebuffer,
 }
 
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn command(index_range: Range<u32>, base_vertex: i32) -> DrawIndexedCommand {
+		DrawIndexedCommand { index_range, base_vertex, instances: 1 }
+	}
+
+	#[test]
+	fn draw_commands_must_fit_in_the_index_buffer() {
+		let draws = [command(0..6, 0), command(90..96, 0), command(6..12, 0)];
+		assert!(check_draw_commands(&draws, 96, 4, false).is_ok());
+		assert!(check_draw_commands(&draws, 95, 4, false).is_err());
+		assert!(check_draw_commands(&[], 0, 4, false).is_ok());
+
+		#[allow(clippy::reversed_empty_ranges)]
+		let reversed = [command(6..0, 0)];
+		assert!(check_draw_commands(&reversed, 96, 4, false).is_err());
+	}
+
+	#[test]
+	fn base_vertices_need_support() {
+		let draws = [command(0..6, 0), command(0..6, 4)];
+		assert!(check_draw_commands(&draws, 6, 4, true).is_ok());
+		assert!(check_draw_commands(&draws, 6, 4, false).is_err());
+	}
+
+	#[test]
+	fn index_offsets_are_computed_in_64_bits() {
+		assert_eq!(index_draw_range(&(3..9), 2).unwrap(), (6, 6));
+		assert_eq!(index_draw_range(&(0..0), 4).unwrap(), (0, 0));
+
+		/* The largest offset an i32 can hold, and one index past it. */
+		let last = i32::MAX as u32 / 4;
+		assert_eq!(index_draw_range(&(last..last), 4).unwrap(),
+			(0, last as i32 * 4));
+		assert!(index_draw_range(&(last + 1..last + 1), 4).is_err());
+
+		/* Multiplying these in 32 bits would have wrapped around to zero. */
+		assert!(index_draw_range(&(0x4000_0000..0x4000_0000), 4).is_err());
+		assert!(index_draw_range(&(0..u32::MAX), 1).is_err());
+
+		let draws = [command(u32::MAX / 2..u32::MAX / 2 + 1, 0)];
+		assert!(check_draw_commands(&draws, u32::MAX, 1, false).is_ok());
+		assert!(check_draw_commands(&draws, u32::MAX, 2, false).is_err());
+	}
+
+	#[test]
+	fn vertex_ranges_must_fit_in_an_i32() {
+		let max = i32::MAX as u32;
+		assert_eq!(vertex_draw_range(&(2..5)).unwrap(), (2, 3));
+		assert_eq!(vertex_draw_range(&(0..max)).unwrap(), (0, i32::MAX));
+		assert!(vertex_draw_range(&(0..max + 1)).is_err());
+		assert!(vertex_draw_range(&(max + 1..u32::MAX)).is_err());
+		#[allow(clippy::reversed_empty_ranges)]
+		let reversed = 5..2;
+		assert!(vertex_draw_range(&reversed).is_err());
+
+		assert_eq!(instance_count(max).unwrap(), i32::MAX);
+		assert!(instance_count(u32::MAX).is_err());
+	}
+
+	#[test]
+	fn viewports_must_fit_in_the_limits() {
+		let viewport = |width, height| Viewport { x: -4, y: 8, width, height };
+		assert_eq!(check_viewport(&viewport(640, 480), Some(640), Some(480)).unwrap(),
+			(640, 480));
+		assert!(check_viewport(&viewport(641, 480), Some(640), Some(480)).is_err());
+		assert!(check_viewport(&viewport(640, 481), Some(640), Some(480)).is_err());
+
+		/* Without limits, the viewport still has to fit in an i32. */
+		let max = i32::MAX as u32;
+		assert_eq!(check_viewport(&viewport(max, 1), None, None).unwrap(),
+			(i32::MAX, 1));
+		assert!(check_viewport(&viewport(max + 1, 1), None, None).is_err());
+		assert!(check_viewport(&viewport(1, u32::MAX), Some(u32::MAX), None).is_err());
+	}
+}
@@ -3,10 +3,16 @@ use std::rc::Rc;
 use glow::{HasContext, Context};
 use std::borrow::Cow;
 use crate::access::{AccessLock, UnitAccessLock};
-use crate::{VertexBuffer, IndexBuffer, Framebuffer, FramebufferVariants, Color};
+use crate::{Buffer, Framebuffer, FramebufferVariants, Color, Information, Version};
 use std::convert::TryFrom;
 use std::collections::HashMap;
-use std::cell::Cell;
+use std::cell::RefCell;
+use crate::blocks::BlockRegistry;
+use crate::debug::Debugger;
+use crate::vao::{AttributePointer, SharedVertexArrays, VertexArrayKey};
+use crate::registry::InterfaceRecorder;
+use crate::reflection::{PipelineReflection, ReflectedAttribute, ReflectedSampler, ReflectedBlock};
+use smallvec::SmallVec;
 
 /** Wrapper around a shader program used in a render pipeline. */
 #[derive(Debug)]
@@ -15,8 +21,21 @@ pub(crate) struct RenderProgram {
 	pub(crate) program: <Context as HasContext>::Program,
 	/** Set of active vertex attributes exposed by the program. */
 	pub(crate) attributes: HashMap<String, ActiveBinding>,
+	/** Locations of the active vertex attributes, looked up once, when the
+	 * program is linked, by the name of the attribute. */
+	pub(crate) locations: HashMap<String, u32>,
 	/** Set of active uniforms exposed by the program. */
 	pub(crate) uniforms: HashMap<String, ActiveBinding>,
+	/** Binding points the uniform blocks of the program were pinned to when
+	 * it was linked, by the name of the block. These never change, so binding
+	 * a buffer to one of these blocks takes no further setup. */
+	pub(crate) pinned: HashMap<String, u32>,
+	/** Indices and last assigned binding points of the uniform blocks that
+	 * could not be pinned, by the name of the block, so that they only ever
+	 * have to be looked up and assigned once. */
+	pub(crate) blocks: RefCell<HashMap<String, BlockBinding>>,
+	/** Active interface of the program, as gathered when it was linked. */
+	pub(crate) reflection: PipelineReflection,
 }
 impl RenderProgram {
 	/** Creates a new instance of this structure from the given raw program
@@ -25,33 +44,138 @@ impl RenderProgram {
 		gl: &Context,
 		program: <Context as HasContext>::Program) -> Self {
 
-		let attributes = 0..gl.get_active_attributes(program);
-		let uniforms = 0..gl.get_active_uniforms(program);
-
-		Self {
+		let attributes = (0..gl.get_active_attributes(program))
+			.filter_map(|index| gl.get_active_attribute(program, index))
+			.map(|attribute| (
+				attribute.name,
+				ActiveBinding {
+					kind: attribute.atype,
+					size: u32::try_from(attribute.size).unwrap()
+				}
+			))
+			.collect::<HashMap<_, _>>();
+		let locations = attributes.keys()
+			.filter_map(|name| gl.get_attrib_location(program, name)
+				.map(|location| (name.clone(), location)))
+			.collect();
+		let uniforms = (0..gl.get_active_uniforms(program))
+			.filter_map(|index| gl.get_active_uniform(program, index))
+			.map(|attribute| (
+				attribute.name,
+				ActiveBinding {
+					kind: attribute.utype,
+					size: u32::try_from(attribute.size).unwrap()
+				}
+			))
+			.collect::<HashMap<_, _>>();
+
+		let mut program = Self {
 			program,
-			attributes: attributes.into_iter()
-				.filter_map(|index| gl.get_active_attribute(program, index))
-				.map(|attribute| (
-					attribute.name,
-					ActiveBinding {
-						kind: attribute.atype,
-						size: u32::try_from(attribute.size).unwrap()
-					}
-				))
-				.collect(),
-			uniforms: uniforms.into_iter()
-				.filter_map(|index| gl.get_active_uniform(program, index))
-				.map(|attribute| (
-					attribute.name,
-					ActiveBinding {
-						kind: attribute.utype,
-						size: u32::try_from(attribute.size).unwrap()
-					}
-				))
-				.collect(),
+			attributes,
+			locations,
+			uniforms,
+			pinned: Default::default(),
+			blocks: Default::default(),
+			reflection: Default::default(),
+		};
+		program.reflect();
+		program
+	}
+
+	/** Gathers the active attributes and samplers of the program into its
+	 * reflection, sorted by location and by name, respectively. Uniform blocks
+	 * are added as they get pinned. */
+	fn reflect(&mut self) {
+		let mut attributes = self.attributes.iter()
+			.filter_map(|(name, binding)| {
+				let (kind, components, columns) =
+					crate::reflection::shader_type(binding.kind)?;
+				Some(ReflectedAttribute {
+					name: name.clone(),
+					location: self.locations.get(name).copied(),
+					kind,
+					components,
+					columns,
+				})
+			})
+			.collect::<Vec<_>>();
+		attributes.sort_by(|a, b| (a.location, &a.name).cmp(&(b.location, &b.name)));
+
+		let mut samplers = self.uniforms.iter()
+			.filter_map(|(name, binding)| {
+				let (dimension, shadow) =
+					crate::reflection::sampler_type(binding.kind)?;
+				Some(ReflectedSampler {
+					name: name.clone(),
+					dimension,
+					shadow,
+					count: binding.size.max(1),
+				})
+			})
+			.collect::<Vec<_>>();
+		samplers.sort_by(|a, b| a.name.cmp(&b.name));
+
+		self.reflection.attributes = attributes;
+		self.reflection.samplers = samplers;
+	}
+
+	/** Takes the given attributes to be at the given locations, unless the
+	 * program already reported a location for them. */
+	pub fn assume_locations<'a, I>(&mut self, locations: I)
+		where I: IntoIterator<Item = (&'a str, u32)> {
+
+		for (name, location) in locations {
+			self.locations.entry(name.to_owned()).or_insert(location);
 		}
+		self.reflect();
 	}
+
+	/** Pins the uniform blocks with the given names to the binding points the
+	 * registry has for them. Blocks that aren't active in the program are left
+	 * alone, as are the ones the registry had no binding point left for. */
+	pub unsafe fn pin_blocks<'a, I>(
+		&mut self,
+		gl: &Context,
+		names: I,
+		registry: &mut BlockRegistry)
+		where I: IntoIterator<Item = &'a String> {
+
+		for name in names {
+			let seen = self.reflection.uniform_blocks.iter()
+				.any(|block| &block.name == name);
+			if seen { continue }
+
+			let index = match gl.get_uniform_block_index(self.program, name) {
+				Some(index) => index,
+				None => continue
+			};
+			let binding = registry.pin(name);
+			let size = gl.get_active_uniform_block_parameter_i32(
+				self.program,
+				index,
+				glow::UNIFORM_BLOCK_DATA_SIZE);
+			self.reflection.uniform_blocks.push(ReflectedBlock {
+				name: name.clone(),
+				binding,
+				size: u32::try_from(size).unwrap_or(0)
+			});
+
+			if let Some(binding) = binding {
+				gl.uniform_block_binding(self.program, index, binding);
+				self.pinned.insert(name.clone(), binding);
+			}
+		}
+	}
+}
+
+/** Block index of a uniform block that could not be pinned, along with the
+ * binding point last assigned to it, if any. */
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub(crate) struct BlockBinding {
+	/** Index of the block in the program, or `None` if it isn't active. */
+	pub index: Option<u32>,
+	/** Binding point last assigned to the block, if it ever was. */
+	pub binding: Option<u32>,
 }
 
 /** Information on an active shader program binding.
@@ -71,39 +195,67 @@ pub(crate) struct ActiveBinding {
 	/** The size of this binding, in bytes. */
 	pub size: u32,
 }
+impl ActiveBinding {
+	/** Number of consecutive locations taken up by this binding, when used as
+	 * a vertex attribute. Matrices take up one location per column. */
+	pub(crate) fn locations(&self) -> u32 {
+		let columns = match self.kind {
+			glow::FLOAT_MAT2 | glow::FLOAT_MAT2x3 | glow::FLOAT_MAT2x4 => 2,
+			glow::FLOAT_MAT3 | glow::FLOAT_MAT3x2 | glow::FLOAT_MAT3x4 => 3,
+			glow::FLOAT_MAT4 | glow::FLOAT_MAT4x2 | glow::FLOAT_MAT4x3 => 4,
+			_ => 1
+		};
+
+		columns * self.size.max(1)
+	}
+}
 
 #[derive(Debug)]
 pub(crate) struct InnerRenderPipeline {
 	/** Handle to the shared context. */
 	pub(crate) context: Rc<Context>,
+	/** Shared error checking and labeling state. */
+	pub(crate) debug: Rc<Debugger>,
+	/** Label used to refer to this pipeline in diagnostics. */
+	pub(crate) label: RefCell<Option<Rc<str>>>,
 	/** Access control structure. */
 	pub(crate) access: UnitAccessLock,
 	/** Shader program, linked from the shaders specified in the descriptor. */
 	pub(crate) program: RenderProgram,
-	/** Vertex Array Object specifying the layout of the vertex buffer. */
-	pub(crate) vao: Cell<Option<<Context as HasContext>::VertexArray>>,
+	/** Vertex Array Objects of the device, which this pipeline shares with
+	 * every other pipeline whose attributes have the same pointers. */
+	pub(crate) vertex_arrays: Rc<SharedVertexArrays>,
+	/** Where the active attributes of this pipeline are read from. */
+	pub(crate) attribute_pointers: Rc<[AttributePointer]>,
 	/** Layout of the vertex buffer. */
 	pub(crate) vertex_layout: OwnedVertexBufferLayout,
+	/** Layout of the instance buffer, if any. */
+	pub(crate) instance_layout: Option<OwnedVertexBufferLayout>,
 	/** Reference to the vertex shader used in this pipeline. */
 	pub(crate) vertex_shader: VertexShader,
 	/** Reference to the fragment shader used in this pipeline, if any. */
 	pub(crate) fragment_shader: Option<FragmentShader>,
-	/** State information for the primitive assembler. */
+	/** State information for the primitive assembler, with the line width
+	 * already clamped to the range supported by the implementation. */
 	pub(crate) primitive_state: PrimitiveState,
+	/** Whether the context has a switch for letting the vertex stage size
+	 * points. Only core profiles have one, everywhere else it's always on. */
+	pub(crate) point_size_switch: bool,
 	/** The effect of draw calls on the depth and stencil aspects of the output
 	 * target, if any. */
 	pub(crate) depth_stencil: Option<DepthStencilState>,
-	/** The operations to be applied to the color targets of this pipeline. */
-	pub(crate) color_target_state: ColorTargetState
+	/** The operations to be applied to each of the color targets of this
+	 * pipeline, in order. */
+	pub(crate) color_targets: SmallVec<[ColorTargetState; 4]>,
+	/** Interface registry this pipeline was recorded into, along with its
+	 * position in it, if it was recorded. */
+	pub(crate) registration: Option<(Rc<InterfaceRecorder>, usize)>,
 }
 impl Drop for InnerRenderPipeline {
 	fn drop(&mut self) {
 		unsafe {
 			let _atom = self.access.acquire_write_guarded();
 			self.context.delete_program(self.program.program);
-			if let Some(vao) = self.vao.replace(None) {
-				self.context.delete_vertex_array(vao);
-			}
 		}
 	}
 }
@@ -137,24 +289,79 @@ impl AccessLock for RenderPipeline {
 	}
 }
 impl RenderPipeline {
+	/** The label used to refer to this pipeline in diagnostics, if any. */
+	pub fn label(&self) -> Option<String> {
+		self.inner.label.borrow().as_deref().map(str::to_owned)
+	}
+
+	/** The label of this pipeline, shared rather than copied, for passes to
+	 * hand over to the pipeline lock every time they get started. */
+	pub(crate) fn shared_label(&self) -> Option<Rc<str>> {
+		self.inner.label.borrow().clone()
+	}
+
+	/** Changes the label used to refer to this pipeline in diagnostics. The
+	 * label is also attached to the program object of the pipeline, for
+	 * debuggers to pick up, when the [`debug_labels`] capability is present.
+	 *
+	 * [`debug_labels`]: crate::Capabilities::debug_labels */
+	pub fn set_label(&self, label: &str) {
+		unsafe {
+			self.inner.debug.label(
+				&self.inner.context,
+				glow::PROGRAM,
+				self.inner.program.program,
+				label);
+		}
+		*self.inner.label.borrow_mut() = Some(Rc::from(label));
+
+		if let Some((interfaces, index)) = &self.inner.registration {
+			interfaces.rename_pipeline(*index, self.name())
+		}
+	}
+
+	/** Name of this pipeline in diagnostics, which is either its label or the
+	 * name of its program object, if it has no label. */
+	pub(crate) fn name(&self) -> String {
+		crate::debug::name(
+			self.inner.label.borrow().as_deref(),
+			self.inner.program.program)
+	}
+
+	/** The active uniform blocks, samplers and vertex attributes of this
+	 * pipeline, as reported by the implementation when it was linked. */
+	pub fn reflection(&self) -> &PipelineReflection {
+		&self.inner.program.reflection
+	}
+
+	/** The effect of draw calls with this pipeline on the depth and stencil
+	 * aspects of the output target, if any. */
+	pub fn depth_stencil(&self) -> Option<&DepthStencilState> {
+		self.inner.depth_stencil.as_ref()
+	}
+
 	/** Bind this pipeline for use in OpenGL. */
 	pub(crate) unsafe fn bind(&self, gl: &Context) {
 		gl.use_program(Some(self.inner.program.program));
 
 		/* Set up culling. */
-		match self.inner.primitive_state.front_face {
-			FrontFace::Ccw => gl.front_face(glow::CCW),
-			FrontFace::Cw => gl.front_face(glow::CW)
-		}
-		match self.inner.primitive_state.cull_mode {
-			CullMode::None => gl.disable(glow::CULL_FACE),
-			CullMode::Back => {
+		gl.front_face(self.inner.primitive_state.front_face.as_opengl());
+		match self.inner.primitive_state.cull_mode.as_opengl() {
+			Some(face) => {
 				gl.enable(glow::CULL_FACE);
-				gl.cull_face(glow::BACK)
+				gl.cull_face(face)
 			},
-			CullMode::Front => {
-				gl.enable(glow::CULL_FACE);
-				gl.cull_face(glow::FRONT)
+			None => gl.disable(glow::CULL_FACE)
+		}
+
+		/* Set up line and point rasterization. Every pipeline sets these, even
+		 * when it draws neither, so that no pipeline leaks them to the next. */
+		gl.line_width(self.inner.primitive_state.line_width);
+		if self.inner.point_size_switch {
+			if self.inner.primitive_state.program_point_size {
+				gl.enable(glow::PROGRAM_POINT_SIZE)
+			} else {
+				gl.disable(glow::PROGRAM_POINT_SIZE)
 			}
 		}
 
@@ -167,12 +374,28 @@ impl RenderPipeline {
 			gl.disable(glow::DEPTH_TEST)
 		}
 
-		/* Set up color masking. */
-		gl.color_mask(
-			self.inner.color_target_state.write_mask.contains(ColorWrite::RED),
-			self.inner.color_target_state.write_mask.contains(ColorWrite::GREEN),
-			self.inner.color_target_state.write_mask.contains(ColorWrite::BLUE),
-			self.inner.color_target_state.write_mask.contains(ColorWrite::ALPHA));
+		/* Set up color masking, for all of the targets at once when they
+		 * share their state, or for each one of them otherwise. */
+		let mask = |state: &ColorTargetState| (
+			state.write_mask.contains(ColorWrite::RED),
+			state.write_mask.contains(ColorWrite::GREEN),
+			state.write_mask.contains(ColorWrite::BLUE),
+			state.write_mask.contains(ColorWrite::ALPHA));
+		match shared_target_state(&self.inner.color_targets) {
+			Some(state) => {
+				let (red, green, blue, alpha) = mask(&state);
+				gl.color_mask(red, green, blue, alpha)
+			},
+			None => for (i, state) in (0u32..).zip(&self.inner.color_targets) {
+				let (red, green, blue, alpha) = mask(state);
+				gl.color_mask_draw_buffer(i, red, green, blue, alpha)
+			}
+		}
+	}
+
+	/** Number of color targets written to by this pipeline. */
+	pub(crate) fn color_target_count(&self) -> usize {
+		self.inner.color_targets.len()
 	}
 
 	/** Checks whether the depth aspect is written to by this pipeline. */
@@ -187,33 +410,7 @@ impl RenderPipeline {
 	/** Checks whether the stencil aspect is written to by this pipeline. */
 	fn stencil_write_enabled(&self) -> bool {
 		if let Some(ds) = self.inner.depth_stencil {
-			let masked = ds.stencil.write_mask == 0;
-
-			let kept_pass = ds.stencil.pass_op == StencilOperation::Keep;
-			let kept_fail = ds.stencil.fail_op == StencilOperation::Keep;
-			let kept_dfal = ds.stencil.depth_fail_op == StencilOperation::Keep;
-
-			let kept = match ds.stencil.compare {
-				CompareFunction::Always =>
-					/* We can ignore what the fail operation does if the test is
-					 * never set to fail. We only check for the other two. */
-					kept_dfal && kept_pass,
-				CompareFunction::Never =>
-					/* We can ignore what both the pass and depth fail
-					 * operations do, because the test is never going to pass in
-					 * the first place. We only check for what the fail
-					 * operation does. */
-					kept_fail,
-				_ =>
-					/* The compare function doesn't let us disregard any of the
-					 * operations, so they all must be set to keep. */
-					kept_pass && kept_fail && kept_dfal
-			};
-
-			/* We consider writing to the stencil aspect to be enabled if both
-			 * the write mask is non-zero and any of the used operations are set
-			 * to write to the stencil buffer. */
-			!kept && !masked
+			stencil_writes(&ds.front) || stencil_writes(&ds.back)
 		} else {
 			false
 		}
@@ -276,17 +473,14 @@ impl RenderPipeline {
 	 * is done separately from the rest of the setup, which is done in the
 	 * [`bind()`] function. */
 	pub(crate) unsafe fn stencil_setup(&self, gl: &Context, reference: u8) {
-		if let Some(DepthStencilState { stencil, .. }) = self.inner.depth_stencil {
+		if let Some(ds) = self.inner.depth_stencil {
 			gl.enable(glow::STENCIL_TEST);
-			gl.stencil_mask(u32::from(stencil.write_mask));
-			gl.stencil_func(
-				stencil.compare.as_opengl(),
-				i32::from(reference),
-				u32::from(stencil.read_mask));
-			gl.stencil_op(
-				stencil.fail_op.as_opengl(),
-				stencil.depth_fail_op.as_opengl(),
-				stencil.pass_op.as_opengl())
+			if ds.symmetric() {
+				apply_stencil(gl, glow::FRONT_AND_BACK, &ds.front, reference)
+			} else {
+				apply_stencil(gl, glow::FRONT, &ds.front, reference);
+				apply_stencil(gl, glow::BACK, &ds.back, reference)
+			}
 		} else {
 			gl.disable(glow::STENCIL_TEST);
 		}
@@ -298,58 +492,111 @@ impl RenderPipeline {
 	 * is done separately from the rest of the setup, which is done in the
 	 * [`bind()`] function. */
 	pub(crate) unsafe fn blending_setup(&self, gl: &Context, constant: Color) {
-		let state = &self.inner.color_target_state;
-
-		let alpha_required = !state.alpha_blend.may_be_skipped();
-		let color_required = !state.color_blend.may_be_skipped();
-		let required = alpha_required || color_required;
-
-		if required {
-			gl.enable(glow::BLEND);
+		let targets = &self.inner.color_targets;
+
+		/* The constant only matters to the factors that reference it. */
+		let constant_used = targets.iter()
+			.filter(|state| state.blends())
+			.any(|state| state.color_blend.uses_constant()
+				|| state.alpha_blend.uses_constant());
+		if constant_used {
 			gl.blend_color(
 				constant.red,
 				constant.green,
 				constant.blue,
 				constant.alpha);
+		}
 
-			/* Set up the blend factors. */
-			gl.blend_func_separate(
-				state.color_blend.src_factor.as_opengl(),
-				state.color_blend.dst_factor.as_opengl(),
-				state.alpha_blend.src_factor.as_opengl(),
-				state.alpha_blend.dst_factor.as_opengl());
-
-			/* Set up the blend equations. */
-			gl.blend_equation_separate(
-				state.color_blend.operation.as_opengl(),
-				state.alpha_blend.operation.as_opengl());
-		} else {
-			gl.disable(glow::BLEND);
+		match shared_target_state(targets) {
+			Some(state) if state.blends() => {
+				gl.enable(glow::BLEND);
+				gl.blend_func_separate(
+					state.color_blend.src_factor.as_opengl(),
+					state.color_blend.dst_factor.as_opengl(),
+					state.alpha_blend.src_factor.as_opengl(),
+					state.alpha_blend.dst_factor.as_opengl());
+				gl.blend_equation_separate(
+					state.color_blend.operation.as_opengl(),
+					state.alpha_blend.operation.as_opengl());
+			},
+			Some(_) => gl.disable(glow::BLEND),
+			None => for (i, state) in (0u32..).zip(targets) {
+				if !state.blends() {
+					gl.disable_draw_buffer(glow::BLEND, i);
+					continue
+				}
+
+				gl.enable_draw_buffer(glow::BLEND, i);
+				gl.blend_func_separate_draw_buffer(
+					i,
+					state.color_blend.src_factor.as_opengl(),
+					state.color_blend.dst_factor.as_opengl(),
+					state.alpha_blend.src_factor.as_opengl(),
+					state.alpha_blend.dst_factor.as_opengl());
+				gl.blend_equation_separate_draw_buffer(
+					i,
+					state.color_blend.operation.as_opengl(),
+					state.alpha_blend.operation.as_opengl());
+			}
 		}
 	}
 
 	/** Sets up the vertex array state of the pipeline.
 	 *
 	 * This part of the setup requires previous knowledge of exactly which
-	 * buffers are going to be used for drawing. This forces us to configure the
-	 * VAO right before its use in the pipeline. */
+	 * buffers are going to be used for drawing. Vertex arrays are cached by the
+	 * attribute pointers and by the vertex and instance buffers they point to,
+	 * so going back to buffers this pipeline, or any other pipeline with the
+	 * same attribute pointers, has been used with recently only takes binding
+	 * their vertex array again, along with the index buffer, which is not part
+	 * of the key.
+	 *
+	 * Contexts without vertex array objects get the attributes specified
+	 * straight into the global vertex array state instead, after disabling
+	 * whatever attributes were left enabled by the last pipeline. */
 	pub(crate) unsafe fn vertex_array_setup(
 		&self,
 		gl: &Context,
-		vertex_buffer: Option<&VertexBuffer>,
-		index_buffer: Option<&IndexBuffer>) {
-
-		/* Create a new VAO and delete the old one. */
-		let vao = gl.create_vertex_array()
-			.expect("could not create clean vertex array for pipeline \
-				setup");
-		if let Some(old) = self.inner.vao.replace(Some(vao)) {
-			gl.delete_vertex_array(old);
-		}
+		information: &Information,
+		vertex_buffer: Option<&Buffer>,
+		instance_buffer: Option<&Buffer>,
+		index_buffer: Option<&Buffer>) {
 
-		/* Bind the new vertex array so that we get a clean namespace right
-		 * away, even if we error out. */
-		gl.bind_vertex_array(Some(vao));
+		let index_buffer = index_buffer.map(|buffer| buffer.inner.buffer);
+		if information.capabilities.vertex_arrays {
+			let key = VertexArrayKey::new(
+				self.inner.attribute_pointers.clone(),
+				vertex_buffer.map(|buffer| Rc::downgrade(&buffer.inner)),
+				instance_buffer.map(|buffer| Rc::downgrade(&buffer.inner)));
+
+			let mut evicted = Vec::new();
+			let cached = self.inner.vertex_arrays.cache.get(&key, &mut evicted);
+			for vao in evicted {
+				gl.delete_vertex_array(vao);
+			}
+			if let Some(vao) = cached {
+				/* Binding to `ELEMENT_ARRAY_BUFFER` by itself is enough to
+				 * make the VAO point to it. */
+				gl.bind_vertex_array(Some(vao));
+				gl.bind_buffer(glow::ELEMENT_ARRAY_BUFFER, index_buffer);
+				return
+			}
+
+			let vao = gl.create_vertex_array()
+				.expect("could not create clean vertex array for pipeline \
+					setup");
+			if let Some(old) = self.inner.vertex_arrays.cache.insert(key, vao) {
+				gl.delete_vertex_array(old);
+			}
+
+			/* Bind the new vertex array so that we get a clean namespace right
+			 * away, even if we error out. */
+			gl.bind_vertex_array(Some(vao));
+		} else {
+			for location in 0..information.limits.max_vertex_attribs {
+				gl.disable_vertex_attrib_array(location);
+			}
+		}
 
 
 		/* Expecting to use attributes from a non-existent vertex buffer is
@@ -360,79 +607,82 @@ impl RenderPipeline {
 			panic!("tried to use a non-empty vertex buffer layout with no \
 				vertex buffer to be bound")
 		}
+		let instance_attributes = self.inner.instance_layout.as_ref()
+			.map(|layout| layout.attributes.len())
+			.unwrap_or(0);
+		if instance_buffer.is_none() && instance_attributes != 0 {
+			panic!("tried to use a non-empty instance buffer layout with no \
+				instance buffer to be bound")
+		}
 
-		let vertex_buffer = vertex_buffer.map(|buffer| buffer.inner.buffer);
-		let index_buffer = index_buffer.map(|buffer| buffer.inner.buffer);
+		let instancing = information.capabilities.instancing;
+		self.attribute_setup(gl, vertex_buffer, false, instancing);
+		if self.inner.instance_layout.is_some() {
+			self.attribute_setup(gl, instance_buffer, true, instancing);
+		}
+
+		/* Binding to `ELEMENT_ARRAY_BUFFER` by itself is enough to make the
+		 * VAO point to it. */
+		gl.bind_buffer(glow::ELEMENT_ARRAY_BUFFER, index_buffer);
+	}
+
+	/** Points the per-vertex or per-instance attributes of this pipeline to
+	 * the given buffer, in the currently bound VAO, advancing per-instance
+	 * ones once every instance. Divisors are left alone in contexts without
+	 * instancing, where pipelines can't have per-instance attributes to begin
+	 * with. */
+	unsafe fn attribute_setup(
+		&self,
+		gl: &Context,
+		buffer: Option<&Buffer>,
+		instanced: bool,
+		instancing: bool) {
 
 		/* Bind the buffer, then set up all of the vertex attributes to point to
 		 * it in the right places. We have to do this with the target buffer
 		 * bound to `ARRAY_BUFFER`, otherwise the implementation would likely
 		 * assume us to be giving it a location in host memory. */
-
-		gl.bind_buffer(glow::ARRAY_BUFFER, vertex_buffer);
-		for attribute in &self.inner.vertex_layout.attributes {
-			if let None = self.inner.program.attributes.get(attribute.binding.as_ref()) {
-				trace!("tried to bind to the inactive attribute \"{}\". data \
-					for this attribute will be missing",
-					attribute.binding);
-
-				continue
+		let buffer = buffer.map(|buffer| buffer.inner.buffer);
+		gl.bind_buffer(glow::ARRAY_BUFFER, buffer);
+		for pointer in self.inner.attribute_pointers.iter()
+			.filter(|pointer| pointer.instanced == instanced) {
+
+			gl.enable_vertex_attrib_array(pointer.location);
+			if pointer.integer {
+				gl.vertex_attrib_pointer_i32(
+					pointer.location,
+					pointer.components,
+					pointer.kind,
+					pointer.stride,
+					pointer.offset);
+			} else {
+				gl.vertex_attrib_pointer_f32(
+					pointer.location,
+					pointer.components,
+					pointer.kind,
+					pointer.normalized,
+					pointer.stride,
+					pointer.offset);
+			}
+			if instancing {
+				gl.vertex_attrib_divisor(pointer.location, u32::from(instanced));
 			}
-
-			let binding = gl.get_attrib_location(
-				self.inner.program.program,
-				&attribute.binding)
-				.expect("could not find binding previously determined to \
-					be active");
-
-			let kind = attribute.kind.as_opengl();
-			let count = attribute.components as _;
-
-			let offset = i32::try_from(attribute.offset)
-				.expect("invalid vertex attribute offset");
-			let stride = i32::try_from(self.inner.vertex_layout.array_stride)
-				.expect("invalid vertex buffer stride");
-
-			gl.enable_vertex_attrib_array(binding);
-			gl.vertex_attrib_pointer_f32(
-				binding,
-				count,
-				kind,
-				false,
-				stride,
-				offset)
 		}
-
-		/* Binding to `ELEMENT_ARRAY_BUFFER` by itself is enough to make the
-		 * VAO point to it. */
-		gl.bind_buffer(glow::ELEMENT_ARRAY_BUFFER, index_buffer);
 	}
 
 	/** OpenGL drawing mode for this pipeline. */
 	pub(crate) fn drawing_mode(&self) -> u32 {
-		match self.inner.primitive_state.topology {
-			PrimitiveTopology::LineList      => glow::LINES,
-			PrimitiveTopology::LineStrip     => glow::LINE_STRIP,
-			PrimitiveTopology::PointList     => glow::POINTS,
-			PrimitiveTopology::TriangleList  => glow::TRIANGLES,
-			PrimitiveTopology::TriangleStrip => glow::TRIANGLE_STRIP
-		}
+		self.inner.primitive_state.topology.as_opengl()
 	}
 
 	/** OpenGL type used for model indices in this pipeline. */
 	pub(crate) fn index_type(&self) -> u32 {
-		match self.inner.primitive_state.index_format {
-			IndexFormat::Uint16 => glow::UNSIGNED_SHORT,
-			IndexFormat::Uint32 => glow::UNSIGNED_INT
-		}
+		self.inner.primitive_state.index_format.as_opengl()
 	}
 
 	/** Number of bytes used by every model index in this pipeline. */
 	pub(crate) fn index_len(&self) -> u32 {
-		match self.inner.primitive_state.index_format {
-			IndexFormat::Uint16 => 2,
-			IndexFormat::Uint32 => 4
-		}
+		self.inner.primitive_state.index_format.len()
 	}
 }
 
@@ -452,6 +702,89 @@ pub struct RenderPipelineDescriptor<'a> {
 	 * target, if any. */
 	pub depth_stencil: Option<DepthStencilState>,
 }
+impl<'a> RenderPipelineDescriptor<'a> {
+	/** Describes a pipeline with the given vertex stage, the default primitive
+	 * state, and neither a fragment stage nor depth and stencil state, which
+	 * may then be filled in with the other functions in this structure.
+	 *
+	 * This is the exact same as writing the structure out by hand with those
+	 * values, either way of describing pipelines may be used. */
+	pub fn new(
+		shader: &'a VertexShader,
+		buffer: &'a VertexBufferLayout<'a>) -> Self {
+
+		Self {
+			vertex: VertexState {
+				shader,
+				buffer,
+				instance: None
+			},
+			primitive_state: Default::default(),
+			fragment: None,
+			depth_stencil: None
+		}
+	}
+
+	/** Reads per-instance attributes from a buffer with the given layout. */
+	pub fn with_instance(mut self, instance: &'a VertexBufferLayout<'a>) -> Self {
+		self.vertex.instance = Some(instance);
+		self
+	}
+
+	/** Uses the given primitive state in its entirety. */
+	pub fn with_primitive_state(mut self, primitive_state: PrimitiveState) -> Self {
+		self.primitive_state = primitive_state;
+		self
+	}
+
+	/** Uses the given primitive topology. */
+	pub fn with_topology(mut self, topology: PrimitiveTopology) -> Self {
+		self.primitive_state.topology = topology;
+		self
+	}
+
+	/** Uses the given index format. */
+	pub fn with_index_format(mut self, index_format: IndexFormat) -> Self {
+		self.primitive_state.index_format = index_format;
+		self
+	}
+
+	/** Uses the given face culling mode. */
+	pub fn with_cull_mode(mut self, cull_mode: CullMode) -> Self {
+		self.primitive_state.cull_mode = cull_mode;
+		self
+	}
+
+	/** Uses the given line width, in pixels. */
+	pub fn with_line_width(mut self, line_width: f32) -> Self {
+		self.primitive_state.line_width = line_width;
+		self
+	}
+
+	/** Lets the vertex stage set the size of points through `gl_PointSize`. */
+	pub fn with_program_point_size(mut self) -> Self {
+		self.primitive_state.program_point_size = true;
+		self
+	}
+
+	/** Uses the given fragment shader, writing to the color target with the
+	 * default color target state. */
+	pub fn with_fragment(self, shader: &'a FragmentShader) -> Self {
+		self.with_fragment_state(FragmentState::new(shader))
+	}
+
+	/** Uses the given fragment state in its entirety. */
+	pub fn with_fragment_state(mut self, fragment: FragmentState<'a>) -> Self {
+		self.fragment = Some(fragment);
+		self
+	}
+
+	/** Uses the given depth and stencil state. */
+	pub fn with_depth(mut self, depth_stencil: DepthStencilState) -> Self {
+		self.depth_stencil = Some(depth_stencil);
+		self
+	}
+}
 
 /** Describes the depth and stencil aspects in a render pipeline. */
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
@@ -460,8 +793,47 @@ pub struct DepthStencilState {
 	pub depth_write_enabled: bool,
 	/** Comparison function used to compare depth values in the depth test. */
 	pub depth_compare: CompareFunction,
-	/** Stencil state. */
-	pub stencil: StencilState,
+	/** Stencil state for front facing primitives. Points and lines always
+	 * count as front facing. */
+	pub front: StencilState,
+	/** Stencil state for back facing primitives. */
+	pub back: StencilState,
+}
+impl DepthStencilState {
+	/** Keep the fragments closest to the viewer, writing their depth, and
+	 * ignore the stencil buffer. */
+	pub const LESS_WRITE: Self = Self::new(
+		true,
+		CompareFunction::Less,
+		StencilState::IGNORE);
+
+	/** Keep every fragment, leaving both the depth and stencil buffers as they
+	 * are. */
+	pub const DISABLED: Self = Self::new(
+		false,
+		CompareFunction::Always,
+		StencilState::IGNORE);
+
+	/** Depth state with the given stencil state shared by both front and back
+	 * facing primitives. */
+	pub const fn new(
+		depth_write_enabled: bool,
+		depth_compare: CompareFunction,
+		stencil: StencilState) -> Self {
+
+		Self {
+			depth_write_enabled,
+			depth_compare,
+			front: stencil,
+			back: stencil
+		}
+	}
+
+	/** Whether both front and back facing primitives share their stencil
+	 * state. */
+	pub fn symmetric(&self) -> bool {
+		self.front == self.back
+	}
 }
 
 /** Describes stencil state in a render pipeline.
@@ -496,6 +868,57 @@ impl StencilState {
 		pass_op: StencilOperation::Keep
 	};
 }
+impl Default for StencilState {
+	fn default() -> Self {
+		Self::IGNORE
+	}
+}
+
+/** Checks whether draws with the given stencil state may write to the stencil
+ * buffer. */
+fn stencil_writes(stencil: &StencilState) -> bool {
+	let masked = stencil.write_mask == 0;
+
+	let kept_pass = stencil.pass_op == StencilOperation::Keep;
+	let kept_fail = stencil.fail_op == StencilOperation::Keep;
+	let kept_dfal = stencil.depth_fail_op == StencilOperation::Keep;
+
+	let kept = match stencil.compare {
+		CompareFunction::Always =>
+			/* We can ignore what the fail operation does if the test is never
+			 * set to fail. We only check for the other two. */
+			kept_dfal && kept_pass,
+		CompareFunction::Never =>
+			/* We can ignore what both the pass and depth fail operations do,
+			 * because the test is never going to pass in the first place. We
+			 * only check for what the fail operation does. */
+			kept_fail,
+		_ =>
+			/* The compare function doesn't let us disregard any of the
+			 * operations, so they all must be set to keep. */
+			kept_pass && kept_fail && kept_dfal
+	};
+
+	/* We consider writing to the stencil aspect to be enabled if both the
+	 * write mask is non-zero and any of the used operations are set to write
+	 * to the stencil buffer. */
+	!kept && !masked
+}
+
+/** Applies the given stencil state to the given faces. */
+unsafe fn apply_stencil(gl: &Context, face: u32, stencil: &StencilState, reference: u8) {
+	gl.stencil_mask_separate(face, u32::from(stencil.write_mask));
+	gl.stencil_func_separate(
+		face,
+		stencil.compare.as_opengl(),
+		i32::from(reference),
+		u32::from(stencil.read_mask));
+	gl.stencil_op_separate(
+		face,
+		stencil.fail_op.as_opengl(),
+		stencil.depth_fail_op.as_opengl(),
+		stencil.pass_op.as_opengl())
+}
 
 /** Operation to perform on the stencil value. */
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
@@ -517,21 +940,6 @@ pub enum StencilOperation {
 	/** Decrements stencil value by one, wrapping on underflow. */
 	DecrementWrap,
 }
-impl StencilOperation {
-	/** Get the OpenGL enum value for the current variant. */
-	fn as_opengl(&self) -> u32 {
-		match self {
-			Self::Keep => glow::KEEP,
-			Self::Zero => glow::ZERO,
-			Self::Replace => glow::REPLACE,
-			Self::IncrementClamp => glow::INCR,
-			Self::IncrementWrap => glow::INCR_WRAP,
-			Self::DecrementClamp => glow::DECR,
-			Self::DecrementWrap => glow::DECR_WRAP,
-			Self::Invert => glow::INVERT
-		}
-	}
-}
 
 /** Comparison function used for depth and stencil operations. */
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
@@ -553,32 +961,38 @@ pub enum CompareFunction {
 	/** Function always passes. */
 	Always,
 }
-impl CompareFunction {
-	/** Get the OpenGL enum value for the current variant. */
-	fn as_opengl(&self) -> u32 {
-		match self {
-			Self::Equal => glow::EQUAL,
-			Self::Always => glow::ALWAYS,
-			Self::Greater => glow::GREATER,
-			Self::GreaterEqual => glow::GEQUAL,
-			Self::Less => glow::LESS,
-			Self::LessEqual => glow::LEQUAL,
-			Self::NotEqual => glow::NOTEQUAL,
-			Self::Never => glow::NEVER
-		}
-	}
-}
 
 /** Describes the fragment process in a render pipeline. */
 pub struct FragmentState<'a> {
 	/** The compiled shader module for this stage. */
 	pub shader: &'a FragmentShader,
-	/** The color operations to be applied to all of the color targets.
+	/** The color operations to be applied to each of the color attachments of
+	 * the framebuffer, in order, with the fragment shader output at location
+	 * `i` going to the `i`-th attachment. Attachments past the end of this
+	 * list are left untouched by the pipeline.
 	 *
-	 * Normally this would be an array of valid color target states, one for
-	 * each target in the framebuffer. However, the implementation of OpenGL ES
-	 * backing this library does not support multiple color target states. */
-	pub targets: ColorTargetState
+	 * Targets may only differ from one another in contexts with the
+	 * [`indexed_draw_buffers`] capability. Everywhere else, every target has
+	 * to have the same state.
+	 *
+	 * [`indexed_draw_buffers`]: crate::Capabilities::indexed_draw_buffers */
+	pub targets: &'a [ColorTargetState]
+}
+impl<'a> FragmentState<'a> {
+	/** Describes a fragment stage with the given shader, writing to the first
+	 * color attachment with the default color target state. */
+	pub fn new(shader: &'a FragmentShader) -> Self {
+		Self {
+			shader,
+			targets: &[ColorTargetState::REPLACE]
+		}
+	}
+
+	/** Uses the given color target states, one for each attachment. */
+	pub fn with_targets(mut self, targets: &'a [ColorTargetState]) -> Self {
+		self.targets = targets;
+		self
+	}
 }
 
 /** Describes the color state of a render pipeline. */
@@ -591,6 +1005,41 @@ pub struct ColorTargetState {
 	/** Mask which enables or disables writes to different target channels. */
 	pub write_mask: ColorWrite
 }
+impl ColorTargetState {
+	/** Replace the contents of every channel of the target. */
+	pub const REPLACE: Self = Self {
+		alpha_blend: BlendState::REPLACE,
+		color_blend: BlendState::REPLACE,
+		write_mask: ColorWrite::ALL
+	};
+
+	/** Whether blending has to be enabled for this target. */
+	pub(crate) fn blends(&self) -> bool {
+		!self.alpha_blend.may_be_skipped() || !self.color_blend.may_be_skipped()
+	}
+}
+impl Default for ColorTargetState {
+	fn default() -> Self {
+		Self::REPLACE
+	}
+}
+
+/** The state shared by all of the given color targets, if they all have the
+ * same one, in which case it may be set up for every draw buffer at once.
+ * Pipelines without any color targets write to none of them. */
+pub(crate) fn shared_target_state(targets: &[ColorTargetState])
+	-> Option<ColorTargetState> {
+
+	match targets.split_first() {
+		None => Some(ColorTargetState {
+			write_mask: ColorWrite::empty(),
+			..ColorTargetState::REPLACE
+		}),
+		Some((first, rest)) if rest.iter().all(|state| state == first) =>
+			Some(*first),
+		Some(_) => None
+	}
+}
 
 bitflags::bitflags! {
 	/// Color write mask. Disabled color channels will not be written to.
@@ -641,6 +1090,12 @@ impl BlendState {
 	pub(crate) fn may_be_skipped(&self) -> bool {
 		*self == Self::REPLACE
 	}
+
+	/** Whether either of the factors of this blending state reference the
+	 * blend constant of the render pass. */
+	pub(crate) fn uses_constant(&self) -> bool {
+		self.src_factor.uses_constant() || self.dst_factor.uses_constant()
+	}
 }
 
 
@@ -665,25 +1120,9 @@ pub enum BlendFactor {
 	OneMinusBlendColor,
 }
 impl BlendFactor {
-	/** Get the OpenGL enum value for the current variant. */
-	fn as_opengl(&self) -> u32 {
-		match self {
-			Self::Zero => glow::ZERO,
-			Self::One => glow::ONE,
-			Self::SrcColor => glow::SRC_COLOR,
-			Self::OneMinusSrcColor => glow::ONE_MINUS_SRC_COLOR,
-			Self::DstColor => glow::DST_COLOR,
-			Self::OneMinusDstColor => glow::ONE_MINUS_DST_COLOR,
-			Self::SrcAlpha => glow::SRC_ALPHA,
-			Self::OneMinusSrcAlpha => glow::ONE_MINUS_SRC_ALPHA,
-			Self::DstAlpha => glow::DST_ALPHA,
-			Self::OneMinusDstAlpha => glow::ONE_MINUS_DST_ALPHA,
-			Self::BlendColor => glow::CONSTANT_COLOR,
-			Self::OneMinusBlendColor => glow::ONE_MINUS_CONSTANT_COLOR,
-			Self::SrcAlphaSaturated =>
-				/* Use the same as SrcAlpha. */
-				glow::SRC_ALPHA
-		}
+	/** Whether this factor is derived from the blend constant. */
+	fn uses_constant(&self) -> bool {
+		matches!(self, Self::BlendColor | Self::OneMinusBlendColor)
 	}
 }
 
@@ -699,18 +1138,6 @@ pub enum BlendOperation {
 	Min,
 	Max,
 }
-impl BlendOperation {
-	/** Get the OpenGL enum value for the current variant. */
-	fn as_opengl(&self) -> u32 {
-		match self {
-			Self::Add => glow::FUNC_ADD,
-			Self::Subtract => glow::FUNC_SUBTRACT,
-			Self::ReverseSubtract => glow::FUNC_REVERSE_SUBTRACT,
-			Self::Min => glow::MIN,
-			Self::Max => glow::MAX,
-		}
-	}
-}
 impl Default for BlendOperation {
 	fn default() -> Self {
 		Self::Add
@@ -730,10 +1157,20 @@ pub struct VertexState<'a> {
 	 * the one single VAO we are afforded for render commands.
 	 */
 	pub buffer: &'a VertexBufferLayout<'a>,
+	/** The layout of the instance buffer used in this pipeline, if any.
+	 *
+	 * Attributes in this layout are read from the buffer given to
+	 * [`RenderPass::set_instance_buffer`], and advance once per instance,
+	 * rather than once per vertex. Their names share the same namespace as
+	 * the ones in the vertex buffer layout.
+	 *
+	 * [`RenderPass::set_instance_buffer`]: crate::RenderPass::set_instance_buffer
+	 */
+	pub instance: Option<&'a VertexBufferLayout<'a>>,
 }
 
 /** Description of the layout of a vertex buffer. */
-#[derive(Debug, Clone, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub struct VertexBufferLayout<'a> {
 	/** The stride, in bytes, between elements of this buffer. */
 	pub array_stride: u32,
@@ -742,7 +1179,7 @@ pub struct VertexBufferLayout<'a> {
 }
 
 /** Owned version of the [`VertexBufferLayout`] structure. */
-#[derive(Debug)]
+#[derive(Debug, Eq, PartialEq, Hash)]
 pub(crate) struct OwnedVertexBufferLayout {
 	pub(crate) array_stride: u32,
 	pub(crate) attributes: Vec<VertexAttribute<'static>>,
@@ -756,7 +1193,8 @@ impl<'a> From<&'_ VertexBufferLayout<'a>> for OwnedVertexBufferLayout {
 					kind: attribute.kind,
 					components: attribute.components,
 					offset: attribute.offset,
-					binding: Cow::Owned(attribute.binding.to_string())
+					binding: Cow::Owned(attribute.binding.to_string()),
+					location: attribute.location
 				})
 				.collect()
 		}
@@ -764,7 +1202,7 @@ impl<'a> From<&'_ VertexBufferLayout<'a>> for OwnedVertexBufferLayout {
 }
 
 /** Description of a single vertex attribute. */
-#[derive(Debug, Clone, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub struct VertexAttribute<'a> {
 	/** Specifies the data type of each component in the vertex attribute. */
 	pub kind: VertexType,
@@ -787,24 +1225,39 @@ pub struct VertexAttribute<'a> {
 	 *
 	 * This leaves us with using variable names as the only viable option.
 	 */
-	pub binding: Cow<'a, str>
+	pub binding: Cow<'a, str>,
+	/** Location the attribute has to be bound to, if any.
+	 *
+	 * Attributes without one get bound to the location the device has for
+	 * their name, which is shared by every pipeline the device creates, so
+	 * that pipelines with the same vertex layout lay their attributes out the
+	 * same way. Locations declared in the shader, through the
+	 * `layout(location = N)` qualifier, take precedence over both. */
+	pub location: Option<u32>,
 }
 impl<'a> VertexAttribute<'a> {
 	/** Length in bytes of this attribute, in the buffer. */
 	pub fn len(&self) -> u32 {
-		let component = match self.kind {
-			VertexType::I8  | VertexType::U8  => 1,
-			VertexType::I16 | VertexType::U16 => 2,
-			VertexType::F16 => 2,
-			VertexType::F32 => 4,
-		};
+		let component = self.kind.size();
 		let multiplier = self.components as u32;
 
 		component * multiplier
 	}
 }
 
-/** Data types a vertex attribute may be of. */
+/** Data types a vertex attribute may be of.
+ *
+ * Attributes in the plain integer types, such as [`VertexType::U8`], get
+ * converted to floating point values as they are, so that a `255` in the
+ * buffer is seen as `255.0` by the shader. The normalized types map the range
+ * of their integers to `[0.0; 1.0]`, or to `[-1.0; 1.0]`, for signed ones,
+ * which is what colors are usually stored as. Only the pure integer types,
+ * such as [`VertexType::Uint8`], are seen by shaders as integers, and those
+ * must be declared as `int`, `uint` or vectors of them in the shader. Pure
+ * integer types are not available in legacy contexts.
+ *
+ * [`VertexType::U8`]: VertexType::U8
+ * [`VertexType::Uint8`]: VertexType::Uint8 */
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub enum VertexType {
 	/** Signed 8-bit integer. Corresponds to `GL_BYTE`. */
@@ -818,25 +1271,164 @@ pub enum VertexType {
 	/** Signed 16-bit floating point number. Corresponds to `GL_HALF_FLOAT`. */
 	F16,
 	/** Signed 32-bit floating point number. Corresponds to `GL_FLOAT`. */
-	F32
+	F32,
+	/** Unsigned 8-bit integer, normalized to `[0.0; 1.0]`. */
+	Unorm8,
+	/** Unsigned 16-bit integer, normalized to `[0.0; 1.0]`. */
+	Unorm16,
+	/** Signed 8-bit integer, normalized to `[-1.0; 1.0]`. */
+	Snorm8,
+	/** Signed 16-bit integer, normalized to `[-1.0; 1.0]`. */
+	Snorm16,
+	/** Unsigned 8-bit integer, seen by the shader as an integer. */
+	Uint8,
+	/** Unsigned 16-bit integer, seen by the shader as an integer. */
+	Uint16,
+	/** Unsigned 32-bit integer, seen by the shader as an integer. */
+	Uint32,
+	/** Signed 8-bit integer, seen by the shader as an integer. */
+	Sint8,
+	/** Signed 16-bit integer, seen by the shader as an integer. */
+	Sint16,
+	/** Signed 32-bit integer, seen by the shader as an integer. */
+	Sint32,
 }
 impl VertexType {
-	/** Returns the OpenGL enum the current variant is equivalent to. */
-	pub fn as_opengl(&self) -> u32 {
-		match self {
-			Self::I8 => glow::BYTE,
-			Self::U8 => glow::UNSIGNED_BYTE,
-			Self::I16 => glow::SHORT,
-			Self::U16 => glow::UNSIGNED_SHORT,
-			Self::F16 => glow::HALF_FLOAT,
-			Self::F32 => glow::FLOAT
+	/** Whether integers of this type get normalized as they get converted to
+	 * floating point values. */
+	pub fn is_normalized(&self) -> bool {
+		matches!(self,
+			Self::Unorm8 | Self::Unorm16 | Self::Snorm8 | Self::Snorm16)
+	}
+
+	/** Whether attributes of this type are seen as integers by the shader. */
+	pub fn is_integer(&self) -> bool {
+		matches!(self,
+			Self::Uint8 | Self::Uint16 | Self::Uint32
+				| Self::Sint8 | Self::Sint16 | Self::Sint32)
+	}
+}
+
+/** Where the attributes in the given vertex and instance buffer layouts get
+ * read from, for a program with the given attribute locations. Attributes the
+ * program doesn't have are left out, as there's nowhere to point them to.
+ *
+ * Pipelines whose attributes resolve to the same pointers set up their vertex
+ * arrays the same way, so they get to share them. */
+pub(crate) fn attribute_pointers(
+	locations: &HashMap<String, u32>,
+	vertex: &OwnedVertexBufferLayout,
+	instance: Option<&OwnedVertexBufferLayout>) -> Vec<AttributePointer> {
+
+	let layouts = std::iter::once((vertex, false))
+		.chain(instance.map(|layout| (layout, true)));
+
+	let mut pointers = Vec::new();
+	for (layout, instanced) in layouts {
+		let stride = i32::try_from(layout.array_stride)
+			.expect("invalid vertex buffer stride");
+		for attribute in &layout.attributes {
+			let location = match locations.get(attribute.binding.as_ref()) {
+				Some(location) => *location,
+				None => {
+					trace!(target: "gavle::pipeline",
+						"the attribute \"{}\" is inactive. data for this \
+						attribute will be missing",
+						attribute.binding);
+					continue
+				}
+			};
+
+			pointers.push(AttributePointer {
+				location,
+				kind: attribute.kind.as_opengl(),
+				components: attribute.components as _,
+				integer: attribute.kind.is_integer(),
+				normalized: attribute.kind.is_normalized(),
+				stride,
+				offset: i32::try_from(attribute.offset)
+					.expect("invalid vertex attribute offset"),
+				instanced
+			});
 		}
 	}
+
+	pointers
+}
+
+/** Checks that contexts of the given version can read every one of the given
+ * attributes. Legacy contexts have no integer attribute pointers, so pure
+ * integer attributes can't be read there. */
+pub(crate) fn check_integer_attributes(
+	attributes: &[&VertexAttribute],
+	version: &Version) -> Result<(), RenderPipelineError> {
+
+	let integer = attributes.iter()
+		.find(|attribute| attribute.kind.is_integer());
+	match integer {
+		Some(attribute) if version.profile.is_legacy() =>
+			Err(RenderPipelineError::UnsupportedFeature {
+				what: format!("attribute \"{}\" is of type {:?}, but {} has \
+					no support for integer attributes",
+					attribute.binding,
+					attribute.kind,
+					version)
+			}),
+		_ => Ok(())
+	}
+}
+
+/** Checks that every attribute in the given layout is aligned to the size of
+ * its components, and that it fits in a single vertex, listing the bindings of
+ * every one that doesn't. A stride of zero means the vertices are tightly
+ * packed, so there's no stride to check against. */
+pub(crate) fn check_vertex_layout(layout: &VertexBufferLayout)
+	-> Result<(), RenderPipelineError> {
+
+	let stride = layout.array_stride;
+	let offending = layout.attributes.iter()
+		.filter_map(|attribute| {
+			let size = attribute.kind.size();
+			let len = size * attribute.components.count();
+
+			if attribute.offset % size != 0 {
+				Some(format!("\"{}\" is at offset {}, which is not aligned \
+					to its {}-byte components",
+					attribute.binding,
+					attribute.offset,
+					size))
+			} else if stride != 0 && stride % size != 0 {
+				Some(format!("\"{}\" has {}-byte components, which the \
+					stride of {} is not aligned to",
+					attribute.binding,
+					size,
+					stride))
+			} else if stride != 0
+				&& u64::from(attribute.offset) + u64::from(len) > u64::from(stride) {
+				Some(format!("\"{}\" spans bytes {} to {}, past the stride \
+					of {}",
+					attribute.binding,
+					attribute.offset,
+					u64::from(attribute.offset) + u64::from(len),
+					stride))
+			} else {
+				None
+			}
+		})
+		.collect::<Vec<_>>();
+
+	if offending.is_empty() {
+		Ok(())
+	} else {
+		Err(RenderPipelineError::InvalidVertexLayout {
+			what: offending.join("; ")
+		})
+	}
 }
 
 /** Describes the state of primitive assembly and rasterization in a render
  * pipeline. */
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[derive(Debug, Copy, Clone, PartialEq, PartialOrd)]
 pub struct PrimitiveState {
 	/** The primitive topology used to interpret vertices. */
 	pub topology: PrimitiveTopology,
@@ -849,6 +1441,42 @@ pub struct PrimitiveState {
 	pub cull_mode: CullMode,
 	/** Controls the way each polygon is rasterized. */
 	pub polygon_mode: PolygonMode,
+	/** Width of rasterized lines, in pixels. Only lines wider than a single
+	 * pixel are optional, so the width gets clamped to the
+	 * [`aliased_line_width_range`] of the implementation when the pipeline
+	 * gets created.
+	 *
+	 * [`aliased_line_width_range`]: crate::Limits::aliased_line_width_range */
+	pub line_width: f32,
+	/** Whether the vertex stage sets the size of points through
+	 * `gl_PointSize`. Points are a single pixel big otherwise.
+	 *
+	 * OpenGL ES and WebGL always take the size from the vertex stage, so this
+	 * only makes a difference on core profiles, where it has to be enabled for
+	 * `gl_PointSize` to have any effect. */
+	pub program_point_size: bool,
+}
+impl Default for PrimitiveState {
+	/** Filled, unculled lists of counter clockwise triangles, indexed with 16
+	 * bit integers, with single pixel wide lines and single pixel points. */
+	fn default() -> Self {
+		Self {
+			topology: PrimitiveTopology::TriangleList,
+			index_format: IndexFormat::Uint16,
+			front_face: FrontFace::Ccw,
+			cull_mode: CullMode::None,
+			polygon_mode: PolygonMode::Fill,
+			line_width: 1.0,
+			program_point_size: false
+		}
+	}
+}
+
+/** Clamps the given line width to the given range of supported widths. Widths
+ * that aren't numbers get clamped to the bottom of the range. */
+pub(crate) fn clamp_line_width(width: f32, range: [f32; 2]) -> f32 {
+	let [min, max] = range;
+	width.max(min).min(max)
 }
 
 /** Type of drawing modes for polygons. */
@@ -917,6 +1545,7 @@ pub enum PrimitiveTopology {
 
 /** Number of components a vertex attribute may have. */
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum VertexComponents {
 	/** Single component vertex attribute. */
 	One = 1,
@@ -927,6 +1556,12 @@ pub enum VertexComponents {
 	/** Found component vertex attribute. */
 	Four = 4
 }
+impl VertexComponents {
+	/** Number of components in the vertex attribute. */
+	pub fn count(&self) -> u32 {
+		*self as u32
+	}
+}
 
 /** Data types an index may have. */
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
@@ -955,5 +1590,325 @@ pub enum RenderPipelineError {
 	AttributeBindingMissing {
 		/** Name of the binding in the shader that is missing. */
 		binding: String,
+	},
+	#[error("More than one vertex attribute is bound to \"{binding}\"")]
+	AttributeAliasing {
+		binding: String,
+	},
+	#[error("The vertex layout has {count} attributes, more than the limit of {max}")]
+	TooManyAttributes {
+		count: u32,
+		max: u32,
+	},
+	#[error("The fragment stage has {count} color targets, more than the limit \
+		of {max}")]
+	TooManyColorTargets {
+		count: usize,
+		max: u32,
+	},
+	#[error("Attribute \"{binding}\" was assigned location {location}, which \
+		is not lower than the limit of {max}")]
+	AttributeLocationOutOfRange {
+		binding: String,
+		location: u32,
+		max: u32,
+	},
+	#[error("The interface between the vertex and fragment stages is too \
+		large: {what}")]
+	VaryingLimitExceeded {
+		what: String
+	},
+	#[error("Attributes \"{first}\" and \"{second}\" both take up location \
+		{location}")]
+	AttributeLocationConflict {
+		first: String,
+		second: String,
+		location: u32,
+	},
+	#[error("The pipeline uses a feature the context doesn't support: {what}")]
+	UnsupportedFeature {
+		what: String
+	},
+	#[error("The vertex layout has misplaced attributes: {what}")]
+	InvalidVertexLayout {
+		what: String
+	},
+	#[error("The outputs to be captured by transform feedback are invalid: {what}")]
+	InvalidFeedback {
+		what: String
+	},
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{Profile, Release};
+
+	#[test]
+	fn defaults_match_the_literals() {
+		assert_eq!(PrimitiveState::default(), PrimitiveState {
+			topology: PrimitiveTopology::TriangleList,
+			index_format: IndexFormat::Uint16,
+			front_face: FrontFace::Ccw,
+			cull_mode: CullMode::None,
+			polygon_mode: PolygonMode::Fill,
+			line_width: 1.0,
+			program_point_size: false
+		});
+		assert_eq!(ColorTargetState::default(), ColorTargetState {
+			alpha_blend: BlendState::REPLACE,
+			color_blend: BlendState::REPLACE,
+			write_mask: ColorWrite::all(),
+		});
+		assert_eq!(DepthStencilState::LESS_WRITE, DepthStencilState {
+			depth_write_enabled: true,
+			depth_compare: CompareFunction::Less,
+			front: StencilState::IGNORE,
+			back: StencilState::IGNORE
+		});
+		assert_eq!(StencilState::default(), StencilState::IGNORE);
+	}
+
+	#[test]
+	fn either_face_may_write_to_the_stencil() {
+		assert!(!stencil_writes(&StencilState::IGNORE));
+
+		/* A portal frame that marks its front face and leaves the stencil
+		 * alone when seen from behind. */
+		let mark = StencilState {
+			compare: CompareFunction::Always,
+			pass_op: StencilOperation::Replace,
+			..StencilState::IGNORE
+		};
+		let portal = DepthStencilState {
+			back: StencilState::IGNORE,
+			..DepthStencilState::new(false, CompareFunction::Less, mark)
+		};
+		assert!(!portal.symmetric());
+		assert!(stencil_writes(&portal.front));
+		assert!(!stencil_writes(&portal.back));
+
+		/* Failing operations are never carried out by tests that always
+		 * pass, and nothing gets written through an empty mask. */
+		assert!(!stencil_writes(&StencilState {
+			fail_op: StencilOperation::Zero,
+			..StencilState::IGNORE
+		}));
+		assert!(!stencil_writes(&StencilState { write_mask: 0, ..mark }));
+		assert!(DepthStencilState::new(true, CompareFunction::Less, mark).symmetric());
+	}
+
+	#[test]
+	fn only_constant_factors_use_the_constant() {
+		assert!(!BlendState::REPLACE.uses_constant());
+		assert!(!BlendState {
+			src_factor: BlendFactor::SrcAlpha,
+			dst_factor: BlendFactor::OneMinusSrcAlpha,
+			operation: BlendOperation::Add
+		}.uses_constant());
+		assert!(BlendState {
+			src_factor: BlendFactor::BlendColor,
+			dst_factor: BlendFactor::Zero,
+			operation: BlendOperation::Add
+		}.uses_constant());
+		assert!(BlendState {
+			src_factor: BlendFactor::One,
+			dst_factor: BlendFactor::OneMinusBlendColor,
+			operation: BlendOperation::Max
+		}.uses_constant());
+	}
+
+	#[test]
+	fn g_buffer_targets_share_state_unless_they_differ() {
+		let additive = ColorTargetState {
+			alpha_blend: BlendState::REPLACE,
+			color_blend: BlendState {
+				src_factor: BlendFactor::One,
+				dst_factor: BlendFactor::One,
+				operation: BlendOperation::Add
+			},
+			write_mask: ColorWrite::COLOR
+		};
+
+		/* Albedo and normals, written the same way, get set up at once. */
+		let same = [ColorTargetState::REPLACE, ColorTargetState::REPLACE];
+		assert_eq!(shared_target_state(&same), Some(ColorTargetState::REPLACE));
+		assert!(!ColorTargetState::REPLACE.blends());
+
+		/* Lighting accumulated on top of the albedo has to be set up for each
+		 * draw buffer on its own. */
+		let different = [ColorTargetState::REPLACE, additive];
+		assert_eq!(shared_target_state(&different), None);
+		assert!(additive.blends());
+
+		/* Without targets, nothing gets written to at all. */
+		let none = shared_target_state(&[]).unwrap();
+		assert!(none.write_mask.is_empty());
+		assert!(!none.blends());
+	}
+
+	#[test]
+	fn vertex_layouts_must_be_aligned() {
+		let attribute = |kind, components, offset, binding: &'static str|
+			VertexAttribute {
+				kind,
+				components,
+				offset,
+				binding: Cow::Borrowed(binding),
+				location: None
+			};
+
+		let compact = [
+			attribute(VertexType::F32, VertexComponents::Three, 0, "position"),
+			attribute(VertexType::Unorm8, VertexComponents::Four, 12, "color"),
+			attribute(VertexType::Uint16, VertexComponents::Four, 16, "joints"),
+		];
+		assert!(check_vertex_layout(&VertexBufferLayout {
+			array_stride: 24,
+			attributes: &compact
+		}).is_ok());
+
+		let misaligned = [
+			attribute(VertexType::Unorm8, VertexComponents::Three, 0, "color"),
+			attribute(VertexType::F32, VertexComponents::Two, 3, "uv"),
+		];
+		match check_vertex_layout(&VertexBufferLayout {
+			array_stride: 12,
+			attributes: &misaligned
+		}) {
+			Err(RenderPipelineError::InvalidVertexLayout { what }) => {
+				assert!(what.contains("\"uv\""));
+				assert!(!what.contains("\"color\""));
+			},
+			other => panic!("expected an invalid layout, got {:?}", other)
+		}
+
+		/* Strides must be aligned too, and attributes must fit in them. */
+		let joints = [
+			attribute(VertexType::Uint16, VertexComponents::Two, 0, "joints"),
+		];
+		assert!(check_vertex_layout(&VertexBufferLayout {
+			array_stride: 5,
+			attributes: &joints
+		}).is_err());
+		assert!(check_vertex_layout(&VertexBufferLayout {
+			array_stride: 2,
+			attributes: &joints
+		}).is_err());
+	}
+
+	#[test]
+	fn integer_attributes_take_the_integer_path() {
+		let attributes = [
+			VertexAttribute {
+				kind: VertexType::Uint16,
+				components: VertexComponents::Four,
+				offset: 0,
+				binding: Cow::Borrowed("joints"),
+				location: None
+			},
+			VertexAttribute {
+				kind: VertexType::Unorm8,
+				components: VertexComponents::Four,
+				offset: 8,
+				binding: Cow::Borrowed("color"),
+				location: None
+			},
+		];
+		let layout = OwnedVertexBufferLayout::from(&VertexBufferLayout {
+			array_stride: 12,
+			attributes: &attributes
+		});
+		let locations = [("joints".to_string(), 0), ("color".to_string(), 1)]
+			.iter()
+			.cloned()
+			.collect::<HashMap<_, _>>();
+
+		/* Integers go through glVertexAttribIPointer, normalized values
+		 * through glVertexAttribPointer with the normalized flag. */
+		let pointers = attribute_pointers(&locations, &layout, None);
+		assert!(pointers[0].integer && !pointers[0].normalized);
+		assert_eq!(pointers[0].kind, glow::UNSIGNED_SHORT);
+		assert!(!pointers[1].integer && pointers[1].normalized);
+		assert_eq!(pointers[1].kind, glow::UNSIGNED_BYTE);
+		assert_eq!(attributes[0].len(), 8);
+		assert_eq!(attributes[1].len(), 4);
+
+		/* Legacy contexts have no integer pointers to take. */
+		let version = |profile| Version {
+			profile,
+			release: Release { major: 3, minor: 0 },
+			vendor: String::new()
+		};
+		let all = attributes.iter().collect::<Vec<_>>();
+		assert!(check_integer_attributes(&all, &version(Profile::Es)).is_ok());
+		assert!(check_integer_attributes(&all, &version(Profile::Web)).is_ok());
+		assert!(matches!(
+			check_integer_attributes(&all, &version(Profile::EsLegacy)),
+			Err(RenderPipelineError::UnsupportedFeature { .. })));
+		assert!(check_integer_attributes(&all[1..], &version(Profile::WebLegacy)).is_ok());
+	}
+
+	#[test]
+	fn matching_layouts_resolve_to_the_same_pointers() {
+		let layout = |binding: &'static str| OwnedVertexBufferLayout::from(&VertexBufferLayout {
+			array_stride: 16,
+			attributes: &[
+				VertexAttribute {
+					kind: VertexType::F32,
+					components: VertexComponents::Three,
+					offset: 0,
+					binding: Cow::Borrowed(binding),
+					location: None
+				},
+				VertexAttribute {
+					kind: VertexType::Unorm8,
+					components: VertexComponents::Four,
+					offset: 12,
+					binding: Cow::Borrowed("color"),
+					location: None
+				},
+			]
+		});
+		let locations = |pairs: &[(&str, u32)]| pairs.iter()
+			.map(|(name, location)| (name.to_string(), *location))
+			.collect::<HashMap<_, _>>();
+
+		/* Different names at the same locations point the same way. */
+		let a = attribute_pointers(
+			&locations(&[("position", 0), ("color", 1)]),
+			&layout("position"),
+			None);
+		let b = attribute_pointers(
+			&locations(&[("vertex", 0), ("color", 1)]),
+			&layout("vertex"),
+			None);
+		assert_eq!(a, b);
+		assert_eq!(a.len(), 2);
+		assert!(a[1].normalized && !a[1].integer);
+		assert_eq!((a[1].offset, a[1].stride), (12, 16));
+
+		/* Inactive attributes get left out, and instance attributes differ. */
+		let inactive = attribute_pointers(
+			&locations(&[("position", 0)]),
+			&layout("position"),
+			None);
+		assert_eq!(inactive, &a[..1]);
+
+		let instanced = attribute_pointers(
+			&locations(&[("position", 0), ("color", 1)]),
+			&OwnedVertexBufferLayout { array_stride: 0, attributes: Vec::new() },
+			Some(&layout("position")));
+		assert_eq!(instanced.len(), 2);
+		assert!(instanced.iter().all(|pointer| pointer.instanced));
+		assert_ne!(instanced, a);
+	}
+
+	#[test]
+	fn line_widths_get_clamped() {
+		assert_eq!(clamp_line_width(4.0, [1.0, 8.0]), 4.0);
+		assert_eq!(clamp_line_width(16.0, [1.0, 8.0]), 8.0);
+		assert_eq!(clamp_line_width(0.0, [1.0, 8.0]), 1.0);
+		assert_eq!(clamp_line_width(f32::NAN, [1.0, 8.0]), 1.0);
 	}
 }
@@ -0,0 +1,77 @@
+pub use crate::{
+	Device,
+	Information,
+	ShaderSource,
+	ShaderLibrary,
+	VertexShader,
+	FragmentShader,
+	RenderPipeline,
+	RenderPipelineDescriptor,
+	VertexState,
+	VertexBufferLayout,
+	VertexAttribute,
+	VertexType,
+	VertexComponents,
+	FragmentState,
+	PrimitiveState,
+	PrimitiveTopology,
+	IndexFormat,
+	FrontFace,
+	CullMode,
+	PolygonMode,
+	ColorTargetState,
+	ColorWrite,
+	BlendState,
+	BlendFactor,
+	BlendOperation,
+	DepthStencilState,
+	StencilState,
+	StencilOperation,
+	CompareFunction,
+	BufferDescriptor,
+	BufferProfile,
+	BufferContents,
+	VertexBuffer,
+	IndexBuffer,
+	UniformBuffer,
+	BufferWriter,
+	BufferLoadOp,
+	MapMode,
+	BufferUsage,
+	Buffer,
+	UploadBuffer,
+	Texture,
+	TextureDescriptor,
+	TextureExtent,
+	TextureFormat,
+	TextureSwizzle,
+	ChannelSource,
+	TextureFilter,
+	AddressMode,
+	Mipmap,
+	FilterType,
+	Sampler,
+	SamplerDescriptor,
+	UniformGroup,
+	UniformGroupDescriptor,
+	UniformGroupEntry,
+	UniformBind,
+	Framebuffer,
+	FramebufferDescriptor,
+	DefaultFramebufferDescriptor,
+	FramebufferColorAttachmentDescriptor,
+	FramebufferDepthStencilAttachmentDescriptor,
+	LoadOp,
+	Color,
+	RenderPass,
+	RenderPassDescriptor,
+	RenderBundle,
+	RenderBundleDescriptor,
+	RenderBundleEncoder,
+	TransformFeedbackPipeline,
+	TransformFeedbackPipelineDescriptor,
+	TransformFeedbackPass,
+	TransformFeedbackPassDescriptor,
+	FeedbackBuffers,
+	Viewport,
+};
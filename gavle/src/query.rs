@@ -0,0 +1,105 @@
+use glow::{Context, HasContext};
+use std::rc::Rc;
+use std::cell::Cell;
+use crate::access::{AccessLock, UnitAccessLock};
+
+/** Kinds of queries a [`Query`] may be created for. */
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum QueryKind {
+	/** Whether any samples passed the depth and stencil tests during the
+	 * query. Results are either zero or one. */
+	AnySamplesPassed,
+	/** The time the device took to execute the commands issued during the
+	 * query, in nanoseconds.
+	 *
+	 * This is only available when the [`timer_queries`] capability is present
+	 * in the context.
+	 *
+	 * [`timer_queries`]: crate::Capabilities::timer_queries */
+	TimeElapsed,
+}
+
+/** A query object, which measures something about the commands issued between
+ * the point it gets begun and the point it gets ended in a render pass.
+ *
+ * Results become available asynchronously, once the device has gone through
+ * those commands, and are polled for with [`try_result()`]. A query may be
+ * begun again once it has been ended, which discards its previous result.
+ *
+ * [`try_result()`]: Self::try_result */
+#[derive(Debug)]
+pub struct Query {
+	/** Handle to the shared context. */
+	pub(crate) context: Rc<Context>,
+	/** The name of the underlying query object. */
+	pub(crate) query: <Context as HasContext>::Query,
+	/** What gets measured by this query. */
+	pub(crate) kind: QueryKind,
+	/** Write lock held by the render pass this query is active in, if any. */
+	pub(crate) access: UnitAccessLock,
+	/** Whether this query has been ended at least once, and therefore has a
+	 * result, or will have one. */
+	pub(crate) ended: Cell<bool>,
+}
+impl Query {
+	/** What gets measured by this query. */
+	pub fn kind(&self) -> QueryKind {
+		self.kind
+	}
+
+	/** The result of the last run of this query, if the device has gotten to
+	 * it. Returns `None` if the result isn't available yet, or if the query
+	 * has never been run. Never blocks.
+	 *
+	 * # Panic
+	 * This function panics if the query is still active in a render pass. */
+	pub fn try_result(&self) -> Option<u64> {
+		let _guard = self.access.acquire_read_guarded();
+		if !self.ended.get() { return None }
+
+		unsafe {
+			let available = self.context.get_query_parameter_u32(
+				self.query,
+				glow::QUERY_RESULT_AVAILABLE);
+			if available == 0 { return None }
+
+			let result = self.context.get_query_parameter_u32(
+				self.query,
+				glow::QUERY_RESULT);
+			Some(u64::from(result))
+		}
+	}
+}
+impl AccessLock for Query {
+	fn acquire_write(&self) {
+		self.access.acquire_write()
+	}
+	fn release_write(&self) {
+		self.access.release_write()
+	}
+	fn acquire_read(&self) {
+		self.access.acquire_read()
+	}
+	fn release_read(&self) {
+		self.access.release_read()
+	}
+}
+impl Drop for Query {
+	fn drop(&mut self) {
+		unsafe {
+			self.context.delete_query(self.query);
+		}
+	}
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum QueryError {
+	#[error("Failed to create a new query: {what}")]
+	CreationFailed {
+		what: String
+	},
+	#[error("the query uses a feature the context doesn't support: {what}")]
+	UnsupportedFeature {
+		what: String
+	},
+}
@@ -0,0 +1,185 @@
+use std::convert::TryFrom;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/** Token bucket used to keep messages that may be emitted every frame from
+ * flooding the log.
+ *
+ * The bucket starts out holding `burst` tokens and gains one token back every
+ * `period`, up to `burst`. Each message that gets through takes a token, and
+ * messages that arrive at an empty bucket are counted and dropped, so that the
+ * next message to get through can report how many were left out.
+ *
+ * Limiters are meant to live in statics, one per call site, and are most
+ * easily used through the [`rate_limited`] macro.
+ *
+ * [`rate_limited`]: crate::rate_limited */
+#[derive(Debug)]
+pub struct RateLimiter {
+	/** Maximum number of tokens the bucket can hold. */
+	burst: u32,
+	/** Time it takes for a single token to be given back. */
+	period: Duration,
+	/** Mutable state of the bucket. */
+	state: Mutex<Bucket>,
+}
+impl RateLimiter {
+	/** Creates a new limiter letting `burst` messages through at once, and
+	 * one more message through every `period` after that. */
+	pub const fn new(burst: u32, period: Duration) -> Self {
+		Self {
+			burst,
+			period,
+			state: Mutex::new(Bucket {
+				tokens: burst,
+				refilled: None,
+				suppressed: 0
+			})
+		}
+	}
+
+	/** Tries to take a token from the bucket.
+	 *
+	 * Returns `None` if the message should be dropped. Otherwise, returns the
+	 * number of messages that have been dropped since the last one that got
+	 * through. */
+	pub fn acquire(&self) -> Option<u32> {
+		#[cfg(not(target_arch = "wasm32"))]
+		{ self.acquire_at(Instant::now()) }
+
+		/* There is no monotonic clock we can rely on in this target. Rather
+		 * than risking silencing messages forever, let everything through. */
+		#[cfg(target_arch = "wasm32")]
+		{ Some(0) }
+	}
+
+	/** Tries to take a token from the bucket, given the current time. */
+	#[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+	fn acquire_at(&self, now: Instant) -> Option<u32> {
+		let mut state = match self.state.lock() {
+			Ok(state) => state,
+			Err(poisoned) => poisoned.into_inner()
+		};
+
+		/* Give back the tokens for every whole period that has passed. */
+		let refilled = *state.refilled.get_or_insert(now);
+		if self.period > Duration::from_secs(0) {
+			let elapsed = now.saturating_duration_since(refilled);
+			let periods = elapsed.as_nanos() / self.period.as_nanos();
+
+			if periods > 0 {
+				let tokens = u128::from(state.tokens) + periods;
+				state.tokens = u32::try_from(tokens.min(u128::from(self.burst)))
+					.unwrap();
+
+				/* Only move forward by whole periods, so that we don't lose
+				 * the progress towards the next token. */
+				let advance = self.period.as_nanos() * periods;
+				state.refilled = Some(match u64::try_from(advance) {
+					Ok(advance) => refilled + Duration::from_nanos(advance),
+					Err(_) => now
+				});
+			}
+		} else {
+			state.tokens = self.burst;
+		}
+
+		if state.tokens == 0 {
+			state.suppressed = state.suppressed.saturating_add(1);
+			return None
+		}
+
+		state.tokens -= 1;
+		Some(std::mem::replace(&mut state.suppressed, 0))
+	}
+}
+
+/** Mutable state of a token bucket. */
+#[derive(Debug)]
+struct Bucket {
+	/** Number of tokens currently in the bucket. */
+	tokens: u32,
+	/** Point in time up to which tokens have been given back. This is only
+	 * known after the first message goes through the bucket. */
+	refilled: Option<Instant>,
+	/** Number of messages dropped since the last one that got through. */
+	suppressed: u32,
+}
+
+/** Logs a message through one of the macros in the `log` crate, dropping it
+ * whenever messages from the same call site are coming in faster than allowed.
+ *
+ * The first two arguments are the burst size and refill period passed on to
+ * [`RateLimiter::new`], and the rest is the log macro invocation itself. Both
+ * may be left out, in which case four messages are let through at once, and
+ * one more every five seconds. When messages have been dropped, the count is
+ * reported in a follow-up message with the same level and target.
+ *
+ * ```ignore
+ * rate_limited!(warn!(target: "gavle::pass", "something happened again"));
+ * rate_limited!(1, Duration::from_secs(1),
+ *     warn!(target: "gavle::pass", "something happened again"));
+ * ```
+ *
+ * [`RateLimiter::new`]: crate::RateLimiter::new */
+#[macro_export]
+macro_rules! rate_limited {
+	($log:ident!(target: $target:expr, $($arg:tt)+)) => {
+		$crate::rate_limited!(4, ::std::time::Duration::from_secs(5),
+			$log!(target: $target, $($arg)+))
+	};
+	($burst:expr, $period:expr,
+		$log:ident!(target: $target:expr, $($arg:tt)+)) => {{
+		static LIMITER: $crate::RateLimiter =
+			$crate::RateLimiter::new($burst, $period);
+		if let Some(suppressed) = LIMITER.acquire() {
+			$log!(target: $target, $($arg)+);
+			if suppressed > 0 {
+				$log!(target: $target, "({} similar messages were suppressed)",
+					suppressed);
+			}
+		}
+	}};
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn lets_bursts_through_then_refills() {
+		let limiter = RateLimiter::new(3, Duration::from_millis(100));
+		let start = Instant::now();
+
+		for _ in 0..3 {
+			assert_eq!(limiter.acquire_at(start), Some(0));
+		}
+		assert_eq!(limiter.acquire_at(start), None);
+		assert_eq!(limiter.acquire_at(start + Duration::from_millis(99)), None);
+
+		/* One token comes back, and the dropped messages get reported. */
+		let later = start + Duration::from_millis(150);
+		assert_eq!(limiter.acquire_at(later), Some(2));
+		assert_eq!(limiter.acquire_at(later), None);
+
+		/* The partial period before is not lost. */
+		let later = start + Duration::from_millis(200);
+		assert_eq!(limiter.acquire_at(later), Some(1));
+
+		/* Refills never go past the burst size. */
+		let later = start + Duration::from_secs(60);
+		for _ in 0..3 {
+			assert!(limiter.acquire_at(later).is_some());
+		}
+		assert_eq!(limiter.acquire_at(later), None);
+	}
+
+	#[test]
+	fn macro_compiles_against_log() {
+		for _ in 0..8 {
+			rate_limited!(1, Duration::from_secs(1),
+				warn!(target: "gavle::test", "repeated {}", 1));
+			rate_limited!(trace!(target: "gavle::test", "repeated"));
+		}
+	}
+}
@@ -0,0 +1,201 @@
+use crate::pipeline::VertexComponents;
+
+/** Active interface of a linked render pipeline, as reported by the
+ * implementation when its program was linked.
+ *
+ * Only what the implementation considers active shows up here. Variables that
+ * are declared but never used by the shaders get optimized out, and binding
+ * anything to them does nothing, which is what [`UniformGroup::validate()`]
+ * checks for.
+ *
+ * [`UniformGroup::validate()`]: crate::UniformGroup::validate */
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PipelineReflection {
+	/** Active uniform blocks, by the name they were declared with. */
+	pub uniform_blocks: Vec<ReflectedBlock>,
+	/** Active sampler uniforms. */
+	pub samplers: Vec<ReflectedSampler>,
+	/** Active vertex attributes. */
+	pub attributes: Vec<ReflectedAttribute>,
+}
+
+/** Active uniform block of a pipeline. */
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReflectedBlock {
+	/** Name the block was declared with. */
+	pub name: String,
+	/** Binding point the block was pinned to when the program was linked, or
+	 * `None` if it gets assigned one every time a group is bound to it. */
+	pub binding: Option<u32>,
+	/** Minimum size of the buffer range bound to the block, in bytes. */
+	pub size: u32,
+}
+
+/** Active sampler uniform of a pipeline. */
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReflectedSampler {
+	/** Name of the uniform, as reported by the implementation. Arrays are
+	 * reported by the name of their first element, as in `shadows[0]`. */
+	pub name: String,
+	/** Kind of texture the sampler samples. */
+	pub dimension: SamplerDimension,
+	/** Whether this is a shadow sampler, which compares against depth. */
+	pub shadow: bool,
+	/** Number of elements in the uniform, which is one for non-arrays. */
+	pub count: u32,
+}
+
+/** Kinds of texture a sampler uniform may sample. */
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SamplerDimension {
+	/** `sampler1D` and its variants. */
+	D1,
+	/** `sampler2D` and its variants. */
+	D2,
+	/** `sampler2DArray` and its variants. */
+	D2Array,
+	/** `sampler3D` and its variants. */
+	D3,
+	/** `samplerCube` and its variants. */
+	Cube,
+	/** `samplerBuffer` and its variants, which sample texel buffers. */
+	Buffer,
+}
+
+/** Active vertex attribute of a pipeline. */
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReflectedAttribute {
+	/** Name of the attribute. */
+	pub name: String,
+	/** Location the attribute ended up at, if the implementation reported
+	 * one for it. */
+	pub location: Option<u32>,
+	/** Type of the components of the attribute, as seen by the shader. */
+	pub kind: ShaderScalar,
+	/** Number of components in the attribute, or in each of its columns, for
+	 * matrices. */
+	pub components: VertexComponents,
+	/** Number of columns in the attribute, which is one for anything but
+	 * matrices. */
+	pub columns: u32,
+}
+
+/** Types the components of a shader variable may have. */
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ShaderScalar {
+	/** `float` and the vectors and matrices made of it. */
+	F32,
+	/** `int` and the vectors made of it. */
+	I32,
+	/** `uint` and the vectors made of it. */
+	U32,
+	/** `bool` and the vectors made of it. */
+	Bool,
+}
+
+/** Splits the given uniform or attribute type into the type of its components,
+ * the number of components in each of its columns and its number of columns.
+ * Returns `None` for samplers and for types we don't know of. */
+pub(crate) fn shader_type(kind: u32) -> Option<(ShaderScalar, VertexComponents, u32)> {
+	use VertexComponents::*;
+	use ShaderScalar::*;
+
+	Some(match kind {
+		glow::FLOAT => (F32, One, 1),
+		glow::FLOAT_VEC2 => (F32, Two, 1),
+		glow::FLOAT_VEC3 => (F32, Three, 1),
+		glow::FLOAT_VEC4 => (F32, Four, 1),
+		glow::INT => (I32, One, 1),
+		glow::INT_VEC2 => (I32, Two, 1),
+		glow::INT_VEC3 => (I32, Three, 1),
+		glow::INT_VEC4 => (I32, Four, 1),
+		glow::UNSIGNED_INT => (U32, One, 1),
+		glow::UNSIGNED_INT_VEC2 => (U32, Two, 1),
+		glow::UNSIGNED_INT_VEC3 => (U32, Three, 1),
+		glow::UNSIGNED_INT_VEC4 => (U32, Four, 1),
+		glow::BOOL => (Bool, One, 1),
+		glow::BOOL_VEC2 => (Bool, Two, 1),
+		glow::BOOL_VEC3 => (Bool, Three, 1),
+		glow::BOOL_VEC4 => (Bool, Four, 1),
+		glow::FLOAT_MAT2 => (F32, Two, 2),
+		glow::FLOAT_MAT2x3 => (F32, Three, 2),
+		glow::FLOAT_MAT2x4 => (F32, Four, 2),
+		glow::FLOAT_MAT3x2 => (F32, Two, 3),
+		glow::FLOAT_MAT3 => (F32, Three, 3),
+		glow::FLOAT_MAT3x4 => (F32, Four, 3),
+		glow::FLOAT_MAT4x2 => (F32, Two, 4),
+		glow::FLOAT_MAT4x3 => (F32, Three, 4),
+		glow::FLOAT_MAT4 => (F32, Four, 4),
+		_ => return None
+	})
+}
+
+/** Kind of texture sampled by a sampler of the given type, and whether it is
+ * a shadow sampler. Returns `None` for anything that isn't a sampler we know
+ * of. */
+pub(crate) fn sampler_type(kind: u32) -> Option<(SamplerDimension, bool)> {
+	use SamplerDimension::*;
+
+	Some(match kind {
+		glow::SAMPLER_1D
+			| glow::INT_SAMPLER_1D
+			| glow::UNSIGNED_INT_SAMPLER_1D => (D1, false),
+		glow::SAMPLER_2D
+			| glow::INT_SAMPLER_2D
+			| glow::UNSIGNED_INT_SAMPLER_2D => (D2, false),
+		glow::SAMPLER_2D_ARRAY
+			| glow::INT_SAMPLER_2D_ARRAY
+			| glow::UNSIGNED_INT_SAMPLER_2D_ARRAY => (D2Array, false),
+		glow::SAMPLER_3D
+			| glow::INT_SAMPLER_3D
+			| glow::UNSIGNED_INT_SAMPLER_3D => (D3, false),
+		glow::SAMPLER_CUBE
+			| glow::INT_SAMPLER_CUBE
+			| glow::UNSIGNED_INT_SAMPLER_CUBE => (Cube, false),
+		glow::SAMPLER_BUFFER
+			| glow::INT_SAMPLER_BUFFER
+			| glow::UNSIGNED_INT_SAMPLER_BUFFER => (Buffer, false),
+		glow::SAMPLER_1D_SHADOW => (D1, true),
+		glow::SAMPLER_2D_SHADOW => (D2, true),
+		glow::SAMPLER_2D_ARRAY_SHADOW => (D2Array, true),
+		glow::SAMPLER_CUBE_SHADOW => (Cube, true),
+		_ => return None
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn splits_shader_types() {
+		assert_eq!(shader_type(glow::FLOAT_VEC3),
+			Some((ShaderScalar::F32, VertexComponents::Three, 1)));
+		assert_eq!(shader_type(glow::UNSIGNED_INT_VEC2),
+			Some((ShaderScalar::U32, VertexComponents::Two, 1)));
+		assert_eq!(shader_type(glow::FLOAT_MAT4),
+			Some((ShaderScalar::F32, VertexComponents::Four, 4)));
+		assert_eq!(shader_type(glow::FLOAT_MAT2x3),
+			Some((ShaderScalar::F32, VertexComponents::Three, 2)));
+		assert_eq!(shader_type(glow::SAMPLER_2D), None);
+	}
+
+	#[test]
+	fn recognizes_samplers() {
+		assert_eq!(sampler_type(glow::SAMPLER_2D),
+			Some((SamplerDimension::D2, false)));
+		assert_eq!(sampler_type(glow::UNSIGNED_INT_SAMPLER_CUBE),
+			Some((SamplerDimension::Cube, false)));
+		assert_eq!(sampler_type(glow::SAMPLER_2D_ARRAY_SHADOW),
+			Some((SamplerDimension::D2Array, true)));
+		assert_eq!(sampler_type(glow::INT_SAMPLER_BUFFER),
+			Some((SamplerDimension::Buffer, false)));
+		assert_eq!(sampler_type(glow::FLOAT_VEC4), None);
+	}
+}
@@ -0,0 +1,432 @@
+use std::cell::{Cell, RefCell};
+use std::fmt::{Display, Formatter};
+use crate::reflection::{PipelineReflection, SamplerDimension};
+
+/** Record of the interfaces of every pipeline and uniform group created by a
+ * device while it was recording them, turned on with
+ * [`Device::record_interfaces()`] and gathered with
+ * [`Device::interface_registry()`].
+ *
+ * This is what the shaders of an application expect to be bound, next to what
+ * the application actually binds, which makes for a good overview of its
+ * interface with the device, and can be checked for bindings that never meet
+ * each other with [`InterfaceRegistry::check()`].
+ *
+ * [`Device::record_interfaces()`]: crate::Device::record_interfaces
+ * [`Device::interface_registry()`]: crate::Device::interface_registry
+ * [`InterfaceRegistry::check()`]: InterfaceRegistry::check */
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InterfaceRegistry {
+	/** Pipelines, in the order they were created. */
+	pub pipelines: Vec<RegisteredPipeline>,
+	/** Uniform groups, in the order they were created. */
+	pub groups: Vec<RegisteredGroup>,
+}
+
+/** Interface of a pipeline in the registry. */
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RegisteredPipeline {
+	/** Name of the pipeline in diagnostics, which follows its label. */
+	pub name: String,
+	/** Active interface of the pipeline, as reported when it was linked. */
+	pub interface: PipelineReflection,
+}
+
+/** Entries of a uniform group in the registry. Groups have no labels, so they
+ * are referred to by their position in the registry. */
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RegisteredGroup {
+	/** Entries of the group, in the order they were given in. */
+	pub entries: Vec<RegisteredEntry>,
+}
+
+/** Entry of a uniform group in the registry. */
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RegisteredEntry {
+	/** Name of the binding the entry binds to. */
+	pub binding: String,
+	/** Kind of resource the entry binds. */
+	pub kind: ResourceKind,
+}
+
+/** Kinds of resource a uniform group entry may bind. */
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ResourceKind {
+	/** A range of a uniform buffer, bound to a uniform block. */
+	UniformBuffer {
+		/** Size of the bound range, in bytes. */
+		size: u32
+	},
+	/** A texture, bound to a sampler. */
+	Texture,
+	/** A texel buffer, bound to a buffer sampler. */
+	TexelBuffer,
+}
+impl Display for ResourceKind {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		match self {
+			ResourceKind::UniformBuffer { .. } => write!(f, "uniform buffer"),
+			ResourceKind::Texture => write!(f, "texture"),
+			ResourceKind::TexelBuffer => write!(f, "texel buffer"),
+		}
+	}
+}
+
+/** Mismatches between pipelines and uniform groups found by
+ * [`InterfaceRegistry::check()`].
+ *
+ * [`InterfaceRegistry::check()`]: InterfaceRegistry::check */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InterfaceWarning {
+	/** A uniform block of a pipeline no group has a uniform buffer for. */
+	UnsatisfiedBlock {
+		/** Name of the pipeline. */
+		pipeline: String,
+		/** Name of the block. */
+		block: String,
+	},
+	/** A sampler of a pipeline no group has a texture or texel buffer for. */
+	UnsatisfiedSampler {
+		/** Name of the pipeline. */
+		pipeline: String,
+		/** Name of the sampler. */
+		sampler: String,
+	},
+	/** An entry of a group no pipeline has a uniform block or sampler for. */
+	UndeclaredEntry {
+		/** Position of the group in the registry. */
+		group: usize,
+		/** Name of the binding of the entry. */
+		binding: String,
+		/** Kind of resource the entry binds. */
+		kind: ResourceKind,
+	},
+}
+impl Display for InterfaceWarning {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		match self {
+			InterfaceWarning::UnsatisfiedBlock { pipeline, block } =>
+				write!(f, "uniform block \"{}\" of pipeline {} is never \
+					bound by any group", block, pipeline),
+			InterfaceWarning::UnsatisfiedSampler { pipeline, sampler } =>
+				write!(f, "sampler \"{}\" of pipeline {} is never bound by \
+					any group", sampler, pipeline),
+			InterfaceWarning::UndeclaredEntry { group, binding, kind } =>
+				write!(f, "{} \"{}\" of group #{} is not declared by any \
+					pipeline", kind, binding, group),
+		}
+	}
+}
+
+impl InterfaceRegistry {
+	/** Cross-references the pipelines and the groups in the registry, by the
+	 * name of their bindings, looking for uniform blocks and samplers no group
+	 * ever binds anything to, and for group entries no pipeline ever declares.
+	 *
+	 * This is a heuristic, as it doesn't know which groups get bound to which
+	 * pipelines, but it does a good job of catching bindings that went dead
+	 * and typos across a whole application, and is meant to be run once
+	 * every pipeline and group has been created, such as at startup or in a
+	 * test. */
+	pub fn check(&self) -> Vec<InterfaceWarning> {
+		let mut warnings = Vec::new();
+
+		let entries = || self.groups.iter()
+			.flat_map(|group| group.entries.iter());
+		for pipeline in &self.pipelines {
+			for block in &pipeline.interface.uniform_blocks {
+				let bound = entries().any(|entry| entry.binding == block.name
+					&& matches!(entry.kind, ResourceKind::UniformBuffer { .. }));
+				if !bound {
+					warnings.push(InterfaceWarning::UnsatisfiedBlock {
+						pipeline: pipeline.name.clone(),
+						block: block.name.clone()
+					})
+				}
+			}
+			for sampler in &pipeline.interface.samplers {
+				let bound = entries().any(|entry| samples(
+					&entry.binding,
+					entry.kind,
+					&sampler.name,
+					sampler.dimension));
+				if !bound {
+					warnings.push(InterfaceWarning::UnsatisfiedSampler {
+						pipeline: pipeline.name.clone(),
+						sampler: sampler.name.clone()
+					})
+				}
+			}
+		}
+
+		for (index, group) in self.groups.iter().enumerate() {
+			for entry in &group.entries {
+				let declared = self.pipelines.iter()
+					.map(|pipeline| &pipeline.interface)
+					.any(|interface| match entry.kind {
+						ResourceKind::UniformBuffer { .. } =>
+							interface.uniform_blocks.iter()
+								.any(|block| block.name == entry.binding),
+						kind => interface.samplers.iter()
+							.any(|sampler| samples(
+								&entry.binding,
+								kind,
+								&sampler.name,
+								sampler.dimension))
+					});
+				if !declared {
+					warnings.push(InterfaceWarning::UndeclaredEntry {
+						group: index,
+						binding: entry.binding.clone(),
+						kind: entry.kind
+					})
+				}
+			}
+		}
+
+		warnings
+	}
+}
+
+/** Whether an entry with the given binding and kind binds to the sampler with
+ * the given name and dimension. Arrays of samplers are reported by the name of
+ * their first element, but may be bound to by the name of the array. */
+fn samples(
+	binding: &str,
+	kind: ResourceKind,
+	sampler: &str,
+	dimension: SamplerDimension) -> bool {
+
+	let named = binding == sampler
+		|| sampler.strip_suffix("[0]") == Some(binding);
+	named && match kind {
+		ResourceKind::UniformBuffer { .. } => false,
+		ResourceKind::Texture => dimension != SamplerDimension::Buffer,
+		ResourceKind::TexelBuffer => dimension == SamplerDimension::Buffer,
+	}
+}
+
+/** Registry of the interfaces of the pipelines and groups created by a device,
+ * shared by it and by the pipelines it creates, which keep their names in the
+ * registry up to date with their labels. */
+#[derive(Debug, Default)]
+pub(crate) struct InterfaceRecorder {
+	/** Whether new pipelines and groups get recorded. */
+	enabled: Cell<bool>,
+	/** Everything that has been recorded so far. */
+	registry: RefCell<InterfaceRegistry>,
+}
+impl InterfaceRecorder {
+	/** Starts or stops recording new pipelines and groups. */
+	pub(crate) fn set_enabled(&self, enabled: bool) {
+		self.enabled.set(enabled)
+	}
+
+	/** Records the interface of a newly created pipeline with the given name,
+	 * returning its position in the registry, if it got recorded. */
+	pub(crate) fn record_pipeline(
+		&self,
+		name: String,
+		interface: &PipelineReflection) -> Option<usize> {
+
+		if !self.enabled.get() { return None }
+
+		let mut registry = self.registry.borrow_mut();
+		registry.pipelines.push(RegisteredPipeline {
+			name,
+			interface: interface.clone()
+		});
+		Some(registry.pipelines.len() - 1)
+	}
+
+	/** Changes the name of the pipeline at the given position. */
+	pub(crate) fn rename_pipeline(&self, index: usize, name: String) {
+		if let Some(pipeline) = self.registry.borrow_mut().pipelines.get_mut(index) {
+			pipeline.name = name
+		}
+	}
+
+	/** Records the entries of a newly created group. */
+	pub(crate) fn record_group<I>(&self, entries: I)
+		where I: IntoIterator<Item = RegisteredEntry> {
+
+		if !self.enabled.get() { return }
+
+		self.registry.borrow_mut().groups.push(RegisteredGroup {
+			entries: entries.into_iter().collect()
+		});
+	}
+
+	/** A copy of everything that has been recorded so far. */
+	pub(crate) fn registry(&self) -> InterfaceRegistry {
+		self.registry.borrow().clone()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::reflection::{ReflectedBlock, ReflectedSampler};
+
+	fn pipeline(name: &str, blocks: &[&str], samplers: &[(&str, SamplerDimension)])
+		-> RegisteredPipeline {
+
+		RegisteredPipeline {
+			name: format!("\"{}\"", name),
+			interface: PipelineReflection {
+				uniform_blocks: blocks.iter()
+					.map(|name| ReflectedBlock {
+						name: name.to_string(),
+						binding: Some(0),
+						size: 64
+					})
+					.collect(),
+				samplers: samplers.iter()
+					.map(|(name, dimension)| ReflectedSampler {
+						name: name.to_string(),
+						dimension: *dimension,
+						shadow: false,
+						count: 1
+					})
+					.collect(),
+				attributes: Vec::new()
+			}
+		}
+	}
+
+	fn group(entries: &[(&str, ResourceKind)]) -> RegisteredGroup {
+		RegisteredGroup {
+			entries: entries.iter()
+				.map(|(binding, kind)| RegisteredEntry {
+					binding: binding.to_string(),
+					kind: *kind
+				})
+				.collect()
+		}
+	}
+
+	const BUFFER: ResourceKind = ResourceKind::UniformBuffer { size: 64 };
+
+	#[test]
+	fn matching_interfaces_raise_no_warnings() {
+		let registry = InterfaceRegistry {
+			pipelines: vec![
+				pipeline("mesh", &["Camera", "Lighting"], &[
+					("albedo", SamplerDimension::D2),
+					("shadows[0]", SamplerDimension::D2Array),
+					("bones", SamplerDimension::Buffer)
+				]),
+				pipeline("sky", &["Camera"], &[("sky", SamplerDimension::Cube)])
+			],
+			groups: vec![
+				group(&[("Camera", BUFFER), ("Lighting", BUFFER)]),
+				group(&[
+					("albedo", ResourceKind::Texture),
+					("shadows", ResourceKind::Texture),
+					("bones", ResourceKind::TexelBuffer)
+				]),
+				group(&[("sky", ResourceKind::Texture)])
+			]
+		};
+
+		assert_eq!(registry.check(), vec![]);
+	}
+
+	#[test]
+	fn typos_show_up_on_both_sides() {
+		let registry = InterfaceRegistry {
+			pipelines: vec![
+				pipeline("mesh", &["Lighting"], &[("albedo", SamplerDimension::D2)])
+			],
+			groups: vec![
+				group(&[("Lightning", BUFFER), ("albedo", ResourceKind::Texture)]),
+				/* Names have to match along with the kind of the binding. */
+				group(&[("albedo", ResourceKind::TexelBuffer)])
+			]
+		};
+
+		assert_eq!(registry.check(), vec![
+			InterfaceWarning::UnsatisfiedBlock {
+				pipeline: "\"mesh\"".to_string(),
+				block: "Lighting".to_string()
+			},
+			InterfaceWarning::UndeclaredEntry {
+				group: 0,
+				binding: "Lightning".to_string(),
+				kind: BUFFER
+			},
+			InterfaceWarning::UndeclaredEntry {
+				group: 1,
+				binding: "albedo".to_string(),
+				kind: ResourceKind::TexelBuffer
+			}
+		]);
+	}
+
+	#[test]
+	fn unbound_samplers_are_reported() {
+		let registry = InterfaceRegistry {
+			pipelines: vec![
+				pipeline("mesh", &[], &[("normals", SamplerDimension::D2)])
+			],
+			groups: vec![]
+		};
+
+		let warnings = registry.check();
+		assert_eq!(warnings, vec![InterfaceWarning::UnsatisfiedSampler {
+			pipeline: "\"mesh\"".to_string(),
+			sampler: "normals".to_string()
+		}]);
+		assert_eq!(warnings[0].to_string(),
+			"sampler \"normals\" of pipeline \"mesh\" is never bound by any group");
+	}
+
+	#[test]
+	fn recording_is_opt_in() {
+		let recorder = InterfaceRecorder::default();
+		let interface = pipeline("mesh", &["Camera"], &[]).interface;
+
+		assert_eq!(recorder.record_pipeline("#1".to_string(), &interface), None);
+		recorder.record_group(vec![]);
+		assert_eq!(recorder.registry(), InterfaceRegistry::default());
+
+		recorder.set_enabled(true);
+		let index = recorder.record_pipeline("#2".to_string(), &interface);
+		assert_eq!(index, Some(0));
+		recorder.rename_pipeline(0, "\"mesh\"".to_string());
+		recorder.record_group(vec![RegisteredEntry {
+			binding: "Camera".to_string(),
+			kind: BUFFER
+		}]);
+
+		let registry = recorder.registry();
+		assert_eq!(registry.pipelines[0].name, "\"mesh\"");
+		assert_eq!(registry.groups.len(), 1);
+		assert_eq!(registry.check(), vec![]);
+	}
+
+	#[cfg(feature = "serde")]
+	#[test]
+	fn registries_survive_serialization() {
+		let registry = InterfaceRegistry {
+			pipelines: vec![
+				pipeline("mesh", &["Camera"], &[("bones", SamplerDimension::Buffer)])
+			],
+			groups: vec![
+				group(&[("Camera", BUFFER), ("bones", ResourceKind::TexelBuffer)])
+			]
+		};
+
+		let json = serde_json::to_string(&registry).unwrap();
+		assert!(json.contains("\"name\":\"Camera\""));
+		assert!(json.contains("\"UniformBuffer\":{\"size\":64}"));
+
+		let read = serde_json::from_str::<InterfaceRegistry>(&json).unwrap();
+		assert_eq!(read, registry);
+	}
+}
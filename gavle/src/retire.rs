@@ -0,0 +1,170 @@
+use crate::{Texture, TexelBuffer, Buffer, VertexBuffer, IndexBuffer, UniformBuffer};
+use std::collections::VecDeque;
+
+/** The number of frames resources wait in the retirement queue by default.
+ *
+ * This matches the number of frames a device usually gets to fall behind the
+ * host by, so that nothing recorded in a frame still being worked on gets its
+ * resources deleted from under it. */
+pub const DEFAULT_RETIREMENT_LATENCY: u32 = 2;
+
+/** A resource handed over to the device for deferred deletion. */
+#[derive(Debug)]
+pub enum RetiredResource {
+	Texture(Texture),
+	TexelBuffer(TexelBuffer),
+	Buffer(Buffer),
+	VertexBuffer(VertexBuffer),
+	IndexBuffer(IndexBuffer),
+	UniformBuffer(UniformBuffer),
+}
+impl From<Texture> for RetiredResource {
+	fn from(texture: Texture) -> Self {
+		Self::Texture(texture)
+	}
+}
+impl From<TexelBuffer> for RetiredResource {
+	fn from(buffer: TexelBuffer) -> Self {
+		Self::TexelBuffer(buffer)
+	}
+}
+impl From<Buffer> for RetiredResource {
+	fn from(buffer: Buffer) -> Self {
+		Self::Buffer(buffer)
+	}
+}
+impl From<VertexBuffer> for RetiredResource {
+	fn from(buffer: VertexBuffer) -> Self {
+		Self::VertexBuffer(buffer)
+	}
+}
+impl From<IndexBuffer> for RetiredResource {
+	fn from(buffer: IndexBuffer) -> Self {
+		Self::IndexBuffer(buffer)
+	}
+}
+impl From<UniformBuffer> for RetiredResource {
+	fn from(buffer: UniformBuffer) -> Self {
+		Self::UniformBuffer(buffer)
+	}
+}
+
+/** A snapshot of the bookkeeping a device does on its resources. */
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct ResourceReport {
+	/** Index of the frame currently being recorded. */
+	pub frame: u64,
+	/** Number of frames resources wait in the retirement queue. */
+	pub retirement_latency: u32,
+	/** Number of retired resources that have not been dropped yet. */
+	pub pending_retirements: usize,
+}
+
+/** Queue of resources waiting for the frames that may still be using them to
+ * be done before getting dropped. */
+#[derive(Debug)]
+pub(crate) struct RetirementQueue<T> {
+	/** Index of the frame currently being recorded. */
+	frame: u64,
+	/** Number of frames resources wait for before getting dropped. */
+	latency: u32,
+	/** Retired resources, along with the frame they were retired in, from the
+	 * oldest to the newest. */
+	pending: VecDeque<(u64, T)>,
+}
+impl<T> RetirementQueue<T> {
+	/** Creates a new, empty queue, starting at the first frame. */
+	pub(crate) fn new(latency: u32) -> Self {
+		Self {
+			frame: 0,
+			latency,
+			pending: VecDeque::new()
+		}
+	}
+
+	/** Index of the frame currently being recorded. */
+	pub(crate) fn frame(&self) -> u64 {
+		self.frame
+	}
+
+	/** Number of frames resources wait for before getting dropped. */
+	pub(crate) fn latency(&self) -> u32 {
+		self.latency
+	}
+
+	/** Changes the number of frames resources wait for. Resources already in
+	 * the queue are held to the new latency, starting at the next frame. */
+	pub(crate) fn set_latency(&mut self, latency: u32) {
+		self.latency = latency;
+	}
+
+	/** Number of resources waiting in the queue. */
+	pub(crate) fn pending(&self) -> usize {
+		self.pending.len()
+	}
+
+	/** Retires the given resource in the current frame. */
+	pub(crate) fn retire(&mut self, resource: T) {
+		self.pending.push_back((self.frame, resource));
+	}
+
+	/** Marks the end of the current frame, returning the resources which have
+	 * now waited for longer than the latency and may be dropped.
+	 *
+	 * These are handed back rather than dropped in here so that the caller
+	 * can let go of whatever it is keeping the queue in before dropping them. */
+	pub(crate) fn mark_frame(&mut self) -> Vec<T> {
+		self.frame += 1;
+
+		let mut expired = Vec::new();
+		while let Some((retired, _)) = self.pending.front() {
+			if self.frame - retired <= u64::from(self.latency) { break }
+
+			let (_, resource) = self.pending.pop_front()
+				.expect("the queue cannot be empty here");
+			expired.push(resource);
+		}
+
+		expired
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn resources_outlive_the_frames_in_flight() {
+		let mut queue = RetirementQueue::new(2);
+		queue.retire(0);
+		queue.retire(1);
+		assert_eq!(queue.mark_frame(), Vec::<u32>::new());
+
+		queue.retire(2);
+		assert_eq!(queue.mark_frame(), Vec::<u32>::new());
+		assert_eq!(queue.pending(), 3);
+
+		assert_eq!(queue.mark_frame(), vec![0, 1]);
+		assert_eq!(queue.mark_frame(), vec![2]);
+		assert_eq!(queue.pending(), 0);
+		assert_eq!(queue.frame(), 4);
+	}
+
+	#[test]
+	fn zero_latency_drops_at_the_end_of_the_frame() {
+		let mut queue = RetirementQueue::new(0);
+		queue.retire(0);
+		assert_eq!(queue.mark_frame(), vec![0]);
+		assert_eq!(queue.mark_frame(), Vec::<u32>::new());
+	}
+
+	#[test]
+	fn lowering_the_latency_releases_early() {
+		let mut queue = RetirementQueue::new(3);
+		queue.retire(0);
+		assert_eq!(queue.mark_frame(), Vec::<u32>::new());
+
+		queue.set_latency(1);
+		assert_eq!(queue.mark_frame(), vec![0]);
+	}
+}
@@ -0,0 +1,110 @@
+use glow::{Context, HasContext};
+use std::rc::Rc;
+use std::num::NonZeroU8;
+use std::convert::TryFrom;
+use crate::texture::{TextureFilter, AddressMode};
+
+/** Inner shared structure of the sampler. */
+#[derive(Debug)]
+pub(crate) struct InnerSampler {
+	/** Reference to the shared context. */
+	pub(crate) context: Rc<Context>,
+	/** Name of this sampler inside of that context. */
+	pub(crate) sampler: <Context as HasContext>::Sampler,
+}
+impl Drop for InnerSampler {
+	fn drop(&mut self) {
+		unsafe {
+			self.context.delete_sampler(self.sampler)
+		}
+	}
+}
+
+/** A set of parameters for sampling textures, kept apart from the textures
+ * themselves, so that the same texture may be sampled in different ways.
+ *
+ * Samplers are immutable, and clones of a sampler are handles to the same
+ * sampler object. */
+#[derive(Debug, Clone)]
+pub struct Sampler {
+	/** The inner shared structure of this sampler. */
+	pub(crate) inner: Rc<InnerSampler>
+}
+impl Sampler {
+	/** Sets the parameters in the given descriptor on a newly created sampler
+	 * object. The anisotropy clamp must have been validated by this point. */
+	pub(crate) unsafe fn setup(
+		gl: &Context,
+		sampler: <Context as HasContext>::Sampler,
+		descriptor: &SamplerDescriptor,
+		anisotropy: bool) {
+
+		let parameter = |name, value: u32| gl.sampler_parameter_i32(
+			sampler,
+			name,
+			i32::try_from(value).unwrap());
+
+		/* Levels outside of the view being sampled are never accessed, so the
+		 * mipmapped filters are safe to use even for textures without mips. */
+		parameter(glow::TEXTURE_MAG_FILTER, descriptor.near.as_opengl(false));
+		parameter(glow::TEXTURE_MIN_FILTER, descriptor.far.as_opengl(true));
+
+		parameter(glow::TEXTURE_WRAP_S, descriptor.address_u.as_opengl());
+		parameter(glow::TEXTURE_WRAP_T, descriptor.address_v.as_opengl());
+		parameter(glow::TEXTURE_WRAP_R, descriptor.address_w.as_opengl());
+
+		if anisotropy {
+			let clamp = descriptor.anisotropy_clamp
+				.map(|clamp| f32::from(clamp.get()))
+				.unwrap_or(1.0);
+			gl.sampler_parameter_f32(
+				sampler,
+				glow::TEXTURE_MAX_ANISOTROPY_EXT,
+				clamp);
+		}
+	}
+
+	/** Binds this sampler to the given texture unit. */
+	pub(crate) unsafe fn bind(&self, gl: &Context, unit: u32) {
+		gl.bind_sampler(unit, Some(self.inner.sampler))
+	}
+}
+
+/** Descriptor specifying all of the parameters for a newly created sampler. */
+#[derive(Debug, Copy, Clone)]
+pub struct SamplerDescriptor {
+	/** How textures will be filtered when they need to be downscaled. */
+	pub far: TextureFilter,
+	/** How textures will be filtered when they need to be upscaled. */
+	pub near: TextureFilter,
+	/** How the first texture coordinate gets handled outside of the texture. */
+	pub address_u: AddressMode,
+	/** How the second texture coordinate gets handled outside of the texture. */
+	pub address_v: AddressMode,
+	/** How the third texture coordinate gets handled outside of the texture. */
+	pub address_w: AddressMode,
+	/** The level of anisotropic filtering to be applied to textures.
+	 *
+	 * # Errors
+	 * This follows the same rules as the one in [`UniformBind::Texture`], with
+	 * the errors being raised when the sampler is created instead.
+	 *
+	 * [`UniformBind::Texture`]: crate::UniformBind::Texture */
+	pub anisotropy_clamp: Option<NonZeroU8>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SamplerError {
+	#[error("failed to create a new sampler: {what}")]
+	CreationFailed {
+		what: String
+	},
+	#[error("the sampler uses a feature the context doesn't support: {what}")]
+	UnsupportedFeature {
+		what: String
+	},
+	#[error("the sampler exceeds the limits of the context: {what}")]
+	LimitExceeded {
+		what: String
+	},
+}
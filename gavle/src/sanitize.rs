@@ -0,0 +1,241 @@
+use std::cell::Cell;
+use std::fmt::Debug;
+
+/** How the device should react to non-finite floating point values being
+ * written into buffers that hold floating point data.
+ *
+ * A single NaN in a transformation matrix is enough to poison everything drawn
+ * with it, and by the time that shows up on screen it is very hard to trace
+ * back to where it came from. The sanitizer catches these values at the moment
+ * they cross into device memory instead. */
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum SanitizeMode {
+	/** Don't check written data at all. This is the default. */
+	Off,
+	/** Log a warning for every write that contains non-finite values. */
+	Warn,
+	/** Panic on the first write that contains non-finite values. */
+	Panic,
+}
+impl Default for SanitizeMode {
+	fn default() -> Self {
+		Self::Off
+	}
+}
+
+/** The kind of data a buffer is going to hold.
+ *
+ * This is used to decide which buffers get checked by the write sanitizer, as
+ * reinterpreting integer data as floating point values would otherwise result
+ * in a lot of false positives. */
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum BufferContents {
+	/** The contents of the buffer are of no concern to the sanitizer. */
+	Opaque,
+	/** Every four-byte-aligned word of the buffer is an `f32` value. */
+	F32,
+}
+impl Default for BufferContents {
+	fn default() -> Self {
+		Self::Opaque
+	}
+}
+
+/** Shared state of the write sanitizer for a device. */
+#[derive(Debug, Default)]
+pub(crate) struct Sanitizer {
+	/** The currently active mode. */
+	mode: Cell<SanitizeMode>,
+}
+impl Sanitizer {
+	/** Change the currently active mode. */
+	pub(crate) fn set_mode(&self, mode: SanitizeMode) {
+		self.mode.set(mode)
+	}
+
+	/** Check the data being written at the given byte offset of a buffer
+	 * holding the given kind of contents, reacting to any non-finite values in
+	 * it according to the current mode.
+	 *
+	 * The `buffer` parameter is only used to identify the buffer in reports. */
+	pub(crate) fn check(
+		&self,
+		buffer: &dyn Debug,
+		contents: BufferContents,
+		offset: u32,
+		data: &[u8]) {
+
+		let hits = self.find(contents, offset, data);
+		self.report(buffer, &hits);
+	}
+
+	/** Find the non-finite values in the data being written at the given byte
+	 * offset of a buffer holding the given kind of contents, without reacting
+	 * to them yet, returning their buffer offsets and values.
+	 *
+	 * This is for writes that have to be wrapped up before the sanitizer gets
+	 * to panic, such as the ones to mapped buffers, which must not be left
+	 * mapped. The values found get reported with [`report()`] afterwards.
+	 *
+	 * [`report()`]: Self::report */
+	pub(crate) fn find(
+		&self,
+		contents: BufferContents,
+		offset: u32,
+		data: &[u8]) -> Vec<(u32, f32)> {
+
+		let mut hits = Vec::new();
+		if self.mode.get() == SanitizeMode::Off || contents != BufferContents::F32 {
+			return hits
+		}
+
+		scan_f32(offset, data, |at, value| hits.push((at, value)));
+		hits
+	}
+
+	/** React to the given non-finite values, found by [`find()`], according
+	 * to the current mode.
+	 *
+	 * Panicking while the thread is already unwinding would abort the whole
+	 * process, so, in that case, the values only get logged.
+	 *
+	 * [`find()`]: Self::find */
+	pub(crate) fn report(&self, buffer: &dyn Debug, hits: &[(u32, f32)]) {
+		let mode = self.mode.get();
+		for &(at, value) in hits {
+			match mode {
+				SanitizeMode::Panic if !std::thread::panicking() =>
+					panic!("non-finite value {} written to buffer {:?} at byte \
+						offset {}", value, buffer, at),
+				_ =>
+					rate_limited!(warn!(target: "gavle::buffer",
+						"non-finite value {} written to buffer {:?} at byte \
+						offset {}", value, buffer, at))
+			}
+		}
+	}
+}
+
+/** Number of `f32` lanes checked at a time by the fast path of the scan. */
+const LANES: usize = 16;
+
+/** Scan the given data, which starts at the given byte offset of a buffer, for
+ * non-finite `f32` values, calling the given function with the buffer offset
+ * and value of every one of them.
+ *
+ * Values are read from the four-byte-aligned words of the buffer, so bytes at
+ * the edges of the data that don't make up a whole aligned word are skipped.
+ * Data is checked in chunks, with a branch-free test over the lanes of each
+ * chunk that the compiler is free to vectorize, and only chunks that contain
+ * a hit get scanned value by value. */
+pub(crate) fn scan_f32<F>(offset: u32, data: &[u8], mut report: F)
+	where F: FnMut(u32, f32) {
+
+	/* Skip ahead to the first word boundary of the buffer. */
+	let skip = ((4 - offset % 4) % 4) as usize;
+	if data.len() <= skip { return }
+
+	let base = offset as usize + skip;
+	let data = &data[skip..];
+
+	let word = |bytes: &[u8]| u32::from_ne_bytes([
+		bytes[0], bytes[1], bytes[2], bytes[3]]);
+	let non_finite = |bits: u32| bits & 0x7f80_0000 == 0x7f80_0000;
+
+	let chunks = data.chunks_exact(LANES * 4);
+	let rest = chunks.remainder();
+
+	let mut scan = |start: usize, chunk: &[u8]| {
+		for (i, bytes) in chunk.chunks_exact(4).enumerate() {
+			let bits = word(bytes);
+			if non_finite(bits) {
+				let at = base + start + i * 4;
+				report(at as u32, f32::from_bits(bits));
+			}
+		}
+	};
+
+	let mut start = 0;
+	for chunk in chunks {
+		let mut hit = false;
+		for bytes in chunk.chunks_exact(4) {
+			hit |= non_finite(word(bytes));
+		}
+
+		if hit { scan(start, chunk) }
+		start += chunk.len();
+	}
+	scan(start, rest);
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn floats(values: &[f32]) -> Vec<u8> {
+		values.iter().flat_map(|value| value.to_ne_bytes().to_vec()).collect()
+	}
+
+	fn hits(offset: u32, data: &[u8]) -> Vec<u32> {
+		let mut hits = Vec::new();
+		scan_f32(offset, data, |at, _| hits.push(at));
+		hits
+	}
+
+	#[test]
+	fn finds_values_at_every_offset() {
+		/* Cover both the chunked path and the remainder. */
+		for position in 0..(LANES * 2 + 3) {
+			let mut values = vec![1.0f32; LANES * 2 + 3];
+			values[position] = f32::NAN;
+
+			assert_eq!(hits(0, &floats(&values)), vec![position as u32 * 4]);
+		}
+
+		let values = [0.0, f32::INFINITY, 2.0, f32::NEG_INFINITY];
+		assert_eq!(hits(64, &floats(&values)), vec![68, 76]);
+	}
+
+	#[test]
+	fn respects_word_alignment() {
+		/* Data starting in the middle of a word skips to the next word. */
+		let mut data = vec![0xffu8; 2];
+		data.extend(floats(&[1.0, f32::NAN]));
+
+		assert_eq!(hits(2, &data), vec![8]);
+		assert_eq!(hits(1, &[0xff; 3]), Vec::<u32>::new());
+	}
+
+	#[test]
+	fn only_checks_opted_in_buffers() {
+		let sanitizer = Sanitizer::default();
+		let data = floats(&[f32::NAN]);
+
+		sanitizer.set_mode(SanitizeMode::Panic);
+		sanitizer.check(&"opaque", BufferContents::Opaque, 0, &data);
+
+		sanitizer.set_mode(SanitizeMode::Off);
+		sanitizer.check(&"off", BufferContents::F32, 0, &data);
+	}
+
+	#[test]
+	fn finds_values_without_reporting_them() {
+		let sanitizer = Sanitizer::default();
+		let data = floats(&[0.0, f32::NAN, 1.0, f32::INFINITY]);
+
+		assert!(sanitizer.find(BufferContents::F32, 0, &data).is_empty());
+
+		sanitizer.set_mode(SanitizeMode::Panic);
+		let hits = sanitizer.find(BufferContents::F32, 8, &data);
+		assert_eq!(hits.iter().map(|(at, _)| *at).collect::<Vec<_>>(), vec![12, 20]);
+		assert!(sanitizer.find(BufferContents::Opaque, 0, &data).is_empty());
+	}
+
+	#[test]
+	#[should_panic(expected = "at byte offset 4")]
+	fn panics_on_non_finite_values() {
+		let sanitizer = Sanitizer::default();
+		sanitizer.set_mode(SanitizeMode::Panic);
+		sanitizer.check(&"f32", BufferContents::F32, 0, &floats(&[0.0, f32::NAN]));
+	}
+}
@@ -1,7 +1,10 @@
 use glow::{Context, HasContext};
 use std::rc::Rc;
 use std::borrow::Cow;
+use std::convert::TryFrom;
+use std::collections::HashMap;
 use crate::access::{AccessLock, UnitAccessLock};
+use crate::interface::ShaderInterface;
 
 #[derive(Debug)]
 pub(crate) struct InnerShader {
@@ -11,6 +14,11 @@ pub(crate) struct InnerShader {
 	pub(crate) access: UnitAccessLock,
 	/** The name of the underlying shader object. */
 	pub(crate) shader: <Context as HasContext>::Shader,
+	/** Declarations in the interface of the shader, as found in its source. */
+	pub(crate) interface: ShaderInterface,
+	/** Whether the shader was created from a SPIR-V module, whose interface
+	 * is neither scanned nor reflected by name. */
+	pub(crate) spirv: bool,
 }
 impl Drop for InnerShader {
 	fn drop(&mut self) {
@@ -35,7 +43,389 @@ pub enum ShaderSource<'a> {
 	 * The source will be fed to the driver for compilation and validation,
 	 * which may result in small CPU usage spikes, as parsing GLSL directly is
 	 * expensive. */
-	Glsl(Cow<'a, str>)
+	Glsl(Cow<'a, str>),
+	/** Module represented as SPIR-V words, in host byte order, whose `main`
+	 * entry point gets used with its specialization constants left at their
+	 * defaults.
+	 *
+	 * This is only supported by contexts with [`spirv_shaders`], everywhere
+	 * else creating a shader from it fails with
+	 * [`ShaderError::UnsupportedSource`]. Unlike with GLSL sources, the
+	 * interface of these modules doesn't get scanned, so the checks on how
+	 * much of the interface between stages a pipeline uses are skipped, and
+	 * both attributes and uniform blocks should be given explicit locations
+	 * and bindings in the module itself.
+	 *
+	 * [`spirv_shaders`]: crate::Capabilities::spirv_shaders */
+	SpirV(Cow<'a, [u32]>),
+	/** Module represented as GLSL source code put together from several
+	 * pieces, by [`ShaderSource::glsl_with_defines`] or by a
+	 * [`ShaderLibrary`].
+	 *
+	 * This gets compiled just like [`ShaderSource::Glsl`] does, except that
+	 * the line numbers in compilation errors get mapped back to the pieces
+	 * they came from. */
+	Preprocessed(PreprocessedGlsl)
+}
+impl ShaderSource<'_> {
+	/** Creates a GLSL source from the given code, with a `#define` for each
+	 * of the given name and value pairs put right after its `#version` line,
+	 * or at the very top, if it has none.
+	 *
+	 * `#include` directives are left alone, use [`ShaderLibrary`] for those. */
+	pub fn glsl_with_defines(
+		source: &str,
+		defines: &[(&str, &str)]) -> ShaderSource<'static> {
+
+		let preprocessed = preprocess(source, defines, None)
+			.expect("preprocessing without a library never fails");
+		ShaderSource::Preprocessed(preprocessed)
+	}
+}
+
+/** GLSL source code put together from several pieces, along with the table
+ * telling which piece, and which line in it, each of its lines came from. */
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct PreprocessedGlsl {
+	/** The source code, as it gets fed to the driver. */
+	source: String,
+	/** Spans of consecutive lines coming from the same piece, in order. */
+	spans: Vec<LineSpan>,
+}
+impl PreprocessedGlsl {
+	/** The source code, as it gets fed to the driver. */
+	pub fn as_str(&self) -> &str {
+		&self.source
+	}
+
+	/** The piece the given line of the source came from, along with the line
+	 * in that piece it corresponds to. Lines start at one, as in compilation
+	 * errors. */
+	pub(crate) fn locate(&self, line: u32) -> Option<(&str, u32)> {
+		let index = self.spans.iter()
+			.rposition(|span| span.start <= line)?;
+		let span = &self.spans[index];
+
+		Some((&span.origin, span.line + (line - span.start)))
+	}
+
+	/** Rewrites the locations in the given compilation log so that they point
+	 * to the pieces the lines came from, rather than to the source as a whole.
+	 *
+	 * Drivers don't agree on how to format locations, so this understands the
+	 * `0:12` and `0(12)` forms, which cover the ones we know of, and leaves
+	 * lines it finds neither in alone. Only the first location in each line
+	 * gets rewritten, as that's where drivers put it. */
+	pub(crate) fn map_log(&self, log: &str) -> String {
+		let mut mapped = String::with_capacity(log.len());
+		for line in log.split_inclusive('\n') {
+			match find_location(line) {
+				Some(location) => {
+					let original = self.locate(location.line);
+					match original {
+						Some((origin, original)) => {
+							mapped.push_str(&line[..location.start]);
+							mapped.push_str(origin);
+							if location.parenthesized {
+								mapped.push_str(&format!("({})", original));
+							} else {
+								mapped.push_str(&format!(":{}", original));
+							}
+							mapped.push_str(&line[location.end..]);
+						},
+						None => mapped.push_str(line)
+					}
+				},
+				None => mapped.push_str(line)
+			}
+		}
+
+		mapped
+	}
+}
+
+/** Span of consecutive lines in preprocessed source code, all of which came
+ * from consecutive lines in the same piece. */
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct LineSpan {
+	/** First line of the span in the preprocessed source. */
+	start: u32,
+	/** Name of the piece the lines came from. */
+	origin: String,
+	/** Line of the piece the first line of the span came from. */
+	line: u32,
+}
+
+/** Name under which the lines of the source being preprocessed are reported,
+ * which is the same name drivers give it, as it's the only string we feed to
+ * them. */
+const ROOT_ORIGIN: &str = "0";
+
+/** Name under which the lines with injected `#define`s are reported. */
+const DEFINES_ORIGIN: &str = "<defines>";
+
+/** Most nested includes allowed in a single source, past which an include is
+ * assumed to be cyclic. */
+const MAX_INCLUDE_DEPTH: usize = 32;
+
+/** Named GLSL snippets, which get spliced into the sources preprocessed by it
+ * wherever they contain an `#include "name"` directive.
+ *
+ * Includes are spliced in unconditionally, so an `#include` inside of an
+ * `#ifdef` block, or inside of a comment, gets expanded all the same, and a
+ * snippet included more than once gets spliced in more than once. Snippets
+ * meant to be included that way should have include guards of their own. */
+#[derive(Debug, Clone, Default)]
+pub struct ShaderLibrary {
+	/** Registered snippets, by name. */
+	snippets: HashMap<String, String>,
+}
+impl ShaderLibrary {
+	/** Creates a new, empty library. */
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/** Registers a snippet under the given name, returning the one it
+	 * replaces, if any. */
+	pub fn register<N, S>(&mut self, name: N, snippet: S) -> Option<String>
+		where N: Into<String>,
+			  S: Into<String> {
+
+		self.snippets.insert(name.into(), snippet.into())
+	}
+
+	/** Preprocesses the given GLSL code, splicing the snippets it includes
+	 * into it.
+	 *
+	 * # Errors
+	 * This function fails if the code includes a snippet that isn't in the
+	 * library, if the includes are nested too deeply, which is usually the
+	 * case when they are cyclic, or if an `#include` directive has no quoted
+	 * name after it. */
+	pub fn source(&self, source: &str) -> Result<ShaderSource<'static>, ShaderError> {
+		self.source_with_defines(source, &[])
+	}
+
+	/** Preprocesses the given GLSL code, splicing the snippets it includes
+	 * into it and adding a `#define` for each of the given name and value
+	 * pairs, just like [`ShaderSource::glsl_with_defines`] does.
+	 *
+	 * # Errors
+	 * This function fails under the same conditions as [`source`] does.
+	 *
+	 * [`source`]: Self::source */
+	pub fn source_with_defines(
+		&self,
+		source: &str,
+		defines: &[(&str, &str)]) -> Result<ShaderSource<'static>, ShaderError> {
+
+		preprocess(source, defines, Some(self))
+			.map(ShaderSource::Preprocessed)
+	}
+}
+
+/** Builder of preprocessed source code, which keeps track of where its lines
+ * came from as they get pushed into it. */
+struct Builder {
+	/** Source code put together so far. */
+	source: String,
+	/** Spans of lines pushed so far. */
+	spans: Vec<LineSpan>,
+	/** Number of lines pushed so far. */
+	lines: u32,
+}
+impl Builder {
+	/** Pushes the given line, which came from the given line of the given
+	 * piece, starting a new span if it doesn't follow the last one. */
+	fn push(&mut self, text: &str, origin: &str, line: u32) {
+		self.lines += 1;
+
+		let continues = self.spans.last()
+			.map(|span| span.origin == origin
+				&& span.line + (self.lines - span.start) == line)
+			.unwrap_or(false);
+		if !continues {
+			self.spans.push(LineSpan {
+				start: self.lines,
+				origin: origin.to_owned(),
+				line
+			});
+		}
+
+		self.source.push_str(text);
+		self.source.push('\n');
+	}
+}
+
+/** Preprocesses the given source, adding the given defines to it and, when
+ * given a library, splicing in the snippets it includes. */
+fn preprocess(
+	source: &str,
+	defines: &[(&str, &str)],
+	library: Option<&ShaderLibrary>) -> Result<PreprocessedGlsl, ShaderError> {
+
+	let mut builder = Builder {
+		source: String::with_capacity(source.len()),
+		spans: Vec::new(),
+		lines: 0
+	};
+
+	/* Defines go right after the version, which must come before anything
+	 * else in the source, save for comments and blank lines. */
+	let version = source.lines()
+		.position(|line| directive(line) == Some("version"))
+		.map(|index| index + 1)
+		.unwrap_or(0);
+	let (head, tail) = split_lines(source, version);
+
+	expand(&mut builder, head, ROOT_ORIGIN, 1, library, &mut Vec::new())?;
+	for (line, (name, value)) in (1..).zip(defines) {
+		builder.push(&format!("#define {} {}", name, value), DEFINES_ORIGIN, line);
+	}
+
+	let first = u32::try_from(version).unwrap_or(u32::MAX).saturating_add(1);
+	expand(&mut builder, tail, ROOT_ORIGIN, first, library, &mut Vec::new())?;
+
+	Ok(PreprocessedGlsl {
+		source: builder.source,
+		spans: builder.spans
+	})
+}
+
+/** Pushes the lines of the given piece into the builder, splicing in the
+ * snippets they include if there's a library to take them from. The first
+ * line is numbered `first`, and `stack` holds the snippets being expanded. */
+fn expand<'a>(
+	builder: &mut Builder,
+	text: &str,
+	origin: &str,
+	first: u32,
+	library: Option<&'a ShaderLibrary>,
+	stack: &mut Vec<&'a str>) -> Result<(), ShaderError> {
+
+	for (index, line) in text.lines().enumerate() {
+		let number = first
+			.saturating_add(u32::try_from(index).unwrap_or(u32::MAX));
+
+		let library = match library {
+			Some(library) if directive(line) == Some("include") => library,
+			_ => {
+				builder.push(line, origin, number);
+				continue
+			}
+		};
+
+		let name = include_name(line)
+			.ok_or_else(|| ShaderError::PreprocessingFailed {
+				what: format!("{}:{}: expected a quoted name after #include",
+					origin, number)
+			})?;
+		let (name, snippet) = library.snippets.get_key_value(name)
+			.ok_or_else(|| ShaderError::PreprocessingFailed {
+				what: format!("{}:{}: there is no snippet named \"{}\" in \
+					the library", origin, number, name)
+			})?;
+		if stack.len() >= MAX_INCLUDE_DEPTH {
+			return Err(ShaderError::PreprocessingFailed {
+				what: format!("{}:{}: includes are nested more than {} \
+					levels deep, through {}", origin, number, MAX_INCLUDE_DEPTH,
+					stack.join(" -> "))
+			})
+		}
+
+		stack.push(name);
+		expand(builder, snippet, name, 1, Some(library), stack)?;
+		stack.pop();
+	}
+
+	Ok(())
+}
+
+/** Name of the preprocessor directive in the given line, if it has one. */
+fn directive(line: &str) -> Option<&str> {
+	let rest = line.trim_start().strip_prefix('#')?.trim_start();
+	let end = rest.find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+		.unwrap_or(rest.len());
+
+	Some(&rest[..end])
+}
+
+/** Quoted name in the given `#include` directive, if it has a well formed
+ * one, which may only be followed by a line comment. */
+fn include_name(line: &str) -> Option<&str> {
+	let rest = line.trim_start()
+		.strip_prefix('#')?
+		.trim_start()
+		.strip_prefix("include")?
+		.trim_start()
+		.strip_prefix('"')?;
+	let end = rest.find('"')?;
+
+	let trailing = rest[end + 1..].trim();
+	if !trailing.is_empty() && !trailing.starts_with("//") {
+		return None
+	}
+
+	Some(&rest[..end])
+}
+
+/** Splits the given text into its first `count` lines and the rest of it. */
+fn split_lines(text: &str, count: usize) -> (&str, &str) {
+	if count == 0 { return ("", text) }
+	match text.match_indices('\n').nth(count - 1) {
+		Some((index, _)) => text.split_at(index + 1),
+		None => (text, "")
+	}
+}
+
+/** Location of a line number in a line of a compilation log. */
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+struct LogLocation {
+	/** Index at which the location starts. */
+	start: usize,
+	/** Index right past the end of the location. */
+	end: usize,
+	/** The line number. */
+	line: u32,
+	/** Whether the location is of the `0(12)` form, rather than `0:12`. */
+	parenthesized: bool,
+}
+
+/** Finds the first location of the `0:12` or `0(12)` forms in the given line
+ * of a compilation log, not counting the ones preceded by another digit. */
+fn find_location(line: &str) -> Option<LogLocation> {
+	let bytes = line.as_bytes();
+	for start in 0..bytes.len() {
+		if bytes[start] != b'0' { continue }
+		if start > 0 && bytes[start - 1].is_ascii_alphanumeric() { continue }
+
+		let parenthesized = match bytes.get(start + 1) {
+			Some(b':') => false,
+			Some(b'(') => true,
+			_ => continue
+		};
+		let digits = bytes[start + 2..].iter()
+			.take_while(|byte| byte.is_ascii_digit())
+			.count();
+		if digits == 0 { continue }
+
+		let mut end = start + 2 + digits;
+		if parenthesized {
+			if bytes.get(end) != Some(&b')') { continue }
+			end += 1;
+		}
+
+		let number = &line[start + 2..start + 2 + digits];
+		let number = match number.parse() {
+			Ok(number) => number,
+			Err(_) => continue
+		};
+
+		return Some(LogLocation { start, end, line: number, parenthesized })
+	}
+
+	None
 }
 
 /**
@@ -106,5 +496,114 @@ pub enum ShaderError {
 	#[error("could not compile shader object: {what}")]
 	CompilationFailed {
 		what: String
+	},
+	#[error("the shader source is not supported by the context: {what}")]
+	UnsupportedSource {
+		what: String
+	},
+	#[error("could not preprocess shader source: {what}")]
+	PreprocessingFailed {
+		what: String
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn preprocessed(source: ShaderSource) -> PreprocessedGlsl {
+		match source {
+			ShaderSource::Preprocessed(source) => source,
+			other => panic!("expected a preprocessed source, got {:?}", other)
+		}
+	}
+
+	#[test]
+	fn defines_go_after_the_version() {
+		let source = preprocessed(ShaderSource::glsl_with_defines(
+			"// Header.\n#version 300 es\nvoid main() {}\n",
+			&[("LIGHTS", "4"), ("SHADOWS", "1")]));
+
+		assert_eq!(source.as_str(),
+			"// Header.\n#version 300 es\n#define LIGHTS 4\n#define SHADOWS 1\n\
+			void main() {}\n");
+		assert_eq!(source.locate(2), Some(("0", 2)));
+		assert_eq!(source.locate(4), Some(("<defines>", 2)));
+		assert_eq!(source.locate(5), Some(("0", 3)));
+	}
+
+	#[test]
+	fn defines_go_on_top_without_a_version() {
+		let source = preprocessed(ShaderSource::glsl_with_defines(
+			"void main() {}",
+			&[("LIGHTS", "4")]));
+
+		assert_eq!(source.as_str(), "#define LIGHTS 4\nvoid main() {}\n");
+		assert_eq!(source.locate(2), Some(("0", 1)));
+	}
+
+	#[test]
+	fn includes_are_spliced_in() {
+		let mut library = ShaderLibrary::new();
+		library.register("light", "struct Light { vec3 color; };\n#include \"math\"");
+		library.register("math", "float sq(float x) {\n\treturn x * x;\n}");
+
+		let source = preprocessed(library.source(
+			"#version 300 es\n#include \"light\" // Lights.\nvoid main() {}")
+			.unwrap());
+
+		assert_eq!(source.as_str(),
+			"#version 300 es\nstruct Light { vec3 color; };\n\
+			float sq(float x) {\n\treturn x * x;\n}\nvoid main() {}\n");
+		assert_eq!(source.locate(1), Some(("0", 1)));
+		assert_eq!(source.locate(2), Some(("light", 1)));
+		assert_eq!(source.locate(4), Some(("math", 2)));
+		assert_eq!(source.locate(6), Some(("0", 3)));
+		assert_eq!(source.locate(0), None);
+	}
+
+	#[test]
+	fn includes_are_left_alone_without_a_library() {
+		let source = preprocessed(ShaderSource::glsl_with_defines(
+			"#include \"light\"", &[]));
+		assert_eq!(source.as_str(), "#include \"light\"\n");
+	}
+
+	#[test]
+	fn bad_includes_are_rejected() {
+		let mut library = ShaderLibrary::new();
+		library.register("a", "#include \"b\"");
+		library.register("b", "#include \"a\"");
+
+		assert!(matches!(library.source("#include \"c\""),
+			Err(ShaderError::PreprocessingFailed { .. })));
+		assert!(matches!(library.source("#include <a>"),
+			Err(ShaderError::PreprocessingFailed { .. })));
+		assert!(matches!(library.source("#include \"a\""),
+			Err(ShaderError::PreprocessingFailed { .. })));
+	}
+
+	#[test]
+	fn logs_point_to_the_original_lines() {
+		let mut library = ShaderLibrary::new();
+		library.register("light", "vec3 light;\nvec3 broken");
+		let source = preprocessed(library.source_with_defines(
+			"#version 300 es\n#include \"light\"\nvoid main() {}",
+			&[("LIGHTS", "4")])
+			.unwrap());
+
+		/* Mesa, NVIDIA and ANGLE, in that order. */
+		assert_eq!(
+			source.map_log("0:4(1): error: syntax error\n"),
+			"light:2(1): error: syntax error\n");
+		assert_eq!(
+			source.map_log("0(5) : error C0000: syntax error\n"),
+			"0(3) : error C0000: syntax error\n");
+		assert_eq!(
+			source.map_log("ERROR: 0:2: 'LIGHTS' : redefinition\n\
+				ERROR: 1 compilation errors.\n"),
+			"ERROR: <defines>:1: 'LIGHTS' : redefinition\n\
+				ERROR: 1 compilation errors.\n");
+		assert_eq!(source.map_log("10:4 unrelated"), "10:4 unrelated");
 	}
 }
@@ -0,0 +1,127 @@
+use glow::{Context, HasContext};
+use std::convert::TryFrom;
+use std::os::raw::{c_char, c_void};
+use crate::shader::ShaderError;
+
+/** Magic number every SPIR-V module starts with, in host byte order. */
+const SPIRV_MAGIC: u32 = 0x0723_0203;
+
+/** Signature of `glShaderBinary`. */
+type ShaderBinary = unsafe extern "system" fn(
+	count: i32,
+	shaders: *const u32,
+	format: u32,
+	binary: *const c_void,
+	length: i32);
+
+/** Signature of `glSpecializeShader`, which is the same as the one of
+ * `glSpecializeShaderARB`. */
+type SpecializeShader = unsafe extern "system" fn(
+	shader: u32,
+	entry_point: *const c_char,
+	constants: u32,
+	indices: *const u32,
+	values: *const u32);
+
+/** Entry points needed to create shaders from SPIR-V modules.
+ *
+ * The bindings we use don't expose either of these functions, so they have to
+ * be loaded separately, through the same loader function the context was
+ * loaded with. That means they're only ever available to devices created with
+ * [`Device::new_from_loader_function`].
+ *
+ * [`Device::new_from_loader_function`]: crate::Device::new_from_loader_function */
+#[derive(Debug)]
+pub(crate) struct SpirVFunctions {
+	shader_binary: ShaderBinary,
+	specialize_shader: SpecializeShader,
+}
+impl SpirVFunctions {
+	/** Loads the entry points using the given loader function, looking for
+	 * the core version of `glSpecializeShader` first and the one from
+	 * `ARB_gl_spirv` after it. Returns `None` if any of them is missing. */
+	pub(crate) unsafe fn load<F>(mut loader: F) -> Option<Self>
+		where F: FnMut(&str) -> *const c_void {
+
+		let shader_binary = loader("glShaderBinary");
+		let specialize_shader = match loader("glSpecializeShader") {
+			pointer if pointer.is_null() => loader("glSpecializeShaderARB"),
+			pointer => pointer
+		};
+		if shader_binary.is_null() || specialize_shader.is_null() {
+			return None
+		}
+
+		Some(Self {
+			shader_binary: std::mem::transmute::<_, ShaderBinary>(shader_binary),
+			specialize_shader:
+				std::mem::transmute::<_, SpecializeShader>(specialize_shader),
+		})
+	}
+
+	/** Loads the given SPIR-V module into the given shader object and
+	 * specializes its `main` entry point, with all of its specialization
+	 * constants left at their default values.
+	 *
+	 * Just like with compiling GLSL shaders, whether this worked is told by
+	 * the compile status of the shader afterwards, which is also the case for
+	 * modules the driver refuses to load. */
+	pub(crate) unsafe fn specialize(
+		&self,
+		shader: <Context as HasContext>::Shader,
+		module: &[u32]) -> Result<(), ShaderError> {
+
+		check_module(module)?;
+		let length = module.len()
+			.checked_mul(std::mem::size_of::<u32>())
+			.and_then(|length| i32::try_from(length).ok())
+			.ok_or_else(|| ShaderError::CompilationFailed {
+				what: format!("the module is too large, at {} words",
+					module.len())
+			})?;
+
+		(self.shader_binary)(
+			1,
+			&shader,
+			glow::SHADER_BINARY_FORMAT_SPIR_V,
+			module.as_ptr() as *const c_void,
+			length);
+		(self.specialize_shader)(
+			shader,
+			b"main\0".as_ptr() as *const c_char,
+			0,
+			std::ptr::null(),
+			std::ptr::null());
+
+		Ok(())
+	}
+}
+
+/** Checks whether the given words look like a SPIR-V module, which catches
+ * modules loaded with the wrong byte order before the driver gets to them. */
+fn check_module(module: &[u32]) -> Result<(), ShaderError> {
+	match module.first() {
+		Some(&SPIRV_MAGIC) => Ok(()),
+		Some(&magic) if magic.swap_bytes() == SPIRV_MAGIC =>
+			Err(ShaderError::CompilationFailed {
+				what: "the module is in the wrong byte order".into()
+			}),
+		_ => Err(ShaderError::CompilationFailed {
+			what: "the module does not start with the SPIR-V magic number"
+				.into()
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn checks_modules() {
+		assert!(check_module(&[SPIRV_MAGIC, 0x0001_0000]).is_ok());
+		assert!(check_module(&[SPIRV_MAGIC.swap_bytes(), 0x0000_0100]).is_err());
+		assert!(check_module(&[0xdead_beef]).is_err());
+		assert!(check_module(&[]).is_err());
+	}
+}
@@ -0,0 +1,118 @@
+use glow::{Context, HasContext};
+use std::convert::TryFrom;
+use std::os::raw::c_void;
+use crate::info::{Capabilities, Profile};
+
+/** Signature of `glGetFramebufferAttachmentParameteriv`. */
+type GetFramebufferAttachmentParameteriv = unsafe extern "system" fn(
+	target: u32,
+	attachment: u32,
+	parameter: u32,
+	value: *mut i32);
+
+/** Name the color attachment of the default framebuffer goes by when it gets
+ * queried in contexts of the given profile, or `None` if its encoding can't be
+ * queried there. WebGL never gives the default framebuffer the sRGB encoding,
+ * and legacy contexts have no way to tell encodings apart. */
+fn default_color_attachment(profile: Profile) -> Option<u32> {
+	match profile {
+		Profile::Core => Some(glow::BACK_LEFT),
+		Profile::Es => Some(glow::BACK),
+		Profile::Web | Profile::EsLegacy | Profile::WebLegacy => None,
+	}
+}
+
+/** Whether the given value of `GL_FRAMEBUFFER_ATTACHMENT_COLOR_ENCODING` is
+ * the one of the sRGB encoding. */
+fn is_srgb_encoding(encoding: i32) -> bool {
+	u32::try_from(encoding) == Ok(glow::SRGB)
+}
+
+/** Entry point for querying the attachments of framebuffers.
+ *
+ * Just like with [`SpirVFunctions`], the bindings we use don't expose it, so
+ * it has to be loaded through the same loader function the context was loaded
+ * with, and only devices created with [`Device::new_from_loader_function`] get
+ * to know whether their default framebuffer uses the sRGB encoding.
+ *
+ * [`SpirVFunctions`]: crate::spirv::SpirVFunctions
+ * [`Device::new_from_loader_function`]: crate::Device::new_from_loader_function */
+#[derive(Debug)]
+pub(crate) struct FramebufferQuery {
+	get_attachment_parameter: GetFramebufferAttachmentParameteriv,
+}
+impl FramebufferQuery {
+	/** Loads the entry point using the given loader function, returning
+	 * `None` if it is missing. */
+	pub(crate) unsafe fn load<F>(mut loader: F) -> Option<Self>
+		where F: FnMut(&str) -> *const c_void {
+
+		let pointer = loader("glGetFramebufferAttachmentParameteriv");
+		if pointer.is_null() { return None }
+
+		Some(Self {
+			get_attachment_parameter:
+				std::mem::transmute::<_, GetFramebufferAttachmentParameteriv>(pointer)
+		})
+	}
+
+	/** Whether the color attachment of the default framebuffer of the given
+	 * context stores its colors with the sRGB encoding. Queries that raise
+	 * errors, as some implementations do for single-buffered surfaces, count
+	 * as it not doing so. */
+	pub(crate) unsafe fn default_framebuffer_is_srgb(
+		&self,
+		gl: &Context,
+		profile: Profile) -> bool {
+
+		let attachment = match default_color_attachment(profile) {
+			Some(attachment) => attachment,
+			None => return false
+		};
+
+		let mut encoding = 0;
+		gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+		(self.get_attachment_parameter)(
+			glow::FRAMEBUFFER,
+			attachment,
+			glow::FRAMEBUFFER_ATTACHMENT_COLOR_ENCODING,
+			&mut encoding);
+
+		gl.get_error() == glow::NO_ERROR && is_srgb_encoding(encoding)
+	}
+}
+
+/** Turns the encoding of colors written to attachments that use the sRGB
+ * encoding on or off, in contexts with the [`srgb_write_control`] capability.
+ * Everywhere else, those colors always get encoded.
+ *
+ * [`srgb_write_control`]: crate::Capabilities::srgb_write_control */
+pub(crate) unsafe fn set_srgb_writes(
+	gl: &Context,
+	capabilities: &Capabilities,
+	enabled: bool) {
+
+	if !capabilities.srgb_write_control { return }
+	if enabled {
+		gl.enable(glow::FRAMEBUFFER_SRGB)
+	} else {
+		gl.disable(glow::FRAMEBUFFER_SRGB)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn default_framebuffer_encoding_queries() {
+		assert_eq!(default_color_attachment(Profile::Core), Some(glow::BACK_LEFT));
+		assert_eq!(default_color_attachment(Profile::Es), Some(glow::BACK));
+		assert_eq!(default_color_attachment(Profile::Web), None);
+		assert_eq!(default_color_attachment(Profile::EsLegacy), None);
+
+		assert!(is_srgb_encoding(glow::SRGB as i32));
+		assert!(!is_srgb_encoding(glow::LINEAR as i32));
+		assert!(!is_srgb_encoding(-1));
+	}
+}
@@ -0,0 +1,289 @@
+use std::borrow::Cow;
+use crate::framebuffer::*;
+use crate::pipeline::*;
+use crate::texture::{Texture, TextureDescriptor, TextureExtent, TextureFormat, Mipmap};
+use crate::shader::ShaderSource;
+use crate::pass::{RenderPassDescriptor, Viewport};
+
+/** Vertex shader drawing a single triangle that covers the whole target, out
+ * of nothing but the index of each vertex. */
+const FULLSCREEN_VERTEX_SHADER: &str = "#version 300 es
+void main() {
+	vec2 position = vec2(
+		float((gl_VertexID & 1) << 2),
+		float((gl_VertexID & 2) << 1)) - 1.0;
+	gl_Position = vec4(position, 0.0, 1.0);
+}
+";
+
+/** Fragment shader writing full intensity to every channel, which the blend
+ * constant then scales down to the stencil value being extracted. */
+const CONSTANT_FRAGMENT_SHADER: &str = "#version 300 es
+precision mediump float;
+out vec4 color;
+void main() {
+	color = vec4(1.0);
+}
+";
+
+/** The blend constant that makes a fragment of full intensity come out of the
+ * blender with the given stencil value in every channel of an
+ * [`TextureFormat::Rgba8Unorm`] target. */
+pub(crate) fn reference_color(reference: u8) -> Color {
+	let value = f32::from(reference) / 255.0;
+	Color {
+		red: value,
+		green: value,
+		blue: value,
+		alpha: value
+	}
+}
+
+/** Keeps only the red channel of tightly packed [`TextureFormat::Rgba8Unorm`]
+ * pixels, which is the one the stencil values get read back from. */
+pub(crate) fn red_channel(pixels: &[u8]) -> Vec<u8> {
+	pixels.chunks_exact(4).map(|pixel| pixel[0]).collect()
+}
+
+impl Framebuffer {
+	/** Copies the stencil aspect of the depth stencil attachment of this
+	 * framebuffer into the given texture, as color, so that it can be sampled
+	 * from or read back. Every texel of the target ends up with the stencil
+	 * value of its matching texel in every one of its channels, normalized to
+	 * the `[0; 1]` range.
+	 *
+	 * Stencil values can't be read directly in a portable way, so this draws
+	 * a quad over the whole target for every possible nonzero stencil value,
+	 * each one passing the stencil test only where the stencil buffer holds
+	 * that value. Those are 255 draw calls, which makes this function meant
+	 * for debugging, rather than for use every frame.
+	 *
+	 * # Errors
+	 * The target must be a two-dimensional [`TextureFormat::Rgba8Unorm`]
+	 * texture with the same size as the depth stencil attachment, and this
+	 * framebuffer must be a custom framebuffer with a depth stencil
+	 * attachment. The stencil buffer of the default framebuffer can't be
+	 * attached anywhere else, so it can't be extracted.
+	 *
+	 * # Panic
+	 * This function will panic if the pipeline can't be locked mutably, as is
+	 * the case while a render pass is running, or if either this framebuffer
+	 * or the target is in use. */
+	pub fn extract_stencil(&self, device: &crate::Device, target: &Texture)
+		-> Result<(), FramebufferError> {
+
+		self.extract_stencil_into(device, target).map(|_| ())
+	}
+
+	/** Reads back the stencil values in the given region of the depth stencil
+	 * attachment of this framebuffer, by extracting them into a temporary
+	 * texture with [`Framebuffer::extract_stencil()`].
+	 *
+	 * Values are tightly packed, one byte per texel, with rows going from the
+	 * bottom of the region to the top, the same as in
+	 * [`Framebuffer::read_pixels()`].
+	 *
+	 * # Errors
+	 * This framebuffer must be a custom framebuffer with a depth stencil
+	 * attachment, and the region must lie within it.
+	 *
+	 * # Panic
+	 * This function will panic for the same reasons
+	 * [`Framebuffer::extract_stencil()`] does.
+	 *
+	 * [`Framebuffer::extract_stencil()`]: Framebuffer::extract_stencil
+	 * [`Framebuffer::read_pixels()`]: Framebuffer::read_pixels */
+	pub fn read_stencil(&self, device: &crate::Device, region: Viewport)
+		-> Result<Vec<u8>, FramebufferError> {
+
+		let (width, height, _) = self.stencil_attachment()?.extent().axes();
+		let target = device.create_texture(&TextureDescriptor {
+			extent: TextureExtent::D2 { width, height },
+			format: TextureFormat::Rgba8Unorm,
+			mip: Mipmap::None,
+			swizzle: None
+		}).map_err(|what| FramebufferError::CreationError {
+			what: format!("could not create the texture the stencil values \
+				get extracted into: {}", what)
+		})?;
+		target.set_label("stencil readback");
+
+		let framebuffer = self.extract_stencil_into(device, &target)?;
+		let pixels = framebuffer.read_pixels(region, TextureFormat::Rgba8Unorm)?;
+
+		Ok(red_channel(&pixels))
+	}
+
+	/** The depth stencil attachment of this framebuffer, if it has one the
+	 * stencil values can be extracted from. */
+	fn stencil_attachment(&self) -> Result<&Texture, FramebufferError> {
+		let inner = match &self.variants {
+			FramebufferVariants::Custom { inner } => inner,
+			FramebufferVariants::Default { .. } =>
+				return Err(FramebufferError::InvalidAttachment {
+					what: "the stencil buffer of the default framebuffer \
+						can't be extracted".into()
+				})
+		};
+
+		inner.depth_stencil.as_ref()
+			.ok_or_else(|| FramebufferError::InvalidAttachment {
+				what: format!("framebuffer {} has no depth stencil attachment \
+					to extract the stencil values from",
+					self.name())
+			})
+	}
+
+	/** Extracts the stencil values of this framebuffer into the given target,
+	 * returning the framebuffer the target was drawn to through, so that it
+	 * can be read back from. */
+	fn extract_stencil_into(&self, device: &crate::Device, target: &Texture)
+		-> Result<Framebuffer, FramebufferError> {
+
+		let attachment = self.stencil_attachment()?;
+		let (layer, mip_level, (width, height)) = match &self.variants {
+			FramebufferVariants::Custom { inner } => (
+				inner.depth_stencil_layer,
+				inner.depth_stencil_level,
+				inner.depth_stencil_size().unwrap()),
+			FramebufferVariants::Default { .. } => unreachable!()
+		};
+
+		if target.format() != TextureFormat::Rgba8Unorm {
+			return Err(FramebufferError::UnsupportedFormat {
+				what: format!("stencil values can only be extracted into {:?} \
+					textures, but texture {} is in the {:?} format",
+					TextureFormat::Rgba8Unorm,
+					target.inner.name(),
+					target.format())
+			})
+		}
+		match target.extent() {
+			TextureExtent::D2 { width: w, height: h } if (w, h) == (width, height) => {},
+			extent => return Err(FramebufferError::InvalidAttachment {
+				what: format!("stencil values can only be extracted into a \
+					two-dimensional texture of the same size as the {}x{} \
+					depth stencil attachment, but texture {} has extent {:?}",
+					width,
+					height,
+					target.inner.name(),
+					extent)
+			})
+		}
+
+		let framebuffer = device.create_framebuffer(&FramebufferDescriptor {
+			color_attachments: &[FramebufferColorAttachmentDescriptor {
+				attachment: target,
+				layer: 0,
+				mip_level: 0,
+				load_op: LoadOp::Clear(reference_color(0))
+			}],
+			depth_stencil_attachment: Some(FramebufferDepthStencilAttachmentDescriptor {
+				attachment,
+				layer,
+				mip_level,
+				depth_load_op: LoadOp::Load,
+				stencil_load_op: LoadOp::Load
+			})
+		})?;
+		framebuffer.set_label("stencil extraction");
+
+		let pipeline = extraction_pipeline(device)?;
+		let mut pass = device.start_render_pass(&RenderPassDescriptor {
+			pipeline: &pipeline,
+			framebuffer: &framebuffer
+		});
+		pass.set_viewport(Viewport {
+			x: 0,
+			y: 0,
+			width,
+			height
+		}).expect("the stencil attachment must fit in the viewport limits");
+
+		/* Texels with a stencil value of zero are taken care of by clearing
+		 * the target, every other value gets a draw of its own. */
+		for reference in 1..=u8::MAX {
+			pass.set_stencil_reference(reference);
+			pass.set_blend_constant(reference_color(reference));
+			pass.draw(0..3, 1)
+				.expect("drawing a triangle with no vertex buffer can't fail");
+		}
+		drop(pass);
+
+		Ok(framebuffer)
+	}
+}
+
+/** Creates the pipeline used to extract stencil values, which passes the
+ * stencil test only where the stencil buffer holds the reference value, and
+ * writes the blend constant to the color target where it does. */
+fn extraction_pipeline(device: &crate::Device)
+	-> Result<RenderPipeline, FramebufferError> {
+
+	let creation_error = |what: String| FramebufferError::CreationError {
+		what: format!("could not create the stencil extraction pipeline: {}",
+			what)
+	};
+
+	let vertex = device.create_vertex_shader(
+		ShaderSource::Glsl(Cow::Borrowed(FULLSCREEN_VERTEX_SHADER)))
+		.map_err(|what| creation_error(what.to_string()))?;
+	let fragment = device.create_fragment_shader(
+		ShaderSource::Glsl(Cow::Borrowed(CONSTANT_FRAGMENT_SHADER)))
+		.map_err(|what| creation_error(what.to_string()))?;
+
+	let blend = BlendState {
+		src_factor: BlendFactor::BlendColor,
+		dst_factor: BlendFactor::Zero,
+		operation: BlendOperation::Add
+	};
+	let layout = VertexBufferLayout {
+		array_stride: 0,
+		attributes: &[]
+	};
+
+	let pipeline = device.create_render_pipeline(
+		&RenderPipelineDescriptor::new(&vertex, &layout)
+			.with_fragment_state(FragmentState::new(&fragment)
+				.with_targets(&[ColorTargetState {
+					alpha_blend: blend,
+					color_blend: blend,
+					write_mask: ColorWrite::ALL
+				}]))
+			.with_depth(DepthStencilState::new(
+				false,
+				CompareFunction::Always,
+				StencilState {
+					write_mask: 0,
+					read_mask: 0xff,
+					compare: CompareFunction::Equal,
+					fail_op: StencilOperation::Keep,
+					depth_fail_op: StencilOperation::Keep,
+					pass_op: StencilOperation::Keep
+				})))
+		.map_err(|what| creation_error(what.to_string()))?;
+	pipeline.set_label("stencil extraction");
+
+	Ok(pipeline)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn stencil_values_survive_normalization() {
+		for reference in 0..=u8::MAX {
+			let color = reference_color(reference);
+			let texel = (color.red * 255.0).round() as u8;
+			assert_eq!(texel, reference);
+			assert_eq!(color.red, color.alpha);
+		}
+	}
+
+	#[test]
+	fn keeps_the_red_channel() {
+		let pixels = [3, 3, 3, 3, 0, 0, 0, 0, 255, 255, 255, 255];
+		assert_eq!(red_channel(&pixels), vec![3, 0, 255]);
+	}
+}
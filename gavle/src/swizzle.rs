@@ -0,0 +1,219 @@
+use crate::texture::TextureFormat;
+
+/** Where a channel of a texture, as seen by the shaders sampling it, comes
+ * from. */
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum ChannelSource {
+	/** The red channel of the stored data. */
+	Red,
+	/** The green channel of the stored data. */
+	Green,
+	/** The blue channel of the stored data. */
+	Blue,
+	/** The alpha channel of the stored data. */
+	Alpha,
+	/** A constant zero. */
+	Zero,
+	/** A constant one, which is the largest value for normalized formats. */
+	One,
+}
+
+/** Sources of the red, green, blue and alpha channels of a texture, in that
+ * order, as seen by the shaders sampling it.
+ *
+ * # Implementation
+ * Core and OpenGL ES contexts with the [`texture_swizzle`] capability apply
+ * these with texture parameters, leaving the stored data as it was uploaded.
+ * Everywhere else, and most notably in WebGL, the data gets converted on the
+ * host as it gets uploaded instead, so the texture ends up storing the
+ * channels the way shaders would have seen them. There, [`Texture::read()`]
+ * returns the converted data, and whatever gets rendered into the texture
+ * doesn't get swizzled at all.
+ *
+ * [`texture_swizzle`]: crate::Capabilities::texture_swizzle
+ * [`Texture::read()`]: crate::Texture::read */
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct TextureSwizzle(
+	pub ChannelSource,
+	pub ChannelSource,
+	pub ChannelSource,
+	pub ChannelSource);
+impl TextureSwizzle {
+	/** Channels are seen as they are stored. */
+	pub const IDENTITY: Self = Self(
+		ChannelSource::Red,
+		ChannelSource::Green,
+		ChannelSource::Blue,
+		ChannelSource::Alpha);
+
+	/** Data stored as BGRA gets seen as RGBA. */
+	pub const BGRA: Self = Self(
+		ChannelSource::Blue,
+		ChannelSource::Green,
+		ChannelSource::Red,
+		ChannelSource::Alpha);
+
+	/** The red channel gets seen as an opaque shade of gray, for grayscale
+	 * images loaded into the red channel without expanding them. */
+	pub const GRAYSCALE: Self = Self(
+		ChannelSource::Red,
+		ChannelSource::Red,
+		ChannelSource::Red,
+		ChannelSource::One);
+
+	/** The sources of the channels, in order. */
+	pub fn channels(&self) -> [ChannelSource; 4] {
+		[self.0, self.1, self.2, self.3]
+	}
+
+	/** Whether every channel is seen as it is stored. */
+	pub fn is_identity(&self) -> bool {
+		*self == Self::IDENTITY
+	}
+
+	/** The OpenGL parameters for this swizzle, paired with their values. */
+	pub(crate) fn as_opengl(&self) -> [(u32, i32); 4] {
+		[
+			(glow::TEXTURE_SWIZZLE_R, self.0.as_opengl()),
+			(glow::TEXTURE_SWIZZLE_G, self.1.as_opengl()),
+			(glow::TEXTURE_SWIZZLE_B, self.2.as_opengl()),
+			(glow::TEXTURE_SWIZZLE_A, self.3.as_opengl()),
+		]
+	}
+
+	/** Converts the given texels in the given format in place, so that they
+	 * store the channels the way they would be seen with this swizzle applied.
+	 * Returns `None` if the format can't be swizzled.
+	 *
+	 * The data must be a whole number of texels. Rows are tightly packed, so
+	 * how wide the data is has no bearing on how it gets converted. */
+	pub(crate) fn convert(&self, format: TextureFormat, data: &mut [u8]) -> Option<()> {
+		let component = match format {
+			TextureFormat::Rgba8Unorm | TextureFormat::Srgb8Alpha8 => 1,
+			TextureFormat::Rgba32Float => 4,
+			TextureFormat::Depth24Stencil8
+				| TextureFormat::Etc2Rgb8Unorm
+				| TextureFormat::Etc2Rgba8Unorm
+				| TextureFormat::Bc1RgbaUnorm
+				| TextureFormat::Bc2RgbaUnorm
+				| TextureFormat::Bc3RgbaUnorm => return None
+		};
+		debug_assert_eq!(data.len() % (component * 4), 0);
+
+		if self.is_identity() { return Some(()) }
+		match (format, *self) {
+			(TextureFormat::Rgba8Unorm | TextureFormat::Srgb8Alpha8, Self::BGRA) =>
+				for texel in data.chunks_exact_mut(4) {
+					texel.swap(0, 2)
+				},
+			(TextureFormat::Rgba8Unorm | TextureFormat::Srgb8Alpha8, Self::GRAYSCALE) =>
+				for texel in data.chunks_exact_mut(4) {
+					texel[1] = texel[0];
+					texel[2] = texel[0];
+					texel[3] = u8::MAX;
+				},
+			_ => {
+				let one = match format {
+					TextureFormat::Rgba32Float => 1.0f32.to_ne_bytes(),
+					_ => [u8::MAX; 4]
+				};
+				let channels = self.channels();
+
+				let mut source = [0u8; 16];
+				for texel in data.chunks_exact_mut(component * 4) {
+					source[..texel.len()].copy_from_slice(texel);
+					let components = texel.chunks_exact_mut(component);
+					for (target, channel) in components.zip(&channels) {
+						let index = match channel {
+							ChannelSource::Red => 0,
+							ChannelSource::Green => 1,
+							ChannelSource::Blue => 2,
+							ChannelSource::Alpha => 3,
+							ChannelSource::Zero => {
+								target.iter_mut().for_each(|byte| *byte = 0);
+								continue
+							},
+							ChannelSource::One => {
+								target.copy_from_slice(&one[..component]);
+								continue
+							}
+						};
+						target.copy_from_slice(
+							&source[index * component..(index + 1) * component]);
+					}
+				}
+			}
+		}
+
+		Some(())
+	}
+}
+impl Default for TextureSwizzle {
+	fn default() -> Self {
+		Self::IDENTITY
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn bgra_to_rgba() {
+		/* Three texels wide, which is an odd width whose rows don't fall on
+		 * any alignment larger than that of a single texel. */
+		let mut data = vec![
+			1, 2, 3, 4,   5, 6, 7, 8,   9, 10, 11, 12,
+			13, 14, 15, 16,   17, 18, 19, 20,   21, 22, 23, 24];
+		TextureSwizzle::BGRA.convert(TextureFormat::Rgba8Unorm, &mut data).unwrap();
+		assert_eq!(data, vec![
+			3, 2, 1, 4,   7, 6, 5, 8,   11, 10, 9, 12,
+			15, 14, 13, 16,   19, 18, 17, 20,   23, 22, 21, 24]);
+	}
+
+	#[test]
+	fn grayscale_expansion() {
+		let mut data = vec![10, 0, 0, 0,   20, 7, 7, 7,   30, 1, 2, 3];
+		TextureSwizzle::GRAYSCALE.convert(TextureFormat::Rgba8Unorm, &mut data).unwrap();
+		assert_eq!(data, vec![
+			10, 10, 10, 255,   20, 20, 20, 255,   30, 30, 30, 255]);
+	}
+
+	#[test]
+	fn general_bytes() {
+		let swizzle = TextureSwizzle(
+			ChannelSource::Green,
+			ChannelSource::Red,
+			ChannelSource::Alpha,
+			ChannelSource::Zero);
+		let mut data = vec![1, 2, 3, 4,   5, 6, 7, 8,   9, 10, 11, 12];
+		swizzle.convert(TextureFormat::Rgba8Unorm, &mut data).unwrap();
+		assert_eq!(data, vec![2, 1, 4, 0,   6, 5, 8, 0,   10, 9, 12, 0]);
+	}
+
+	#[test]
+	fn constants_and_floats() {
+		let swizzle = TextureSwizzle(
+			ChannelSource::Alpha,
+			ChannelSource::Zero,
+			ChannelSource::One,
+			ChannelSource::Red);
+		let mut data = [0.5f32, 0.25, 0.125, 2.0].iter()
+			.flat_map(|value| value.to_ne_bytes().to_vec())
+			.collect::<Vec<_>>();
+		swizzle.convert(TextureFormat::Rgba32Float, &mut data).unwrap();
+
+		let values = data.chunks_exact(4)
+			.map(|bytes| f32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+			.collect::<Vec<_>>();
+		assert_eq!(values, vec![2.0, 0.0, 1.0, 0.5]);
+	}
+
+	#[test]
+	fn depth_formats_are_not_swizzled() {
+		let mut data = vec![0; 8];
+		assert!(TextureSwizzle::BGRA
+			.convert(TextureFormat::Depth24Stencil8, &mut data)
+			.is_none());
+	}
+}
@@ -0,0 +1,42 @@
+/* Texel buffer declarations and accessors provided by Gavle. */
+#ifdef GAVLE_TEXELFETCH_FALLBACK
+	/* Texels are laid out in the rows of a two-dimensional texture, as many
+	 * as fit in a single row. */
+	#define GAVLE_TEXEL_BUFFER sampler2D
+	#define GAVLE_UTEXEL_BUFFER usampler2D
+
+	ivec2 gavle_texel_coords(int width, int index)
+	{
+		return ivec2(index % width, index / width);
+	}
+
+	highp vec4 gavle_fetch(highp sampler2D buffer, int index)
+	{
+		int width = textureSize(buffer, 0).x;
+		return texelFetch(buffer, gavle_texel_coords(width, index), 0);
+	}
+
+	highp uvec4 gavle_fetch(highp usampler2D buffer, int index)
+	{
+		int width = textureSize(buffer, 0).x;
+		return texelFetch(buffer, gavle_texel_coords(width, index), 0);
+	}
+#else
+	/* Buffer textures can't be read in older versions of the language. */
+	#if defined(GL_ES) && __VERSION__ < 320 || !defined(GL_ES) && __VERSION__ < 140
+		#error texel buffers are kept in buffer textures on this device, which need GLSL 1.40 or GLSL ES 3.20
+	#endif
+
+	#define GAVLE_TEXEL_BUFFER samplerBuffer
+	#define GAVLE_UTEXEL_BUFFER usamplerBuffer
+
+	highp vec4 gavle_fetch(highp samplerBuffer buffer, int index)
+	{
+		return texelFetch(buffer, index);
+	}
+
+	highp uvec4 gavle_fetch(highp usamplerBuffer buffer, int index)
+	{
+		return texelFetch(buffer, index);
+	}
+#endif
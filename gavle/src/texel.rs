@@ -0,0 +1,563 @@
+use glow::{Context, HasContext};
+use crate::lock::{PipelineLock, LockOperation};
+use std::rc::Rc;
+use std::convert::TryFrom;
+use std::os::raw::c_void;
+use crate::access::{AccessLock, UnitAccessLock};
+use crate::buffer::BufferError;
+use crate::info::Capabilities;
+use crate::sanitize::{Sanitizer, BufferContents};
+
+/** Signature of `glTexBuffer`. */
+type TexBuffer = unsafe extern "system" fn(
+	target: u32,
+	internal_format: u32,
+	buffer: u32);
+
+/** Formats of the texels held by a [`TexelBuffer`].
+ *
+ * [`TexelBuffer`]: TexelBuffer */
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum TexelFormat {
+	/** RGBA with a 32-bit floating point for every component, fetched as a
+	 * `vec4` in shaders. */
+	Rgba32Float,
+	/** RGBA with a 32-bit unsigned integer for every component, fetched as a
+	 * `uvec4` in shaders. */
+	Rgba32Uint,
+}
+impl TexelFormat {
+	/** Number of bytes taken up by a single texel in this format. */
+	pub fn bytes_per_texel(&self) -> u32 {
+		match self {
+			Self::Rgba32Float => 16,
+			Self::Rgba32Uint => 16,
+		}
+	}
+}
+
+/** GLSL code for declaring and reading texel buffers in shaders.
+ *
+ * Texel buffers must be declared with the `GAVLE_TEXEL_BUFFER` type, for the
+ * [`TexelFormat::Rgba32Float`] format, or with the `GAVLE_UTEXEL_BUFFER` type,
+ * for the [`TexelFormat::Rgba32Uint`] format, and read with the overloads of
+ * `gavle_fetch(buffer, index)`, which return the texel at the given index:
+ *
+ * ```glsl
+ * uniform highp GAVLE_TEXEL_BUFFER particles;
+ *
+ * void main() {
+ *     vec4 particle = gavle_fetch(particles, gl_InstanceID);
+ *     ...
+ * }
+ * ```
+ *
+ * When `GAVLE_TEXELFETCH_FALLBACK` is defined, the buffers are declared as
+ * two-dimensional textures, which are read back in rows. Otherwise, they are
+ * declared as proper buffer textures. This goes into the shader source after
+ * the precision statements, preceded by the definitions in
+ * [`TexelBuffer::glsl()`], which is what should normally be used instead.
+ *
+ * [`TexelFormat::Rgba32Float`]: TexelFormat::Rgba32Float
+ * [`TexelFormat::Rgba32Uint`]: TexelFormat::Rgba32Uint
+ * [`TexelBuffer::glsl()`]: TexelBuffer::glsl */
+pub const TEXEL_BUFFER_GLSL: &str = include_str!("texel.glsl");
+
+/** Entry point for attaching buffers to buffer textures.
+ *
+ * Just like with [`SpirVFunctions`], the bindings we use don't expose it, so
+ * it has to be loaded through the same loader function the context was loaded
+ * with, and only devices created with [`Device::new_from_loader_function`] get
+ * to keep texel buffers in buffer textures.
+ *
+ * [`SpirVFunctions`]: crate::spirv::SpirVFunctions
+ * [`Device::new_from_loader_function`]: crate::Device::new_from_loader_function */
+#[derive(Debug)]
+pub(crate) struct TexBufferFunction {
+	tex_buffer: TexBuffer,
+}
+impl TexBufferFunction {
+	/** Loads the entry point using the given loader function, returning
+	 * `None` if it is missing. */
+	pub(crate) unsafe fn load<F>(mut loader: F) -> Option<Self>
+		where F: FnMut(&str) -> *const c_void {
+
+		let pointer = loader("glTexBuffer");
+		if pointer.is_null() { return None }
+
+		Some(Self {
+			tex_buffer: std::mem::transmute::<_, TexBuffer>(pointer)
+		})
+	}
+
+	/** Attaches the given buffer to the buffer texture currently bound, whose
+	 * texels it then holds in the given internal format. */
+	pub(crate) unsafe fn attach(
+		&self,
+		internal_format: u32,
+		buffer: <Context as HasContext>::Buffer) {
+
+		(self.tex_buffer)(glow::TEXTURE_BUFFER, internal_format, buffer)
+	}
+}
+
+/** Where the texels of a [`TexelBuffer`] are kept.
+ *
+ * [`TexelBuffer`]: TexelBuffer */
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub(crate) enum TexelStorage {
+	/** In the given buffer, read through a buffer texture. */
+	Buffer(<Context as HasContext>::Buffer),
+	/** In the rows of a two-dimensional texture, laid out as given. */
+	Rows(TexelLayout),
+}
+impl TexelStorage {
+	/** Target the texture holding the texels gets bound to. */
+	pub(crate) fn target(&self) -> u32 {
+		match self {
+			Self::Buffer(_) => glow::TEXTURE_BUFFER,
+			Self::Rows(_) => glow::TEXTURE_2D,
+		}
+	}
+}
+
+/** Inner shared structure of the texel buffer. */
+#[derive(Debug)]
+pub(crate) struct InnerTexelBuffer {
+	/** Shared handle to the underlying context. */
+	pub(crate) context: Rc<Context>,
+	/** Shared OpenGL pipeline lock. */
+	pub(crate) pipeline: Rc<PipelineLock>,
+	/** Shared write sanitizer state. */
+	pub(crate) sanitizer: Rc<Sanitizer>,
+	/** Name of the texture the data of this buffer is read through. */
+	pub(crate) texture: <Context as HasContext>::Texture,
+	/** Access control structure. */
+	pub(crate) access: UnitAccessLock,
+	/** Kind of data held by this buffer. */
+	pub(crate) contents: BufferContents,
+	/** Format of the texels in this buffer. */
+	pub(crate) format: TexelFormat,
+	/** Where the texels are kept. */
+	pub(crate) storage: TexelStorage,
+	/** Number of texels in the buffer. */
+	pub(crate) texels: u32,
+	/** Length of the buffer, in bytes. */
+	pub(crate) len: u32,
+}
+impl Drop for InnerTexelBuffer {
+	fn drop(&mut self) {
+		unsafe {
+			let _atomic = self.access.acquire_write_guarded();
+			self.context.delete_texture(self.texture);
+			if let TexelStorage::Buffer(buffer) = self.storage {
+				self.context.delete_buffer(buffer);
+			}
+		}
+	}
+}
+impl AccessLock for InnerTexelBuffer {
+	fn acquire_write(&self) {
+		self.access.acquire_write()
+	}
+	fn release_write(&self) {
+		self.access.release_write()
+	}
+	fn acquire_read(&self) {
+		self.access.acquire_read()
+	}
+	fn release_read(&self) {
+		self.access.release_read()
+	}
+}
+
+/** A buffer of texels which shaders may fetch from at random, through
+ * [`UniformBind::TexelBuffer`].
+ *
+ * These are meant for data that is too large to fit in a uniform block, such
+ * as the state of particles or the lights in a clustered renderer.
+ *
+ * # Implementation
+ * On devices with the [`texel_buffers`] capability, the texels are kept in a
+ * buffer, read through a buffer texture. Everywhere else, they are kept in the
+ * rows of a two-dimensional texture, as many as fit in a row, and writes get
+ * repacked into those rows. This is transparent to shaders written against
+ * [`TexelBuffer::glsl()`], as long as they get the code for the device they
+ * run on.
+ *
+ * [`UniformBind::TexelBuffer`]: crate::UniformBind::TexelBuffer
+ * [`texel_buffers`]: crate::Capabilities::texel_buffers
+ * [`TexelBuffer::glsl()`]: TexelBuffer::glsl */
+#[derive(Debug)]
+pub struct TexelBuffer {
+	/** Inner access-controlled version of this structure. */
+	pub(crate) inner: Rc<InnerTexelBuffer>,
+}
+impl TexelBuffer {
+	/** Length of this buffer, in bytes. */
+	pub fn len(&self) -> u32 {
+		self.inner.len
+	}
+
+	/** Whether this buffer holds no texels at all. */
+	pub fn is_empty(&self) -> bool {
+		self.inner.len == 0
+	}
+
+	/** Number of texels in this buffer. */
+	pub fn texels(&self) -> u32 {
+		self.inner.texels
+	}
+
+	/** Format of the texels in this buffer. */
+	pub fn format(&self) -> TexelFormat {
+		self.inner.format
+	}
+
+	/** GLSL code declaring the texel buffer types and the `gavle_fetch()`
+	 * functions, as described in [`TEXEL_BUFFER_GLSL`], for the way texel
+	 * buffers are stored on devices with the given capabilities. This goes
+	 * into the shader source, after the precision statements.
+	 *
+	 * Where texel buffers are kept in buffer textures, the `samplerBuffer`
+	 * types this declares need at least GLSL 1.40 or GLSL ES 3.20, and shaders
+	 * written for older versions fail to compile with an error saying so.
+	 *
+	 * [`TEXEL_BUFFER_GLSL`]: TEXEL_BUFFER_GLSL */
+	pub fn glsl(capabilities: &Capabilities) -> String {
+		texel_buffer_glsl(capabilities.texel_buffers)
+	}
+
+	/** Writes the given data into this buffer, starting at the given byte
+	 * offset. Both the offset and the length of the data must be multiples of
+	 * the size of a texel.
+	 *
+	 * # Panic
+	 * This function will panic if the buffer can't be locked mutably or if
+	 * the pipeline can't be locked mutably. */
+	pub fn write(&self, offset: u32, data: &[u8]) -> Result<(), BufferError> {
+		let texel = self.inner.format.bytes_per_texel();
+		let end = u32::try_from(data.len()).ok()
+			.and_then(|len| offset.checked_add(len));
+		match end {
+			Some(end) if end <= self.len() => {},
+			_ => return Err(BufferError::OutOfBounds {
+				offset,
+				len: u64::try_from(data.len()).unwrap_or(u64::MAX),
+				size: self.len()
+			})
+		}
+		if offset % texel != 0 || data.len() % texel as usize != 0 {
+			return Err(BufferError::Misaligned {
+				offset,
+				len: data.len() as u64,
+				alignment: texel
+			})
+		}
+
+		let _buffer_lock = self.inner.acquire_write_guarded();
+		let _pipeline_lock = self.inner.pipeline.acquire(LockOperation::BufferWrite);
+		if data.is_empty() { return Ok(()) }
+
+		/* Catch non-finite values before they make it to the device. */
+		self.inner.sanitizer.check(
+			&self.inner.texture,
+			self.inner.contents,
+			offset,
+			data);
+
+		let gl = self.inner.context.as_ref();
+		let layout = match self.inner.storage {
+			TexelStorage::Buffer(buffer) => {
+				unsafe {
+					gl.bind_buffer(glow::TEXTURE_BUFFER, Some(buffer));
+					gl.buffer_sub_data_u8_slice(
+						glow::TEXTURE_BUFFER,
+						i32::try_from(offset).unwrap(),
+						data);
+					gl.bind_buffer(glow::TEXTURE_BUFFER, None);
+				}
+				return Ok(())
+			},
+			TexelStorage::Rows(layout) => layout
+		};
+
+		let first = offset / texel;
+		let count = data.len() as u32 / texel;
+
+		let (format, _, kind) = self.inner.format.as_opengl();
+		unsafe {
+			gl.bind_texture(glow::TEXTURE_2D, Some(self.inner.texture));
+			for span in layout.spans(first, count) {
+				let start = (span.first - first) as usize * texel as usize;
+				let end = start + span.len() as usize * texel as usize;
+
+				/* Every span lies inside of the texture, whose size has been
+				 * checked to fit in an i32 when it was created. */
+				gl.tex_sub_image_2d(
+					glow::TEXTURE_2D,
+					0,
+					i32::try_from(span.x).unwrap(),
+					i32::try_from(span.y).unwrap(),
+					i32::try_from(span.width).unwrap(),
+					i32::try_from(span.height).unwrap(),
+					format,
+					kind,
+					glow::PixelUnpackData::Slice(&data[start..end]));
+			}
+			gl.bind_texture(glow::TEXTURE_2D, None);
+		}
+
+		Ok(())
+	}
+}
+impl AccessLock for TexelBuffer {
+	fn acquire_write(&self) {
+		self.inner.acquire_write()
+	}
+	fn release_write(&self) {
+		self.inner.release_write()
+	}
+	fn acquire_read(&self) {
+		self.inner.acquire_read()
+	}
+	fn release_read(&self) {
+		self.inner.release_read()
+	}
+}
+
+/** GLSL code for texel buffers kept in buffer textures, if `native` is set,
+ * or in the rows of two-dimensional textures otherwise. */
+fn texel_buffer_glsl(native: bool) -> String {
+	if native {
+		TEXEL_BUFFER_GLSL.to_string()
+	} else {
+		format!("#define GAVLE_TEXELFETCH_FALLBACK\n{}", TEXEL_BUFFER_GLSL)
+	}
+}
+
+/** Arrangement of the texels of a buffer in the rows of a texture. */
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub(crate) struct TexelLayout {
+	/** Number of texels in the buffer. */
+	pub(crate) texels: u32,
+	/** Width of the texture, in texels. */
+	pub(crate) width: u32,
+	/** Height of the texture, in texels. */
+	pub(crate) height: u32,
+}
+impl TexelLayout {
+	/** Lays the given number of texels out in rows no wider than the given
+	 * maximum size, returning `None` if they need more rows than that.
+	 *
+	 * Rows are made as wide as possible, so that most buffers fit in a single
+	 * row and writes to them turn into a single upload. Textures can't be
+	 * empty, so an empty buffer still takes up a single texel. */
+	pub(crate) fn new(texels: u32, max_size: u32) -> Option<Self> {
+		let width = u32::max(u32::min(texels, max_size), 1);
+		let height = u32::max(texels / width + u32::from(texels % width != 0), 1);
+		if height > max_size {
+			return None
+		}
+
+		Some(Self { texels, width, height })
+	}
+
+	/** Splits a range of texels in the buffer into the rectangles it covers in
+	 * the texture, in order: the rest of the row it starts in, the full rows
+	 * after that, and the start of the row it ends in. */
+	pub(crate) fn spans(&self, first: u32, count: u32) -> Vec<TexelSpan> {
+		let mut spans = Vec::with_capacity(3);
+		let mut first = first;
+		let mut count = count;
+
+		/* Finish off the row the range starts in. */
+		let x = first % self.width;
+		if count > 0 && x != 0 {
+			let width = u32::min(self.width - x, count);
+			spans.push(TexelSpan { first, x, y: first / self.width, width, height: 1 });
+
+			first += width;
+			count -= width;
+		}
+
+		/* Upload all of the full rows at once. */
+		let rows = count / self.width;
+		if rows > 0 {
+			spans.push(TexelSpan {
+				first,
+				x: 0,
+				y: first / self.width,
+				width: self.width,
+				height: rows
+			});
+
+			first += rows * self.width;
+			count -= rows * self.width;
+		}
+
+		/* Whatever is left starts a row of its own. */
+		if count > 0 {
+			spans.push(TexelSpan { first, x: 0, y: first / self.width, width: count, height: 1 });
+		}
+
+		spans
+	}
+}
+
+/** Rectangle of a texel buffer texture covered by a contiguous range of the
+ * texels in the buffer. */
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub(crate) struct TexelSpan {
+	/** Index of the first texel in the rectangle. */
+	pub(crate) first: u32,
+	/** Horizontal offset of the rectangle, in texels. */
+	pub(crate) x: u32,
+	/** Vertical offset of the rectangle, in texels. */
+	pub(crate) y: u32,
+	/** Width of the rectangle, in texels. */
+	pub(crate) width: u32,
+	/** Height of the rectangle, in texels. */
+	pub(crate) height: u32,
+}
+impl TexelSpan {
+	/** Number of texels covered by this rectangle. */
+	pub(crate) fn len(&self) -> u32 {
+		self.width * self.height
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/** Writes a range of texels into a simulated texture through the spans it
+	 * gets split into, then reads the texture back as a buffer. */
+	fn round_trip(layout: TexelLayout, first: u32, data: &[u32]) -> Vec<u32> {
+		let mut texture = vec![0; (layout.width * layout.height) as usize];
+		for span in layout.spans(first, data.len() as u32) {
+			let mut source = (span.first - first) as usize;
+			for y in span.y..span.y + span.height {
+				for x in span.x..span.x + span.width {
+					texture[(y * layout.width + x) as usize] = data[source];
+					source += 1;
+				}
+			}
+		}
+
+		/* This is the indexing done by gavle_fetch() in the shader. */
+		(0..layout.texels)
+			.map(|index| texture[((index / layout.width) * layout.width
+				+ index % layout.width) as usize])
+			.collect()
+	}
+
+	#[test]
+	fn glsl_follows_the_storage() {
+		let native = texel_buffer_glsl(true);
+		let fallback = texel_buffer_glsl(false);
+		assert!(!native.contains("#define GAVLE_TEXELFETCH_FALLBACK"));
+		assert!(fallback.starts_with("#define GAVLE_TEXELFETCH_FALLBACK\n"));
+		assert!(native.contains("samplerBuffer") && fallback.contains("sampler2D"));
+
+		/* Both declare the same types and accessors. */
+		for code in &[native, fallback] {
+			assert!(code.contains("GAVLE_TEXEL_BUFFER"));
+			assert!(code.contains("GAVLE_UTEXEL_BUFFER"));
+			assert!(code.contains("gavle_fetch("));
+		}
+	}
+
+	thread_local! {
+		static ATTACHED: std::cell::Cell<Option<(u32, u32, u32)>> =
+			std::cell::Cell::new(None);
+	}
+
+	unsafe extern "system" fn tex_buffer(target: u32, internal_format: u32, buffer: u32) {
+		ATTACHED.with(|attached| attached.set(Some((target, internal_format, buffer))));
+	}
+
+	#[test]
+	fn loads_the_tex_buffer_entry_point() {
+		let missing = unsafe { TexBufferFunction::load(|_| std::ptr::null()) };
+		assert!(missing.is_none());
+
+		let mut asked = Vec::new();
+		let function = unsafe {
+			TexBufferFunction::load(|name| {
+				asked.push(name.to_string());
+				tex_buffer as *const c_void
+			})
+		}.unwrap();
+		assert_eq!(asked, vec!["glTexBuffer"]);
+
+		unsafe { function.attach(glow::RGBA32F, 7) };
+		assert_eq!(
+			ATTACHED.with(|attached| attached.get()),
+			Some((glow::TEXTURE_BUFFER, glow::RGBA32F, 7)));
+	}
+
+	#[test]
+	fn lays_texels_out_in_rows() {
+		assert_eq!(TexelLayout::new(100, 4096),
+			Some(TexelLayout { texels: 100, width: 100, height: 1 }));
+		assert_eq!(TexelLayout::new(10, 4),
+			Some(TexelLayout { texels: 10, width: 4, height: 3 }));
+		assert_eq!(TexelLayout::new(16, 4),
+			Some(TexelLayout { texels: 16, width: 4, height: 4 }));
+		assert_eq!(TexelLayout::new(0, 4),
+			Some(TexelLayout { texels: 0, width: 1, height: 1 }));
+		assert_eq!(TexelLayout::new(17, 4), None);
+	}
+
+	#[test]
+	fn splits_writes_into_spans() {
+		let layout = TexelLayout { texels: 20, width: 4, height: 5 };
+
+		/* Within a single row. */
+		assert_eq!(layout.spans(5, 2), vec![
+			TexelSpan { first: 5, x: 1, y: 1, width: 2, height: 1 }
+		]);
+
+		/* Partial row, full rows, then another partial row. */
+		assert_eq!(layout.spans(3, 11), vec![
+			TexelSpan { first: 3, x: 3, y: 0, width: 1, height: 1 },
+			TexelSpan { first: 4, x: 0, y: 1, width: 4, height: 2 },
+			TexelSpan { first: 12, x: 0, y: 3, width: 2, height: 1 },
+		]);
+
+		/* Aligned to the rows. */
+		assert_eq!(layout.spans(8, 8), vec![
+			TexelSpan { first: 8, x: 0, y: 2, width: 4, height: 2 }
+		]);
+
+		assert_eq!(layout.spans(8, 0), vec![]);
+	}
+
+	#[test]
+	fn writes_round_trip() {
+		for &(texels, max) in &[(16, 4), (7, 7), (33, 6), (1, 1)] {
+			let layout = TexelLayout::new(texels, max).unwrap();
+			let data = (1..=texels).collect::<Vec<_>>();
+
+			/* Write the whole buffer in pieces of every possible size. */
+			for piece in 1..=texels {
+				let mut buffer = vec![0; texels as usize];
+				let mut first = 0;
+				while first < texels {
+					let count = u32::min(piece, texels - first);
+					let range = first as usize..(first + count) as usize;
+
+					let read = round_trip(layout, first, &data[range.clone()]);
+					for (i, value) in read.iter().enumerate() {
+						if range.contains(&i) { buffer[i] = *value }
+					}
+					first += count;
+				}
+
+				assert_eq!(buffer, data, "{} texels, {} wide, in pieces of {}",
+					texels, layout.width, piece);
+			}
+		}
+	}
+}
@@ -1,27 +1,152 @@
 use glow::{Context, HasContext};
+use crate::lock::{PipelineLock, LockOperation};
 use std::rc::Rc;
 use crate::access::{AccessLock, UnitAccessLock};
 use std::num::NonZeroU32;
+use std::cell::{Cell, RefCell};
+use std::convert::TryFrom;
+use std::sync::atomic::{AtomicU64, Ordering};
+use crate::pass::Viewport;
+use crate::framebuffer::read_bound_pixels;
+use crate::init::InitTracker;
+use crate::debug::Debugger;
+use crate::info::Profile;
+use std::ops::Range;
+use crate::buffer::{Buffer, BufferWriter};
+use crate::upload::{UploadBuffer, UploadStorage, staged_range};
+use crate::swizzle::TextureSwizzle;
 
 /** Inner shared structure of the texture. */
 #[derive(Debug)]
 pub(crate) struct InnerTexture {
 	/** Reference to the shared context. */
 	pub(crate) context: Rc<Context>,
+	/** Shared OpenGL pipeline lock. */
+	pub(crate) pipeline: Rc<PipelineLock>,
+	/** Shared error checking and labeling state. */
+	pub(crate) debug: Rc<Debugger>,
 	/** Name of this texture inside of that context. */
 	pub(crate) texture: <Context as HasContext>::Texture,
 	/** Access control structure. */
 	pub(crate) access: UnitAccessLock,
 	/** Format this texture is in. */
 	pub(crate) format: TextureFormat,
-	/** Extent of this texture. */
-	pub(crate) extent: TextureExtent,
+	/** Internal format the storage of this texture is allocated with, as
+	 * given by [`TextureFormat::internal_format()`]. */
+	pub(crate) internal_format: u32,
+	/** Extent of the base level of this texture, which changes when the
+	 * framebuffer it is attached to gets resized. */
+	pub(crate) extent: Cell<TextureExtent>,
+	/** Number of mip levels available in this texture. */
+	pub(crate) levels: u32,
+	/** Whether the texture object gets deleted along with this structure. */
+	pub(crate) ownership: RawOwnership,
+	/** Identifier of this texture, unique for the lifetime of the program. */
+	pub(crate) id: u64,
+	/** Label used to refer to this texture in diagnostics. */
+	pub(crate) label: RefCell<Option<String>>,
+	/** Which of the levels and layers of this texture have been written to. */
+	pub(crate) init: RefCell<InitTracker>,
+	/** How the channels of this texture are seen by shaders sampling it,
+	 * relative to the data uploaded to it. */
+	pub(crate) swizzle: TextureSwizzle,
+	/** Whether the swizzle gets applied to uploaded data on the host, rather
+	 * than by the context, as the texture gets sampled. */
+	pub(crate) swizzle_on_upload: bool,
+}
+impl InnerTexture {
+	/** Picks the identifier of the next texture to be created. */
+	pub(crate) fn next_id() -> u64 {
+		static NEXT: AtomicU64 = AtomicU64::new(0);
+		NEXT.fetch_add(1, Ordering::Relaxed)
+	}
+
+	/** Approximate amount of memory taken up by this texture, counting all of
+	 * its mip levels, in bytes. */
+	pub(crate) fn bytes(&self) -> u64 {
+		(0..self.levels)
+			.map(|level| self.format.len(&self.extent.get().mip(level))
+				.map(|len| len as u64)
+				.unwrap_or(u64::MAX))
+			.fold(0, u64::saturating_add)
+	}
+
+	/** Name of this texture in diagnostics, which is either its label or its
+	 * identifier, if it has no label. */
+	pub(crate) fn name(&self) -> String {
+		crate::debug::name(self.label.borrow().as_deref(), self.id)
+	}
+
+	/** Checks for errors raised by the given operation on this texture. */
+	pub(crate) unsafe fn check(&self, operation: &str) {
+		self.debug.check(
+			&self.context,
+			operation,
+			|| format!("texture {}", self.name()))
+	}
+
+	/** Marks the given layers of the given levels as written to. */
+	pub(crate) fn mark_written(&self, levels: Range<u32>, layers: Range<u32>) {
+		self.init.borrow_mut().mark(levels, layers)
+	}
+
+	/** Warns about the subresources in the given ranges that have never been
+	 * written to, which are about to be read from in the given way. */
+	pub(crate) fn warn_uninitialized(
+		&self,
+		levels: Range<u32>,
+		layers: Range<u32>,
+		usage: &str) {
+
+		let missing = self.init.borrow().missing(levels, layers);
+		if missing.is_empty() { return }
+
+		rate_limited!(warn!(target: "gavle::init",
+			"texture {} is being {} before anything was written to {}",
+			self.name(),
+			usage,
+			crate::init::describe(&missing)));
+	}
+
+	/** Reallocates the storage of every level of this two-dimensional texture
+	 * for the given size of its base level, leaving all of them undefined.
+	 *
+	 * # Safety
+	 * The texture must be two-dimensional, have no more levels than a texture
+	 * of the given size can, and the size must fit in an `i32`. */
+	pub(crate) unsafe fn reallocate(&self, gl: &Context, width: u32, height: u32) {
+		let extent = TextureExtent::D2 { width, height };
+		let (format, _, kind) = self.format.as_opengl();
+		let internal_format = self.internal_format;
+
+		gl.bind_texture(glow::TEXTURE_2D, Some(self.texture));
+		for level in 0..self.levels {
+			let (width, height, _) = extent.mip(level).axes();
+			gl.tex_image_2d(
+				glow::TEXTURE_2D,
+				i32::try_from(level).unwrap(),
+				i32::try_from(internal_format).unwrap(),
+				i32::try_from(width).unwrap(),
+				i32::try_from(height).unwrap(),
+				0,
+				format,
+				kind,
+				None);
+		}
+		gl.bind_texture(glow::TEXTURE_2D, None);
+
+		self.extent.set(extent);
+		*self.init.borrow_mut() = InitTracker::new(self.levels, 1);
+		self.check("reallocating");
+	}
 }
 impl Drop for InnerTexture {
 	fn drop(&mut self) {
 		unsafe {
 			let _atom = self.access.acquire_write_guarded();
-			self.context.delete_texture(self.texture)
+			if let RawOwnership::Owned = self.ownership {
+				self.context.delete_texture(self.texture)
+			}
 		}
 	}
 }
@@ -50,10 +175,618 @@ impl Texture {
 	pub fn format(&self) -> TextureFormat {
 		self.inner.format
 	}
-	/** Returns the underlying handle to the texture object. */
+	/** The extent of the base level of this texture. */
+	pub fn extent(&self) -> TextureExtent {
+		self.inner.extent.get()
+	}
+	/** The number of mip levels available in this texture. */
+	pub fn levels(&self) -> u32 {
+		self.inner.levels
+	}
+	/** How the channels of this texture are seen by shaders sampling it,
+	 * relative to the data uploaded to it. Whether this is applied as the
+	 * texture gets sampled or as data gets uploaded to it, as described in
+	 * [`TextureSwizzle`], is told by [`swizzled_on_upload()`].
+	 *
+	 * [`TextureSwizzle`]: crate::TextureSwizzle
+	 * [`swizzled_on_upload()`]: Self::swizzled_on_upload */
+	pub fn swizzle(&self) -> TextureSwizzle {
+		self.inner.swizzle
+	}
+	/** Whether the [`swizzle()`] of this texture got applied to the data
+	 * uploaded to it, in which case its storage already holds the channels in
+	 * the order shaders see them.
+	 *
+	 * [`swizzle()`]: Self::swizzle */
+	pub fn swizzled_on_upload(&self) -> bool {
+		self.inner.swizzle_on_upload
+	}
+	/** Identifier of this texture, unique for the lifetime of the program
+	 * and shared by all of its handles. */
+	pub fn id(&self) -> u64 {
+		self.inner.id
+	}
+	/** The label used to refer to this texture in diagnostics, if any. */
+	pub fn label(&self) -> Option<String> {
+		self.inner.label.borrow().clone()
+	}
+	/** Changes the label used to refer to this texture in diagnostics, such
+	 * as the ones in [`Device::texture_usage_report()`]. The label is also
+	 * attached to the texture object, for debuggers to pick up, when the
+	 * [`debug_labels`] capability is present.
+	 *
+	 * [`Device::texture_usage_report()`]: crate::Device::texture_usage_report
+	 * [`debug_labels`]: crate::Capabilities::debug_labels */
+	pub fn set_label(&self, label: &str) {
+		unsafe {
+			self.inner.debug.label(
+				&self.inner.context,
+				glow::TEXTURE,
+				self.inner.texture,
+				label);
+		}
+		*self.inner.label.borrow_mut() = Some(label.to_owned())
+	}
+	/** The number of array layers in this texture. Textures that are not
+	 * arrays are considered to have a single layer. */
+	pub fn layers(&self) -> u32 {
+		match self.inner.extent.get() {
+			TextureExtent::D2Array { layers, .. } => layers,
+			_ => 1
+		}
+	}
+
+	/** Create a view over a range of the mip levels and array layers of this
+	 * texture, which may be bound for sampling in place of the whole texture
+	 * through [`UniformBind::TextureView`].
+	 *
+	 * Views are lightweight handles that share the storage of this texture, no
+	 * data is copied in their creation.
+	 *
+	 * # Implementation
+	 * OpenGL ES and WebGL lack texture views, and the bindings we use don't
+	 * expose them for desktop OpenGL either, so views are emulated at bind
+	 * time on every context. The mip range is applied by clamping the base and maximum
+	 * levels of the texture. The layer range of an array texture can't be
+	 * applied to the sampler itself, so the shader must sample it as a
+	 * `sampler2DArray` and offset the layer by the value of a helper integer
+	 * uniform, following the convention laid out by [`TextureView::glsl()`].
+	 * Whether a group binds its views to such samplers can be checked for with
+	 * [`UniformGroup::validate()`].
+	 *
+	 * [`UniformBind::TextureView`]: crate::UniformBind::TextureView
+	 * [`TextureView::glsl()`]: TextureView::glsl
+	 * [`UniformGroup::validate()`]: crate::UniformGroup::validate */
+	pub fn view(&self, descriptor: &TextureViewDescriptor)
+		-> Result<TextureView, TextureError> {
+
+		descriptor.validate(self.levels(), self.layers())?;
+
+		Ok(TextureView {
+			texture: Texture { inner: self.inner.clone() },
+			descriptor: *descriptor
+		})
+	}
+	/** Replace the contents of the given mip level of this texture with the
+	 * given data.
+	 *
+	 * The data must cover the whole level, in the same layout expected for a
+	 * single level of the initialization data of the texture. For array
+	 * textures and cube maps, this means every layer or face of the level, one
+	 * after the other. */
+	pub fn write_level<A: AsRef<[u8]>>(&self, level: u32, data: A)
+		-> Result<(), TextureError> {
+
+		let data = data.as_ref();
+		if level >= self.levels() {
+			return Err(TextureError::InvalidLevel {
+				level,
+				levels: self.levels()
+			})
+		}
+
+		let extent = self.inner.extent.get().mip(level);
+		self.write_region(level, (0, 0, 0), extent, TexelSource::Host(data))
+	}
+
+	/** Replace the contents of a region of the base level of this texture with
+	 * the given data, without touching the rest of it.
+	 *
+	 * The region starts at the given origin and spans the axes of the given
+	 * extent, as given by [`TextureExtent::axes()`], with the third axis going
+	 * over the layers of array textures and the faces of cube maps. So, for
+	 * instance, a single layer of an array texture may be written with a
+	 * two-dimensional extent whose origin is at that layer. The data is laid
+	 * out just like the initialization data of a texture of that extent.
+	 *
+	 * One-dimensional textures can only be written to as a whole.
+	 *
+	 * # Panic
+	 * This function will panic if the pipeline can't be locked mutably, as is
+	 * the case while a render pass is running.
+	 *
+	 * [`TextureExtent::axes()`]: TextureExtent::axes */
+	pub fn write<A: AsRef<[u8]>>(
+		&self,
+		origin: (u32, u32, u32),
+		extent: TextureExtent,
+		data: A) -> Result<(), TextureError> {
+
+		self.write_region(0, origin, extent, TexelSource::Host(data.as_ref()))
+	}
+
+	/** Replace the contents of a region of the base level of this texture with
+	 * the texels in the given upload buffer, starting at the given byte offset
+	 * into it. The region is given just like it is to [`write()`], and the
+	 * texels have to be laid out in the buffer just like the data given to it.
+	 *
+	 * When the buffer is kept on the device, this only schedules the copy,
+	 * which the implementation is free to carry out whenever it wants, and the
+	 * buffer may be written to again right away. Otherwise, this is the exact
+	 * same as calling [`write()`] with the texels in the buffer.
+	 *
+	 * One-dimensional textures can only be written to from the start of a
+	 * buffer.
+	 *
+	 * # Panic
+	 * This function will panic if the pipeline can't be locked mutably, as is
+	 * the case while a render pass is running.
+	 *
+	 * [`write()`]: Self::write */
+	pub fn write_from_buffer(
+		&self,
+		origin: (u32, u32, u32),
+		extent: TextureExtent,
+		buffer: &UploadBuffer,
+		offset: u32) -> Result<(), TextureError> {
+
+		let expected = self.inner.format.len(&extent)?;
+		let range = staged_range(offset, expected, buffer.len())
+			.ok_or(TextureError::InvalidDataLength {
+				expected: expected as u64,
+				provided: u64::from(buffer.len().saturating_sub(offset))
+			})?;
+
+		match &buffer.storage {
+			UploadStorage::Device(buffer) =>
+				self.write_region(0, origin, extent, TexelSource::Buffer(buffer, offset)),
+			UploadStorage::Host(storage) => {
+				let storage = storage.borrow();
+				self.write_region(0, origin, extent, TexelSource::Host(&storage[range]))
+			}
+		}
+	}
+
+	/** Replace the contents of the given mip level of this texture, one row
+	 * of texels at a time, with the rows written by the given function.
+	 *
+	 * The function gets called with the layer, or the face, for array
+	 * textures and cube maps, and the row it should write, going from the
+	 * bottom of the level to the top and from the first layer to the last,
+	 * along with a writer over a single row, which must be written to
+	 * completely. One-dimensional textures have a single row.
+	 *
+	 * Only a single row is ever kept in host memory, which makes this a good
+	 * fit for images generated procedurally. The function is called with the
+	 * pipeline unlocked, so it may use the device for anything it needs.
+	 *
+	 * # Panic
+	 * This function will panic if the pipeline can't be locked mutably, as is
+	 * the case while a render pass is running. */
+	pub fn write_rows<F>(&self, level: u32, mut f: F) -> Result<(), TextureError>
+		where F: FnMut(u32, u32, &mut BufferWriter) {
+
+		if level >= self.levels() {
+			return Err(TextureError::InvalidLevel {
+				level,
+				levels: self.levels()
+			})
+		}
+
+		let bounds = self.inner.extent.get().mip(level);
+		let (width, height, layers) = bounds.axes();
+		let row = match bounds {
+			TextureExtent::D1 { length } => TextureExtent::D1 { length },
+			_ => TextureExtent::D2 { width, height: 1 }
+		};
+		let len = self.inner.format.len(&row)?;
+
+		let name = self.inner.name();
+		let mut storage = vec![0; len];
+		for layer in 0..layers {
+			for y in 0..height {
+				let mut writer = BufferWriter::new(&mut storage[..]);
+				f(layer, y, &mut writer);
+				let data = writer.finish(&format_args!(
+					"row {} of layer {} of texture {}",
+					y,
+					layer,
+					name));
+
+				self.write_region(level, (0, y, layer), row, TexelSource::Host(data))?;
+			}
+		}
+
+		Ok(())
+	}
+
+	/** Checks and uploads the given region of the given mip level. Texels in
+	 * upload buffers must have already been checked to fit in them. */
+	fn write_region(
+		&self,
+		level: u32,
+		origin: (u32, u32, u32),
+		extent: TextureExtent,
+		source: TexelSource) -> Result<(), TextureError> {
+
+		if self.inner.format.is_compressed() {
+			return Err(TextureError::UnsupportedFormat {
+				what: format!("textures in the {:?} format can only be \
+					written to when they get created", self.inner.format)
+			})
+		}
+
+		let bounds = self.inner.extent.get().mip(level);
+		check_region(&bounds, origin, &extent)?;
+
+		let expected = self.inner.format.len(&extent)?;
+		if let TexelSource::Host(data) = source {
+			if expected != data.len() {
+				return Err(TextureError::InvalidDataLength {
+					expected: expected as u64,
+					provided: data.len() as u64
+				})
+			}
+		}
+		if expected == 0 {
+			/* Empty regions have nothing to write. */
+			return Ok(())
+		}
+		let swizzled = match source {
+			TexelSource::Host(data) if self.inner.swizzle_on_upload => {
+				let mut data = data.to_vec();
+				self.inner.swizzle.convert(self.inner.format, &mut data)
+					.expect("textures swizzled on upload must be in a format \
+						that can be swizzled");
+				Some(data)
+			},
+			_ => None
+		};
+		let source = match &swizzled {
+			Some(data) => TexelSource::Host(&data[..]),
+			None => source
+		};
+
+		let (x, y, z) = origin;
+		let (width, height, depth) = extent.axes();
+		if let TextureExtent::D1 { .. } = bounds {
+			if (x, width) != (0, bounds.axes().0) {
+				return Err(TextureError::InvalidBounds {
+					what: "one-dimensional textures can only be written to as \
+						a whole".into()
+				})
+			}
+			if let TexelSource::Buffer(_, offset) = source {
+				if offset != 0 {
+					return Err(TextureError::InvalidBounds {
+						what: "one-dimensional textures can only be written \
+							to from the start of an upload buffer".into()
+					})
+				}
+			}
+		}
+
+		let _pipeline = self.inner.pipeline.acquire(LockOperation::TextureWrite);
+		let _atom = self.inner.access.acquire_write_guarded();
+		let _buffer = match source {
+			TexelSource::Buffer(buffer, _) => Some(buffer.acquire_read_guarded()),
+			TexelSource::Host(_) => None
+		};
+		self.inner.mark_written(
+			level..level + 1,
+			crate::init::region_layers(&bounds, z, depth));
+
+		/* The region fits in the level, whose axes are at most as large as the
+		 * ones in the base level, which were already checked to fit in an
+		 * i32, as are the faces of a cube map. */
+		let check_i32 = |val: u32| i32::try_from(val).unwrap();
+
+		/* Texels in upload buffers get found by their offset into the buffer
+		 * bound to the unpack slot, rather than by a pointer. */
+		let texels = |start: usize, len: usize| match source {
+			TexelSource::Host(data) =>
+				glow::PixelUnpackData::Slice(&data[start..start + len]),
+			TexelSource::Buffer(_, offset) =>
+				glow::PixelUnpackData::BufferOffset(
+					offset + u32::try_from(start).unwrap())
+		};
+
+		let gl = self.inner.context.as_ref();
+		let target = bounds.target();
+		let (format, internal_format, kind) = self.inner.format.as_opengl();
+		unsafe {
+			if let TexelSource::Buffer(buffer, _) = source {
+				gl.bind_buffer(glow::PIXEL_UNPACK_BUFFER, Some(buffer.as_raw_handle()));
+			}
+			gl.bind_texture(target, Some(self.inner.texture));
+			match bounds {
+				TextureExtent::D1 { .. } =>
+					/* There is no sub-image upload for one-dimensional
+					 * textures, so just respecify the whole level. Leaving
+					 * out the data reads it from the start of the bound
+					 * upload buffer, if there is one. */
+					gl.tex_image_1d(
+						target,
+						check_i32(level),
+						check_i32(internal_format),
+						check_i32(width),
+						0,
+						format,
+						kind,
+						match source {
+							TexelSource::Host(data) => Some(data),
+							TexelSource::Buffer(..) => None
+						}),
+				TextureExtent::D2 { .. } =>
+					gl.tex_sub_image_2d(
+						target,
+						check_i32(level),
+						check_i32(x),
+						check_i32(y),
+						check_i32(width),
+						check_i32(height),
+						format,
+						kind,
+						texels(0, expected)),
+				TextureExtent::D2Array { .. } | TextureExtent::D3 { .. } =>
+					gl.tex_sub_image_3d(
+						target,
+						check_i32(level),
+						check_i32(x),
+						check_i32(y),
+						check_i32(z),
+						check_i32(width),
+						check_i32(height),
+						check_i32(depth),
+						format,
+						kind,
+						texels(0, expected)),
+				TextureExtent::Cube { .. } => {
+					let face_len = expected / depth as usize;
+					for (i, face) in (z..z + depth).enumerate() {
+						gl.tex_sub_image_2d(
+							TextureExtent::cube_face_target(face),
+							check_i32(level),
+							check_i32(x),
+							check_i32(y),
+							check_i32(width),
+							check_i32(height),
+							format,
+							kind,
+							texels(i * face_len, face_len))
+					}
+				},
+			}
+			gl.bind_texture(target, None);
+			if let TexelSource::Buffer(..) = source {
+				gl.bind_buffer(glow::PIXEL_UNPACK_BUFFER, None);
+			}
+			self.inner.check("writing to");
+		}
+
+		Ok(())
+	}
+
+	/** Reads back the contents of the base level of this texture.
+	 *
+	 * The data comes out in the same layout expected for the initialization
+	 * data of the texture, with rows going from the bottom of the texture to
+	 * the top and, for array and three-dimensional textures and cube maps,
+	 * every layer or face one after the other.
+	 *
+	 * # Implementation
+	 * OpenGL ES and WebGL can't download texture images directly, so the
+	 * texture is attached, one layer at a time, to a temporary framebuffer
+	 * and read back from there. This means only color formats can be read,
+	 * and that floating point textures require the context to be able to
+	 * render to them. One-dimensional textures can't be attached to a
+	 * framebuffer at all, so they can't be read back.
+	 *
+	 * # Panic
+	 * This function will panic if the pipeline can't be locked mutably, as is
+	 * the case while a render pass is running. */
+	pub fn read(&self) -> Result<Vec<u8>, TextureError> {
+		let format = self.inner.format;
+		match format {
+			TextureFormat::Rgba8Unorm
+				| TextureFormat::Srgb8Alpha8
+				| TextureFormat::Rgba32Float => {},
+			_ => return Err(TextureError::UnsupportedReadback {
+				what: format!("textures in the {:?} format can't be read back",
+					format)
+			})
+		}
+		if let TextureExtent::D1 { .. } = self.inner.extent.get() {
+			return Err(TextureError::UnsupportedReadback {
+				what: "one-dimensional textures can't be attached to a \
+					framebuffer to be read back".into()
+			})
+		}
+
+		let (width, height, layers) = self.inner.extent.get().axes();
+		let region = Viewport { x: 0, y: 0, width, height };
+		let mut pixels = Vec::with_capacity(format.len(&self.inner.extent.get())?);
+
+		let _pipeline = self.inner.pipeline.acquire(LockOperation::TextureRead);
+		let _atom = self.inner.access.acquire_read_guarded();
+
+		let gl = self.inner.context.as_ref();
+		unsafe {
+			let framebuffer = gl.create_framebuffer()
+				.map_err(|what| TextureError::UnsupportedReadback { what })?;
+			gl.bind_framebuffer(glow::READ_FRAMEBUFFER, Some(framebuffer));
+
+			let mut read_layers = || {
+				for layer in 0..layers {
+					self.attach(
+						gl,
+						glow::READ_FRAMEBUFFER,
+						glow::COLOR_ATTACHMENT0,
+						0,
+						layer);
+					gl.read_buffer(glow::COLOR_ATTACHMENT0);
+
+					let status = gl.check_framebuffer_status(glow::READ_FRAMEBUFFER);
+					if status != glow::FRAMEBUFFER_COMPLETE {
+						return Err(TextureError::UnsupportedReadback {
+							what: format!("the texture can't be attached to a \
+								framebuffer for reading, status 0x{:08x}",
+								status)
+						})
+					}
+
+					let layer = read_bound_pixels(gl, region, format)
+						.map_err(|what| TextureError::LimitExceeded {
+							what: what.to_string()
+						})?;
+					pixels.extend_from_slice(&layer);
+				}
+				Ok(())
+			};
+			let result = read_layers();
+
+			gl.bind_framebuffer(glow::READ_FRAMEBUFFER, None);
+			gl.delete_framebuffer(framebuffer);
+
+			result.map(|_| pixels)
+		}
+	}
+
+	/** Attaches the given layer or face of the given mip level of this texture
+	 * to the given attachment point of the framebuffer bound to the given
+	 * target. Textures without layers only have their first layer.
+	 *
+	 * # Panic
+	 * This function will panic for one-dimensional textures, which can't be
+	 * attached to a framebuffer. */
+	pub(crate) unsafe fn attach(
+		&self,
+		gl: &Context,
+		target: u32,
+		attachment: u32,
+		level: u32,
+		layer: u32) {
+
+		/* Both the number of levels and the number of layers were checked to
+		 * fit in an i32 when the texture was created. */
+		let level = i32::try_from(level).unwrap();
+		match self.inner.extent.get() {
+			TextureExtent::D1 { .. } =>
+				panic!("cannot attach a one-dimensional texture to a \
+					framebuffer"),
+			TextureExtent::D2 { .. } =>
+				gl.framebuffer_texture_2d(
+					target,
+					attachment,
+					glow::TEXTURE_2D,
+					Some(self.inner.texture),
+					level),
+			TextureExtent::Cube { .. } =>
+				gl.framebuffer_texture_2d(
+					target,
+					attachment,
+					TextureExtent::cube_face_target(layer),
+					Some(self.inner.texture),
+					level),
+			TextureExtent::D2Array { .. } | TextureExtent::D3 { .. } =>
+				gl.framebuffer_texture_layer(
+					target,
+					attachment,
+					Some(self.inner.texture),
+					level,
+					i32::try_from(layer).unwrap())
+		}
+	}
+
+	/** Returns the underlying handle to the texture object.
+	 *
+	 * # Safety
+	 * The handle may only be used from within [`Device::with_external_gl`],
+	 * and the texture must not be deleted, reallocated or have its format
+	 * changed through it, as this structure keeps track of all of those.
+	 *
+	 * [`Device::with_external_gl`]: crate::Device::with_external_gl */
 	pub unsafe fn as_raw_handle(&self) -> <Context as HasContext>::Texture {
 		self.inner.texture
 	}
+
+	/** Wraps a texture object created outside of gavle, through the context of
+	 * the given device, so that it can be used like any other texture.
+	 *
+	 * The texture is assumed to have only its base level, which is what gets
+	 * reported by [`levels()`], and the given ownership decides whether the
+	 * texture object gets deleted once the last reference to it is dropped.
+	 *
+	 * # Safety
+	 * The handle must name a live texture object in the context of the given
+	 * device, whose target matches the one implied by the given extent and
+	 * whose base level has been allocated with the given format and extent.
+	 * For as long as the texture is wrapped, it must only be changed from
+	 * within [`Device::with_external_gl`], in ways that keep all of the above
+	 * true. Borrowed textures must outlive every reference to the wrapper.
+	 *
+	 * [`levels()`]: Self::levels
+	 * [`Device::with_external_gl`]: crate::Device::with_external_gl */
+	pub unsafe fn from_raw(
+		device: &crate::Device,
+		handle: <Context as HasContext>::Texture,
+		format: TextureFormat,
+		extent: TextureExtent,
+		ownership: RawOwnership) -> Self {
+
+		let internal_format = format.internal_format(device.information.version.profile)
+			.unwrap_or(format.as_opengl().1);
+
+		Self {
+			inner: Rc::new(InnerTexture {
+				context: device.context.clone(),
+				pipeline: device.pipeline_lock.clone(),
+				debug: device.debug.clone(),
+				texture: handle,
+				access: Default::default(),
+				format,
+				internal_format,
+				extent: Cell::new(extent),
+				levels: 1,
+				ownership,
+				id: InnerTexture::next_id(),
+				label: Default::default(),
+				/* There's no telling what's in a texture we didn't create, so
+				 * just trust that it's all there. */
+				init: RefCell::new({
+					let mut init = InitTracker::new(1, crate::init::tracked_layers(&extent));
+					init.mark_all();
+					init
+				}),
+				swizzle: TextureSwizzle::IDENTITY,
+				swizzle_on_upload: false,
+			})
+		}
+	}
+}
+
+/** Whether a wrapped texture object belongs to gavle or to whoever created
+ * it. See [`Texture::from_raw`].
+ *
+ * [`Texture::from_raw`]: Texture::from_raw */
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum RawOwnership {
+	/** The object belongs to gavle, and gets deleted once the last reference
+	 * to it is dropped. */
+	Owned,
+	/** The object belongs to someone else, and is left alone once the last
+	 * reference to it is dropped. */
+	Borrowed,
 }
 impl AccessLock for Texture {
 	fn acquire_write(&self) {
@@ -70,6 +803,150 @@ impl AccessLock for Texture {
 	}
 }
 
+/** A view over a range of the mip levels and layers of a texture.
+ *
+ * Created with [`Texture::view()`].
+ *
+ * [`Texture::view()`]: Texture::view */
+#[derive(Debug)]
+pub struct TextureView {
+	/** The texture this is a view of. */
+	pub(crate) texture: Texture,
+	/** The range of the texture covered by this view. */
+	pub(crate) descriptor: TextureViewDescriptor,
+}
+impl TextureView {
+	/** The texture this is a view of. */
+	pub fn texture(&self) -> &Texture {
+		&self.texture
+	}
+
+	/** The range of the texture covered by this view. */
+	pub fn descriptor(&self) -> &TextureViewDescriptor {
+		&self.descriptor
+	}
+
+	/** Name of the helper uniform that gets set to the first layer of views
+	 * bound to the uniform with the given name. */
+	pub fn layer_uniform(binding: &str) -> String {
+		format!("{}_layer", binding)
+	}
+
+	/** GLSL declarations for sampling a view of an array texture bound to the
+	 * uniform with the given name.
+	 *
+	 * This declares both the `sampler2DArray` uniform and its helper layer
+	 * uniform, as well as a `<binding>_sample(uv, layer)` macro which samples
+	 * the given layer of the view, relative to its first layer. These go into
+	 * the shader source, after the precision statements. */
+	pub fn glsl(binding: &str) -> String {
+		format!("uniform highp sampler2DArray {binding};\n\
+			uniform int {layer};\n\
+			#define {binding}_sample(uv, layer) \
+				texture({binding}, vec3((uv), float({layer} + (layer))))\n",
+			binding = binding,
+			layer = Self::layer_uniform(binding))
+	}
+}
+
+/** Range of a texture covered by a [`TextureView`].
+ *
+ * [`TextureView`]: TextureView */
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct TextureViewDescriptor {
+	/** The first mip level in the view. */
+	pub base_mip: u32,
+	/** The number of mip levels in the view. */
+	pub mip_count: u32,
+	/** The first array layer in the view. */
+	pub base_layer: u32,
+	/** The number of array layers in the view. */
+	pub layer_count: u32,
+}
+
+impl TextureViewDescriptor {
+	/** Check whether this view fits inside of a texture with the given number
+	 * of mip levels and layers. */
+	pub(crate) fn validate(&self, levels: u32, layers: u32) -> Result<(), TextureError> {
+		let check = |name, base: u32, count: u32, available: u32| {
+			let end = base.checked_add(count);
+			if count == 0 {
+				Err(TextureError::InvalidView {
+					what: format!("the view must span at least one {}", name)
+				})
+			} else if end.map(|end| end > available).unwrap_or(true) {
+				Err(TextureError::InvalidView {
+					what: format!("the view spans {}s {} through {}, but the \
+						texture only has {} of them",
+						name,
+						base,
+						u64::from(base) + u64::from(count) - 1,
+						available)
+				})
+			} else {
+				Ok(())
+			}
+		};
+
+		check("mip level", self.base_mip, self.mip_count, levels)?;
+		check("layer", self.base_layer, self.layer_count, layers)
+	}
+}
+
+/** A region of a single mip level of a texture, starting at the given origin,
+ * used as either end of a copy between textures. See
+ * [`Device::copy_texture_to_texture()`].
+ *
+ * [`Device::copy_texture_to_texture()`]: crate::Device::copy_texture_to_texture */
+#[derive(Debug, Copy, Clone)]
+pub struct TextureCopyView<'a> {
+	/** The texture being copied from or to. */
+	pub texture: &'a Texture,
+	/** The mip level being copied from or to. */
+	pub mip_level: u32,
+	/** Texel the copied region starts at, in the same axes as the ones given
+	 * by [`TextureExtent::axes()`], so that its last coordinate is the first
+	 * layer, face or slice in the region.
+	 *
+	 * [`TextureExtent::axes()`]: TextureExtent::axes */
+	pub origin: (u32, u32, u32),
+}
+impl<'a> TextureCopyView<'a> {
+	/** Checks whether a copy of a region of the given extent can start at this
+	 * view. */
+	pub(crate) fn validate(&self, region: &TextureExtent) -> Result<(), TextureError> {
+		check_copy_view(
+			&self.texture.inner.extent.get(),
+			self.texture.inner.levels,
+			self.mip_level,
+			self.origin,
+			region)
+	}
+}
+
+/** Checks whether a region of the given extent, at the given origin in the
+ * given mip level of a texture of the given extent and number of levels, can
+ * be attached to a framebuffer to be copied from or to. */
+pub(crate) fn check_copy_view(
+	bounds: &TextureExtent,
+	levels: u32,
+	level: u32,
+	origin: (u32, u32, u32),
+	region: &TextureExtent) -> Result<(), TextureError> {
+
+	if let TextureExtent::D1 { .. } = bounds {
+		return Err(TextureError::InvalidCopy {
+			what: "one-dimensional textures can't be attached to a \
+				framebuffer to be copied".into()
+		})
+	}
+	if level >= levels {
+		return Err(TextureError::InvalidLevel { level, levels })
+	}
+
+	check_region(&bounds.mip(level), origin, region)
+}
+
 /** Formats textures are allowed to have. */
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub enum TextureFormat {
@@ -77,8 +954,147 @@ pub enum TextureFormat {
 	Rgba32Float,
 	/** RGBA with an 8-bit unsigned integer for every component. */
 	Rgba8Unorm,
+	/** RGBA with an 8-bit unsigned integer for every component, with the
+	 * color components encoded with the sRGB transfer function. Shaders
+	 * sampling textures in this format see linear colors, which is what color
+	 * data, such as albedo maps, should be stored as. */
+	Srgb8Alpha8,
 	/** Combined depth-stencil format. 24-bit depth and 8-bit stencil. */
-	Depth24Stencil8
+	Depth24Stencil8,
+	/** RGB compressed with ETC2, in blocks of 4x4 texels taking up 8 bytes
+	 * each. Requires [`Capabilities::compressed_etc2`].
+	 *
+	 * [`Capabilities::compressed_etc2`]: crate::Capabilities::compressed_etc2 */
+	Etc2Rgb8Unorm,
+	/** RGBA compressed with ETC2, with the alpha compressed with EAC, in
+	 * blocks of 4x4 texels taking up 16 bytes each. Requires
+	 * [`Capabilities::compressed_etc2`].
+	 *
+	 * [`Capabilities::compressed_etc2`]: crate::Capabilities::compressed_etc2 */
+	Etc2Rgba8Unorm,
+	/** RGBA compressed with BC1, also known as DXT1, in blocks of 4x4 texels
+	 * taking up 8 bytes each, with a single bit of alpha. Requires
+	 * [`Capabilities::compressed_s3tc`].
+	 *
+	 * [`Capabilities::compressed_s3tc`]: crate::Capabilities::compressed_s3tc */
+	Bc1RgbaUnorm,
+	/** RGBA compressed with BC2, also known as DXT3, in blocks of 4x4 texels
+	 * taking up 16 bytes each, with four bits of explicit alpha. Requires
+	 * [`Capabilities::compressed_s3tc`].
+	 *
+	 * [`Capabilities::compressed_s3tc`]: crate::Capabilities::compressed_s3tc */
+	Bc2RgbaUnorm,
+	/** RGBA compressed with BC3, also known as DXT5, in blocks of 4x4 texels
+	 * taking up 16 bytes each, with interpolated alpha. Requires
+	 * [`Capabilities::compressed_s3tc`].
+	 *
+	 * [`Capabilities::compressed_s3tc`]: crate::Capabilities::compressed_s3tc */
+	Bc3RgbaUnorm,
+}
+impl TextureFormat {
+	/** Number of bytes taken up by a single texel in this format, or, for
+	 * compressed formats, by a whole block of texels. */
+	pub fn bytes_per_texel(&self) -> u32 {
+		match self {
+			Self::Rgba32Float => 4 * 4,
+			Self::Rgba8Unorm => 4,
+			Self::Srgb8Alpha8 => 4,
+			Self::Depth24Stencil8 => 4,
+			Self::Etc2Rgb8Unorm | Self::Bc1RgbaUnorm => 8,
+			Self::Etc2Rgba8Unorm | Self::Bc2RgbaUnorm | Self::Bc3RgbaUnorm => 16,
+		}
+	}
+
+	/** Whether data in this format is stored in compressed blocks of texels,
+	 * rather than one texel at a time. */
+	pub fn is_compressed(&self) -> bool {
+		self.block_size() > 1
+	}
+
+	/** Width and height of the blocks texels in this format are stored in,
+	 * which is one for formats that aren't compressed. */
+	pub fn block_size(&self) -> u32 {
+		match self {
+			Self::Rgba32Float
+				| Self::Rgba8Unorm
+				| Self::Srgb8Alpha8
+				| Self::Depth24Stencil8 => 1,
+			Self::Etc2Rgb8Unorm
+				| Self::Etc2Rgba8Unorm
+				| Self::Bc1RgbaUnorm
+				| Self::Bc2RgbaUnorm
+				| Self::Bc3RgbaUnorm => 4,
+		}
+	}
+
+	/** Number of bytes taken up by data in this format covering the given
+	 * extent, failing if that doesn't fit in the address space. Compressed
+	 * formats take up whole blocks, even along the edges of the extent. */
+	pub(crate) fn len(&self, extent: &TextureExtent) -> Result<usize, TextureError> {
+		let (width, height, depth) = extent.axes();
+		let block = self.block_size();
+		let len = u64::from(width.div_ceil(block))
+			.checked_mul(u64::from(height.div_ceil(block)))
+			.and_then(|len| len.checked_mul(u64::from(depth)))
+			.and_then(|len| len.checked_mul(u64::from(self.bytes_per_texel())));
+
+		match len {
+			Some(len) => usize::try_from(len)
+				.map_err(|_| TextureError::TooLarge { computed_bytes: len }),
+			None => Err(TextureError::TooLarge { computed_bytes: u64::MAX })
+		}
+	}
+
+	/** Ranges of bytes taken up by each of the given number of levels of a
+	 * texture of the given extent in data that packs the levels one after the
+	 * other, starting with the base level. */
+	pub(crate) fn level_ranges(&self, extent: &TextureExtent, levels: u32)
+		-> Result<Vec<Range<usize>>, TextureError> {
+
+		let mut ranges = Vec::with_capacity(levels as usize);
+		let mut end = 0usize;
+		for level in 0..levels {
+			let len = self.len(&extent.mip(level))?;
+			let next = end.checked_add(len)
+				.ok_or(TextureError::TooLarge {
+					computed_bytes: (end as u64).saturating_add(len as u64)
+				})?;
+
+			ranges.push(end..next);
+			end = next;
+		}
+
+		Ok(ranges)
+	}
+
+	/** The internal format storage for data in this format gets allocated
+	 * with, in a context of the given profile, or `None` if textures in this
+	 * format can't be created there at all.
+	 *
+	 * Legacy contexts only take unsized internal formats, which must match
+	 * the pixel format of the data, and only [`TextureFormat::Rgba8Unorm`]
+	 * has one that's always available. sRGB formats are only available there
+	 * through extensions, which are not supported, and neither are compressed
+	 * formats. Whether compressed formats are available elsewhere depends on
+	 * the [`Capabilities`] of the context, which this doesn't check.
+	 *
+	 * [`Capabilities`]: crate::Capabilities */
+	pub(crate) fn internal_format(&self, profile: Profile) -> Option<u32> {
+		let (format, internal_format, _) = self.as_opengl();
+		if !profile.is_legacy() { return Some(internal_format) }
+
+		match self {
+			Self::Rgba8Unorm => Some(format),
+			Self::Srgb8Alpha8
+				| Self::Rgba32Float
+				| Self::Depth24Stencil8
+				| Self::Etc2Rgb8Unorm
+				| Self::Etc2Rgba8Unorm
+				| Self::Bc1RgbaUnorm
+				| Self::Bc2RgbaUnorm
+				| Self::Bc3RgbaUnorm => None
+		}
+	}
 }
 
 
@@ -95,13 +1111,22 @@ pub enum TextureFilter {
 	/** Linear based filtering. */
 	Linear
 }
-impl TextureFilter {
-	/** Get the OpenGL enum value for the current variant. */
-	pub(crate) fn as_opengl(&self, min: bool) -> u32 {
-		match self {
-			Self::Nearest => if min { glow::NEAREST_MIPMAP_NEAREST } else { glow::NEAREST },
-			Self::Linear => if min { glow::LINEAR_MIPMAP_LINEAR } else { glow::LINEAR },
-		}
+
+/** Addressing modes for texture coordinates that fall outside of the texture,
+ * set independently for each of its axes. */
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum AddressMode {
+	/** The texture gets tiled. */
+	Repeat,
+	/** The texture gets tiled, with every other tile being mirrored. */
+	MirrorRepeat,
+	/** Coordinates get clamped to the texels at the edge of the texture. */
+	ClampToEdge,
+}
+impl Default for AddressMode {
+	/** OpenGL repeats textures unless told otherwise. */
+	fn default() -> Self {
+		Self::Repeat
 	}
 }
 
@@ -114,6 +1139,14 @@ pub struct TextureDescriptor {
 	pub format: TextureFormat,
 	/** How this texture  */
 	pub mip: Mipmap,
+	/** How the channels of this texture are seen by shaders sampling it, or
+	 * `None`, for them to be seen as they are stored. Textures in depth and
+	 * stencil formats can't be swizzled. See [`TextureSwizzle`] for how this
+	 * is implemented in contexts without the [`texture_swizzle`] capability.
+	 *
+	 * [`TextureSwizzle`]: crate::TextureSwizzle
+	 * [`texture_swizzle`]: crate::Capabilities::texture_swizzle */
+	pub swizzle: Option<TextureSwizzle>,
 }
 
 /** Mipmap behavior of a texture. */
@@ -122,27 +1155,63 @@ pub enum Mipmap {
 	/** No mips will be generated or available for this texture. */
 	None,
 	/** The given number of mips will be available, and the user must provide
-	 * the image data for each of the mips manually. */
+	 * the image data for each of the mips manually.
+	 *
+	 * Initialization data for textures with manual mipmaps must contain every
+	 * level, packed one after the other, starting with the base level. Each
+	 * level is laid out just like the base level, at its own extent, as given
+	 * by [`TextureExtent::mip()`]. Levels may also be replaced later on with
+	 * [`Texture::write_level()`].
+	 *
+	 * [`TextureExtent::mip()`]: TextureExtent::mip
+	 * [`Texture::write_level()`]: Texture::write_level */
 	Manual {
 		/** Number of mip levels of the texture. */
 		levels: NonZeroU32,
 	},
 	/** All of the mip levels that can be generated for this texture will be
-	 * generated automatically by Gavle.
+	 * generated automatically from the base level, right after it gets
+	 * uploaded.
+	 *
+	 * With the `mipmap-generation` feature, two-dimensional textures created
+	 * with data in the [`TextureFormat::Rgba8Unorm`] and
+	 * [`TextureFormat::Rgba32Float`] formats get their levels scaled down on
+	 * the host, with the given filter. Every other texture gets its levels
+	 * generated by OpenGL, with filtering that is up to the implementation.
 	 *
 	 * Often, what you'll want is to pre-bake the mipmaps before runtime in
-	 * order to save on initialization time, seeing as mipmap generation is very
-	 * expensive. */
-	#[cfg(feature = "mipmap-generation")]
+	 * order to have control over their quality and to save on initialization
+	 * time. Mipmaps can't be generated for textures in depth-stencil formats,
+	 * nor in compressed formats, and they can only be generated by OpenGL for
+	 * textures in the [`TextureFormat::Rgba32Float`] format in contexts with
+	 * both the [`float_texture_filtering`] and the [`float_color_buffers`]
+	 * capabilities.
+	 *
+	 * [`float_texture_filtering`]: crate::Capabilities::float_texture_filtering
+	 * [`float_color_buffers`]: crate::Capabilities::float_color_buffers */
 	Automatic {
-		/** Filter to be used to scale down the image during generation of the
-		 * mip levels. T */
+		/** Filter used to scale the image down during generation of the mip
+		 * levels on the host. */
 		filter: FilterType
 	},
 }
 
-#[cfg(feature = "mipmap-generation")]
-pub use image::imageops::FilterType;
+/** Filters the levels of textures with [`Mipmap::Automatic`] may be scaled
+ * down with, when they get generated on the host. These are the same as the
+ * filters of the `image` crate, which does the scaling. */
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum FilterType {
+	/** Nearest neighbor. */
+	Nearest,
+	/** Linear filter. */
+	Triangle,
+	/** Cubic filter. */
+	CatmullRom,
+	/** Gaussian filter. */
+	Gaussian,
+	/** Lanczos with a window of three. */
+	Lanczos3,
+}
 
 /** Extents of a given texture in their given dimensional layout. */
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
@@ -167,8 +1236,120 @@ pub enum TextureExtent {
 		width: u32,
 		height: u32,
 		depth: u32
+	},
+	/** Cube map, made up of six square two-dimensional faces.
+	 *
+	 * Initialization data for cube maps packs the faces one after the other,
+	 * in the order given by [`TextureExtent::CUBE_FACES`], with every face laid
+	 * out just like a two-dimensional texture. Cube maps are sampled through a
+	 * `samplerCube` uniform.
+	 *
+	 * [`TextureExtent::CUBE_FACES`]: TextureExtent::CUBE_FACES */
+	Cube {
+		size: u32,
 	}
 }
+impl TextureExtent {
+	/** Names of the faces of a cube map, in the order they are packed in and
+	 * indexed by: `+X`, `-X`, `+Y`, `-Y`, `+Z` and `-Z`. */
+	pub const CUBE_FACES: [&'static str; 6] = ["+X", "-X", "+Y", "-Y", "+Z", "-Z"];
+
+	/** The extent of the given mip level of a texture with this extent. Every
+	 * axis is halved once per level, down to a single texel, except for the
+	 * number of layers in an array, which is the same across all levels. */
+	pub fn mip(&self, level: u32) -> Self {
+		let shrink = |axis: u32| u32::max(axis.checked_shr(level).unwrap_or(0), 1);
+		match *self {
+			Self::D1 { length } => Self::D1 {
+				length: shrink(length)
+			},
+			Self::D2 { width, height } => Self::D2 {
+				width: shrink(width),
+				height: shrink(height)
+			},
+			Self::D2Array { width, height, layers } => Self::D2Array {
+				width: shrink(width),
+				height: shrink(height),
+				layers
+			},
+			Self::D3 { width, height, depth } => Self::D3 {
+				width: shrink(width),
+				height: shrink(height),
+				depth: shrink(depth)
+			},
+			Self::Cube { size } => Self::Cube {
+				size: shrink(size)
+			},
+		}
+	}
+
+	/** The number of mip levels in a full chain for a texture with this extent,
+	 * going all the way down to a single texel on the largest of the axes
+	 * affected by mipmapping. */
+	pub fn max_levels(&self) -> u32 {
+		let axis = match *self {
+			Self::D1 { length } => length,
+			Self::D2 { width, height } => u32::max(width, height),
+			Self::D2Array { width, height, .. } => u32::max(width, height),
+			Self::D3 { width, height, depth } =>
+				u32::max(u32::max(width, height), depth),
+			Self::Cube { size } => size,
+		};
+		32 - u32::max(axis, 1).leading_zeros()
+	}
+
+	/** The width, height and depth of this extent, with the axes a layout
+	 * lacks being one texel long. The layers of an array and the faces of a
+	 * cube map are its depth. */
+	pub(crate) fn axes(&self) -> (u32, u32, u32) {
+		match *self {
+			Self::D1 { length } => (length, 1, 1),
+			Self::D2 { width, height } => (width, height, 1),
+			Self::D2Array { width, height, layers } => (width, height, layers),
+			Self::D3 { width, height, depth } => (width, height, depth),
+			Self::Cube { size } => (size, size, 6),
+		}
+	}
+}
+
+/** Where the texels written to a region of a texture come from. */
+#[derive(Copy, Clone)]
+enum TexelSource<'a> {
+	/** Exactly the texels of the region, in host memory. */
+	Host(&'a [u8]),
+	/** The texels of the region, in a pixel unpack buffer, starting at the
+	 * given byte offset into it. */
+	Buffer(&'a Buffer, u32),
+}
+
+/** Checks whether the region starting at the given origin and spanning the
+ * axes of the given extent fits inside of the given bounds. */
+pub(crate) fn check_region(
+	bounds: &TextureExtent,
+	origin: (u32, u32, u32),
+	region: &TextureExtent) -> Result<(), TextureError> {
+
+	let (width, height, depth) = region.axes();
+	let (max_width, max_height, max_depth) = bounds.axes();
+
+	let fits = |start: u32, len: u32, max: u32| start.checked_add(len)
+		.map(|end| end <= max)
+		.unwrap_or(false);
+	if !fits(origin.0, width, max_width)
+		|| !fits(origin.1, height, max_height)
+		|| !fits(origin.2, depth, max_depth) {
+
+		return Err(TextureError::InvalidBounds {
+			what: format!("a region of extent {:?} at {:?} does not fit in a \
+				texture of extent {:?}",
+				region,
+				origin,
+				bounds)
+		})
+	}
+
+	Ok(())
+}
 
 #[derive(Debug, thiserror::Error)]
 pub enum TextureError {
@@ -179,5 +1360,239 @@ pub enum TextureError {
 	#[error("the bounds given to the texture are invalid")]
 	InvalidBounds {
 		what: String
+	},
+	#[error("the texture exceeds the limits of the context: {what}")]
+	LimitExceeded {
+		what: String
+	},
+	#[error("the requested mipmap configuration is not supported: {what}")]
+	UnsupportedMipmap {
+		what: String
+	},
+	#[error("the texture extent is not supported by the context: {what}")]
+	UnsupportedExtent {
+		what: String
+	},
+	#[error("the requested texture view is invalid: {what}")]
+	InvalidView {
+		what: String
+	},
+	#[error("mip level {level} is out of range for a texture with {levels} \
+		levels")]
+	InvalidLevel {
+		level: u32,
+		levels: u32
+	},
+	#[error("the textures can't be copied: {what}")]
+	InvalidCopy {
+		what: String
+	},
+	#[error("the texture can't be read back: {what}")]
+	UnsupportedReadback {
+		what: String
+	},
+	#[error("the texture data is {provided} bytes long, but {expected} bytes \
+		were expected")]
+	InvalidDataLength {
+		expected: u64,
+		provided: u64
+	},
+	#[error("the texture format is not supported by the context: {what}")]
+	UnsupportedFormat {
+		what: String
+	},
+	/** The data of the texture takes up more bytes than there are in the
+	 * address space. The computed size saturates at [`u64::MAX`]. */
+	#[error("the texture data would take up {computed_bytes} bytes, which \
+		doesn't fit in the address space")]
+	TooLarge {
+		computed_bytes: u64
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn view(base_mip: u32, mip_count: u32, base_layer: u32, layer_count: u32)
+		-> TextureViewDescriptor {
+
+		TextureViewDescriptor { base_mip, mip_count, base_layer, layer_count }
+	}
+
+	#[test]
+	fn regions_must_fit_in_the_texture() {
+		let array = TextureExtent::D2Array { width: 64, height: 32, layers: 4 };
+		let layer = TextureExtent::D2 { width: 16, height: 16 };
+		assert!(check_region(&array, (48, 16, 3), &layer).is_ok());
+		assert!(check_region(&array, (48, 16, 4), &layer).is_err());
+		assert!(check_region(&array, (49, 0, 0), &layer).is_err());
+		assert!(check_region(&array, (0, u32::MAX, 0), &layer).is_err());
+
+		let cube = TextureExtent::Cube { size: 16 };
+		assert!(check_region(&cube, (0, 0, 5), &layer).is_ok());
+		assert!(check_region(&cube, (0, 0, 0), &cube).is_ok());
+		assert!(check_region(&cube, (0, 0, 1), &cube).is_err());
+	}
+
+	#[test]
+	fn copies_must_fit_in_the_level() {
+		let extent = TextureExtent::D2Array { width: 64, height: 32, layers: 4 };
+		let layer = TextureExtent::D2 { width: 16, height: 16 };
+		assert!(check_copy_view(&extent, 3, 0, (48, 16, 3), &layer).is_ok());
+		assert!(check_copy_view(&extent, 3, 2, (0, 0, 0), &layer).is_err());
+		assert!(check_copy_view(&extent, 3, 3, (0, 0, 0), &layer).is_err());
+
+		/* The third level is only 16x8 texels big. */
+		let level = TextureExtent::D2 { width: 16, height: 8 };
+		assert!(check_copy_view(&extent, 3, 2, (0, 0, 3), &level).is_ok());
+		assert!(check_copy_view(&extent, 3, 2, (1, 0, 0), &level).is_err());
+
+		let line = TextureExtent::D1 { length: 16 };
+		assert!(check_copy_view(&line, 1, 0, (0, 0, 0), &line).is_err());
+	}
+
+	#[test]
+	fn cube_maps() {
+		let extent = TextureExtent::Cube { size: 64 };
+		assert_eq!(extent.max_levels(), 7);
+		assert_eq!(extent.mip(3), TextureExtent::Cube { size: 8 });
+		assert_eq!(extent.target(), glow::TEXTURE_CUBE_MAP);
+
+		/* All six faces are packed in the initialization data. */
+		assert_eq!(TextureFormat::Rgba8Unorm.len(&extent).unwrap(), 64 * 64 * 4 * 6);
+
+		let targets = (0..6)
+			.map(TextureExtent::cube_face_target)
+			.collect::<Vec<_>>();
+		assert_eq!(targets, vec![
+			glow::TEXTURE_CUBE_MAP_POSITIVE_X,
+			glow::TEXTURE_CUBE_MAP_NEGATIVE_X,
+			glow::TEXTURE_CUBE_MAP_POSITIVE_Y,
+			glow::TEXTURE_CUBE_MAP_NEGATIVE_Y,
+			glow::TEXTURE_CUBE_MAP_POSITIVE_Z,
+			glow::TEXTURE_CUBE_MAP_NEGATIVE_Z,
+		]);
+	}
+
+	#[test]
+	fn address_modes() {
+		assert_eq!(AddressMode::Repeat.as_opengl(), glow::REPEAT);
+		assert_eq!(AddressMode::MirrorRepeat.as_opengl(), glow::MIRRORED_REPEAT);
+		assert_eq!(AddressMode::ClampToEdge.as_opengl(), glow::CLAMP_TO_EDGE);
+		assert_eq!(AddressMode::default(), AddressMode::Repeat);
+	}
+
+	#[test]
+	fn mip_extents() {
+		let extent = TextureExtent::D2Array { width: 16, height: 4, layers: 3 };
+		assert_eq!(extent.max_levels(), 5);
+		assert_eq!(extent.mip(2),
+			TextureExtent::D2Array { width: 4, height: 1, layers: 3 });
+		assert_eq!(extent.mip(40),
+			TextureExtent::D2Array { width: 1, height: 1, layers: 3 });
+
+		let format = TextureFormat::Rgba32Float;
+		assert_eq!(format.len(&extent.mip(1)).unwrap(), 8 * 2 * 3 * 16);
+		assert_eq!(TextureExtent::D1 { length: 0 }.max_levels(), 1);
+		assert!(matches!(format.len(&TextureExtent::D3 {
+			width: u32::MAX,
+			height: u32::MAX,
+			depth: u32::MAX
+		}), Err(TextureError::TooLarge { computed_bytes: u64::MAX })));
+	}
+
+	#[test]
+	fn sizes_are_computed_in_64_bits() {
+		/* Every one of the factors fits in 32 bits, but their product doesn't. */
+		let extent = TextureExtent::D2Array { width: 65536, height: 65536, layers: 2 };
+		let computed = 65536u64 * 65536 * 2 * 4;
+		match TextureFormat::Rgba8Unorm.len(&extent) {
+			Ok(len) => assert_eq!(len as u64, computed),
+			Err(TextureError::TooLarge { computed_bytes }) =>
+				assert_eq!(computed_bytes, computed),
+			Err(what) => panic!("unexpected error: {}", what)
+		}
+
+		/* Two layers of the largest extent already overflow 64 bits. */
+		let layers = TextureExtent::D2Array {
+			width: u32::MAX,
+			height: u32::MAX,
+			layers: 2
+		};
+		assert!(matches!(TextureFormat::Rgba8Unorm.len(&layers),
+			Err(TextureError::TooLarge { computed_bytes: u64::MAX })));
+		assert!(matches!(TextureFormat::Rgba8Unorm.len(&layers.mip(1)),
+			Err(TextureError::TooLarge { .. })));
+	}
+
+	#[test]
+	fn levels_are_packed_one_after_the_other() {
+		let extent = TextureExtent::D2 { width: 4, height: 2 };
+		let ranges = TextureFormat::Rgba8Unorm.level_ranges(&extent, 3).unwrap();
+		assert_eq!(ranges, vec![0..32, 32..40, 40..44]);
+		assert!(TextureFormat::Rgba8Unorm.level_ranges(&extent, 0)
+			.unwrap()
+			.is_empty());
+
+		let huge = TextureExtent::D2Array {
+			width: u32::MAX,
+			height: u32::MAX,
+			layers: u32::MAX
+		};
+		assert!(matches!(
+			TextureFormat::Rgba8Unorm.level_ranges(&huge, huge.max_levels()),
+			Err(TextureError::TooLarge { .. })));
+	}
+
+	#[test]
+	fn view_validation() {
+		assert!(view(0, 4, 0, 1).validate(4, 1).is_ok());
+		assert!(view(3, 1, 2, 2).validate(4, 4).is_ok());
+
+		assert!(view(0, 0, 0, 1).validate(4, 1).is_err());
+		assert!(view(0, 1, 0, 0).validate(4, 1).is_err());
+		assert!(view(2, 3, 0, 1).validate(4, 1).is_err());
+		assert!(view(0, 1, 1, 1).validate(4, 1).is_err());
+		assert!(view(0, 1, u32::MAX, 2).validate(1, u32::MAX).is_err());
+	}
+
+	#[test]
+	fn legacy_formats_are_unsized() {
+		assert_eq!(
+			TextureFormat::Rgba8Unorm.internal_format(Profile::Es),
+			Some(glow::RGBA8));
+		assert_eq!(
+			TextureFormat::Rgba8Unorm.internal_format(Profile::WebLegacy),
+			Some(glow::RGBA));
+		assert_eq!(TextureFormat::Rgba32Float.internal_format(Profile::EsLegacy), None);
+		assert_eq!(TextureFormat::Srgb8Alpha8.internal_format(Profile::WebLegacy), None);
+		assert_eq!(TextureFormat::Depth24Stencil8.internal_format(Profile::WebLegacy), None);
+		assert_eq!(TextureFormat::Bc1RgbaUnorm.internal_format(Profile::EsLegacy), None);
+	}
+
+	#[test]
+	fn compressed_sizes_round_up_to_blocks() {
+		let format = TextureFormat::Bc3RgbaUnorm;
+		assert!(format.is_compressed());
+		assert!(!TextureFormat::Rgba8Unorm.is_compressed());
+
+		let extent = TextureExtent::D2 { width: 10, height: 4 };
+		assert_eq!(format.len(&extent).unwrap(), 3 * 16);
+		assert_eq!(TextureFormat::Etc2Rgb8Unorm.len(&extent.mip(2)).unwrap(), 8);
+
+		/* Levels smaller than a block still take up a whole one. */
+		let ranges = TextureFormat::Bc1RgbaUnorm.level_ranges(&extent, 4).unwrap();
+		assert_eq!(ranges, vec![0..24, 24..40, 40..48, 48..56]);
+	}
+
+	#[test]
+	fn view_glsl() {
+		let glsl = TextureView::glsl("tt_cascades");
+
+		assert!(glsl.contains("uniform highp sampler2DArray tt_cascades;"));
+		assert!(glsl.contains("uniform int tt_cascades_layer;"));
+		assert!(glsl.contains("#define tt_cascades_sample(uv, layer) \
+			texture(tt_cascades, vec3((uv), float(tt_cascades_layer + (layer))))"));
 	}
 }
@@ -0,0 +1,198 @@
+use std::cell::RefCell;
+use std::convert::TryFrom;
+use std::ops::Range;
+use glow::HasContext;
+use crate::Device;
+use crate::access::AccessLock;
+use crate::buffer::*;
+use crate::lock::LockOperation;
+use crate::sanitize::BufferContents;
+
+/** Range of bytes of a staging area that is `available` bytes long holding
+ * `len` bytes of texels starting at `offset`, or `None` if they run past the
+ * end of it. */
+pub(crate) fn staged_range(offset: u32, len: usize, available: u32) -> Option<Range<usize>> {
+	let start = usize::try_from(offset).ok()?;
+	let end = start.checked_add(len)?;
+	if end > usize::try_from(available).ok()? { return None }
+
+	Some(start..end)
+}
+
+/** Where the texels of an upload buffer are kept. */
+#[derive(Debug)]
+pub(crate) enum UploadStorage {
+	/** A pixel unpack buffer on the device, which textures get written to
+	 * from by the implementation, without going through the host. */
+	Device(Buffer),
+	/** Host memory, for contexts without pixel unpack buffers, which textures
+	 * get written to from directly, as if the texels had been handed to
+	 * [`Texture::write()`].
+	 *
+	 * [`Texture::write()`]: crate::Texture::write */
+	Host(RefCell<Box<[u8]>>),
+}
+
+/** A staging area for texels on their way to a texture, created with
+ * [`Device::create_upload_buffer`] and copied from with
+ * [`Texture::write_from_buffer()`].
+ *
+ * In contexts with the [`pixel_unpack_buffers`] capability, this is a buffer
+ * on the device, which gets mapped to be filled in, and which the texels then
+ * get copied out of by the implementation, on its own time, rather than the
+ * frame stalling while they get copied from host memory. Everywhere else, the
+ * texels are kept in host memory and uploaded directly, so the same code works
+ * on every context, just without the benefit.
+ *
+ * [`Device::create_upload_buffer`]: crate::Device::create_upload_buffer
+ * [`Texture::write_from_buffer()`]: crate::Texture::write_from_buffer
+ * [`pixel_unpack_buffers`]: crate::Capabilities::pixel_unpack_buffers */
+#[derive(Debug)]
+pub struct UploadBuffer {
+	/** Where the texels are kept. */
+	pub(crate) storage: UploadStorage,
+}
+impl UploadBuffer {
+	/** Length of this buffer, in bytes. */
+	pub fn len(&self) -> u32 {
+		match &self.storage {
+			UploadStorage::Device(buffer) => buffer.len(),
+			UploadStorage::Host(storage) =>
+				u32::try_from(storage.borrow().len()).unwrap()
+		}
+	}
+
+	/** Whether the texels in this buffer are kept on the device, rather than
+	 * in host memory. */
+	pub fn is_on_device(&self) -> bool {
+		matches!(self.storage, UploadStorage::Device(_))
+	}
+
+	/** Writes the given data into this buffer, starting at the given byte
+	 * offset.
+	 *
+	 * # Panic
+	 * This function will panic if the buffer is kept on the device and the
+	 * pipeline can't be locked mutably, as is the case while a render pass is
+	 * running. */
+	pub fn write(&self, offset: u32, data: &[u8]) -> Result<(), BufferError> {
+		match &self.storage {
+			UploadStorage::Device(buffer) => buffer.write(offset, data),
+			UploadStorage::Host(storage) => {
+				let mut storage = storage.borrow_mut();
+				let len = u32::try_from(storage.len()).unwrap();
+				write_bounds(offset, data.len(), len)?;
+
+				let offset = usize::try_from(offset).unwrap();
+				storage[offset..offset + data.len()].copy_from_slice(data);
+				Ok(())
+			}
+		}
+	}
+
+	/** Replaces the contents of this whole buffer with the data written by
+	 * the given function, through a writer over the buffer mapped into host
+	 * memory, which must be written to completely.
+	 *
+	 * The old contents of the buffer get orphaned rather than overwritten, so
+	 * that mapping doesn't have to wait on copies out of them that the
+	 * implementation may still be doing. The function is called with the
+	 * pipeline locked, so it can't use the device.
+	 *
+	 * # Panic
+	 * This function will panic if the buffer is kept on the device and the
+	 * pipeline can't be locked mutably, as is the case while a render pass is
+	 * running, or if the buffer fails to get mapped. */
+	pub fn write_with<F>(&self, f: F)
+		where F: FnOnce(&mut BufferWriter) {
+
+		let buffer = match &self.storage {
+			UploadStorage::Device(buffer) => buffer,
+			UploadStorage::Host(storage) => {
+				let mut storage = storage.borrow_mut();
+				let mut writer = BufferWriter::new(&mut storage[..]);
+				f(&mut writer);
+				writer.finish(&format_args!("upload buffer in host memory"));
+				return
+			}
+		};
+
+		let inner = &buffer.inner;
+		let _buffer_lock = inner.acquire_write_guarded();
+		let _pipeline_lock = inner.pipeline.acquire(LockOperation::BufferMapping);
+		if inner.len == 0 {
+			f(&mut BufferWriter::new(&mut []));
+			return
+		}
+
+		let gl = inner.context.as_ref();
+		let len = i32::try_from(inner.len).unwrap();
+		unsafe {
+			gl.bind_buffer(glow::PIXEL_UNPACK_BUFFER, Some(inner.buffer));
+			let ptr = gl.map_buffer_range(
+				glow::PIXEL_UNPACK_BUFFER,
+				0,
+				len,
+				glow::MAP_WRITE_BIT | glow::MAP_INVALIDATE_BUFFER_BIT);
+			if ptr.is_null() {
+				panic!("opengl failed to map the buffer {} to memory: {}",
+					inner.name(),
+					crate::debug::error_name(gl.get_error()));
+			}
+
+			let storage = std::slice::from_raw_parts_mut(
+				ptr,
+				usize::try_from(inner.len).unwrap());
+			let mut writer = BufferWriter::new(storage);
+			f(&mut writer);
+			writer.finish(&format_args!("upload buffer {}", inner.lazy_name()));
+
+			gl.unmap_buffer(glow::PIXEL_UNPACK_BUFFER);
+			gl.bind_buffer(glow::PIXEL_UNPACK_BUFFER, None);
+			inner.check("writing to");
+		}
+	}
+}
+
+impl Device {
+	/** Tries to create a new, zeroed out, upload buffer of the given size, in
+	 * bytes, for staging texels on their way to textures. The buffer is only
+	 * kept on the device in contexts with the [`pixel_unpack_buffers`]
+	 * capability, and in host memory everywhere else.
+	 *
+	 * [`pixel_unpack_buffers`]: crate::Capabilities::pixel_unpack_buffers */
+	pub fn create_upload_buffer(&self, size: u32) -> Result<UploadBuffer, BufferError> {
+		if !self.information.capabilities.pixel_unpack_buffers {
+			let storage = vec![0; usize::try_from(size).unwrap()];
+			return Ok(UploadBuffer {
+				storage: UploadStorage::Host(RefCell::new(storage.into_boxed_slice()))
+			})
+		}
+
+		let buffer = self.create_buffer(&BufferDescriptor {
+			size,
+			profile: BufferProfile::DynamicUpload,
+			contents: BufferContents::Opaque,
+			usage: BufferUsage::PIXEL_UNPACK
+		})?;
+		Ok(UploadBuffer {
+			storage: UploadStorage::Device(buffer)
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn staged_texels_must_fit_in_the_buffer() {
+		assert_eq!(staged_range(0, 16, 16), Some(0..16));
+		assert_eq!(staged_range(4, 8, 16), Some(4..12));
+		assert_eq!(staged_range(16, 0, 16), Some(16..16));
+
+		assert_eq!(staged_range(12, 8, 16), None);
+		assert_eq!(staged_range(17, 0, 16), None);
+		assert_eq!(staged_range(u32::MAX, usize::MAX, 16), None);
+	}
+}
@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+
+/** How recently a texture has been bound, as of the time a usage report was
+ * put together. See [`Device::texture_usage_report()`].
+ *
+ * [`Device::texture_usage_report()`]: crate::Device::texture_usage_report */
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct TextureUsage {
+	/** Identifier of the texture, as given by [`Texture::id()`].
+	 *
+	 * [`Texture::id()`]: crate::Texture::id */
+	pub id: u64,
+	/** Label of the texture, if it was given one. */
+	pub label: Option<String>,
+	/** Approximate amount of video memory taken up by the texture, counting
+	 * all of its mip levels, in bytes. */
+	pub bytes: u64,
+	/** Index of the last frame the texture was bound in. */
+	pub last_used_frame: u64,
+	/** Number of times the texture was bound in the frame before the current
+	 * one. */
+	pub binds_last_frame: u32,
+}
+
+/** Bind counts of a single texture. */
+#[derive(Debug)]
+struct Usage<T> {
+	/** Handle used to look the texture up when putting a report together. */
+	handle: T,
+	/** Index of the last frame the texture was bound in. */
+	last_used_frame: u64,
+	/** Number of times the texture was bound in its last frame. */
+	binds: u32,
+	/** Index of the frame the texture was bound in before its last one, and
+	 * the number of times it was bound in it. */
+	previous: (u64, u32),
+}
+
+/** Tracker of the frames in which textures get bound, approximating which of
+ * them are actually being sampled from. */
+#[derive(Debug)]
+pub(crate) struct UsageTracker<T> {
+	/** Whether binds are being recorded. */
+	enabled: bool,
+	/** Index of the frame currently being recorded. */
+	frame: u64,
+	/** Usage of every texture bound since recording was turned on. */
+	textures: HashMap<u64, Usage<T>>,
+}
+impl<T> UsageTracker<T> {
+	/** Creates a new tracker, with recording turned off. */
+	pub(crate) fn new() -> Self {
+		Self {
+			enabled: false,
+			frame: 0,
+			textures: HashMap::new()
+		}
+	}
+
+	/** Whether binds are being recorded. */
+	pub(crate) fn enabled(&self) -> bool {
+		self.enabled
+	}
+
+	/** Turns the recording of binds on or off. Everything recorded so far is
+	 * thrown away when recording gets turned off. */
+	pub(crate) fn set_enabled(&mut self, enabled: bool) {
+		self.enabled = enabled;
+		if !enabled {
+			self.textures.clear();
+		}
+	}
+
+	/** Records a bind of the texture with the given identifier in the current
+	 * frame. The handle is only created the first time a texture is seen. */
+	pub(crate) fn record<H>(&mut self, id: u64, handle: H)
+		where H: FnOnce() -> T {
+
+		if !self.enabled { return }
+
+		let frame = self.frame;
+		let usage = self.textures.entry(id).or_insert_with(|| Usage {
+			handle: handle(),
+			last_used_frame: frame,
+			binds: 0,
+			previous: (0, 0)
+		});
+		if usage.last_used_frame != frame {
+			usage.previous = (usage.last_used_frame, usage.binds);
+			usage.last_used_frame = frame;
+			usage.binds = 0;
+		}
+		usage.binds = usage.binds.saturating_add(1);
+	}
+
+	/** Marks the end of the current frame, forgetting about the textures for
+	 * which the given function returns `false`. */
+	pub(crate) fn mark_frame<F>(&mut self, mut alive: F)
+		where F: FnMut(&T) -> bool {
+
+		self.frame += 1;
+		if self.enabled {
+			self.textures.retain(|_, usage| alive(&usage.handle));
+		}
+	}
+
+	/** Puts together a report of the usage of every texture whose label and
+	 * size the given function can resolve, from the one that has gone for the
+	 * longest without being bound to the most recently bound one. */
+	pub(crate) fn report<F>(&self, mut resolve: F) -> Vec<TextureUsage>
+		where F: FnMut(&T) -> Option<(Option<String>, u64)> {
+
+		let last_frame = self.frame.checked_sub(1);
+		let mut report = self.textures.iter()
+			.filter_map(|(id, usage)| {
+				let (label, bytes) = resolve(&usage.handle)?;
+				let binds_last_frame = match last_frame {
+					Some(last) if usage.last_used_frame == last => usage.binds,
+					Some(last) if usage.previous.0 == last => usage.previous.1,
+					_ => 0
+				};
+
+				Some(TextureUsage {
+					id: *id,
+					label,
+					bytes,
+					last_used_frame: usage.last_used_frame,
+					binds_last_frame
+				})
+			})
+			.collect::<Vec<_>>();
+
+		/* Among the ones that are just as stale, larger textures come first,
+		 * as those are the ones worth evicting. */
+		report.sort_unstable_by(|a, b| a.last_used_frame.cmp(&b.last_used_frame)
+			.then(b.bytes.cmp(&a.bytes))
+			.then(a.id.cmp(&b.id)));
+		report
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn report(tracker: &UsageTracker<u64>) -> Vec<(u64, u64, u32)> {
+		tracker.report(|bytes| Some((None, *bytes)))
+			.into_iter()
+			.map(|usage| (usage.id, usage.last_used_frame, usage.binds_last_frame))
+			.collect()
+	}
+
+	#[test]
+	fn nothing_gets_recorded_when_turned_off() {
+		let mut tracker = UsageTracker::new();
+		tracker.record(0, || 16);
+		tracker.mark_frame(|_| true);
+		assert!(report(&tracker).is_empty());
+	}
+
+	#[test]
+	fn stalest_textures_come_first() {
+		let mut tracker = UsageTracker::new();
+		tracker.set_enabled(true);
+
+		/* Frame 0. */
+		tracker.record(0, || 16);
+		tracker.record(1, || 16);
+		tracker.record(2, || 16);
+		tracker.record(3, || 64);
+		tracker.mark_frame(|_| true);
+
+		/* Frame 1. */
+		tracker.record(1, || 16);
+		tracker.record(1, || 16);
+		tracker.mark_frame(|_| true);
+
+		/* Frame 2. */
+		tracker.record(1, || 16);
+		tracker.record(0, || 16);
+		tracker.mark_frame(|_| true);
+
+		/* Frame 3. Textures 2 and 3 are just as stale, but 3 is larger. */
+		tracker.record(1, || 16);
+		assert_eq!(report(&tracker), vec![
+			(3, 0, 0),
+			(2, 0, 0),
+			(0, 2, 1),
+			(1, 3, 1),
+		]);
+	}
+
+	#[test]
+	fn dead_textures_get_forgotten() {
+		let mut tracker = UsageTracker::new();
+		tracker.set_enabled(true);
+		tracker.record(0, || 16);
+		tracker.record(1, || 32);
+		tracker.mark_frame(|bytes| *bytes != 32);
+
+		assert_eq!(report(&tracker), vec![(0, 0, 1)]);
+	}
+}
@@ -0,0 +1,268 @@
+use std::rc::{Rc, Weak};
+use std::cell::RefCell;
+use glow::{Context, HasContext};
+use crate::buffer::InnerBuffer;
+
+/** Largest number of vertex arrays kept around by a device, for all of its
+ * pipelines. */
+pub(crate) const VERTEX_ARRAY_CACHE_SIZE: usize = 64;
+
+/** How a vertex array points a single attribute into one of its buffers. */
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub(crate) struct AttributePointer {
+	/** Location of the attribute. */
+	pub(crate) location: u32,
+	/** OpenGL type of the components of the attribute. */
+	pub(crate) kind: u32,
+	/** Number of components in the attribute. */
+	pub(crate) components: i32,
+	/** Whether the attribute is read as an integer, rather than as a float. */
+	pub(crate) integer: bool,
+	/** Whether fixed point values get normalized as they are read. */
+	pub(crate) normalized: bool,
+	/** Distance between consecutive elements in the buffer, in bytes. */
+	pub(crate) stride: i32,
+	/** Offset of the attribute from the start of an element, in bytes. */
+	pub(crate) offset: i32,
+	/** Whether the attribute is read from the instance buffer, once per
+	 * instance, rather than from the vertex buffer, once per vertex. */
+	pub(crate) instanced: bool,
+}
+
+/** Attributes and buffers a vertex array points to.
+ *
+ * Vertex arrays don't depend on the program they're used with, only on where
+ * their attributes are read from, so pipelines whose attributes end up with the
+ * same pointers share them. Buffers are held weakly, which keeps their
+ * allocations from being reused for other buffers for as long as the key is
+ * around, so two keys are only ever equal when they refer to the very same
+ * buffers. */
+#[derive(Debug)]
+pub(crate) struct VertexArrayKey<B> {
+	/** Attributes set up in the vertex array. */
+	pointers: Rc<[AttributePointer]>,
+	/** Buffer the per-vertex attributes are read from, if any. */
+	vertex: Option<Weak<B>>,
+	/** Buffer the per-instance attributes are read from, if any. */
+	instance: Option<Weak<B>>,
+}
+impl<B> VertexArrayKey<B> {
+	/** Key for the given attributes, read from the given vertex and instance
+	 * buffers. */
+	pub(crate) fn new(
+		pointers: Rc<[AttributePointer]>,
+		vertex: Option<Weak<B>>,
+		instance: Option<Weak<B>>) -> Self {
+
+		Self { pointers, vertex, instance }
+	}
+
+	/** Whether any of the buffers in this key has been dropped, in which case
+	 * the vertex array it is for can never be used again. */
+	fn is_stale(&self) -> bool {
+		let dropped = |buffer: &Option<Weak<B>>| buffer.as_ref()
+			.map(|buffer| buffer.strong_count() == 0)
+			.unwrap_or(false);
+		dropped(&self.vertex) || dropped(&self.instance)
+	}
+}
+impl<B> PartialEq for VertexArrayKey<B> {
+	fn eq(&self, other: &Self) -> bool {
+		let same = |a: &Option<Weak<B>>, b: &Option<Weak<B>>| match (a, b) {
+			(Some(a), Some(b)) => Weak::ptr_eq(a, b),
+			(None, None) => true,
+			_ => false
+		};
+		let pointers = Rc::ptr_eq(&self.pointers, &other.pointers)
+			|| self.pointers == other.pointers;
+		pointers
+			&& same(&self.vertex, &other.vertex)
+			&& same(&self.instance, &other.instance)
+	}
+}
+
+/** Vertex arrays, already set up for the attributes and buffers they were
+ * created with, so that switching between buffers, or between pipelines with
+ * the same attributes, is a matter of binding another vertex array, rather
+ * than of specifying every attribute again.
+ *
+ * This holds at most [`VERTEX_ARRAY_CACHE_SIZE`] vertex arrays, evicting the
+ * least recently used ones first. Vertex arrays whose buffers get dropped are
+ * evicted the next time the cache gets looked up. Evicted vertex arrays are
+ * handed back to the caller, which is the one that has to delete them. */
+#[derive(Debug)]
+pub(crate) struct VertexArrayCache<V, B> {
+	/** Cached vertex arrays, from the least to the most recently used. */
+	entries: RefCell<Vec<(VertexArrayKey<B>, V)>>,
+}
+impl<V: Copy, B> VertexArrayCache<V, B> {
+	/** Creates a new empty cache. */
+	pub(crate) fn new() -> Self {
+		Self { entries: RefCell::new(Vec::new()) }
+	}
+
+	/** Looks up the vertex array for the given key, marking it as the most
+	 * recently used one, and evicting every vertex array whose buffers have
+	 * been dropped into the given list. */
+	pub(crate) fn get(&self, key: &VertexArrayKey<B>, evicted: &mut Vec<V>) -> Option<V> {
+		let mut entries = self.entries.borrow_mut();
+		entries.retain(|(key, vao)| if key.is_stale() {
+			evicted.push(*vao);
+			false
+		} else {
+			true
+		});
+
+		let index = entries.iter().position(|(other, _)| other == key)?;
+		let entry = entries.remove(index);
+		let vao = entry.1;
+		entries.push(entry);
+
+		Some(vao)
+	}
+
+	/** Inserts the vertex array for the given key as the most recently used
+	 * one, returning the least recently used one if it had to be evicted to
+	 * make room for it. */
+	pub(crate) fn insert(&self, key: VertexArrayKey<B>, vao: V) -> Option<V> {
+		let mut entries = self.entries.borrow_mut();
+		let evicted = if entries.len() >= VERTEX_ARRAY_CACHE_SIZE {
+			Some(entries.remove(0).1)
+		} else {
+			None
+		};
+		entries.push((key, vao));
+
+		evicted
+	}
+
+	/** Removes every vertex array from the cache, handing them back. */
+	pub(crate) fn drain(&self) -> Vec<V> {
+		self.entries.borrow_mut()
+			.drain(..)
+			.map(|(_, vao)| vao)
+			.collect()
+	}
+}
+
+/** The vertex array cache of a device, shared by all of its pipelines, which
+ * deletes the vertex arrays left in it once the device and every one of its
+ * pipelines are gone. */
+#[derive(Debug)]
+pub(crate) struct SharedVertexArrays {
+	/** Handle to the shared context. */
+	context: Rc<Context>,
+	/** The cached vertex arrays. */
+	pub(crate) cache: VertexArrayCache<<Context as HasContext>::VertexArray, InnerBuffer>,
+}
+impl SharedVertexArrays {
+	/** Creates a new empty cache for vertex arrays of the given context. */
+	pub(crate) fn new(context: Rc<Context>) -> Self {
+		Self {
+			context,
+			cache: VertexArrayCache::new()
+		}
+	}
+}
+impl Drop for SharedVertexArrays {
+	fn drop(&mut self) {
+		for vao in self.cache.drain() {
+			unsafe { self.context.delete_vertex_array(vao) }
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn pointers(location: u32) -> Rc<[AttributePointer]> {
+		Rc::from(vec![AttributePointer {
+			location,
+			kind: glow::FLOAT,
+			components: 3,
+			integer: false,
+			normalized: false,
+			stride: 12,
+			offset: 0,
+			instanced: false
+		}])
+	}
+
+	fn key(vertex: &Rc<u32>, instance: Option<&Rc<u32>>) -> VertexArrayKey<u32> {
+		VertexArrayKey::new(
+			pointers(0),
+			Some(Rc::downgrade(vertex)),
+			instance.map(Rc::downgrade))
+	}
+
+	#[test]
+	fn switching_back_hits_the_cache() {
+		let cache = VertexArrayCache::<u32, u32>::new();
+		let (a, b) = (Rc::new(0), Rc::new(1));
+		let mut evicted = Vec::new();
+
+		assert_eq!(cache.get(&key(&a, None), &mut evicted), None);
+		assert_eq!(cache.insert(key(&a, None), 10), None);
+		assert_eq!(cache.insert(key(&b, None), 20), None);
+		assert_eq!(cache.get(&key(&a, None), &mut evicted), Some(10));
+		assert_eq!(cache.get(&key(&b, None), &mut evicted), Some(20));
+
+		/* The instance buffer is part of the key. */
+		assert_eq!(cache.get(&key(&a, Some(&b)), &mut evicted), None);
+		assert!(evicted.is_empty());
+	}
+
+	#[test]
+	fn pipelines_with_the_same_attributes_share() {
+		let cache = VertexArrayCache::<u32, u32>::new();
+		let buffer = Rc::new(0);
+		let mut evicted = Vec::new();
+
+		cache.insert(VertexArrayKey::new(pointers(0), Some(Rc::downgrade(&buffer)), None), 10);
+
+		/* Equal pointers built separately, as different pipelines do. */
+		let same = VertexArrayKey::new(pointers(0), Some(Rc::downgrade(&buffer)), None);
+		assert_eq!(cache.get(&same, &mut evicted), Some(10));
+
+		let moved = VertexArrayKey::new(pointers(1), Some(Rc::downgrade(&buffer)), None);
+		assert_eq!(cache.get(&moved, &mut evicted), None);
+	}
+
+	#[test]
+	fn least_recently_used_gets_evicted() {
+		let cache = VertexArrayCache::<usize, u32>::new();
+		let buffers = (0..=VERTEX_ARRAY_CACHE_SIZE as u32)
+			.map(Rc::new)
+			.collect::<Vec<_>>();
+		let mut evicted = Vec::new();
+
+		for (i, buffer) in buffers[..VERTEX_ARRAY_CACHE_SIZE].iter().enumerate() {
+			assert_eq!(cache.insert(key(buffer, None), i), None);
+		}
+
+		/* Using the first one makes the second one the least recent. */
+		assert_eq!(cache.get(&key(&buffers[0], None), &mut evicted), Some(0));
+		let last = VERTEX_ARRAY_CACHE_SIZE;
+		assert_eq!(cache.insert(key(&buffers[last], None), last), Some(1));
+		assert_eq!(cache.get(&key(&buffers[1], None), &mut evicted), None);
+		assert_eq!(cache.get(&key(&buffers[0], None), &mut evicted), Some(0));
+	}
+
+	#[test]
+	fn dropped_buffers_get_evicted() {
+		let cache = VertexArrayCache::<u32, u32>::new();
+		let (a, b) = (Rc::new(0), Rc::new(1));
+		let mut evicted = Vec::new();
+
+		cache.insert(key(&a, None), 10);
+		cache.insert(key(&b, Some(&a)), 20);
+		cache.insert(key(&b, None), 30);
+		drop(a);
+
+		assert_eq!(cache.get(&key(&b, None), &mut evicted), Some(30));
+		evicted.sort_unstable();
+		assert_eq!(evicted, vec![10, 20]);
+		assert_eq!(cache.drain(), vec![30]);
+	}
+}
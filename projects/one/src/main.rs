@@ -3,113 +3,98 @@ mod render;
 mod shaders;
 mod scene;
 
-use environment::Environment;
-use winit::event::{Event, WindowEvent, ElementState};
+use environment::{Environment, FixedApplication, SurfaceSize, Input};
+use winit::event::{WindowEvent, VirtualKeyCode};
 use winit::event_loop::ControlFlow;
-use gavle::{Viewport, FramebufferDescriptor, DefaultFramebufferDescriptor, LoadOp, Color};
-use winit::dpi::PhysicalSize;
+use gavle::{Device, Framebuffer, DefaultFramebufferDescriptor, LoadOp, Color};
+use std::time::Duration;
 use crate::scene::Scene;
 use crate::render::Renderer;
 
-/** Function responsible for running the game inside of a given application
- * environment, provided by the [`environment`] crate. */
-pub fn run(env: Environment) {
-	let Environment {
-		window,
-		event_loop,
-		device,
-		mut swap_buffers,
-		mut delta_time
-	} = env;
-
-	let mut viewport = Viewport {
-		x: 0,
-		y: 0,
-		width: 800,
-		height: 600
-	};
-	let framebuffer = device.default_framebuffer(
-		&DefaultFramebufferDescriptor {
-			color_load_op: LoadOp::Clear(Color {
-				red: 0.0,
-				green: 0.0,
-				blue: 0.0,
-				alpha: 1.0
-			}),
-			depth_load_op: LoadOp::Clear(f32::INFINITY),
-			stencil_load_op: LoadOp::Clear(0)
-		});
-
-	let mut scene = Scene::new(800.0 / 600.0);
-	let mut renderer = Renderer::new(&device);
-
-	let _ = (delta_time)();
-
-	let mut direction = 0.0f32;
-	let mut angle = std::f32::consts::FRAC_PI_4;
-
-	event_loop.run(move |event, _, flow| {
-		let mut pass = false;
-		match event {
-			Event::WindowEvent { window_id, event }
-				if window_id == window.id() => {
-
-				match event {
-					WindowEvent::CloseRequested => *flow = ControlFlow::Exit,
-					WindowEvent::Resized(size) => {
-						let PhysicalSize { width, height } = size;
-
-						viewport.width = width;
-						viewport.height = height;
+/** The game, which gets run inside of a given application environment,
+ * provided by the [`environment`] crate, with its scene being updated in
+ * fixed steps of [`One::TICK`]. */
+pub struct One {
+	device: Device,
+	framebuffer: Framebuffer,
+	surface_size: SurfaceSize,
+	scene: Scene,
+	renderer: Renderer,
+}
+impl One {
+	/** Duration of a single update of the scene. */
+	pub const TICK: Duration = Duration::from_millis(16);
+
+	/** Sets the game up inside of the given environment. */
+	pub fn new(env: &Environment) -> Self {
+		let device = env.device.clone();
+		let framebuffer = device.default_framebuffer(
+			&DefaultFramebufferDescriptor {
+				color_load_op: LoadOp::Clear(Color {
+					red: 0.0,
+					green: 0.0,
+					blue: 0.0,
+					alpha: 1.0
+				}),
+				depth_load_op: LoadOp::Clear(f32::INFINITY),
+				stencil_load_op: LoadOp::Clear(0)
+			});
+
+		let surface_size = env.surface_size.clone();
+		let scene = Scene::new(surface_size.aspect_ratio());
+
+		/* Every pipeline and group gets created along with the renderer, so it's
+		 * the only thing we need to record in order to look for bindings that
+		 * don't meet up with each other. */
+		device.record_interfaces(cfg!(debug_assertions));
+		let renderer = Renderer::new(&device);
+		device.record_interfaces(false);
+		for warning in device.interface_registry().check() {
+			log::warn!(target: "one::render", "{}", warning);
+		}
 
-						let aspect = f64::from(width) / f64::from(height);
-						scene.aspect = aspect as f32;
-					},
-					WindowEvent::KeyboardInput { input, .. } => {
-						let (button, state) = (input.scancode, input.state);
+		Self {
+			device,
+			framebuffer,
+			surface_size,
+			scene,
+			renderer
+		}
+	}
+}
+impl FixedApplication for One {
+	fn event(&mut self, event: &WindowEvent, _: &mut ControlFlow) {
+		if let WindowEvent::Resized(_) = event {
+			self.scene.aspect = self.surface_size.aspect_ratio();
+		}
+	}
 
-						match (button, state) {
-							(57419, ElementState::Pressed)                      => direction = 1.0,
-							(57419, ElementState::Released) if direction >= 0.0 => direction = 0.0,
-							(57421, ElementState::Pressed)                      => direction = -1.0,
-							(57421, ElementState::Released) if direction <= 0.0 => direction = 0.0,
-							_ => {}
-						}
-					},
-					_ => {}
-				}
-			},
-			Event::MainEventsCleared => pass = true,
-			_ => {},
+	fn update(&mut self, tick: Duration, input: &Input) {
+		/* F3 cycles through the debug views. */
+		if input.key_pressed(VirtualKeyCode::F3) {
+			self.renderer.cycle_debug_view();
+		}
+		/* M cycles through the multisampling settings. */
+		if input.key_pressed(VirtualKeyCode::M) {
+			self.renderer.cycle_msaa(&self.device);
 		}
-		if !pass { return }
 
-		let delta = (delta_time)();
+		let direction = input.axis(VirtualKeyCode::Right, VirtualKeyCode::Left);
 		if direction != 0.0 {
-			angle += std::f32::consts::FRAC_PI_8 * delta.as_secs_f32() * direction.signum();
-			angle = angle.clamp(0.0, std::f32::consts::PI);
+			/* Scrub through the day at three hours per second. */
+			let hours = 3.0 * tick.as_secs_f32() * direction;
+			self.scene.time_of_day.advance(hours * 3600.0);
 		}
+		self.scene.update(tick);
+	}
 
-		scene.light_position[0] = angle.cos() * 2.0;
-		scene.light_position[1] = angle.sin() * 2.0;
-		let _ = {
-			let t = angle.sin();
-			let t = t.clamp(0.0, 1.0);
-
-			scene.light_color[0] = t * 0.486 + (1.0 - t) * 0.957;
-			scene.light_color[1] = 0.792;
-			scene.light_color[2] = t * 0.957 + (1.0 - t) * 0.486;
-		};
-		scene.update(delta);
-
-		renderer.update(&scene);
-		renderer.draw(&device, &framebuffer, viewport);
-
-		(swap_buffers)();
-	});
+	fn render(&mut self, _: f32) {
+		self.renderer.update(&self.device, &self.scene);
+		self.renderer.draw(&self.device, &self.framebuffer, self.surface_size.viewport());
+	}
 }
 
 
 
 /* Generate the main function. */
-environment::main!(run);
\ No newline at end of file
+environment::main!(One::new, fixed = One::TICK);
\ No newline at end of file
@@ -1,92 +1,335 @@
 use gavle::*;
-use support::{Vertex, Matrix4, Camera, Projection};
+use support::{Vertex, MeshIndex, Matrix4, Camera, Projection, SkyUniforms, SKY_BINDING, UniformArena, UniformSlice, DebugView, StencilView, MsaaTarget, MsaaTargetDescriptor};
 use std::convert::TryFrom;
 use crate::scene::Scene;
 use std::hint::unreachable_unchecked;
+use log::warn;
 
 pub struct Renderer {
+	sky: Sky,
 	mountains: Mountains,
 	snowfall: Snowfall,
 	backwall: Backwall,
 	waterfall: Waterfall,
 	uniforms: Uniforms,
+	debug: DebugView,
+	stencil_view: StencilView,
+	offscreen: Option<Offscreen>,
+	msaa: Option<MsaaTarget>,
+	samples: u32,
 }
 impl Renderer {
 	pub fn new(device: &Device) -> Self {
+		let mut stencil_view = StencilView::new(device).unwrap();
+		/* The mountains are the only thing writing to the stencil buffer, and
+		 * all they write are ones. */
+		stencil_view.set_range(1);
+
 		Self {
+			sky: Sky::new(device),
 			mountains: Mountains::new(device),
 			snowfall: Snowfall::new(device),
 			backwall: Backwall::new(device),
 			waterfall: Waterfall::new(device),
 			uniforms: Uniforms::new(device),
+			debug: DebugView::Off,
+			stencil_view,
+			offscreen: None,
+			msaa: None,
+			samples: 1,
+		}
+	}
+
+	/** Switches over to the next multisampling setting, going from off to
+	 * 2x, 4x and 8x, and back. The setting gets clamped to what the context
+	 * supports. */
+	pub fn cycle_msaa(&mut self, device: &Device) {
+		let next = match self.samples {
+			1 => 2,
+			2 => 4,
+			4 => 8,
+			_ => 1
+		};
+		/* Settings the context can't give us wrap back around to off. */
+		let max = device.information().limits.max_samples;
+		self.samples = if next > max { 1 } else { next };
+		log::info!(target: "one::render", "multisampling set to {}x", self.samples);
+	}
+
+	/** Switches over to the next debug view. */
+	pub fn cycle_debug_view(&mut self) {
+		self.debug = self.debug.next();
+		if self.debug == DebugView::Off {
+			self.offscreen = None;
+		}
+	}
+
+	pub fn update(&mut self, device: &Device, scene: &Scene) {
+		self.snowfall.upload(device, scene.snowflakes.entities.entities()
+			.map(|snowflake| [
+				snowflake.position[0],
+				snowflake.position[1],
+				1.2,
+			]));
+		self.uniforms.update(
+			&Globals::new(
+				scene.light_position,
+				scene.light_color,
+				[0.486, 0.792, 0.957],
+				scene.camera,
+				scene.aspect),
+			&scene.time_of_day.uniforms());
+	}
+
+	pub fn draw(&mut self, device: &Device, target: &Framebuffer, viewport: Viewport) {
+		match self.debug {
+			DebugView::Off => self.draw_multisampled(device, target, viewport),
+			DebugView::ShowStencil => self.draw_stencil(device, target, viewport)
 		}
 	}
 
-	pub fn update(&mut self, scene: &Scene) {
-		let mut iter = scene.snowflakes.entities.entities();
-		self.uniforms.snowflakes
-			.resize_with(
-				scene.snowflakes.entities.len() as u32,
-				|| {
-					let snowflake = iter.next().unwrap();
-					Instance::new(
-						[
-							snowflake.position[0],
-							snowflake.position[1],
-							1.2,
-						],
-						[1.0, 1.0])
-				});
-		self.uniforms.global
-			.resize_with(
-				1,
-				|| Globals::new(
-					scene.light_position,
-					scene.light_color,
-					[0.486, 0.792, 0.957],
-					scene.camera,
-					scene.aspect
-				));
+	/** Draws the scene into the multisampled target, and blits it over to
+	 * the given framebuffer once it has been resolved. This is the same for
+	 * any number of samples, with the target taking care of the difference. */
+	fn draw_multisampled(&mut self, device: &Device, target: &Framebuffer, viewport: Viewport) {
+		let (width, height) = (viewport.width, viewport.height);
+		if width == 0 || height == 0 { return }
+
+		/* The target is taken out for the duration of the frame, so that the
+		 * framebuffer it lends us doesn't keep the rest of us borrowed. */
+		let mut msaa = match self.msaa.take() {
+			Some(mut msaa) => {
+				msaa.resize(width, height);
+				msaa.set_samples(device, self.samples);
+				msaa
+			},
+			None => MsaaTarget::new(device, &MsaaTargetDescriptor {
+				width,
+				height,
+				samples: self.samples,
+				color_load_op: LoadOp::Clear(Color {
+					red: 0.0,
+					green: 0.0,
+					blue: 0.0,
+					alpha: 1.0
+				}),
+				depth_load_op: LoadOp::Clear(f32::INFINITY),
+				stencil_load_op: LoadOp::Clear(0)
+			}).unwrap()
+		};
+
+		let rect = Viewport { x: 0, y: 0, width, height };
+		match msaa.begin(device) {
+			Ok(framebuffer) => self.draw_scene(device, framebuffer, rect),
+			Err(what) => warn!("Could not prepare the multisampled target: {}", what)
+		}
+		let result = msaa.resolve(device).and_then(|_| device.blit_framebuffer(
+			msaa.resolved(),
+			target,
+			rect,
+			viewport,
+			TextureFilter::Nearest).map_err(Into::into));
+		if let Err(what) = result {
+			warn!("Could not resolve the multisampled target: {}", what);
+		}
+
+		self.msaa = Some(msaa);
 	}
 
-	pub fn draw(&self, device: &Device, target: &Framebuffer, viewport: Viewport) {
+	/** Draws the scene offscreen, where its stencil buffer can be got at, and
+	 * shows the stencil buffer in its place. */
+	fn draw_stencil(&mut self, device: &Device, target: &Framebuffer, viewport: Viewport) {
+		let (width, height) = (viewport.width, viewport.height);
+		if width == 0 || height == 0 { return }
+
+		let stale = match &self.offscreen {
+			Some(offscreen) => (offscreen.width, offscreen.height) != (width, height),
+			None => true
+		};
+		if stale {
+			self.offscreen = Some(Offscreen::new(device, width, height));
+		}
+		let offscreen = self.offscreen.as_ref().unwrap();
+
+		self.draw_scene(device, &offscreen.framebuffer, Viewport {
+			x: 0,
+			y: 0,
+			width,
+			height
+		});
+		if let Err(what) = self.stencil_view.prepare(
+			device,
+			&offscreen.framebuffer,
+			width,
+			height) {
+
+			warn!("Could not show the stencil buffer: {}", what);
+			return
+		}
+		self.stencil_view.draw(device, target, viewport);
+	}
+
+	fn draw_scene(&self, device: &Device, target: &Framebuffer, viewport: Viewport) {
 		let mut pass = device.start_render_pass(
 			&RenderPassDescriptor {
-				pipeline: &self.snowfall.pipeline,
+				pipeline: &self.sky.pipeline,
 				framebuffer: target
 			});
 
-		pass.set_viewport(viewport);
+		pass.set_viewport(viewport)
+			.expect("the viewport must fit in the limits of the context");
 		pass.set_stencil_reference(1);
-		pass.set_bind_group(&self.uniforms.group);
+		pass.set_bind_group_with_offsets(&self.uniforms.group, &self.uniforms.offsets())
+			.expect("the slices of the arena must be aligned for dynamic offsets");
+
+		/* Render the sky behind everything else. */
+		pass.set_vertex_buffer(&self.sky.geometry.0);
+		pass.set_index_buffer(&self.sky.geometry.1);
+
+		pass.draw_indexed(0..6, 1)
+			.expect("the geometry must hold the indices being drawn");
 
 		/* Render the snow. */
 		pass.set_pipeline(&self.snowfall.pipeline);
 		pass.set_vertex_buffer(&self.snowfall.geometry.0);
 		pass.set_index_buffer(&self.snowfall.geometry.1);
+		pass.set_instance_buffer(&self.snowfall.instances);
 
-		pass.draw_indexed(0..3, self.uniforms.snowflakes.len());
+		pass.draw_indexed(0..3, self.snowfall.count)
+			.expect("the geometry must hold the indices being drawn");
 
 		/* Render the mountains. */
 		pass.set_pipeline(&self.mountains.pipeline);
 		pass.set_vertex_buffer(&self.mountains.geometry.0);
 		pass.set_index_buffer(&self.mountains.geometry.1);
 
-		pass.draw_indexed(0..27, self.uniforms.mountains.len());
+		pass.draw_indexed(0..27, self.uniforms.mountains.len() as u32)
+			.expect("the geometry must hold the indices being drawn");
 
 		/* Render the backwall. */
 		pass.set_pipeline(&self.backwall.pipeline);
 		pass.set_vertex_buffer(&self.backwall.geometry.0);
 		pass.set_index_buffer(&self.backwall.geometry.1);
 
-		pass.draw_indexed(0..27, self.uniforms.backwalls.len());
+		pass.draw_indexed(0..27, self.uniforms.backwalls.len() as u32)
+			.expect("the geometry must hold the indices being drawn");
 
 		/* Render the waterfall. */
 		pass.set_pipeline(&self.waterfall.pipeline);
 		pass.set_vertex_buffer(&self.waterfall.geometry.0);
 		pass.set_index_buffer(&self.waterfall.geometry.1);
 
-		pass.draw_indexed(0..27, self.uniforms.waterfalls.len());
+		pass.draw_indexed(0..27, self.uniforms.waterfalls.len() as u32)
+			.expect("the geometry must hold the indices being drawn");
+	}
+}
+
+/** Framebuffer the scene gets drawn into when its stencil buffer has to be
+ * shown, as the one of the default framebuffer can't be got at. */
+struct Offscreen {
+	framebuffer: Framebuffer,
+	width: u32,
+	height: u32,
+}
+impl Offscreen {
+	pub fn new(device: &Device, width: u32, height: u32) -> Self {
+		let texture = |format| device.create_texture(
+			&TextureDescriptor {
+				extent: TextureExtent::D2 { width, height },
+				format,
+				mip: Mipmap::None,
+				swizzle: None
+			}).unwrap();
+		let color = texture(TextureFormat::Rgba8Unorm);
+		let depth_stencil = texture(TextureFormat::Depth24Stencil8);
+
+		let framebuffer = device.create_framebuffer(
+			&FramebufferDescriptor {
+				color_attachments: &[FramebufferColorAttachmentDescriptor {
+					attachment: &color,
+					layer: 0,
+					mip_level: 0,
+					load_op: LoadOp::Clear(Color {
+						red: 0.0,
+						green: 0.0,
+						blue: 0.0,
+						alpha: 1.0
+					})
+				}],
+				depth_stencil_attachment: Some(FramebufferDepthStencilAttachmentDescriptor {
+					attachment: &depth_stencil,
+					layer: 0,
+					mip_level: 0,
+					depth_load_op: LoadOp::Clear(f32::INFINITY),
+					stencil_load_op: LoadOp::Clear(0)
+				})
+			}).unwrap();
+		framebuffer.set_label("offscreen scene");
+
+		Self {
+			framebuffer,
+			width,
+			height
+		}
+	}
+}
+
+/** Gradient sky covering the whole screen, shaded for the time of day. */
+pub struct Sky {
+	pipeline: RenderPipeline,
+	geometry: (VertexBuffer, IndexBuffer),
+}
+impl Sky {
+	pub fn new(device: &Device) -> Self {
+		/* A single quad covering the whole screen, in clip space. */
+		const GEOMETRY: &'static [Vertex] = &[
+			Vertex::new_unchecked([-1.0, -1.0, 0.0], [0.0, 0.0], [0.0, 0.0, -1.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+			Vertex::new_unchecked([ 1.0, -1.0, 0.0], [1.0, 0.0], [0.0, 0.0, -1.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+			Vertex::new_unchecked([-1.0,  1.0, 0.0], [0.0, 1.0], [0.0, 0.0, -1.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+			Vertex::new_unchecked([ 1.0,  1.0, 0.0], [1.0, 1.0], [0.0, 0.0, -1.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+		];
+		const INDICES: &'static [u16] = &[0, 1, 3, 3, 2, 0];
+		let geometry = upload_geometry(device, GEOMETRY, INDICES);
+
+		use crate::shaders::sky as shaders;
+		let vertex_shader = device.create_vertex_shader(shaders::VERTEX)
+			.unwrap();
+		let fragment_shader = device.create_fragment_shader(shaders::FRAGMENT)
+			.unwrap();
+
+		/* The sky is drawn before anything else, so it neither needs to be
+		 * tested against the depth buffer nor should it ever write to it. */
+		let pipeline = device.create_render_pipeline(
+			&RenderPipelineDescriptor {
+				vertex: VertexState {
+					shader: &vertex_shader,
+					buffer: &Vertex::LAYOUT,
+					instance: None
+				},
+				primitive_state: PrimitiveState {
+					topology: PrimitiveTopology::TriangleList,
+					index_format: IndexFormat::Uint16,
+					front_face: FrontFace::Ccw,
+					cull_mode: CullMode::None,
+					polygon_mode: PolygonMode::Fill,
+					line_width: 1.0,
+					program_point_size: false
+				},
+				fragment: Some(FragmentState {
+					shader: &fragment_shader,
+					targets: &[ColorTargetState {
+						alpha_blend: BlendState::REPLACE,
+						color_blend: BlendState::REPLACE,
+						write_mask: ColorWrite::ALL
+					}]
+				}),
+				depth_stencil: Some(DepthStencilState::new(
+					false,
+					CompareFunction::Always,
+					StencilState::IGNORE))
+			}).unwrap();
+
+		Self { pipeline, geometry }
 	}
 }
 
@@ -137,28 +380,30 @@ impl Waterfall {
 			&RenderPipelineDescriptor {
 				vertex: VertexState {
 					shader: &vertex_shader,
-					buffer: &Vertex::LAYOUT
+					buffer: &Vertex::LAYOUT,
+					instance: None
 				},
 				primitive_state: PrimitiveState {
 					topology: PrimitiveTopology::TriangleList,
 					index_format: IndexFormat::Uint16,
 					front_face: FrontFace::Ccw,
 					cull_mode: CullMode::None,
-					polygon_mode: PolygonMode::Fill
+					polygon_mode: PolygonMode::Fill,
+					line_width: 1.0,
+					program_point_size: false
 				},
 				fragment: Some(FragmentState {
 					shader: &fragment_shader,
-					targets: ColorTargetState {
+					targets: &[ColorTargetState {
 						alpha_blend: BlendState::REPLACE,
 						color_blend: BlendState::REPLACE,
 						write_mask: ColorWrite::ALL
-					}
+					}]
 				}),
-				depth_stencil: Some(DepthStencilState {
-					depth_write_enabled: true,
-					depth_compare: CompareFunction::Less,
-					stencil: StencilState::IGNORE
-				})
+				depth_stencil: Some(DepthStencilState::new(
+					true,
+					CompareFunction::Less,
+					StencilState::IGNORE))
 			}).unwrap();
 
 		Self { pipeline, geometry }
@@ -210,28 +455,30 @@ impl Backwall {
 			&RenderPipelineDescriptor {
 				vertex: VertexState {
 					shader: &vertex_shader,
-					buffer: &Vertex::LAYOUT
+					buffer: &Vertex::LAYOUT,
+					instance: None
 				},
 				primitive_state: PrimitiveState {
 					topology: PrimitiveTopology::TriangleList,
 					index_format: IndexFormat::Uint16,
 					front_face: FrontFace::Ccw,
 					cull_mode: CullMode::Back,
-					polygon_mode: PolygonMode::Fill
+					polygon_mode: PolygonMode::Fill,
+					line_width: 1.0,
+					program_point_size: false
 				},
 				fragment: Some(FragmentState {
 					shader: &fragment_shader,
-					targets: ColorTargetState {
+					targets: &[ColorTargetState {
 						alpha_blend: BlendState::REPLACE,
 						color_blend: BlendState::REPLACE,
 						write_mask: ColorWrite::ALL
-					}
+					}]
 				}),
-				depth_stencil: Some(DepthStencilState {
-					depth_write_enabled: true,
-					depth_compare: CompareFunction::Less,
-					stencil: StencilState::IGNORE
-				})
+				depth_stencil: Some(DepthStencilState::new(
+					true,
+					CompareFunction::Less,
+					StencilState::IGNORE))
 			}).unwrap();
 
 		Self { pipeline, geometry }
@@ -241,8 +488,16 @@ impl Backwall {
 pub struct Snowfall {
 	pipeline: RenderPipeline,
 	geometry: (VertexBuffer, IndexBuffer),
+	instances: VertexBuffer,
+	/** Number of snowflakes the instance buffer has room for. */
+	capacity: u32,
+	count: u32,
 }
 impl Snowfall {
+	/** Number of snowflakes the instance buffer starts out with room for. It
+	 * grows whenever there are more of them than that. */
+	const INITIAL_SNOWFLAKES: u32 = 4096;
+
 	pub fn new(device: &Device) -> Self {
 		/* Specify the geometry of the particles and upload it. */
 		const GEOMETRY: &'static [Vertex] = &[
@@ -263,34 +518,126 @@ impl Snowfall {
 			&RenderPipelineDescriptor {
 				vertex: VertexState {
 					shader: &vertex_shader,
-					buffer: &Vertex::LAYOUT
+					buffer: &Vertex::LAYOUT,
+					instance: Some(&SnowflakeInstance::LAYOUT)
 				},
 				primitive_state: PrimitiveState {
 					topology: PrimitiveTopology::TriangleList,
 					index_format: IndexFormat::Uint16,
 					front_face: FrontFace::Ccw,
 					cull_mode: CullMode::Back,
-					polygon_mode: PolygonMode::Fill
+					polygon_mode: PolygonMode::Fill,
+					line_width: 1.0,
+					program_point_size: false
 				},
 				fragment: Some(FragmentState {
 					shader: &fragment_shader,
-					targets: ColorTargetState {
+					targets: &[ColorTargetState {
 						alpha_blend: BlendState::REPLACE,
 						color_blend: BlendState::REPLACE,
 						write_mask: ColorWrite::ALL
-					}
+					}]
 				}),
-				depth_stencil: Some(DepthStencilState {
-					depth_write_enabled: true,
-					depth_compare: CompareFunction::Less,
-					stencil: StencilState::IGNORE
-				})
+				depth_stencil: Some(DepthStencilState::new(
+					true,
+					CompareFunction::Less,
+					StencilState::IGNORE))
 			}).unwrap();
 
-		Self { pipeline, geometry }
+		/* Each snowflake only needs its own position, which is read straight
+		 * from the instance buffer, so there's no limit on how many of them
+		 * there can be other than the size of the buffer. */
+		let instances = Self::create_instances(device, Self::INITIAL_SNOWFLAKES)
+			.unwrap();
+
+		Self {
+			pipeline,
+			geometry,
+			instances,
+			capacity: Self::INITIAL_SNOWFLAKES,
+			count: 0
+		}
+	}
+
+	/** Creates an instance buffer with room for the given number of
+	 * snowflakes. */
+	fn create_instances(device: &Device, capacity: u32) -> Result<VertexBuffer, BufferError> {
+		let instance_size = u32::try_from(std::mem::size_of::<SnowflakeInstance>())
+			.expect("The size of a snowflake instance does not fit into an \
+				unsigned 32-bit integer.");
+		let size = instance_size.checked_mul(capacity)
+			.ok_or(BufferError::TooLarge {
+				computed_bytes: u64::from(instance_size) * u64::from(capacity)
+			})?;
+
+		device.create_vertex_buffer(
+			&BufferDescriptor {
+				size,
+				profile: BufferProfile::DynamicUpload,
+				contents: BufferContents::F32,
+				..Default::default()
+			})
+	}
+
+	/** Repopulates the instance buffer with the given snowflake positions,
+	 * growing it to twice its size, or more, when they don't fit in it. */
+	pub fn upload(&mut self, device: &Device, positions: impl Iterator<Item = [f32; 3]>) {
+		let mut instances = positions
+			.map(|position| SnowflakeInstance { position })
+			.collect::<Vec<_>>();
+
+		if instances.len() > self.capacity as usize {
+			let grown = u32::try_from(instances.len()).ok()
+				.and_then(|len| len.checked_next_power_of_two())
+				.ok_or_else(|| format!("{} instances don't fit in a buffer",
+					instances.len()))
+				.and_then(|capacity| Self::create_instances(device, capacity)
+					.map(|buffer| (buffer, capacity))
+					.map_err(|what| what.to_string()));
+
+			match grown {
+				Ok((buffer, capacity)) => {
+					self.instances = buffer;
+					self.capacity = capacity;
+				},
+				Err(what) => {
+					gavle::rate_limited!(warn!(target: "one::render",
+						"Clipping the number of snowflakes from the requested \
+						{} to the {} there is room for, as the instance buffer \
+						could not be grown: {}",
+						instances.len(), self.capacity, what));
+					instances.truncate(self.capacity as usize);
+				}
+			}
+		}
+
+		self.instances.write(0, bytemuck::cast_slice(&instances[..]))
+			.unwrap();
+		self.count = instances.len() as u32;
 	}
 }
 
+/** Per-instance data of a single snowflake. */
+#[derive(Debug, Copy, Clone, PartialEq, bytemuck::Zeroable, bytemuck::Pod)]
+#[repr(C)]
+struct SnowflakeInstance {
+	position: [f32; 3],
+}
+impl SnowflakeInstance {
+	const LAYOUT: VertexBufferLayout<'static> = VertexBufferLayout {
+		array_stride: 12,
+		attributes: &[
+			VertexAttribute {
+				kind: VertexType::F32,
+				components: VertexComponents::Three,
+				offset: 0,
+				binding: std::borrow::Cow::Borrowed("tt_inst_position"),
+				location: None
+			},
+		]
+	};
+}
+
 pub struct Mountains {
 	pipeline: RenderPipeline,
 	geometry: (VertexBuffer, IndexBuffer),
@@ -328,35 +675,37 @@ impl Mountains {
 			&RenderPipelineDescriptor {
 				vertex: VertexState {
 					shader: &vertex_shader,
-					buffer: &Vertex::LAYOUT
+					buffer: &Vertex::LAYOUT,
+					instance: None
 				},
 				primitive_state: PrimitiveState {
 					topology: PrimitiveTopology::TriangleList,
 					index_format: IndexFormat::Uint16,
 					front_face: FrontFace::Ccw,
 					cull_mode: CullMode::Back,
-					polygon_mode: PolygonMode::Fill
+					polygon_mode: PolygonMode::Fill,
+					line_width: 1.0,
+					program_point_size: false
 				},
 				fragment: Some(FragmentState {
 					shader: &fragment_shader,
-					targets: ColorTargetState {
+					targets: &[ColorTargetState {
 						alpha_blend: BlendState::REPLACE,
 						color_blend: BlendState::REPLACE,
 						write_mask: ColorWrite::ALL
-					}
+					}]
 				}),
-				depth_stencil: Some(DepthStencilState {
-					depth_write_enabled: true,
-					depth_compare: CompareFunction::Less,
-					stencil: StencilState {
+				depth_stencil: Some(DepthStencilState::new(
+					true,
+					CompareFunction::Less,
+					StencilState {
 						write_mask: 0xff,
 						read_mask: 0xff,
 						compare: CompareFunction::Always,
 						fail_op: StencilOperation::Keep,
 						depth_fail_op: StencilOperation::Keep,
 						pass_op: StencilOperation::Replace
-					}
-				})
+					}))
 			}).unwrap();
 
 		Self {
@@ -463,258 +812,134 @@ impl Globals {
 	}
 }
 
-/** All of the uniform buffers used in this pass. */
+/** All of the uniform data used in this pass, pushed to an arena anew every
+ * frame, in the order of the entries of the group. */
 struct Uniforms {
-	global: UniformVec<Globals>,
-	mountains: UniformVec<Instance>,
-	snowflakes: UniformVec<Instance>,
-	backwalls: UniformVec<Instance>,
-	waterfalls: UniformVec<Instance>,
-
+	arena: UniformArena,
+	mountains: Vec<Instance>,
+	backwalls: Vec<Instance>,
+	waterfalls: Vec<Instance>,
+
+	/** Slices of the global, mountain, backwall, waterfall and sky data, in
+	 * that order, for the current frame. */
+	slices: [UniformSlice; 5],
 	group: UniformGroup,
 }
 impl Uniforms {
-	const MAX_SNOWFLAKES: u32 = 4096;
-
 	pub fn new(device: &Device) -> Self {
-		let global = UniformVec::with_items(
-			device,
-			1,
-			|| bytemuck::Zeroable::zeroed());
-
-		let mut instance = 0u32;
-		let mountains = UniformVec::with_items(
-			device,
-			5,
-			|| {
-				let data = Instance::new(
-					match instance {
-						0 => [-1.0, -0.1, 3.0],
-						1 => [-0.5, -0.1, 2.0],
-						2 => [ 0.0, -0.1, 3.0],
-						3 => [ 0.5, -0.1, 2.0],
-						4 => [ 1.0, -0.1, 3.0],
-						_ => unreachable!()
-					},
-					[1.0, 1.0]);
-
-				instance += 1;
-				data
-			});
-		let snowflakes = UniformVec::with_capacity(
-			device,
-			Self::MAX_SNOWFLAKES);
-		let backwalls = UniformVec::with_items(
-			device,
-			1,
-			|| {
-				Instance::new([0.0, -0.3, 1.0], [1.0, 1.0])
-			}
-		);
-		let mut instance = 0u32;
-		let waterfalls = UniformVec::with_items(
-			device,
-			2,
-			|| {
-				let data = Instance::new(
-					match instance {
-						0 => [ 0.45, -0.3, 1.0],
-						1 => [-0.45, -0.3, 1.0],
-						_ => unreachable!(),
-					}, [1.0, 1.0]);
-				instance += 1;
-				data
-			}
-		);
+		let mut arena = UniformArena::new(device, 4).unwrap();
+
+		let mountains = vec![
+			Instance::new([-1.0, -0.1, 3.0], [1.0, 1.0]),
+			Instance::new([-0.5, -0.1, 2.0], [1.0, 1.0]),
+			Instance::new([ 0.0, -0.1, 3.0], [1.0, 1.0]),
+			Instance::new([ 0.5, -0.1, 2.0], [1.0, 1.0]),
+			Instance::new([ 1.0, -0.1, 3.0], [1.0, 1.0]),
+		];
+		let backwalls = vec![
+			Instance::new([0.0, -0.3, 1.0], [1.0, 1.0]),
+		];
+		let waterfalls = vec![
+			Instance::new([ 0.45, -0.3, 1.0], [1.0, 1.0]),
+			Instance::new([-0.45, -0.3, 1.0], [1.0, 1.0]),
+		];
 
+		let global: Globals = bytemuck::Zeroable::zeroed();
+		let sky: SkyUniforms = bytemuck::Zeroable::zeroed();
+		let slices = Self::push(&mut arena, &global, &mountains, &backwalls, &waterfalls, &sky);
+
+		/* Every entry is bound at the start of the arena, and gets shifted over
+		 * to the slices of the current frame when the group is set. */
+		let bindings = ["rc_global", "rc_mountains", "rc_backwalls", "rc_waterfalls", SKY_BINDING];
+		let entries = bindings.iter()
+			.zip(&slices)
+			.map(|(binding, slice)| UniformGroupEntry {
+				binding: (*binding).into(),
+				kind: arena.bind_dynamic(slice)
+			})
+			.collect::<Vec<_>>();
 		let group = device.create_uniform_bind_group(
 			&UniformGroupDescriptor {
-				entries: &[
-					UniformGroupEntry {
-						binding: "rc_global".into(),
-						kind: UniformBind::Buffer {
-							buffer: global.buffer()
-						}
-					},
-					UniformGroupEntry {
-						binding: "rc_mountains".into(),
-						kind: UniformBind::Buffer {
-							buffer: mountains.buffer()
-						}
-					},
-					UniformGroupEntry {
-						binding: "rc_snowflakes".into(),
-						kind: UniformBind::Buffer {
-							buffer: snowflakes.buffer()
-						}
-					},
-					UniformGroupEntry {
-						binding: "rc_backwalls".into(),
-						kind: UniformBind::Buffer {
-							buffer: backwalls.buffer(),
-						}
-					},
-					UniformGroupEntry {
-						binding: "rc_waterfalls".into(),
-						kind: UniformBind::Buffer {
-							buffer: waterfalls.buffer(),
-						}
-					}
-				]
-			});
+				entries: &entries[..]
+			}).unwrap();
 
 		Self {
-			global,
+			arena,
 			mountains,
-			snowflakes,
 			backwalls,
 			waterfalls,
+			slices,
 			group
 		}
 	}
-}
-
-/** Vector of a given type in [`UniformBuffer`]-backed storage. */
-struct UniformVec<T> {
-	buffer: UniformBuffer,
-	item_size: u32,
-	max_items: u32,
-	items: u32,
-	_param: std::marker::PhantomData<T>,
-}
-impl<T> UniformVec<T>
-	where T: bytemuck::Pod {
-
-	pub fn with_capacity(device: &Device, capacity: u32) -> Self {
-		let item: T = bytemuck::Zeroable::zeroed();
-		let item_size = u32::try_from(bytemuck::bytes_of(&item).len())
-			.expect("The size of one element in this buffer does not fit \
-				into an unsigned 32-bit integer.");
-
-		let max_items = device.information()
-			.limits
-			.max_uniform_block_size / item_size;
-		let max_items = max_items.min(capacity);
-
-		let buffer = device.create_uniform_buffer(
-			&BufferDescriptor {
-				size: max_items * item_size,
-				profile: BufferProfile::DynamicUpload
-			}).unwrap();
-
-		Self {
-			buffer,
-			item_size,
-			max_items,
-			items: 0,
-			_param: Default::default()
-		}
-	}
 
-	pub fn with_items(
-		device: &Device,
-		items: u32,
-		f: impl FnMut() -> T) -> Self {
-
-		let mut this = Self::with_capacity(device, items);
-		this.resize_with(items, f);
-
-		this
+	/** Starts a new frame in the arena, pushing all of the data used in this
+	 * pass to it again. */
+	pub fn update(&mut self, global: &Globals, sky: &SkyUniforms) {
+		self.arena.reset();
+		self.slices = Self::push(
+			&mut self.arena,
+			global,
+			&self.mountains,
+			&self.backwalls,
+			&self.waterfalls,
+			sky);
 	}
 
-	/** Repopulates the data in the buffer with the given generator function. */
-	pub fn resize_with(
-		&mut self,
-		items: u32,
-		mut f: impl FnMut() -> T) {
-
-		let items = if items > self.max_items {
-			log::warn!("Clipping the number of populated items in the buffer \
-				from the requested {} items to the maximum of {} items",
-				items, self.max_items);
-			self.max_items
-		} else {
-			items
-		};
-
-		let size = self.item_size * items;
-
-		let slice = self.buffer.slice(..size);
-		let mut map = slice.try_map_mut(BufferLoadOp::DontCare)
-			.unwrap();
-
-		let mut offset = 0;
-		for _ in 0..items {
-			(&mut map[offset as usize..(offset + self.item_size) as usize])
-				.copy_from_slice(bytemuck::bytes_of(&(f)()));
-			offset += self.item_size;
+	/** Offsets shifting the entries of the group over to the current frame. */
+	pub fn offsets(&self) -> [u32; 5] {
+		let mut offsets = [0; 5];
+		for (offset, slice) in offsets.iter_mut().zip(&self.slices) {
+			*offset = self.arena.offset(slice);
 		}
-
-		self.items = items;
+		offsets
 	}
 
-	/** The maximum capacity of this buffer, in elements. */
-	pub fn capacity(&self) -> u32 {
-		self.max_items
-	}
-
-	/** The number of items in this buffer. */
-	pub fn len(&self) -> u32 {
-		self.items
-	}
-
-	pub fn buffer(&self) -> &UniformBuffer {
-		&self.buffer
+	fn push(
+		arena: &mut UniformArena,
+		global: &Globals,
+		mountains: &[Instance],
+		backwalls: &[Instance],
+		waterfalls: &[Instance],
+		sky: &SkyUniforms) -> [UniformSlice; 5] {
+
+		let mut push = |data: &[u8]| arena.push_bytes(data)
+			.expect("The uniform arena must fit all of the data of a frame.");
+		[
+			push(bytemuck::bytes_of(global)),
+			push(bytemuck::cast_slice(mountains)),
+			push(bytemuck::cast_slice(backwalls)),
+			push(bytemuck::cast_slice(waterfalls)),
+			push(bytemuck::bytes_of(sky)),
+		]
 	}
 }
 
-/** Uploads geometry to the device. */
-fn upload_geometry(device: &Device, vertices: &[Vertex], indices: &[u16])
+/** Uploads geometry to the device. Pipelines drawing it must read indices in
+ * the format given by [`MeshIndex::FORMAT`] for the type of the indices. */
+fn upload_geometry<I: MeshIndex>(device: &Device, vertices: &[Vertex], indices: &[I])
 	-> (VertexBuffer, IndexBuffer) {
-	let vert_size = {
-		let vert: Vertex = bytemuck::Zeroable::zeroed();
-		let size = bytemuck::bytes_of(&vert);
-
-		u32::try_from(size.len())
-			.expect("The size of a vertex cannot be converted into an \
-					unsigned 32-bit integer.")
-	};
-
 	let vertices = device.create_vertex_buffer_with_data(
 		&BufferDescriptor {
-			size: {
-				let count = u32::try_from(vertices.len())
-					.expect("The number of vertices to be uploaded \
-							does not fit into an unsigned 32-bit integer.");
-				let size = vert_size.checked_mul(count)
-					.expect("The number of bytes that would be taken up by \
-							the total number of vertices does not fit into an \
-							unsigned 32-bit integer.");
-
-				size
-			},
-			profile: BufferProfile::StaticUpload
+			size: buffer_size_of::<Vertex>(vertices.len())
+				.expect("The number of bytes that would be taken up by the \
+					total number of vertices does not fit into an unsigned \
+					32-bit integer."),
+			profile: BufferProfile::StaticUpload,
+			contents: BufferContents::Opaque,
+			..Default::default()
 		},
 		bytemuck::cast_slice(vertices))
 		.expect("Could not upload vertex buffer data.");
 	let indices = device.create_index_buffer_with_data(
 		&BufferDescriptor {
-			size: {
-				let one = u32::try_from(std::mem::size_of::<i16>())
-					.expect("The size of an u16 in bytes does not fit \
-							into an u32 value. What kind of architecture are \
-							you even using!?");
-				let count = u32::try_from(indices.len())
-					.expect("The number of indices to be uploaded \
-							does not fit into an unsigned 32-bit integer.");
-				let size = one.checked_mul(count)
-					.expect("The number of bytes that would be taken up by \
-							the total number of indices does not fit into an \
-							unsigned 32-bit integer.");
-
-				size
-			},
+			size: buffer_size_of::<I>(indices.len())
+				.expect("The number of bytes that would be taken up by the \
+					total number of indices does not fit into an unsigned \
+					32-bit integer."),
 			profile: BufferProfile::StaticUpload,
+			contents: BufferContents::Opaque,
+			..Default::default()
 		},
 		bytemuck::cast_slice(indices))
 		.expect("Could not upload index buffer data.");
@@ -1,4 +1,4 @@
-use support::{Camera, Projection};
+use support::{Camera, Projection, TimeOfDay};
 use crate::entity::{Entities, Entity, Class};
 use std::time::Duration;
 
@@ -10,10 +10,19 @@ pub struct Scene {
 
 	pub light_position: [f32; 2],
 	pub light_color: [f32; 3],
+	/** Time of day, which drives the sky and the light. */
+	pub time_of_day: TimeOfDay,
 
 	pub snowflakes: Snowflakes,
 }
 impl Scene {
+	/** Latitude the scene takes place at, in radians. */
+	const LATITUDE: f32 = 0.6;
+	/** How much faster than real time the day goes by. */
+	const TIME_SCALE: f32 = 240.0;
+	/** Color of the moonlight, which lights the scene up at night. */
+	const MOONLIGHT: [f32; 3] = [0.097, 0.158, 0.191];
+
 	pub fn new(aspect: f32) -> Self {
 		Self {
 			camera: Camera {
@@ -22,8 +31,10 @@ impl Scene {
 					right: 1.0,
 					top: 1.0,
 					bottom: -1.0,
-					near: 1.0,
-					far: 20.0,
+					/* The scene is flat, and sits right at the camera, so
+					 * center the box on it. */
+					near: -9.5,
+					far: 9.5,
 				},
 				position: [0.0, 0.0, 0.0,],
 				yaw: 0.0,
@@ -32,11 +43,15 @@ impl Scene {
 			aspect,
 			light_position: [2.0, 2.0],
 			light_color: [1.0, 1.0, 1.0],
+			time_of_day: TimeOfDay::from_hours(9.0, Self::LATITUDE),
 			snowflakes: Snowflakes::new(),
 		}
 	}
 
 	pub fn update(&mut self, delta: Duration) {
+		self.time_of_day.advance(delta.as_secs_f32() * Self::TIME_SCALE);
+		self.update_light();
+
 		self.snowflakes.entities.simulate(delta);
 
 		self.snowflakes.spawn_timer += delta;
@@ -56,6 +71,21 @@ impl Scene {
 			self.snowflakes.spawn_timer -= Duration::from_millis(250);
 		}
 	}
+
+	/** Places the light at the sun, or at the moon opposite to it once the sun
+	 * has set, and colors it for the current time of day. */
+	fn update_light(&mut self) {
+		let [east, up, _] = self.time_of_day.sun_direction();
+		let (east, up) = if up >= 0.0 { (east, up) } else { (-east, -up) };
+		self.light_position = [east * 2.0, up * 2.0];
+
+		let sun = self.time_of_day.sun_color();
+		let intensity = self.time_of_day.sun_intensity();
+		for i in 0..3 {
+			self.light_color[i] = sun[i] * intensity
+				+ Self::MOONLIGHT[i] * (1.0 - intensity);
+		}
+	}
 }
 
 /** Snowflake particle simulation bundle. */
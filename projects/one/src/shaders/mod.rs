@@ -38,3 +38,12 @@ pub mod waterfall {
 	pub const FRAGMENT: ShaderSource<'static> =
 		ShaderSource::Glsl(Cow::Borrowed(include_str!("lighting/VertexColoredDirect.glsl")));
 }
+
+pub mod sky {
+	use gavle::ShaderSource;
+	use std::borrow::Cow;
+
+	pub const VERTEX: ShaderSource<'static> =
+		ShaderSource::Glsl(Cow::Borrowed(include_str!("shapes/Sky.glsl")));
+	pub const FRAGMENT: ShaderSource<'static> = support::SKY_FRAGMENT_SHADER;
+}
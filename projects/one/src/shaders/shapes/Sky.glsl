@@ -0,0 +1,16 @@
+#version 300 es
+precision highp float;
+
+in vec3 tt_vert_position;
+
+out highp vec3 vs_sky_direction;
+
+void main()
+{
+    /* The quad covers the whole screen, which looks north, with east to the
+     * right of it and the horizon a bit under its middle. */
+    vec2 position = tt_vert_position.xy;
+    vs_sky_direction = normalize(vec3(position.x, position.y + 0.3, -1.0));
+
+    gl_Position = vec4(position, 1.0, 1.0);
+}
@@ -29,11 +29,6 @@ layout(std140) uniform rc_mountains
 	Instance Mountains[MAX_INSTANCES];
 };
 
-layout(std140) uniform rc_snowflakes
-{
-	Instance Snowflakes[MAX_INSTANCES];
-};
-
 layout(std140) uniform rc_backwalls
 {
 	Instance Backwalls[MAX_INSTANCES];
@@ -47,6 +42,9 @@ layout(std140) uniform rc_waterfalls
 in vec3 tt_vert_position;
 in vec3 tt_vert_color;
 
+/* Position offset of the snowflake in world space, one per instance. */
+in vec3 tt_inst_position;
+
 out vec3  vs_color;
 out vec3  vs_center_position;
 out vec3  vs_vertex_position;
@@ -60,7 +58,7 @@ void main()
 	vec4 position = vec4(tt_vert_position, 1.0);
 	     position = SnowflakeWorldTransformation * position;
 
-	vec3 offset = Snowflakes[gl_InstanceID].position;
+	vec3 offset = tt_inst_position;
 	position.x += offset.x;
 	position.y += offset.y;
 	position.z += offset.z;
@@ -29,11 +29,6 @@ layout(std140) uniform rc_mountains
 	Instance Mountains[MAX_INSTANCES];
 };
 
-layout(std140) uniform rc_snowflakes
-{
-	Instance Snowflakes[MAX_INSTANCES];
-};
-
 layout(std140) uniform rc_backwalls
 {
 	Instance Backwalls[MAX_INSTANCES];
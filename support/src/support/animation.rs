@@ -0,0 +1,807 @@
+use crate::support::{Matrix4, Quaternion};
+use std::collections::HashMap;
+
+/** Transformation of a single joint relative to its parent, split into its
+ * translation, rotation and scale, so that it can be blended with others.
+ *
+ * Rotations are unit [`Quaternion`]s. */
+#[derive(Debug, Copy, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "PascalCase")]
+pub struct JointTransform {
+	/** Offset of the joint, applied last. */
+	pub translation: [f32; 3],
+	/** Rotation of the joint, applied after scaling. */
+	pub rotation: Quaternion,
+	/** Scale of the joint along each of the axes, applied first. */
+	pub scale: [f32; 3],
+}
+impl JointTransform {
+	/** The transformation that leaves the joint where it is. */
+	pub const IDENTITY: Self = Self {
+		translation: [0.0; 3],
+		rotation: Quaternion::IDENTITY,
+		scale: [1.0; 3],
+	};
+
+	/** Creates a new translation with the given offsets for each of the axes. */
+	pub fn translate(x: f32, y: f32, z: f32) -> Self {
+		Self {
+			translation: [x, y, z],
+			..Self::IDENTITY
+		}
+	}
+
+	/** Creates a new axis-angle rotation with the given pivot vector and
+	 * rotation angle, given in radians. This turns the same way as the matrix
+	 * created by [`Matrix4::rotate()`] does.
+	 *
+	 * [`Matrix4::rotate()`]: Matrix4::rotate */
+	pub fn rotate(x: f32, y: f32, z: f32, angle: f32) -> Self {
+		Self {
+			rotation: Quaternion::from_axis_angle(x, y, z, angle),
+			..Self::IDENTITY
+		}
+	}
+
+	/** The matrix applying this transformation. */
+	pub fn matrix(&self) -> Matrix4 {
+		let [x, y, z, w] = *self.rotation.as_array();
+		let [sx, sy, sz] = self.scale;
+		let [tx, ty, tz] = self.translation;
+
+		Matrix4::from_row_major_array([
+			(1.0 - 2.0 * (y * y + z * z)) * sx,
+			(2.0 * (x * y - z * w)) * sy,
+			(2.0 * (x * z + y * w)) * sz,
+			tx,
+			(2.0 * (x * y + z * w)) * sx,
+			(1.0 - 2.0 * (x * x + z * z)) * sy,
+			(2.0 * (y * z - x * w)) * sz,
+			ty,
+			(2.0 * (x * z - y * w)) * sx,
+			(2.0 * (y * z + x * w)) * sy,
+			(1.0 - 2.0 * (x * x + y * y)) * sz,
+			tz,
+			0.0, 0.0, 0.0, 1.0
+		])
+	}
+
+	/** Linear interpolation between this transformation and the given one.
+	 *
+	 * Rotations are interpolated along the shortest arc between them and then
+	 * normalized, which doesn't keep a constant angular velocity, but agrees
+	 * with a spherical interpolation at both ends and at the midpoint, and is
+	 * what blending usually calls for. */
+	pub fn lerp(&self, other: &Self, s: f32) -> Self {
+		Self {
+			translation: lerp3(self.translation, other.translation, s),
+			rotation: self.rotation.nlerp(other.rotation, s),
+			scale: lerp3(self.scale, other.scale, s),
+		}
+	}
+
+	/** Layers the given transformation on top of this one, taking it as a
+	 * difference from the identity and scaling it by the given weight. */
+	pub fn add(&self, other: &Self, weight: f32) -> Self {
+		let offset = lerp3([0.0; 3], other.translation, weight);
+		let rotation = Quaternion::IDENTITY.nlerp(other.rotation, weight);
+		let scale = lerp3([1.0; 3], other.scale, weight);
+
+		Self {
+			translation: [
+				self.translation[0] + offset[0],
+				self.translation[1] + offset[1],
+				self.translation[2] + offset[2],
+			],
+			rotation: (self.rotation * rotation).normalize()
+				.unwrap_or(Quaternion::IDENTITY),
+			scale: [
+				self.scale[0] * scale[0],
+				self.scale[1] * scale[1],
+				self.scale[2] * scale[2],
+			],
+		}
+	}
+}
+impl Default for JointTransform {
+	fn default() -> Self {
+		Self::IDENTITY
+	}
+}
+
+/** A snapshot of the transformation of a joint at a given point in a
+ * [`JointTrack`].
+ *
+ * [`JointTrack`]: JointTrack */
+#[derive(Debug, Copy, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "PascalCase")]
+pub struct JointKeyframe {
+	/** Time at which this keyframe happens, in seconds. */
+	pub time: f32,
+	/** Transformation of the joint relative to its parent. */
+	pub transform: JointTransform,
+}
+
+/** The motion of a single joint, made up of timestamped keyframes that get
+ * interpolated linearly. */
+#[derive(Debug, Clone, PartialEq, Default, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "PascalCase")]
+pub struct JointTrack {
+	/** Keyframes in this track, sorted by their timestamps. */
+	keyframes: Vec<JointKeyframe>,
+}
+impl JointTrack {
+	/** Create a joint track from the given list of keyframes.
+	 *
+	 * The keyframes do not have to be sorted, as they get sorted by their
+	 * timestamps as part of this function. */
+	pub fn from_keyframes(mut keyframes: Vec<JointKeyframe>) -> Self {
+		keyframes.sort_by(|a, b| a.time.partial_cmp(&b.time)
+			.expect("joint keyframe timestamps must not be NaN"));
+		Self { keyframes }
+	}
+
+	/** All of the keyframes in this track, sorted by their timestamps. */
+	pub fn keyframes(&self) -> &[JointKeyframe] {
+		&self.keyframes[..]
+	}
+
+	/** Time at which the last keyframe of this track happens, in seconds. */
+	pub fn duration(&self) -> f32 {
+		self.keyframes.last().map(|frame| frame.time).unwrap_or(0.0)
+	}
+
+	/** Sample the transformation of the joint at the given time.
+	 *
+	 * Times before the first keyframe and after the last one are clamped to
+	 * those keyframes. Tracks without any keyframes hold the joint at the
+	 * identity transformation. */
+	pub fn sample(&self, time: f32) -> JointTransform {
+		let (first, last) = match (self.keyframes.first(), self.keyframes.last()) {
+			(Some(first), Some(last)) => (first, last),
+			_ => return JointTransform::IDENTITY
+		};
+		if time <= first.time { return first.transform }
+		if time >= last.time { return last.transform }
+
+		/* Given the checks above, there's always a keyframe strictly after the
+		 * one we land on. */
+		let i = self.keyframes
+			.iter()
+			.rposition(|frame| frame.time <= time)
+			.unwrap();
+		let a = &self.keyframes[i];
+		let b = &self.keyframes[i + 1];
+
+		a.transform.lerp(&b.transform, (time - a.time) / (b.time - a.time))
+	}
+}
+
+/** What happens to a clip once it plays past its duration. */
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum ClipWrap {
+	/** The clip starts over from the beginning. */
+	Loop,
+	/** The clip holds on to its last frame. */
+	Clamp,
+}
+
+/** A set of joint tracks that play together, one for every joint in a
+ * skeleton, in the same order as the joints. */
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "PascalCase")]
+pub struct AnimationClip {
+	/** Tracks of every joint. */
+	tracks: Vec<JointTrack>,
+	/** What happens once the clip plays past its last keyframe. */
+	wrap: ClipWrap,
+}
+impl AnimationClip {
+	/** Create a new clip from the given tracks. */
+	pub fn new(tracks: Vec<JointTrack>, wrap: ClipWrap) -> Self {
+		Self { tracks, wrap }
+	}
+
+	/** The tracks in this clip, one per joint. */
+	pub fn tracks(&self) -> &[JointTrack] {
+		&self.tracks[..]
+	}
+
+	/** What happens once the clip plays past its last keyframe. */
+	pub fn wrap(&self) -> ClipWrap {
+		self.wrap
+	}
+
+	/** Time at which the last keyframe of the longest track happens, which is
+	 * how long the clip takes to play, in seconds. */
+	pub fn duration(&self) -> f32 {
+		self.tracks.iter()
+			.map(JointTrack::duration)
+			.fold(0.0, f32::max)
+	}
+
+	/** Brings the given time into the range the clip plays in. */
+	fn wrap_time(&self, time: f32) -> f32 {
+		let duration = self.duration();
+		match self.wrap {
+			ClipWrap::Loop if duration > 0.0 => time.rem_euclid(duration),
+			ClipWrap::Loop => 0.0,
+			ClipWrap::Clamp => time.clamp(0.0, duration.max(0.0)),
+		}
+	}
+
+	/** Sample every joint at the given time, which must already have been
+	 * wrapped, into the given pose. */
+	fn sample_into(&self, time: f32, pose: &mut [JointTransform]) {
+		for (transform, track) in pose.iter_mut().zip(&self.tracks) {
+			*transform = track.sample(time);
+		}
+	}
+}
+
+/** A value controlling a node in a [`BlendTree`], which is either fixed when
+ * the tree is built, or can be changed by name afterwards.
+ *
+ * [`BlendTree`]: BlendTree */
+#[derive(Debug, Clone, PartialEq)]
+pub enum BlendParam {
+	/** A value that never changes. */
+	Fixed(f32),
+	/** A value that can be changed through [`BlendTree::set_param()`], along
+	 * with the value it starts at. Nodes sharing a name share their value,
+	 * which starts at the first default given to it.
+	 *
+	 * [`BlendTree::set_param()`]: BlendTree::set_param */
+	Named {
+		name: String,
+		default: f32,
+	},
+}
+impl BlendParam {
+	/** Creates a named parameter starting at the given value. */
+	pub fn named(name: &str, default: f32) -> Self {
+		Self::Named {
+			name: name.to_owned(),
+			default
+		}
+	}
+}
+impl From<f32> for BlendParam {
+	fn from(value: f32) -> Self {
+		Self::Fixed(value)
+	}
+}
+
+/** Description of a node in a [`BlendTree`].
+ *
+ * [`BlendTree`]: BlendTree */
+#[derive(Debug, Clone, PartialEq)]
+pub enum BlendNode {
+	/** Plays back a clip. */
+	Clip(AnimationClip),
+	/** Interpolates between two poses, going from `a` at a weight of zero to
+	 * `b` at a weight of one. Weights are clamped to that range. */
+	Blend2 {
+		a: Box<BlendNode>,
+		b: Box<BlendNode>,
+		weight: BlendParam,
+	},
+	/** Layers a pose on top of another, taking the transformations in it as
+	 * differences from the identity, scaled by the given weight. */
+	Additive {
+		base: Box<BlendNode>,
+		add: Box<BlendNode>,
+		weight: BlendParam,
+	},
+	/** Plays a pose back at the given rate, where a rate of one plays it at
+	 * normal speed and negative rates play it backwards. */
+	Speed {
+		child: Box<BlendNode>,
+		rate: BlendParam,
+	},
+}
+
+/** Parameter of a node, after its name has been resolved. */
+#[derive(Debug, Copy, Clone)]
+enum Param {
+	/** A value that never changes. */
+	Fixed(f32),
+	/** Index of the value in the parameters of the tree. */
+	Slot(usize),
+}
+impl Param {
+	/** The current value of this parameter. */
+	fn get(&self, params: &[f32]) -> f32 {
+		match self {
+			Self::Fixed(value) => *value,
+			Self::Slot(slot) => params[*slot],
+		}
+	}
+}
+
+/** A node in a built tree, referring to its children by their indices. */
+#[derive(Debug)]
+enum Node {
+	Clip {
+		clip: AnimationClip,
+		/** Current time in the clip, already wrapped. */
+		time: f32,
+	},
+	Blend2 {
+		a: usize,
+		b: usize,
+		weight: Param,
+		/** Pose buffer the second child gets evaluated into. */
+		scratch: usize,
+	},
+	Additive {
+		base: usize,
+		add: usize,
+		weight: Param,
+		/** Pose buffer the added child gets evaluated into. */
+		scratch: usize,
+	},
+	Speed {
+		child: usize,
+		rate: Param,
+	},
+}
+
+/** A tree of clips, mixed together through blend nodes into a single pose.
+ *
+ * Every clip in the tree keeps its own time, so clips of different durations
+ * loop or clamp independently of each other, and they all keep advancing
+ * regardless of how much they currently contribute to the pose, so that
+ * bringing one back in doesn't make it jump.
+ *
+ * All of the memory needed to evaluate the tree is allocated when it is built,
+ * so advancing and evaluating it every frame doesn't allocate. */
+#[derive(Debug)]
+pub struct BlendTree {
+	/** Nodes in the tree, with every node coming after its children. */
+	nodes: Vec<Node>,
+	/** Current values of the named parameters. */
+	params: Vec<f32>,
+	/** Slots of the named parameters. */
+	names: HashMap<String, usize>,
+	/** Pose buffers for the nodes mixing two poses. */
+	scratch: Vec<Vec<JointTransform>>,
+	/** Transformations of every joint in the last evaluated pose. */
+	transforms: Vec<JointTransform>,
+	/** Matrices of every joint in the last evaluated pose. */
+	matrices: Vec<Matrix4>,
+}
+impl BlendTree {
+	/** Builds a new tree from the given description, with every clip at its
+	 * start and every named parameter at its default value.
+	 *
+	 * # Errors
+	 * All of the clips in the tree must have the same number of joints. */
+	pub fn new(root: BlendNode) -> Result<Self, BlendTreeError> {
+		let mut builder = Builder {
+			nodes: Vec::new(),
+			params: Vec::new(),
+			names: HashMap::new(),
+			scratch: 0,
+			joints: None,
+		};
+		builder.push(root)?;
+
+		let joints = builder.joints.unwrap_or(0);
+		Ok(Self {
+			nodes: builder.nodes,
+			params: builder.params,
+			names: builder.names,
+			scratch: vec![vec![JointTransform::IDENTITY; joints]; builder.scratch],
+			transforms: vec![JointTransform::IDENTITY; joints],
+			matrices: vec![Matrix4::identity(); joints],
+		})
+	}
+
+	/** Number of joints in the poses produced by this tree. */
+	pub fn joints(&self) -> usize {
+		self.transforms.len()
+	}
+
+	/** The current value of the named parameter, if there is one. */
+	pub fn param(&self, name: &str) -> Option<f32> {
+		self.names.get(name).map(|slot| self.params[*slot])
+	}
+
+	/** Changes the value of the named parameter. */
+	pub fn set_param(&mut self, name: &str, value: f32) -> Result<(), BlendTreeError> {
+		match self.names.get(name) {
+			Some(slot) => {
+				self.params[*slot] = value;
+				Ok(())
+			},
+			None => Err(BlendTreeError::UnknownParam { name: name.to_owned() })
+		}
+	}
+
+	/** Moves every clip in the tree back to its start. */
+	pub fn reset(&mut self) {
+		for node in &mut self.nodes {
+			if let Node::Clip { time, .. } = node {
+				*time = 0.0;
+			}
+		}
+	}
+
+	/** Advances the tree by the given number of seconds. */
+	pub fn advance(&mut self, seconds: f32) {
+		let root = self.nodes.len() - 1;
+		advance(&mut self.nodes, &self.params, root, seconds);
+	}
+
+	/** Evaluates the pose at the current time, returning the transformation
+	 * of every joint relative to its parent, as taken by
+	 * [`Skeleton::set_pose()`].
+	 *
+	 * [`Skeleton::set_pose()`]: crate::support::Skeleton::set_pose */
+	pub fn evaluate(&mut self) -> &[Matrix4] {
+		let root = self.nodes.len() - 1;
+		evaluate(
+			&self.nodes,
+			&self.params,
+			root,
+			&mut self.scratch,
+			&mut self.transforms);
+
+		for (matrix, transform) in self.matrices.iter_mut().zip(&self.transforms) {
+			*matrix = transform.matrix();
+		}
+		&self.matrices[..]
+	}
+
+	/** The transformation of every joint in the last evaluated pose. */
+	pub fn transforms(&self) -> &[JointTransform] {
+		&self.transforms[..]
+	}
+}
+
+/** State used while flattening the description of a tree. */
+struct Builder {
+	nodes: Vec<Node>,
+	params: Vec<f32>,
+	names: HashMap<String, usize>,
+	/** Number of pose buffers handed out so far. */
+	scratch: usize,
+	/** Number of joints in the first clip found. */
+	joints: Option<usize>,
+}
+impl Builder {
+	/** Flattens the given node, after its children, returning its index. */
+	fn push(&mut self, node: BlendNode) -> Result<usize, BlendTreeError> {
+		let node = match node {
+			BlendNode::Clip(clip) => {
+				let joints = *self.joints.get_or_insert(clip.tracks.len());
+				if clip.tracks.len() != joints {
+					return Err(BlendTreeError::JointMismatch {
+						expected: joints,
+						provided: clip.tracks.len()
+					})
+				}
+				Node::Clip { clip, time: 0.0 }
+			},
+			BlendNode::Blend2 { a, b, weight } => Node::Blend2 {
+				a: self.push(*a)?,
+				b: self.push(*b)?,
+				weight: self.param(weight),
+				scratch: self.scratch(),
+			},
+			BlendNode::Additive { base, add, weight } => Node::Additive {
+				base: self.push(*base)?,
+				add: self.push(*add)?,
+				weight: self.param(weight),
+				scratch: self.scratch(),
+			},
+			BlendNode::Speed { child, rate } => Node::Speed {
+				child: self.push(*child)?,
+				rate: self.param(rate),
+			},
+		};
+
+		self.nodes.push(node);
+		Ok(self.nodes.len() - 1)
+	}
+
+	/** Resolves the given parameter. */
+	fn param(&mut self, param: BlendParam) -> Param {
+		match param {
+			BlendParam::Fixed(value) => Param::Fixed(value),
+			BlendParam::Named { name, default } => {
+				let params = &mut self.params;
+				let slot = *self.names.entry(name).or_insert_with(|| {
+					params.push(default);
+					params.len() - 1
+				});
+				Param::Slot(slot)
+			}
+		}
+	}
+
+	/** Hands out a new pose buffer. */
+	fn scratch(&mut self) -> usize {
+		self.scratch += 1;
+		self.scratch - 1
+	}
+}
+
+/** Advances the given node and its children by the given number of seconds. */
+fn advance(nodes: &mut [Node], params: &[f32], node: usize, seconds: f32) {
+	match &mut nodes[node] {
+		Node::Clip { clip, time } => *time = clip.wrap_time(*time + seconds),
+		Node::Blend2 { a, b, .. } => {
+			let (a, b) = (*a, *b);
+			advance(nodes, params, a, seconds);
+			advance(nodes, params, b, seconds);
+		},
+		Node::Additive { base, add, .. } => {
+			let (base, add) = (*base, *add);
+			advance(nodes, params, base, seconds);
+			advance(nodes, params, add, seconds);
+		},
+		Node::Speed { child, rate } => {
+			let (child, rate) = (*child, rate.get(params));
+			advance(nodes, params, child, seconds * rate);
+		},
+	}
+}
+
+/** Evaluates the pose at the given node into the given buffer. */
+fn evaluate(
+	nodes: &[Node],
+	params: &[f32],
+	node: usize,
+	scratch: &mut [Vec<JointTransform>],
+	pose: &mut [JointTransform]) {
+
+	/* Evaluates both children, the second one into its own pose buffer, and
+	 * mixes it into the first one with the given function. */
+	let mut mix = |first, second, slot: usize, op: &dyn Fn(&mut JointTransform, &JointTransform)| {
+		evaluate(nodes, params, first, scratch, pose);
+
+		/* Taking the buffer out leaves an empty one behind, which doesn't
+		 * allocate, and keeps the rest of them available to the child. */
+		let mut other = std::mem::take(&mut scratch[slot]);
+		evaluate(nodes, params, second, scratch, &mut other);
+		for (transform, other) in pose.iter_mut().zip(&other) {
+			op(transform, other);
+		}
+		scratch[slot] = other;
+	};
+
+	match &nodes[node] {
+		Node::Clip { clip, time } => clip.sample_into(*time, pose),
+		Node::Blend2 { a, b, weight, scratch: slot } => {
+			let weight = weight.get(params).clamp(0.0, 1.0);
+			mix(*a, *b, *slot, &|a, b| *a = a.lerp(b, weight))
+		},
+		Node::Additive { base, add, weight, scratch: slot } => {
+			let weight = weight.get(params);
+			mix(*base, *add, *slot, &|base, add| *base = base.add(add, weight))
+		},
+		Node::Speed { child, .. } => evaluate(nodes, params, *child, scratch, pose),
+	}
+}
+
+/** Linear interpolation between two vectors. */
+fn lerp3(a: [f32; 3], b: [f32; 3], s: f32) -> [f32; 3] {
+	[
+		a[0] + (b[0] - a[0]) * s,
+		a[1] + (b[1] - a[1]) * s,
+		a[2] + (b[2] - a[2]) * s,
+	]
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum BlendTreeError {
+	#[error("a clip has {provided} joints, but the tree has {expected}")]
+	JointMismatch {
+		expected: usize,
+		provided: usize
+	},
+	#[error("the tree has no parameter named {name:?}")]
+	UnknownParam {
+		name: String
+	},
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::alloc::{GlobalAlloc, Layout, System};
+	use std::cell::Cell;
+	use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+
+	/** Allocator counting the allocations made by every thread. */
+	struct CountingAllocator;
+	thread_local! {
+		static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+	}
+	unsafe impl GlobalAlloc for CountingAllocator {
+		unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+			let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+			System.alloc(layout)
+		}
+		unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+			System.dealloc(ptr, layout)
+		}
+	}
+	#[global_allocator]
+	static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+	fn allocations() -> usize {
+		ALLOCATIONS.with(Cell::get)
+	}
+
+	fn assert_close(a: &Matrix4, b: &Matrix4) {
+		let pairs = a.as_row_major_array().iter().zip(b.as_row_major_array());
+		for (a, b) in pairs {
+			assert!((a - b).abs() < 1e-5, "{:?} != {:?}", a, b);
+		}
+	}
+
+	/** A single joint going from one transformation to another in a second. */
+	fn clip(from: JointTransform, to: JointTransform, wrap: ClipWrap) -> BlendNode {
+		BlendNode::Clip(AnimationClip::new(vec![JointTrack::from_keyframes(vec![
+			JointKeyframe { time: 0.0, transform: from },
+			JointKeyframe { time: 1.0, transform: to },
+		])], wrap))
+	}
+
+	/** A single joint held at the given transformation. */
+	fn hold(transform: JointTransform) -> BlendNode {
+		clip(transform, transform, ClipWrap::Clamp)
+	}
+
+	#[test]
+	fn rotations_match_matrices() {
+		for &(x, y, z, angle) in &[(0.0, 0.0, 1.0, FRAC_PI_2), (1.0, 2.0, 3.0, 1.0)] {
+			assert_close(
+				&JointTransform::rotate(x, y, z, angle).matrix(),
+				&Matrix4::rotate(x, y, z, angle));
+		}
+
+		let transform = JointTransform {
+			scale: [2.0, 3.0, 4.0],
+			..JointTransform::translate(1.0, 2.0, 3.0)
+		};
+		assert_close(
+			&transform.matrix(),
+			&(Matrix4::translate(1.0, 2.0, 3.0) * Matrix4::scale(2.0, 3.0, 4.0)));
+	}
+
+	#[test]
+	fn clips_wrap_independently() {
+		/* A looping clip a second long, and a clamped one two seconds long. */
+		let short = AnimationClip::new(vec![JointTrack::from_keyframes(vec![
+			JointKeyframe { time: 0.0, transform: JointTransform::translate(0.0, 0.0, 0.0) },
+			JointKeyframe { time: 1.0, transform: JointTransform::translate(4.0, 0.0, 0.0) },
+		])], ClipWrap::Loop);
+		let long = AnimationClip::new(vec![JointTrack::from_keyframes(vec![
+			JointKeyframe { time: 0.0, transform: JointTransform::translate(0.0, 0.0, 0.0) },
+			JointKeyframe { time: 2.0, transform: JointTransform::translate(0.0, 4.0, 0.0) },
+		])], ClipWrap::Clamp);
+
+		let mut tree = BlendTree::new(BlendNode::Additive {
+			base: Box::new(BlendNode::Clip(short)),
+			add: Box::new(BlendNode::Clip(long)),
+			weight: 1.0.into(),
+		}).unwrap();
+
+		tree.advance(1.5);
+		tree.evaluate();
+		assert_eq!(tree.transforms()[0].translation, [2.0, 3.0, 0.0]);
+
+		tree.advance(1.0);
+		tree.evaluate();
+		assert_eq!(tree.transforms()[0].translation, [2.0, 4.0, 0.0]);
+	}
+
+	#[test]
+	fn blends_take_the_shortest_arc() {
+		let mut tree = BlendTree::new(BlendNode::Blend2 {
+			a: Box::new(hold(JointTransform::translate(0.0, 0.0, 0.0))),
+			b: Box::new(hold(JointTransform {
+				rotation: JointTransform::rotate(0.0, 0.0, 1.0, FRAC_PI_2).rotation,
+				..JointTransform::translate(2.0, 0.0, 0.0)
+			})),
+			weight: BlendParam::named("weight", 0.5),
+		}).unwrap();
+
+		let expected = Matrix4::translate(1.0, 0.0, 0.0)
+			* Matrix4::rotate(0.0, 0.0, 1.0, FRAC_PI_4);
+		assert_close(&tree.evaluate()[0], &expected);
+
+		/* Weights are clamped. */
+		tree.set_param("weight", 4.0).unwrap();
+		assert_close(&tree.evaluate()[0], &(Matrix4::translate(2.0, 0.0, 0.0)
+			* Matrix4::rotate(0.0, 0.0, 1.0, FRAC_PI_2)));
+		assert!(tree.set_param("walk_weight", 0.5).is_err());
+	}
+
+	#[test]
+	fn additive_layers_stack_on_the_base() {
+		let mut tree = BlendTree::new(BlendNode::Additive {
+			base: Box::new(hold(JointTransform {
+				rotation: JointTransform::rotate(0.0, 0.0, 1.0, FRAC_PI_2).rotation,
+				..JointTransform::translate(1.0, 0.0, 0.0)
+			})),
+			add: Box::new(hold(JointTransform {
+				rotation: JointTransform::rotate(0.0, 0.0, 1.0, FRAC_PI_2).rotation,
+				..JointTransform::translate(0.0, 2.0, 0.0)
+			})),
+			weight: BlendParam::named("wave", 1.0),
+		}).unwrap();
+
+		assert_close(&tree.evaluate()[0], &(Matrix4::translate(1.0, 2.0, 0.0)
+			* Matrix4::rotate(0.0, 0.0, 1.0, PI)));
+
+		tree.set_param("wave", 0.5).unwrap();
+		assert_close(&tree.evaluate()[0], &(Matrix4::translate(1.0, 1.0, 0.0)
+			* Matrix4::rotate(0.0, 0.0, 1.0, FRAC_PI_2 + FRAC_PI_4)));
+	}
+
+	#[test]
+	fn speed_scales_time() {
+		let mut tree = BlendTree::new(BlendNode::Speed {
+			child: Box::new(clip(
+				JointTransform::translate(0.0, 0.0, 0.0),
+				JointTransform::translate(1.0, 0.0, 0.0),
+				ClipWrap::Loop)),
+			rate: BlendParam::named("rate", 2.0),
+		}).unwrap();
+
+		tree.advance(0.25);
+		tree.evaluate();
+		assert_eq!(tree.transforms()[0].translation, [0.5, 0.0, 0.0]);
+
+		/* Playing backwards wraps around the start. */
+		tree.set_param("rate", -1.0).unwrap();
+		tree.advance(0.75);
+		tree.evaluate();
+		assert_eq!(tree.transforms()[0].translation, [0.75, 0.0, 0.0]);
+	}
+
+	#[test]
+	fn clips_must_agree_on_joints() {
+		let two = BlendNode::Clip(AnimationClip::new(
+			vec![JointTrack::default(), JointTrack::default()],
+			ClipWrap::Loop));
+		assert!(BlendTree::new(BlendNode::Blend2 {
+			a: Box::new(hold(JointTransform::IDENTITY)),
+			b: Box::new(two),
+			weight: 0.5.into(),
+		}).is_err());
+	}
+
+	#[test]
+	fn evaluation_does_not_allocate() {
+		let walk = || BlendNode::Blend2 {
+			a: Box::new(clip(
+				JointTransform::IDENTITY,
+				JointTransform::translate(1.0, 0.0, 0.0),
+				ClipWrap::Loop)),
+			b: Box::new(hold(JointTransform::rotate(1.0, 0.0, 0.0, 1.0))),
+			weight: BlendParam::named("walk_weight", 0.7),
+		};
+		let mut tree = BlendTree::new(BlendNode::Additive {
+			base: Box::new(BlendNode::Speed {
+				child: Box::new(walk()),
+				rate: 1.5.into()
+			}),
+			add: Box::new(walk()),
+			weight: BlendParam::named("wave", 0.3),
+		}).unwrap();
+
+		let before = allocations();
+		for _ in 0..16 {
+			tree.set_param("walk_weight", 0.2).unwrap();
+			tree.advance(0.1);
+			tree.evaluate();
+		}
+		assert_eq!(allocations(), before);
+	}
+}
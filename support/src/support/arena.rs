@@ -0,0 +1,243 @@
+use gavle::*;
+use std::convert::TryFrom;
+use std::num::NonZeroU32;
+
+/** Offset of a push of `len` bytes to a ring of `capacity` bytes whose next
+ * free byte is at `head`, aligned to `alignment`, along with whether the ring
+ * has wrapped back around to its start in the current frame, or `None` if it
+ * would run into the data pushed so far in the current frame, which started at
+ * `frame_start`. */
+fn ring_allocation(
+	head: u32,
+	frame_start: u32,
+	wrapped: bool,
+	len: u32,
+	alignment: u32,
+	capacity: u32) -> Option<(u32, bool)> {
+
+	let end = if wrapped { frame_start } else { capacity };
+	let aligned = head.checked_add(alignment - 1)
+		.map(|head| head / alignment * alignment);
+	match aligned.and_then(|offset| offset.checked_add(len)) {
+		Some(fits) if fits <= end => Some((aligned.unwrap(), wrapped)),
+		_ if !wrapped && len <= frame_start => Some((0, true)),
+		_ => None
+	}
+}
+
+/** A range of a [`UniformArena`] holding data pushed to it in the current
+ * frame. Slices are only valid until the arena they came from gets reset,
+ * after which their data may get overwritten at any time. */
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct UniformSlice {
+	/** Offset of the slice into the buffer of the arena, in bytes. */
+	offset: u32,
+	/** Length of the slice, in bytes. */
+	size: NonZeroU32,
+	/** Frame of the arena the slice was pushed in. */
+	frame: u64,
+}
+impl UniformSlice {
+	/** Offset of this slice into the buffer of its arena, in bytes. */
+	pub fn offset(&self) -> u32 {
+		self.offset
+	}
+
+	/** Length of this slice, in bytes. */
+	pub fn size(&self) -> u32 {
+		self.size.get()
+	}
+}
+
+/** A single large uniform buffer that per-draw data gets pushed to, one frame
+ * at a time, rather than every kind of data having its own buffer.
+ *
+ * Data gets pushed right after whatever was pushed before it, aligned to the
+ * [`uniform_buffer_offset_alignment`] limit, and gets written straight into
+ * the buffer. Once a frame is done, the arena gets [reset], and the next frame
+ * picks up where the last one left off, wrapping back around to the start of
+ * the buffer once it reaches the end of it. This way, the data of a frame goes
+ * over the data of the oldest frames, which the device is the most likely to
+ * be done with, rather than over the data the last frame is still drawing with.
+ *
+ * The slices returned by pushes may be bound to groups with [`bind()`], or
+ * with [`bind_dynamic()`] and [`offset()`], in order to keep the same group
+ * around from frame to frame and shift it over to the slices of the current
+ * frame with [`RenderPass::set_bind_group_with_offsets`]. Slices are only
+ * valid in the frame they were pushed in, which is asserted for in debug
+ * builds.
+ *
+ * [`uniform_buffer_offset_alignment`]: gavle::Limits::uniform_buffer_offset_alignment
+ * [reset]: Self::reset
+ * [`bind()`]: Self::bind
+ * [`bind_dynamic()`]: Self::bind_dynamic
+ * [`offset()`]: Self::offset
+ * [`RenderPass::set_bind_group_with_offsets`]: gavle::RenderPass::set_bind_group_with_offsets */
+pub struct UniformArena {
+	/** The buffer holding the data. */
+	buffer: UniformBuffer,
+	/** Alignment of the offset of every slice, in bytes. */
+	alignment: u32,
+	/** Offset of the byte right after the last slice pushed. */
+	head: u32,
+	/** Offset of the first slice pushed in the current frame. */
+	frame_start: u32,
+	/** Whether the slices of the current frame have wrapped around to the
+	 * start of the buffer. */
+	wrapped: bool,
+	/** Number of times the arena has been reset. */
+	frame: u64,
+}
+impl UniformArena {
+	/** Creates a new arena, large enough to hold the given number of uniform
+	 * blocks of the largest size the context supports. */
+	pub fn new(device: &Device, blocks: u32) -> Result<Self, BufferError> {
+		let limits = &device.information().limits;
+		let size = limits.max_uniform_block_size.checked_mul(blocks)
+			.ok_or(BufferError::TooLarge {
+				computed_bytes: u64::from(limits.max_uniform_block_size)
+					* u64::from(blocks)
+			})?;
+
+		Ok(Self {
+			buffer: device.create_uniform_buffer(&BufferDescriptor {
+				size,
+				profile: BufferProfile::DynamicUpload,
+				contents: BufferContents::Opaque,
+				..Default::default()
+			})?,
+			alignment: limits.uniform_buffer_offset_alignment.max(1),
+			head: 0,
+			frame_start: 0,
+			wrapped: false,
+			frame: 0
+		})
+	}
+
+	/** The buffer holding the data of the arena. */
+	pub fn buffer(&self) -> &UniformBuffer {
+		&self.buffer
+	}
+
+	/** Number of bytes the arena can hold in a single frame. */
+	pub fn capacity(&self) -> u32 {
+		self.buffer.len()
+	}
+
+	/** Starts a new frame, invalidating every slice pushed so far. */
+	pub fn reset(&mut self) {
+		self.frame_start = self.head;
+		self.wrapped = false;
+		self.frame += 1;
+	}
+
+	/** Pushes the given value to the arena. */
+	pub fn push<T: bytemuck::Pod>(&mut self, value: &T) -> Result<UniformSlice, BufferError> {
+		self.push_bytes(bytemuck::bytes_of(value))
+	}
+
+	/** Pushes all of the given values to the arena, one after the other, as
+	 * a single slice, such as for a block holding an array of them. */
+	pub fn push_slice<T: bytemuck::Pod>(&mut self, values: &[T]) -> Result<UniformSlice, BufferError> {
+		self.push_bytes(bytemuck::cast_slice(values))
+	}
+
+	/** Pushes the given bytes to the arena. Pushing nothing still takes up a
+	 * byte of the arena, so that every slice can be bound.
+	 *
+	 * # Errors
+	 * This function fails if the data doesn't fit in what is left of the
+	 * arena in the current frame. */
+	pub fn push_bytes(&mut self, data: &[u8]) -> Result<UniformSlice, BufferError> {
+		let len = u32::try_from(data.len()).ok()
+			.and_then(|len| NonZeroU32::new(len.max(1)));
+		let allocation = len.and_then(|len| ring_allocation(
+			self.head,
+			self.frame_start,
+			self.wrapped,
+			len.get(),
+			self.alignment,
+			self.capacity()));
+		let (len, (offset, wrapped)) = len.zip(allocation)
+			.ok_or_else(|| BufferError::LimitExceeded {
+				what: format!("a uniform arena of {} bytes has no room left \
+					for {} more bytes in the current frame",
+					self.capacity(),
+					data.len())
+			})?;
+
+		self.buffer.write(offset, data)?;
+		self.head = offset + len.get();
+		self.wrapped = wrapped;
+
+		Ok(UniformSlice {
+			offset,
+			size: len,
+			frame: self.frame
+		})
+	}
+
+	/** Binds the given slice to a uniform block. */
+	pub fn bind(&self, slice: &UniformSlice) -> UniformBind {
+		self.check(slice);
+		UniformBind::Buffer {
+			buffer: &self.buffer,
+			offset: slice.offset,
+			size: Some(slice.size)
+		}
+	}
+
+	/** Binds a range as large as the given slice at the start of the arena
+	 * to a uniform block, for it to be shifted over to slices of that same
+	 * size with dynamic offsets, given by [`offset()`].
+	 *
+	 * [`offset()`]: Self::offset */
+	pub fn bind_dynamic(&self, slice: &UniformSlice) -> UniformBind {
+		self.check(slice);
+		UniformBind::Buffer {
+			buffer: &self.buffer,
+			offset: 0,
+			size: Some(slice.size)
+		}
+	}
+
+	/** Dynamic offset shifting a range bound with [`bind_dynamic()`] over to
+	 * the given slice.
+	 *
+	 * [`bind_dynamic()`]: Self::bind_dynamic */
+	pub fn offset(&self, slice: &UniformSlice) -> u32 {
+		self.check(slice);
+		slice.offset
+	}
+
+	/** Asserts, in debug builds, that the given slice was pushed in the
+	 * current frame. */
+	fn check(&self, slice: &UniformSlice) {
+		debug_assert_eq!(slice.frame, self.frame,
+			"a slice pushed to a uniform arena {} frames ago was used after \
+			the arena got reset", self.frame.wrapping_sub(slice.frame));
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn pushes_wrap_around_the_frames_before_them() {
+		assert_eq!(ring_allocation(0, 0, false, 16, 256, 1024), Some((0, false)));
+		assert_eq!(ring_allocation(20, 0, false, 16, 256, 1024), Some((256, false)));
+		assert_eq!(ring_allocation(768, 0, false, 256, 256, 1024), Some((768, false)));
+
+		/* Pushes past the end go back to the start, as long as they don't run
+		 * into the data of the current frame. */
+		assert_eq!(ring_allocation(1000, 512, false, 100, 256, 1024), Some((0, true)));
+		assert_eq!(ring_allocation(1000, 50, false, 100, 256, 1024), None);
+		assert_eq!(ring_allocation(100, 512, true, 100, 256, 1024), Some((256, true)));
+		assert_eq!(ring_allocation(300, 512, true, 100, 256, 1024), None);
+
+		/* A frame can't take up more than the whole ring. */
+		assert_eq!(ring_allocation(0, 0, false, 2048, 256, 1024), None);
+		assert_eq!(ring_allocation(u32::MAX, 0, false, 1, 256, 1024), None);
+	}
+}
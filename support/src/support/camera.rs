@@ -1,4 +1,4 @@
-use crate::support::Matrix4;
+use crate::support::{Matrix4, Quaternion, Frustum, ClipDepth};
 
 /** This structure allows for obtaining the matrix transformation from camera
  * parameters such as position, rotation and projection type. */
@@ -15,6 +15,39 @@ pub struct Camera {
 	pub pitch: f32,
 }
 impl Camera {
+	/** Creates a new camera at the given position, with the given projection,
+	 * turned to the given orientation, as returned by [`orientation()`].
+	 *
+	 * Cameras have no roll, so any roll in the orientation is dropped. This
+	 * is what lets animation code interpolate between orientations, which
+	 * interpolating the yaw and pitch angles themselves can't do smoothly.
+	 *
+	 * [`orientation()`]: Camera::orientation */
+	pub fn from_orientation(
+		projection: Projection,
+		position: [f32; 3],
+		orientation: Quaternion) -> Self {
+
+		/* Without roll, the rotation is the pitch applied after the yaw, so its
+		 * first column and its second row leave the angles easy to pick out. */
+		let m = orientation.matrix();
+		let m = m.as_row_major_array();
+		let yaw = -f32::atan2(m[2], m[0]);
+		let pitch = -f32::atan2(m[9], m[5]);
+
+		Self {
+			projection,
+			position,
+			yaw,
+			pitch
+		}
+	}
+
+	/** The rotation this camera applies to the world, past its translation. */
+	pub fn orientation(&self) -> Quaternion {
+		Quaternion::from_euler(self.yaw, self.pitch, 0.0)
+	}
+
 	/** Calculate the composite camera transformation.
 	 *
 	 * In more technical terms, the camera transformation is responsible for
@@ -40,6 +73,12 @@ impl Camera {
 
 		matrix
 	}
+
+	/** The volume of world space this camera sees, with the given aspect
+	 * ratio, for skipping objects that are entirely out of view. */
+	pub fn frustum(&self, aspect: f32) -> Frustum {
+		Frustum::from_matrix(&self.matrix(aspect), self.projection.clip_depth())
+	}
 }
 
 /** Projection type to be applied by the camera.
@@ -129,4 +168,87 @@ impl Projection {
 					*near, *far)
 		}
 	}
+
+	/** The range of depth values this projection maps to in clip space. */
+	fn clip_depth(&self) -> ClipDepth {
+		match self {
+			Self::Perspective { .. } => ClipDepth::ZeroToOne,
+			Self::Orthographic { .. } => ClipDepth::NegativeOneToOne
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn assert_close(a: Matrix4, b: Matrix4) {
+		let pairs = a.as_row_major_array().iter().zip(b.as_row_major_array());
+		for (a, b) in pairs {
+			assert!((a - b).abs() < 1e-5, "{:?} != {:?}", a, b);
+		}
+	}
+
+	fn camera(position: [f32; 3], yaw: f32) -> Camera {
+		Camera {
+			projection: Projection::Orthographic {
+				left: -4.0,
+				right: 4.0,
+				top: 3.0,
+				bottom: -3.0,
+				near: 1.0,
+				far: 20.0
+			},
+			position,
+			yaw,
+			pitch: 0.0
+		}
+	}
+
+	#[test]
+	fn orientations_round_trip_through_cameras() {
+		let projection = camera([0.0; 3], 0.0).projection;
+		for &(yaw, pitch) in &[(0.0, 0.0), (1.0, 0.5), (-2.5, -1.2), (3.0, 1.5)] {
+			let camera = Camera { yaw, pitch, ..camera([1.0, 2.0, 3.0], 0.0) };
+
+			assert_close(
+				camera.matrix(1.0),
+				projection.matrix(1.0)
+					* camera.orientation().matrix()
+					* Matrix4::translate(-1.0, -2.0, -3.0));
+
+			let rebuilt = Camera::from_orientation(
+				projection,
+				camera.position,
+				camera.orientation());
+			assert!((rebuilt.yaw - yaw).abs() < 1e-4, "{} != {}", rebuilt.yaw, yaw);
+			assert!((rebuilt.pitch - pitch).abs() < 1e-4, "{} != {}", rebuilt.pitch, pitch);
+		}
+	}
+
+	#[test]
+	fn orthographic_cameras_compose_with_their_view() {
+		let projection = Matrix4::orthographic_projection(-4.0, 4.0, 3.0, -3.0, 1.0, 20.0);
+		let up = [0.0, 1.0, 0.0];
+
+		/* With no rotation, the camera looks towards positive z. */
+		let position = [1.0, 2.0, 3.0];
+		assert_close(
+			camera(position, 0.0).matrix(1.0),
+			projection * Matrix4::look_at(position, [1.0, 2.0, 4.0], up));
+
+		/* A quarter turn of yaw has it looking towards positive x instead. */
+		assert_close(
+			camera(position, std::f32::consts::FRAC_PI_2).matrix(1.0),
+			projection * Matrix4::look_at(position, [2.0, 2.0, 3.0], up));
+
+		/* The far top right corner of the box in front of the camera ends up
+		 * in the far top right corner of the cube. */
+		let corner = camera(position, 0.0).matrix(1.0)
+			* Matrix4::translate(5.0, 5.0, 23.0);
+		let corner = corner.as_row_major_array();
+		assert!((corner[3] - 1.0).abs() < 1e-5);
+		assert!((corner[7] - 1.0).abs() < 1e-5);
+		assert!((corner[11] - 1.0).abs() < 1e-5);
+	}
 }
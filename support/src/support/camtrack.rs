@@ -0,0 +1,486 @@
+use crate::support::{Camera, Projection};
+use std::time::Duration;
+
+/** A recorded camera path, made up of timestamped keyframes, that can be
+ * played back smoothly.
+ *
+ * Positions are interpolated using a Catmull-Rom spline over the timestamps of
+ * the keyframes, so that the velocity of the camera is continuous across them.
+ * Orientations get the same spline, built out of [`slerp()`] rather than out
+ * of linear interpolation, so that each keyframe is reached from the previous
+ * one through the shortest arc, and the camera turns without any jumps in its
+ * angular velocity either.
+ *
+ * [`slerp()`]: crate::Quaternion::slerp */
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "PascalCase")]
+pub struct CameraTrack {
+	/** Keyframes in this track, sorted by their timestamps. */
+	keyframes: Vec<CameraKeyframe>,
+}
+impl CameraTrack {
+	/** Create a new, empty camera track. */
+	pub fn new() -> Self {
+		Self {
+			keyframes: Vec::new()
+		}
+	}
+
+	/** Create a camera track from the given list of keyframes.
+	 *
+	 * The keyframes do not have to be sorted, as they get sorted by their
+	 * timestamps as part of this function. Of the keyframes sharing the same
+	 * timestamp, only the first one in the list is kept. */
+	pub fn from_keyframes(mut keyframes: Vec<CameraKeyframe>) -> Self {
+		keyframes.sort_by(|a, b| a.time.partial_cmp(&b.time)
+			.expect("camera keyframe timestamps must not be NaN"));
+		keyframes.dedup_by(|b, a| a.time == b.time);
+		Self { keyframes }
+	}
+
+	/** Load a camera track from the given JSON text, as written out by
+	 * [`to_json()`].
+	 *
+	 * [`to_json()`]: Self::to_json */
+	pub fn from_json(text: &str) -> Result<Self, serde_json::Error> {
+		let track = serde_json::from_str::<Self>(text)?;
+		Ok(Self::from_keyframes(track.keyframes))
+	}
+
+	/** Save this camera track as JSON text. */
+	pub fn to_json(&self) -> String {
+		serde_json::to_string_pretty(self)
+			.expect("camera tracks are always representable as JSON")
+	}
+
+	/** Add a snapshot of the given camera as a keyframe at the given time.
+	 *
+	 * A keyframe already present at the exact same time gets replaced. */
+	pub fn insert(&mut self, time: f32, camera: &Camera) {
+		assert!(!time.is_nan(), "camera keyframe timestamps must not be NaN");
+
+		let keyframe = CameraKeyframe {
+			time,
+			position: camera.position,
+			yaw: camera.yaw,
+			pitch: camera.pitch,
+			projection: camera.projection
+		};
+		let index = self.keyframes
+			.iter()
+			.position(|frame| frame.time >= time)
+			.unwrap_or(self.keyframes.len());
+
+		match self.keyframes.get_mut(index) {
+			Some(frame) if frame.time == time => *frame = keyframe,
+			_ => self.keyframes.insert(index, keyframe)
+		}
+	}
+
+	/** All of the keyframes in this track, sorted by their timestamps. */
+	pub fn keyframes(&self) -> &[CameraKeyframe] {
+		&self.keyframes[..]
+	}
+
+	/** Whether this track contains no keyframes at all. */
+	pub fn is_empty(&self) -> bool {
+		self.keyframes.is_empty()
+	}
+
+	/** Time at which the last keyframe of this track happens, in seconds. */
+	pub fn duration(&self) -> f32 {
+		self.keyframes.last().map(|frame| frame.time).unwrap_or(0.0)
+	}
+
+	/** Sample the state of the camera at the given time.
+	 *
+	 * Times before the first keyframe and after the last one are clamped to
+	 * those keyframes. Returns `None` if this track has no keyframes. */
+	pub fn sample(&self, time: f32) -> Option<Camera> {
+		let first = self.keyframes.first()?;
+		let last = self.keyframes.last()?;
+
+		if self.keyframes.len() == 1 || time <= first.time {
+			return Some(first.camera())
+		}
+		if time >= last.time {
+			return Some(last.camera())
+		}
+
+		/* Find the segment we're in. Given the checks above, there's always a
+		 * keyframe strictly after the one we land on. */
+		let i = self.keyframes
+			.iter()
+			.rposition(|frame| frame.time <= time)
+			.unwrap();
+		let a = &self.keyframes[i];
+		let b = &self.keyframes[i + 1];
+
+		let s = (time - a.time) / (b.time - a.time);
+
+		let position = self.spline(i, time, |frame| frame.position, |a, b, s| [
+			lerp(a[0], b[0], s),
+			lerp(a[1], b[1], s),
+			lerp(a[2], b[2], s)
+		]);
+		let orientation = self.spline(
+			i,
+			time,
+			|frame| frame.camera().orientation(),
+			|a, b, s| a.slerp(b, s));
+
+		let projection = match (a.projection, b.projection) {
+			(
+				Projection::Perspective {
+					field_of_view: fa,
+					near: na,
+					far: ra
+				},
+				Projection::Perspective {
+					field_of_view: fb,
+					near: nb,
+					far: rb
+				}
+			) => Projection::Perspective {
+				field_of_view: lerp(fa, fb, s),
+				near: lerp(na, nb, s),
+				far: lerp(ra, rb, s)
+			},
+			(
+				Projection::Orthographic {
+					left: la, right: ra,
+					top: ta, bottom: ba,
+					near: na, far: fa
+				},
+				Projection::Orthographic {
+					left: lb, right: rb,
+					top: tb, bottom: bb,
+					near: nb, far: fb
+				}
+			) => Projection::Orthographic {
+				left: lerp(la, lb, s),
+				right: lerp(ra, rb, s),
+				top: lerp(ta, tb, s),
+				bottom: lerp(ba, bb, s),
+				near: lerp(na, nb, s),
+				far: lerp(fa, fb, s)
+			},
+			/* There is no sensible way to blend between projection types, so
+			 * just hold on to the one at the start of the segment. */
+			(projection, _) => projection
+		};
+
+		Some(Camera::from_orientation(projection, position, orientation))
+	}
+
+	/** Evaluate the Catmull-Rom spline over segment `i` at the given time, for
+	 * the quantity extracted from the keyframes by `value`, using `mix` to
+	 * interpolate between two values of it by a factor.
+	 *
+	 * This uses the construction by Barry and Goldman, which only ever mixes
+	 * pairs of values, with factors taken from the timestamps of the keyframes,
+	 * so it works for anything that can be mixed, rather than just for values
+	 * that can be added together. Past the ends of the track, the segment gets
+	 * mirrored to stand in for the missing keyframe, which has the spline
+	 * leave the end going straight towards the other keyframe of the segment,
+	 * with `mix` being asked to extrapolate for it. */
+	fn spline<T, V, M>(&self, i: usize, time: f32, value: V, mix: M) -> T
+		where T: Copy,
+			V: Fn(&CameraKeyframe) -> T,
+			M: Fn(T, T, f32) -> T {
+
+		let a = &self.keyframes[i];
+		let b = &self.keyframes[i + 1];
+		let (p1, t1) = (value(a), a.time);
+		let (p2, t2) = (value(b), b.time);
+
+		let (p0, t0) = match i.checked_sub(1).map(|j| &self.keyframes[j]) {
+			Some(frame) => (value(frame), frame.time),
+			None => (mix(p1, p2, -1.0), 2.0 * t1 - t2)
+		};
+		let (p3, t3) = match self.keyframes.get(i + 2) {
+			Some(frame) => (value(frame), frame.time),
+			None => (mix(p1, p2, 2.0), 2.0 * t2 - t1)
+		};
+
+		let at = |p, q, ta: f32, tb: f32| mix(p, q, (time - ta) / (tb - ta));
+		let a1 = at(p0, p1, t0, t1);
+		let a2 = at(p1, p2, t1, t2);
+		let a3 = at(p2, p3, t2, t3);
+		let b1 = at(a1, a2, t0, t2);
+		let b2 = at(a2, a3, t1, t3);
+
+		at(b1, b2, t1, t2)
+	}
+}
+impl Default for CameraTrack {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/** A snapshot of the state of a camera at a given point in a [`CameraTrack`].
+ *
+ * [`CameraTrack`]: CameraTrack */
+#[derive(Debug, Copy, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "PascalCase")]
+pub struct CameraKeyframe {
+	/** Time at which this keyframe happens, in seconds. */
+	pub time: f32,
+	/** Position of the camera, in world space. */
+	pub position: [f32; 3],
+	/** Yaw rotation angle of the camera, in radians. */
+	pub yaw: f32,
+	/** Pitch rotation angle of the camera, in radians. */
+	pub pitch: f32,
+	/** Projection applied by the camera. */
+	pub projection: Projection,
+}
+impl CameraKeyframe {
+	/** The camera described by this keyframe. */
+	pub fn camera(&self) -> Camera {
+		Camera {
+			projection: self.projection,
+			position: self.position,
+			yaw: self.yaw,
+			pitch: self.pitch
+		}
+	}
+}
+
+/** Records a [`CameraTrack`] out of the camera of a running application, as
+ * it gets moved around, taking a keyframe of it whenever asked to.
+ *
+ * Keyframes are timestamped with the time passed since the first one, so the
+ * track plays back at the same pace the camera was moved at while recording.
+ *
+ * [`CameraTrack`]: CameraTrack */
+#[derive(Debug, Clone, Default)]
+pub struct CameraRecorder {
+	/** Track recorded so far. */
+	track: CameraTrack,
+	/** Time passed since the first keyframe, if any. */
+	time: Duration,
+}
+impl CameraRecorder {
+	/** Create a new recorder, with no keyframes in it. */
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/** Let the given amount of time pass. Time only starts counting from the
+	 * first keyframe onwards. */
+	pub fn advance(&mut self, delta: Duration) {
+		if !self.track.is_empty() {
+			self.time += delta;
+		}
+	}
+
+	/** Take a keyframe of the given camera, at the current time. */
+	pub fn keyframe(&mut self, camera: &Camera) {
+		self.track.insert(self.time.as_secs_f32(), camera);
+	}
+
+	/** The track recorded so far. */
+	pub fn track(&self) -> &CameraTrack {
+		&self.track
+	}
+}
+
+/** Linear interpolation between two values. */
+fn lerp(a: f32, b: f32, s: f32) -> f32 {
+	a + (b - a) * s
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::support::Quaternion;
+	use std::f32::consts::PI;
+
+	const PROJECTION: Projection = Projection::Perspective {
+		field_of_view: std::f32::consts::FRAC_PI_2,
+		near: 0.1,
+		far: 100.0
+	};
+
+	fn keyframe(time: f32, x: f32, yaw: f32) -> CameraKeyframe {
+		CameraKeyframe {
+			time,
+			position: [x, 0.0, 0.0],
+			yaw,
+			pitch: 0.0,
+			projection: PROJECTION
+		}
+	}
+
+	/** Signed difference that takes angle `a` to angle `b` through the
+	 * shortest arc between them. */
+	fn shortest_arc(a: f32, b: f32) -> f32 {
+		(b - a + PI).rem_euclid(2.0 * PI) - PI
+	}
+
+	/** Angular velocity of a camera turning from orientation `a` to `b` over
+	 * the given time, as an axis scaled by the angle per second. */
+	fn angular_velocity(a: Quaternion, b: Quaternion, time: f32) -> [f32; 3] {
+		let [x, y, z, w] = *(b * a.conjugate()).as_array();
+		let sign = if w < 0.0 { -1.0 } else { 1.0 };
+		[x, y, z].map(|c| 2.0 * sign * c / time)
+	}
+
+	#[test]
+	fn hits_keyframes() {
+		let track = CameraTrack::from_keyframes(vec![
+			keyframe(0.0, 0.0, 0.0),
+			keyframe(1.0, 2.0, 0.5),
+			keyframe(3.0, 1.0, 1.0),
+		]);
+
+		assert_eq!(track.duration(), 3.0);
+		for frame in track.keyframes() {
+			let camera = track.sample(frame.time).unwrap();
+			assert!((camera.position[0] - frame.position[0]).abs() < 1e-5);
+			assert!((camera.yaw - frame.yaw).abs() < 1e-5);
+		}
+	}
+
+	#[test]
+	fn velocity_is_continuous() {
+		let track = CameraTrack::from_keyframes(vec![
+			keyframe(0.0, 0.0, 0.0),
+			keyframe(1.0, 4.0, 0.0),
+			keyframe(1.5, 1.0, 0.0),
+			keyframe(4.0, 3.0, 0.0),
+		]);
+
+		let h = 1e-3;
+		for frame in &track.keyframes()[1..3] {
+			let t = frame.time;
+			let x = |t| track.sample(t).unwrap().position[0];
+
+			let before = (x(t) - x(t - h)) / h;
+			let after = (x(t + h) - x(t)) / h;
+			assert!(
+				(before - after).abs() < 0.05,
+				"velocity pops at {}: {} vs {}", t, before, after);
+		}
+	}
+
+	#[test]
+	fn angular_velocity_is_continuous() {
+		let mut frames = vec![
+			keyframe(0.0, 0.0, 0.0),
+			keyframe(1.0, 0.0, 1.2),
+			keyframe(1.5, 0.0, -2.5),
+			keyframe(4.0, 0.0, 0.5),
+		];
+		frames[1].pitch = 0.6;
+		frames[2].pitch = -0.4;
+		let track = CameraTrack::from_keyframes(frames);
+
+		let h = 1e-3;
+		for frame in &track.keyframes()[1..3] {
+			let t = frame.time;
+			let q = |t| track.sample(t).unwrap().orientation();
+
+			let before = angular_velocity(q(t - h), q(t), h);
+			let after = angular_velocity(q(t), q(t + h), h);
+			for (before, after) in before.iter().zip(&after) {
+				assert!(
+					(before - after).abs() < 0.05,
+					"angular velocity pops at {}: {:?} vs {:?}", t, before, after);
+			}
+		}
+	}
+
+	#[test]
+	fn angles_take_shortest_arc() {
+		/* Going from just under +π to just over -π should cross the seam,
+		 * never passing through zero. */
+		let track = CameraTrack::from_keyframes(vec![
+			keyframe(0.0, 0.0, PI - 0.1),
+			keyframe(1.0, 0.0, -PI + 0.1),
+		]);
+
+		for i in 0..=10 {
+			let yaw = track.sample(i as f32 / 10.0).unwrap().yaw;
+			assert!(yaw.abs() > PI - 0.1 - 1e-4, "yaw {} went the long way", yaw);
+		}
+
+		let mid = track.sample(0.5).unwrap().yaw;
+		assert!((mid.abs() - PI).abs() < 1e-4);
+	}
+
+	#[test]
+	fn angles_unwrap_across_multiple_turns() {
+		/* Each step is a quarter turn, so the camera should spin steadily in
+		 * the same direction, even though the stored values wrap around. */
+		let track = CameraTrack::from_keyframes(vec![
+			keyframe(0.0, 0.0, 0.0),
+			keyframe(1.0, 0.0, PI / 2.0),
+			keyframe(2.0, 0.0, -PI),
+			keyframe(3.0, 0.0, -PI / 2.0),
+			keyframe(4.0, 0.0, 0.0),
+		]);
+
+		let mut last = track.sample(0.0).unwrap().yaw;
+		for i in 1..=40 {
+			let yaw = track.sample(i as f32 / 10.0).unwrap().yaw;
+			let step = shortest_arc(last, yaw);
+
+			assert!(step > 0.0, "yaw stepped backwards by {}", step);
+			last = yaw;
+		}
+	}
+
+	#[test]
+	fn clamps_outside_of_range() {
+		let track = CameraTrack::from_keyframes(vec![
+			keyframe(1.0, 1.0, 0.0),
+			keyframe(2.0, 2.0, 0.0),
+		]);
+
+		assert_eq!(track.sample(-5.0).unwrap().position[0], 1.0);
+		assert_eq!(track.sample(50.0).unwrap().position[0], 2.0);
+		assert!(CameraTrack::new().sample(0.0).is_none());
+	}
+
+	#[test]
+	fn recorder_counts_time_from_the_first_keyframe() {
+		let camera = keyframe(0.0, 1.0, 0.5).camera();
+		let mut recorder = CameraRecorder::new();
+
+		recorder.advance(Duration::from_secs(10));
+		recorder.keyframe(&camera);
+		recorder.advance(Duration::from_millis(1500));
+		recorder.keyframe(&Camera { position: [2.0, 0.0, 0.0], ..camera });
+
+		let times = recorder.track()
+			.keyframes()
+			.iter()
+			.map(|frame| frame.time)
+			.collect::<Vec<_>>();
+		assert_eq!(times, [0.0, 1.5]);
+	}
+
+	#[test]
+	fn round_trips_through_json() {
+		let track = CameraTrack::from_keyframes(vec![
+			keyframe(0.0, 0.0, 0.0),
+			keyframe(2.0, 3.0, -1.0),
+		]);
+		assert_eq!(CameraTrack::from_json(&track.to_json()).unwrap(), track);
+
+		/* Hand edited files may list keyframes out of order. */
+		let text = r#"{ "Keyframes": [
+			{ "Time": 2.0, "Position": [0, 0, 0], "Yaw": 0, "Pitch": 0,
+				"Projection": { "Type": "Perspective", "FieldOfView": 1,
+				"Near": 0.1, "Far": 10 } },
+			{ "Time": 1.0, "Position": [0, 0, 0], "Yaw": 0, "Pitch": 0,
+				"Projection": { "Type": "Perspective", "FieldOfView": 1,
+				"Near": 0.1, "Far": 10 } }
+		] }"#;
+		let track = CameraTrack::from_json(text).unwrap();
+		assert_eq!(track.keyframes()[0].time, 1.0);
+	}
+}
@@ -0,0 +1,453 @@
+use std::convert::TryInto;
+
+/** Block compressed encodings texels may be stored in, all of which pack 4x4
+ * blocks of texels into a fixed number of bytes. */
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum BlockEncoding {
+	/** BC1, also known as DXT1, with a single bit of alpha. */
+	Bc1,
+	/** BC2, also known as DXT3, with four bits of explicit alpha. */
+	Bc2,
+	/** BC3, also known as DXT5, with interpolated alpha. */
+	Bc3,
+	/** ETC2 with opaque RGB texels. */
+	Etc2Rgb,
+	/** ETC2 with alpha compressed with EAC. */
+	Etc2Rgba,
+}
+impl BlockEncoding {
+	/** Width and height of a block of texels. */
+	pub const BLOCK_SIZE: u32 = 4;
+
+	/** Number of bytes taken up by a single block of texels. */
+	pub fn block_bytes(&self) -> usize {
+		match self {
+			Self::Bc1 | Self::Etc2Rgb => 8,
+			Self::Bc2 | Self::Bc3 | Self::Etc2Rgba => 16,
+		}
+	}
+
+	/** Number of bytes taken up by an image of the given size, which takes up
+	 * whole blocks, even along its edges. */
+	pub fn len(&self, width: u32, height: u32) -> usize {
+		let blocks = |axis: u32| axis.div_ceil(Self::BLOCK_SIZE) as usize;
+		blocks(width) * blocks(height) * self.block_bytes()
+	}
+
+	/** Decompresses an image of the given size in this encoding into RGBA
+	 * texels with eight bits for every channel, in the same order as the
+	 * blocks, with rows going the same way. Returns `None` if the data is not
+	 * exactly as long as the image needs it to be.
+	 *
+	 * This is meant for contexts that can't sample the encoding themselves,
+	 * and is nowhere near as fast as having the device do it. */
+	pub fn decompress(&self, width: u32, height: u32, data: &[u8]) -> Option<Vec<u8>> {
+		if data.len() != self.len(width, height) { return None }
+
+		let size = Self::BLOCK_SIZE;
+		let blocks_x = width.div_ceil(size);
+		let mut texels = vec![0; width as usize * height as usize * 4];
+		for (index, block) in data.chunks_exact(self.block_bytes()).enumerate() {
+			let decoded = self.decode_block(block);
+			let bx = index as u32 % blocks_x * size;
+			let by = index as u32 / blocks_x * size;
+
+			/* Blocks along the edges may run past the image. */
+			for y in 0..size.min(height - by) {
+				for x in 0..size.min(width - bx) {
+					let target = (((by + y) * width + bx + x) * 4) as usize;
+					texels[target..target + 4]
+						.copy_from_slice(&decoded[(y * size + x) as usize]);
+				}
+			}
+		}
+
+		Some(texels)
+	}
+
+	/** Decodes a single block into its texels, row by row. */
+	fn decode_block(&self, block: &[u8]) -> [[u8; 4]; 16] {
+		match self {
+			Self::Bc1 => bc_color(block, true),
+			Self::Bc2 => {
+				let mut texels = bc_color(&block[8..], false);
+				let alpha = u64::from_le_bytes(block[..8].try_into().unwrap());
+				for (i, texel) in texels.iter_mut().enumerate() {
+					texel[3] = ((alpha >> (i * 4)) & 0xf) as u8 * 17;
+				}
+				texels
+			},
+			Self::Bc3 => {
+				let mut texels = bc_color(&block[8..], false);
+				let alpha = bc3_alpha(&block[..8]);
+				for (texel, alpha) in texels.iter_mut().zip(&alpha) {
+					texel[3] = *alpha;
+				}
+				texels
+			},
+			Self::Etc2Rgb => etc2_color(block),
+			Self::Etc2Rgba => {
+				let mut texels = etc2_color(&block[8..]);
+				let alpha = eac_alpha(&block[..8]);
+				for (texel, alpha) in texels.iter_mut().zip(&alpha) {
+					texel[3] = *alpha;
+				}
+				texels
+			},
+		}
+	}
+}
+
+/** Expands a color in RGB565 to eight bits per channel. */
+fn rgb565(color: u16) -> [u8; 3] {
+	let r = ((color >> 11) & 0x1f) as u8;
+	let g = ((color >> 5) & 0x3f) as u8;
+	let b = (color & 0x1f) as u8;
+	[(r << 3) | (r >> 2), (g << 2) | (g >> 4), (b << 3) | (b >> 2)]
+}
+
+/** Decodes the color half of a BC block. Only BC1 blocks may use the mode
+ * with three colors and a transparent one, which the other encodings always
+ * read as having four colors. */
+fn bc_color(block: &[u8], punch_through: bool) -> [[u8; 4]; 16] {
+	let c0 = u16::from_le_bytes([block[0], block[1]]);
+	let c1 = u16::from_le_bytes([block[2], block[3]]);
+	let indices = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
+
+	let [a, b] = [rgb565(c0), rgb565(c1)];
+	let mix = |wa: u16, wb: u16| {
+		let mut color = [0, 0, 0, u8::MAX];
+		for c in 0..3 {
+			color[c] = ((u16::from(a[c]) * wa + u16::from(b[c]) * wb) / (wa + wb)) as u8;
+		}
+		color
+	};
+	let palette = if c0 > c1 || !punch_through {
+		[mix(1, 0), mix(0, 1), mix(2, 1), mix(1, 2)]
+	} else {
+		[mix(1, 0), mix(0, 1), mix(1, 1), [0; 4]]
+	};
+
+	let mut texels = [[0; 4]; 16];
+	for (i, texel) in texels.iter_mut().enumerate() {
+		*texel = palette[((indices >> (i * 2)) & 3) as usize];
+	}
+	texels
+}
+
+/** Decodes the interpolated alpha of a BC3 block. */
+fn bc3_alpha(block: &[u8]) -> [u8; 16] {
+	let (a0, a1) = (u32::from(block[0]), u32::from(block[1]));
+	let mut palette = [a0, a1, 0, 0, 0, 0, 0, u32::from(u8::MAX)];
+	if a0 > a1 {
+		for (i, entry) in (1..7).zip(&mut palette[2..]) {
+			*entry = ((7 - i) * a0 + i * a1) / 7;
+		}
+	} else {
+		for (i, entry) in (1..5).zip(&mut palette[2..]) {
+			*entry = ((5 - i) * a0 + i * a1) / 5;
+		}
+	}
+
+	let mut bits = [0; 8];
+	bits[..6].copy_from_slice(&block[2..8]);
+	let indices = u64::from_le_bytes(bits);
+
+	let mut alpha = [0; 16];
+	for (i, alpha) in alpha.iter_mut().enumerate() {
+		*alpha = palette[((indices >> (i * 3)) & 7) as usize] as u8;
+	}
+	alpha
+}
+
+/** Modifiers of the sub-blocks of ETC1 blocks, for each of their tables. */
+const ETC1_MODIFIERS: [[i32; 2]; 8] = [
+	[2, 8], [5, 17], [9, 29], [13, 42],
+	[18, 60], [24, 80], [33, 106], [47, 183],
+];
+
+/** Distances between the paint colors of ETC2 blocks in the T and H modes. */
+const ETC2_DISTANCES: [i32; 8] = [3, 6, 11, 16, 23, 32, 41, 64];
+
+/** Modifiers of EAC blocks, for each of their tables. */
+const EAC_MODIFIERS: [[i32; 8]; 16] = [
+	[-3, -6, -9, -15, 2, 5, 8, 14],
+	[-3, -7, -10, -13, 2, 6, 9, 12],
+	[-2, -5, -8, -13, 1, 4, 7, 12],
+	[-2, -4, -6, -13, 1, 3, 5, 12],
+	[-3, -6, -8, -12, 2, 5, 7, 11],
+	[-3, -7, -9, -11, 2, 6, 8, 10],
+	[-4, -7, -8, -11, 3, 6, 7, 10],
+	[-3, -5, -8, -11, 2, 4, 7, 10],
+	[-2, -6, -8, -10, 1, 5, 7, 9],
+	[-2, -5, -8, -10, 1, 4, 7, 9],
+	[-2, -4, -8, -10, 1, 3, 7, 9],
+	[-2, -5, -7, -10, 1, 4, 6, 9],
+	[-3, -4, -7, -10, 2, 3, 6, 9],
+	[-1, -2, -3, -10, 0, 1, 2, 9],
+	[-4, -6, -8, -9, 3, 5, 7, 8],
+	[-3, -5, -7, -9, 2, 4, 6, 8],
+];
+
+/** Bits of a value from the given highest bit down to the given lowest one. */
+fn bits(value: u64, high: u32, low: u32) -> u32 {
+	((value >> low) & ((1 << (high - low + 1)) - 1)) as u32
+}
+
+/** Expands a value of the given number of bits to eight bits. */
+fn expand(value: u32, bits: u32) -> i32 {
+	((value << (8 - bits)) | (value >> (2 * bits - 8))) as i32
+}
+
+/** Clamps a channel to the range of a byte. */
+fn clamp(value: i32) -> u8 {
+	value.clamp(0, i32::from(u8::MAX)) as u8
+}
+
+/** Adds the given offset to every channel of a color. */
+fn offset(color: [i32; 3], by: i32) -> [u8; 4] {
+	[clamp(color[0] + by), clamp(color[1] + by), clamp(color[2] + by), u8::MAX]
+}
+
+/** Decodes an opaque ETC2 color block, which may be in any of the modes of
+ * ETC1 or in one of the T, H and planar modes ETC2 adds on top of them. */
+fn etc2_color(block: &[u8]) -> [[u8; 4]; 16] {
+	let block = u64::from_be_bytes(block[..8].try_into().unwrap());
+
+	/* Texels are indexed by column, with the most significant bits of all of
+	 * their indices coming before the least significant ones. */
+	let index = |x: u32, y: u32| {
+		let k = x * 4 + y;
+		(bits(block, k + 16, k + 16) << 1) | bits(block, k, k)
+	};
+	let mut texels = [[0; 4]; 16];
+
+	if bits(block, 33, 33) == 0 {
+		/* Individual mode, with two colors of four bits. */
+		let base = [
+			[expand(bits(block, 63, 60), 4), expand(bits(block, 55, 52), 4), expand(bits(block, 47, 44), 4)],
+			[expand(bits(block, 59, 56), 4), expand(bits(block, 51, 48), 4), expand(bits(block, 43, 40), 4)],
+		];
+		etc1_subblocks(block, base, index, &mut texels);
+		return texels
+	}
+
+	let r = bits(block, 63, 59) as i32;
+	let g = bits(block, 55, 51) as i32;
+	let b = bits(block, 47, 43) as i32;
+	let delta = |high: u32, low: u32| ((bits(block, high, low) as i32) << 29) >> 29;
+	let (r2, g2, b2) = (r + delta(58, 56), g + delta(50, 48), b + delta(42, 40));
+	let overflows = |channel: i32| !(0..32).contains(&channel);
+
+	if overflows(r2) {
+		/* T mode, with a single color for one paint and the other three spread
+		 * around a second color. */
+		let c1 = [
+			expand((bits(block, 60, 59) << 2) | bits(block, 57, 56), 4),
+			expand(bits(block, 55, 52), 4),
+			expand(bits(block, 51, 48), 4),
+		];
+		let c2 = [
+			expand(bits(block, 47, 44), 4),
+			expand(bits(block, 43, 40), 4),
+			expand(bits(block, 39, 36), 4),
+		];
+		let d = ETC2_DISTANCES[((bits(block, 35, 34) << 1) | bits(block, 32, 32)) as usize];
+		let paint = [offset(c1, 0), offset(c2, d), offset(c2, 0), offset(c2, -d)];
+		for_texels(&mut texels, |x, y| paint[index(x, y) as usize]);
+	} else if overflows(g2) {
+		/* H mode, with two paints spread around each of two colors. */
+		let c1 = [
+			bits(block, 62, 59),
+			(bits(block, 58, 56) << 1) | bits(block, 52, 52),
+			(bits(block, 51, 51) << 3) | bits(block, 49, 47),
+		];
+		let c2 = [bits(block, 46, 43), bits(block, 42, 39), bits(block, 38, 35)];
+		let packed = |c: [u32; 3]| (c[0] << 8) | (c[1] << 4) | c[2];
+		let d = ETC2_DISTANCES[((bits(block, 34, 34) << 2)
+			| (bits(block, 32, 32) << 1)
+			| (packed(c1) >= packed(c2)) as u32) as usize];
+
+		let c1 = [expand(c1[0], 4), expand(c1[1], 4), expand(c1[2], 4)];
+		let c2 = [expand(c2[0], 4), expand(c2[1], 4), expand(c2[2], 4)];
+		let paint = [offset(c1, d), offset(c1, -d), offset(c2, d), offset(c2, -d)];
+		for_texels(&mut texels, |x, y| paint[index(x, y) as usize]);
+	} else if overflows(b2) {
+		/* Planar mode, with colors interpolated across the block from the
+		 * origin, a horizontal and a vertical color. */
+		let origin = [
+			expand(bits(block, 62, 57), 6),
+			expand((bits(block, 56, 56) << 6) | bits(block, 54, 49), 7),
+			expand((bits(block, 48, 48) << 5) | (bits(block, 44, 43) << 3) | bits(block, 41, 39), 6),
+		];
+		let horizontal = [
+			expand((bits(block, 38, 34) << 1) | bits(block, 32, 32), 6),
+			expand(bits(block, 31, 25), 7),
+			expand(bits(block, 24, 19), 6),
+		];
+		let vertical = [
+			expand(bits(block, 18, 13), 6),
+			expand(bits(block, 12, 6), 7),
+			expand(bits(block, 5, 0), 6),
+		];
+		for_texels(&mut texels, |x, y| {
+			let (x, y) = (x as i32, y as i32);
+			let channel = |c: usize| clamp((x * (horizontal[c] - origin[c])
+				+ y * (vertical[c] - origin[c])
+				+ 4 * origin[c] + 2) >> 2);
+			[channel(0), channel(1), channel(2), u8::MAX]
+		});
+	} else {
+		/* Differential mode, with a color of five bits and a second one given
+		 * relative to it. */
+		let base = [
+			[expand(r as u32, 5), expand(g as u32, 5), expand(b as u32, 5)],
+			[expand(r2 as u32, 5), expand(g2 as u32, 5), expand(b2 as u32, 5)],
+		];
+		etc1_subblocks(block, base, index, &mut texels);
+	}
+
+	texels
+}
+
+/** Decodes the two sub-blocks of an ETC1 block, in either the individual or
+ * the differential mode, whose base colors have already been worked out. */
+fn etc1_subblocks(
+	block: u64,
+	base: [[i32; 3]; 2],
+	index: impl Fn(u32, u32) -> u32,
+	texels: &mut [[u8; 4]; 16]) {
+
+	let tables = [bits(block, 39, 37) as usize, bits(block, 36, 34) as usize];
+	let flip = bits(block, 32, 32) == 1;
+	for_texels(texels, |x, y| {
+		let sub = if flip { (y >= 2) as usize } else { (x >= 2) as usize };
+		let [small, large] = ETC1_MODIFIERS[tables[sub]];
+		let modifier = match index(x, y) {
+			0 => small,
+			1 => large,
+			2 => -small,
+			_ => -large
+		};
+		offset(base[sub], modifier)
+	});
+}
+
+/** Decodes the alpha of an EAC block. */
+fn eac_alpha(block: &[u8]) -> [u8; 16] {
+	let base = i32::from(block[0]);
+	let multiplier = i32::from(block[1] >> 4);
+	let modifiers = EAC_MODIFIERS[usize::from(block[1] & 0xf)];
+	let block = u64::from_be_bytes(block[..8].try_into().unwrap());
+
+	let mut alpha = [0; 16];
+	for y in 0..4 {
+		for x in 0..4 {
+			let k = x * 4 + y;
+			let index = bits(block, 47 - k * 3, 45 - k * 3) as usize;
+			alpha[(y * 4 + x) as usize] = clamp(base + modifiers[index] * multiplier);
+		}
+	}
+	alpha
+}
+
+/** Sets every texel of a block, row by row, to what the given function gives
+ * for its position. */
+fn for_texels(texels: &mut [[u8; 4]; 16], mut f: impl FnMut(u32, u32) -> [u8; 4]) {
+	for y in 0..4 {
+		for x in 0..4 {
+			texels[(y * 4 + x) as usize] = f(x, y);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn bc1_modes() {
+		/* Pure red and pure blue, with four colors, picking red, blue and the
+		 * two thirds in between along the first row. */
+		let block = [0x00, 0xf8, 0x1f, 0x00, 0b1110_0100, 0, 0, 0];
+		let texels = BlockEncoding::Bc1.decompress(4, 4, &block).unwrap();
+		assert_eq!(&texels[..16], &[
+			255, 0, 0, 255,
+			0, 0, 255, 255,
+			170, 0, 85, 255,
+			85, 0, 170, 255,
+		]);
+
+		/* The same colors the other way around switch to three colors and a
+		 * transparent one, which only BC1 has. */
+		let block = [0x1f, 0x00, 0x00, 0xf8, 0b1110_0100, 0, 0, 0];
+		let texels = BlockEncoding::Bc1.decompress(4, 4, &block).unwrap();
+		assert_eq!(&texels[8..16], &[127, 0, 127, 255, 0, 0, 0, 0]);
+	}
+
+	#[test]
+	fn bc3_alpha_interpolates() {
+		let mut block = [0u8; 16];
+		block[0] = 255;
+		block[1] = 0;
+		/* Indices 0, 1, 2 and 7 for the first four texels. */
+		block[2] = 0b10_001_000;
+		block[3] = 0b0000_1110;
+		let texels = BlockEncoding::Bc3.decompress(4, 4, &block).unwrap();
+		let alpha = texels.chunks_exact(4).map(|texel| texel[3]).collect::<Vec<_>>();
+		assert_eq!(&alpha[..4], &[255, 0, 218, 36]);
+		assert!(alpha[4..].iter().all(|alpha| *alpha == 255));
+	}
+
+	#[test]
+	fn etc1_individual_mode() {
+		/* Red 8 and green 4 on the left and red 15 on the right, side by side,
+		 * with the smallest table, the first column picking the large negative
+		 * modifier and every other one the small positive one. */
+		let block = [0x8f, 0x40, 0x00, 0x00, 0x00, 0x0f, 0x00, 0x0f];
+		let texels = BlockEncoding::Etc2Rgb.decompress(4, 4, &block).unwrap();
+		assert_eq!(&texels[..8], &[128, 60, 0, 255, 138, 70, 2, 255]);
+		assert_eq!(&texels[12..16], &[255, 2, 2, 255]);
+	}
+
+	#[test]
+	fn etc2_planar_mode() {
+		/* Neither red nor green overflow, but blue does, with every color
+		 * set to zero apart from the origin, which has full red. */
+		let r = 0b11_1111u64;
+		let mut block = (r << 57) | (0b1 << 33);
+		/* Blue of five bits at 31 plus a delta of one. */
+		block |= (0b1_1111 << 43) | (0b001 << 40);
+		let texels = BlockEncoding::Etc2Rgb.decompress(4, 4, &block.to_be_bytes()).unwrap();
+
+		/* The origin color gets full weight at the corner, and fades away
+		 * toward the horizontal and vertical colors. */
+		assert_eq!(texels[0], 255);
+		assert_eq!(texels[3], 255);
+		assert!(texels[4] < texels[0]);
+	}
+
+	#[test]
+	fn eac_alpha_multiplies_modifiers() {
+		let mut block = [0u8; 16];
+		block[0] = 100;
+		/* A multiplier of two, with the first table. */
+		block[1] = 2 << 4;
+		/* Index 7 for the first texel, which is the top left one. */
+		block[2] = 0b1110_0000;
+		let alpha = eac_alpha(&block[..8]);
+		assert_eq!(alpha[0], 100 + 14 * 2);
+		assert_eq!(alpha[1], 100 - 3 * 2);
+	}
+
+	#[test]
+	fn edges_are_cropped() {
+		let block = [0x00, 0xf8, 0x00, 0xf8, 0, 0, 0, 0];
+		let data = block.repeat(4);
+		let texels = BlockEncoding::Bc1.decompress(5, 6, &data).unwrap();
+		assert_eq!(texels.len(), 5 * 6 * 4);
+		assert!(texels.chunks_exact(4).all(|texel| texel == [255, 0, 0, 255]));
+
+		assert!(BlockEncoding::Bc1.decompress(5, 6, &data[1..]).is_none());
+	}
+}
@@ -0,0 +1,281 @@
+use gavle::*;
+use std::borrow::Cow;
+use std::convert::TryFrom;
+
+/** Name of the binding of the uniform block of the stencil view shader. */
+const STENCIL_VIEW_BINDING: &str = "rc_stencil_view";
+
+/** Colors the stencil ramp goes through, from its lowest nonzero value to its
+ * highest. */
+const RAMP: [[f32; 3]; 5] = [
+	[0.0, 0.0, 1.0],
+	[0.0, 1.0, 1.0],
+	[0.0, 1.0, 0.0],
+	[1.0, 1.0, 0.0],
+	[1.0, 0.0, 0.0],
+];
+
+/** Debugging visualizations that may replace the regular output of a
+ * renderer. */
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+pub enum DebugView {
+	/** Show the regular output. */
+	#[default]
+	Off,
+	/** Show the contents of the stencil buffer, through a [`StencilView`]. */
+	ShowStencil,
+}
+impl DebugView {
+	/** The view that comes after this one, going back to [`DebugView::Off`]
+	 * after the last one, for cycling through them with a single key. */
+	pub fn next(self) -> Self {
+		match self {
+			Self::Off => Self::ShowStencil,
+			Self::ShowStencil => Self::Off
+		}
+	}
+}
+
+/** Color the stencil view shows the given stencil value in, when values
+ * from one to `range` are spread over the whole ramp. Zero is always black,
+ * and values past the range share the color at the end of the ramp.
+ *
+ * The ramp goes from blue, through cyan, green and yellow, to red. */
+pub fn stencil_ramp(value: u8, range: u8) -> [f32; 3] {
+	if value == 0 { return [0.0; 3] }
+
+	let t = (f32::from(value) / f32::from(range.max(1))).min(1.0);
+	let x = t * (RAMP.len() - 1) as f32;
+	let i = (x as usize).min(RAMP.len() - 2);
+	let f = x - i as f32;
+
+	let mut color = [0.0; 3];
+	for c in 0..3 {
+		color[c] = RAMP[i][c] + (RAMP[i + 1][c] - RAMP[i][c]) * f;
+	}
+	color
+}
+
+/** Layout of the uniform block of the stencil view shader. */
+#[derive(Debug, Copy, Clone, PartialEq, bytemuck::Zeroable, bytemuck::Pod)]
+#[repr(C)]
+struct StencilViewUniforms {
+	/** Stencil value at the end of the ramp, in the first component. */
+	range: [f32; 4],
+}
+
+/** Helper for showing the contents of a stencil buffer on screen.
+ *
+ * The stencil values of a framebuffer get extracted into a texture of this
+ * helper with [`prepare()`], through [`Framebuffer::extract_stencil()`], and
+ * then drawn over the whole of another framebuffer with [`draw()`], colored
+ * by [`stencil_ramp()`]. Extraction takes hundreds of draw calls, so this is
+ * only meant for debugging.
+ *
+ * [`prepare()`]: Self::prepare
+ * [`draw()`]: Self::draw
+ * [`Framebuffer::extract_stencil()`]: gavle::Framebuffer::extract_stencil */
+pub struct StencilView {
+	/** Fullscreen ramp pipeline. */
+	pipeline: RenderPipeline,
+	/** Uniform buffer holding the range of the ramp. */
+	uniforms: UniformBuffer,
+	/** Texture the stencil values are extracted into, along with the binding
+	 * group it is in, once there has been a prepared pass. */
+	extracted: Option<(Texture, UniformGroup)>,
+	/** Stencil value shown at the end of the ramp. */
+	range: u8,
+}
+impl StencilView {
+	/** Layout of the vertex buffer, of which there is none, as the vertices
+	 * are generated in the vertex shader. */
+	const LAYOUT: VertexBufferLayout<'static> = VertexBufferLayout {
+		array_stride: 0,
+		attributes: &[]
+	};
+
+	/** Create a new stencil view on the given device, spreading the whole
+	 * range of stencil values over the ramp. */
+	pub fn new(device: &Device) -> Result<Self, StencilViewError> {
+		let uniforms = device.create_uniform_buffer(
+			&BufferDescriptor {
+				size: u32::try_from(std::mem::size_of::<StencilViewUniforms>()).unwrap(),
+				profile: BufferProfile::DynamicUpload,
+				contents: BufferContents::F32,
+				..Default::default()
+			})?;
+
+		let vertex = device.create_vertex_shader(
+			ShaderSource::Glsl(Cow::Borrowed(include_str!("debugview/vert.glsl"))))?;
+		let fragment = device.create_fragment_shader(
+			ShaderSource::Glsl(Cow::Borrowed(include_str!("debugview/stencil.glsl"))))?;
+
+		let pipeline = device.create_render_pipeline(
+			&RenderPipelineDescriptor::new(&vertex, &Self::LAYOUT)
+				.with_fragment(&fragment)
+				.with_depth(DepthStencilState::DISABLED))?;
+		pipeline.set_label("stencil view");
+
+		Ok(Self {
+			pipeline,
+			uniforms,
+			extracted: None,
+			range: u8::MAX
+		})
+	}
+
+	/** Stencil value shown at the end of the ramp. */
+	pub fn range(&self) -> u8 {
+		self.range
+	}
+
+	/** Spread the stencil values from one to the given value over the whole
+	 * ramp. Effects that only use the first few stencil values are easier to
+	 * tell apart with a shorter range. */
+	pub fn set_range(&mut self, range: u8) {
+		self.range = range.max(1)
+	}
+
+	/** Extract the stencil values of the given framebuffer for the next call
+	 * to [`draw()`]. The size must be the one of the depth stencil attachment
+	 * of the framebuffer, and the texture the values get extracted into is
+	 * only recreated when it changes.
+	 *
+	 * This must be called outside of a render pass.
+	 *
+	 * [`draw()`]: Self::draw */
+	pub fn prepare(
+		&mut self,
+		device: &Device,
+		framebuffer: &Framebuffer,
+		width: u32,
+		height: u32)
+		-> Result<(), StencilViewError> {
+
+		let uniforms = StencilViewUniforms {
+			range: [f32::from(self.range), 0.0, 0.0, 0.0]
+		};
+		let data = bytemuck::bytes_of(&uniforms);
+		let slice = self.uniforms.slice(..);
+		let mut map = slice.try_map_mut(BufferLoadOp::DontCare, MapMode::Orphan)
+			.unwrap();
+		map[..data.len()].copy_from_slice(data);
+		drop(map);
+
+		let extent = TextureExtent::D2 { width, height };
+		let stale = match &self.extracted {
+			Some((texture, _)) => texture.extent() != extent,
+			None => true
+		};
+		if stale {
+			let texture = device.create_texture(&TextureDescriptor {
+				extent,
+				format: TextureFormat::Rgba8Unorm,
+				mip: Mipmap::None,
+				swizzle: None
+			})?;
+			texture.set_label("stencil view");
+
+			let bind = device.create_uniform_bind_group(
+				&UniformGroupDescriptor {
+					entries: &[
+						UniformGroupEntry {
+							binding: STENCIL_VIEW_BINDING.into(),
+							kind: UniformBind::Buffer {
+								buffer: &self.uniforms,
+								offset: 0,
+								size: None
+							}
+						},
+						UniformGroupEntry {
+							binding: "tt_stencil".into(),
+							kind: UniformBind::Texture {
+								texture: &texture,
+								far: TextureFilter::Nearest,
+								near: TextureFilter::Nearest,
+								address_mode: AddressMode::ClampToEdge,
+								anisotropy_clamp: None,
+								sampler: None
+							}
+						},
+					]
+				})?;
+			self.extracted = Some((texture, bind));
+		}
+
+		let (texture, _) = self.extracted.as_ref().unwrap();
+		framebuffer.extract_stencil(device, texture)?;
+
+		Ok(())
+	}
+
+	/** Draw the stencil values extracted by the last call to [`prepare()`]
+	 * over the given viewport of the given framebuffer. Nothing is drawn if
+	 * there has been no call to it yet.
+	 *
+	 * [`prepare()`]: Self::prepare */
+	pub fn draw(&self, device: &Device, target: &Framebuffer, viewport: Viewport) {
+		let bind = match &self.extracted {
+			Some((_, bind)) => bind,
+			None => return
+		};
+
+		let mut pass = device.start_render_pass(
+			&RenderPassDescriptor {
+				pipeline: &self.pipeline,
+				framebuffer: target
+			});
+		pass.set_viewport(viewport)
+			.expect("the viewport must fit in the limits of the context");
+		pass.set_bind_group(bind);
+		pass.draw(0..3, 1)
+			.expect("drawing a triangle with no vertex buffer can't fail");
+	}
+}
+
+/** Errors that may happen while creating or preparing a stencil view. */
+#[derive(Debug, thiserror::Error)]
+pub enum StencilViewError {
+	#[error("could not create stencil view buffers: {0}")]
+	Buffer(#[from] BufferError),
+	#[error("could not create stencil view textures: {0}")]
+	Texture(#[from] TextureError),
+	#[error("could not create stencil view shaders: {0}")]
+	Shader(#[from] ShaderError),
+	#[error("could not create stencil view pipeline: {0}")]
+	Pipeline(#[from] RenderPipelineError),
+	#[error("could not bind the extracted stencil values: {0}")]
+	BindGroup(#[from] BindGroupError),
+	#[error("could not extract the stencil values: {0}")]
+	Framebuffer(#[from] FramebufferError),
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn zero_is_black() {
+		assert_eq!(stencil_ramp(0, 255), [0.0; 3]);
+		assert_eq!(stencil_ramp(0, 1), [0.0; 3]);
+	}
+
+	#[test]
+	fn ramp_spans_the_range() {
+		assert_eq!(stencil_ramp(4, 4), [1.0, 0.0, 0.0]);
+		assert_eq!(stencil_ramp(2, 4), [0.0, 1.0, 0.0]);
+		assert_eq!(stencil_ramp(1, 8), [0.0, 0.5, 1.0]);
+
+		/* Values past the range stay at the end of the ramp. */
+		assert_eq!(stencil_ramp(200, 4), [1.0, 0.0, 0.0]);
+		/* A range of zero behaves like a range of one. */
+		assert_eq!(stencil_ramp(1, 0), [1.0, 0.0, 0.0]);
+	}
+
+	#[test]
+	fn views_cycle_back_to_off() {
+		let mut view = DebugView::default();
+		for _ in 0..2 { view = view.next() }
+		assert_eq!(view, DebugView::Off);
+	}
+}
@@ -0,0 +1,38 @@
+#version 300 es
+precision highp float;
+
+layout(std140) uniform rc_stencil_view
+{
+/* Stencil value shown at the far end of the ramp, in the first component. */
+    vec4 Range;
+};
+
+uniform highp sampler2D tt_stencil;
+
+in vec2 vs_uv;
+
+out vec4 color;
+
+/* Must be kept in sync with `stencil_ramp()` in debugview.rs. */
+vec3 ramp(float t)
+{
+    const vec3 stops[5] = vec3[5](
+        vec3(0.0, 0.0, 1.0),
+        vec3(0.0, 1.0, 1.0),
+        vec3(0.0, 1.0, 0.0),
+        vec3(1.0, 1.0, 0.0),
+        vec3(1.0, 0.0, 0.0));
+
+    float x = clamp(t, 0.0, 1.0) * 4.0;
+    int i = min(int(x), 3);
+    return mix(stops[i], stops[i + 1], x - float(i));
+}
+
+void main()
+{
+    float value = floor(texture(tt_stencil, vs_uv).r * 255.0 + 0.5);
+    if (value == 0.0)
+        color = vec4(0.0, 0.0, 0.0, 1.0);
+    else
+        color = vec4(ramp(value / max(Range.x, 1.0)), 1.0);
+}
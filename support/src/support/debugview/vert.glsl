@@ -0,0 +1,13 @@
+#version 300 es
+precision highp float;
+
+out vec2 vs_uv;
+
+/* Single triangle covering the whole target, built from the vertex index. */
+void main()
+{
+    vec2 uv = vec2(float((gl_VertexID & 1) << 1), float(gl_VertexID & 2));
+
+    vs_uv = uv;
+    gl_Position = vec4(uv * 2.0 - 1.0, 0.0, 1.0);
+}
@@ -0,0 +1,209 @@
+use crate::support::{Matrix4, Vec3, Vec4};
+
+/** Range of depth values clip space gets mapped to by a projection, which
+ * decides where its near plane lies in clip space. */
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ClipDepth {
+	/** The near plane is at a depth of zero, and the far plane at one, as
+	 * done by [`Matrix4::rectilinear_projection()`].
+	 *
+	 * [`Matrix4::rectilinear_projection()`]: crate::Matrix4::rectilinear_projection */
+	ZeroToOne,
+	/** The near plane is at a depth of minus one, and the far plane at one,
+	 * as done by [`Matrix4::orthographic_projection()`].
+	 *
+	 * [`Matrix4::orthographic_projection()`]: crate::Matrix4::orthographic_projection */
+	NegativeOneToOne,
+}
+
+/** Volume of world space that ends up in front of a camera, bounded by six
+ * planes, which is used to skip drawing objects that can't be seen at all.
+ *
+ * Checks against the frustum are conservative: everything they reject is
+ * out of view, but some of what they accept, such as large objects near its
+ * corners, might not be. */
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Frustum {
+	/** Left, right, bottom, top, near and far planes, in that order, as the
+	 * `[a, b, c, d]` coefficients of `ax + by + cz + d = 0`, with normals of
+	 * unit length pointing into the frustum. */
+	planes: [Vec4; 6],
+}
+impl Frustum {
+	/** Extracts the frustum of the given view-projection matrix, which maps
+	 * depth to the given range in clip space. */
+	pub fn from_matrix(matrix: &Matrix4, depth: ClipDepth) -> Self {
+		let m = matrix.as_row_major_array();
+		let row = |i: usize| Vec4::new(m[i * 4], m[i * 4 + 1], m[i * 4 + 2], m[i * 4 + 3]);
+		let (x, y, z, w) = (row(0), row(1), row(2), row(3));
+
+		/* A point is inside whenever each of its clip space coordinates lies
+		 * between -w and w, except for depth, whose lower bound depends on
+		 * the projection. */
+		let near = match depth {
+			ClipDepth::ZeroToOne => z,
+			ClipDepth::NegativeOneToOne => w + z
+		};
+		let planes = [w + x, w - x, w + y, w - y, near, w - z];
+
+		Self {
+			planes: planes.map(|plane| {
+				let length = plane.truncate().length();
+				if length == 0.0 { plane } else { plane / length }
+			})
+		}
+	}
+
+	/** The planes bounding this frustum, in the order left, right, bottom,
+	 * top, near and far, as the `[a, b, c, d]` coefficients of the plane
+	 * `ax + by + cz + d = 0`, with normals of unit length pointing inwards. */
+	pub fn planes(&self) -> &[Vec4; 6] {
+		&self.planes
+	}
+
+	/** Signed distance of the given point from each of the planes, which is
+	 * positive on the inner side of them. */
+	fn distances(&self, point: Vec3) -> impl Iterator<Item = f32> + '_ {
+		self.planes.iter().map(move |plane| plane.dot(point.extend(1.0)))
+	}
+
+	/** Whether any part of the sphere with the given center and radius may be
+	 * inside of this frustum. */
+	pub fn contains_sphere(&self, center: Vec3, radius: f32) -> bool {
+		self.distances(center).all(|distance| distance >= -radius)
+	}
+
+	/** Whether any part of the axis-aligned box with the given minimum and
+	 * maximum corners may be inside of this frustum. */
+	pub fn contains_aabb(&self, min: Vec3, max: Vec3) -> bool {
+		/* The box is only outside of a plane when even the corner furthest
+		 * along the normal of that plane is. */
+		self.planes.iter().all(|plane| {
+			let pick = |normal: f32, min: f32, max: f32| if normal >= 0.0 { max } else { min };
+			let corner = Vec3::new(
+				pick(plane.x(), min.x(), max.x()),
+				pick(plane.y(), min.y(), max.y()),
+				pick(plane.z(), min.z(), max.z()));
+
+			plane.dot(corner.extend(1.0)) >= 0.0
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::support::{Camera, Projection};
+
+	/** Camera at the origin, looking towards positive z, with a square field
+	 * of view of ninety degrees, so that its side planes are at `x = ±z` and
+	 * `y = ±z`, and depth going from one to ten. */
+	fn perspective() -> Frustum {
+		Camera {
+			projection: Projection::Perspective {
+				field_of_view: std::f32::consts::FRAC_PI_2,
+				near: 1.0,
+				far: 10.0
+			},
+			position: [0.0; 3],
+			yaw: 0.0,
+			pitch: 0.0
+		}.frustum(1.0)
+	}
+
+	/** Camera at the origin, looking towards positive z, seeing the box going
+	 * from `[-4, -3, 1]` to `[4, 3, 20]`. */
+	fn orthographic(yaw: f32) -> Frustum {
+		Camera {
+			projection: Projection::Orthographic {
+				left: -4.0,
+				right: 4.0,
+				top: 3.0,
+				bottom: -3.0,
+				near: 1.0,
+				far: 20.0
+			},
+			position: [0.0; 3],
+			yaw,
+			pitch: 0.0
+		}.frustum(1.0)
+	}
+
+	fn point(frustum: &Frustum, x: f32, y: f32, z: f32) -> bool {
+		frustum.contains_sphere(Vec3::new(x, y, z), 0.0)
+	}
+
+	#[test]
+	fn perspective_planes_bound_points() {
+		let frustum = perspective();
+		assert!(point(&frustum, 0.0, 0.0, 5.0));
+
+		/* Near and far planes. */
+		assert!(point(&frustum, 0.0, 0.0, 1.1));
+		assert!(!point(&frustum, 0.0, 0.0, 0.9));
+		assert!(point(&frustum, 0.0, 0.0, 9.9));
+		assert!(!point(&frustum, 0.0, 0.0, 10.1));
+		assert!(!point(&frustum, 0.0, 0.0, -5.0));
+
+		/* Side planes. */
+		for &(x, y) in &[(1.0, 0.0), (-1.0, 0.0), (0.0, 1.0), (0.0, -1.0)] {
+			assert!(point(&frustum, x * 4.9, y * 4.9, 5.0));
+			assert!(!point(&frustum, x * 5.1, y * 5.1, 5.0));
+		}
+	}
+
+	#[test]
+	fn orthographic_planes_bound_points() {
+		let frustum = orthographic(0.0);
+		assert!(point(&frustum, 0.0, 0.0, 10.0));
+
+		assert!(point(&frustum, 0.0, 0.0, 1.1));
+		assert!(!point(&frustum, 0.0, 0.0, 0.9));
+		assert!(point(&frustum, 0.0, 0.0, 19.9));
+		assert!(!point(&frustum, 0.0, 0.0, 20.1));
+
+		assert!(point(&frustum, 3.9, 0.0, 10.0));
+		assert!(!point(&frustum, 4.1, 0.0, 10.0));
+		assert!(point(&frustum, -3.9, 0.0, 10.0));
+		assert!(!point(&frustum, -4.1, 0.0, 10.0));
+		assert!(point(&frustum, 0.0, 2.9, 10.0));
+		assert!(!point(&frustum, 0.0, 3.1, 10.0));
+		assert!(point(&frustum, 0.0, -2.9, 10.0));
+		assert!(!point(&frustum, 0.0, -3.1, 10.0));
+
+		/* A quarter turn of yaw has the camera looking towards positive x. */
+		let frustum = orthographic(std::f32::consts::FRAC_PI_2);
+		assert!(point(&frustum, 10.0, 0.0, 0.0));
+		assert!(!point(&frustum, -10.0, 0.0, 0.0));
+		assert!(!point(&frustum, 0.0, 0.0, 10.0));
+	}
+
+	#[test]
+	fn spheres_touching_a_plane_are_inside() {
+		let frustum = perspective();
+
+		/* The center is half a unit past the x = z plane, which puts it at a
+		 * distance of a half over the square root of two from it. */
+		let center = Vec3::new(5.5, 0.0, 5.0);
+		assert!(frustum.contains_sphere(center, 0.4));
+		assert!(!frustum.contains_sphere(center, 0.3));
+
+		let center = Vec3::new(0.0, 0.0, 10.5);
+		assert!(frustum.contains_sphere(center, 0.6));
+		assert!(!frustum.contains_sphere(center, 0.4));
+	}
+
+	#[test]
+	fn boxes_straddling_a_plane_are_inside() {
+		let frustum = perspective();
+		let aabb = |min: [f32; 3], max: [f32; 3]|
+			frustum.contains_aabb(Vec3::from(min), Vec3::from(max));
+
+		assert!(aabb([-1.0, -1.0, 4.0], [1.0, 1.0, 6.0]));
+		assert!(aabb([4.0, -1.0, 4.0], [6.0, 1.0, 6.0]));
+		assert!(aabb([-100.0; 3], [100.0; 3]));
+		assert!(!aabb([5.5, -1.0, 4.0], [7.0, 1.0, 5.0]));
+		assert!(!aabb([-1.0, -1.0, 10.5], [1.0, 1.0, 12.0]));
+		assert!(!aabb([-1.0, -1.0, -3.0], [1.0, 1.0, 0.5]));
+	}
+}
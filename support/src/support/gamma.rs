@@ -0,0 +1,157 @@
+use gavle::*;
+use std::borrow::Cow;
+
+/** Encodes a linear color value to sRGB, clamping it to `[0, 1]` first. */
+pub fn encode_srgb(linear: f32) -> f32 {
+	let t = linear.clamp(0.0, 1.0);
+	if t <= 0.003_130_8 {
+		t * 12.92
+	} else {
+		1.055 * t.powf(1.0 / 2.4) - 0.055
+	}
+}
+
+/** Full screen pass drawing the linear colors of a texture to a framebuffer,
+ * encoded to sRGB in the shader.
+ *
+ * This is the fallback for when the device can't get colors encoded to sRGB
+ * as they get written to the default framebuffer, as described by
+ * [`Device::set_srgb_encoding`]: the scene gets drawn, in linear values, into
+ * an offscreen texture, which then gets drawn to the screen with this pass.
+ * Whether it is needed is told by [`is_needed()`].
+ *
+ * The source may be in any format, including [`TextureFormat::Srgb8Alpha8`],
+ * which gets decoded back to linear values as it gets sampled, but keep in
+ * mind that eight bits of linear values aren't enough to keep dark colors
+ * from banding.
+ *
+ * [`Device::set_srgb_encoding`]: gavle::Device::set_srgb_encoding
+ * [`is_needed()`]: Self::is_needed */
+pub struct GammaPass {
+	/** Fullscreen encoding pipeline. */
+	pipeline: RenderPipeline,
+	/** Binding group, once there has been a source to bind to it. */
+	bind: Option<UniformGroup>,
+}
+impl GammaPass {
+	/** Layout of the vertex buffer, of which there is none, as the vertices
+	 * are generated in the vertex shader. */
+	const LAYOUT: VertexBufferLayout<'static> = VertexBufferLayout {
+		array_stride: 0,
+		attributes: &[]
+	};
+
+	/** Whether colors drawn to the default framebuffer of the given device
+	 * have to go through this pass to get encoded to sRGB, because it doesn't
+	 * store them with the sRGB encoding. Where it does, either the context
+	 * always encodes them, or [`Device::set_srgb_encoding`] turns it on.
+	 *
+	 * [`Device::set_srgb_encoding`]: gavle::Device::set_srgb_encoding */
+	pub fn is_needed(device: &Device) -> bool {
+		!device.information().capabilities.srgb_default_framebuffer
+	}
+
+	/** Create a new gamma pass on the given device. */
+	pub fn new(device: &Device) -> Result<Self, GammaPassError> {
+		/* Just like the color grade, this draws a single triangle over the
+		 * whole target. */
+		let vertex = device.create_vertex_shader(
+			ShaderSource::Glsl(Cow::Borrowed(include_str!("debugview/vert.glsl"))))?;
+		let fragment = device.create_fragment_shader(
+			ShaderSource::Glsl(Cow::Borrowed(include_str!("gamma/encode.glsl"))))?;
+
+		let pipeline = device.create_render_pipeline(
+			&RenderPipelineDescriptor::new(&vertex, &Self::LAYOUT)
+				.with_fragment(&fragment)
+				.with_depth(DepthStencilState::DISABLED))?;
+		pipeline.set_label("gamma pass");
+
+		Ok(Self {
+			pipeline,
+			bind: None
+		})
+	}
+
+	/** Draw the colors of the given texture, encoded to sRGB, over the given
+	 * viewport of the given framebuffer. The source texture must not be
+	 * attached to the framebuffer.
+	 *
+	 * This must be called outside of a render pass. */
+	pub fn draw(
+		&mut self,
+		device: &Device,
+		source: &Texture,
+		target: &Framebuffer,
+		viewport: Viewport) -> Result<(), GammaPassError> {
+
+		match &self.bind {
+			Some(bind) => bind.replace_texture("tt_source", source)?,
+			None => {
+				let bind = device.create_uniform_bind_group(
+					&UniformGroupDescriptor {
+						entries: &[
+							UniformGroupEntry {
+								binding: "tt_source".into(),
+								kind: UniformBind::Texture {
+									texture: source,
+									far: TextureFilter::Nearest,
+									near: TextureFilter::Nearest,
+									address_mode: AddressMode::ClampToEdge,
+									anisotropy_clamp: None,
+									sampler: None
+								}
+							},
+						]
+					})?;
+				self.bind = Some(bind);
+			}
+		}
+
+		let mut pass = device.start_render_pass(
+			&RenderPassDescriptor {
+				pipeline: &self.pipeline,
+				framebuffer: target
+			});
+		pass.set_viewport(viewport)?;
+		pass.set_bind_group(self.bind.as_ref().unwrap());
+		pass.draw(0..3, 1)
+			.expect("drawing a triangle with no vertex buffer can't fail");
+
+		Ok(())
+	}
+}
+
+/** Errors that may happen while creating or drawing a gamma pass. */
+#[derive(Debug, thiserror::Error)]
+pub enum GammaPassError {
+	#[error("could not create gamma pass shaders: {0}")]
+	Shader(#[from] ShaderError),
+	#[error("could not create gamma pass pipeline: {0}")]
+	Pipeline(#[from] RenderPipelineError),
+	#[error("could not bind the source of the gamma pass: {0}")]
+	BindGroup(#[from] BindGroupError),
+	#[error("could not set the viewport of the gamma pass: {0}")]
+	Viewport(#[from] ViewportError),
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn encodes_the_srgb_curve() {
+		assert_eq!(encode_srgb(0.0), 0.0);
+		assert!((encode_srgb(1.0) - 1.0).abs() < 1e-6);
+
+		/* Both pieces of the curve meet at the threshold. */
+		let low = 0.003_130_8 * 12.92;
+		let high = 1.055 * 0.003_130_8f32.powf(1.0 / 2.4) - 0.055;
+		assert!((low - high).abs() < 1e-4);
+
+		/* Middle gray in linear values comes out at about 188 of 255. */
+		assert_eq!((encode_srgb(0.5) * 255.0).round() as u8, 188);
+
+		assert_eq!(encode_srgb(-1.0), 0.0);
+		assert_eq!(encode_srgb(2.0), encode_srgb(1.0));
+	}
+}
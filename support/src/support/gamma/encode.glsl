@@ -0,0 +1,26 @@
+#version 300 es
+precision highp float;
+
+uniform highp sampler2D tt_source;
+
+in vec2 vs_uv;
+
+out vec4 color;
+
+/* Encodes a linear color value to sRGB, with the piecewise curve of the
+ * standard, rather than a plain power curve.
+ *
+ * Must be kept in sync with `encode_srgb()` in gamma.rs. */
+vec3 encode_srgb(vec3 linear)
+{
+    vec3 t = clamp(linear, 0.0, 1.0);
+    vec3 low = t * 12.92;
+    vec3 high = 1.055 * pow(t, vec3(1.0 / 2.4)) - 0.055;
+    return mix(high, low, vec3(lessThanEqual(t, vec3(0.0031308))));
+}
+
+void main()
+{
+    vec4 source = texture(tt_source, vs_uv);
+    color = vec4(encode_srgb(source.rgb), source.a);
+}
@@ -0,0 +1,539 @@
+use crate::support::{Matrix4, Mesh, MeshOptions, SceneImage, Transform, Vertex};
+use gavle::*;
+use std::convert::TryFrom;
+use std::path::Path;
+
+/** Name of the sampler the albedo texture of a glTF material is bound to. */
+pub const GLTF_ALBEDO_BINDING: &str = "tt_tex_albedo";
+
+/** Name of the sampler the normal map of a glTF material is bound to. */
+pub const GLTF_NORMAL_BINDING: &str = "tt_tex_normal";
+
+/** Name of the sampler the metallic and roughness texture of a glTF material
+ * is bound to, which holds the roughness in its green channel and the
+ * metalness in its blue channel. */
+pub const GLTF_METALLIC_ROUGHNESS_BINDING: &str = "tt_tex_metallic_roughness";
+
+/** Model loaded from a glTF 2.0 file, with its geometry converted to meshes of
+ * [`Vertex`], and its images decoded, but with nothing uploaded to a device
+ * yet, which is done with [`upload()`].
+ *
+ * Texture coordinates in glTF start at the top left corner of the image, and
+ * images get uploaded from their top row down, so they need no flipping.
+ *
+ * [`Vertex`]: crate::Vertex
+ * [`upload()`]: Self::upload */
+pub struct GltfModel {
+	/** Meshes of the model, in the order they are in the file. */
+	pub meshes: Vec<GltfMesh>,
+	/** Materials of the model, in the order they are in the file. */
+	pub materials: Vec<GltfMaterial>,
+	/** Images of the model, in the order they are in the file, as RGBA. */
+	pub images: Vec<SceneImage>,
+	/** Nodes of the default scene of the model, parents before their
+	 * children. */
+	pub nodes: Vec<GltfNode>,
+}
+
+/** Mesh of a glTF model, which is made of primitives that may each have a
+ * different material. */
+pub struct GltfMesh {
+	/** Name of the mesh, if it has one. */
+	pub name: Option<String>,
+	/** Triangles of the mesh, grouped by material. */
+	pub primitives: Vec<GltfPrimitive>,
+}
+
+/** Part of a glTF mesh drawn with a single material. */
+pub struct GltfPrimitive {
+	/** Geometry of the primitive. */
+	pub mesh: Mesh,
+	/** Index of the material of the primitive, if it has one. */
+	pub material: Option<usize>,
+}
+
+/** Metallic roughness material of a glTF model. Textures refer to the images
+ * of the model, and get multiplied by the factors of the material. */
+#[derive(Debug, Clone, PartialEq)]
+pub struct GltfMaterial {
+	/** Name of the material, if it has one. */
+	pub name: Option<String>,
+	/** Factor the albedo is multiplied by, in linear RGBA. */
+	pub base_color: [f32; 4],
+	/** Factor the metalness is multiplied by. */
+	pub metallic: f32,
+	/** Factor the roughness is multiplied by. */
+	pub roughness: f32,
+	/** Index of the image holding the albedo, in sRGB. */
+	pub albedo: Option<usize>,
+	/** Index of the image holding the normal map, in tangent space. */
+	pub normal: Option<usize>,
+	/** Index of the image holding the roughness in its green channel and the
+	 * metalness in its blue channel. */
+	pub metallic_roughness: Option<usize>,
+}
+
+/** Node in the hierarchy of a glTF scene. */
+#[derive(Debug, Clone, PartialEq)]
+pub struct GltfNode {
+	/** Name of the node, if it has one. */
+	pub name: Option<String>,
+	/** Index of the parent of the node, which comes before it in the list of
+	 * nodes, if it has one. */
+	pub parent: Option<usize>,
+	/** Index of the mesh drawn at the node, if any. */
+	pub mesh: Option<usize>,
+	/** Placement of the node relative to its parent. */
+	pub transform: Transform,
+	/** Transformation from the space of the node into world space, which
+	 * includes the transformations of all of its parents. */
+	pub world: Matrix4,
+}
+
+impl GltfModel {
+	/** Loads the glTF or binary glTF file at the given path, along with the
+	 * buffers and images it refers to, which are looked for relative to it. */
+	pub fn open<P: AsRef<Path>>(path: P, options: &MeshOptions) -> Result<Self, GltfError> {
+		let (document, buffers, images) = ::gltf::import(path)
+			.map_err(|what| GltfError::Import { what: what.to_string() })?;
+		Self::from_document(&document, &buffers, images, options)
+	}
+
+	/** Loads a glTF or binary glTF file from the given bytes. Buffers and
+	 * images must be embedded in it, as there is nowhere to look for them. */
+	pub fn from_slice(data: &[u8], options: &MeshOptions) -> Result<Self, GltfError> {
+		let (document, buffers, images) = ::gltf::import_slice(data)
+			.map_err(|what| GltfError::Import { what: what.to_string() })?;
+		Self::from_document(&document, &buffers, images, options)
+	}
+
+	/** Converts the contents of an imported document. */
+	fn from_document(
+		document: &::gltf::Document,
+		buffers: &[::gltf::buffer::Data],
+		images: Vec<::gltf::image::Data>,
+		options: &MeshOptions) -> Result<Self, GltfError> {
+
+		let images = images.into_iter()
+			.enumerate()
+			.map(|(index, image)| rgba(image)
+				.ok_or(GltfError::UnsupportedImage { index }))
+			.collect::<Result<Vec<_>, _>>()?;
+
+		let meshes = document.meshes()
+			.map(|mesh| load_mesh(&mesh, buffers, options))
+			.collect::<Result<Vec<_>, _>>()?;
+
+		let materials = document.materials()
+			.map(|material| {
+				let pbr = material.pbr_metallic_roughness();
+				GltfMaterial {
+					name: material.name().map(str::to_owned),
+					base_color: pbr.base_color_factor(),
+					metallic: pbr.metallic_factor(),
+					roughness: pbr.roughness_factor(),
+					albedo: pbr.base_color_texture()
+						.map(|info| info.texture().source().index()),
+					normal: material.normal_texture()
+						.map(|info| info.texture().source().index()),
+					metallic_roughness: pbr.metallic_roughness_texture()
+						.map(|info| info.texture().source().index())
+				}
+			})
+			.collect();
+
+		let mut nodes = Vec::new();
+		let scene = document.default_scene().or_else(|| document.scenes().next());
+		if let Some(scene) = scene {
+			for node in scene.nodes() {
+				flatten(&node, None, &mut nodes);
+			}
+		}
+
+		Ok(Self {
+			meshes,
+			materials,
+			images,
+			nodes
+		})
+	}
+
+	/** Uploads the meshes and the images of this model to the given device,
+	 * binding the textures of every primitive into a group of its own.
+	 *
+	 * Albedo textures get stored in the sRGB format where the device has it,
+	 * and every other texture is stored as linear data. Primitives without
+	 * some of the textures get a single texel in their place, which leaves
+	 * the factors of the material as they are, and gives a flat normal. */
+	pub fn upload(&self, device: &Device) -> Result<LoadedGltf, GltfError> {
+		let srgb = if device.information().supports_texture_format(TextureFormat::Srgb8Alpha8) {
+			TextureFormat::Srgb8Alpha8
+		} else {
+			TextureFormat::Rgba8Unorm
+		};
+
+		/* Images may be used as more than one kind of texture, so they get
+		 * uploaded in every format they are used in, but only once, and so do
+		 * the texels standing in for missing images. */
+		let mut textures = Vec::<(Result<usize, [u8; 4]>, TextureFormat, Texture)>::new();
+		let mut texture = |image: Option<usize>, format: TextureFormat, fallback: [u8; 4]|
+			-> Result<usize, GltfError> {
+
+			let key = image.ok_or(fallback);
+			let found = textures.iter()
+				.position(|(other, other_format, _)| key == *other && format == *other_format);
+			if let Some(found) = found { return Ok(found) }
+
+			let (width, height, texels) = match image {
+				Some(index) => {
+					let image = self.images.get(index)
+						.ok_or(GltfError::MissingImage { index })?;
+					(image.width, image.height, &image.texels[..])
+				},
+				None => (1, 1, &fallback[..])
+			};
+			let uploaded = device.create_texture_with_data(
+				&TextureDescriptor {
+					extent: TextureExtent::D2 { width, height },
+					format,
+					mip: Mipmap::Automatic { filter: FilterType::Triangle },
+					swizzle: None
+				},
+				texels)?;
+
+			textures.push((key, format, uploaded));
+			Ok(textures.len() - 1)
+		};
+
+		let default = GltfMaterial {
+			name: None,
+			base_color: [1.0; 4],
+			metallic: 1.0,
+			roughness: 1.0,
+			albedo: None,
+			normal: None,
+			metallic_roughness: None
+		};
+
+		let mut primitives = Vec::new();
+		for mesh in &self.meshes {
+			let mut uploaded = Vec::with_capacity(mesh.primitives.len());
+			for primitive in &mesh.primitives {
+				let material = match primitive.material {
+					Some(index) => self.materials.get(index)
+						.ok_or(GltfError::MissingMaterial { index })?,
+					None => &default
+				};
+
+				let albedo = texture(material.albedo, srgb, [255; 4])?;
+				let normal = texture(material.normal, TextureFormat::Rgba8Unorm, [128, 128, 255, 255])?;
+				let metallic_roughness = texture(
+					material.metallic_roughness,
+					TextureFormat::Rgba8Unorm,
+					[255; 4])?;
+
+				uploaded.push((primitive, [albedo, normal, metallic_roughness]));
+			}
+			primitives.push(uploaded);
+		}
+
+		let mut meshes = Vec::with_capacity(primitives.len());
+		for uploaded in primitives {
+			let mut loaded = Vec::with_capacity(uploaded.len());
+			for (primitive, [albedo, normal, metallic_roughness]) in uploaded {
+				let vertices = primitive.mesh.vertices();
+				let indices = primitive.mesh.indices();
+				let index_count = u32::try_from(indices.len())
+					.map_err(|_| GltfError::TooLarge)?;
+
+				let vertices = device.create_vertex_buffer_with_data(
+					&BufferDescriptor {
+						size: buffer_size_of::<Vertex>(vertices.len())?,
+						profile: BufferProfile::StaticUpload,
+						contents: BufferContents::Opaque,
+						..Default::default()
+					},
+					bytemuck::cast_slice(vertices))?;
+				let indices = device.create_index_buffer_with_data(
+					&BufferDescriptor {
+						size: buffer_size_of::<u32>(indices.len())?,
+						profile: BufferProfile::StaticUpload,
+						contents: BufferContents::Opaque,
+						..Default::default()
+					},
+					bytemuck::cast_slice(indices))?;
+
+				let group = device.create_uniform_bind_group(
+					&UniformGroupDescriptor {
+						entries: &[
+							bind(GLTF_ALBEDO_BINDING, &textures[albedo].2),
+							bind(GLTF_NORMAL_BINDING, &textures[normal].2),
+							bind(
+								GLTF_METALLIC_ROUGHNESS_BINDING,
+								&textures[metallic_roughness].2),
+						]
+					})?;
+
+				loaded.push(LoadedGltfPrimitive {
+					vertices,
+					indices,
+					index_count,
+					material: primitive.material,
+					group
+				});
+			}
+			meshes.push(loaded);
+		}
+
+		let instances = self.nodes.iter()
+			.filter_map(|node| node.mesh.map(|mesh| (mesh, node.world)))
+			.collect();
+
+		Ok(LoadedGltf {
+			meshes,
+			textures: textures.into_iter().map(|(_, _, texture)| texture).collect(),
+			instances
+		})
+	}
+}
+
+/** Primitive of a glTF model uploaded to a device, which may be drawn with
+ * any pipeline taking vertices of type [`Vertex`] and 32-bit indices, and
+ * sampling the textures bound under [`GLTF_ALBEDO_BINDING`],
+ * [`GLTF_NORMAL_BINDING`] and [`GLTF_METALLIC_ROUGHNESS_BINDING`].
+ *
+ * [`Vertex`]: crate::Vertex */
+pub struct LoadedGltfPrimitive {
+	/** Vertices of the primitive. */
+	pub vertices: VertexBuffer,
+	/** Indices of the primitive. */
+	pub indices: IndexBuffer,
+	/** Number of indices in the primitive. */
+	pub index_count: u32,
+	/** Index of the material of the primitive in the model, if any. */
+	pub material: Option<usize>,
+	/** Group binding the textures of the material of the primitive. */
+	pub group: UniformGroup,
+}
+
+/** glTF model uploaded to a device. */
+pub struct LoadedGltf {
+	/** Primitives of every mesh of the model, in the same order as in it. */
+	pub meshes: Vec<Vec<LoadedGltfPrimitive>>,
+	/** Textures shared by the primitives. */
+	pub textures: Vec<Texture>,
+	/** Meshes drawn by the nodes of the scene, by their index, along with
+	 * their transformation into world space. */
+	pub instances: Vec<(usize, Matrix4)>,
+}
+
+/** Binds the given texture for sampling with linear filtering and repeating
+ * texture coordinates, like the textures of scene files. */
+fn bind<'a>(binding: &'static str, texture: &'a Texture) -> UniformGroupEntry<'a> {
+	UniformGroupEntry {
+		binding: binding.into(),
+		kind: UniformBind::Texture {
+			texture,
+			far: TextureFilter::Linear,
+			near: TextureFilter::Linear,
+			address_mode: AddressMode::Repeat,
+			anisotropy_clamp: None,
+			sampler: None
+		}
+	}
+}
+
+/** Converts the primitives of a glTF mesh to meshes. Primitives made of
+ * points or lines have no faces to convert, and are left out. */
+fn load_mesh(
+	mesh: &::gltf::Mesh,
+	buffers: &[::gltf::buffer::Data],
+	options: &MeshOptions) -> Result<GltfMesh, GltfError> {
+
+	let name = mesh.name().map(str::to_owned);
+	let failed = |what: String| GltfError::Mesh {
+		mesh: name.clone().unwrap_or_else(|| format!("#{}", mesh.index())),
+		what
+	};
+
+	let mut primitives = Vec::new();
+	for primitive in mesh.primitives() {
+		if primitive.mode() != ::gltf::mesh::Mode::Triangles {
+			log::warn!(target: "support::gltf",
+				"Leaving out a primitive of mesh {:?} drawn as {:?}, as only \
+				triangle lists are supported",
+				name, primitive.mode());
+			continue
+		}
+
+		let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+		let positions = reader.read_positions()
+			.ok_or_else(|| failed("a primitive has no positions".to_owned()))?
+			.collect::<Vec<_>>();
+		let normals = reader.read_normals()
+			.map(|normals| normals.collect::<Vec<_>>());
+		let texture = reader.read_tex_coords(0)
+			.map(|texture| texture.into_f32().collect::<Vec<_>>());
+		let tangents = reader.read_tangents()
+			.map(|tangents| tangents.collect::<Vec<_>>());
+		let indices = match reader.read_indices() {
+			Some(indices) => indices.into_u32().collect(),
+			None => (0..u32::try_from(positions.len()).map_err(|_| GltfError::TooLarge)?)
+				.collect::<Vec<_>>()
+		};
+
+		let (mesh, warnings) = Mesh::from_indexed(
+			&positions,
+			normals.as_deref(),
+			texture.as_deref(),
+			tangents.as_deref(),
+			&indices,
+			options).map_err(|what| failed(what.to_string()))?;
+		for warning in warnings {
+			log::warn!(target: "support::gltf", "{}", warning);
+		}
+
+		primitives.push(GltfPrimitive {
+			mesh,
+			material: primitive.material().index()
+		});
+	}
+
+	Ok(GltfMesh {
+		name,
+		primitives
+	})
+}
+
+/** Appends the given node and all of its children to the list of nodes,
+ * parents first. */
+fn flatten(node: &::gltf::Node, parent: Option<usize>, nodes: &mut Vec<GltfNode>) {
+	let (translation, rotation, scale) = node.transform().decomposed();
+	let transform = transform(translation, rotation, scale);
+	let world = match parent {
+		Some(parent) => nodes[parent].world * transform.matrix(),
+		None => transform.matrix()
+	};
+
+	let index = nodes.len();
+	nodes.push(GltfNode {
+		name: node.name().map(str::to_owned),
+		parent,
+		mesh: node.mesh().map(|mesh| mesh.index()),
+		transform,
+		world
+	});
+	for child in node.children() {
+		flatten(&child, Some(index), nodes);
+	}
+}
+
+/** Converts the decomposed transformation of a glTF node, whose rotation is a
+ * unit quaternion, into a [`Transform`], whose rotation is an axis and an
+ * angle.
+ *
+ * Rotations in glTF turn counter clockwise around their axis, when looking
+ * down at it, while [`Matrix4::rotate()`] turns the other way around, so the
+ * angle gets flipped. */
+fn transform(translation: [f32; 3], rotation: [f32; 4], scale: [f32; 3]) -> Transform {
+	let [x, y, z, w] = rotation;
+	let w = w.max(-1.0).min(1.0);
+	let sin = (1.0 - w * w).sqrt();
+
+	let rotation = if sin < 1e-6 {
+		Transform::default().rotation
+	} else {
+		[x / sin, y / sin, z / sin, -2.0 * w.acos()]
+	};
+
+	Transform {
+		translation,
+		rotation,
+		scale
+	}
+}
+
+/** Converts a decoded glTF image to eight bit RGBA, if it is in a format with
+ * eight bits per channel. */
+fn rgba(image: ::gltf::image::Data) -> Option<SceneImage> {
+	use ::gltf::image::Format;
+
+	let texels = match image.format {
+		Format::R8G8B8A8 => image.pixels,
+		Format::R8G8B8 => image.pixels.chunks_exact(3)
+			.flat_map(|texel| [texel[0], texel[1], texel[2], u8::MAX])
+			.collect(),
+		Format::R8G8 => image.pixels.chunks_exact(2)
+			.flat_map(|texel| [texel[0], texel[0], texel[0], texel[1]])
+			.collect(),
+		Format::R8 => image.pixels.iter()
+			.flat_map(|texel| [*texel, *texel, *texel, u8::MAX])
+			.collect(),
+		_ => return None
+	};
+
+	Some(SceneImage {
+		width: image.width,
+		height: image.height,
+		texels
+	})
+}
+
+/** Errors that may happen while loading or uploading a glTF model. */
+#[derive(Debug, thiserror::Error)]
+pub enum GltfError {
+	#[error("could not import the gltf file: {what}")]
+	Import {
+		what: String
+	},
+	#[error("image {index} has more than eight bits per channel")]
+	UnsupportedImage {
+		index: usize
+	},
+	#[error("could not load mesh {mesh}: {what}")]
+	Mesh {
+		mesh: String,
+		what: String
+	},
+	#[error("there is no image {index} in the model")]
+	MissingImage {
+		index: usize
+	},
+	#[error("there is no material {index} in the model")]
+	MissingMaterial {
+		index: usize
+	},
+	#[error("the model has more indices than fit in 32 bits")]
+	TooLarge,
+	#[error("could not create a buffer for the model: {0}")]
+	Buffer(#[from] BufferError),
+	#[error("could not create a texture for the model: {0}")]
+	Texture(#[from] TextureError),
+	#[error("could not bind the textures of the model: {0}")]
+	BindGroup(#[from] BindGroupError),
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn rotations_turn_the_same_way() {
+		/* A quarter turn around z, which takes x over to y. */
+		let half = std::f32::consts::FRAC_PI_4;
+		let turned = transform([0.0; 3], [0.0, 0.0, half.sin(), half.cos()], [1.0; 3]);
+		let moved = turned.matrix() * Matrix4::translate(1.0, 0.0, 0.0);
+		let moved = moved.as_row_major_array();
+		assert!(moved[3].abs() < 1e-5, "{:?}", moved);
+		assert!((moved[7] - 1.0).abs() < 1e-5, "{:?}", moved);
+	}
+
+	#[test]
+	fn identity_rotations_are_left_out() {
+		let placed = transform([1.0, 2.0, 3.0], [0.0, 0.0, 0.0, 1.0], [2.0; 3]);
+		assert_eq!(placed.rotation, Transform::default().rotation);
+		assert_eq!(
+			placed.matrix(),
+			Matrix4::translate(1.0, 2.0, 3.0) * Matrix4::scale(2.0, 2.0, 2.0));
+	}
+}
@@ -0,0 +1,760 @@
+use gavle::*;
+use std::borrow::Cow;
+use std::convert::TryFrom;
+
+/** Name of the binding of the uniform block of the color grading shaders. */
+const COLOR_GRADE_BINDING: &str = "rc_color_grade";
+
+/** Largest number of entries along each axis of a table, as allowed by the
+ * `.cube` format. */
+const MAX_LUT_SIZE: u32 = 256;
+
+/** Three dimensional color lookup table, mapping input colors to graded ones.
+ *
+ * Entries are stored with the red axis changing the fastest, followed by the
+ * green axis and then by the blue axis, which is the order they are listed in
+ * `.cube` files, and the order in which the texels of a three dimensional
+ * texture are laid out. Colors between entries get interpolated trilinearly,
+ * as done by [`sample()`].
+ *
+ * [`sample()`]: Self::sample */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lut3d {
+	/** Title of the table, if it has one. */
+	title: Option<String>,
+	/** Number of entries along each axis. */
+	size: u32,
+	/** Input color mapped to the first entry along each axis. */
+	domain_min: [f32; 3],
+	/** Input color mapped to the last entry along each axis. */
+	domain_max: [f32; 3],
+	/** Entries of the table, in order. */
+	entries: Vec<[f32; 3]>,
+}
+impl Lut3d {
+	/** Parse a table from the contents of an Adobe `.cube` file.
+	 *
+	 * Only three dimensional tables are supported. The input domain defaults
+	 * to `[0, 1]` on every axis, and may be changed with either `DOMAIN_MIN`
+	 * and `DOMAIN_MAX` or with `LUT_3D_INPUT_RANGE`. */
+	pub fn from_cube(source: &str) -> Result<Self, LutError> {
+		let mut title = None;
+		let mut size = None;
+		let mut domain_min = [0.0; 3];
+		let mut domain_max = [1.0; 3];
+		let mut entries = Vec::new();
+
+		for (index, line) in source.lines().enumerate() {
+			let line = line.trim();
+			if line.is_empty() || line.starts_with('#') { continue }
+
+			let syntax = |what: String| LutError::Syntax { line: index + 1, what };
+			let mut words = line.split_whitespace();
+			let keyword = words.next().unwrap();
+			match keyword {
+				"TITLE" => {
+					let text = line["TITLE".len()..].trim();
+					title = Some(text.trim_matches('"').to_owned());
+				},
+				"LUT_3D_SIZE" => {
+					let [value] = parse_numbers::<u32, 1>(words).map_err(syntax)?;
+					size = Some(value);
+				},
+				"LUT_1D_SIZE" =>
+					return Err(syntax("one dimensional tables are not \
+						supported".into())),
+				"DOMAIN_MIN" =>
+					domain_min = parse_numbers(words).map_err(syntax)?,
+				"DOMAIN_MAX" =>
+					domain_max = parse_numbers(words).map_err(syntax)?,
+				"LUT_3D_INPUT_RANGE" => {
+					let [min, max] = parse_numbers::<f32, 2>(words).map_err(syntax)?;
+					domain_min = [min; 3];
+					domain_max = [max; 3];
+				},
+				_ if keyword.starts_with(|c: char| c.is_ascii_alphabetic()) =>
+					return Err(syntax(format!("unknown keyword {}", keyword))),
+				_ => {
+					let line = std::iter::once(keyword).chain(words);
+					entries.push(parse_numbers(line).map_err(syntax)?);
+				}
+			}
+		}
+
+		let size = size.ok_or_else(|| LutError::InvalidTable {
+			what: "the table is missing its LUT_3D_SIZE".into()
+		})?;
+		Self::new(title, size, domain_min, domain_max, entries)
+	}
+
+	/** Repack a table from an image of its slices along the blue axis, laid
+	 * side by side, as commonly exported from image editors as a PNG.
+	 *
+	 * The image is given as tightly packed RGBA texels, with eight bits per
+	 * channel, in rows from the top down. For a table with `N` entries along
+	 * each axis, it must be `N * N` texels wide and `N` texels tall, with the
+	 * red axis going left to right in each slice, and the green axis going
+	 * from the top down. The alpha channel is ignored, and the input domain is
+	 * always `[0, 1]`. */
+	pub fn from_strip(width: u32, height: u32, texels: &[u8]) -> Result<Self, LutError> {
+		let size = height;
+		if size.checked_mul(size) != Some(width) {
+			return Err(LutError::InvalidStrip {
+				what: format!("a strip of {} entries per axis must be {} \
+					texels wide, but it is {} texels wide",
+					size,
+					u64::from(size) * u64::from(size),
+					width)
+			})
+		}
+		let expected = u64::from(width) * u64::from(height) * 4;
+		if texels.len() as u64 != expected {
+			return Err(LutError::InvalidStrip {
+				what: format!("a {}x{} strip takes {} bytes of texel data, \
+					but {} were given",
+					width,
+					height,
+					expected,
+					texels.len())
+			})
+		}
+
+		let size_ = size as usize;
+		let mut entries = Vec::with_capacity(size_ * size_ * size_);
+		for blue in 0..size_ {
+			for green in 0..size_ {
+				for red in 0..size_ {
+					let texel = (green * size_ * size_ + blue * size_ + red) * 4;
+					let channel = |offset: usize| f32::from(texels[texel + offset]) / 255.0;
+					entries.push([channel(0), channel(1), channel(2)]);
+				}
+			}
+		}
+
+		Self::new(None, size, [0.0; 3], [1.0; 3], entries)
+	}
+
+	/** Check the given parts of a table and put them together. */
+	fn new(
+		title: Option<String>,
+		size: u32,
+		domain_min: [f32; 3],
+		domain_max: [f32; 3],
+		entries: Vec<[f32; 3]>) -> Result<Self, LutError> {
+
+		if !(2..=MAX_LUT_SIZE).contains(&size) {
+			return Err(LutError::InvalidTable {
+				what: format!("tables must have between 2 and {} entries \
+					along each axis, not {}",
+					MAX_LUT_SIZE,
+					size)
+			})
+		}
+		let expected = (size as usize).pow(3);
+		if entries.len() != expected {
+			return Err(LutError::InvalidTable {
+				what: format!("a table of size {} has {} entries, but {} \
+					were given",
+					size,
+					expected,
+					entries.len())
+			})
+		}
+		let ascending = |c: usize| domain_min[c].partial_cmp(&domain_max[c])
+			== Some(std::cmp::Ordering::Less);
+		if !(0..3).all(ascending) {
+			return Err(LutError::InvalidTable {
+				what: format!("the lower end of the domain {:?} must be \
+					below its upper end {:?} on every axis",
+					domain_min,
+					domain_max)
+			})
+		}
+
+		Ok(Self { title, size, domain_min, domain_max, entries })
+	}
+
+	/** Title of the table, if it has one. */
+	pub fn title(&self) -> Option<&str> {
+		self.title.as_deref()
+	}
+
+	/** Number of entries along each axis. */
+	pub fn size(&self) -> u32 {
+		self.size
+	}
+
+	/** Input color mapped to the first entry along each axis. */
+	pub fn domain_min(&self) -> [f32; 3] {
+		self.domain_min
+	}
+
+	/** Input color mapped to the last entry along each axis. */
+	pub fn domain_max(&self) -> [f32; 3] {
+		self.domain_max
+	}
+
+	/** Entries of the table, with the red axis changing the fastest. */
+	pub fn entries(&self) -> &[[f32; 3]] {
+		&self.entries
+	}
+
+	/** The entry at the given position along the red, green and blue axes. */
+	fn entry(&self, red: usize, green: usize, blue: usize) -> [f32; 3] {
+		let size = self.size as usize;
+		self.entries[(blue * size + green) * size + red]
+	}
+
+	/** Graded version of the given color, interpolated trilinearly between
+	 * the eight entries around it. Colors outside of the domain get clamped to
+	 * its edges.
+	 *
+	 * This is the reference the color grading shaders are held to. */
+	pub fn sample(&self, color: [f32; 3]) -> [f32; 3] {
+		let last = self.size as usize - 1;
+
+		let mut index = [0usize; 3];
+		let mut fraction = [0.0f32; 3];
+		for c in 0..3 {
+			let t = ((color[c] - self.domain_min[c])
+				/ (self.domain_max[c] - self.domain_min[c]))
+				.clamp(0.0, 1.0);
+			let x = t * last as f32;
+			index[c] = (x as usize).min(last - 1);
+			fraction[c] = x - index[c] as f32;
+		}
+
+		let [r, g, b] = index;
+		let [fr, fg, fb] = fraction;
+		let c00 = lerp(self.entry(r, g, b), self.entry(r + 1, g, b), fr);
+		let c10 = lerp(self.entry(r, g + 1, b), self.entry(r + 1, g + 1, b), fr);
+		let c01 = lerp(self.entry(r, g, b + 1), self.entry(r + 1, g, b + 1), fr);
+		let c11 = lerp(self.entry(r, g + 1, b + 1), self.entry(r + 1, g + 1, b + 1), fr);
+
+		lerp(lerp(c00, c10, fg), lerp(c01, c11, fg), fb)
+	}
+
+	/** Texel data for a three dimensional [`TextureFormat::Rgba32Float`]
+	 * texture holding this table, `size` texels along every axis. */
+	pub fn volume_texels(&self) -> Vec<u8> {
+		let texels = self.entries.iter()
+			.map(|[r, g, b]| [*r, *g, *b, 1.0])
+			.collect::<Vec<_>>();
+		bytemuck::cast_slice(&texels).to_vec()
+	}
+
+	/** Texel data for a two dimensional [`TextureFormat::Rgba32Float`]
+	 * texture holding this table as its slices along the blue axis, laid side
+	 * by side, `size * size` texels wide and `size` texels tall. */
+	pub fn strip_texels(&self) -> Vec<u8> {
+		let size = self.size as usize;
+		let mut texels = vec![[0.0f32; 4]; size * size * size];
+		for blue in 0..size {
+			for green in 0..size {
+				for red in 0..size {
+					let [r, g, b] = self.entry(red, green, blue);
+					texels[green * size * size + blue * size + red] = [r, g, b, 1.0];
+				}
+			}
+		}
+		bytemuck::cast_slice(&texels).to_vec()
+	}
+}
+
+/** Parse exactly `N` whitespace separated numbers. */
+fn parse_numbers<'a, T, const N: usize>(
+	words: impl Iterator<Item = &'a str>) -> Result<[T; N], String>
+	where T: std::str::FromStr + Default + Copy {
+
+	let mut numbers = [T::default(); N];
+	let mut count = 0;
+	for word in words {
+		if count == N {
+			return Err(format!("expected {} numbers, found more", N))
+		}
+		numbers[count] = word.parse()
+			.map_err(|_| format!("{} is not a valid number", word))?;
+		count += 1;
+	}
+	if count != N {
+		return Err(format!("expected {} numbers, found {}", N, count))
+	}
+
+	Ok(numbers)
+}
+
+/** Linear interpolation between two colors. */
+fn lerp(a: [f32; 3], b: [f32; 3], t: f32) -> [f32; 3] {
+	[
+		a[0] + (b[0] - a[0]) * t,
+		a[1] + (b[1] - a[1]) * t,
+		a[2] + (b[2] - a[2]) * t,
+	]
+}
+
+/** Normalized texture coordinate the volume shader samples a table of the
+ * given size at, for a color already normalized to `[0, 1]` over the domain.
+ *
+ * The centers of the first and last texels sit half a texel in from the edges
+ * of the texture, at `0.5 / size` and `1 - 0.5 / size`. Sampling at `t`
+ * directly would have the ends of the domain land on the edges instead, where
+ * clamping pulls every color near them toward the end entries, and would
+ * spread the whole domain over `size` texels rather than the `size - 1` spans
+ * between entries. Must be kept in sync with `lut_coordinates()` in the
+ * volume shader. */
+fn lut_coordinate(t: f32, size: u32) -> f32 {
+	let size = size as f32;
+	t * ((size - 1.0) / size) + 0.5 / size
+}
+
+/** Layout of the uniform block of the color grading shaders. */
+#[derive(Debug, Copy, Clone, PartialEq, bytemuck::Zeroable, bytemuck::Pod)]
+#[repr(C)]
+struct ColorGradeUniforms {
+	/** Lower end of the domain, and the intensity in the last component. */
+	domain_min: [f32; 4],
+	/** Upper end of the domain, and the size in the last component. */
+	domain_max: [f32; 4],
+}
+
+/** Full screen effect grading the colors of a texture through a [`Lut3d`].
+ *
+ * The table gets stored in a three dimensional texture, and looked up with
+ * the trilinear filtering of the sampler, as long as the context can filter
+ * [`TextureFormat::Rgba32Float`] textures, as told by the
+ * [`float_texture_filtering`] capability. Otherwise, it gets stored as its
+ * slices laid side by side in a two dimensional texture, and interpolated by
+ * hand in the shader, which takes eight lookups instead of one, but gives the
+ * same results.
+ *
+ * The graded colors get blended with the original ones by the intensity of
+ * the effect, which may be changed every frame, so that fading the grade in
+ * and out is a matter of animating a single value.
+ *
+ * [`float_texture_filtering`]: gavle::Capabilities::float_texture_filtering */
+pub struct ColorGrade {
+	/** Fullscreen grading pipeline. */
+	pipeline: RenderPipeline,
+	/** Uniform buffer holding the domain, size and intensity. */
+	uniforms: UniformBuffer,
+	/** Texture holding the table. */
+	lut: Texture,
+	/** Binding group, once there has been a source to bind to it. */
+	bind: Option<UniformGroup>,
+	/** Domain and size of the table, as they go into the uniform buffer. */
+	domain: ([f32; 3], [f32; 3], u32),
+	/** How much of the graded color makes it into the output. */
+	intensity: f32,
+	/** Whether the table is stored as a strip, rather than as a volume. */
+	strip: bool,
+}
+impl ColorGrade {
+	/** Layout of the vertex buffer, of which there is none, as the vertices
+	 * are generated in the vertex shader. */
+	const LAYOUT: VertexBufferLayout<'static> = VertexBufferLayout {
+		array_stride: 0,
+		attributes: &[]
+	};
+
+	/** Create a new color grading effect on the given device, from the given
+	 * table, at full intensity. */
+	pub fn new(device: &Device, lut: &Lut3d) -> Result<Self, ColorGradeError> {
+		let information = device.information();
+		let strip = !information.capabilities.float_texture_filtering
+			|| lut.size() > information.limits.max_texture_size_3d;
+		if strip {
+			log::info!(target: "support::grade",
+				"Grading colors through a strip, as the context can't filter \
+				a {0}x{0}x{0} table in a three dimensional texture",
+				lut.size());
+		}
+
+		let uniforms = device.create_uniform_buffer(
+			&BufferDescriptor {
+				size: u32::try_from(std::mem::size_of::<ColorGradeUniforms>()).unwrap(),
+				profile: BufferProfile::DynamicUpload,
+				contents: BufferContents::F32,
+				..Default::default()
+			})?;
+
+		let size = lut.size();
+		let texture = if strip {
+			device.create_texture_with_data(
+				&TextureDescriptor {
+					extent: TextureExtent::D2 { width: size * size, height: size },
+					format: TextureFormat::Rgba32Float,
+					mip: Mipmap::None,
+					swizzle: None
+				},
+				lut.strip_texels())?
+		} else {
+			device.create_texture_with_data(
+				&TextureDescriptor {
+					extent: TextureExtent::D3 { width: size, height: size, depth: size },
+					format: TextureFormat::Rgba32Float,
+					mip: Mipmap::None,
+					swizzle: None
+				},
+				lut.volume_texels())?
+		};
+		texture.set_label(lut.title().unwrap_or("color grade"));
+
+		/* Both variants draw a single triangle over the whole target, just
+		 * like the stencil view does. */
+		let vertex = device.create_vertex_shader(
+			ShaderSource::Glsl(Cow::Borrowed(include_str!("debugview/vert.glsl"))))?;
+		let fragment = device.create_fragment_shader(
+			ShaderSource::Glsl(Cow::Borrowed(if strip {
+				include_str!("grade/strip.glsl")
+			} else {
+				include_str!("grade/volume.glsl")
+			})))?;
+
+		let pipeline = device.create_render_pipeline(
+			&RenderPipelineDescriptor::new(&vertex, &Self::LAYOUT)
+				.with_fragment(&fragment)
+				.with_depth(DepthStencilState::DISABLED))?;
+		pipeline.set_label("color grade");
+
+		Ok(Self {
+			pipeline,
+			uniforms,
+			lut: texture,
+			bind: None,
+			domain: (lut.domain_min(), lut.domain_max(), size),
+			intensity: 1.0,
+			strip
+		})
+	}
+
+	/** How much of the graded color makes it into the output, from zero, for
+	 * none of it, to one, for only the graded color. */
+	pub fn intensity(&self) -> f32 {
+		self.intensity
+	}
+
+	/** Change how much of the graded color makes it into the output. Values
+	 * get clamped to `[0, 1]`. */
+	pub fn set_intensity(&mut self, intensity: f32) {
+		self.intensity = intensity.clamp(0.0, 1.0)
+	}
+
+	/** Whether the table is being looked up from a strip, by hand, because
+	 * the context can't filter it as a three dimensional texture. */
+	pub fn uses_strip(&self) -> bool {
+		self.strip
+	}
+
+	/** Texture holding the table. */
+	pub fn lut(&self) -> &Texture {
+		&self.lut
+	}
+
+	/** Draw the colors of the given texture, graded, over the given viewport
+	 * of the given framebuffer. The source texture must not be attached to
+	 * the framebuffer.
+	 *
+	 * This must be called outside of a render pass. */
+	pub fn draw(
+		&mut self,
+		device: &Device,
+		source: &Texture,
+		target: &Framebuffer,
+		viewport: Viewport) -> Result<(), ColorGradeError> {
+
+		let (min, max, size) = self.domain;
+		let uniforms = ColorGradeUniforms {
+			domain_min: [min[0], min[1], min[2], self.intensity],
+			domain_max: [max[0], max[1], max[2], size as f32],
+		};
+		let data = bytemuck::bytes_of(&uniforms);
+		let slice = self.uniforms.slice(..);
+		let mut map = slice.try_map_mut(BufferLoadOp::DontCare, MapMode::Orphan)
+			.unwrap();
+		map[..data.len()].copy_from_slice(data);
+		drop(map);
+
+		match &self.bind {
+			Some(bind) => bind.replace_texture("tt_source", source)?,
+			None => {
+				let filter = if self.strip {
+					TextureFilter::Nearest
+				} else {
+					TextureFilter::Linear
+				};
+				let bind = device.create_uniform_bind_group(
+					&UniformGroupDescriptor {
+						entries: &[
+							UniformGroupEntry {
+								binding: COLOR_GRADE_BINDING.into(),
+								kind: UniformBind::Buffer {
+									buffer: &self.uniforms,
+									offset: 0,
+									size: None
+								}
+							},
+							UniformGroupEntry {
+								binding: "tt_source".into(),
+								kind: UniformBind::Texture {
+									texture: source,
+									far: TextureFilter::Nearest,
+									near: TextureFilter::Nearest,
+									address_mode: AddressMode::ClampToEdge,
+									anisotropy_clamp: None,
+									sampler: None
+								}
+							},
+							UniformGroupEntry {
+								binding: "tt_lut".into(),
+								kind: UniformBind::Texture {
+									texture: &self.lut,
+									far: filter,
+									near: filter,
+									address_mode: AddressMode::ClampToEdge,
+									anisotropy_clamp: None,
+									sampler: None
+								}
+							},
+						]
+					})?;
+				self.bind = Some(bind);
+			}
+		}
+
+		let mut pass = device.start_render_pass(
+			&RenderPassDescriptor {
+				pipeline: &self.pipeline,
+				framebuffer: target
+			});
+		pass.set_viewport(viewport)?;
+		pass.set_bind_group(self.bind.as_ref().unwrap());
+		pass.draw(0..3, 1)
+			.expect("drawing a triangle with no vertex buffer can't fail");
+
+		Ok(())
+	}
+}
+
+/** Errors that may happen while loading a color lookup table. */
+#[derive(Debug, thiserror::Error)]
+pub enum LutError {
+	#[error("line {line} of the table is invalid: {what}")]
+	Syntax {
+		line: usize,
+		what: String
+	},
+	#[error("the table is invalid: {what}")]
+	InvalidTable {
+		what: String
+	},
+	#[error("the strip is invalid: {what}")]
+	InvalidStrip {
+		what: String
+	},
+}
+
+/** Errors that may happen while creating or drawing a color grading effect. */
+#[derive(Debug, thiserror::Error)]
+pub enum ColorGradeError {
+	#[error("could not create color grade buffers: {0}")]
+	Buffer(#[from] BufferError),
+	#[error("could not create the color lookup texture: {0}")]
+	Texture(#[from] TextureError),
+	#[error("could not create color grade shaders: {0}")]
+	Shader(#[from] ShaderError),
+	#[error("could not create color grade pipeline: {0}")]
+	Pipeline(#[from] RenderPipelineError),
+	#[error("could not bind the source of the color grade: {0}")]
+	BindGroup(#[from] BindGroupError),
+	#[error("could not set the viewport of the color grade: {0}")]
+	Viewport(#[from] ViewportError),
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/** Table of the given size whose entries are given by a function of their
+	 * position in the domain. */
+	fn table(size: u32, f: impl Fn([f32; 3]) -> [f32; 3]) -> Lut3d {
+		let last = (size - 1) as f32;
+		let mut entries = Vec::new();
+		for b in 0..size {
+			for g in 0..size {
+				for r in 0..size {
+					entries.push(f([r as f32 / last, g as f32 / last, b as f32 / last]));
+				}
+			}
+		}
+		Lut3d::new(None, size, [0.0; 3], [1.0; 3], entries).unwrap()
+	}
+
+	fn assert_close(a: [f32; 3], b: [f32; 3]) {
+		for c in 0..3 {
+			assert!((a[c] - b[c]).abs() < 1e-5, "{:?} != {:?}", a, b);
+		}
+	}
+
+	#[test]
+	fn parses_cube_files() {
+		let lut = Lut3d::from_cube("\
+			# Created by hand\n\
+			TITLE \"warm\"\n\
+			LUT_3D_SIZE 2\n\
+			DOMAIN_MIN 0.0 0.0 0.0\n\
+			DOMAIN_MAX 2.0 2.0 2.0\n\
+			\n\
+			0 0 0\n\
+			1 0 0\n\
+			0 1 0\n\
+			1 1 0\n\
+			0 0 1\n\
+			1 0 1\n\
+			0 1 1\n\
+			1 1 1\n").unwrap();
+
+		assert_eq!(lut.title(), Some("warm"));
+		assert_eq!(lut.size(), 2);
+		assert_eq!(lut.domain_max(), [2.0; 3]);
+		assert_eq!(lut.entries()[1], [1.0, 0.0, 0.0]);
+		assert_eq!(lut.entries()[6], [0.0, 1.0, 1.0]);
+
+		/* The domain is twice as wide as the entries, so inputs get halved,
+		 * and anything past it gets clamped. */
+		assert_close(lut.sample([1.0, 0.5, 2.0]), [0.5, 0.25, 1.0]);
+		assert_close(lut.sample([-1.0, 4.0, 0.0]), [0.0, 1.0, 0.0]);
+	}
+
+	#[test]
+	fn parses_input_ranges() {
+		let mut source = String::from("LUT_3D_SIZE 2\nLUT_3D_INPUT_RANGE -1 1\n");
+		for _ in 0..8 { source.push_str("0.5 0.5 0.5\n") }
+
+		let lut = Lut3d::from_cube(&source).unwrap();
+		assert_eq!(lut.domain_min(), [-1.0; 3]);
+		assert_eq!(lut.domain_max(), [1.0; 3]);
+	}
+
+	#[test]
+	fn rejects_broken_cube_files() {
+		/* Missing an entry. */
+		let mut source = String::from("LUT_3D_SIZE 2\n");
+		for _ in 0..7 { source.push_str("0 0 0\n") }
+		assert!(matches!(Lut3d::from_cube(&source),
+			Err(LutError::InvalidTable { .. })));
+
+		/* An entry with two components, on the third line. */
+		let source = "LUT_3D_SIZE 2\n0 0 0\n0 0\n";
+		assert!(matches!(Lut3d::from_cube(source),
+			Err(LutError::Syntax { line: 3, .. })));
+
+		/* An empty domain. */
+		let mut source = String::from("LUT_3D_SIZE 2\nDOMAIN_MIN 0 1 0\nDOMAIN_MAX 1 1 1\n");
+		for _ in 0..8 { source.push_str("0 0 0\n") }
+		assert!(matches!(Lut3d::from_cube(&source),
+			Err(LutError::InvalidTable { .. })));
+
+		assert!(matches!(Lut3d::from_cube("LUT_1D_SIZE 2\n"),
+			Err(LutError::Syntax { line: 1, .. })));
+	}
+
+	#[test]
+	fn repacks_strips() {
+		/* A 2x2x2 strip, with the blue slices side by side, each entry
+		 * storing its own position. */
+		let size = 2usize;
+		let mut texels = vec![0u8; size * size * size * 4];
+		for g in 0..size {
+			for b in 0..size {
+				for r in 0..size {
+					let texel = (g * size * size + b * size + r) * 4;
+					texels[texel] = (r * 255) as u8;
+					texels[texel + 1] = (g * 255) as u8;
+					texels[texel + 2] = (b * 255) as u8;
+					texels[texel + 3] = 255;
+				}
+			}
+		}
+
+		let lut = Lut3d::from_strip(4, 2, &texels).unwrap();
+		assert_eq!(lut, table(2, |color| color));
+		assert_eq!(lut.domain_min(), [0.0; 3]);
+		assert_eq!(lut.domain_max(), [1.0; 3]);
+
+		/* Repacking the table back into a strip gives the same layout. */
+		let strip = lut.strip_texels();
+		let strip: &[f32] = bytemuck::cast_slice(&strip);
+		for (texel, original) in strip.chunks_exact(4).zip(texels.chunks_exact(4)) {
+			for c in 0..3 {
+				assert_eq!(texel[c], f32::from(original[c]) / 255.0);
+			}
+		}
+
+		assert!(matches!(Lut3d::from_strip(4, 3, &texels),
+			Err(LutError::InvalidStrip { .. })));
+		assert!(matches!(Lut3d::from_strip(4, 2, &texels[4..]),
+			Err(LutError::InvalidStrip { .. })));
+	}
+
+	#[test]
+	fn half_texel_correction() {
+		/* The ends of the domain land on the centers of the end texels. */
+		assert_eq!(lut_coordinate(0.0, 32), 0.5 / 32.0);
+		assert_eq!(lut_coordinate(1.0, 32), 1.0 - 0.5 / 32.0);
+		assert!((lut_coordinate(0.5, 32) - 0.5).abs() < 1e-6);
+	}
+
+	/** Linear filtering of a single texel axis, the way the sampler does it,
+	 * returning the two texels it reads from and the weight of the second. */
+	fn filter_axis(u: f32, size: u32) -> (usize, usize, f32) {
+		let x = u * size as f32 - 0.5;
+		let i = x.floor();
+		let clamp = |i: f32| i.max(0.0).min((size - 1) as f32) as usize;
+		(clamp(i), clamp(i + 1.0), x - i)
+	}
+
+	/** Trilinear sampling of the volume texture of the given table at the
+	 * given normalized coordinates, emulating the sampler. */
+	fn filter_volume(lut: &Lut3d, uvw: [f32; 3]) -> [f32; 3] {
+		let (r0, r1, fr) = filter_axis(uvw[0], lut.size());
+		let (g0, g1, fg) = filter_axis(uvw[1], lut.size());
+		let (b0, b1, fb) = filter_axis(uvw[2], lut.size());
+
+		let c00 = lerp(lut.entry(r0, g0, b0), lut.entry(r1, g0, b0), fr);
+		let c10 = lerp(lut.entry(r0, g1, b0), lut.entry(r1, g1, b0), fr);
+		let c01 = lerp(lut.entry(r0, g0, b1), lut.entry(r1, g0, b1), fr);
+		let c11 = lerp(lut.entry(r0, g1, b1), lut.entry(r1, g1, b1), fr);
+		lerp(lerp(c00, c10, fg), lerp(c01, c11, fg), fb)
+	}
+
+	#[test]
+	fn volume_lookups_match_the_reference() {
+		let lut = table(5, |[r, g, b]| [r * r, g * b, 1.0 - (r + b) * 0.5]);
+
+		/* Run a gradient going through the whole domain, off of the lattice,
+		 * through the lookup done by the volume shader. */
+		for step in 0..=64 {
+			let t = step as f32 / 64.0;
+			let color = [t, 1.0 - t, (t * 3.0).fract()];
+			let uvw = [
+				lut_coordinate(color[0], lut.size()),
+				lut_coordinate(color[1], lut.size()),
+				lut_coordinate(color[2], lut.size())];
+			assert_close(filter_volume(&lut, uvw), lut.sample(color));
+		}
+
+		/* Without the correction, the lookup drifts away from it. */
+		let color = [0.3, 0.6, 0.1];
+		let drift = filter_volume(&lut, color);
+		let reference = lut.sample(color);
+		assert!((0..3).any(|c| (drift[c] - reference[c]).abs() > 1e-3));
+	}
+
+	#[test]
+	fn lattice_points_are_exact() {
+		let lut = table(4, |[r, g, b]| [b, r, g]);
+		assert_eq!(lut.sample([0.0, 0.0, 0.0]), [0.0, 0.0, 0.0]);
+		assert_close(lut.sample([1.0, 1.0 / 3.0, 2.0 / 3.0]), [2.0 / 3.0, 1.0, 1.0 / 3.0]);
+		assert_eq!(lut.sample([1.0, 1.0, 1.0]), [1.0, 1.0, 1.0]);
+	}
+}
@@ -0,0 +1,51 @@
+#version 300 es
+precision highp float;
+
+layout(std140) uniform rc_color_grade
+{
+/* Lower corner of the input domain of the table, and the intensity of the
+ * grade in the last component. */
+    vec4 DomainMin;
+/* Upper corner of the input domain of the table, and the number of entries
+ * along each of its axes in the last component. */
+    vec4 DomainMax;
+};
+
+uniform highp sampler2D tt_source;
+/* Slices of the table along the blue axis, laid side by side. */
+uniform highp sampler2D tt_lut;
+
+in vec2 vs_uv;
+
+out vec4 color;
+
+vec3 entry(ivec3 index, int size)
+{
+    return texelFetch(tt_lut, ivec2(index.b * size + index.r, index.g), 0).rgb;
+}
+
+/* Trilinear interpolation between the eight entries around the given color,
+ * done by hand, as the table can't be filtered by the sampler.
+ *
+ * Must be kept in sync with `Lut3d::sample()` in grade.rs. */
+vec3 grade(vec3 rgb)
+{
+    int size = int(DomainMax.w);
+    vec3 t = clamp((rgb - DomainMin.rgb) / (DomainMax.rgb - DomainMin.rgb), 0.0, 1.0);
+    vec3 x = t * float(size - 1);
+    ivec3 i = min(ivec3(x), ivec3(size - 2));
+    vec3 f = x - vec3(i);
+
+    vec3 c00 = mix(entry(i + ivec3(0, 0, 0), size), entry(i + ivec3(1, 0, 0), size), f.r);
+    vec3 c10 = mix(entry(i + ivec3(0, 1, 0), size), entry(i + ivec3(1, 1, 0), size), f.r);
+    vec3 c01 = mix(entry(i + ivec3(0, 0, 1), size), entry(i + ivec3(1, 0, 1), size), f.r);
+    vec3 c11 = mix(entry(i + ivec3(0, 1, 1), size), entry(i + ivec3(1, 1, 1), size), f.r);
+
+    return mix(mix(c00, c10, f.g), mix(c01, c11, f.g), f.b);
+}
+
+void main()
+{
+    vec4 source = texture(tt_source, vs_uv);
+    color = vec4(mix(source.rgb, grade(source.rgb), DomainMin.w), source.a);
+}
@@ -0,0 +1,40 @@
+#version 300 es
+precision highp float;
+precision highp sampler3D;
+
+layout(std140) uniform rc_color_grade
+{
+/* Lower corner of the input domain of the table, and the intensity of the
+ * grade in the last component. */
+    vec4 DomainMin;
+/* Upper corner of the input domain of the table, and the number of entries
+ * along each of its axes in the last component. */
+    vec4 DomainMax;
+};
+
+uniform highp sampler2D tt_source;
+uniform highp sampler3D tt_lut;
+
+in vec2 vs_uv;
+
+out vec4 color;
+
+/* Coordinates the table gets sampled at for the given color. The centers of
+ * the first and last entries along an axis sit half a texel in from the edges
+ * of the texture, so the domain has to be squeezed into that range for the
+ * filtering to land on the entries, rather than onto [0, 1].
+ *
+ * Must be kept in sync with `lut_coordinate()` in grade.rs. */
+vec3 lut_coordinates(vec3 rgb)
+{
+    float size = DomainMax.w;
+    vec3 t = clamp((rgb - DomainMin.rgb) / (DomainMax.rgb - DomainMin.rgb), 0.0, 1.0);
+    return t * ((size - 1.0) / size) + 0.5 / size;
+}
+
+void main()
+{
+    vec4 source = texture(tt_source, vs_uv);
+    vec3 graded = texture(tt_lut, lut_coordinates(source.rgb)).rgb;
+    color = vec4(mix(source.rgb, graded, DomainMin.w), source.a);
+}
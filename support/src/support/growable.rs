@@ -0,0 +1,134 @@
+use gavle::*;
+use std::convert::TryFrom;
+
+/** Capacity a buffer with the given capacity, in bytes, has to grow to in
+ * order to fit the given number of bytes, doubling it for as long as it is
+ * too small, or `None` if it is already large enough. */
+fn grown_capacity(capacity: u32, required: u32) -> Option<u32> {
+	if required <= capacity { return None }
+
+	let mut grown = capacity.max(1);
+	while grown < required {
+		grown = grown.checked_mul(2).unwrap_or(required);
+	}
+	Some(grown)
+}
+
+/** A vertex buffer that grows to fit whatever gets written to it.
+ *
+ * Writes that go past the end of the buffer replace it with one of double its
+ * capacity, or more, if doubling isn't enough, and the data written to the old
+ * buffer so far gets copied over to the new one on the device, without going
+ * through the host. This needs the [`buffer_copies`] capability, which legacy
+ * contexts lack.
+ *
+ * Keep in mind that growing replaces the buffer, so the one returned by
+ * [`buffer()`] has to be fetched again after every write, rather than kept
+ * around from before it.
+ *
+ * [`buffer_copies`]: gavle::Capabilities::buffer_copies
+ * [`buffer()`]: Self::buffer */
+pub struct GrowableVertexBuffer {
+	/** The buffer holding the data. */
+	buffer: VertexBuffer,
+	/** Number of bytes written to the buffer so far, counting from the start
+	 * of the buffer up to the end of the furthest write. */
+	len: u32,
+	/** Descriptor of the buffer, for when it has to be replaced. */
+	descriptor: BufferDescriptor,
+}
+impl GrowableVertexBuffer {
+	/** Creates a new, empty buffer, with the size in the descriptor as its
+	 * starting capacity. */
+	pub fn new(device: &Device, descriptor: &BufferDescriptor) -> Result<Self, BufferError> {
+		Ok(Self {
+			buffer: device.create_vertex_buffer(descriptor)?,
+			len: 0,
+			descriptor: *descriptor
+		})
+	}
+
+	/** The buffer currently holding the data. */
+	pub fn buffer(&self) -> &VertexBuffer {
+		&self.buffer
+	}
+
+	/** Number of bytes written to the buffer so far, from its start up to the
+	 * end of the furthest write. */
+	pub fn len(&self) -> u32 {
+		self.len
+	}
+
+	/** Whether nothing has been written to the buffer since it was created or
+	 * last cleared. */
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+
+	/** Number of bytes the buffer can hold before it has to grow. */
+	pub fn capacity(&self) -> u32 {
+		self.buffer.len()
+	}
+
+	/** Forgets about everything written to the buffer so far, keeping its
+	 * capacity, so that the next write to the start of it doesn't have to
+	 * copy anything when it grows. */
+	pub fn clear(&mut self) {
+		self.len = 0
+	}
+
+	/** Writes the given data into the buffer, starting at the given byte
+	 * offset, growing it first if the data doesn't fit. */
+	pub fn write(&mut self, device: &Device, offset: u32, data: &[u8]) -> Result<(), BufferError> {
+		let end = u32::try_from(data.len()).ok()
+			.and_then(|len| offset.checked_add(len))
+			.ok_or(BufferError::TooLarge {
+				computed_bytes: u64::from(offset) + data.len() as u64
+			})?;
+
+		if let Some(capacity) = grown_capacity(self.capacity(), end) {
+			let buffer = device.create_vertex_buffer(
+				&BufferDescriptor {
+					size: capacity,
+					..self.descriptor
+				})?;
+			if let Some(label) = self.buffer.label() {
+				buffer.set_label(&label);
+			}
+			device.copy_buffer_to_buffer(&self.buffer, 0, &buffer, 0, self.len)?;
+			self.buffer = buffer;
+		}
+
+		self.buffer.write(offset, data)?;
+		self.len = self.len.max(end);
+
+		Ok(())
+	}
+
+	/** Writes the given data right after everything written so far, growing
+	 * the buffer if need be, and returns the byte offset it was written at. */
+	pub fn push(&mut self, device: &Device, data: &[u8]) -> Result<u32, BufferError> {
+		let offset = self.len;
+		self.write(device, offset, data)?;
+
+		Ok(offset)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn capacity_doubles_until_the_data_fits() {
+		assert_eq!(grown_capacity(64, 64), None);
+		assert_eq!(grown_capacity(64, 0), None);
+		assert_eq!(grown_capacity(64, 65), Some(128));
+		assert_eq!(grown_capacity(64, 300), Some(512));
+		assert_eq!(grown_capacity(0, 3), Some(4));
+
+		/* Doubling past the largest size a buffer can have stops right at
+		 * what is needed. */
+		assert_eq!(grown_capacity(0x8000_0000, 0x8000_0001), Some(0x8000_0001));
+	}
+}
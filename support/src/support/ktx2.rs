@@ -0,0 +1,374 @@
+use gavle::*;
+use std::convert::{TryFrom, TryInto};
+use std::num::NonZeroU32;
+use crate::BlockEncoding;
+
+/** Identifier every KTX2 file starts with. */
+const KTX2_IDENTIFIER: [u8; 12] = [
+	0xab, 0x4b, 0x54, 0x58, 0x20, 0x32, 0x30, 0xbb, 0x0d, 0x0a, 0x1a, 0x0a
+];
+
+/** Length of the header of a KTX2 file, including the index, up to the point
+ * where the level index starts. */
+const KTX2_HEADER_LEN: usize = 80;
+
+/** Length of every entry of the level index of a KTX2 file. */
+const KTX2_LEVEL_LEN: usize = 24;
+
+/** How the texels of a KTX2 texture are stored. */
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Ktx2Encoding {
+	/** RGBA with eight bits for every channel. */
+	Rgba8,
+	/** Compressed in blocks of texels. */
+	Block(BlockEncoding),
+}
+
+/** A two-dimensional texture loaded from a KTX2 container, along with all of
+ * the mip levels stored in it.
+ *
+ * Only textures in RGBA with eight bits for every channel, and textures
+ * compressed with BC1 through BC3 or with ETC2 are supported, either linear or
+ * with their colors encoded with the sRGB transfer function. Supercompressed
+ * containers, such as those holding Basis Universal textures, are not.
+ *
+ * Rows are kept in the order they are stored in, which, for most files, goes
+ * from the top of the image to the bottom. Textures created from them end up
+ * upside down as far as OpenGL is concerned, so texture coordinates have to
+ * start at the top, just like with images. */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ktx2Texture {
+	/** How the texels are stored. */
+	encoding: Ktx2Encoding,
+	/** Whether the color channels are encoded with the sRGB transfer
+	 * function. */
+	srgb: bool,
+	/** Width of the base level, in texels. */
+	width: u32,
+	/** Height of the base level, in texels. */
+	height: u32,
+	/** Data of every level, starting with the base level. */
+	levels: Vec<Vec<u8>>,
+	/** Whether the file asks for mip levels to be generated, rather than
+	 * having any stored in it. */
+	generate_mips: bool,
+}
+impl Ktx2Texture {
+	/** Parses a texture from the contents of a KTX2 file. */
+	pub fn parse(bytes: &[u8]) -> Result<Self, Ktx2Error> {
+		if bytes.len() < KTX2_HEADER_LEN || bytes[..12] != KTX2_IDENTIFIER {
+			return Err(Ktx2Error::NotKtx2)
+		}
+		let u32_at = |offset: usize|
+			u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+
+		let vk_format = u32_at(12);
+		let (encoding, srgb) = match vk_format {
+			37 => (Ktx2Encoding::Rgba8, false),
+			43 => (Ktx2Encoding::Rgba8, true),
+			131 | 133 => (Ktx2Encoding::Block(BlockEncoding::Bc1), false),
+			132 | 134 => (Ktx2Encoding::Block(BlockEncoding::Bc1), true),
+			135 => (Ktx2Encoding::Block(BlockEncoding::Bc2), false),
+			136 => (Ktx2Encoding::Block(BlockEncoding::Bc2), true),
+			137 => (Ktx2Encoding::Block(BlockEncoding::Bc3), false),
+			138 => (Ktx2Encoding::Block(BlockEncoding::Bc3), true),
+			147 => (Ktx2Encoding::Block(BlockEncoding::Etc2Rgb), false),
+			148 => (Ktx2Encoding::Block(BlockEncoding::Etc2Rgb), true),
+			151 => (Ktx2Encoding::Block(BlockEncoding::Etc2Rgba), false),
+			152 => (Ktx2Encoding::Block(BlockEncoding::Etc2Rgba), true),
+			_ => return Err(Ktx2Error::UnsupportedFormat { vk_format })
+		};
+
+		let (width, height, depth) = (u32_at(20), u32_at(24), u32_at(28));
+		let (layers, faces) = (u32_at(32), u32_at(36));
+		if width == 0 || height == 0 || depth != 0 || layers != 0 || faces != 1 {
+			return Err(Ktx2Error::Unsupported {
+				what: format!("only two-dimensional textures are supported, \
+					not {}x{}x{} ones with {} layers and {} faces",
+					width, height, depth, layers, faces)
+			})
+		}
+
+		let supercompression = u32_at(44);
+		if supercompression != 0 {
+			return Err(Ktx2Error::Unsupported {
+				what: format!("supercompression scheme {} is not supported",
+					supercompression)
+			})
+		}
+
+		/* A level count of zero asks for the levels to be generated from the
+		 * one that is there. */
+		let level_count = u32_at(40);
+		let generate_mips = level_count == 0;
+		let level_count = level_count.max(1) as usize;
+		let max_levels = 32 - width.max(height).leading_zeros();
+		if level_count > max_levels as usize {
+			return Err(Ktx2Error::Invalid {
+				what: format!("{} levels were given, but a {}x{} texture has \
+					at most {}", level_count, width, height, max_levels)
+			})
+		}
+
+		let index_end = KTX2_HEADER_LEN + level_count * KTX2_LEVEL_LEN;
+		if bytes.len() < index_end {
+			return Err(Ktx2Error::Invalid {
+				what: "the level index runs past the end of the file".into()
+			})
+		}
+
+		let mut levels = Vec::with_capacity(level_count);
+		for level in 0..level_count {
+			let entry = KTX2_HEADER_LEN + level * KTX2_LEVEL_LEN;
+			let u64_at = |offset: usize|
+				u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap());
+			let (offset, len) = (u64_at(entry), u64_at(entry + 8));
+
+			let range = usize::try_from(offset).ok()
+				.and_then(|offset| Some(offset..offset.checked_add(usize::try_from(len).ok()?)?))
+				.filter(|range| range.end <= bytes.len())
+				.ok_or_else(|| Ktx2Error::Invalid {
+					what: format!("level {} runs past the end of the file", level)
+				})?;
+
+			let level_width = (width >> level).max(1);
+			let level_height = (height >> level).max(1);
+			let expected = match encoding {
+				Ktx2Encoding::Rgba8 => level_width as usize * level_height as usize * 4,
+				Ktx2Encoding::Block(block) => block.len(level_width, level_height)
+			};
+			if range.len() != expected {
+				return Err(Ktx2Error::Invalid {
+					what: format!("level {} takes up {} bytes rather than the \
+						{} expected of a {}x{} level",
+						level, range.len(), expected, level_width, level_height)
+				})
+			}
+
+			levels.push(bytes[range].to_vec());
+		}
+
+		Ok(Self {
+			encoding,
+			srgb,
+			width,
+			height,
+			levels,
+			generate_mips
+		})
+	}
+
+	/** How the texels of this texture are stored. */
+	pub fn encoding(&self) -> Ktx2Encoding {
+		self.encoding
+	}
+
+	/** Whether the color channels of this texture are encoded with the sRGB
+	 * transfer function. */
+	pub fn is_srgb(&self) -> bool {
+		self.srgb
+	}
+
+	/** Width of the base level, in texels. */
+	pub fn width(&self) -> u32 {
+		self.width
+	}
+
+	/** Height of the base level, in texels. */
+	pub fn height(&self) -> u32 {
+		self.height
+	}
+
+	/** Number of mip levels stored in this texture. */
+	pub fn levels(&self) -> u32 {
+		self.levels.len() as u32
+	}
+
+	/** The format this texture could be uploaded in without converting its
+	 * data, if there is one, regardless of what the device supports. */
+	pub fn native_format(&self) -> Option<TextureFormat> {
+		match (self.encoding, self.srgb) {
+			(Ktx2Encoding::Rgba8, false) => Some(TextureFormat::Rgba8Unorm),
+			(Ktx2Encoding::Rgba8, true) => Some(TextureFormat::Srgb8Alpha8),
+			(Ktx2Encoding::Block(_), true) => None,
+			(Ktx2Encoding::Block(block), false) => Some(match block {
+				BlockEncoding::Bc1 => TextureFormat::Bc1RgbaUnorm,
+				BlockEncoding::Bc2 => TextureFormat::Bc2RgbaUnorm,
+				BlockEncoding::Bc3 => TextureFormat::Bc3RgbaUnorm,
+				BlockEncoding::Etc2Rgb => TextureFormat::Etc2Rgb8Unorm,
+				BlockEncoding::Etc2Rgba => TextureFormat::Etc2Rgba8Unorm,
+			}),
+		}
+	}
+
+	/** Creates a texture holding this one on the given device.
+	 *
+	 * The data gets uploaded as it is if the [`Information`] of the device
+	 * reports its format as supported. Otherwise, every level gets
+	 * decompressed on the host into RGBA with eight bits for every channel,
+	 * which takes up to eight times as much memory as the compressed data. The
+	 * same goes for compressed textures with sRGB colors, which have no
+	 * compressed format to go in.
+	 *
+	 * [`Information`]: gavle::Information */
+	pub fn create_texture(&self, device: &Device) -> Result<Texture, Ktx2Error> {
+		let information = device.information();
+		let mip = if self.generate_mips {
+			Mipmap::Automatic { filter: FilterType::Triangle }
+		} else if self.levels.len() > 1 {
+			Mipmap::Manual { levels: NonZeroU32::new(self.levels()).unwrap() }
+		} else {
+			Mipmap::None
+		};
+
+		let native = self.native_format()
+			.filter(|format| information.supports_texture_format(*format));
+		let (format, data) = match native {
+			Some(format) => (format, self.levels.concat()),
+			None => {
+				let mut format = if self.srgb {
+					TextureFormat::Srgb8Alpha8
+				} else {
+					TextureFormat::Rgba8Unorm
+				};
+				if !information.supports_texture_format(format) {
+					log::warn!(target: "support::ktx2",
+						"Storing a texture with sRGB colors as {:?}, as {:?} \
+						is not supported", TextureFormat::Rgba8Unorm, format);
+					format = TextureFormat::Rgba8Unorm;
+				}
+				if let Ktx2Encoding::Block(block) = self.encoding {
+					log::info!(target: "support::ktx2",
+						"Decompressing a {}x{} {:?} texture on the host, as \
+						the context can't sample it", self.width, self.height,
+						block);
+				}
+
+				(format, self.decompressed())
+			}
+		};
+		let mip = match mip {
+			Mipmap::Automatic { .. } if format.is_compressed() => Mipmap::None,
+			mip => mip
+		};
+
+		let texture = device.create_texture_with_data(
+			&TextureDescriptor {
+				extent: TextureExtent::D2 {
+					width: self.width,
+					height: self.height
+				},
+				format,
+				mip,
+				swizzle: None
+			},
+			data)?;
+		Ok(texture)
+	}
+
+	/** Every level of this texture in RGBA with eight bits for every channel,
+	 * one after the other. */
+	fn decompressed(&self) -> Vec<u8> {
+		let block = match self.encoding {
+			Ktx2Encoding::Rgba8 => return self.levels.concat(),
+			Ktx2Encoding::Block(block) => block
+		};
+
+		let mut texels = Vec::new();
+		for (level, data) in (0..).zip(&self.levels) {
+			let width = (self.width >> level).max(1);
+			let height = (self.height >> level).max(1);
+			texels.extend(block.decompress(width, height, data)
+				.expect("the length of every level was checked while parsing"));
+		}
+		texels
+	}
+}
+
+/** Errors that may happen while loading a KTX2 texture. */
+#[derive(Debug, thiserror::Error)]
+pub enum Ktx2Error {
+	#[error("the file is not a ktx2 container")]
+	NotKtx2,
+	#[error("textures of vulkan format {vk_format} are not supported")]
+	UnsupportedFormat {
+		vk_format: u32
+	},
+	#[error("the texture is not supported: {what}")]
+	Unsupported {
+		what: String
+	},
+	#[error("the container is invalid: {what}")]
+	Invalid {
+		what: String
+	},
+	#[error("could not create the texture: {0}")]
+	Texture(#[from] TextureError),
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/** A KTX2 container of the given format and size, with the given levels
+	 * laid out one after the other, right after the level index. */
+	fn container(vk_format: u32, width: u32, height: u32, levels: &[&[u8]]) -> Vec<u8> {
+		let mut bytes = KTX2_IDENTIFIER.to_vec();
+		for value in &[vk_format, 1, width, height, 0, 0, 1, levels.len() as u32, 0] {
+			bytes.extend_from_slice(&value.to_le_bytes());
+		}
+		bytes.resize(KTX2_HEADER_LEN, 0);
+
+		let mut offset = (KTX2_HEADER_LEN + levels.len() * KTX2_LEVEL_LEN) as u64;
+		for level in levels {
+			let len = level.len() as u64;
+			for value in &[offset, len, len] {
+				bytes.extend_from_slice(&value.to_le_bytes());
+			}
+			offset += len;
+		}
+		for level in levels {
+			bytes.extend_from_slice(level);
+		}
+		bytes
+	}
+
+	#[test]
+	fn parses_levels() {
+		let bytes = container(133, 8, 4, &[&[1; 16], &[2; 8], &[3; 8], &[4; 8]]);
+		let texture = Ktx2Texture::parse(&bytes).unwrap();
+
+		assert_eq!(texture.encoding(), Ktx2Encoding::Block(BlockEncoding::Bc1));
+		assert!(!texture.is_srgb());
+		assert_eq!((texture.width(), texture.height(), texture.levels()), (8, 4, 4));
+		assert_eq!(texture.native_format(), Some(TextureFormat::Bc1RgbaUnorm));
+		assert_eq!(texture.levels[1], vec![2; 8]);
+	}
+
+	#[test]
+	fn srgb_blocks_have_no_native_format() {
+		let bytes = container(152, 4, 4, &[&[0; 16]]);
+		let texture = Ktx2Texture::parse(&bytes).unwrap();
+		assert!(texture.is_srgb());
+		assert_eq!(texture.native_format(), None);
+		assert_eq!(texture.decompressed().len(), 4 * 4 * 4);
+	}
+
+	#[test]
+	fn rejects_bad_containers() {
+		assert!(matches!(Ktx2Texture::parse(b"KTX 11"), Err(Ktx2Error::NotKtx2)));
+		assert!(matches!(
+			Ktx2Texture::parse(&container(100, 4, 4, &[&[0; 16]])),
+			Err(Ktx2Error::UnsupportedFormat { vk_format: 100 })));
+
+		/* Levels must be exactly as long as their extent calls for. */
+		assert!(matches!(
+			Ktx2Texture::parse(&container(37, 2, 2, &[&[0; 15]])),
+			Err(Ktx2Error::Invalid { .. })));
+
+		/* And must not run past the end of the file. */
+		let mut bytes = container(37, 1, 1, &[&[0; 4]]);
+		bytes.truncate(bytes.len() - 1);
+		assert!(matches!(Ktx2Texture::parse(&bytes), Err(Ktx2Error::Invalid { .. })));
+	}
+}
@@ -0,0 +1,521 @@
+use gavle::*;
+use std::borrow::Cow;
+use std::convert::TryFrom;
+use std::f32::consts::PI;
+use crate::support::Matrix4;
+
+/** Maximum number of lights that can be drawn in a single accumulation pass.
+ *
+ * This must match the size of the light array in the accumulation shaders. */
+pub const MAX_ACCUMULATED_LIGHTS: u32 = 256;
+
+/** Number of segments around the equator of the light volume sphere. */
+const SPHERE_SEGMENTS: u32 = 16;
+/** Number of rings from pole to pole of the light volume sphere. */
+const SPHERE_RINGS: u32 = 8;
+
+/** A point light with a limited radius of influence. */
+#[derive(Debug, Copy, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "PascalCase")]
+pub struct PointLight {
+	/** Position of the light, in world space. */
+	pub position: [f32; 3],
+	/** Distance from the position of the light after which it has no effect. */
+	pub radius: f32,
+	/** Color of the light. */
+	pub color: [f32; 3],
+	/** Multiplier applied to the color of the light. */
+	pub intensity: f32,
+}
+impl PointLight {
+	/** Whether any part of the volume of influence of this light may end up
+	 * on the screen, given the view-projection transformation of the camera.
+	 *
+	 * This checks the sphere of influence of the light against each of the
+	 * six planes of the view frustum, so lights entirely to the side of the
+	 * viewport, or entirely in front of the near plane or past the far plane
+	 * are rejected. This test is conservative, and some lights near the edges
+	 * of the frustum may pass it even if they're not really visible. */
+	pub fn is_visible(&self, view_projection: &Matrix4) -> bool {
+		let m = view_projection.as_row_major_array();
+		let row = |i: usize| [m[i * 4], m[i * 4 + 1], m[i * 4 + 2], m[i * 4 + 3]];
+		let w = row(3);
+
+		for i in 0..3 {
+			let r = row(i);
+			for sign in [-1.0f32, 1.0].iter() {
+				let plane = [
+					w[0] + sign * r[0],
+					w[1] + sign * r[1],
+					w[2] + sign * r[2],
+					w[3] + sign * r[3]
+				];
+				let length = (
+					plane[0] * plane[0]
+						+ plane[1] * plane[1]
+						+ plane[2] * plane[2]).sqrt();
+				if length == 0.0 { continue }
+
+				let distance = (
+					plane[0] * self.position[0]
+						+ plane[1] * self.position[1]
+						+ plane[2] * self.position[2]
+						+ plane[3]) / length;
+				if distance < -self.radius {
+					return false
+				}
+			}
+		}
+
+		true
+	}
+}
+
+/** Camera parameters needed to accumulate lights onto a scene. */
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LightView {
+	/** View-projection transformation the scene was rendered with. */
+	pub view_projection: Matrix4,
+	/** Inverse of the view-projection transformation, used to reconstruct the
+	 * world space position of the scene from its depth. */
+	pub inverse_view_projection: Matrix4,
+	/** Viewport the scene was rendered to. */
+	pub viewport: Viewport,
+}
+
+/** Uniform data shared by all lights in the accumulation pass. */
+#[derive(Debug, Copy, Clone, PartialEq, bytemuck::Zeroable, bytemuck::Pod)]
+#[repr(C)]
+struct LightGlobals {
+	view_projection: Matrix4,
+	inverse_view_projection: Matrix4,
+	viewport: [f32; 4],
+}
+
+/** Per-light uniform data, laid out to match the `std140` shader structure. */
+#[derive(Debug, Copy, Clone, PartialEq, bytemuck::Zeroable, bytemuck::Pod)]
+#[repr(C)]
+struct LightInstance {
+	position_radius: [f32; 4],
+	color_intensity: [f32; 4],
+}
+
+/** Helper for additive light accumulation.
+ *
+ * After the base pass of a scene has been rendered, this structure draws the
+ * volume of influence of each queued point light as a sphere with additive
+ * blending, shading the scene underneath it by reading back its depth and
+ * normals. This lets scenes have many more lights than could be looped over in
+ * a single forward shader.
+ *
+ * Lights get queued up with [`queue()`], culled against the view frustum and
+ * uploaded with [`prepare()`], and drawn in a single instanced draw call with
+ * [`draw()`]. The framebuffer of the render pass used for drawing must have
+ * the depth buffer of the scene attached to it, as light volumes are depth
+ * tested against it.
+ *
+ * [`queue()`]: Self::queue
+ * [`prepare()`]: Self::prepare
+ * [`draw()`]: Self::draw */
+pub struct LightAccumulator {
+	/** Additive light volume pipeline. */
+	pipeline: RenderPipeline,
+	/** Vertices of the light volume sphere. */
+	vertices: VertexBuffer,
+	/** Indices of the light volume sphere. */
+	indices: IndexBuffer,
+	/** Number of indices in the light volume sphere. */
+	index_count: u32,
+	/** Uniform buffer holding the globals of the pass. */
+	globals: UniformBuffer,
+	/** Uniform buffer holding the array of lights. */
+	lights: UniformBuffer,
+	/** Binding group for the last prepared pass, if any. */
+	bind: Option<UniformGroup>,
+	/** Lights queued up for the next pass. */
+	queue: Vec<PointLight>,
+	/** Number of lights that survived culling in the last prepared pass. */
+	visible: u32,
+}
+impl LightAccumulator {
+	/** Layout of the light volume vertex buffer. */
+	const LAYOUT: VertexBufferLayout<'static> = VertexBufferLayout {
+		array_stride: 12,
+		attributes: &[
+			VertexAttribute {
+				kind: VertexType::F32,
+				components: VertexComponents::Three,
+				offset: 0,
+				binding: Cow::Borrowed("tt_vert_position"),
+				location: None
+			},
+		]
+	};
+
+	/** Create a new light accumulator on the given device. */
+	pub fn new(device: &Device) -> Result<Self, LightAccumulatorError> {
+		let (vertices, indices) = sphere();
+		let index_count = u32::try_from(indices.len()).unwrap();
+
+		let vertices = device.create_vertex_buffer_with_data(
+			&BufferDescriptor {
+				size: buffer_size_of::<[f32; 3]>(vertices.len())?,
+				profile: BufferProfile::StaticUpload,
+				contents: BufferContents::F32,
+				..Default::default()
+			},
+			bytemuck::cast_slice(&vertices[..]))?;
+		let indices = device.create_index_buffer_with_data(
+			&BufferDescriptor {
+				size: buffer_size_of::<u32>(indices.len())?,
+				profile: BufferProfile::StaticUpload,
+				contents: BufferContents::Opaque,
+				..Default::default()
+			},
+			bytemuck::cast_slice(&indices[..]))?;
+
+		let globals = device.create_uniform_buffer(
+			&BufferDescriptor {
+				size: u32::try_from(std::mem::size_of::<LightGlobals>()).unwrap(),
+				profile: BufferProfile::DynamicUpload,
+				contents: BufferContents::F32,
+				..Default::default()
+			})?;
+		let lights = device.create_uniform_buffer(
+			&BufferDescriptor {
+				size: u32::try_from(std::mem::size_of::<LightInstance>()).unwrap()
+					* MAX_ACCUMULATED_LIGHTS,
+				profile: BufferProfile::DynamicUpload,
+				contents: BufferContents::F32,
+				..Default::default()
+			})?;
+
+		let vertex = device.create_vertex_shader(
+			ShaderSource::Glsl(Cow::Borrowed(include_str!("lights/vert.glsl"))))?;
+		let fragment = device.create_fragment_shader(
+			ShaderSource::Glsl(Cow::Borrowed(include_str!("lights/frag.glsl"))))?;
+
+		let pipeline = device.create_render_pipeline(
+			&RenderPipelineDescriptor {
+				vertex: VertexState {
+					shader: &vertex,
+					buffer: &Self::LAYOUT,
+					instance: None
+				},
+				primitive_state: PrimitiveState {
+					topology: PrimitiveTopology::TriangleList,
+					index_format: IndexFormat::Uint32,
+					front_face: FrontFace::Ccw,
+					/* Draw the back faces of the volumes, so that lights
+					 * still get drawn with the camera inside of them. */
+					cull_mode: CullMode::Front,
+					polygon_mode: PolygonMode::Fill,
+					line_width: 1.0,
+					program_point_size: false
+				},
+				fragment: Some(FragmentState {
+					shader: &fragment,
+					targets: &[ColorTargetState {
+						alpha_blend: BlendState::REPLACE,
+						color_blend: BlendState {
+							src_factor: BlendFactor::One,
+							dst_factor: BlendFactor::One,
+							operation: BlendOperation::Add
+						},
+						write_mask: ColorWrite::all(),
+					}]
+				}),
+				/* Only shade the parts of the scene in front of the back faces
+				 * of the volumes, and leave the depth of the scene alone. */
+				depth_stencil: Some(DepthStencilState::new(
+					false,
+					CompareFunction::GreaterEqual,
+					StencilState::IGNORE))
+			})?;
+
+		Ok(Self {
+			pipeline,
+			vertices,
+			indices,
+			index_count,
+			globals,
+			lights,
+			bind: None,
+			queue: Vec::new(),
+			visible: 0
+		})
+	}
+
+	/** Queue a light up to be drawn in the next pass. */
+	pub fn queue(&mut self, light: PointLight) {
+		self.queue.push(light)
+	}
+
+	/** Number of lights that will be drawn by [`draw()`].
+	 *
+	 * [`draw()`]: Self::draw */
+	pub fn visible(&self) -> u32 {
+		self.visible
+	}
+
+	/** Cull the queued lights against the given view, upload the ones that
+	 * survive, and set up the bindings to the scene textures for the next call
+	 * to [`draw()`]. The queue is emptied afterwards.
+	 *
+	 * This must be called outside of a render pass, as it needs to write to
+	 * the uniform buffers of the accumulator.
+	 *
+	 * [`draw()`]: Self::draw */
+	pub fn prepare(
+		&mut self,
+		device: &Device,
+		view: &LightView,
+		scene_depth: &Texture,
+		scene_normals: &Texture)
+		-> Result<(), LightAccumulatorError> {
+
+		let lights = self.queue
+			.drain(..)
+			.filter(|light| light.is_visible(&view.view_projection))
+			.map(|light| LightInstance {
+				position_radius: [
+					light.position[0],
+					light.position[1],
+					light.position[2],
+					light.radius
+				],
+				color_intensity: [
+					light.color[0],
+					light.color[1],
+					light.color[2],
+					light.intensity
+				]
+			})
+			.collect::<Vec<_>>();
+
+		let count = u32::try_from(lights.len()).unwrap_or(u32::MAX);
+		self.visible = if count > MAX_ACCUMULATED_LIGHTS {
+			log::warn!("Clipping the number of accumulated lights from {} to \
+				the maximum of {}", count, MAX_ACCUMULATED_LIGHTS);
+			MAX_ACCUMULATED_LIGHTS
+		} else {
+			count
+		};
+
+		let globals = LightGlobals {
+			view_projection: view.view_projection.transpose(),
+			inverse_view_projection: view.inverse_view_projection.transpose(),
+			viewport: [
+				view.viewport.x as f32,
+				view.viewport.y as f32,
+				view.viewport.width as f32,
+				view.viewport.height as f32
+			]
+		};
+		let data = bytemuck::bytes_of(&globals);
+		let slice = self.globals.slice(..);
+		let mut map = slice.try_map_mut(BufferLoadOp::DontCare, MapMode::Orphan)
+			.unwrap();
+		map[..data.len()].copy_from_slice(data);
+		drop(map);
+
+		if self.visible > 0 {
+			let data = bytemuck::cast_slice(&lights[..self.visible as usize]);
+			let size = u32::try_from(data.len()).unwrap();
+
+			let slice = self.lights.slice(..size);
+			let mut map = slice.try_map_mut(BufferLoadOp::DontCare, MapMode::Orphan)
+				.unwrap();
+			map.copy_from_slice(data);
+		}
+
+		self.bind = Some(device.create_uniform_bind_group(
+			&UniformGroupDescriptor {
+				entries: &[
+					UniformGroupEntry {
+						binding: "rc_light_globals".into(),
+						kind: UniformBind::Buffer {
+							buffer: &self.globals,
+							offset: 0,
+							size: None
+						}
+					},
+					UniformGroupEntry {
+						binding: "rc_lights".into(),
+						kind: UniformBind::Buffer {
+							buffer: &self.lights,
+							offset: 0,
+							size: None
+						}
+					},
+					UniformGroupEntry {
+						binding: "tt_scene_depth".into(),
+						kind: UniformBind::Texture {
+							texture: scene_depth,
+							far: TextureFilter::Nearest,
+							near: TextureFilter::Nearest,
+							address_mode: AddressMode::Repeat,
+							anisotropy_clamp: None,
+							sampler: None
+						}
+					},
+					UniformGroupEntry {
+						binding: "tt_scene_normals".into(),
+						kind: UniformBind::Texture {
+							texture: scene_normals,
+							far: TextureFilter::Nearest,
+							near: TextureFilter::Nearest,
+							address_mode: AddressMode::Repeat,
+							anisotropy_clamp: None,
+							sampler: None
+						}
+					},
+				]
+			})?);
+
+		Ok(())
+	}
+
+	/** Draw the lights set up by the last call to [`prepare()`] into the given
+	 * render pass. Nothing is drawn if there are no visible lights.
+	 *
+	 * This changes the pipeline, buffers and bind group of the pass.
+	 *
+	 * [`prepare()`]: Self::prepare */
+	pub fn draw<'a>(&'a self, pass: &mut RenderPass<'a>) {
+		let bind = match &self.bind {
+			Some(bind) if self.visible > 0 => bind,
+			_ => return
+		};
+
+		pass.set_pipeline(&self.pipeline);
+		pass.set_bind_group(bind);
+		pass.set_vertex_buffer(&self.vertices);
+		pass.set_index_buffer(&self.indices);
+
+		pass.draw_indexed(0..self.index_count, self.visible)
+			.expect("the light volume holds all of its indices");
+	}
+}
+
+/** Generate the mesh for the light volume sphere.
+ *
+ * The vertices of the sphere are pushed out just enough for its faces to fully
+ * contain the unit sphere, so that no part of the volume of a light gets cut
+ * off by the coarseness of the mesh. */
+fn sphere() -> (Vec<[f32; 3]>, Vec<u32>) {
+	let scale = 1.0 / (
+		(PI / SPHERE_SEGMENTS as f32).cos()
+			* (PI / (2 * SPHERE_RINGS) as f32).cos());
+
+	let mut vertices = Vec::new();
+	for ring in 0..=SPHERE_RINGS {
+		let theta = PI * ring as f32 / SPHERE_RINGS as f32;
+		for segment in 0..=SPHERE_SEGMENTS {
+			let phi = 2.0 * PI * segment as f32 / SPHERE_SEGMENTS as f32;
+			vertices.push([
+				theta.sin() * phi.cos() * scale,
+				theta.cos() * scale,
+				theta.sin() * phi.sin() * scale
+			]);
+		}
+	}
+
+	let stride = SPHERE_SEGMENTS + 1;
+	let mut indices = Vec::new();
+	for ring in 0..SPHERE_RINGS {
+		for segment in 0..SPHERE_SEGMENTS {
+			let a = ring * stride + segment;
+			let b = a + stride;
+
+			indices.extend_from_slice(&[a, a + 1, b]);
+			indices.extend_from_slice(&[a + 1, b + 1, b]);
+		}
+	}
+
+	(vertices, indices)
+}
+
+/** Errors that may happen while creating a light accumulator. */
+#[derive(Debug, thiserror::Error)]
+pub enum LightAccumulatorError {
+	#[error("could not create light accumulator buffers: {0}")]
+	Buffer(#[from] BufferError),
+	#[error("could not create light accumulator shaders: {0}")]
+	Shader(#[from] ShaderError),
+	#[error("could not create light accumulator pipeline: {0}")]
+	Pipeline(#[from] RenderPipelineError),
+	#[error("could not bind the scene to the light accumulator: {0}")]
+	BindGroup(#[from] BindGroupError),
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn light(x: f32, y: f32, z: f32, radius: f32) -> PointLight {
+		PointLight {
+			position: [x, y, z],
+			radius,
+			color: [1.0; 3],
+			intensity: 1.0
+		}
+	}
+
+	fn projection() -> Matrix4 {
+		Matrix4::rectilinear_projection(
+			std::f32::consts::FRAC_PI_2,
+			1.0,
+			1.0,
+			100.0)
+	}
+
+	#[test]
+	fn accepts_lights_in_view() {
+		assert!(light(0.0, 0.0, 10.0, 1.0).is_visible(&projection()));
+		assert!(light(9.0, -9.0, 10.0, 1.0).is_visible(&projection()));
+	}
+
+	#[test]
+	fn rejects_lights_beside_the_viewport() {
+		assert!(!light(20.0, 0.0, 10.0, 1.0).is_visible(&projection()));
+		assert!(!light(0.0, -20.0, 10.0, 1.0).is_visible(&projection()));
+
+		/* Partially overlapping the viewport is still visible. */
+		assert!(light(10.5, 0.0, 10.0, 1.0).is_visible(&projection()));
+	}
+
+	#[test]
+	fn rejects_lights_clipped_by_depth() {
+		/* Behind the camera, and fully in front of the near clipping plane. */
+		assert!(!light(0.0, 0.0, -10.0, 1.0).is_visible(&projection()));
+		assert!(!light(0.0, 0.0, 0.2, 0.25).is_visible(&projection()));
+		/* Straddling the near clipping plane. */
+		assert!(light(0.0, 0.0, 0.2, 1.0).is_visible(&projection()));
+		/* Past the far plane. */
+		assert!(!light(0.0, 0.0, 110.0, 5.0).is_visible(&projection()));
+	}
+
+	#[test]
+	fn sphere_contains_unit_sphere() {
+		let (vertices, indices) = sphere();
+		for triangle in indices.chunks_exact(3) {
+			let a = vertices[triangle[0] as usize];
+			let b = vertices[triangle[1] as usize];
+			let c = vertices[triangle[2] as usize];
+
+			let u = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+			let v = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+			let n = [
+				u[1] * v[2] - u[2] * v[1],
+				u[2] * v[0] - u[0] * v[2],
+				u[0] * v[1] - u[1] * v[0]
+			];
+			let length = (n[0] * n[0] + n[1] * n[1] + n[2] * n[2]).sqrt();
+			if length < 1e-6 { continue }
+
+			let distance = (n[0] * a[0] + n[1] * a[1] + n[2] * a[2]) / length;
+			assert!(distance.abs() >= 1.0 - 1e-4, "face at {} cuts in", distance);
+		}
+	}
+}
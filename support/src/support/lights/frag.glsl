@@ -0,0 +1,49 @@
+#version 300 es
+precision highp float;
+
+#define MAX_LIGHTS 256
+struct Light
+{
+/* Position of the light in world space, and its radius of influence. */
+    vec4 position_radius;
+/* Color of the light, and its intensity. */
+    vec4 color_intensity;
+};
+
+layout(std140) uniform rc_light_globals
+{
+    mat4 ViewProjection;
+    mat4 InverseViewProjection;
+    vec4 Viewport;
+};
+
+layout(std140) uniform rc_lights
+{
+    Light Lights[MAX_LIGHTS];
+};
+
+uniform highp sampler2D tt_scene_depth;
+uniform highp sampler2D tt_scene_normals;
+
+flat in int vs_light;
+
+out vec4 color;
+void main()
+{
+    Light light = Lights[vs_light];
+
+    /* Reconstruct the world position of the surface behind this fragment. */
+    vec2  uv    = (gl_FragCoord.xy - Viewport.xy) / Viewport.zw;
+    float depth = texture(tt_scene_depth, uv).r;
+    vec4  world = InverseViewProjection * vec4(vec3(uv, depth) * 2.0 - 1.0, 1.0);
+    world /= world.w;
+
+    vec3  normal   = normalize(texture(tt_scene_normals, uv).xyz * 2.0 - 1.0);
+    vec3  to_light = light.position_radius.xyz - world.xyz;
+    float distance = length(to_light);
+
+    float falloff = clamp(1.0 - distance / light.position_radius.w, 0.0, 1.0);
+    float lambert = max(dot(normal, to_light / max(distance, 0.0001)), 0.0);
+
+    color = vec4(light.color_intensity.rgb * light.color_intensity.a * lambert * falloff * falloff, 1.0);
+}
@@ -0,0 +1,36 @@
+#version 300 es
+precision highp float;
+
+#define MAX_LIGHTS 256
+struct Light
+{
+/* Position of the light in world space, and its radius of influence. */
+    vec4 position_radius;
+/* Color of the light, and its intensity. */
+    vec4 color_intensity;
+};
+
+layout(std140) uniform rc_light_globals
+{
+    mat4 ViewProjection;
+    mat4 InverseViewProjection;
+    vec4 Viewport;
+};
+
+layout(std140) uniform rc_lights
+{
+    Light Lights[MAX_LIGHTS];
+};
+
+in vec3 tt_vert_position;
+
+flat out int vs_light;
+
+void main()
+{
+    Light light = Lights[gl_InstanceID];
+    vec3 world = light.position_radius.xyz + tt_vert_position * light.position_radius.w;
+
+    vs_light = gl_InstanceID;
+    gl_Position = ViewProjection * vec4(world, 1.0);
+}
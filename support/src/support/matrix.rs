@@ -70,7 +70,59 @@ impl Matrix4 {
 		])
 	}
 
-	/** Creates a new transformation which applies an orthographic projection. */
+	/** Creates a new transformation which applies a depth-based perspective
+	 * projection just like [`rectilinear_projection()`], except for depth
+	 * being reversed, going from `1` at the near plane down to `0` at the far
+	 * plane.
+	 *
+	 * Depth values get denser the closer they are to zero, and reversing depth
+	 * spreads them out over the distance, where a regular projection would
+	 * have them bunched up near the viewer, instead. Reversing the depth of a
+	 * scene takes all of the pieces of a pipeline to agree, however:
+	 * - Pipelines compare depth with [`CompareFunction::Greater`], or
+	 *   [`CompareFunction::GreaterEqual`], so that fragments closer to the
+	 *   viewer win out.
+	 * - Depth attachments get cleared to `0.0`, the depth of the far plane.
+	 * - Passes keep the default depth range, or, if they narrow it down with
+	 *   [`RenderPass::set_depth_range()`], keep the near value above the far
+	 *   one.
+	 *
+	 * Keep in mind that OpenGL maps depth onto the depth range from `-1` in
+	 * normalized device coordinates, not from `0`, so, like with a regular
+	 * projection, only the upper half of the depth buffer gets used, and the
+	 * offset added to every value on the way there eats into the precision
+	 * gained in the distance. Surfaces that are close to one another keep
+	 * their order in normalized device coordinates, but may still end up
+	 * with the same value in a depth buffer of limited precision.
+	 *
+	 * [`rectilinear_projection()`]: Self::rectilinear_projection
+	 * [`CompareFunction::Greater`]: gavle::CompareFunction::Greater
+	 * [`CompareFunction::GreaterEqual`]: gavle::CompareFunction::GreaterEqual
+	 * [`RenderPass::set_depth_range()`]: gavle::RenderPass::set_depth_range */
+	pub fn rectilinear_projection_reversed_z(fovy: f32, aspect: f32, n: f32, f: f32) -> Self {
+		let z = n / (n - f);
+		let c = -f * n / (n - f);
+
+		let f = 1.0 / f32::tan(fovy / 2.0);
+		let x = f / aspect;
+		Self([
+			  x, 0.0,  0.0, 0.0,
+			0.0,   f,  0.0, 0.0,
+			0.0, 0.0,    z,   c,
+			0.0, 0.0,  1.0, 0.0,
+		])
+	}
+
+	/** Creates a new transformation which applies an orthographic projection,
+	 * mapping the given box onto the normalized device coordinate cube, with
+	 * its left, bottom and near faces going to `-1` and its right, top and far
+	 * faces going to `1`.
+	 *
+	 * Just like with [`rectilinear_projection()`], the viewer looks towards
+	 * positive `z`, so the near and far faces are the distances in front of
+	 * the viewer the box starts and ends at.
+	 *
+	 * [`rectilinear_projection()`]: Self::rectilinear_projection */
 	pub fn orthographic_projection(
 		left: f32, right: f32,
 		top:  f32, bottom: f32,
@@ -79,14 +131,53 @@ impl Matrix4 {
 		let w = right - left;
 		let h = top - bottom;
 		let d = far - near;
+
+		let x = -(right + left) / w;
+		let y = -(top + bottom) / h;
+		let z = -(far + near) / d;
 		Self([
-			2.0 / w, 0.0,     0.0,     0.0,
-			0.0,     2.0 / h, 0.0,     0.0,
-			0.0,     0.0,     2.0 / d, 0.0,
+			2.0 / w, 0.0,     0.0,     x,
+			0.0,     2.0 / h, 0.0,     y,
+			0.0,     0.0,     2.0 / d, z,
 			0.0,     0.0,     0.0,     1.0
 		])
 	}
 
+	/** Creates a new view transformation for a viewer at the given eye point
+	 * looking at the given center point, with the given up direction, which
+	 * must not be parallel to the direction the viewer is looking in.
+	 *
+	 * The viewer ends up at the origin, looking towards positive `z`, as the
+	 * projections expect it to, with the up direction pointing towards
+	 * positive `y`, and the right of the viewer towards positive `x`. */
+	pub fn look_at(eye: [f32; 3], center: [f32; 3], up: [f32; 3]) -> Self {
+		let normalize = |[x, y, z]: [f32; 3]| {
+			let len = f32::sqrt(x * x + y * y + z * z);
+			[x / len, y / len, z / len]
+		};
+		let cross = |[ax, ay, az]: [f32; 3], [bx, by, bz]: [f32; 3]| [
+			ay * bz - az * by,
+			az * bx - ax * bz,
+			ax * by - ay * bx
+		];
+		let dot = |[ax, ay, az]: [f32; 3], [bx, by, bz]: [f32; 3]|
+			ax * bx + ay * by + az * bz;
+
+		let forward = normalize([
+			center[0] - eye[0],
+			center[1] - eye[1],
+			center[2] - eye[2]]);
+		let right = normalize(cross(up, forward));
+		let up = cross(forward, right);
+
+		Self([
+			  right[0],   right[1],   right[2], -dot(right, eye),
+			     up[0],      up[1],      up[2], -dot(up, eye),
+			forward[0], forward[1], forward[2], -dot(forward, eye),
+			       0.0,        0.0,        0.0, 1.0
+		])
+	}
+
 	/** Creates a new axis-angle rotation transformation with the given pivot
 	 * vector and rotation angle, given in radians. */
 	pub fn rotate(x: f32, y: f32, z: f32, angle: f32) -> Self {
@@ -170,6 +261,44 @@ impl Matrix4 {
 
 		(a11 * x) - (a21 * y) + (a31 * z) - (a41 * w)
 	}
+
+	/** Find the inverse of this matrix, which undoes the transformation this
+	 * one applies, if there is one. Matrices whose determinant is zero, such
+	 * as the ones flattening space onto a plane, have no inverse. */
+	pub fn inverse(&self) -> Option<Self> {
+		let det = self.det();
+		if det == 0.0 || !det.is_finite() { return None }
+
+		/* Indices from zero to three, except for the given one. */
+		let others = |k: usize| {
+			let mut indices = [0; 3];
+			for (slot, index) in indices.iter_mut().zip((0..4).filter(|i| *i != k)) {
+				*slot = index;
+			}
+			indices
+		};
+		/* Determinant of what's left of this matrix without the given row
+		 * and column. */
+		let minor = |i: usize, j: usize| {
+			let (rows, columns) = (others(i), others(j));
+			let m = |i: usize, j: usize| self.0[rows[i] * 4 + columns[j]];
+
+			m(0, 0) * (m(1, 1) * m(2, 2) - m(1, 2) * m(2, 1))
+				- m(0, 1) * (m(1, 0) * m(2, 2) - m(1, 2) * m(2, 0))
+				+ m(0, 2) * (m(1, 0) * m(2, 1) - m(1, 1) * m(2, 0))
+		};
+
+		/* The adjugate is the transpose of the matrix of cofactors. */
+		let mut inverse = [0.0; 16];
+		for i in 0..4 {
+			for j in 0..4 {
+				let sign = if (i + j) % 2 == 0 { 1.0 } else { -1.0 };
+				inverse[j * 4 + i] = sign * minor(i, j) / det;
+			}
+		}
+
+		Some(Self(inverse))
+	}
 }
 impl Default for Matrix4 {
 	fn default() -> Self {
@@ -270,10 +399,10 @@ impl std::ops::Mul for Matrix4 {
 			(a(2, 0) * b(0, 1)) + (a(2, 1) * b(1, 1)) + (a(2, 2) * b(2, 1)) + (a(2, 3) * b(3, 1)),
 			(a(2, 0) * b(0, 2)) + (a(2, 1) * b(1, 2)) + (a(2, 2) * b(2, 2)) + (a(2, 3) * b(3, 2)),
 			(a(2, 0) * b(0, 3)) + (a(2, 1) * b(1, 3)) + (a(2, 2) * b(2, 3)) + (a(2, 3) * b(3, 3)),
-			(a(3, 0) * b(3, 0)) + (a(3, 1) * b(1, 0)) + (a(3, 2) * b(2, 0)) + (a(3, 3) * b(3, 0)),
-			(a(3, 0) * b(3, 1)) + (a(3, 1) * b(1, 1)) + (a(3, 2) * b(2, 1)) + (a(3, 3) * b(3, 1)),
-			(a(3, 0) * b(3, 2)) + (a(3, 1) * b(1, 2)) + (a(3, 2) * b(2, 2)) + (a(3, 3) * b(3, 2)),
-			(a(3, 0) * b(3, 3)) + (a(3, 1) * b(1, 3)) + (a(3, 2) * b(2, 3)) + (a(3, 3) * b(3, 3)),
+			(a(3, 0) * b(0, 0)) + (a(3, 1) * b(1, 0)) + (a(3, 2) * b(2, 0)) + (a(3, 3) * b(3, 0)),
+			(a(3, 0) * b(0, 1)) + (a(3, 1) * b(1, 1)) + (a(3, 2) * b(2, 1)) + (a(3, 3) * b(3, 1)),
+			(a(3, 0) * b(0, 2)) + (a(3, 1) * b(1, 2)) + (a(3, 2) * b(2, 2)) + (a(3, 3) * b(3, 2)),
+			(a(3, 0) * b(0, 3)) + (a(3, 1) * b(1, 3)) + (a(3, 2) * b(2, 3)) + (a(3, 3) * b(3, 3)),
 		])
 	}
 }
@@ -297,10 +426,177 @@ impl std::ops::MulAssign for Matrix4 {
 			(a(2, 0) * b(0, 1)) + (a(2, 1) * b(1, 1)) + (a(2, 2) * b(2, 1)) + (a(2, 3) * b(3, 1)),
 			(a(2, 0) * b(0, 2)) + (a(2, 1) * b(1, 2)) + (a(2, 2) * b(2, 2)) + (a(2, 3) * b(3, 2)),
 			(a(2, 0) * b(0, 3)) + (a(2, 1) * b(1, 3)) + (a(2, 2) * b(2, 3)) + (a(2, 3) * b(3, 3)),
-			(a(3, 0) * b(3, 0)) + (a(3, 1) * b(1, 0)) + (a(3, 2) * b(2, 0)) + (a(3, 3) * b(3, 0)),
-			(a(3, 0) * b(3, 1)) + (a(3, 1) * b(1, 1)) + (a(3, 2) * b(2, 1)) + (a(3, 3) * b(3, 1)),
-			(a(3, 0) * b(3, 2)) + (a(3, 1) * b(1, 2)) + (a(3, 2) * b(2, 2)) + (a(3, 3) * b(3, 2)),
-			(a(3, 0) * b(3, 3)) + (a(3, 1) * b(1, 3)) + (a(3, 2) * b(2, 3)) + (a(3, 3) * b(3, 3)),
+			(a(3, 0) * b(0, 0)) + (a(3, 1) * b(1, 0)) + (a(3, 2) * b(2, 0)) + (a(3, 3) * b(3, 0)),
+			(a(3, 0) * b(0, 1)) + (a(3, 1) * b(1, 1)) + (a(3, 2) * b(2, 1)) + (a(3, 3) * b(3, 1)),
+			(a(3, 0) * b(0, 2)) + (a(3, 1) * b(1, 2)) + (a(3, 2) * b(2, 2)) + (a(3, 3) * b(3, 2)),
+			(a(3, 0) * b(0, 3)) + (a(3, 1) * b(1, 3)) + (a(3, 2) * b(2, 3)) + (a(3, 3) * b(3, 3)),
 		])
 	}
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/** Transforms the given point, dividing it by its resulting `w`. */
+	fn project(matrix: &Matrix4, [x, y, z]: [f32; 3]) -> [f32; 3] {
+		let m = matrix.as_row_major_array();
+		let row = |i: usize| m[i * 4] * x + m[i * 4 + 1] * y + m[i * 4 + 2] * z + m[i * 4 + 3];
+		let w = row(3);
+		[row(0) / w, row(1) / w, row(2) / w]
+	}
+
+	fn assert_close(a: [f32; 3], b: [f32; 3]) {
+		for (a, b) in a.iter().zip(&b) {
+			assert!((a - b).abs() < 1e-5, "{:?} != {:?}", a, b);
+		}
+	}
+
+	fn assert_matrices_close(a: &Matrix4, b: &Matrix4, epsilon: f32) {
+		let close = a.as_row_major_array().iter()
+			.zip(b.as_row_major_array())
+			.all(|(a, b)| (a - b).abs() <= epsilon * a.abs().max(b.abs()).max(1.0));
+		assert!(close, "{:?} != {:?}", a, b);
+	}
+
+	/** Matrices with elements in `[-2, 2)`, drawn from a fixed sequence, so
+	 * that failures can be reproduced. */
+	fn random_matrices(count: usize) -> Vec<Matrix4> {
+		let mut state = 0x2545_f491_4f6c_dd1du64;
+		let mut next = move || {
+			state ^= state << 13;
+			state ^= state >> 7;
+			state ^= state << 17;
+			(state >> 40) as f32 / (1u64 << 24) as f32 * 4.0 - 2.0
+		};
+
+		(0..count)
+			.map(|_| {
+				let mut array = [0.0; 16];
+				for element in &mut array { *element = next(); }
+				Matrix4::from_row_major_array(array)
+			})
+			.collect()
+	}
+
+	#[test]
+	fn orthographic_box_maps_to_the_cube() {
+		let (left, right, top, bottom, near, far) = (-2.0, 6.0, 3.0, -1.0, 0.5, 10.0);
+		let matrix = Matrix4::orthographic_projection(left, right, top, bottom, near, far);
+
+		for &(x, nx) in &[(left, -1.0), (right, 1.0)] {
+			for &(y, ny) in &[(bottom, -1.0), (top, 1.0)] {
+				for &(z, nz) in &[(near, -1.0), (far, 1.0)] {
+					assert_close(project(&matrix, [x, y, z]), [nx, ny, nz]);
+				}
+			}
+		}
+		assert_close(project(&matrix, [2.0, 1.0, 5.25]), [0.0, 0.0, 0.0]);
+	}
+
+	#[test]
+	fn reversed_depth_keeps_distant_surfaces_apart() {
+		let (near, far) = (0.1, 1000.0);
+		let regular = Matrix4::rectilinear_projection(1.2, 1.5, near, far);
+		let reversed = Matrix4::rectilinear_projection_reversed_z(1.2, 1.5, near, far);
+		let depth = |matrix: &Matrix4, z: f32| project(matrix, [0.0, 0.0, z])[2];
+
+		assert_close([depth(&regular, near), depth(&regular, far), 0.0], [0.0, 1.0, 0.0]);
+		assert_close([depth(&reversed, near), depth(&reversed, far), 0.0], [1.0, 0.0, 0.0]);
+
+		/* Two surfaces fighting in the distance, with the closer one drawn
+		 * last, over a depth attachment cleared to the far plane. */
+		let (front, back) = (depth(&reversed, 990.0), depth(&reversed, 990.4));
+		let cleared = 0.0;
+		assert!(back > cleared);
+		assert!(front > back, "{} <= {}", front, back);
+
+		/* The regular projection can't tell them apart anymore. */
+		let (front, back) = (depth(&regular, 990.0), depth(&regular, 990.4));
+		assert!(back - front <= f32::EPSILON, "{} and {}", front, back);
+	}
+
+	#[test]
+	fn look_at_puts_the_eye_at_the_origin() {
+		let eye = [1.0, 2.0, 3.0];
+		let matrix = Matrix4::look_at(eye, [1.0, 2.0, -1.0], [0.0, 1.0, 0.0]);
+		assert_close(project(&matrix, eye), [0.0, 0.0, 0.0]);
+
+		/* Looking down negative z turns the viewer around, so what was to its
+		 * right is now towards negative x. */
+		assert_close(project(&matrix, [1.0, 2.0, -1.0]), [0.0, 0.0, 4.0]);
+		assert_close(project(&matrix, [1.0, 5.0, 3.0]), [0.0, 3.0, 0.0]);
+		assert_close(project(&matrix, [2.0, 2.0, 3.0]), [-1.0, 0.0, 0.0]);
+
+		let identity = Matrix4::look_at([0.0; 3], [0.0, 0.0, 1.0], [0.0, 1.0, 0.0]);
+		assert_eq!(identity, Matrix4::identity());
+	}
+
+	#[test]
+	fn products_use_every_row() {
+		let a = Matrix4::from_row_major_array([
+			1.0, 2.0, 3.0, 4.0,
+			5.0, 6.0, 7.0, 8.0,
+			9.0, 1.0, 2.0, 3.0,
+			4.0, 5.0, 6.0, 7.0,
+		]);
+		let expected = Matrix4::from_row_major_array([
+			54.0, 37.0, 47.0, 57.0,
+			130.0, 93.0, 119.0, 145.0,
+			44.0, 41.0, 56.0, 71.0,
+			111.0, 79.0, 101.0, 123.0,
+		]);
+		assert_eq!(a * a, expected);
+
+		let mut b = a;
+		b *= a;
+		assert_eq!(b, expected);
+	}
+
+	#[test]
+	fn identity_is_neutral() {
+		for matrix in random_matrices(16) {
+			assert_eq!(Matrix4::identity() * matrix, matrix);
+			assert_eq!(matrix * Matrix4::identity(), matrix);
+		}
+	}
+
+	#[test]
+	fn products_are_associative() {
+		let matrices = random_matrices(24);
+		for abc in matrices.chunks(3) {
+			let (a, b, c) = (abc[0], abc[1], abc[2]);
+			assert_matrices_close(&((a * b) * c), &(a * (b * c)), 1e-5);
+		}
+
+		/* Projections and views have to compose the same either way. */
+		let projection = Matrix4::rectilinear_projection(1.2, 1.5, 0.1, 100.0);
+		let view = Matrix4::look_at([1.0, 2.0, 3.0], [0.0; 3], [0.0, 1.0, 0.0]);
+		let world = Matrix4::translate(1.0, -2.0, 0.5) * Matrix4::scale(2.0, 2.0, 2.0);
+		assert_matrices_close(
+			&((projection * view) * world),
+			&(projection * (view * world)),
+			1e-5);
+	}
+
+	#[test]
+	fn inverses_undo_their_matrices() {
+		let transforms = [
+			Matrix4::translate(1.0, -2.0, 3.0),
+			Matrix4::scale(0.5, 2.0, 4.0),
+			Matrix4::rotate(1.0, 1.0, 0.0, 0.7),
+			Matrix4::rectilinear_projection(1.2, 1.5, 0.1, 100.0),
+			Matrix4::orthographic_projection(-2.0, 6.0, 3.0, -1.0, 0.5, 10.0),
+			Matrix4::look_at([1.0, 2.0, 3.0], [0.0; 3], [0.0, 1.0, 0.0]),
+		];
+		for matrix in transforms.iter().copied().chain(random_matrices(16)) {
+			let inverse = matrix.inverse().unwrap();
+			assert_matrices_close(&(matrix * inverse), &Matrix4::identity(), 1e-4);
+			assert_matrices_close(&(inverse * matrix), &Matrix4::identity(), 1e-4);
+		}
+
+		let translation = Matrix4::translate(1.0, -2.0, 3.0).inverse().unwrap();
+		assert_eq!(translation, Matrix4::translate(-1.0, 2.0, -3.0));
+		assert_eq!(Matrix4::scale(1.0, 0.0, 1.0).inverse(), None);
+	}
+}
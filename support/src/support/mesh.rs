@@ -1,10 +1,29 @@
-use ordered_float::OrderedFloat;
-use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
 use std::convert::TryFrom;
-use smallvec::SmallVec;
-use crate::support::Vertex;
+use crate::support::{Vertex, Vec3};
 use std::num::TryFromIntError;
-use tinyvec::ArrayVec;
+use gavle::{FrontFace, IndexFormat};
+
+/** Options controlling how meshes get loaded. */
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct MeshOptions {
+	/** Winding order the triangles of the mesh should be in, when seen from
+	 * the side their normals point to. Triangles found to be in the other
+	 * order get flipped as the mesh is loaded. When this is `None`, triangles
+	 * are kept in the order they came in. */
+	pub force_winding: Option<FrontFace>,
+	/** Whether faces with no area, no surface normal or no texture
+	 * coordinates to work a tangent space out of should be left out of the
+	 * mesh, with a [`MeshWarning`] for each of them, rather than failing the
+	 * whole mesh. */
+	pub skip_degenerate_faces: bool,
+	/** When set, the normals in the model are ignored, and get rebuilt from
+	 * the shape of its faces instead, with the given smoothing angle, as done
+	 * by [`Mesh::recompute_normals()`]. This is useful for models that come
+	 * with broken normals, or with none at all. */
+	pub recompute_normals: Option<f32>,
+}
 
 pub struct Mesh {
 	vertices: Vec<Vertex>,
@@ -15,271 +34,358 @@ impl Mesh {
 	pub fn from_obj(model: &obj::Obj<obj::TexturedVertex, u32>)
 		-> Result<Self, InvalidMesh> {
 
-		/** Vertex type that implements full order and equality. */
-		#[derive(Copy, Clone, PartialEq, Eq, Ord, PartialOrd, Hash)]
-		struct Vertex {
-			position: [OrderedFloat<f32>; 3],
-			normal:   [OrderedFloat<f32>; 3],
-			texture:  [OrderedFloat<f32>; 3],
-		}
-		impl From<obj::TexturedVertex> for Vertex {
-			fn from(vert: obj::TexturedVertex) -> Self {
-				Self {
-					position: [
-						vert.position[0].into(),
-						vert.position[1].into(),
-						vert.position[2].into()
-					],
-					normal: [
-						vert.normal[0].into(),
-						vert.normal[1].into(),
-						vert.normal[2].into()
-					],
-					texture: [
-						vert.texture[0].into(),
-						vert.texture[1].into(),
-						vert.texture[2].into()
-					]
-				}
-			}
+		Self::from_obj_with_options(model, &MeshOptions::default())
+	}
+
+	/** Load the data for this mesh from the given object file, with the given
+	 * options. Faces skipped along the way get logged. */
+	pub fn from_obj_with_options(
+		model: &obj::Obj<obj::TexturedVertex, u32>,
+		options: &MeshOptions) -> Result<Self, InvalidMesh> {
+
+		let (mesh, warnings) = Self::from_obj_with_warnings(model, options)?;
+		for warning in warnings {
+			log::warn!(target: "support::mesh", "{}", warning);
 		}
 
-		/** Three vertices of a triangulated face in the model. */
-		struct Face<'a> {
-			vert0: &'a obj::TexturedVertex,
-			vert1: &'a obj::TexturedVertex,
-			vert2: &'a obj::TexturedVertex,
-
-			/** Surface-wide normal. */
-			normal: [f32; 3],
-			/** Tangent vector of the face. */
-			tangent: [f32; 3],
-			/** Bitangent vector of the face. */
-			bitangent: [f32; 3]
-		}
-
-		let mut global_faces = Vec::with_capacity(model.indices.len() / 3);
-		let mut vertices = BTreeMap::new();
-
-		/* Build the list of elements. */
-		for triplet in model.indices.chunks_exact(3) {
-			let vert0 = usize::try_from(triplet[0])
-				.map_err(|what| InvalidMesh::InnumerableVertices { what })?;
-			let vert1 = usize::try_from(triplet[1])
-				.map_err(|what| InvalidMesh::InnumerableVertices { what })?;
-			let vert2 = usize::try_from(triplet[2])
-				.map_err(|what| InvalidMesh::InnumerableVertices { what })?;
-
-			let vert0 = &model.vertices[vert0];
-			let vert1 = &model.vertices[vert1];
-			let vert2 = &model.vertices[vert2];
-
-			let normal = {
-				let x = (vert0.normal[0] + vert1.normal[0] + vert2.normal[0]) / 3.0;
-				let y = (vert0.normal[1] + vert1.normal[1] + vert2.normal[1]) / 3.0;
-				let z = (vert0.normal[2] + vert1.normal[2] + vert2.normal[2]) / 3.0;
-
-				let l = f32::sqrt(x.powf(2.0) + y.powf(2.0) + z.powf(2.0));
-				if l == 0.0 {
-					/* This surface normal is null, meaning that this is an
-					 * invalid triangle. Give up on the mesh. */
-					return Err(InvalidMesh::NullSurfaceNormal)
-				}
+		Ok(mesh)
+	}
 
-				let x = x / l;
-				let y = y / l;
-				let z = z / l;
+	/** Load the data for this mesh from the given object file, with the given
+	 * options, returning the faces that got skipped along with it. */
+	pub fn from_obj_with_warnings(
+		model: &obj::Obj<obj::TexturedVertex, u32>,
+		options: &MeshOptions) -> Result<(Self, Vec<MeshWarning>), InvalidMesh> {
 
-				[x, y, z]
-			};
-			let (tangent, bitangent) = {
-				let edge0 = [
-					vert1.position[0] - vert0.position[0],
-					vert1.position[1] - vert0.position[1],
-					vert1.position[2] - vert0.position[2]];
-				let edge1 = [
-					vert2.position[0] - vert0.position[0],
-					vert2.position[1] - vert0.position[1],
-					vert2.position[2] - vert0.position[2]];
-
-				let uv0 = [vert1.texture[0] - vert0.texture[0], vert1.texture[1] - vert0.texture[1]];
-				let uv1 = [vert2.texture[0] - vert0.texture[0], vert2.texture[1] - vert0.texture[1]];
-
-				let edge_cross =
-					  (edge0[1] * edge1[2] - edge0[2] * edge1[1]).powf(2.0)
-					+ (edge0[2] * edge1[0] - edge0[0] * edge1[2]).powf(2.0)
-					+ (edge0[0] * edge1[1] - edge0[1] * edge1[0]).powf(2.0);
-				let uv_cross = uv0[0] * uv1[1] - uv0[1] * uv1[0];
-
-				if edge_cross == 0.0 || uv_cross == 0.0 {
-					/* This is a degenerate triangle, we can't really calculate the
-					 * tangent direction for it, so we just give up. */
-					return Err(InvalidMesh::DegenerateTriangle {
+		let mut warnings = Vec::new();
+		let mut triangles = Vec::with_capacity(model.indices.len() / 3);
+		for (face, triplet) in model.indices.chunks_exact(3).enumerate() {
+			let [vert0, vert1, vert2] = obj_triangle(&model.vertices, triplet)?;
+
+			let normal = (Vec3::from(vert0.normal)
+				+ Vec3::from(vert1.normal)
+				+ Vec3::from(vert2.normal)) / 3.0;
+			if options.recompute_normals.is_none() && normal.normalize().is_none() {
+				/* A null surface normal means that this is an invalid
+				 * triangle. */
+				skip(
+					options,
+					&mut warnings,
+					MeshWarning::NullSurfaceNormal { face },
+					InvalidMesh::NullSurfaceNormal)?;
+				continue
+			}
+
+			let edge0 = Vec3::from(vert1.position) - Vec3::from(vert0.position);
+			let edge1 = Vec3::from(vert2.position) - Vec3::from(vert0.position);
+
+			let uv0 = [vert1.texture[0] - vert0.texture[0], vert1.texture[1] - vert0.texture[1]];
+			let uv1 = [vert2.texture[0] - vert0.texture[0], vert2.texture[1] - vert0.texture[1]];
+
+			let surface = edge0.cross(edge1);
+			let edge_cross = surface.dot(surface);
+			let uv_cross = uv0[0] * uv1[1] - uv0[1] * uv1[0];
+
+			if edge_cross == 0.0 || uv_cross == 0.0 {
+				/* This is a degenerate triangle, we can't really calculate the
+				 * tangent direction for it. */
+				skip(
+					options,
+					&mut warnings,
+					MeshWarning::DegenerateTriangle {
+						face,
+						edge_cross,
+						uv_cross
+					},
+					InvalidMesh::DegenerateTriangle {
 						vertex0: *vert0,
 						vertex1: *vert1,
 						vertex2: *vert2,
 						edge_cross,
 						uv_cross
-					})
-				} else {
-					/* Calculate the tangent vectors. */
-					let base = 1.0 / uv_cross;
-					let tangent = [
-						base * (uv1[1] * edge0[0] - uv0[1] * edge1[0]),
-						base * (uv1[1] * edge0[1] - uv0[1] * edge1[1]),
-						base * (uv1[1] * edge0[2] - uv0[1] * edge1[2])
-					];
-					let bitangent = [
-						base * (-uv1[0] * edge0[0] + uv0[0] * edge1[0]),
-						base * (-uv1[0] * edge0[1] + uv0[0] * edge1[1]),
-						base * (-uv1[0] * edge0[2] + uv0[0] * edge1[2]),
-					];
-
-					(tangent, bitangent)
+					})?;
+				continue
+			}
+			let (tangent, bitangent) = face_tangents(edge0, edge1, uv0, uv1);
+
+			/* The normals are going to get rebuilt anyway, so all that's
+			 * needed from them until then is that they're valid. */
+			let surface = surface / edge_cross.sqrt();
+			let key = |vert: &obj::TexturedVertex| VertexKey::new(
+				vert.position,
+				if options.recompute_normals.is_some() { surface.into() } else { vert.normal },
+				[vert.texture[0], vert.texture[1]]);
+			triangles.push(Triangle {
+				vertices: [key(vert0), key(vert1), key(vert2)],
+				tangent,
+				bitangent
+			});
+		}
+
+		let mut mesh = Self::assemble(&triangles)?;
+		mesh.apply_options(options);
+
+		Ok((mesh, warnings))
+	}
+
+	/** Load the data for this mesh from the given object file, which has no
+	 * texture coordinates, with the given options, returning the faces that
+	 * got skipped along with it.
+	 *
+	 * Texture coordinates get made up by projecting every face onto the axis
+	 * aligned plane it faces the most, in model space units, so that textures
+	 * repeat across the mesh without stretching much. Tangents follow the
+	 * axes of that plane, rather than being worked out from the coordinates,
+	 * and only faces with no surface normal get skipped. */
+	pub fn from_obj_positions_normals(
+		model: &obj::Obj<obj::Vertex, u32>,
+		options: &MeshOptions) -> Result<(Self, Vec<MeshWarning>), InvalidMesh> {
+
+		let mut warnings = Vec::new();
+		let mut triangles = Vec::with_capacity(model.indices.len() / 3);
+		for (face, triplet) in model.indices.chunks_exact(3).enumerate() {
+			let [vert0, vert1, vert2] = obj_triangle(&model.vertices, triplet)?;
+
+			let normal = if options.recompute_normals.is_some() {
+				let position = |vert: &obj::Vertex| Vec3::from(vert.position);
+				(position(vert1) - position(vert0)).cross(position(vert2) - position(vert0))
+			} else {
+				(Vec3::from(vert0.normal)
+					+ Vec3::from(vert1.normal)
+					+ Vec3::from(vert2.normal)) / 3.0
+			};
+			let normal = match normal.normalize() {
+				Some(normal) => normal,
+				None => {
+					skip(
+						options,
+						&mut warnings,
+						MeshWarning::NullSurfaceNormal { face },
+						InvalidMesh::NullSurfaceNormal)?;
+					continue
 				}
 			};
 
-			/* Add the newly created face to the bank of faces. */
-			global_faces.push(Face {
-				vert0,
-				vert1,
-				vert2,
-				normal,
+			let (tangent, bitangent) = planar_axes(normal);
+			let key = |vert: &obj::Vertex| {
+				let position = Vec3::from(vert.position);
+				VertexKey::new(
+					vert.position,
+					if options.recompute_normals.is_some() { normal.into() } else { vert.normal },
+					[position.dot(tangent), position.dot(bitangent)])
+			};
+			triangles.push(Triangle {
+				vertices: [key(vert0), key(vert1), key(vert2)],
 				tangent,
 				bitangent
 			});
+		}
 
-			/* Register the newly added face to the vertex lookup table. */
-			vertices.entry(Vertex::from(*vert0))
-				.or_insert_with(SmallVec::<[usize; 32]>::new)
-				.push(global_faces.len() - 1);
-			vertices.entry(Vertex::from(*vert1))
-				.or_insert_with(SmallVec::<[usize; 32]>::new)
-				.push(global_faces.len() - 1);
-			vertices.entry(Vertex::from(*vert2))
-				.or_insert_with(SmallVec::<[usize; 32]>::new)
-				.push(global_faces.len() - 1);
-		}
-
-		/* Build a new, stably allocated and sorted array of vertices array that
-		 * can be search through a binary search and that will later be used to
-		 * generate the indices for the faces. */
-		let vertex_array = vertices
-			.keys()
-			.cloned()
-			.collect::<Vec<_>>();
+		let mut mesh = Self::assemble(&triangles)?;
+		mesh.apply_options(options);
+
+		Ok((mesh, warnings))
+	}
+
+	/** Builds a mesh out of indexed triangles, as models in formats like glTF
+	 * come in, from the positions of its vertices, along with their normals,
+	 * texture coordinates and tangents, where the model has them, with the
+	 * given options, returning the faces that got skipped along with it.
+	 *
+	 * Tangents have the sign of the bitangent in their fourth component, and
+	 * are kept as they are, along with the vertices they belong to, when the
+	 * model has both normals and tangents. Otherwise, tangents get worked out
+	 * from the texture coordinates, as they are for object files, and faces
+	 * whose texture coordinates don't span an area, or which have none, get
+	 * the tangents of the axis aligned plane they face the most. Models with
+	 * no normals get them rebuilt from the shape of their faces, as if they
+	 * were being recomputed with [`DEFAULT_SMOOTHING_ANGLE`], unless the
+	 * options give an angle of their own. */
+	pub fn from_indexed(
+		positions: &[[f32; 3]],
+		normals: Option<&[[f32; 3]]>,
+		texture: Option<&[[f32; 2]]>,
+		tangents: Option<&[[f32; 4]]>,
+		indices: &[u32],
+		options: &MeshOptions) -> Result<(Self, Vec<MeshWarning>), InvalidMesh> {
 
-		/* Generate the index buffer. */
-		let indices = global_faces.iter()
-			.map(|face| {
-				let vert0 = Vertex::from(*face.vert0);
-				let vert1 = Vertex::from(*face.vert1);
-				let vert2 = Vertex::from(*face.vert2);
-
-				let vert0 = u32::try_from(vertex_array.binary_search(&vert0).unwrap())
-					.map_err(|what| InvalidMesh::InnumerableVertices { what })?;
-				let vert1 = u32::try_from(vertex_array.binary_search(&vert1).unwrap())
-					.map_err(|what| InvalidMesh::InnumerableVertices { what })?;
-				let vert2 = u32::try_from(vertex_array.binary_search(&vert2).unwrap())
-					.map_err(|what| InvalidMesh::InnumerableVertices { what })?;
-
-				let array = ArrayVec::<[u32; 3]>::from([vert0, vert1, vert2]);
-				Ok(array.into_iter())
+		let check = |attribute: &'static str, len: Option<usize>| match len {
+			Some(len) if len != positions.len() =>
+				Err(InvalidMesh::AttributeLength {
+					attribute,
+					len,
+					vertices: positions.len()
+				}),
+			_ => Ok(())
+		};
+		check("normals", normals.map(<[_]>::len))?;
+		check("texture coordinates", texture.map(<[_]>::len))?;
+		check("tangents", tangents.map(<[_]>::len))?;
+		if let Some(index) = indices.iter().find(|index| **index as usize >= positions.len()) {
+			return Err(InvalidMesh::IndexOutOfRange {
+				index: *index,
+				vertices: positions.len()
 			})
-			.collect::<Result<Vec<_>, _>>()?
-			.into_iter()
-			.flat_map(|iter| iter)
-			.collect::<Vec<_>>();
+		}
+		let indices = &indices[..indices.len() / 3 * 3];
 
-		/* Generate the vertices. */
-		let vertices = vertices.into_iter()
-			.map(|(vertex, faces)| {
-				/* Copy the first three parameters. */
-				let position = [
-					vertex.position[0].into_inner(),
-					vertex.position[1].into_inner(),
-					vertex.position[2].into_inner(),
-				];
-				let texture = [
-					vertex.texture[0].into_inner(),
-					vertex.texture[1].into_inner(),
-				];
-				let normal = [
-					vertex.normal[0].into_inner(),
-					vertex.normal[1].into_inner(),
-					vertex.normal[2].into_inner(),
-				];
+		if let (Some(normals), Some(tangents), None) = (normals, tangents, options.recompute_normals) {
+			let vertices = (0..positions.len())
+				.map(|i| {
+					let normal = Vec3::from(normals[i]);
+					let [x, y, z, sign] = tangents[i];
+					let tangent = Vec3::new(x, y, z);
+					let bitangent = normal.cross(tangent) * sign;
+
+					let (normal, tangent, bitangent) = tangent_space(normal, tangent, bitangent)?;
+					Some(Vertex::new_unchecked(
+						positions[i],
+						texture.map(|texture| texture[i]).unwrap_or([0.0; 2]),
+						normal.into(),
+						tangent.into(),
+						bitangent.into()))
+				})
+				.collect::<Option<Vec<_>>>();
+
+			if let Some(vertices) = vertices {
+				let mut mesh = Self::from_parts(vertices, indices.to_vec());
+				mesh.apply_options(options);
+
+				return Ok((mesh, Vec::new()))
+			}
+			log::debug!(target: "support::mesh",
+				"Some of the normals in the model are null vectors, working out \
+				its tangent spaces again");
+		}
 
-				/* Find the mean of the other parameters from their faces, then
-				 * normalize the vector space. */
-				let (tangent, bitangent) = faces.iter()
-					.map(|index| &global_faces[*index])
-					.map(|face| (face.tangent, face.bitangent))
-					.reduce(|(a, c), (b, d)| (
-						[
-							a[0] + b[0],
-							a[1] + b[1],
-							a[2] + b[2]
-						],
-						[
-							c[0] + d[0],
-							c[1] + d[1],
-							c[2] + d[2]
-						]
-					))
-					.map(|(a, b)| (
-						[
-							a[0] / faces.len() as f32,
-							a[1] / faces.len() as f32,
-							a[2] / faces.len() as f32,
-						],
-						[
-							b[0] / faces.len() as f32,
-							b[1] / faces.len() as f32,
-							b[2] / faces.len() as f32,
-						],
-					))
-					.unwrap();
-
-				let ntb = {
-					/* Normalize the NTB matrix. */
-					let nl = f32::sqrt(normal[0].powf(2.0) + normal[1].powf(2.0) + normal[2].powf(2.0));
-					let tl = f32::sqrt(tangent[0].powf(2.0) + tangent[1].powf(2.0) + tangent[2].powf(2.0));
-					let bl = f32::sqrt(bitangent[0].powf(2.0) + bitangent[1].powf(2.0) + bitangent[2].powf(2.0));
-
-					assert_ne!(nl, 0.0, "NTB normal vector length must not be zero at this point");
-					assert_ne!(tl, 0.0, "NTB tangent vector length must not be zero at this point");
-					assert_ne!(bl, 0.0, "NTB bitangent vector length must not be zero at this point");
-
-					(
-						[
-							normal[0] / nl,
-							normal[1] / nl,
-							normal[2] / nl,
-						],
-						[
-							tangent[0] / tl,
-							tangent[1] / tl,
-							tangent[2] / tl,
-						],
-						[
-							bitangent[0] / bl,
-							bitangent[1] / bl,
-							bitangent[2] / bl,
-						]
-					)
+		let mut warnings = Vec::new();
+		let mut triangles = Vec::with_capacity(indices.len() / 3);
+		for (face, triplet) in indices.chunks_exact(3).enumerate() {
+			let [i0, i1, i2] = [triplet[0] as usize, triplet[1] as usize, triplet[2] as usize];
+
+			let edge0 = Vec3::from(positions[i1]) - Vec3::from(positions[i0]);
+			let edge1 = Vec3::from(positions[i2]) - Vec3::from(positions[i0]);
+			let surface = match edge0.cross(edge1).normalize() {
+				Some(surface) => surface,
+				None => {
+					skip(
+						options,
+						&mut warnings,
+						MeshWarning::NullSurfaceNormal { face },
+						InvalidMesh::NullSurfaceNormal)?;
+					continue
+				}
+			};
+
+			let use_surface = normals.is_none() || options.recompute_normals.is_some();
+			if let (Some(normals), false) = (normals, use_surface) {
+				let normal = Vec3::from(normals[i0])
+					+ Vec3::from(normals[i1])
+					+ Vec3::from(normals[i2]);
+				if normal.normalize().is_none() {
+					skip(
+						options,
+						&mut warnings,
+						MeshWarning::NullSurfaceNormal { face },
+						InvalidMesh::NullSurfaceNormal)?;
+					continue
+				}
+			}
+
+			let uv = |i: usize| texture.map(|texture| texture[i]).unwrap_or([0.0; 2]);
+			let uv0 = [uv(i1)[0] - uv(i0)[0], uv(i1)[1] - uv(i0)[1]];
+			let uv1 = [uv(i2)[0] - uv(i0)[0], uv(i2)[1] - uv(i0)[1]];
+			let (tangent, bitangent) = if uv0[0] * uv1[1] - uv0[1] * uv1[0] == 0.0 {
+				planar_axes(surface)
+			} else {
+				face_tangents(edge0, edge1, uv0, uv1)
+			};
+
+			let key = |i: usize| VertexKey::new(
+				positions[i],
+				match normals {
+					Some(normals) if !use_surface => normals[i],
+					_ => surface.into()
+				},
+				uv(i));
+			triangles.push(Triangle {
+				vertices: [key(i0), key(i1), key(i2)],
+				tangent,
+				bitangent
+			});
+		}
+
+		let mut mesh = Self::assemble(&triangles)?;
+		if normals.is_none() && options.recompute_normals.is_none() {
+			mesh.recompute_normals(DEFAULT_SMOOTHING_ANGLE);
+		}
+		mesh.apply_options(options);
+
+		Ok((mesh, warnings))
+	}
+
+	/** Builds a mesh out of the given triangles, sharing the vertices that are
+	 * the same across all of them, and giving each vertex the mean of the
+	 * tangent spaces of the triangles it is a part of, brought back to an
+	 * orthonormal basis around its normal.
+	 *
+	 * Vertices are numbered in the order they are first seen, and the index
+	 * buffer is built in the same pass that finds them. */
+	fn assemble(triangles: &[Triangle]) -> Result<Self, InvalidMesh> {
+		/** Vertex found in the triangles, along with the sum of the tangent
+		 * spaces of the triangles it is a part of. */
+		struct Shared {
+			key: VertexKey,
+			tangent: Vec3,
+			bitangent: Vec3,
+		}
+
+		let mut lookup = HashMap::<VertexKey, u32>::with_capacity(triangles.len());
+		let mut shared = Vec::<Shared>::with_capacity(triangles.len());
+		let mut indices = Vec::with_capacity(triangles.len() * 3);
+
+		for triangle in triangles {
+			for vertex in &triangle.vertices {
+				let index = match lookup.entry(*vertex) {
+					Entry::Occupied(entry) => *entry.get(),
+					Entry::Vacant(entry) => {
+						let index = u32::try_from(shared.len())
+							.map_err(|what| InvalidMesh::InnumerableVertices { what })?;
+						shared.push(Shared {
+							key: *vertex,
+							tangent: Vec3::zero(),
+							bitangent: Vec3::zero()
+						});
+
+						*entry.insert(index)
+					}
 				};
 
-				/* Build the vertex. */
-				self::Vertex::new_unchecked(
-					position,
-					texture,
-					ntb.0,
-					ntb.1,
-					ntb.2)
-			})
-			.collect::<Vec<_>>();
+				let vertex = &mut shared[index as usize];
+				vertex.tangent += triangle.tangent;
+				vertex.bitangent += triangle.bitangent;
 
+				indices.push(index);
+			}
+		}
+
+		/* Generate the vertices. Only the direction of the sums matters for
+		 * the tangent space, so there's no need to divide them into means. */
+		let vertices = shared.into_iter()
+			.map(|vertex| {
+				let (normal, tangent, bitangent) = tangent_space(
+					vertex.key.normal(),
+					vertex.tangent,
+					vertex.bitangent).ok_or(InvalidMesh::NullSurfaceNormal)?;
+
+				Ok(self::Vertex::new_unchecked(
+					vertex.key.position(),
+					vertex.key.texture(),
+					normal.into(),
+					tangent.into(),
+					bitangent.into()))
+			})
+			.collect::<Result<Vec<_>, _>>()?;
 
 		Ok(Self {
 			vertices,
@@ -287,6 +393,47 @@ impl Mesh {
 		})
 	}
 
+	/** Applies the given options to this freshly loaded mesh. */
+	fn apply_options(&mut self, options: &MeshOptions) {
+		if let Some(smoothing_angle) = options.recompute_normals {
+			self.recompute_normals(smoothing_angle);
+		}
+		if let Some(winding) = options.force_winding {
+			let estimate = self.winding_order();
+			if !estimate.is_consistent() {
+				log::warn!(target: "support::mesh",
+					"The triangles in the mesh are not all wound the same way, \
+					{} are counter clockwise, {} are clockwise and {} can't \
+					tell. Flipping the ones that aren't {:?}",
+					estimate.counter_clockwise,
+					estimate.clockwise,
+					estimate.undecided,
+					winding);
+			}
+
+			let flipped = self.fix_winding(winding);
+			if flipped > 0 {
+				log::debug!(target: "support::mesh",
+					"Flipped {} out of {} triangles to {:?} winding",
+					flipped,
+					self.indices.len() / 3,
+					winding);
+			}
+		}
+	}
+
+	/** Creates a mesh out of the given vertices and the indices of its
+	 * triangles into them. */
+	pub(crate) fn from_parts(vertices: Vec<Vertex>, indices: Vec<u32>) -> Self {
+		debug_assert!(indices.len() % 3 == 0);
+		debug_assert!(indices.iter().all(|index| (*index as usize) < vertices.len()));
+
+		Self {
+			vertices,
+			indices
+		}
+	}
+
 	/** Get a reference to the vertices in this mesh. */
 	pub fn vertices(&self) -> &[Vertex] {
 		&self.vertices
@@ -296,6 +443,632 @@ impl Mesh {
 	pub fn indices(&self) -> &[u32] {
 		&self.indices
 	}
+
+	/** Rebuilds the normals of this mesh from the shape of its triangles,
+	 * then rebuilds the tangent spaces of its vertices around them.
+	 *
+	 * The normal at every corner of a triangle is the mean of the normals of
+	 * the triangles around the same position, weighted by their angle at it,
+	 * out of those whose normals are within the given angle, in radians, of
+	 * the normal of the triangle itself. Vertices get split where that gives
+	 * corners of the same vertex different normals, such as along hard edges,
+	 * and merged where it gives them the same one.
+	 *
+	 * Triangles are assumed to be wound counter clockwise when seen from the
+	 * outside. Triangles with no area are dropped, and ones whose texture
+	 * coordinates don't span an area get the tangents of the axis aligned
+	 * plane they face the most. */
+	pub fn recompute_normals(&mut self, smoothing_angle: f32) {
+		let threshold = smoothing_angle.cos();
+		let position = |index: u32| Vec3::from(self.vertices[index as usize].position());
+
+		/* Corners and normals of the triangles that have an area. */
+		let faces = self.indices.chunks_exact(3)
+			.filter_map(|triangle| {
+				let corners = [triangle[0], triangle[1], triangle[2]];
+				let [a, b, c] = corners.map(position);
+
+				(b - a).cross(c - a).normalize().map(|normal| (corners, normal))
+			})
+			.collect::<Vec<_>>();
+
+		/* Angle of a triangle at one of its corners. */
+		let angle = |face: usize, corner: usize| {
+			let (corners, _) = faces[face];
+			let origin = position(corners[corner]);
+			let a = (position(corners[(corner + 1) % 3]) - origin).normalize();
+			let b = (position(corners[(corner + 2) % 3]) - origin).normalize();
+
+			a.zip(b)
+				.map(|(a, b)| a.dot(b).clamp(-1.0, 1.0).acos())
+				.unwrap_or(0.0)
+		};
+
+		/* Corners of triangles around every position. */
+		let mut around = HashMap::<[u32; 3], Vec<(usize, usize)>>::new();
+		for (face, (corners, _)) in faces.iter().enumerate() {
+			for (corner, index) in corners.iter().enumerate() {
+				let key = self.vertices[*index as usize].position().map(bits);
+				around.entry(key).or_default().push((face, corner));
+			}
+		}
+
+		let triangles = faces.iter()
+			.map(|(corners, normal)| {
+				let key = |index: u32| {
+					let vertex = &self.vertices[index as usize];
+					let smooth = around[&vertex.position().map(bits)].iter()
+						.filter(|(face, _)| faces[*face].1.dot(*normal) >= threshold)
+						.map(|(face, corner)| faces[*face].1 * angle(*face, *corner))
+						.fold(Vec3::zero(), |a, b| a + b)
+						.normalize()
+						.unwrap_or(*normal);
+
+					VertexKey::new(vertex.position(), smooth.into(), vertex.texture())
+				};
+
+				let [a, b, c] = corners.map(|index| self.vertices[index as usize]);
+				let uv = |vertex: Vertex| [
+					vertex.texture()[0] - a.texture()[0],
+					vertex.texture()[1] - a.texture()[1]
+				];
+				let (uv0, uv1) = (uv(b), uv(c));
+				let (tangent, bitangent) = if uv0[0] * uv1[1] - uv0[1] * uv1[0] != 0.0 {
+					face_tangents(
+						Vec3::from(b.position()) - Vec3::from(a.position()),
+						Vec3::from(c.position()) - Vec3::from(a.position()),
+						uv0,
+						uv1)
+				} else {
+					planar_axes(*normal)
+				};
+
+				Triangle {
+					vertices: corners.map(key),
+					tangent,
+					bitangent
+				}
+			})
+			.collect::<Vec<_>>();
+
+		*self = Self::assemble(&triangles)
+			.expect("recomputed normals must never be null, and there must be \
+				no more vertices than there were corners before");
+	}
+
+	/** The smallest index format that can address every vertex in this mesh.
+	 * Pipelines drawing this mesh with the indices from [`indices_u16()`]
+	 * must use [`IndexFormat::Uint16`], and ones drawing it with those from
+	 * [`indices()`] must use [`IndexFormat::Uint32`], regardless of this.
+	 *
+	 * [`indices_u16()`]: Self::indices_u16
+	 * [`indices()`]: Self::indices */
+	pub fn index_format(&self) -> IndexFormat {
+		if self.vertices.len() <= U16_VERTICES {
+			IndexFormat::Uint16
+		} else {
+			IndexFormat::Uint32
+		}
+	}
+
+	/** Get a copy of the indices in this mesh as 16-bit integers, or an error
+	 * if any of them doesn't fit in one. Meshes that are too large for this
+	 * can be broken up with [`split_for_u16()`].
+	 *
+	 * The largest 16-bit index, `0xffff`, counts as not fitting, as WebGL 2
+	 * always treats it as a primitive restart, rather than as a vertex.
+	 *
+	 * [`split_for_u16()`]: Self::split_for_u16 */
+	pub fn indices_u16(&self) -> Result<Vec<u16>, IndexOverflow> {
+		self.indices.iter()
+			.map(|index| u16::try_from(*index).ok()
+				.filter(|index| usize::from(*index) < U16_VERTICES)
+				.ok_or(IndexOverflow { index: *index }))
+			.collect()
+	}
+
+	/** Breaks this mesh up into a sequence of meshes that each have few enough
+	 * vertices to be addressed with 16-bit indices, for devices that don't
+	 * support 32-bit indices or that are slower with them. Drawing all of
+	 * them draws the same triangles as drawing this mesh.
+	 *
+	 * Triangles are kept in the order they are in, with a new mesh started
+	 * every time the next triangle doesn't fit in the current one. Vertices
+	 * used by triangles in more than one of the meshes get copied to all of
+	 * them, and vertices not used by any triangle get left out. */
+	pub fn split_for_u16(&self) -> Vec<Mesh> {
+		self.split(U16_VERTICES)
+	}
+
+	/** Breaks this mesh up into a sequence of meshes with at most the given
+	 * number of vertices each, which must be at least three. */
+	fn split(&self, max_vertices: usize) -> Vec<Mesh> {
+		let mut meshes = Vec::new();
+
+		let mut remap = HashMap::new();
+		let mut current = Mesh::from_parts(Vec::new(), Vec::new());
+		for triangle in self.indices.chunks_exact(3) {
+			let new = triangle.iter()
+				.filter(|index| !remap.contains_key(*index))
+				.count();
+			if current.vertices.len() + new > max_vertices {
+				meshes.push(std::mem::replace(
+					&mut current,
+					Mesh::from_parts(Vec::new(), Vec::new())));
+				remap.clear();
+			}
+
+			for index in triangle {
+				let vertices = &mut current.vertices;
+				let local = *remap.entry(*index).or_insert_with(|| {
+					vertices.push(self.vertices[*index as usize]);
+					(vertices.len() - 1) as u32
+				});
+				current.indices.push(local);
+			}
+		}
+		if !current.indices.is_empty() {
+			meshes.push(current);
+		}
+
+		meshes
+	}
+
+	/** Estimates the winding order of the triangles in this mesh.
+	 *
+	 * Every triangle votes for the winding order under which the normal of
+	 * its face, as given by the order of its vertices, points to the same side
+	 * as the normals stored in its vertices. Triangles for which that can't be
+	 * told, either because they have no area or because their face and vertex
+	 * normals are perpendicular, don't vote. */
+	pub fn winding_order(&self) -> WindingEstimate {
+		let mut estimate = WindingEstimate::default();
+		for triangle in self.indices.chunks_exact(3) {
+			match self.triangle_winding(triangle) {
+				Some(FrontFace::Ccw) => estimate.counter_clockwise += 1,
+				Some(FrontFace::Cw) => estimate.clockwise += 1,
+				None => estimate.undecided += 1
+			}
+		}
+
+		estimate
+	}
+
+	/** Reorders the triangles in this mesh so that the ones sharing vertices
+	 * get drawn close together, which lets the device reuse more of the
+	 * vertices it has already transformed instead of running the vertex shader
+	 * on them again. Every triangle keeps its own winding order.
+	 *
+	 * This uses the Tipsify algorithm, from Sander, Nehab and Barczak's "Fast
+	 * Triangle Reordering for Vertex Locality and Reduced Overdraw", which
+	 * runs in linear time, tuned for a cache of [`VERTEX_CACHE_SIZE`]
+	 * vertices. */
+	pub fn optimize(&mut self) {
+		self.indices = vertex_cache_order(
+			&self.indices,
+			self.vertices.len(),
+			VERTEX_CACHE_SIZE);
+	}
+
+	/** Average number of vertices that have to be transformed for every
+	 * triangle in this mesh, when drawn through a first-in, first-out cache of
+	 * transformed vertices of the given size. This goes from three, for meshes
+	 * that share no vertices in the cache at all, down to a half, for large
+	 * regular grids. Meshes with no triangles have a ratio of zero. */
+	pub fn average_cache_miss_ratio(&self, cache_size: usize) -> f32 {
+		let triangles = self.indices.len() / 3;
+		if triangles == 0 { return 0.0 }
+
+		let mut cache = std::collections::VecDeque::with_capacity(cache_size);
+		let mut misses = 0;
+		for index in &self.indices[..triangles * 3] {
+			if !cache.contains(index) {
+				misses += 1;
+				if cache_size > 0 {
+					if cache.len() == cache_size { cache.pop_front(); }
+					cache.push_back(*index);
+				}
+			}
+		}
+
+		misses as f32 / triangles as f32
+	}
+
+	/** Reverses the winding order of every triangle in this mesh.
+	 *
+	 * Only the order of the indices changes. The tangent and bitangent of the
+	 * vertices stay as they are, as they follow from the positions and
+	 * texture coordinates of the triangles, which don't depend on the order
+	 * their vertices are visited in, so the tangent space normal maps get
+	 * sampled in is the same either way. */
+	pub fn flip_winding(&mut self) {
+		for triangle in self.indices.chunks_exact_mut(3) {
+			triangle.swap(1, 2);
+		}
+	}
+
+	/** Reverses the winding order of the triangles in this mesh that vote for
+	 * the other winding order in [`winding_order()`], returning how many of
+	 * them got flipped. Triangles that don't vote are left alone.
+	 *
+	 * [`winding_order()`]: Self::winding_order */
+	pub fn fix_winding(&mut self, winding: FrontFace) -> usize {
+		let mut flipped = 0;
+		for index in (0..self.indices.len() / 3).map(|index| index * 3) {
+			let triangle = &self.indices[index..index + 3];
+			match self.triangle_winding(triangle) {
+				Some(current) if current != winding => {
+					self.indices.swap(index + 1, index + 2);
+					flipped += 1;
+				},
+				_ => {}
+			}
+		}
+
+		flipped
+	}
+
+	/** Winding order under which the face normal of the given triangle points
+	 * to the same side as its vertex normals, if that can be told. */
+	fn triangle_winding(&self, triangle: &[u32]) -> Option<FrontFace> {
+		let vertex = |index: u32| &self.vertices[index as usize];
+		let (vert0, vert1, vert2) = (
+			vertex(triangle[0]),
+			vertex(triangle[1]),
+			vertex(triangle[2]));
+
+		let face = face_normal(
+			vert0.position(),
+			vert1.position(),
+			vert2.position());
+		let normal = [
+			vert0.normal()[0] + vert1.normal()[0] + vert2.normal()[0],
+			vert0.normal()[1] + vert1.normal()[1] + vert2.normal()[1],
+			vert0.normal()[2] + vert1.normal()[2] + vert2.normal()[2],
+		];
+
+		/* Compare the cosine of the angle between both normals, so that the
+		 * tolerance doesn't depend on the size of the triangle. */
+		let dot = face[0] * normal[0] + face[1] * normal[1] + face[2] * normal[2];
+		let lengths = length(face) * length(normal);
+		if lengths == 0.0 { return None }
+
+		let cosine = dot / lengths;
+		if cosine > WINDING_TOLERANCE {
+			Some(FrontFace::Ccw)
+		} else if cosine < -WINDING_TOLERANCE {
+			Some(FrontFace::Cw)
+		} else {
+			None
+		}
+	}
+}
+
+/** Smoothing angle, in radians, normals get rebuilt with when a model comes
+ * with none at all, and nothing else was asked for. */
+pub const DEFAULT_SMOOTHING_ANGLE: f32 = std::f32::consts::FRAC_PI_3;
+
+/** Smallest cosine of the angle between the face and vertex normals of a
+ * triangle for it to vote on the winding order of its mesh. */
+const WINDING_TOLERANCE: f32 = 1e-3;
+
+/** Normal of the face of the triangle with the given vertices, following the
+ * right hand rule, and scaled by twice the area of the triangle. */
+fn face_normal(vert0: [f32; 3], vert1: [f32; 3], vert2: [f32; 3]) -> [f32; 3] {
+	let edge0 = [vert1[0] - vert0[0], vert1[1] - vert0[1], vert1[2] - vert0[2]];
+	let edge1 = [vert2[0] - vert0[0], vert2[1] - vert0[1], vert2[2] - vert0[2]];
+
+	[
+		edge0[1] * edge1[2] - edge0[2] * edge1[1],
+		edge0[2] * edge1[0] - edge0[0] * edge1[2],
+		edge0[0] * edge1[1] - edge0[1] * edge1[0],
+	]
+}
+
+/** Length of the given vector. */
+fn length(vector: [f32; 3]) -> f32 {
+	f32::sqrt(vector[0].powf(2.0) + vector[1].powf(2.0) + vector[2].powf(2.0))
+}
+
+/** Number of vertices that can be addressed with 16-bit indices. The largest
+ * index, `0xffff`, is left out, as WebGL 2 always treats it as a primitive
+ * restart. */
+const U16_VERTICES: usize = u16::MAX as usize;
+
+/** Integer types the indices of a mesh can be uploaded to the device as. */
+pub trait MeshIndex: bytemuck::Pod {
+	/** Format pipelines must read index buffers of this type in. */
+	const FORMAT: IndexFormat;
+}
+impl MeshIndex for u16 {
+	const FORMAT: IndexFormat = IndexFormat::Uint16;
+}
+impl MeshIndex for u32 {
+	const FORMAT: IndexFormat = IndexFormat::Uint32;
+}
+
+/** Number of transformed vertices [`Mesh::optimize()`] assumes the device
+ * keeps around. Most hardware keeps at least this many. */
+pub const VERTEX_CACHE_SIZE: usize = 16;
+
+/** Order of the given triangles, by their indices into a buffer of the given
+ * number of vertices, that makes the best use of a vertex cache of the given
+ * size, as worked out by the Tipsify algorithm.
+ *
+ * The triangles around one vertex at a time get emitted together, after
+ * which the next vertex to fan around is picked among the ones that were just
+ * used, preferring those which are still in the cache and will stay there for
+ * the triangles they have left. When none are left, the most recently used
+ * vertex with triangles left, or the lowest numbered one, is picked. */
+fn vertex_cache_order(indices: &[u32], vertices: usize, cache_size: usize) -> Vec<u32> {
+	let triangles = indices.len() / 3;
+
+	/* Triangles around every vertex, packed in one buffer, with those around
+	 * vertex `i` in `adjacency[offsets[i]..offsets[i + 1]]`. */
+	let mut live = vec![0usize; vertices];
+	for index in &indices[..triangles * 3] {
+		live[*index as usize] += 1;
+	}
+	let mut offsets = Vec::with_capacity(vertices + 1);
+	offsets.push(0);
+	for count in &live {
+		offsets.push(offsets[offsets.len() - 1] + count);
+	}
+	let mut adjacency = vec![0usize; triangles * 3];
+	let mut cursors = offsets[..vertices].to_vec();
+	for (triangle, corners) in indices.chunks_exact(3).enumerate() {
+		for vertex in corners {
+			adjacency[cursors[*vertex as usize]] = triangle;
+			cursors[*vertex as usize] += 1;
+		}
+	}
+
+	/* Time at which every vertex last entered the cache. The clock starts past
+	 * the size of the cache, so that every vertex starts out of it. */
+	let mut cache_time = vec![0usize; vertices];
+	let mut time = cache_size + 1;
+
+	let mut emitted = vec![false; triangles];
+	let mut dead_end = Vec::new();
+	let mut candidates = Vec::new();
+	let mut cursor = 0;
+	let mut order = Vec::with_capacity(triangles * 3);
+
+	let mut fanning = if vertices > 0 { Some(0) } else { None };
+	while let Some(vertex) = fanning {
+		candidates.clear();
+		for triangle in &adjacency[offsets[vertex]..offsets[vertex + 1]] {
+			if emitted[*triangle] { continue }
+			emitted[*triangle] = true;
+
+			for index in &indices[triangle * 3..triangle * 3 + 3] {
+				order.push(*index);
+				let index = *index as usize;
+				dead_end.push(index);
+				candidates.push(index);
+
+				live[index] -= 1;
+				if time - cache_time[index] > cache_size {
+					cache_time[index] = time;
+					time += 1;
+				}
+			}
+		}
+
+		/* Pick the candidate that entered the cache the longest ago, among the
+		 * ones that will still be in it after its remaining triangles are
+		 * drawn. Candidates that won't be get the lowest priority. */
+		let mut best = None;
+		let mut priority = None;
+		for candidate in &candidates {
+			let candidate = *candidate;
+			if live[candidate] == 0 { continue }
+
+			let age = time - cache_time[candidate];
+			let score = if age + 2 * live[candidate] <= cache_size { age } else { 0 };
+			if priority.map(|priority| score > priority).unwrap_or(true) {
+				priority = Some(score);
+				best = Some(candidate);
+			}
+		}
+
+		fanning = best.or_else(|| {
+			while let Some(vertex) = dead_end.pop() {
+				if live[vertex] > 0 { return Some(vertex) }
+			}
+			while cursor < vertices {
+				if live[cursor] > 0 { return Some(cursor) }
+				cursor += 1;
+			}
+			None
+		});
+	}
+
+	order
+}
+
+/** Vertex of a triangle being loaded, as the bit patterns of its components,
+ * so that vertices shared between triangles can be found by hashing.
+ *
+ * Both zeroes share the same bit pattern in here, so that they still compare
+ * equal, as they do as floating point numbers. */
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+struct VertexKey {
+	position: [u32; 3],
+	normal:   [u32; 3],
+	texture:  [u32; 2],
+}
+impl VertexKey {
+	fn new(position: [f32; 3], normal: [f32; 3], texture: [f32; 2]) -> Self {
+		Self {
+			position: position.map(bits),
+			normal: normal.map(bits),
+			texture: texture.map(bits)
+		}
+	}
+
+	fn position(&self) -> [f32; 3] {
+		self.position.map(f32::from_bits)
+	}
+
+	fn normal(&self) -> Vec3 {
+		Vec3::from(self.normal.map(f32::from_bits))
+	}
+
+	fn texture(&self) -> [f32; 2] {
+		self.texture.map(f32::from_bits)
+	}
+}
+
+/** Bit pattern of the given number, with both zeroes sharing the same one. */
+fn bits(value: f32) -> u32 {
+	if value == 0.0 { 0 } else { value.to_bits() }
+}
+
+/** Triangle ready to be assembled into a mesh. */
+struct Triangle {
+	/** Vertices of the triangle, in order. */
+	vertices: [VertexKey; 3],
+	/** Tangent vector of the face. */
+	tangent: Vec3,
+	/** Bitangent vector of the face. */
+	bitangent: Vec3,
+}
+
+/** Looks up the vertices of a triangle in an object file. */
+fn obj_triangle<'a, V>(vertices: &'a [V], triplet: &[u32]) -> Result<[&'a V; 3], InvalidMesh> {
+	let vertex = |index: u32| usize::try_from(index)
+		.map(|index| &vertices[index])
+		.map_err(|what| InvalidMesh::InnumerableVertices { what });
+
+	Ok([vertex(triplet[0])?, vertex(triplet[1])?, vertex(triplet[2])?])
+}
+
+/** Records the given warning if the options allow for the face it is about
+ * to be skipped, or fails with the given error otherwise. */
+fn skip(
+	options: &MeshOptions,
+	warnings: &mut Vec<MeshWarning>,
+	warning: MeshWarning,
+	error: InvalidMesh) -> Result<(), InvalidMesh> {
+
+	if options.skip_degenerate_faces {
+		warnings.push(warning);
+		Ok(())
+	} else {
+		Err(error)
+	}
+}
+
+/** Tangent and bitangent of the axis aligned plane the given unit normal
+ * faces the most, picked so that they form a right handed frame with it. */
+fn planar_axes(normal: Vec3) -> (Vec3, Vec3) {
+	let [x, y, z] = [normal.x(), normal.y(), normal.z()];
+	let sign = |value: f32| if value < 0.0 { -1.0 } else { 1.0 };
+
+	if x.abs() >= y.abs() && x.abs() >= z.abs() {
+		(Vec3::new(0.0, 0.0, -sign(x)), Vec3::new(0.0, 1.0, 0.0))
+	} else if y.abs() >= z.abs() {
+		(Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -sign(y)))
+	} else {
+		(Vec3::new(sign(z), 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0))
+	}
+}
+
+/** Orthonormal tangent space around the given normal, with the tangent as
+ * close as it can be to the given one, and the bitangent on the same side of
+ * the plane of the other two as the given one, or `None` if the normal has no
+ * length. */
+fn tangent_space(normal: Vec3, tangent: Vec3, bitangent: Vec3) -> Option<(Vec3, Vec3, Vec3)> {
+	let normal = normal.normalize()?;
+	let tangent = (tangent - normal * normal.dot(tangent)).normalize()
+		.or_else(|| (bitangent - normal * normal.dot(bitangent)).normalize()
+			.map(|bitangent| bitangent.cross(normal)))
+		.unwrap_or_else(|| planar_axes(normal).0);
+	let tangent = (tangent - normal * normal.dot(tangent)).normalize()?;
+
+	let mut bitangent_ = normal.cross(tangent);
+	if bitangent_.dot(bitangent) < 0.0 {
+		bitangent_ = -bitangent_;
+	}
+
+	Some((normal, tangent, bitangent_))
+}
+
+/** Estimate of the winding order of the triangles in a mesh, as the number of
+ * triangles voting for each of the winding orders. */
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct WindingEstimate {
+	/** Number of triangles wound counter clockwise. */
+	pub counter_clockwise: usize,
+	/** Number of triangles wound clockwise. */
+	pub clockwise: usize,
+	/** Number of triangles whose winding order couldn't be told. */
+	pub undecided: usize,
+}
+impl WindingEstimate {
+	/** The winding order most triangles are in, if there is one. Ties, which
+	 * include meshes where no triangles voted at all, have none. */
+	pub fn majority(&self) -> Option<FrontFace> {
+		match self.counter_clockwise.cmp(&self.clockwise) {
+			std::cmp::Ordering::Greater => Some(FrontFace::Ccw),
+			std::cmp::Ordering::Less => Some(FrontFace::Cw),
+			std::cmp::Ordering::Equal => None
+		}
+	}
+
+	/** Share of all of the triangles voting for the majority, going from zero,
+	 * when there is no majority, to one, when every triangle voted for it. */
+	pub fn confidence(&self) -> f32 {
+		let total = self.counter_clockwise + self.clockwise + self.undecided;
+		match self.majority() {
+			Some(FrontFace::Ccw) => self.counter_clockwise as f32 / total as f32,
+			Some(FrontFace::Cw) => self.clockwise as f32 / total as f32,
+			None => 0.0
+		}
+	}
+
+	/** Whether all of the triangles that voted agree on the winding order. */
+	pub fn is_consistent(&self) -> bool {
+		self.counter_clockwise == 0 || self.clockwise == 0
+	}
+}
+
+/** Tangent and bitangent vectors of a triangle, in model space, given the
+ * vectors along two of its edges, from a shared vertex, and the differences in
+ * texture coordinates along the same edges, which must not be parallel. */
+fn face_tangents(edge0: Vec3, edge1: Vec3, uv0: [f32; 2], uv1: [f32; 2]) -> (Vec3, Vec3) {
+	let base = 1.0 / (uv0[0] * uv1[1] - uv0[1] * uv1[0]);
+	let tangent = (edge0 * uv1[1] - edge1 * uv0[1]) * base;
+	let bitangent = (edge1 * uv0[0] - edge0 * uv1[0]) * base;
+
+	(tangent, bitangent)
+}
+
+/** Faces left out of a mesh as it got loaded, by their index in the model. */
+#[derive(Debug, Copy, Clone, PartialEq, thiserror::Error)]
+pub enum MeshWarning {
+	#[error("Face {face} is a degenerate triangle. The length of the cross \
+		product vector of the edge vectors is {edge_cross} and the length of \
+		the cross vector of the texture vectors is {uv_cross}")]
+	DegenerateTriangle {
+		face: usize,
+		edge_cross: f32,
+		uv_cross: f32,
+	},
+	#[error("The surface normal of face {face} is a null vector")]
+	NullSurfaceNormal {
+		face: usize,
+	},
+}
+
+/** Error for an index that doesn't fit in 16 bits. */
+#[derive(Debug, thiserror::Error)]
+#[error("The index {index} does not fit in 16 bits, which can only address \
+	vertices up to {}", U16_VERTICES - 1)]
+pub struct IndexOverflow {
+	/** The index that didn't fit. */
+	pub index: u32,
 }
 
 /** Error types for invalid meshes. */
@@ -317,5 +1090,612 @@ pub enum InvalidMesh {
 	#[error("One of the calculated surface normals is a null vector")]
 	NullSurfaceNormal,
 	#[error("The number of vertices in the mesh would be larger than a u32: {what}")]
-	InnumerableVertices { what: TryFromIntError }
+	InnumerableVertices { what: TryFromIntError },
+	#[error("The mesh has {len} {attribute} for {vertices} vertices")]
+	AttributeLength {
+		attribute: &'static str,
+		len: usize,
+		vertices: usize,
+	},
+	#[error("The mesh refers to vertex {index}, but only has {vertices} vertices")]
+	IndexOutOfRange {
+		index: u32,
+		vertices: usize,
+	},
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const UP: [f32; 3] = [0.0, 0.0, 1.0];
+	const TANGENT: [f32; 3] = [1.0, 0.0, 0.0];
+	const BITANGENT: [f32; 3] = [0.0, 1.0, 0.0];
+
+	/** Vertex on the XY plane, facing up, with texture coordinates matching
+	 * its position. */
+	fn vertex(x: f32, y: f32, normal: [f32; 3]) -> Vertex {
+		Vertex::new_unchecked([x, y, 0.0], [x, y], normal, TANGENT, BITANGENT)
+	}
+
+	/** A unit square on the XY plane made of two triangles, wound counter
+	 * clockwise when seen from above, with all of its normals pointing up. */
+	fn square() -> Mesh {
+		Mesh {
+			vertices: vec![
+				vertex(0.0, 0.0, UP),
+				vertex(1.0, 0.0, UP),
+				vertex(1.0, 1.0, UP),
+				vertex(0.0, 1.0, UP),
+			],
+			indices: vec![0, 1, 2, 0, 2, 3]
+		}
+	}
+
+	/** Tangent and bitangent of the given triangle, as worked out from its
+	 * positions and texture coordinates. */
+	fn tangents(mesh: &Mesh, triangle: &[u32]) -> ([f32; 3], [f32; 3]) {
+		let vert = |index: usize| mesh.vertices[triangle[index] as usize];
+		let edge = |index: usize|
+			Vec3::from(vert(index).position()) - Vec3::from(vert(0).position());
+		let uv = |index: usize| {
+			let (a, b) = (vert(0).texture(), vert(index).texture());
+			[b[0] - a[0], b[1] - a[1]]
+		};
+
+		let (tangent, bitangent) = face_tangents(edge(1), edge(2), uv(1), uv(2));
+		(tangent.into(), bitangent.into())
+	}
+
+	#[test]
+	fn tangents_follow_the_texture_coordinates() {
+		let x = Vec3::new(2.0, 0.0, 0.0);
+		let y = Vec3::new(0.0, 2.0, 0.0);
+
+		/* Texture coordinates running along the edges, at half the rate. */
+		assert_eq!(
+			face_tangents(x, y, [1.0, 0.0], [0.0, 1.0]),
+			(x, y));
+
+		/* Texture coordinates turned a quarter of a turn from the edges. */
+		assert_eq!(
+			face_tangents(x, y, [0.0, 1.0], [-1.0, 0.0]),
+			(-y, x));
+	}
+
+	#[test]
+	fn indexed_models_keep_their_tangents() {
+		let positions = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 0.0]];
+		let normals = [UP; 4];
+		let texture = [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]];
+		let tangents = [[1.0, 0.0, 0.0, -1.0]; 4];
+		let indices = [0, 1, 2, 0, 2, 3];
+
+		let (mesh, warnings) = Mesh::from_indexed(
+			&positions,
+			Some(&normals),
+			Some(&texture),
+			Some(&tangents),
+			&indices,
+			&Default::default()).unwrap();
+		assert!(warnings.is_empty());
+		assert_eq!(mesh.indices(), &indices);
+		for vertex in mesh.vertices() {
+			assert_eq!(vertex.tangent(), TANGENT);
+			assert_eq!(vertex.bitangent(), [0.0, -1.0, 0.0]);
+		}
+
+		/* Without tangents, they follow the texture coordinates instead. */
+		let (mesh, _) = Mesh::from_indexed(
+			&positions,
+			Some(&normals),
+			Some(&texture),
+			None,
+			&indices,
+			&Default::default()).unwrap();
+		assert_eq!(mesh.vertices().len(), 4);
+		for vertex in mesh.vertices() {
+			assert_eq!(vertex.tangent(), TANGENT);
+			assert_eq!(vertex.bitangent(), BITANGENT);
+		}
+	}
+
+	#[test]
+	fn indexed_models_without_normals_get_them_rebuilt() {
+		let positions = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 0.0]];
+		let (mesh, _) = Mesh::from_indexed(
+			&positions,
+			None,
+			None,
+			None,
+			&[0, 1, 2, 0, 2, 3],
+			&Default::default()).unwrap();
+
+		assert_eq!(mesh.vertices().len(), 4);
+		for vertex in mesh.vertices() {
+			assert_eq!(vertex.normal(), UP);
+		}
+
+		assert!(matches!(
+			Mesh::from_indexed(&positions, None, None, None, &[0, 1, 4], &Default::default()),
+			Err(InvalidMesh::IndexOutOfRange { index: 4, vertices: 4 })));
+		assert!(matches!(
+			Mesh::from_indexed(&positions, Some(&[UP]), None, None, &[0, 1, 2], &Default::default()),
+			Err(InvalidMesh::AttributeLength { len: 1, vertices: 4, .. })));
+	}
+
+	#[test]
+	fn counter_clockwise_meshes_are_estimated_as_such() {
+		let estimate = square().winding_order();
+		assert_eq!(estimate, WindingEstimate {
+			counter_clockwise: 2,
+			clockwise: 0,
+			undecided: 0
+		});
+		assert_eq!(estimate.majority(), Some(FrontFace::Ccw));
+		assert!(estimate.is_consistent());
+		assert_eq!(estimate.confidence(), 1.0);
+	}
+
+	#[test]
+	fn clockwise_meshes_are_estimated_as_such() {
+		let mut mesh = square();
+		mesh.indices = vec![0, 2, 1, 0, 3, 2];
+
+		let estimate = mesh.winding_order();
+		assert_eq!(estimate.clockwise, 2);
+		assert_eq!(estimate.majority(), Some(FrontFace::Cw));
+		assert_eq!(estimate.confidence(), 1.0);
+	}
+
+	#[test]
+	fn undecidable_triangles_do_not_vote() {
+		let mut mesh = square();
+
+		/* One triangle with normals along its plane, and one with no area. */
+		mesh.vertices.push(vertex(2.0, 0.0, TANGENT));
+		mesh.vertices.push(vertex(3.0, 0.0, TANGENT));
+		mesh.vertices.push(vertex(2.0, 1.0, TANGENT));
+		mesh.indices.extend_from_slice(&[4, 5, 6, 0, 1, 1]);
+
+		let estimate = mesh.winding_order();
+		assert_eq!(estimate, WindingEstimate {
+			counter_clockwise: 2,
+			clockwise: 0,
+			undecided: 2
+		});
+		assert_eq!(estimate.confidence(), 0.5);
+
+		assert_eq!(Mesh { vertices: vec![], indices: vec![] }
+			.winding_order()
+			.majority(), None);
+	}
+
+	#[test]
+	fn flipping_reverses_the_winding_and_keeps_the_tangent_space() {
+		let mut mesh = square();
+		let before = mesh.indices.chunks_exact(3)
+			.map(|triangle| tangents(&mesh, triangle))
+			.collect::<Vec<_>>();
+
+		mesh.flip_winding();
+		assert_eq!(mesh.indices, vec![0, 2, 1, 0, 3, 2]);
+		assert_eq!(mesh.winding_order().majority(), Some(FrontFace::Cw));
+
+		/* The tangents follow from the texture coordinates, so the ones in the
+		 * vertices still match the flipped triangles, and the handedness of
+		 * their frame is still the same. */
+		let after = mesh.indices.chunks_exact(3)
+			.map(|triangle| tangents(&mesh, triangle))
+			.collect::<Vec<_>>();
+		assert_eq!(before, after);
+		assert_eq!(after[0], (TANGENT, BITANGENT));
+		for vertex in &mesh.vertices {
+			assert_eq!(vertex.tangent(), TANGENT);
+			assert_eq!(vertex.bitangent(), BITANGENT);
+		}
+
+		mesh.flip_winding();
+		assert_eq!(mesh.indices, square().indices);
+	}
+
+	#[test]
+	fn inconsistent_meshes_are_fixed_one_triangle_at_a_time() {
+		let mut mesh = square();
+		mesh.vertices.push(vertex(2.0, 0.0, UP));
+		mesh.vertices.push(vertex(2.0, 1.0, UP));
+		mesh.indices.extend_from_slice(&[1, 4, 5, 1, 2, 5]);
+
+		let estimate = mesh.winding_order();
+		assert_eq!(estimate, WindingEstimate {
+			counter_clockwise: 3,
+			clockwise: 1,
+			undecided: 0
+		});
+		assert!(!estimate.is_consistent());
+		assert_eq!(estimate.majority(), Some(FrontFace::Ccw));
+		assert_eq!(estimate.confidence(), 0.75);
+
+		/* Only the odd one out gets flipped. */
+		assert_eq!(mesh.fix_winding(FrontFace::Ccw), 1);
+		assert_eq!(mesh.indices[9..], [1, 5, 2]);
+		assert_eq!(mesh.winding_order().counter_clockwise, 4);
+
+		assert_eq!(mesh.fix_winding(FrontFace::Cw), 4);
+		assert_eq!(mesh.winding_order().clockwise, 4);
+	}
+
+	/** Checks that every vertex in the mesh has an orthonormal tangent space. */
+	fn assert_orthonormal(mesh: &Mesh) {
+		for vertex in mesh.vertices() {
+			let (n, t, b) = (
+				Vec3::from(vertex.normal()),
+				Vec3::from(vertex.tangent()),
+				Vec3::from(vertex.bitangent()));
+
+			for length in &[n.length(), t.length(), b.length()] {
+				assert!((length - 1.0).abs() < 1e-5, "{:?}", vertex);
+			}
+			for dot in &[n.dot(t), n.dot(b), t.dot(b)] {
+				assert!(dot.abs() < 1e-5, "{:?}", vertex);
+			}
+		}
+	}
+
+	fn textured(position: [f32; 3], normal: [f32; 3], texture: [f32; 2]) -> obj::TexturedVertex {
+		obj::TexturedVertex {
+			position,
+			normal,
+			texture: [texture[0], texture[1], 0.0]
+		}
+	}
+
+	#[test]
+	fn untextured_models_get_planar_texture_coordinates() {
+		let vertices = [
+			([0.0, 0.0, 0.0], [0.0, 0.0, 1.0]),
+			([2.0, 0.0, 0.0], [0.0, 0.0, 1.0]),
+			([2.0, 3.0, 0.0], [0.0, 0.0, 1.0]),
+			([0.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+			([0.0, 0.0, 1.0], [0.0, 1.0, 0.0]),
+			([1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+		];
+		let model = obj::Obj {
+			name: None,
+			vertices: vertices.iter()
+				.map(|&(position, normal)| obj::Vertex { position, normal })
+				.collect(),
+			indices: vec![0, 1, 2, 3, 4, 5]
+		};
+
+		let (mesh, warnings) = Mesh::from_obj_positions_normals(
+			&model,
+			&MeshOptions::default()).unwrap();
+		assert!(warnings.is_empty());
+		assert_eq!(mesh.indices().len(), 6);
+		assert_orthonormal(&mesh);
+
+		/* Faces pointing up the z axis get their coordinates from x and y, and
+		 * ones pointing up the y axis get them from x and z. */
+		let texture = |position: [f32; 3]| mesh.vertices().iter()
+			.find(|vertex| vertex.position() == position)
+			.map(|vertex| vertex.texture())
+			.unwrap();
+		assert_eq!(texture([2.0, 3.0, 0.0]), [2.0, 3.0]);
+		assert_eq!(texture([0.0, 0.0, 1.0]), [0.0, -1.0]);
+		assert_eq!(texture([1.0, 0.0, 0.0]), [1.0, 0.0]);
+	}
+
+	#[test]
+	fn degenerate_faces_are_skipped_when_asked_to() {
+		let model = obj::Obj {
+			name: None,
+			vertices: vec![
+				textured([0.0, 0.0, 0.0], UP, [0.0, 0.0]),
+				textured([1.0, 0.0, 0.0], UP, [1.0, 0.0]),
+				textured([1.0, 1.0, 0.0], UP, [1.0, 1.0]),
+				textured([0.0, 1.0, 0.0], UP, [1.0, 1.0]),
+			],
+			/* The second face has all of its texture coordinates on a line. */
+			indices: vec![0, 1, 2, 1, 2, 3]
+		};
+
+		assert!(matches!(
+			Mesh::from_obj(&model),
+			Err(InvalidMesh::DegenerateTriangle { .. })));
+
+		let options = MeshOptions {
+			skip_degenerate_faces: true,
+			..Default::default()
+		};
+		let (mesh, warnings) = Mesh::from_obj_with_warnings(&model, &options).unwrap();
+		assert_eq!(mesh.indices().len(), 3);
+		assert_eq!(mesh.vertices().len(), 3);
+		assert!(matches!(
+			warnings.as_slice(),
+			[MeshWarning::DegenerateTriangle { face: 1, .. }]));
+		assert_orthonormal(&mesh);
+	}
+
+	#[test]
+	fn kept_faces_have_orthonormal_tangent_spaces() {
+		/* Normals leaning away from the plane of the face, and texture
+		 * coordinates that are sheared, so that the tangents worked out from
+		 * them are neither perpendicular to the normals nor to each other. */
+		let lean = Vec3::new(0.3, -0.2, 1.0).normalize().unwrap().into();
+		let model = obj::Obj {
+			name: None,
+			vertices: vec![
+				textured([0.0, 0.0, 0.0], lean, [0.0, 0.0]),
+				textured([1.0, 0.0, 0.0], UP, [1.0, 0.5]),
+				textured([0.0, 1.0, 0.0], UP, [0.2, 1.0]),
+			],
+			indices: vec![0, 1, 2]
+		};
+
+		let mesh = Mesh::from_obj(&model).unwrap();
+		assert_orthonormal(&mesh);
+		for vertex in mesh.vertices() {
+			assert!(Vertex::try_new(
+				vertex.position(),
+				vertex.texture(),
+				vertex.normal(),
+				vertex.tangent(),
+				vertex.bitangent()).is_ok());
+		}
+	}
+
+	/** Square grid of the given number of quads on each side, on the XY plane,
+	 * with every triangle getting its own copies of its vertices, as they are
+	 * in object files where faces don't share any of their attributes. */
+	fn grid(size: usize) -> obj::Obj<obj::TexturedVertex, u32> {
+		/* Negative zeroes must still be the same vertex as positive ones. */
+		let corner = |x: usize, y: usize| {
+			let (x, y) = (x as f32, y as f32);
+			let x = if x == 0.0 { -0.0 } else { x };
+			textured([x, y, 0.0], UP, [x, y])
+		};
+
+		let mut vertices = Vec::new();
+		for y in 0..size {
+			for x in 0..size {
+				vertices.extend_from_slice(&[
+					corner(x, y), corner(x + 1, y), corner(x + 1, y + 1),
+					corner(x, y), corner(x + 1, y + 1), corner(x, y + 1),
+				]);
+			}
+		}
+
+		obj::Obj {
+			name: None,
+			indices: (0..vertices.len() as u32).collect(),
+			vertices
+		}
+	}
+
+	#[test]
+	fn hashed_vertices_match_sorted_ones() {
+		let model = grid(32);
+		let mesh = Mesh::from_obj(&model).unwrap();
+
+		/* Sorting the vertices and searching for every index among them, as
+		 * the meshes used to be built. */
+		let attributes = |vertex: &obj::TexturedVertex| [
+			vertex.position[0], vertex.position[1], vertex.position[2],
+			vertex.normal[0], vertex.normal[1], vertex.normal[2],
+			vertex.texture[0], vertex.texture[1],
+		];
+		let mut sorted = model.vertices.iter().map(attributes).collect::<Vec<_>>();
+		sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+		sorted.dedup();
+		let indices = model.indices.iter()
+			.map(|index| attributes(&model.vertices[*index as usize]))
+			.map(|vertex| sorted.binary_search_by(|a| a.partial_cmp(&vertex).unwrap()).unwrap())
+			.collect::<Vec<_>>();
+
+		assert_eq!(mesh.vertices().len(), 33 * 33);
+		assert_eq!(mesh.vertices().len(), sorted.len());
+		assert_eq!(mesh.indices().len(), indices.len());
+		for (index, sorted_index) in mesh.indices().iter().zip(&indices) {
+			let vertex = &mesh.vertices()[*index as usize];
+			let expected = &sorted[*sorted_index];
+
+			assert_eq!(vertex.position(), [expected[0], expected[1], expected[2]]);
+			assert_eq!(vertex.normal(), [expected[3], expected[4], expected[5]]);
+			assert_eq!(vertex.texture(), [expected[6], expected[7]]);
+		}
+	}
+
+	/** Triangles of the mesh, as the vertices they index, in order. */
+	fn triangles(mesh: &Mesh) -> Vec<[u32; 3]> {
+		mesh.indices().chunks_exact(3)
+			.map(|triangle| [triangle[0], triangle[1], triangle[2]])
+			.collect()
+	}
+
+	#[test]
+	fn optimizing_keeps_the_triangles_and_reduces_cache_misses() {
+		let mut mesh = Mesh::from_obj(&grid(24)).unwrap();
+
+		/* Scatter the triangles around, so that there's nothing in the cache
+		 * by the time their neighbours get drawn. */
+		let mut shuffled = triangles(&mesh);
+		let count = shuffled.len();
+		for index in 0..count {
+			shuffled.swap(index, (index * 7919 + 13) % count);
+		}
+		mesh.indices = shuffled.iter().flatten().copied().collect();
+
+		let before = mesh.average_cache_miss_ratio(VERTEX_CACHE_SIZE);
+		mesh.optimize();
+		let after = mesh.average_cache_miss_ratio(VERTEX_CACHE_SIZE);
+		assert!(after < before * 0.5, "{} is not much better than {}", after, before);
+		assert!(after < 1.0, "{}", after);
+
+		/* The same triangles are still there, wound the same way. */
+		let mut optimized = triangles(&mesh);
+		optimized.sort_unstable();
+		shuffled.sort_unstable();
+		assert_eq!(optimized, shuffled);
+	}
+
+	#[test]
+	fn cache_miss_ratio_counts_transformed_vertices() {
+		let mut mesh = square();
+		assert_eq!(mesh.average_cache_miss_ratio(16), 2.0);
+		assert_eq!(mesh.average_cache_miss_ratio(0), 3.0);
+
+		mesh.indices.clear();
+		assert_eq!(mesh.average_cache_miss_ratio(16), 0.0);
+		mesh.optimize();
+		assert!(mesh.indices().is_empty());
+	}
+
+	#[test]
+	fn small_meshes_use_16_bit_indices() {
+		let mut mesh = square();
+		assert_eq!(mesh.index_format(), IndexFormat::Uint16);
+		assert_eq!(mesh.indices_u16().unwrap(), vec![0, 1, 2, 0, 2, 3]);
+
+		mesh.vertices = vec![vertex(0.0, 0.0, UP); U16_VERTICES];
+		assert_eq!(mesh.index_format(), IndexFormat::Uint16);
+		mesh.indices.push(0xfffe);
+		assert_eq!(*mesh.indices_u16().unwrap().last().unwrap(), 0xfffe);
+
+		/* The primitive restart index is never handed out. */
+		mesh.vertices.push(vertex(0.0, 0.0, UP));
+		mesh.indices.push(0xffff);
+		assert_eq!(mesh.index_format(), IndexFormat::Uint32);
+		assert_eq!(mesh.indices_u16().unwrap_err().index, 0xffff);
+	}
+
+	#[test]
+	fn split_meshes_draw_the_same_triangles() {
+		let mesh = Mesh::from_obj(&grid(4)).unwrap();
+		let triangles = |mesh: &Mesh| mesh.indices().chunks_exact(3)
+			.map(|triangle| triangle.iter()
+				.map(|index| mesh.vertices()[*index as usize])
+				.collect::<Vec<_>>())
+			.collect::<Vec<_>>();
+
+		let parts = mesh.split(7);
+		assert!(parts.len() > 1);
+		for part in &parts {
+			assert!(part.vertices().len() <= 7);
+			assert!(part.indices().iter().all(|index| (*index as usize) < part.vertices().len()));
+		}
+		assert_eq!(
+			parts.iter().flat_map(triangles).collect::<Vec<_>>(),
+			triangles(&mesh));
+
+		let parts = mesh.split_for_u16();
+		assert_eq!(parts.len(), 1);
+		assert_eq!(triangles(&parts[0]), triangles(&mesh));
+		assert_eq!(parts[0].vertices().len(), mesh.vertices().len());
+	}
+
+	/** Cube going from minus one to one along every axis, with one vertex at
+	 * each of its corners, shared between all of the faces around it, with
+	 * texture coordinates that only span the faces facing along `z`. */
+	fn welded_cube() -> Mesh {
+		let corner = |index: u32| {
+			let position = [
+				if index & 1 == 0 { -1.0 } else { 1.0 },
+				if index & 2 == 0 { -1.0 } else { 1.0 },
+				if index & 4 == 0 { -1.0 } else { 1.0 },
+			];
+			Vertex::new_unchecked(position, [position[0], position[1]], UP, TANGENT, BITANGENT)
+		};
+
+		Mesh {
+			vertices: (0..8).map(corner).collect(),
+			indices: vec![
+				1, 3, 7, 1, 7, 5, /* +x */
+				0, 4, 6, 0, 6, 2, /* -x */
+				2, 6, 7, 2, 7, 3, /* +y */
+				0, 1, 5, 0, 5, 4, /* -y */
+				4, 5, 7, 4, 7, 6, /* +z */
+				0, 2, 3, 0, 3, 1, /* -z */
+			]
+		}
+	}
+
+	#[test]
+	fn hard_edges_split_vertices() {
+		let mut mesh = welded_cube();
+		mesh.recompute_normals(std::f32::consts::FRAC_PI_4);
+
+		assert_eq!(mesh.vertices().len(), 24);
+		assert_eq!(mesh.indices().len(), 36);
+		assert_orthonormal(&mesh);
+		assert_eq!(mesh.winding_order().counter_clockwise, 12);
+
+		/* Every corner of every face takes the normal of the face. */
+		for triangle in mesh.indices().chunks_exact(3) {
+			let [a, b, c] = [0, 1, 2].map(|index| mesh.vertices()[triangle[index] as usize]);
+			let face = (Vec3::from(b.position()) - Vec3::from(a.position()))
+				.cross(Vec3::from(c.position()) - Vec3::from(a.position()))
+				.normalize()
+				.unwrap();
+			for vertex in &[a, b, c] {
+				assert!((Vec3::from(vertex.normal()) - face).length() < 1e-6);
+			}
+		}
+
+		/* Smoothing across every edge weighs the three faces around a corner
+		 * the same, however many triangles they're split into. */
+		let mut mesh = welded_cube();
+		mesh.recompute_normals(std::f32::consts::PI);
+
+		assert_eq!(mesh.vertices().len(), 8);
+		assert_orthonormal(&mesh);
+		for vertex in mesh.vertices() {
+			let expected = Vec3::from(vertex.position()).normalize().unwrap();
+			assert!((Vec3::from(vertex.normal()) - expected).length() < 1e-5);
+		}
+	}
+
+	#[test]
+	fn smooth_surfaces_keep_sharing_vertices() {
+		let mut sphere = Mesh::uv_sphere(8, 12);
+		let vertices = sphere.vertices().len();
+		let indices = sphere.indices().len();
+
+		sphere.recompute_normals(std::f32::consts::FRAC_PI_3);
+		assert_eq!(sphere.vertices().len(), vertices);
+		assert_eq!(sphere.indices().len(), indices);
+		assert_orthonormal(&sphere);
+		for vertex in sphere.vertices() {
+			let outwards = Vec3::from(vertex.position());
+			assert!(Vec3::from(vertex.normal()).dot(outwards) > 0.97, "{:?}", vertex);
+		}
+	}
+
+	#[test]
+	fn broken_normals_can_be_rebuilt_on_load() {
+		let model = obj::Obj {
+			name: None,
+			vertices: vec![
+				textured([0.0, 0.0, 0.0], [0.0; 3], [0.0, 0.0]),
+				textured([1.0, 0.0, 0.0], [0.0; 3], [1.0, 0.0]),
+				textured([1.0, 1.0, 0.0], [0.0; 3], [1.0, 1.0]),
+				textured([0.0, 1.0, 0.0], [0.0; 3], [0.0, 1.0]),
+			],
+			indices: vec![0, 1, 2, 0, 2, 3]
+		};
+		assert!(matches!(Mesh::from_obj(&model), Err(InvalidMesh::NullSurfaceNormal)));
+
+		let options = MeshOptions {
+			recompute_normals: Some(std::f32::consts::FRAC_PI_4),
+			..Default::default()
+		};
+		let (mesh, warnings) = Mesh::from_obj_with_warnings(&model, &options).unwrap();
+		assert!(warnings.is_empty());
+		assert_eq!(mesh.vertices().len(), 4);
+		for vertex in mesh.vertices() {
+			assert_eq!(vertex.normal(), UP);
+			assert_eq!(vertex.tangent(), TANGENT);
+			assert_eq!(vertex.bitangent(), BITANGENT);
+		}
+	}
 }
@@ -1,9 +1,58 @@
 mod matrix;
 pub use matrix::*;
+mod vector;
+pub use vector::*;
+mod quaternion;
+pub use quaternion::*;
 mod vertex;
 pub use vertex::*;
 mod mesh;
 pub use mesh::*;
+mod shapes;
 mod camera;
 pub use camera::*;
+mod frustum;
+pub use frustum::*;
 
+mod camtrack;
+pub use camtrack::*;
+mod lights;
+pub use lights::*;
+mod queue;
+pub use queue::*;
+mod skinning;
+pub use skinning::*;
+mod animation;
+pub use animation::*;
+mod sky;
+pub use sky::*;
+mod debugview;
+pub use debugview::*;
+mod grade;
+pub use grade::*;
+mod gamma;
+pub use gamma::*;
+mod video;
+pub use video::*;
+mod scenefile;
+pub use scenefile::*;
+mod msaa;
+pub use msaa::*;
+mod compressed;
+pub use compressed::*;
+mod ktx2;
+pub use ktx2::*;
+mod text;
+pub use text::*;
+mod sprite;
+pub use sprite::*;
+mod orbit;
+pub use orbit::*;
+mod growable;
+pub use growable::*;
+mod arena;
+pub use arena::*;
+#[cfg(feature = "gltf")]
+mod gltf;
+#[cfg(feature = "gltf")]
+pub use self::gltf::*;
@@ -0,0 +1,453 @@
+use gavle::*;
+
+/** Clamps a requested number of samples per pixel to what the context can
+ * provide, with zero counting as one, logging whenever the request had to be
+ * lowered. */
+pub fn clamp_samples(requested: u32, max_samples: u32) -> u32 {
+	let samples = requested.max(1);
+	let max = max_samples.max(1);
+	if samples > max {
+		log::warn!(target: "support::msaa",
+			"{}x multisampling was requested, but the context only supports \
+			up to {}x, which will be used instead",
+			samples,
+			max);
+		max
+	} else {
+		samples
+	}
+}
+
+/** How the storage of an [`MsaaTarget`] is laid out for a given number of
+ * samples per pixel. */
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MsaaStorage {
+	/** Everything gets drawn straight into the resolve texture, which needs
+	 * no resolving at all. */
+	Passthrough,
+	/** Everything gets drawn into a multisampled framebuffer with the given
+	 * number of samples per pixel, which gets resolved into the resolve
+	 * texture by blitting it over. */
+	Multisampled(u32),
+}
+impl MsaaStorage {
+	/** The storage needed for the given number of samples per pixel. */
+	pub fn for_samples(samples: u32) -> Self {
+		if samples > 1 {
+			Self::Multisampled(samples)
+		} else {
+			Self::Passthrough
+		}
+	}
+
+	/** Number of samples per pixel of this storage. */
+	pub fn samples(&self) -> u32 {
+		match self {
+			Self::Passthrough => 1,
+			Self::Multisampled(samples) => *samples
+		}
+	}
+
+	/** Whether drawings into this storage have to be resolved before they
+	 * can be sampled from. */
+	pub fn resolves(&self) -> bool {
+		*self != Self::Passthrough
+	}
+}
+
+/** Keeps track of the storage an [`MsaaTarget`] has been asked for and the
+ * storage it has actually got, so that changing the setting any number of
+ * times in between two frames only ever recreates it once, if at all. */
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct MsaaState {
+	/** Storage layout currently asked for. */
+	storage: MsaaStorage,
+	/** Size currently asked for, in pixels. */
+	size: (u32, u32),
+	/** Storage layout and size of the storage that currently exists, if any. */
+	built: Option<(MsaaStorage, (u32, u32))>,
+}
+impl MsaaState {
+	/** Creates a new state with nothing built yet, asking for the given
+	 * number of samples, clamped to the given maximum, and for the given
+	 * size. */
+	pub fn new(samples: u32, max_samples: u32, width: u32, height: u32) -> Self {
+		Self {
+			storage: MsaaStorage::for_samples(clamp_samples(samples, max_samples)),
+			size: (width, height),
+			built: None
+		}
+	}
+
+	/** Asks for the given number of samples, clamped to the given maximum,
+	 * and returns the number that got asked for. */
+	pub fn set_samples(&mut self, samples: u32, max_samples: u32) -> u32 {
+		self.storage = MsaaStorage::for_samples(clamp_samples(samples, max_samples));
+		self.storage.samples()
+	}
+
+	/** Asks for the given size. */
+	pub fn set_size(&mut self, width: u32, height: u32) {
+		self.size = (width, height);
+	}
+
+	/** Storage layout currently asked for. */
+	pub fn storage(&self) -> MsaaStorage {
+		self.storage
+	}
+
+	/** Size currently asked for, in pixels. */
+	pub fn size(&self) -> (u32, u32) {
+		self.size
+	}
+
+	/** Whether the storage has to be created again before it can be used. */
+	pub fn needs_rebuild(&self) -> bool {
+		self.built != Some((self.storage, self.size))
+	}
+
+	/** Whether only the multisampled part of the storage has to be created
+	 * again, with the resolve texture being fine as it is. */
+	pub fn needs_resolve_rebuild(&self) -> bool {
+		self.built.map(|(_, size)| size) != Some(self.size)
+	}
+
+	/** Records that the storage currently asked for has been created. */
+	pub fn mark_built(&mut self) {
+		self.built = Some((self.storage, self.size));
+	}
+}
+
+/** Descriptor of a new [`MsaaTarget`]. */
+#[derive(Debug, Copy, Clone)]
+pub struct MsaaTargetDescriptor {
+	/** Width of the target, in pixels. */
+	pub width: u32,
+	/** Height of the target, in pixels. */
+	pub height: u32,
+	/** Number of samples per pixel, which gets clamped to what the context
+	 * supports. One turns multisampling off. */
+	pub samples: u32,
+	/** The operation to perform on the color storage when it is loaded. */
+	pub color_load_op: LoadOp<Color>,
+	/** The operation to perform on the depth storage when it is loaded. */
+	pub depth_load_op: LoadOp<f32>,
+	/** The operation to perform on the stencil storage when it is loaded. */
+	pub stencil_load_op: LoadOp<u8>,
+}
+
+/** Offscreen render target whose number of samples per pixel can be changed
+ * at any time, so that render code drawing into it and sampling from it looks
+ * the same whether multisampling is on or off.
+ *
+ * Drawings go into the framebuffer given by [`begin()`], and are read back as
+ * a single-sampled texture given by [`resolve()`]. With multisampling turned
+ * off, both refer to the same storage and resolving does nothing.
+ *
+ * [`begin()`]: Self::begin
+ * [`resolve()`]: Self::resolve */
+pub struct MsaaTarget {
+	/** Number of samples and size asked for, and those of the current
+	 * storage. */
+	state: MsaaState,
+	/** Load operations the framebuffers get created with. */
+	load_ops: (LoadOp<Color>, LoadOp<f32>, LoadOp<u8>),
+	/** Single-sampled color texture drawings get resolved into. */
+	color: Texture,
+	/** Framebuffer around the resolve texture, which also gets drawn into
+	 * directly when multisampling is off. */
+	resolve: Framebuffer,
+	/** Multisampled framebuffer, when multisampling is on. */
+	multisampled: Option<Framebuffer>,
+}
+impl MsaaTarget {
+	/** Creates a new target with the given size and number of samples. */
+	pub fn new(device: &Device, descriptor: &MsaaTargetDescriptor)
+		-> Result<Self, MsaaTargetError> {
+
+		let mut state = MsaaState::new(
+			descriptor.samples,
+			device.information().limits.max_samples,
+			descriptor.width,
+			descriptor.height);
+		let load_ops = (
+			descriptor.color_load_op,
+			descriptor.depth_load_op,
+			descriptor.stencil_load_op);
+		let (color, resolve) = Self::create_resolve(
+			device,
+			load_ops,
+			descriptor.width,
+			descriptor.height)?;
+
+		let multisampled = Self::create_multisampled(
+			device,
+			load_ops,
+			state.storage(),
+			descriptor.width,
+			descriptor.height)?;
+		state.mark_built();
+
+		Ok(Self {
+			state,
+			load_ops,
+			color,
+			resolve,
+			multisampled
+		})
+	}
+
+	/** Number of samples per pixel currently asked for, which is the one the
+	 * target will have by the next call to [`begin()`].
+	 *
+	 * [`begin()`]: Self::begin */
+	pub fn samples(&self) -> u32 {
+		self.state.storage().samples()
+	}
+
+	/** Size of the target, in pixels. */
+	pub fn size(&self) -> (u32, u32) {
+		self.state.size()
+	}
+
+	/** Changes the number of samples per pixel, clamping it to what the
+	 * context supports, and returns the number that will be used. The storage
+	 * is only created again by the next call to [`begin()`], and only if the
+	 * number actually changed.
+	 *
+	 * [`begin()`]: Self::begin */
+	pub fn set_samples(&mut self, device: &Device, samples: u32) -> u32 {
+		self.state.set_samples(samples, device.information().limits.max_samples)
+	}
+
+	/** Changes the size of the target. Like with [`set_samples()`], the
+	 * storage is only created again by the next call to [`begin()`].
+	 *
+	 * [`set_samples()`]: Self::set_samples
+	 * [`begin()`]: Self::begin */
+	pub fn resize(&mut self, width: u32, height: u32) {
+		self.state.set_size(width, height);
+	}
+
+	/** Gets the framebuffer drawings into this target go into, creating the
+	 * storage again first if the number of samples or the size changed.
+	 *
+	 * This must be called outside of a render pass. */
+	pub fn begin(&mut self, device: &Device) -> Result<&Framebuffer, MsaaTargetError> {
+		if self.state.needs_rebuild() {
+			self.rebuild(device)?;
+		}
+
+		Ok(self.multisampled.as_ref().unwrap_or(&self.resolve))
+	}
+
+	/** Resolves everything drawn into the framebuffer given by [`begin()`]
+	 * into the resolve texture, and gets it. When multisampling is off, this
+	 * does nothing but get the texture that got drawn into directly.
+	 *
+	 * This must be called outside of a render pass.
+	 *
+	 * [`begin()`]: Self::begin */
+	pub fn resolve(&self, device: &Device) -> Result<&Texture, MsaaTargetError> {
+		if let Some(multisampled) = &self.multisampled {
+			let (width, height) = self.state.size();
+			let rect = Viewport { x: 0, y: 0, width, height };
+			device.blit_framebuffer(
+				multisampled,
+				&self.resolve,
+				rect,
+				rect,
+				TextureFilter::Nearest)?;
+		}
+
+		Ok(&self.color)
+	}
+
+	/** Single-sampled framebuffer around the resolve texture, which holds
+	 * the resolved drawings after a call to [`resolve()`], and can be blitted
+	 * over to another framebuffer, such as the default one.
+	 *
+	 * [`resolve()`]: Self::resolve */
+	pub fn resolved(&self) -> &Framebuffer {
+		&self.resolve
+	}
+
+	/** Creates the storage asked for, replacing the current one. */
+	fn rebuild(&mut self, device: &Device) -> Result<(), MsaaTargetError> {
+		let (width, height) = self.state.size();
+		if self.state.needs_resolve_rebuild() {
+			let (color, resolve) = Self::create_resolve(
+				device,
+				self.load_ops,
+				width,
+				height)?;
+			self.color = color;
+			self.resolve = resolve;
+		}
+
+		self.multisampled = Self::create_multisampled(
+			device,
+			self.load_ops,
+			self.state.storage(),
+			width,
+			height)?;
+		log::debug!(target: "support::msaa",
+			"created a {}x{} target with {:?}",
+			width,
+			height,
+			self.state.storage());
+
+		self.state.mark_built();
+		Ok(())
+	}
+
+	/** Creates the multisampled framebuffer for the given storage layout, if
+	 * it needs one. */
+	fn create_multisampled(
+		device: &Device,
+		load_ops: (LoadOp<Color>, LoadOp<f32>, LoadOp<u8>),
+		storage: MsaaStorage,
+		width: u32,
+		height: u32)
+		-> Result<Option<Framebuffer>, MsaaTargetError> {
+
+		let samples = match storage {
+			MsaaStorage::Passthrough => return Ok(None),
+			MsaaStorage::Multisampled(samples) => samples
+		};
+
+		let (color_load_op, depth_load_op, stencil_load_op) = load_ops;
+		let framebuffer = device.create_multisampled_framebuffer(
+			&MultisampledFramebufferDescriptor {
+				width,
+				height,
+				samples,
+				color_format: TextureFormat::Rgba8Unorm,
+				depth_stencil: true,
+				color_load_op,
+				depth_load_op,
+				stencil_load_op
+			})?;
+		framebuffer.set_label("msaa target");
+
+		Ok(Some(framebuffer))
+	}
+
+	/** Creates the resolve texture and the single-sampled framebuffer
+	 * around it. */
+	fn create_resolve(
+		device: &Device,
+		load_ops: (LoadOp<Color>, LoadOp<f32>, LoadOp<u8>),
+		width: u32,
+		height: u32)
+		-> Result<(Texture, Framebuffer), MsaaTargetError> {
+
+		let texture = |format| device.create_texture(
+			&TextureDescriptor {
+				extent: TextureExtent::D2 { width, height },
+				format,
+				mip: Mipmap::None,
+				swizzle: None
+			});
+		let color = texture(TextureFormat::Rgba8Unorm)?;
+		let depth_stencil = texture(TextureFormat::Depth24Stencil8)?;
+
+		let (color_load_op, depth_load_op, stencil_load_op) = load_ops;
+		let framebuffer = device.create_framebuffer(
+			&FramebufferDescriptor {
+				color_attachments: &[FramebufferColorAttachmentDescriptor {
+					attachment: &color,
+					layer: 0,
+					mip_level: 0,
+					load_op: color_load_op
+				}],
+				depth_stencil_attachment: Some(FramebufferDepthStencilAttachmentDescriptor {
+					attachment: &depth_stencil,
+					layer: 0,
+					mip_level: 0,
+					depth_load_op,
+					stencil_load_op
+				})
+			})?;
+		framebuffer.set_label("msaa resolve target");
+
+		Ok((color, framebuffer))
+	}
+}
+
+/** Errors that may happen while creating the storage of an [`MsaaTarget`] or
+ * resolving it. */
+#[derive(Debug, thiserror::Error)]
+pub enum MsaaTargetError {
+	#[error("could not create the resolve texture: {0}")]
+	Texture(#[from] TextureError),
+	#[error("could not create or resolve the target framebuffer: {0}")]
+	Framebuffer(#[from] FramebufferError),
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn requested_samples_are_clamped() {
+		assert_eq!(clamp_samples(0, 8), 1);
+		assert_eq!(clamp_samples(1, 8), 1);
+		assert_eq!(clamp_samples(4, 8), 4);
+		assert_eq!(clamp_samples(16, 8), 8);
+		assert_eq!(clamp_samples(8, 0), 1);
+
+		let mut state = MsaaState::new(32, 4, 800, 600);
+		assert_eq!(state.storage(), MsaaStorage::Multisampled(4));
+		assert_eq!(state.set_samples(2, 4), 2);
+		assert_eq!(state.set_samples(8, 1), 1);
+		assert_eq!(state.storage(), MsaaStorage::Passthrough);
+	}
+
+	#[test]
+	fn storage_is_only_rebuilt_on_change() {
+		let mut state = MsaaState::new(4, 8, 800, 600);
+		assert!(state.needs_rebuild());
+		state.mark_built();
+		assert!(!state.needs_rebuild());
+
+		/* Asking for what's already there changes nothing. */
+		state.set_samples(4, 8);
+		state.set_size(800, 600);
+		assert!(!state.needs_rebuild());
+
+		/* Going back and forth before the next frame changes nothing either. */
+		state.set_samples(2, 8);
+		state.set_samples(4, 8);
+		assert!(!state.needs_rebuild());
+
+		/* Changing the number of samples keeps the resolve texture. */
+		state.set_samples(8, 8);
+		assert!(state.needs_rebuild());
+		assert!(!state.needs_resolve_rebuild());
+		state.mark_built();
+
+		/* But changing the size doesn't. */
+		state.set_size(1024, 768);
+		assert!(state.needs_rebuild());
+		assert!(state.needs_resolve_rebuild());
+		state.mark_built();
+		assert!(!state.needs_rebuild());
+	}
+
+	#[test]
+	fn single_sampled_storage_passes_through() {
+		for &samples in &[0, 1] {
+			let storage = MsaaStorage::for_samples(samples);
+			assert_eq!(storage, MsaaStorage::Passthrough);
+			assert_eq!(storage.samples(), 1);
+			assert!(!storage.resolves());
+		}
+		for &samples in &[2, 4, 8] {
+			let storage = MsaaStorage::for_samples(samples);
+			assert_eq!(storage, MsaaStorage::Multisampled(samples));
+			assert_eq!(storage.samples(), samples);
+			assert!(storage.resolves());
+		}
+	}
+}
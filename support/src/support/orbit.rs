@@ -0,0 +1,330 @@
+use crate::support::{Camera, Matrix4, Projection};
+use std::time::Duration;
+
+/** Parameters for how an [`OrbitCamera`] responds to its input. */
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct OrbitSettings {
+	/** Angle turned, in radians, for every unit the cursor travels while
+	 * rotating, in normalized device coordinates, where the window is two
+	 * units across. */
+	pub rotate_sensitivity: f32,
+	/** Distance the target moves, in multiples of the distance between the
+	 * camera and the target, for every unit the cursor travels while panning,
+	 * in normalized device coordinates. */
+	pub pan_sensitivity: f32,
+	/** Distance the camera moves away from the target for every line
+	 * scrolled. */
+	pub zoom_sensitivity: f32,
+	/** Lowest and highest angles of pitch, in radians. */
+	pub pitch_limits: [f32; 2],
+	/** Closest and farthest distances the camera may be from the target. */
+	pub distance_limits: [f32; 2],
+	/** Time it takes the camera to cover about two thirds of the way to where
+	 * its input puts it, or [`None`] for the camera to get there right away. */
+	pub smoothing: Option<Duration>,
+}
+impl Default for OrbitSettings {
+	fn default() -> Self {
+		Self {
+			rotate_sensitivity: std::f32::consts::PI,
+			pan_sensitivity: 0.5,
+			zoom_sensitivity: 1.0,
+			pitch_limits: [-std::f32::consts::FRAC_PI_2, std::f32::consts::FRAC_PI_2],
+			distance_limits: [2.0, 20.0],
+			smoothing: None
+		}
+	}
+}
+
+/** State of the input driving an [`OrbitCamera`] during a frame, which is
+ * usually gathered from the input state of the environment. */
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct OrbitInput {
+	/** Position of the cursor in normalized device coordinates, going from
+	 * minus one to one, left to right and bottom to top, if it is over the
+	 * window. */
+	pub cursor: Option<[f32; 2]>,
+	/** Whether the cursor is rotating the camera, usually while the left
+	 * mouse button is held down. */
+	pub rotating: bool,
+	/** Whether the cursor is panning the camera, usually while the middle
+	 * mouse button is held down. */
+	pub panning: bool,
+	/** Lines scrolled during the frame. */
+	pub zoom: f32,
+}
+
+/** Placement of an orbit camera around its target. */
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Orbit {
+	target: [f32; 3],
+	yaw: f32,
+	pitch: f32,
+	distance: f32,
+}
+impl Orbit {
+	/** The rotation of the camera, without its translation. */
+	fn rotation(&self) -> Matrix4 {
+		Matrix4::rotate(1.0, 0.0, 0.0, self.pitch)
+			* Matrix4::rotate(0.0, 1.0, 0.0, self.yaw)
+	}
+
+	/** Moves this placement the given fraction of the way to the other. */
+	fn approach(&mut self, other: &Self, factor: f32) {
+		let lerp = |a: f32, b: f32| a + (b - a) * factor;
+		for i in 0..3 {
+			self.target[i] = lerp(self.target[i], other.target[i]);
+		}
+		self.yaw = lerp(self.yaw, other.yaw);
+		self.pitch = lerp(self.pitch, other.pitch);
+		self.distance = lerp(self.distance, other.distance);
+	}
+}
+
+/** Controller for a camera that orbits around a target, such as the ones used
+ * to inspect a model, turning around it while the cursor is dragged, panning
+ * the target around, and moving closer and farther away with the scroll wheel.
+ *
+ * The controller gets driven by an [`OrbitInput`] every frame, through
+ * [`update()`], and produces either a view matrix or a whole [`Camera`]. Angles
+ * follow the conventions of [`Camera`], so that a yaw and a pitch of zero have
+ * the camera looking towards positive `z`.
+ *
+ * [`update()`]: Self::update */
+#[derive(Debug, Clone)]
+pub struct OrbitCamera {
+	/** How the camera responds to its input. */
+	pub settings: OrbitSettings,
+	/** Where the camera is. */
+	current: Orbit,
+	/** Where the input has put the camera, which it gets to over time, when
+	 * smoothing is enabled. */
+	goal: Orbit,
+	/** Position of the cursor during the last update, if it was over the
+	 * window. */
+	cursor: Option<[f32; 2]>,
+}
+impl OrbitCamera {
+	/** Creates a new controller orbiting around the given target, at the given
+	 * angles, in radians, and the given distance, with the default settings. */
+	pub fn new(target: [f32; 3], yaw: f32, pitch: f32, distance: f32) -> Self {
+		let orbit = Orbit { target, yaw, pitch, distance };
+		Self {
+			settings: Default::default(),
+			current: orbit,
+			goal: orbit,
+			cursor: None
+		}
+	}
+
+	/** Use the given settings for this controller. Limits in the settings get
+	 * applied with the next update. */
+	pub fn with_settings(mut self, settings: OrbitSettings) -> Self {
+		self.settings = settings;
+		self
+	}
+
+	/** Point the camera orbits around. */
+	pub fn target(&self) -> [f32; 3] {
+		self.current.target
+	}
+
+	/** Angle of yaw of the camera, in radians. */
+	pub fn yaw(&self) -> f32 {
+		self.current.yaw
+	}
+
+	/** Angle of pitch of the camera, in radians. */
+	pub fn pitch(&self) -> f32 {
+		self.current.pitch
+	}
+
+	/** Distance between the camera and its target. */
+	pub fn distance(&self) -> f32 {
+		self.current.distance
+	}
+
+	/** Turns the camera by the given angles, in radians. */
+	pub fn rotate(&mut self, yaw: f32, pitch: f32) {
+		self.goal.yaw += yaw;
+		self.goal.pitch += pitch;
+	}
+
+	/** Moves the target across the view of the camera, by the given distances
+	 * to the right and upwards, in multiples of the distance between the
+	 * camera and the target. */
+	pub fn pan(&mut self, right: f32, up: f32) {
+		let rotation = self.goal.rotation();
+		let m = rotation.as_row_major_array();
+		let scale = self.goal.distance;
+		for i in 0..3 {
+			self.goal.target[i] += (m[i] * right + m[4 + i] * up) * scale;
+		}
+	}
+
+	/** Moves the camera the given distance away from the target. */
+	pub fn zoom(&mut self, distance: f32) {
+		self.goal.distance += distance;
+	}
+
+	/** Updates the camera with the input of a frame that took the given time.
+	 *
+	 * Dragging the cursor to the right turns the camera to the right, and
+	 * dragging it up has the camera look further up, while panning moves the
+	 * scene along with the cursor. Scrolling up moves the camera away from the
+	 * target. */
+	pub fn update(&mut self, input: &OrbitInput, delta: Duration) {
+		if let (Some(last), Some(cursor)) = (self.cursor, input.cursor) {
+			let dx = cursor[0] - last[0];
+			let dy = cursor[1] - last[1];
+
+			if input.rotating {
+				let sensitivity = self.settings.rotate_sensitivity;
+				self.rotate(dx * sensitivity, -dy * sensitivity);
+			}
+			if input.panning {
+				let sensitivity = self.settings.pan_sensitivity;
+				self.pan(-dx * sensitivity, -dy * sensitivity);
+			}
+		}
+		self.cursor = input.cursor;
+		self.zoom(input.zoom * self.settings.zoom_sensitivity);
+
+		let [low, high] = self.settings.pitch_limits;
+		self.goal.pitch = self.goal.pitch.max(low).min(high);
+		let [near, far] = self.settings.distance_limits;
+		self.goal.distance = self.goal.distance.max(near).min(far);
+
+		let factor = match self.settings.smoothing {
+			Some(smoothing) if smoothing > Duration::from_secs(0) =>
+				1.0 - (-delta.as_secs_f32() / smoothing.as_secs_f32()).exp(),
+			_ => 1.0
+		};
+		if factor >= 1.0 {
+			self.current = self.goal;
+		} else {
+			self.current.approach(&self.goal, factor);
+		}
+	}
+
+	/** The transformation from world space to the view space of the camera. */
+	pub fn view_matrix(&self) -> Matrix4 {
+		let [x, y, z] = self.current.target;
+		Matrix4::translate(0.0, 0.0, self.current.distance)
+			* self.current.rotation()
+			* Matrix4::translate(-x, -y, -z)
+	}
+
+	/** Position of the camera, in world space. */
+	pub fn position(&self) -> [f32; 3] {
+		/* The camera sits behind the target, along the direction it looks in,
+		 * which is the third row of its rotation. */
+		let rotation = self.current.rotation();
+		let m = rotation.as_row_major_array();
+		let [x, y, z] = self.current.target;
+		let distance = self.current.distance;
+		[
+			x - m[8] * distance,
+			y - m[9] * distance,
+			z - m[10] * distance
+		]
+	}
+
+	/** A camera with the given projection, placed where this controller is. */
+	pub fn camera(&self, projection: Projection) -> Camera {
+		Camera {
+			projection,
+			position: self.position(),
+			yaw: self.current.yaw,
+			pitch: self.current.pitch
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn assert_close(a: Matrix4, b: Matrix4) {
+		let pairs = a.as_row_major_array().iter().zip(b.as_row_major_array());
+		for (a, b) in pairs {
+			assert!((a - b).abs() < 1e-5, "{:?} != {:?}", a, b);
+		}
+	}
+
+	fn frame(cursor: [f32; 2], rotating: bool, panning: bool) -> OrbitInput {
+		OrbitInput { cursor: Some(cursor), rotating, panning, zoom: 0.0 }
+	}
+
+	#[test]
+	fn cameras_match_the_view() {
+		let projection = Projection::Perspective {
+			field_of_view: std::f32::consts::FRAC_PI_2,
+			near: 1.0,
+			far: 100.0
+		};
+		let orbit = OrbitCamera::new([1.0, 2.0, 3.0], 0.7, -0.3, 5.0);
+		let camera = orbit.camera(projection);
+
+		assert_close(
+			camera.matrix(1.0),
+			Matrix4::rectilinear_projection(std::f32::consts::FRAC_PI_2, 1.0, 1.0, 100.0)
+				* orbit.view_matrix());
+
+		/* With no rotation, the camera sits behind the target along z. */
+		let orbit = OrbitCamera::new([1.0, 2.0, 3.0], 0.0, 0.0, 5.0);
+		assert_eq!(orbit.position(), [1.0, 2.0, -2.0]);
+	}
+
+	#[test]
+	fn dragging_rotates_within_limits() {
+		let mut orbit = OrbitCamera::new([0.0; 3], 0.0, 0.0, 5.0);
+		let frame_time = Duration::from_millis(16);
+
+		/* The first position of the cursor only gets recorded. */
+		orbit.update(&frame([0.0, 0.0], true, false), frame_time);
+		orbit.update(&frame([0.5, 0.0], true, false), frame_time);
+		assert!((orbit.yaw() - std::f32::consts::FRAC_PI_2).abs() < 1e-5);
+
+		/* Moving without dragging does nothing. */
+		orbit.update(&frame([0.0, 0.0], false, false), frame_time);
+		assert!((orbit.yaw() - std::f32::consts::FRAC_PI_2).abs() < 1e-5);
+
+		/* Dragging all the way down stops at the limit of pitch. */
+		orbit.update(&frame([0.0, -2.0], true, false), frame_time);
+		assert_eq!(orbit.pitch(), std::f32::consts::FRAC_PI_2);
+
+		orbit.update(&OrbitInput { zoom: 100.0, ..Default::default() }, frame_time);
+		assert_eq!(orbit.distance(), 20.0);
+	}
+
+	#[test]
+	fn panning_moves_the_target_across_the_view() {
+		let mut orbit = OrbitCamera::new([0.0; 3], 0.0, 0.0, 4.0);
+		let frame_time = Duration::from_millis(16);
+
+		orbit.update(&frame([0.0, 0.0], false, true), frame_time);
+		orbit.update(&frame([0.5, 0.0], false, true), frame_time);
+
+		/* The target moves against the cursor, so the scene follows it. */
+		let [x, y, z] = orbit.target();
+		assert!((x + 1.0).abs() < 1e-5 && y.abs() < 1e-5 && z.abs() < 1e-5, "{:?}", [x, y, z]);
+	}
+
+	#[test]
+	fn smoothing_eases_into_the_goal() {
+		let mut orbit = OrbitCamera::new([0.0; 3], 0.0, 0.0, 5.0)
+			.with_settings(OrbitSettings {
+				smoothing: Some(Duration::from_millis(100)),
+				..Default::default()
+			});
+
+		orbit.update(&OrbitInput { zoom: 5.0, ..Default::default() }, Duration::from_millis(100));
+		let distance = orbit.distance();
+		assert!(distance > 5.0 && distance < 10.0, "{}", distance);
+		assert!((distance - (10.0 - 5.0 / std::f32::consts::E)).abs() < 1e-4);
+
+		orbit.update(&Default::default(), Duration::from_secs(10));
+		assert!((orbit.distance() - 10.0).abs() < 1e-4);
+	}
+}
@@ -0,0 +1,266 @@
+use crate::support::Matrix4;
+
+/** Rotation in three-dimensional space, as a unit quaternion.
+ *
+ * Unlike chains of matrices, rotations in this form can be composed any
+ * number of times and brought back to unit length without drifting away from
+ * a pure rotation, and can be smoothly interpolated with [`slerp()`].
+ *
+ * Rotations follow the same convention as the ones created by
+ * [`Matrix4::rotate()`], and are stored as `[x, y, z, w]`.
+ *
+ * [`slerp()`]: Quaternion::slerp
+ * [`Matrix4::rotate()`]: crate::Matrix4::rotate */
+#[repr(transparent)]
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, bytemuck::Pod, bytemuck::Zeroable, serde::Serialize, serde::Deserialize)]
+pub struct Quaternion([f32; 4]);
+
+impl Quaternion {
+	/** The rotation that leaves everything where it is. */
+	pub const IDENTITY: Self = Self([0.0, 0.0, 0.0, 1.0]);
+
+	/** Creates a new quaternion with the given components. This does not
+	 * bring it to unit length. */
+	pub const fn new(x: f32, y: f32, z: f32, w: f32) -> Self {
+		Self([x, y, z, w])
+	}
+
+	/** Get the contents of this quaternion as an `[x, y, z, w]` array. */
+	pub fn as_array(&self) -> &[f32; 4] {
+		&self.0
+	}
+
+	/** Creates a new rotation around the given axis, by the given angle, in
+	 * radians, matching what [`Matrix4::rotate()`] creates for them.
+	 *
+	 * [`Matrix4::rotate()`]: crate::Matrix4::rotate */
+	pub fn from_axis_angle(x: f32, y: f32, z: f32, angle: f32) -> Self {
+		let len = f32::sqrt(x * x + y * y + z * z);
+		let (sin, cos) = f32::sin_cos(-angle / 2.0);
+
+		Self([x / len * sin, y / len * sin, z / len * sin, cos])
+	}
+
+	/** Creates a new rotation from the given Euler angles, in radians, which
+	 * rotates by the yaw around the `y` axis first, then by the pitch around
+	 * the `x` axis, and then by the roll around the `z` axis. This is the same
+	 * order [`Camera`] applies its angles in.
+	 *
+	 * [`Camera`]: crate::Camera */
+	pub fn from_euler(yaw: f32, pitch: f32, roll: f32) -> Self {
+		Self::from_axis_angle(0.0, 0.0, 1.0, roll)
+			* Self::from_axis_angle(1.0, 0.0, 0.0, pitch)
+			* Self::from_axis_angle(0.0, 1.0, 0.0, yaw)
+	}
+
+	/** Dot product of this quaternion and the given one. */
+	pub fn dot(&self, rhs: Self) -> f32 {
+		self.0.iter().zip(&rhs.0).map(|(a, b)| a * b).sum()
+	}
+
+	/** The rotation undoing this one. */
+	pub fn conjugate(&self) -> Self {
+		let [x, y, z, w] = self.0;
+		Self([-x, -y, -z, w])
+	}
+
+	/** Brings this quaternion back to unit length, or returns `None` if it has
+	 * no length, and thus describes no rotation at all. */
+	pub fn normalize(&self) -> Option<Self> {
+		let len = f32::sqrt(self.dot(*self));
+		if len == 0.0 {
+			None
+		} else {
+			let [x, y, z, w] = self.0;
+			Some(Self([x / len, y / len, z / len, w / len]))
+		}
+	}
+
+	/** Spherical linear interpolation between this rotation and the given
+	 * one, along the shortest arc between them, which turns at a constant
+	 * angular velocity as the factor goes from zero to one. */
+	pub fn slerp(&self, other: Self, s: f32) -> Self {
+		/* Both a quaternion and its negation describe the same rotation, so
+		 * pick whichever one is closest to this one. */
+		let dot = self.dot(other);
+		let (other, dot) = if dot < 0.0 {
+			(Self(other.0.map(|c| -c)), -dot)
+		} else {
+			(other, dot)
+		};
+
+		/* Rotations this close together make the angle between them too
+		 * imprecise to divide by, and are fine to interpolate linearly. */
+		let (a, b) = if dot > 0.9995 {
+			(1.0 - s, s)
+		} else {
+			let angle = f32::acos(dot);
+			let sin = f32::sin(angle);
+
+			(f32::sin((1.0 - s) * angle) / sin, f32::sin(s * angle) / sin)
+		};
+
+		let mut result = [0.0; 4];
+		for (i, result) in result.iter_mut().enumerate() {
+			*result = self.0[i] * a + other.0[i] * b;
+		}
+		Self(result).normalize().unwrap_or(Self::IDENTITY)
+	}
+
+	/** Normalized linear interpolation between this rotation and the given
+	 * one, along the shortest arc between them. This doesn't turn at a
+	 * constant angular velocity, but agrees with [`slerp()`] at both ends and
+	 * at the midpoint, and is cheaper to compute.
+	 *
+	 * [`slerp()`]: Self::slerp */
+	pub fn nlerp(&self, other: Self, s: f32) -> Self {
+		let sign = if self.dot(other) < 0.0 { -1.0 } else { 1.0 };
+
+		let mut result = [0.0; 4];
+		for (i, result) in result.iter_mut().enumerate() {
+			*result = self.0[i] + (other.0[i] * sign - self.0[i]) * s;
+		}
+		Self(result).normalize().unwrap_or(Self::IDENTITY)
+	}
+
+	/** The matrix applying this rotation. */
+	pub fn matrix(&self) -> Matrix4 {
+		let [x, y, z, w] = self.0;
+
+		Matrix4::from_row_major_array([
+			1.0 - 2.0 * (y * y + z * z),
+			2.0 * (x * y - z * w),
+			2.0 * (x * z + y * w),
+			0.0,
+			2.0 * (x * y + z * w),
+			1.0 - 2.0 * (x * x + z * z),
+			2.0 * (y * z - x * w),
+			0.0,
+			2.0 * (x * z - y * w),
+			2.0 * (y * z + x * w),
+			1.0 - 2.0 * (x * x + y * y),
+			0.0,
+			0.0, 0.0, 0.0, 1.0
+		])
+	}
+}
+impl Default for Quaternion {
+	fn default() -> Self {
+		Self::IDENTITY
+	}
+}
+
+/** Composition of two rotations, which rotates by the right hand side first,
+ * and then by the left hand side, like the product of their matrices. */
+impl std::ops::Mul for Quaternion {
+	type Output = Self;
+
+	fn mul(self, rhs: Self) -> Self::Output {
+		let [ax, ay, az, aw] = self.0;
+		let [bx, by, bz, bw] = rhs.0;
+
+		Self([
+			aw * bx + ax * bw + ay * bz - az * by,
+			aw * by - ax * bz + ay * bw + az * bx,
+			aw * bz + ax * by - ay * bx + az * bw,
+			aw * bw - ax * bx - ay * by - az * bz,
+		])
+	}
+}
+
+/** Assigning composition of two rotations. */
+impl std::ops::MulAssign for Quaternion {
+	fn mul_assign(&mut self, rhs: Self) {
+		*self = *self * rhs;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+
+	fn assert_close(a: &Matrix4, b: &Matrix4) {
+		let pairs = a.as_row_major_array().iter().zip(b.as_row_major_array());
+		for (a, b) in pairs {
+			assert!((a - b).abs() < 1e-5, "{:?} != {:?}", a, b);
+		}
+	}
+
+	/** Angle between two rotations, in radians. */
+	fn angle(a: Quaternion, b: Quaternion) -> f32 {
+		2.0 * f32::acos(a.dot(b).abs().min(1.0))
+	}
+
+	#[test]
+	fn single_axis_rotations_match_matrices() {
+		let axes = [(1.0, 0.0, 0.0), (0.0, 1.0, 0.0), (0.0, 0.0, 1.0)];
+		for &(x, y, z) in &axes {
+			for &angle in &[FRAC_PI_4, FRAC_PI_2, -1.0, PI] {
+				assert_close(
+					&Quaternion::from_axis_angle(x, y, z, angle).matrix(),
+					&Matrix4::rotate(x, y, z, angle));
+			}
+		}
+		assert_close(&Quaternion::IDENTITY.matrix(), &Matrix4::identity());
+	}
+
+	#[test]
+	fn products_compose_like_matrices() {
+		let a = Quaternion::from_axis_angle(1.0, 2.0, 3.0, 1.0);
+		let b = Quaternion::from_axis_angle(-1.0, 0.0, 1.0, 2.0);
+
+		assert_close(&(a * b).matrix(), &(a.matrix() * b.matrix()));
+		assert_close(&(a * a.conjugate()).matrix(), &Matrix4::identity());
+		assert_close(
+			&Quaternion::from_euler(0.5, -0.25, 1.0).matrix(),
+			&(Matrix4::rotate(0.0, 0.0, 1.0, 1.0)
+				* Matrix4::rotate(1.0, 0.0, 0.0, -0.25)
+				* Matrix4::rotate(0.0, 1.0, 0.0, 0.5)));
+	}
+
+	#[test]
+	fn slerp_turns_at_a_constant_rate() {
+		let a = Quaternion::from_axis_angle(0.0, 1.0, 0.0, 0.0);
+		let b = Quaternion::from_axis_angle(0.0, 1.0, 0.0, 2.0);
+
+		for &s in &[0.0, 0.1, 0.25, 0.5, 0.9, 1.0] {
+			let rotation = a.slerp(b, s);
+			assert!((angle(a, rotation) - 2.0 * s).abs() < 1e-3);
+			assert!((angle(rotation, b) - 2.0 * (1.0 - s)).abs() < 1e-3);
+		}
+
+		/* The negation of the target is the same rotation, so the path taken
+		 * must be the same one. */
+		let negated = Quaternion(b.0.map(|c| -c));
+		assert_close(&a.slerp(negated, 0.5).matrix(), &a.slerp(b, 0.5).matrix());
+
+		/* Nearly equal rotations still interpolate to unit quaternions. */
+		let c = Quaternion::from_axis_angle(0.0, 1.0, 0.0, 1e-4);
+		let rotation = a.slerp(c, 0.5);
+		assert!((rotation.dot(rotation) - 1.0).abs() < 1e-6);
+	}
+
+	#[test]
+	fn nlerp_agrees_with_slerp_at_the_midpoint() {
+		let a = Quaternion::from_axis_angle(0.0, 1.0, 0.0, 0.0);
+		let b = Quaternion::from_axis_angle(0.0, 1.0, 0.0, FRAC_PI_2);
+
+		assert!(angle(a.nlerp(b, 0.0), a) < 1e-3);
+		assert!(angle(a.nlerp(b, 1.0), b) < 1e-3);
+		assert!(angle(a.nlerp(b, 0.5), a.slerp(b, 0.5)) < 1e-3);
+
+		/* The negation of a rotation is the same rotation, and the path
+		 * between them stays put. */
+		let negated = Quaternion::new(-0.0, -0.0, -0.0, -1.0);
+		assert!(angle(a.nlerp(negated, 0.5), a) < 1e-3);
+	}
+
+	#[test]
+	fn degenerate_quaternions_do_not_normalize() {
+		assert_eq!(Quaternion::new(0.0, 0.0, 0.0, 0.0).normalize(), None);
+		assert_eq!(
+			Quaternion::new(0.0, 0.0, 0.0, 2.0).normalize(),
+			Some(Quaternion::IDENTITY));
+	}
+}
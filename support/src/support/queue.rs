@@ -0,0 +1,260 @@
+use gavle::*;
+use std::ops::Range;
+use std::time::Duration;
+use log::warn;
+
+/** Default number of overlapping draws without a depth buffer that the queue
+ * tolerates in [`QueueMode::Unsorted3D`] before warning about it.
+ *
+ * [`QueueMode::Unsorted3D`]: QueueMode::Unsorted3D */
+pub const DEFAULT_OVERLAP_WARNING: usize = 2;
+
+/** The order in which a [`RenderQueue`] executes the draws submitted to it.
+ *
+ * [`RenderQueue`]: RenderQueue */
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum QueueMode {
+	/** Draws are executed in the order they were submitted in, and their `z`
+	 * values are ignored.
+	 *
+	 * This is meant for scenes that rely on the depth buffer to resolve which
+	 * surfaces end up in front. Overlapping draws using pipelines that have no
+	 * depth-stencil state will come out in submission order, which is rarely
+	 * what was intended, so the queue warns when too many of them show up in
+	 * a single execution. */
+	Unsorted3D,
+	/** Draws are executed from back to front, from the largest `z` value to
+	 * the smallest, so that draws closer to the viewer are painted over the
+	 * ones behind them.
+	 *
+	 * This is meant for user interfaces and other 2D scenes which are drawn
+	 * without a depth buffer. The sort is stable, so draws with equal `z`
+	 * values are executed in the order they were submitted in. */
+	Layer2D,
+}
+impl Default for QueueMode {
+	fn default() -> Self {
+		Self::Unsorted3D
+	}
+}
+
+/** Screen-space rectangle covered by a draw, in normalized device coordinates.
+ *
+ * These bounds are only used to estimate which draws overlap each other, so
+ * they don't need to be tight, so long as they are conservative. */
+#[derive(Debug, Copy, Clone, PartialEq, PartialOrd)]
+pub struct DrawBounds {
+	/** Lower left corner of the rectangle. */
+	pub min: [f32; 2],
+	/** Upper right corner of the rectangle. */
+	pub max: [f32; 2],
+}
+impl DrawBounds {
+	/** Whether this rectangle shares any area with the given one. */
+	pub fn overlaps(&self, other: &Self) -> bool {
+		self.min[0] < other.max[0] && other.min[0] < self.max[0]
+			&& self.min[1] < other.max[1] && other.min[1] < self.max[1]
+	}
+}
+
+/** A single indexed draw submitted to a [`RenderQueue`].
+ *
+ * [`RenderQueue`]: RenderQueue */
+pub struct DrawItem<'a> {
+	/** Pipeline the draw is performed with. */
+	pub pipeline: &'a RenderPipeline,
+	/** Uniform group bound for the draw. If this is `None`, the group bound by
+	 * the previous draw in the pass, if any, stays bound. */
+	pub bind: Option<&'a UniformGroup>,
+	/** Vertex buffer the draw reads from. */
+	pub vertices: &'a VertexBuffer,
+	/** Index buffer the draw reads from. */
+	pub indices: &'a IndexBuffer,
+	/** Range of indices to be drawn. */
+	pub elements: Range<u32>,
+	/** Number of instances to be drawn. */
+	pub instances: u32,
+	/** Distance of the draw from the viewer. Draws with larger values are
+	 * behind the ones with smaller values. Only used in [`QueueMode::Layer2D`].
+	 *
+	 * [`QueueMode::Layer2D`]: QueueMode::Layer2D */
+	pub z: f32,
+	/** Area of the screen covered by the draw, if known. Draws with unknown
+	 * bounds are assumed to overlap every other draw. */
+	pub bounds: Option<DrawBounds>,
+}
+
+/** Collects draws and executes them in a render pass, in the order given by
+ * its [mode].
+ *
+ * [mode]: QueueMode */
+pub struct RenderQueue<'a> {
+	/** Order in which draws are executed. */
+	mode: QueueMode,
+	/** Draws waiting to be executed. */
+	items: Vec<DrawItem<'a>>,
+	/** Number of overlapping depthless draws tolerated before warning. */
+	overlap_warning: usize,
+}
+impl<'a> RenderQueue<'a> {
+	/** Creates a new, empty queue, executing draws in the given mode. */
+	pub fn new(mode: QueueMode) -> Self {
+		Self {
+			mode,
+			items: Vec::new(),
+			overlap_warning: DEFAULT_OVERLAP_WARNING
+		}
+	}
+
+	/** The order in which this queue executes draws. */
+	pub fn mode(&self) -> QueueMode {
+		self.mode
+	}
+
+	/** Changes the number of overlapping draws with pipelines lacking a
+	 * depth-stencil state that [`QueueMode::Unsorted3D`] tolerates in a single
+	 * execution, before warning about it.
+	 *
+	 * [`QueueMode::Unsorted3D`]: QueueMode::Unsorted3D */
+	pub fn set_overlap_warning(&mut self, draws: usize) {
+		self.overlap_warning = draws;
+	}
+
+	/** Queues a draw up to be executed. */
+	pub fn submit(&mut self, item: DrawItem<'a>) {
+		self.items.push(item);
+	}
+
+	/** Number of draws currently in the queue. */
+	pub fn len(&self) -> usize {
+		self.items.len()
+	}
+
+	/** Whether there are no draws in the queue. */
+	pub fn is_empty(&self) -> bool {
+		self.items.is_empty()
+	}
+
+	/** Executes all of the draws in the queue in the given render pass, which
+	 * leaves the queue empty. Draws whose ranges don't fit their buffers get
+	 * skipped with a warning, rather than keeping the rest from being drawn. */
+	pub fn execute(&mut self, pass: &mut RenderPass<'a>) {
+		match self.mode {
+			QueueMode::Unsorted3D => {
+				let depthless = self.items.iter()
+					.filter(|item| item.pipeline.depth_stencil().is_none())
+					.map(|item| item.bounds);
+				let overlapping = count_overlapping(depthless);
+				if overlapping > self.overlap_warning {
+					gavle::rate_limited!(1, Duration::from_secs(5),
+						warn!(target: "support::queue",
+						"{} overlapping draws use pipelines without a depth \
+						buffer in an unsorted queue, so they will be drawn in \
+						submission order. Consider using QueueMode::Layer2D",
+						overlapping));
+				}
+			},
+			QueueMode::Layer2D =>
+				sort_back_to_front(&mut self.items, |item| item.z),
+		}
+
+		for item in self.items.drain(..) {
+			pass.set_pipeline(item.pipeline);
+			if let Some(bind) = item.bind {
+				pass.set_bind_group(bind);
+			}
+			pass.set_vertex_buffer(item.vertices);
+			pass.set_index_buffer(item.indices);
+			if let Err(what) = pass.draw_indexed(item.elements, item.instances) {
+				gavle::rate_limited!(warn!(target: "support::queue",
+					"skipped a queued draw: {}", what));
+			}
+		}
+	}
+}
+
+/** Stably sorts the given items from back to front, largest `z` first.
+ *
+ * NaN layers go behind everything else, and tie with each other, so that they
+ * don't leave the order of the items around them up to the sorting algorithm. */
+fn sort_back_to_front<T>(items: &mut [T], z: impl Fn(&T) -> f32) {
+	let order = |a: f32, b: f32| a.partial_cmp(&b)
+		.unwrap_or_else(|| a.is_nan().cmp(&b.is_nan()));
+	items.sort_by(|a, b| order(z(b), z(a)));
+}
+
+/** Counts how many of the draws with the given bounds overlap at least one
+ * other draw. Draws with unknown bounds overlap everything. */
+fn count_overlapping(bounds: impl Iterator<Item = Option<DrawBounds>>) -> usize {
+	let bounds = bounds.collect::<Vec<_>>();
+	(0..bounds.len())
+		.filter(|&i| (0..bounds.len())
+			.filter(|&j| j != i)
+			.any(|j| match (&bounds[i], &bounds[j]) {
+				(Some(a), Some(b)) => a.overlaps(b),
+				_ => true
+			}))
+		.count()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn layers_sort_stably_back_to_front() {
+		let mut items = vec![
+			(0.0, "front"),
+			(2.0, "back a"),
+			(1.0, "middle a"),
+			(2.0, "back b"),
+			(1.0, "middle b"),
+			(2.0, "back c"),
+		];
+		sort_back_to_front(&mut items, |item| item.0);
+
+		let order = items.iter().map(|item| item.1).collect::<Vec<_>>();
+		assert_eq!(order, vec![
+			"back a", "back b", "back c",
+			"middle a", "middle b",
+			"front"
+		]);
+
+		let mut items = vec![
+			(0.0, "front"),
+			(f32::NAN, "nan a"),
+			(1.0, "back"),
+			(-f32::NAN, "nan b"),
+		];
+		sort_back_to_front(&mut items, |item| item.0);
+
+		let order = items.iter().map(|item| item.1).collect::<Vec<_>>();
+		assert_eq!(order, vec!["nan a", "nan b", "back", "front"]);
+	}
+
+	#[test]
+	fn counts_overlapping_draws() {
+		let rect = |x: f32, y: f32| Some(DrawBounds {
+			min: [x, y],
+			max: [x + 1.0, y + 1.0]
+		});
+
+		assert_eq!(count_overlapping(vec![
+			rect(0.0, 0.0),
+			rect(0.5, 0.5),
+			rect(2.0, 2.0),
+		].into_iter()), 2);
+
+		/* Touching edges don't count as overlapping. */
+		assert_eq!(count_overlapping(vec![
+			rect(0.0, 0.0),
+			rect(1.0, 0.0),
+		].into_iter()), 0);
+
+		assert_eq!(count_overlapping(vec![
+			rect(0.0, 0.0),
+			rect(4.0, 4.0),
+			None,
+		].into_iter()), 3);
+	}
+}
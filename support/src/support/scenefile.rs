@@ -0,0 +1,1207 @@
+use gavle::*;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::fmt::{Display, Formatter};
+use crate::support::{Vertex, Mesh, Matrix4, RenderQueue, DrawItem};
+
+/** Version of the scene file schema understood by this loader. Files of any
+ * other version are rejected as a whole, rather than being partially loaded. */
+pub const SCENE_FILE_VERSION: u32 = 1;
+
+/** Name of the uniform block every object binds its [`ObjectUniforms`] to.
+ *
+ * [`ObjectUniforms`]: ObjectUniforms */
+pub const SCENE_OBJECT_BLOCK: &str = "rc_object";
+
+/** Description of a scene, listing the meshes, materials and objects it is
+ * made of, usually read from a JSON file with [`SceneFile::from_json()`].
+ *
+ * Items refer to each other by name. Objects are made of a mesh and of a
+ * material, and materials are rendered with a shader the application provides
+ * under the name the material gives, which is how scenes get built without any
+ * code having to change.
+ *
+ * [`SceneFile::from_json()`]: SceneFile::from_json */
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "PascalCase")]
+pub struct SceneFile {
+	/** Version of the schema this file was written for, which must be
+	 * [`SCENE_FILE_VERSION`].
+	 *
+	 * [`SCENE_FILE_VERSION`]: SCENE_FILE_VERSION */
+	pub version: u32,
+	/** Meshes in the scene. */
+	#[serde(default)]
+	pub meshes: Vec<MeshEntry>,
+	/** Materials in the scene. */
+	#[serde(default)]
+	pub materials: Vec<MaterialEntry>,
+	/** Objects in the scene, drawn in the order they are listed in. */
+	#[serde(default)]
+	pub objects: Vec<ObjectEntry>,
+}
+
+/** Mesh in a scene file. */
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "PascalCase")]
+pub struct MeshEntry {
+	/** Name objects refer to this mesh by. */
+	pub name: String,
+	/** Where the geometry of the mesh comes from. */
+	pub source: MeshSource,
+}
+
+/** Sources of geometry for the meshes in a scene file. */
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields, tag = "Type")]
+pub enum MeshSource {
+	/** A Wavefront OBJ model, read through the [`SceneAssets`] of the scene.
+	 *
+	 * [`SceneAssets`]: SceneAssets */
+	#[serde(rename_all = "PascalCase")]
+	Obj {
+		/** Path of the model. */
+		path: String,
+	},
+	/** A sphere centered on the origin, with its poles on the `y` axis. */
+	#[serde(rename_all = "PascalCase")]
+	Sphere {
+		/** Radius of the sphere. */
+		radius: f32,
+		/** Number of rings of faces between the poles. */
+		rings: u16,
+		/** Number of faces around each ring. */
+		segments: u16,
+	},
+	/** A rectangle centered on the origin, on the `xz` plane, facing up. */
+	#[serde(rename_all = "PascalCase")]
+	Quad {
+		/** Size of the rectangle along the `x` axis. */
+		width: f32,
+		/** Size of the rectangle along the `z` axis. */
+		depth: f32,
+	},
+}
+
+/** Material in a scene file. */
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "PascalCase")]
+pub struct MaterialEntry {
+	/** Name objects refer to this material by. */
+	pub name: String,
+	/** Name of the shader the application provides for this material. */
+	pub shader: String,
+	/** Textures bound by this material. */
+	#[serde(default)]
+	pub textures: Vec<TextureEntry>,
+	/** Uniform block filled in with default values, if any. */
+	#[serde(default)]
+	pub uniforms: Option<UniformsEntry>,
+}
+
+/** Texture in a material of a scene file. */
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "PascalCase")]
+pub struct TextureEntry {
+	/** Name of the sampler the texture is bound to. */
+	pub binding: String,
+	/** Path of the image, read through the [`SceneAssets`] of the scene.
+	 *
+	 * [`SceneAssets`]: SceneAssets */
+	pub path: String,
+}
+
+/** Uniform block of a material in a scene file. */
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "PascalCase")]
+pub struct UniformsEntry {
+	/** Name of the uniform block the values are bound to. */
+	pub block: String,
+	/** Values of the members of the block, in the order they are declared
+	 * in, laid out following the `std140` rules. */
+	pub values: Vec<UniformDefault>,
+}
+
+/** Default value of a member of a uniform block. */
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "PascalCase")]
+pub struct UniformDefault {
+	/** Name of the member, for diagnostics. */
+	pub name: String,
+	/** Components of the value, of which there must be between one and four,
+	 * for `float` through `vec4` members. */
+	pub value: Vec<f32>,
+}
+
+/** Object in a scene file. */
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "PascalCase")]
+pub struct ObjectEntry {
+	/** Name of the object, for diagnostics. */
+	pub name: String,
+	/** Name of the mesh of the object. */
+	pub mesh: String,
+	/** Name of the material of the object. */
+	pub material: String,
+	/** Placement of the object in the world. */
+	#[serde(default)]
+	pub transform: Transform,
+}
+
+/** Placement of an object in the world, which is scaled first, then rotated,
+ * then translated. */
+#[derive(Debug, Copy, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "PascalCase", default)]
+pub struct Transform {
+	/** Offset of the object along each of the axes. */
+	pub translation: [f32; 3],
+	/** Axis the object is rotated around, followed by the angle it is rotated
+	 * by, in radians. */
+	pub rotation: [f32; 4],
+	/** Scale of the object along each of the axes. */
+	pub scale: [f32; 3],
+}
+impl Transform {
+	/** Matrix applying this transformation. */
+	pub fn matrix(&self) -> Matrix4 {
+		let [x, y, z, angle] = self.rotation;
+		let rotation = if angle == 0.0 {
+			Matrix4::identity()
+		} else {
+			Matrix4::rotate(x, y, z, angle)
+		};
+
+		Matrix4::translate(
+			self.translation[0],
+			self.translation[1],
+			self.translation[2])
+			* rotation
+			* Matrix4::scale(self.scale[0], self.scale[1], self.scale[2])
+	}
+}
+impl Default for Transform {
+	fn default() -> Self {
+		Self {
+			translation: [0.0; 3],
+			rotation: [0.0, 1.0, 0.0, 0.0],
+			scale: [1.0; 3]
+		}
+	}
+}
+
+impl SceneFile {
+	/** Reads a scene file from the given JSON text.
+	 *
+	 * # Errors
+	 * This function fails if the text doesn't follow the schema, or if it was
+	 * written for another version of it. Problems with individual items are
+	 * only found once the scene gets loaded. */
+	pub fn from_json(text: &str) -> Result<Self, SceneFileError> {
+		/* Look at the version before anything else, so that files written for
+		 * other versions get reported as such, rather than as malformed. */
+		#[derive(serde::Deserialize)]
+		#[serde(rename_all = "PascalCase")]
+		struct Versioned {
+			version: u32,
+		}
+		let Versioned { version } = serde_json::from_str(text)?;
+		if version != SCENE_FILE_VERSION {
+			return Err(SceneFileError::UnsupportedVersion {
+				found: version,
+				supported: SCENE_FILE_VERSION
+			})
+		}
+
+		Ok(serde_json::from_str(text)?)
+	}
+
+	/** Works out which items of this scene can be loaded, in the order they
+	 * have to be loaded in, given the names of the shaders the application
+	 * provides, along with the problems keeping the others from being loaded.
+	 *
+	 * Items that can't be loaded are left out, along with everything that
+	 * depends on them, so materials get resolved before the objects using
+	 * them. Only the first of the items of a kind sharing a name is kept. */
+	pub fn plan<F>(&self, has_shader: F) -> (ScenePlan, Vec<SceneIssue>)
+		where F: Fn(&str) -> bool {
+
+		let mut issues = Vec::new();
+
+		let mut names = HashSet::new();
+		let mut meshes = Vec::new();
+		for (index, mesh) in self.meshes.iter().enumerate() {
+			if !names.insert(mesh.name.as_str()) {
+				issues.push(SceneIssue::DuplicateName {
+					kind: SceneItem::Mesh,
+					name: mesh.name.clone()
+				});
+				continue
+			}
+			meshes.push(index);
+		}
+
+		let mut names = HashSet::new();
+		let mut materials = Vec::new();
+		for (index, material) in self.materials.iter().enumerate() {
+			if !names.insert(material.name.as_str()) {
+				issues.push(SceneIssue::DuplicateName {
+					kind: SceneItem::Material,
+					name: material.name.clone()
+				});
+				continue
+			}
+			if !has_shader(&material.shader) {
+				issues.push(SceneIssue::UnknownShader {
+					material: material.name.clone(),
+					shader: material.shader.clone()
+				});
+				continue
+			}
+			let invalid = material.uniforms.iter()
+				.flat_map(|uniforms| uniforms.values.iter())
+				.find(|value| value.value.is_empty() || value.value.len() > 4);
+			if let Some(invalid) = invalid {
+				issues.push(SceneIssue::InvalidUniform {
+					material: material.name.clone(),
+					uniform: invalid.name.clone(),
+					components: invalid.value.len()
+				});
+				continue
+			}
+			materials.push(index);
+		}
+
+		let mesh_names = meshes.iter()
+			.map(|index| self.meshes[*index].name.as_str())
+			.collect::<HashSet<_>>();
+		let material_names = materials.iter()
+			.map(|index| self.materials[*index].name.as_str())
+			.collect::<HashSet<_>>();
+		let mut objects = Vec::new();
+		for (index, object) in self.objects.iter().enumerate() {
+			if !mesh_names.contains(object.mesh.as_str()) {
+				issues.push(SceneIssue::UnknownMesh {
+					object: object.name.clone(),
+					mesh: object.mesh.clone()
+				});
+				continue
+			}
+			if !material_names.contains(object.material.as_str()) {
+				issues.push(SceneIssue::UnknownMaterial {
+					object: object.name.clone(),
+					material: object.material.clone()
+				});
+				continue
+			}
+			objects.push(index);
+		}
+
+		(ScenePlan { meshes, materials, objects }, issues)
+	}
+
+	/** Names of the items of this scene that differ from those in the given
+	 * newer version of it, which have to be loaded again when moving over to
+	 * it. Items that are new count as changed, and objects whose mesh or
+	 * material changed count as changed themselves. */
+	pub fn changes(&self, newer: &SceneFile) -> SceneChanges {
+		fn changed<'a, T, F>(old: &'a [T], new: &'a [T], name: F) -> HashSet<String>
+			where T: PartialEq,
+				F: Fn(&T) -> &str {
+
+			/* Only the first of the items sharing a name ever gets loaded. */
+			let mut names = HashSet::new();
+			new.iter()
+				.filter(|item| names.insert(name(item)))
+				.filter(|item| !old.iter()
+					.find(|old| name(old) == name(item))
+					.map(|old| old == *item)
+					.unwrap_or(false))
+				.map(|item| name(item).to_owned())
+				.collect()
+		}
+
+		let meshes = changed(&self.meshes, &newer.meshes, |mesh| mesh.name.as_str());
+		let materials = changed(
+			&self.materials,
+			&newer.materials,
+			|material| material.name.as_str());
+		let mut objects = changed(
+			&self.objects,
+			&newer.objects,
+			|object| object.name.as_str());
+		objects.extend(newer.objects.iter()
+			.filter(|object| meshes.contains(&object.mesh)
+				|| materials.contains(&object.material))
+			.map(|object| object.name.clone()));
+
+		SceneChanges { meshes, materials, objects }
+	}
+}
+
+/** Items of a scene file that can be loaded, as the indices of their entries
+ * in the file, in the order they have to be loaded in, as worked out by
+ * [`SceneFile::plan()`].
+ *
+ * [`SceneFile::plan()`]: SceneFile::plan */
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScenePlan {
+	/** Meshes to be loaded. */
+	pub meshes: Vec<usize>,
+	/** Materials to be loaded, which only depend on the application. */
+	pub materials: Vec<usize>,
+	/** Objects to be loaded, once their meshes and materials are. */
+	pub objects: Vec<usize>,
+}
+
+/** Names of the items of a scene file that changed between two versions of
+ * it, as worked out by [`SceneFile::changes()`].
+ *
+ * [`SceneFile::changes()`]: SceneFile::changes */
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SceneChanges {
+	/** Meshes that changed. */
+	pub meshes: HashSet<String>,
+	/** Materials that changed. */
+	pub materials: HashSet<String>,
+	/** Objects that changed, or whose mesh or material changed. */
+	pub objects: HashSet<String>,
+}
+
+/** Image decoded by the [`SceneAssets`] of a scene, as tightly packed RGBA
+ * texels with eight bits per channel, from the top row down.
+ *
+ * [`SceneAssets`]: SceneAssets */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SceneImage {
+	/** Width of the image, in texels. */
+	pub width: u32,
+	/** Height of the image, in texels. */
+	pub height: u32,
+	/** Texels of the image. */
+	pub texels: Vec<u8>,
+}
+
+/** Source of the assets scene files refer to by path, which is up to the
+ * application, be it a directory, an archive or assets bundled in the
+ * executable. Loading is synchronous, so applications that have to fetch
+ * their assets asynchronously, as on the web, should do so before loading
+ * the scene. */
+pub trait SceneAssets {
+	/** Reads the contents of the asset at the given path. */
+	fn read(&mut self, path: &str) -> Result<Vec<u8>, String>;
+
+	/** Reads and decodes the image at the given path. */
+	fn image(&mut self, path: &str) -> Result<SceneImage, String>;
+}
+
+/** Kinds of items in a scene file. */
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum SceneItem {
+	Mesh,
+	Material,
+	Object,
+}
+impl Display for SceneItem {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		match self {
+			SceneItem::Mesh => write!(f, "mesh"),
+			SceneItem::Material => write!(f, "material"),
+			SceneItem::Object => write!(f, "object"),
+		}
+	}
+}
+
+/** Problems with individual items of a scene file. These don't keep the rest
+ * of the scene from loading, and only [`SceneIssue::MissingTexture`] still
+ * lets its item load, with a placeholder in place of the texture.
+ *
+ * [`SceneIssue::MissingTexture`]: SceneIssue::MissingTexture */
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum SceneIssue {
+	#[error("there is more than one {kind} named \"{name}\", so only the first \
+		one was loaded")]
+	DuplicateName {
+		kind: SceneItem,
+		name: String,
+	},
+	#[error("material \"{material}\" uses shader \"{shader}\", which the \
+		application doesn't provide, so it was left out")]
+	UnknownShader {
+		material: String,
+		shader: String,
+	},
+	#[error("uniform \"{uniform}\" of material \"{material}\" has {components} \
+		components, rather than one to four, so the material was left out")]
+	InvalidUniform {
+		material: String,
+		uniform: String,
+		components: usize,
+	},
+	#[error("object \"{object}\" uses mesh \"{mesh}\", which was not loaded, \
+		so it was left out")]
+	UnknownMesh {
+		object: String,
+		mesh: String,
+	},
+	#[error("object \"{object}\" uses material \"{material}\", which was not \
+		loaded, so it was left out")]
+	UnknownMaterial {
+		object: String,
+		material: String,
+	},
+	#[error("mesh \"{mesh}\" could not be loaded, so it was left out: {what}")]
+	MeshFailed {
+		mesh: String,
+		what: String,
+	},
+	#[error("texture \"{path}\" of material \"{material}\" could not be loaded, \
+		so a placeholder took its place: {what}")]
+	MissingTexture {
+		material: String,
+		path: String,
+		what: String,
+	},
+	#[error("{kind} \"{name}\" could not be created, so it was left out: {what}")]
+	CreationFailed {
+		kind: SceneItem,
+		name: String,
+		what: String,
+	},
+}
+impl SceneIssue {
+	/** Whether the item this issue is about still got loaded. */
+	pub fn is_warning(&self) -> bool {
+		matches!(self, SceneIssue::MissingTexture { .. })
+	}
+}
+
+/** Uniform values every object of a scene binds to [`SCENE_OBJECT_BLOCK`],
+ * which shaders declare as follows:
+ *
+ * ```glsl
+ * layout(std140) uniform rc_object
+ * {
+ *     mat4 world;
+ *     mat4 world_view_projection;
+ * };
+ * ```
+ *
+ * [`SCENE_OBJECT_BLOCK`]: SCENE_OBJECT_BLOCK */
+#[derive(Debug, Copy, Clone, PartialEq, bytemuck::Zeroable, bytemuck::Pod)]
+#[repr(C)]
+pub struct ObjectUniforms {
+	/** Transformation from model space into world space. */
+	pub world: Matrix4,
+	/** Transformation from model space into clip space. */
+	pub world_view_projection: Matrix4,
+}
+
+/** Mesh of a loaded scene. */
+pub struct LoadedMesh {
+	/** Name of the mesh in the scene file. */
+	pub name: String,
+	/** Vertices of the mesh, of type [`Vertex`].
+	 *
+	 * [`Vertex`]: crate::Vertex */
+	pub vertices: VertexBuffer,
+	/** Indices of the mesh, as 32-bit integers. */
+	pub indices: IndexBuffer,
+	/** Number of indices in the mesh. */
+	pub index_count: u32,
+}
+
+/** Material of a loaded scene. */
+pub struct LoadedMaterial {
+	/** Name of the material in the scene file. */
+	pub name: String,
+	/** Name of the shader the material is rendered with. */
+	pub shader: String,
+	/** Textures of the material, by the name of the sampler they are bound
+	 * to. */
+	pub textures: Vec<(String, Texture)>,
+	/** Uniform buffer holding the default values of the material, along with
+	 * the name of the block it is bound to, if any. */
+	pub uniforms: Option<(String, UniformBuffer)>,
+}
+
+/** Object of a loaded scene. */
+pub struct LoadedObject {
+	/** Name of the object in the scene file. */
+	pub name: String,
+	/** Index of the mesh of the object. */
+	pub mesh: usize,
+	/** Index of the material of the object. */
+	pub material: usize,
+	/** Transformation from model space into world space. */
+	pub world: Matrix4,
+	/** Buffer holding the [`ObjectUniforms`] of the object.
+	 *
+	 * [`ObjectUniforms`]: ObjectUniforms */
+	pub uniforms: UniformBuffer,
+	/** Group binding the uniforms of the object along with its material. */
+	pub group: UniformGroup,
+}
+
+/** Scene loaded from a [`SceneFile`], ready to be drawn.
+ *
+ * Scenes are drawn with the shaders the application provides, by the names
+ * materials give them. Their pipelines must take vertices of type [`Vertex`]
+ * and 32-bit indices, and declare the block described in [`ObjectUniforms`].
+ *
+ * [`SceneFile`]: SceneFile
+ * [`Vertex`]: crate::Vertex
+ * [`ObjectUniforms`]: ObjectUniforms */
+pub struct LoadedScene {
+	/** Meshes of the scene. */
+	pub meshes: Vec<LoadedMesh>,
+	/** Materials of the scene. */
+	pub materials: Vec<LoadedMaterial>,
+	/** Objects of the scene, in the order they are drawn in. */
+	pub objects: Vec<LoadedObject>,
+	/** Scene file this scene was loaded from. */
+	file: SceneFile,
+}
+impl LoadedScene {
+	/** Loads the given scene file, reading the assets it refers to from the
+	 * given source, and rendering its materials with the given shaders, by
+	 * name. Items that can't be loaded are left out of the scene, and the
+	 * problems with them are returned along with it. */
+	pub fn load(
+		device: &Device,
+		file: &SceneFile,
+		assets: &mut dyn SceneAssets,
+		shaders: &HashMap<String, RenderPipeline>) -> (Self, Vec<SceneIssue>) {
+
+		let mut scene = Self {
+			meshes: Vec::new(),
+			materials: Vec::new(),
+			objects: Vec::new(),
+			file: SceneFile {
+				version: SCENE_FILE_VERSION,
+				meshes: Vec::new(),
+				materials: Vec::new(),
+				objects: Vec::new()
+			}
+		};
+		let issues = scene.reload(device, file, assets, shaders);
+
+		(scene, issues)
+	}
+
+	/** Moves this scene over to the given newer version of its file, only
+	 * loading the items that changed since the version it was loaded from, and
+	 * keeping everything else as it is. This is what applications watching
+	 * their scene files for changes should call when they do. */
+	pub fn reload(
+		&mut self,
+		device: &Device,
+		file: &SceneFile,
+		assets: &mut dyn SceneAssets,
+		shaders: &HashMap<String, RenderPipeline>) -> Vec<SceneIssue> {
+
+		let changes = self.file.changes(file);
+		let (plan, mut issues) = file.plan(|shader| shaders.contains_key(shader));
+
+		let mut old_meshes = self.meshes.drain(..)
+			.map(|mesh| (mesh.name.clone(), mesh))
+			.collect::<HashMap<_, _>>();
+		for index in plan.meshes {
+			let entry = &file.meshes[index];
+			let kept = if changes.meshes.contains(&entry.name) {
+				None
+			} else {
+				old_meshes.remove(&entry.name)
+			};
+			let mesh = match kept {
+				Some(mesh) => Ok(mesh),
+				None => load_mesh(device, entry, assets)
+			};
+			match mesh {
+				Ok(mesh) => self.meshes.push(mesh),
+				Err(issue) => issues.push(issue)
+			}
+		}
+
+		let mut old_materials = self.materials.drain(..)
+			.map(|material| (material.name.clone(), material))
+			.collect::<HashMap<_, _>>();
+		for index in plan.materials {
+			let entry = &file.materials[index];
+			let kept = if changes.materials.contains(&entry.name) {
+				None
+			} else {
+				old_materials.remove(&entry.name)
+			};
+			let material = match kept {
+				Some(material) => Ok(material),
+				None => load_material(device, entry, assets, &mut issues)
+			};
+			match material {
+				Ok(material) => self.materials.push(material),
+				Err(issue) => issues.push(issue)
+			}
+		}
+
+		let mut old_objects = self.objects.drain(..)
+			.map(|object| (object.name.clone(), object))
+			.collect::<HashMap<_, _>>();
+		for index in plan.objects {
+			let entry = &file.objects[index];
+
+			/* Meshes that failed to load only show up as missing here. */
+			let mesh = match self.meshes.iter()
+				.position(|mesh| mesh.name == entry.mesh) {
+
+				Some(mesh) => mesh,
+				None => {
+					issues.push(SceneIssue::UnknownMesh {
+						object: entry.name.clone(),
+						mesh: entry.mesh.clone()
+					});
+					continue
+				}
+			};
+			let material = match self.materials.iter()
+				.position(|material| material.name == entry.material) {
+
+				Some(material) => material,
+				None => {
+					issues.push(SceneIssue::UnknownMaterial {
+						object: entry.name.clone(),
+						material: entry.material.clone()
+					});
+					continue
+				}
+			};
+
+			let kept = if changes.objects.contains(&entry.name) {
+				None
+			} else {
+				old_objects.remove(&entry.name)
+			};
+			let object = match kept {
+				Some(object) => Ok(LoadedObject { mesh, material, ..object }),
+				None => load_object(
+					device,
+					entry,
+					mesh,
+					material,
+					&self.materials[material])
+			};
+			match object {
+				Ok(object) => self.objects.push(object),
+				Err(issue) => issues.push(issue)
+			}
+		}
+
+		self.file = file.clone();
+		issues
+	}
+
+	/** Writes the uniforms of every object for the given transformation from
+	 * world space into clip space. */
+	pub fn update(&self, view_projection: Matrix4) -> Result<(), BufferRemap> {
+		for object in &self.objects {
+			let uniforms = ObjectUniforms {
+				world: object.world.transpose(),
+				world_view_projection: (view_projection * object.world).transpose()
+			};
+
+			let slice = object.uniforms.slice(..);
+			let mut map = slice.try_map_mut(BufferLoadOp::DontCare, MapMode::Orphan)?;
+
+			let data = bytemuck::bytes_of(&uniforms);
+			map[..data.len()].copy_from_slice(data);
+		}
+
+		Ok(())
+	}
+
+	/** Submits a draw of every object in this scene to the given queue, with
+	 * the given shaders, which must be the ones the scene was loaded with. */
+	pub fn submit<'a>(
+		&'a self,
+		shaders: &'a HashMap<String, RenderPipeline>,
+		queue: &mut RenderQueue<'a>) {
+
+		for object in &self.objects {
+			let mesh = &self.meshes[object.mesh];
+			let material = &self.materials[object.material];
+			let pipeline = match shaders.get(&material.shader) {
+				Some(pipeline) => pipeline,
+				None => continue
+			};
+
+			queue.submit(DrawItem {
+				pipeline,
+				bind: Some(&object.group),
+				vertices: &mesh.vertices,
+				indices: &mesh.indices,
+				elements: 0..mesh.index_count,
+				instances: 1,
+				z: object.world.as_row_major_array()[11],
+				bounds: None
+			});
+		}
+	}
+}
+
+/** Loads the geometry of the given mesh entry onto the device. */
+fn load_mesh(
+	device: &Device,
+	entry: &MeshEntry,
+	assets: &mut dyn SceneAssets) -> Result<LoadedMesh, SceneIssue> {
+
+	let failed = |what: String| SceneIssue::MeshFailed {
+		mesh: entry.name.clone(),
+		what
+	};
+
+	let (vertices, indices) = match &entry.source {
+		MeshSource::Obj { path } => {
+			let data = assets.read(path).map_err(failed)?;
+			let model = obj::load_obj(std::io::BufReader::new(&data[..]))
+				.map_err(|what| failed(what.to_string()))?;
+			let mesh = Mesh::from_obj(&model)
+				.map_err(|what| failed(what.to_string()))?;
+
+			(mesh.vertices().to_vec(), mesh.indices().to_vec())
+		},
+		MeshSource::Sphere { radius, rings, segments } => scaled(
+			Mesh::uv_sphere(u32::from(*rings), u32::from(*segments)),
+			[*radius; 3]),
+		MeshSource::Quad { width, depth } => scaled(
+			Mesh::plane(1),
+			[width / 2.0, 1.0, depth / 2.0]),
+	};
+	let index_count = u32::try_from(indices.len())
+		.map_err(|what| failed(what.to_string()))?;
+
+	let created = |what: BufferError| SceneIssue::CreationFailed {
+		kind: SceneItem::Mesh,
+		name: entry.name.clone(),
+		what: what.to_string()
+	};
+	let vertices = device.create_vertex_buffer_with_data(
+		&BufferDescriptor {
+			size: buffer_size_of::<Vertex>(vertices.len()).map_err(created)?,
+			profile: BufferProfile::StaticUpload,
+			contents: BufferContents::Opaque,
+			..Default::default()
+		},
+		bytemuck::cast_slice(&vertices[..])).map_err(created)?;
+	let indices = device.create_index_buffer_with_data(
+		&BufferDescriptor {
+			size: buffer_size_of::<u32>(indices.len()).map_err(created)?,
+			profile: BufferProfile::StaticUpload,
+			contents: BufferContents::Opaque,
+			..Default::default()
+		},
+		bytemuck::cast_slice(&indices[..])).map_err(created)?;
+
+	Ok(LoadedMesh {
+		name: entry.name.clone(),
+		vertices,
+		indices,
+		index_count
+	})
+}
+
+/** Loads the textures and the uniform buffer of the given material entry onto
+ * the device. Textures that can't be loaded get replaced by a placeholder, and
+ * get reported in the given list of issues. */
+fn load_material(
+	device: &Device,
+	entry: &MaterialEntry,
+	assets: &mut dyn SceneAssets,
+	issues: &mut Vec<SceneIssue>) -> Result<LoadedMaterial, SceneIssue> {
+
+	let created = |what: String| SceneIssue::CreationFailed {
+		kind: SceneItem::Material,
+		name: entry.name.clone(),
+		what
+	};
+
+	let mut textures = Vec::with_capacity(entry.textures.len());
+	for texture in &entry.textures {
+		let image = match assets.image(&texture.path) {
+			Ok(image) => image,
+			Err(what) => {
+				issues.push(SceneIssue::MissingTexture {
+					material: entry.name.clone(),
+					path: texture.path.clone(),
+					what
+				});
+				placeholder()
+			}
+		};
+
+		let uploaded = device.create_texture_with_data(
+			&TextureDescriptor {
+				extent: TextureExtent::D2 {
+					width: image.width,
+					height: image.height
+				},
+				format: TextureFormat::Rgba8Unorm,
+				mip: Mipmap::None,
+				swizzle: None
+			},
+			image.texels).map_err(|what| created(what.to_string()))?;
+		textures.push((texture.binding.clone(), uploaded));
+	}
+
+	let uniforms = match &entry.uniforms {
+		Some(uniforms) => {
+			let data = pack_std140(&uniforms.values);
+			let size = u32::try_from(data.len())
+				.map_err(|what| created(what.to_string()))?;
+			let buffer = device.create_uniform_buffer_with_data(
+				&BufferDescriptor {
+					size,
+					profile: BufferProfile::StaticUpload,
+					contents: BufferContents::F32,
+					..Default::default()
+				},
+				&data).map_err(|what| created(what.to_string()))?;
+
+			Some((uniforms.block.clone(), buffer))
+		},
+		None => None
+	};
+
+	Ok(LoadedMaterial {
+		name: entry.name.clone(),
+		shader: entry.shader.clone(),
+		textures,
+		uniforms
+	})
+}
+
+/** Creates the uniforms of the given object entry, and binds them along with
+ * its material. */
+fn load_object(
+	device: &Device,
+	entry: &ObjectEntry,
+	mesh: usize,
+	material_index: usize,
+	material: &LoadedMaterial) -> Result<LoadedObject, SceneIssue> {
+
+	let created = |what: String| SceneIssue::CreationFailed {
+		kind: SceneItem::Object,
+		name: entry.name.clone(),
+		what
+	};
+
+	let uniforms = device.create_uniform_buffer(
+		&BufferDescriptor {
+			size: buffer_size_of::<ObjectUniforms>(1)
+				.map_err(|what| created(what.to_string()))?,
+			profile: BufferProfile::DynamicUpload,
+			contents: BufferContents::F32,
+			..Default::default()
+		}).map_err(|what| created(what.to_string()))?;
+
+	let mut entries = vec![
+		UniformGroupEntry {
+			binding: SCENE_OBJECT_BLOCK.into(),
+			kind: UniformBind::Buffer {
+				buffer: &uniforms,
+				offset: 0,
+				size: None
+			}
+		}
+	];
+	if let Some((block, buffer)) = &material.uniforms {
+		entries.push(UniformGroupEntry {
+			binding: block.as_str().into(),
+			kind: UniformBind::Buffer {
+				buffer,
+				offset: 0,
+				size: None
+			}
+		});
+	}
+	for (binding, texture) in &material.textures {
+		entries.push(UniformGroupEntry {
+			binding: binding.as_str().into(),
+			kind: UniformBind::Texture {
+				texture,
+				far: TextureFilter::Linear,
+				near: TextureFilter::Linear,
+				address_mode: AddressMode::Repeat,
+				anisotropy_clamp: None,
+				sampler: None
+			}
+		});
+	}
+	let group = device.create_uniform_bind_group(
+		&UniformGroupDescriptor { entries: &entries })
+		.map_err(|what| created(what.to_string()))?;
+
+	Ok(LoadedObject {
+		name: entry.name.clone(),
+		mesh,
+		material: material_index,
+		world: entry.transform.matrix(),
+		uniforms,
+		group
+	})
+}
+
+/** Image taking the place of textures that could not be loaded, which is a
+ * single magenta texel, so that it stands out. */
+fn placeholder() -> SceneImage {
+	SceneImage {
+		width: 1,
+		height: 1,
+		texels: vec![0xff, 0x00, 0xff, 0xff]
+	}
+}
+
+/** Lays the given values out one after the other, following the `std140`
+ * rules for `float` through `vec4` members, and pads the result up to a
+ * multiple of sixteen bytes, as the size of a uniform block is. */
+fn pack_std140(values: &[UniformDefault]) -> Vec<u8> {
+	let align = |offset: usize, alignment: usize|
+		(offset + alignment - 1) / alignment * alignment;
+
+	let mut data = Vec::new();
+	for value in values {
+		let alignment = match value.value.len() {
+			1 => 4,
+			2 => 8,
+			_ => 16
+		};
+		data.resize(align(data.len(), alignment), 0);
+		for component in &value.value {
+			data.extend_from_slice(&component.to_ne_bytes());
+		}
+	}
+	data.resize(align(data.len().max(1), 16), 0);
+
+	data
+}
+
+/** Vertices and indices of the given generated mesh, with its positions
+ * scaled by the given factor along each axis. Normals are left alone, so the
+ * scale must either be uniform, or not stretch the mesh across its normals. */
+fn scaled(mesh: Mesh, scale: [f32; 3]) -> (Vec<Vertex>, Vec<u32>) {
+	let vertices = mesh.vertices().iter()
+		.map(|vertex| {
+			let [x, y, z] = vertex.position();
+			Vertex::new_unchecked_with_color(
+				[x * scale[0], y * scale[1], z * scale[2]],
+				vertex.texture(),
+				vertex.color(),
+				vertex.normal(),
+				vertex.tangent(),
+				vertex.bitangent())
+		})
+		.collect();
+
+	(vertices, mesh.indices().to_vec())
+}
+
+/** Errors that keep a scene file from being read at all. */
+#[derive(Debug, thiserror::Error)]
+pub enum SceneFileError {
+	#[error("the scene file is malformed: {0}")]
+	Malformed(#[from] serde_json::Error),
+	#[error("the scene file is of version {found}, but only version \
+		{supported} is supported")]
+	UnsupportedVersion {
+		found: u32,
+		supported: u32,
+	},
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const SCENE: &str = r#"{
+		"Version": 1,
+		"Meshes": [
+			{ "Name": "ball", "Source": { "Type": "Sphere", "Radius": 1.0, "Rings": 8, "Segments": 16 } },
+			{ "Name": "floor", "Source": { "Type": "Quad", "Width": 4.0, "Depth": 4.0 } },
+			{ "Name": "ball", "Source": { "Type": "Obj", "Path": "ball.obj" } }
+		],
+		"Materials": [
+			{
+				"Name": "checker",
+				"Shader": "textured",
+				"Textures": [{ "Binding": "tt_tex_albedo", "Path": "checker.png" }],
+				"Uniforms": {
+					"Block": "rc_material",
+					"Values": [{ "Name": "tint", "Value": [1.0, 0.5, 0.5] }]
+				}
+			},
+			{ "Name": "glass", "Shader": "refractive" }
+		],
+		"Objects": [
+			{ "Name": "ball", "Mesh": "ball", "Material": "checker",
+				"Transform": { "Translation": [0.0, 1.0, 0.0] } },
+			{ "Name": "window", "Mesh": "floor", "Material": "glass" },
+			{ "Name": "rock", "Mesh": "rock", "Material": "checker" },
+			{ "Name": "floor", "Mesh": "floor", "Material": "checker" }
+		]
+	}"#;
+
+	fn scene() -> SceneFile {
+		SceneFile::from_json(SCENE).unwrap()
+	}
+
+	#[test]
+	fn files_follow_the_schema() {
+		let scene = scene();
+		assert_eq!(scene.meshes[0].source, MeshSource::Sphere {
+			radius: 1.0,
+			rings: 8,
+			segments: 16
+		});
+		assert_eq!(scene.objects[0].transform, Transform {
+			translation: [0.0, 1.0, 0.0],
+			..Transform::default()
+		});
+		assert_eq!(scene.objects[1].transform, Transform::default());
+
+		let text = serde_json::to_string(&scene).unwrap();
+		assert_eq!(SceneFile::from_json(&text).unwrap(), scene);
+
+		assert!(matches!(
+			SceneFile::from_json(r#"{ "Version": 1, "Lights": [] }"#),
+			Err(SceneFileError::Malformed(_))));
+		assert!(matches!(
+			SceneFile::from_json(r#"{ "Version": 2, "Lights": [] }"#),
+			Err(SceneFileError::UnsupportedVersion { found: 2, supported: 1 })));
+	}
+
+	#[test]
+	fn broken_items_take_their_dependents_with_them() {
+		let (plan, issues) = scene().plan(|shader| shader == "textured");
+
+		assert_eq!(plan, ScenePlan {
+			meshes: vec![0, 1],
+			materials: vec![0],
+			objects: vec![0, 3]
+		});
+		assert_eq!(issues, vec![
+			SceneIssue::DuplicateName {
+				kind: SceneItem::Mesh,
+				name: "ball".to_owned()
+			},
+			SceneIssue::UnknownShader {
+				material: "glass".to_owned(),
+				shader: "refractive".to_owned()
+			},
+			SceneIssue::UnknownMaterial {
+				object: "window".to_owned(),
+				material: "glass".to_owned()
+			},
+			SceneIssue::UnknownMesh {
+				object: "rock".to_owned(),
+				mesh: "rock".to_owned()
+			},
+		]);
+		assert!(issues.iter().all(|issue| !issue.is_warning()));
+	}
+
+	#[test]
+	fn uniforms_must_fit_in_a_vector() {
+		let mut scene = scene();
+		scene.materials[0].uniforms.as_mut().unwrap().values.push(UniformDefault {
+			name: "matrix".to_owned(),
+			value: vec![0.0; 16]
+		});
+
+		let (plan, issues) = scene.plan(|_| true);
+		assert_eq!(plan.materials, vec![1]);
+		assert_eq!(issues[1], SceneIssue::InvalidUniform {
+			material: "checker".to_owned(),
+			uniform: "matrix".to_owned(),
+			components: 16
+		});
+	}
+
+	#[test]
+	fn changes_follow_dependencies() {
+		let old = scene();
+		let mut new = scene();
+		assert_eq!(old.changes(&new), SceneChanges::default());
+
+		new.materials[0].textures[0].path = "bricks.png".to_owned();
+		new.objects[1].transform.scale = [2.0; 3];
+		new.objects.push(ObjectEntry {
+			name: "lamp".to_owned(),
+			mesh: "ball".to_owned(),
+			material: "glass".to_owned(),
+			transform: Transform::default()
+		});
+
+		let changes = old.changes(&new);
+		assert!(changes.meshes.is_empty());
+		assert_eq!(changes.materials, vec!["checker".to_owned()].into_iter().collect());
+
+		let mut objects = changes.objects.into_iter().collect::<Vec<_>>();
+		objects.sort();
+		assert_eq!(objects, vec!["ball", "floor", "lamp", "rock", "window"]);
+	}
+
+	#[test]
+	fn uniforms_are_packed_as_std140() {
+		let value = |value: &[f32]| UniformDefault {
+			name: String::new(),
+			value: value.to_vec()
+		};
+		let data = pack_std140(&[
+			value(&[1.0]),
+			value(&[2.0, 3.0]),
+			value(&[4.0, 5.0, 6.0]),
+			value(&[7.0]),
+		]);
+		let floats = bytemuck::cast_slice::<u8, f32>(&data);
+
+		/* The pair lines up with eight bytes, the triple with sixteen, and the
+		 * last scalar packs right after the triple. */
+		assert_eq!(floats, &[1.0, 0.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0]);
+		assert_eq!(pack_std140(&[]).len(), 16);
+		assert_eq!(pack_std140(&[value(&[1.0, 2.0, 3.0, 4.0]), value(&[5.0])]).len(), 32);
+	}
+
+	#[test]
+	fn transforms_scale_rotate_and_translate() {
+		let transform = Transform {
+			translation: [1.0, 2.0, 3.0],
+			rotation: [0.0, 0.0, 1.0, std::f32::consts::FRAC_PI_2],
+			scale: [2.0; 3]
+		};
+		let m = transform.matrix();
+		let m = m.as_row_major_array();
+		let point = |i: usize| m[i * 4] + m[i * 4 + 3];
+
+		/* The unit x vector gets scaled, turned onto the negative y axis, and
+		 * then moved by the translation. */
+		for (i, expected) in [1.0, 0.0, 3.0].iter().enumerate() {
+			assert!((point(i) - expected).abs() < 1e-5, "{:?}", m);
+		}
+		assert_eq!(Transform::default().matrix(), Matrix4::identity());
+	}
+
+	#[test]
+	fn generated_meshes_get_scaled() {
+		let (vertices, indices) = scaled(Mesh::uv_sphere(4, 6), [2.0; 3]);
+		assert_eq!(vertices.len(), Mesh::uv_sphere(4, 6).vertices().len());
+		assert_eq!(indices, Mesh::uv_sphere(4, 6).indices());
+		for vertex in &vertices {
+			let [x, y, z] = vertex.position();
+			assert!((f32::sqrt(x * x + y * y + z * z) - 2.0).abs() < 1e-5);
+		}
+
+		let (vertices, _) = scaled(Mesh::plane(1), [0.5, 1.0, 1.0]);
+		for vertex in &vertices {
+			let [x, y, z] = vertex.position();
+			assert_eq!((x.abs(), y, z.abs()), (0.5, 0.0, 1.0));
+			assert_eq!(vertex.normal(), [0.0, 1.0, 0.0]);
+		}
+	}
+}
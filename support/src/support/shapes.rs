@@ -0,0 +1,364 @@
+use crate::support::{Mesh, Vertex, Vec3};
+use std::f32::consts::PI;
+
+/** Generators for simple shapes.
+ *
+ * Every shape is centered on the origin, with the `y` axis pointing up, and
+ * fits inside of the cube going from `[-1, -1, -1]` to `[1, 1, 1]`, except for
+ * the torus, whose size is given. Triangles are wound counter clockwise when
+ * seen from the outside, and the tangent and bitangent of every vertex follow
+ * its texture coordinates, forming a right handed frame with its normal.
+ *
+ * Vertices are shared between triangles wherever the surface is smooth, and
+ * only get split along hard edges and along the seams the texture coordinates
+ * wrap around at. */
+impl Mesh {
+	/** Sphere of radius one, made of the given number of rings from pole to
+	 * pole, at least two, and of the given number of segments around the
+	 * equator, at least three.
+	 *
+	 * Texture coordinates go around the equator along `u`, and from the
+	 * bottom pole to the top one along `v`. Each triangle touching a pole gets
+	 * its own vertex there, at the middle of its range of `u`, so that the
+	 * texture doesn't get pinched into a seam around the poles. */
+	pub fn uv_sphere(rings: u32, segments: u32) -> Self {
+		let rings = rings.max(2);
+		let segments = segments.max(3);
+
+		let vertex = |u: f32, v: f32| {
+			let (sin_phi, cos_phi) = turn(u);
+			let (sin_alpha, cos_alpha) = f32::sin_cos(PI * (v - 0.5));
+
+			/* All of the vertices at a pole must be on the exact same spot. */
+			let cos_alpha = if v == 0.0 || v == 1.0 { 0.0 } else { cos_alpha };
+
+			let normal = Vec3::new(cos_alpha * sin_phi, sin_alpha, cos_alpha * cos_phi);
+			let tangent = Vec3::new(cos_phi, 0.0, -sin_phi);
+
+			self::vertex(normal, [u, v], normal, tangent)
+		};
+
+		/* The bottom pole, then the rings in between, from the bottom up, each
+		 * with a seam vertex at the end, and then the top pole. */
+		let mut vertices = Vec::new();
+		let pole = |vertices: &mut Vec<Vertex>, v: f32| {
+			vertices.extend((0..segments)
+				.map(|segment| vertex((segment as f32 + 0.5) / segments as f32, v)));
+		};
+
+		pole(&mut vertices, 0.0);
+		for ring in 1..rings {
+			vertices.extend((0..=segments).map(|segment| vertex(
+				segment as f32 / segments as f32,
+				ring as f32 / rings as f32)));
+		}
+		pole(&mut vertices, 1.0);
+
+		let ring_start = |ring: u32| segments + (ring - 1) * (segments + 1);
+		let top = ring_start(rings);
+
+		let mut indices = Vec::new();
+		for segment in 0..segments {
+			let a = ring_start(1) + segment;
+			indices.extend_from_slice(&[segment, a + 1, a]);
+		}
+		for ring in 1..rings - 1 {
+			for segment in 0..segments {
+				let a = ring_start(ring) + segment;
+				let d = ring_start(ring + 1) + segment;
+				indices.extend_from_slice(&[a, a + 1, d + 1, a, d + 1, d]);
+			}
+		}
+		for segment in 0..segments {
+			let a = ring_start(rings - 1) + segment;
+			indices.extend_from_slice(&[a, a + 1, top + segment]);
+		}
+
+		Self::from_parts(vertices, indices)
+	}
+
+	/** Cylinder of radius one, going from a height of minus one to one, made
+	 * of the given number of segments around it, at least three, and closed
+	 * off by flat caps at both ends if asked to.
+	 *
+	 * Texture coordinates go around the side along `u` and up along `v`, and
+	 * are projected from above onto the caps, the same way they are on a
+	 * [`plane()`], with the one on the bottom seen from below.
+	 *
+	 * [`plane()`]: Self::plane */
+	pub fn cylinder(segments: u32, capped: bool) -> Self {
+		let segments = segments.max(3);
+
+		let mut vertices = Vec::new();
+		let mut indices = Vec::new();
+		grid(&mut vertices, &mut indices, segments, 1, |u, v| {
+			let (sin, cos) = turn(u);
+			let normal = Vec3::new(sin, 0.0, cos);
+
+			vertex(
+				Vec3::new(sin, v * 2.0 - 1.0, cos),
+				[u, v],
+				normal,
+				Vec3::new(cos, 0.0, -sin))
+		});
+
+		if capped {
+			for &side in &[1.0, -1.0] {
+				let normal = Vec3::new(0.0, side, 0.0);
+				let tangent = Vec3::new(1.0, 0.0, 0.0);
+				let cap = |x: f32, z: f32| vertex(
+					Vec3::new(x, side, z),
+					[(x + 1.0) / 2.0, (1.0 - z * side) / 2.0],
+					normal,
+					tangent);
+
+				let center = vertices.len() as u32;
+				vertices.push(cap(0.0, 0.0));
+				vertices.extend((0..segments).map(|segment| {
+					let (sin, cos) = turn(segment as f32 / segments as f32);
+					cap(sin, cos)
+				}));
+
+				for segment in 0..segments {
+					let a = center + 1 + segment;
+					let b = center + 1 + (segment + 1) % segments;
+					if side > 0.0 {
+						indices.extend_from_slice(&[center, a, b]);
+					} else {
+						indices.extend_from_slice(&[center, b, a]);
+					}
+				}
+			}
+		}
+
+		Self::from_parts(vertices, indices)
+	}
+
+	/** Flat square going from minus one to one along the `x` and `z` axes,
+	 * facing up, split into the given number of cells along each side, at
+	 * least one.
+	 *
+	 * Texture coordinates go along the `x` axis in `u`, and against the `z`
+	 * axis in `v`, so that they read the right way when seen from above with
+	 * the `z` axis pointing down. */
+	pub fn plane(subdivisions: u32) -> Self {
+		let subdivisions = subdivisions.max(1);
+
+		let mut vertices = Vec::new();
+		let mut indices = Vec::new();
+		grid(&mut vertices, &mut indices, subdivisions, subdivisions, |u, v| vertex(
+			Vec3::new(u * 2.0 - 1.0, 0.0, 1.0 - v * 2.0),
+			[u, v],
+			Vec3::new(0.0, 1.0, 0.0),
+			Vec3::new(1.0, 0.0, 0.0)));
+
+		Self::from_parts(vertices, indices)
+	}
+
+	/** Cube going from minus one to one along every axis, with every face
+	 * getting its own vertices and the whole of the texture. Texture
+	 * coordinates go up along `v` on the side faces. */
+	pub fn cube() -> Self {
+		/* Normal and tangent of every face. */
+		let faces = [
+			(Vec3::new( 1.0,  0.0,  0.0), Vec3::new( 0.0, 0.0, -1.0)),
+			(Vec3::new(-1.0,  0.0,  0.0), Vec3::new( 0.0, 0.0,  1.0)),
+			(Vec3::new( 0.0,  1.0,  0.0), Vec3::new( 1.0, 0.0,  0.0)),
+			(Vec3::new( 0.0, -1.0,  0.0), Vec3::new( 1.0, 0.0,  0.0)),
+			(Vec3::new( 0.0,  0.0,  1.0), Vec3::new( 1.0, 0.0,  0.0)),
+			(Vec3::new( 0.0,  0.0, -1.0), Vec3::new(-1.0, 0.0,  0.0)),
+		];
+
+		let mut vertices = Vec::new();
+		let mut indices = Vec::new();
+		for &(normal, tangent) in &faces {
+			let bitangent = normal.cross(tangent);
+			grid(&mut vertices, &mut indices, 1, 1, |u, v| vertex(
+				normal + tangent * (u * 2.0 - 1.0) + bitangent * (v * 2.0 - 1.0),
+				[u, v],
+				normal,
+				tangent));
+		}
+
+		Self::from_parts(vertices, indices)
+	}
+
+	/** Torus lying on the plane of the `x` and `z` axes, with its tube going
+	 * around the `y` axis at the given major radius, and having the given
+	 * minor radius, made of the given number of segments both around the `y`
+	 * axis and around the tube, at least three.
+	 *
+	 * Texture coordinates go around the `y` axis along `u`, and around the
+	 * tube along `v`, starting from its outer side, going up. */
+	pub fn torus(major: f32, minor: f32, segments: u32) -> Self {
+		let segments = segments.max(3);
+
+		let mut vertices = Vec::new();
+		let mut indices = Vec::new();
+		grid(&mut vertices, &mut indices, segments, segments, |u, v| {
+			let (sin_phi, cos_phi) = turn(u);
+			let (sin_theta, cos_theta) = turn(v);
+
+			let outwards = Vec3::new(sin_phi, 0.0, cos_phi);
+			let normal = outwards * cos_theta + Vec3::new(0.0, sin_theta, 0.0);
+
+			vertex(
+				outwards * major + normal * minor,
+				[u, v],
+				normal,
+				Vec3::new(cos_phi, 0.0, -sin_phi))
+		});
+
+		Self::from_parts(vertices, indices)
+	}
+}
+
+/** Sine and cosine of the given fraction of a full turn, with both ends of the
+ * turn giving the exact same values, so that the vertices on both sides of a
+ * seam land on the exact same spot. */
+fn turn(fraction: f32) -> (f32, f32) {
+	f32::sin_cos(2.0 * PI * (fraction % 1.0))
+}
+
+/** Vertex with the given position, texture coordinates, normal and tangent,
+ * all of unit length, with a bitangent that completes a right handed frame. */
+fn vertex(position: Vec3, texture: [f32; 2], normal: Vec3, tangent: Vec3) -> Vertex {
+	Vertex::new_unchecked(
+		position.into(),
+		texture,
+		normal.into(),
+		tangent.into(),
+		normal.cross(tangent).into())
+}
+
+/** Appends a grid with the given number of columns and rows of cells to the
+ * given vertices and indices, with the vertex at every corner of the cells
+ * given by the function, from its texture coordinates.
+ *
+ * The triangles of the grid are wound counter clockwise in texture space, so
+ * the tangent space of the vertices must have its tangent follow `u` and its
+ * bitangent follow `v` for them to be wound counter clockwise from outside. */
+fn grid(
+	vertices: &mut Vec<Vertex>,
+	indices: &mut Vec<u32>,
+	columns: u32,
+	rows: u32,
+	vertex: impl Fn(f32, f32) -> Vertex) {
+
+	let base = vertices.len() as u32;
+	for row in 0..=rows {
+		for column in 0..=columns {
+			vertices.push(vertex(
+				column as f32 / columns as f32,
+				row as f32 / rows as f32));
+		}
+	}
+
+	for row in 0..rows {
+		for column in 0..columns {
+			let a = base + row * (columns + 1) + column;
+			let d = a + columns + 1;
+			indices.extend_from_slice(&[a, a + 1, d + 1, a, d + 1, d]);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn shapes() -> Vec<(&'static str, Mesh)> {
+		vec![
+			("sphere", Mesh::uv_sphere(8, 12)),
+			("two ring sphere", Mesh::uv_sphere(2, 3)),
+			("cylinder", Mesh::cylinder(12, false)),
+			("capped cylinder", Mesh::cylinder(12, true)),
+			("plane", Mesh::plane(4)),
+			("cube", Mesh::cube()),
+			("torus", Mesh::torus(1.0, 0.25, 12)),
+		]
+	}
+
+	#[test]
+	fn vertices_have_orthonormal_frames() {
+		for (name, mesh) in shapes() {
+			for vertex in mesh.vertices() {
+				assert!(Vertex::try_new(
+					vertex.position(),
+					vertex.texture(),
+					vertex.normal(),
+					vertex.tangent(),
+					vertex.bitangent()).is_ok(), "{}: {:?}", name, vertex);
+
+				let (n, t, b) = (
+					Vec3::from(vertex.normal()),
+					Vec3::from(vertex.tangent()),
+					Vec3::from(vertex.bitangent()));
+				assert!((t.cross(b) - n).length() < 1e-5, "{}: {:?}", name, vertex);
+			}
+		}
+	}
+
+	#[test]
+	fn triangles_face_outwards() {
+		for (name, mesh) in shapes() {
+			assert!(mesh.indices().iter().all(|index| (*index as usize) < mesh.vertices().len()));
+
+			let estimate = mesh.winding_order();
+			assert_eq!(estimate.clockwise, 0, "{}", name);
+			assert_eq!(estimate.undecided, 0, "{}", name);
+		}
+	}
+
+	#[test]
+	fn tangents_follow_the_texture_coordinates() {
+		for (name, mesh) in shapes() {
+			for triangle in mesh.indices().chunks_exact(3) {
+				let vert = |index: usize| mesh.vertices()[triangle[index] as usize];
+				let edge = |index: usize|
+					Vec3::from(vert(index).position()) - Vec3::from(vert(0).position());
+				let uv = |index: usize| {
+					let (a, b) = (vert(0).texture(), vert(index).texture());
+					[b[0] - a[0], b[1] - a[1]]
+				};
+
+				let (edge0, edge1, uv0, uv1) = (edge(1), edge(2), uv(1), uv(2));
+				let base = 1.0 / (uv0[0] * uv1[1] - uv0[1] * uv1[0]);
+				let tangent = (edge0 * uv1[1] - edge1 * uv0[1]) * base;
+				let bitangent = (edge1 * uv0[0] - edge0 * uv1[0]) * base;
+
+				for index in 0..3 {
+					let vertex = vert(index);
+					assert!(tangent.dot(Vec3::from(vertex.tangent())) > 0.0, "{}", name);
+					assert!(bitangent.dot(Vec3::from(vertex.bitangent())) > 0.0, "{}", name);
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn smooth_surfaces_share_their_vertices() {
+		for (name, mesh) in shapes() {
+			let vertices = mesh.vertices();
+			for (index, vertex) in vertices.iter().enumerate() {
+				assert!(!vertices[index + 1..].contains(vertex), "{}: {:?}", name, vertex);
+			}
+		}
+
+		/* Every vertex of a sphere lies on it, and those on the same spot are
+		 * only told apart by their texture coordinates, along the seam. */
+		let sphere = Mesh::uv_sphere(8, 12);
+		assert_eq!(sphere.vertices().len(), 2 * 12 + 7 * 13);
+		assert_eq!(sphere.indices().len(), 3 * (2 * 12 + 2 * 6 * 12));
+		for vertex in sphere.vertices() {
+			assert!((Vec3::from(vertex.position()).length() - 1.0).abs() < 1e-5);
+			assert_eq!(vertex.position(), vertex.normal());
+		}
+
+		/* The caps of a cylinder are split from its side. */
+		assert_eq!(Mesh::cylinder(12, false).vertices().len(), 2 * 13);
+		assert_eq!(Mesh::cylinder(12, true).vertices().len(), 2 * 13 + 2 * 13);
+		assert_eq!(Mesh::cube().vertices().len(), 24);
+		assert_eq!(Mesh::plane(4).vertices().len(), 25);
+	}
+}
@@ -0,0 +1,379 @@
+use gavle::*;
+use std::borrow::Cow;
+use std::convert::TryFrom;
+use crate::support::Matrix4;
+
+/** Maximum number of joints that may influence a single skinned vertex. */
+pub const MAX_JOINT_INFLUENCES: usize = 4;
+
+/** Name of the binding of the bone palette in the skinning shaders. */
+pub const PALETTE_BINDING: &str = "rc_skin_palette";
+
+/** GLSL code implementing the weighted matrix blend used in skinning.
+ *
+ * This declares the bone palette, which is either a uniform block or, when
+ * `RC_SKIN_PALETTE_TEXTURE` is defined, a floating point texture with one
+ * matrix per row, as well as the following function, to be used from vertex
+ * shaders:
+ * ```glsl
+ * mat4 rc_skin_matrix(vec4 joints, vec4 weights);
+ * ```
+ *
+ * `RC_SKIN_JOINTS` must be defined to the number of joints in the skeleton.
+ * Rather than defining these by hand, use [`Skeleton::glsl()`], which emits
+ * the definitions that match the skeleton, followed by this snippet.
+ *
+ * [`Skeleton::glsl()`]: Skeleton::glsl */
+pub const SKINNING_GLSL: &str = include_str!("skinning/skinning.glsl");
+
+/** Structure containing the data for a vertex influenced by the joints of a
+ * skeleton. */
+#[derive(Debug, Copy, Clone, PartialEq, PartialOrd, bytemuck::Zeroable, bytemuck::Pod, serde::Serialize, serde::Deserialize)]
+#[repr(C)]
+pub struct SkinnedVertex {
+	/** Position data in three-dimensional model space, in the bind pose. */
+	pub position: [f32; 3],
+	/** Normal vector data in normalized three dimensional space, in the bind
+	 * pose. */
+	pub normal: [f32; 3],
+	/** Texture coordinate data in two-dimensional sampler space. */
+	pub texture: [f32; 2],
+	/** Indices of the joints influencing this vertex. */
+	pub joints: [u8; MAX_JOINT_INFLUENCES],
+	/** Weight of the influence of each of the joints in `joints`. These
+	 * should add up to one. */
+	pub weights: [f32; MAX_JOINT_INFLUENCES],
+}
+impl SkinnedVertex {
+	/** Layout of buffers that use this structure as their vertex type.
+	 *
+	 * The joint indices are unsigned bytes that are not normalized, so they
+	 * reach the shader as whole numbers in a `vec4`, ready to be passed on to
+	 * `rc_skin_matrix()` along with the weights. */
+	pub const LAYOUT: VertexBufferLayout<'static> = VertexBufferLayout {
+		array_stride: 52,
+		attributes: &[
+			VertexAttribute {
+				kind: VertexType::F32,
+				components: VertexComponents::Three,
+				offset: 0,
+				binding: Cow::Borrowed("tt_vert_position"),
+				location: None
+			},
+			VertexAttribute {
+				kind: VertexType::F32,
+				components: VertexComponents::Three,
+				offset: 12,
+				binding: Cow::Borrowed("tt_vert_normal"),
+				location: None
+			},
+			VertexAttribute {
+				kind: VertexType::F32,
+				components: VertexComponents::Two,
+				offset: 24,
+				binding: Cow::Borrowed("tt_vert_texture"),
+				location: None
+			},
+			VertexAttribute {
+				kind: VertexType::U8,
+				components: VertexComponents::Four,
+				offset: 32,
+				binding: Cow::Borrowed("tt_vert_joints"),
+				location: None
+			},
+			VertexAttribute {
+				kind: VertexType::F32,
+				components: VertexComponents::Four,
+				offset: 36,
+				binding: Cow::Borrowed("tt_vert_weights"),
+				location: None
+			},
+		]
+	};
+}
+
+/** A single joint in a skeleton. */
+#[derive(Debug, Copy, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Joint {
+	/** Index of the parent of this joint in the skeleton, if any. Parents must
+	 * come before their children. */
+	pub parent: Option<usize>,
+	/** Transformation from model space into the space of this joint, in the
+	 * bind pose. */
+	pub inverse_bind: Matrix4,
+}
+
+/** Storage of the bone palette on the device. */
+enum Palette {
+	/** The palette fits in a single uniform block. */
+	Uniform(UniformBuffer),
+	/** The palette is too big for a uniform block, so it's stored in the rows
+	 * of a floating point texture, instead. */
+	Texture(Texture),
+}
+
+/** A hierarchy of joints, along with the bone palette used to skin meshes
+ * bound to it in the current pose. */
+pub struct Skeleton {
+	/** Joints in the skeleton. */
+	joints: Vec<Joint>,
+	/** Skinning matrices for the current pose. */
+	palette: Vec<Matrix4>,
+	/** Device storage of the palette. */
+	storage: Palette,
+}
+impl Skeleton {
+	/** Creates a new skeleton with the given joints, starting out in the bind
+	 * pose.
+	 *
+	 * The palette is kept in a uniform block when it fits in one, and in a
+	 * texture otherwise, which is transparent to shaders built with
+	 * [`glsl()`].
+	 *
+	 * [`glsl()`]: Self::glsl */
+	pub fn new(device: &Device, joints: Vec<Joint>) -> Result<Self, SkeletonError> {
+		if joints.is_empty() {
+			return Err(SkeletonError::Empty)
+		}
+		for (index, joint) in joints.iter().enumerate() {
+			match joint.parent {
+				Some(parent) if parent >= index =>
+					return Err(SkeletonError::InvalidParent { joint: index, parent }),
+				_ => {}
+			}
+		}
+
+		let size = buffer_size_of::<Matrix4>(joints.len())
+			.map_err(|_| SkeletonError::TooManyJoints { joints: joints.len() })?;
+		let storage = if size <= device.information().limits.max_uniform_block_size {
+			Palette::Uniform(device.create_uniform_buffer(
+				&BufferDescriptor {
+					size,
+					profile: BufferProfile::DynamicUpload,
+					contents: BufferContents::F32,
+					..Default::default()
+				})?)
+		} else {
+			let height = u32::try_from(joints.len())
+				.map_err(|_| SkeletonError::TooManyJoints { joints: joints.len() })?;
+			Palette::Texture(device.create_texture(
+				&TextureDescriptor {
+					extent: TextureExtent::D2 { width: 4, height },
+					format: TextureFormat::Rgba32Float,
+					mip: Mipmap::None,
+					swizzle: None
+				})?)
+		};
+
+		/* In the bind pose, every vertex stays where it is. */
+		let mut this = Self {
+			palette: vec![Matrix4::identity(); joints.len()],
+			joints,
+			storage,
+		};
+		this.upload()?;
+
+		Ok(this)
+	}
+
+	/** The joints in this skeleton. */
+	pub fn joints(&self) -> &[Joint] {
+		&self.joints
+	}
+
+	/** The skinning matrices for the current pose, one per joint. */
+	pub fn palette(&self) -> &[Matrix4] {
+		&self.palette
+	}
+
+	/** Changes the current pose of the skeleton, given the transformation of
+	 * every joint relative to its parent, and uploads the resulting palette.
+	 *
+	 * This must be called outside of a render pass. */
+	pub fn set_pose(&mut self, locals: &[Matrix4]) -> Result<(), SkeletonError> {
+		if locals.len() != self.joints.len() {
+			return Err(SkeletonError::PoseMismatch {
+				expected: self.joints.len(),
+				provided: locals.len()
+			})
+		}
+
+		compose_palette(&self.joints, locals, &mut self.palette);
+		self.upload()
+	}
+
+	/** Entry binding the palette of this skeleton to the skinning shaders. */
+	pub fn bind_entry(&self) -> UniformGroupEntry<'_> {
+		UniformGroupEntry {
+			binding: PALETTE_BINDING.into(),
+			kind: match &self.storage {
+				Palette::Uniform(buffer) => UniformBind::Buffer {
+					buffer,
+					offset: 0,
+					size: None
+				},
+				Palette::Texture(texture) => UniformBind::Texture {
+					texture,
+					far: TextureFilter::Nearest,
+					near: TextureFilter::Nearest,
+					address_mode: AddressMode::Repeat,
+					anisotropy_clamp: None,
+					sampler: None
+				}
+			}
+		}
+	}
+
+	/** GLSL code declaring the palette of this skeleton and the skinning
+	 * function, as described in [`SKINNING_GLSL`]. This goes into the vertex
+	 * shader source, after the precision statements.
+	 *
+	 * [`SKINNING_GLSL`]: SKINNING_GLSL */
+	pub fn glsl(&self) -> String {
+		let texture = match self.storage {
+			Palette::Uniform(_) => "",
+			Palette::Texture(_) => "#define RC_SKIN_PALETTE_TEXTURE\n",
+		};
+		format!("#define RC_SKIN_JOINTS {}\n{}{}",
+			self.joints.len(),
+			texture,
+			SKINNING_GLSL)
+	}
+
+	/** Write the current palette into device storage. */
+	fn upload(&mut self) -> Result<(), SkeletonError> {
+		/* Shaders expect column-major matrices. */
+		let palette = self.palette.iter()
+			.map(|matrix| matrix.transpose())
+			.collect::<Vec<_>>();
+		let data = bytemuck::cast_slice::<_, u8>(&palette[..]);
+
+		match &self.storage {
+			Palette::Uniform(buffer) => {
+				let slice = buffer.slice(..);
+				let mut map = slice.try_map_mut(BufferLoadOp::DontCare, MapMode::Orphan)
+					.map_err(|_| SkeletonError::PaletteInUse)?;
+				map[..data.len()].copy_from_slice(data);
+			},
+			Palette::Texture(texture) => texture.write_level(0, data)?
+		}
+
+		Ok(())
+	}
+}
+
+/** Compose the transformations of the joints in the given pose down the
+ * hierarchy, and write the resulting skinning matrices into `palette`.
+ *
+ * Each skinning matrix takes a vertex from model space in the bind pose into
+ * the space of its joint, then back out into model space in the given pose. */
+fn compose_palette(joints: &[Joint], locals: &[Matrix4], palette: &mut Vec<Matrix4>) {
+	/* Transformations from the space of each joint into model space. */
+	let mut globals = Vec::<Matrix4>::with_capacity(joints.len());
+	for (joint, local) in joints.iter().zip(locals) {
+		let global = match joint.parent {
+			Some(parent) => globals[parent] * *local,
+			None => *local
+		};
+		globals.push(global);
+	}
+
+	palette.clear();
+	palette.extend(joints.iter()
+		.zip(globals)
+		.map(|(joint, global)| global * joint.inverse_bind));
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SkeletonError {
+	#[error("skeletons must have at least one joint")]
+	Empty,
+	#[error("joint {joint} has parent {parent}, but parents must come before \
+		their children")]
+	InvalidParent {
+		joint: usize,
+		parent: usize
+	},
+	#[error("a skeleton with {joints} joints is too big")]
+	TooManyJoints {
+		joints: usize
+	},
+	#[error("the pose has {provided} transformations, but the skeleton has \
+		{expected} joints")]
+	PoseMismatch {
+		expected: usize,
+		provided: usize
+	},
+	#[error("the palette can't be written to while it's in use")]
+	PaletteInUse,
+	#[error("could not create the palette buffer: {0}")]
+	Buffer(#[from] BufferError),
+	#[error("could not create the palette texture: {0}")]
+	Texture(#[from] TextureError),
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn apply(matrix: &Matrix4, point: [f32; 3]) -> [f32; 3] {
+		let m = matrix.as_row_major_array();
+		let row = |i: usize| m[i * 4] * point[0]
+			+ m[i * 4 + 1] * point[1]
+			+ m[i * 4 + 2] * point[2]
+			+ m[i * 4 + 3];
+		[row(0), row(1), row(2)]
+	}
+
+	fn assert_close(a: [f32; 3], b: [f32; 3]) {
+		for (a, b) in a.iter().zip(&b) {
+			assert!((a - b).abs() < 1e-5, "{:?} != {:?}", a, b);
+		}
+	}
+
+	/** Two joints along the y axis, one unit apart, like a simple arm. */
+	fn arm() -> Vec<Joint> {
+		vec![
+			Joint { parent: None, inverse_bind: Matrix4::identity() },
+			Joint {
+				parent: Some(0),
+				inverse_bind: Matrix4::translate(0.0, -1.0, 0.0)
+			},
+		]
+	}
+
+	#[test]
+	fn bind_pose_is_identity() {
+		let mut palette = Vec::new();
+		compose_palette(&arm(), &[
+			Matrix4::identity(),
+			Matrix4::translate(0.0, 1.0, 0.0),
+		], &mut palette);
+
+		for matrix in &palette {
+			assert_close(apply(matrix, [0.3, 1.5, -0.2]), [0.3, 1.5, -0.2]);
+		}
+	}
+
+	#[test]
+	fn composes_down_the_hierarchy() {
+		let bend = Matrix4::rotate(0.0, 0.0, 1.0, std::f32::consts::FRAC_PI_2);
+		let mut palette = Vec::new();
+
+		/* Bend the elbow by a quarter turn around the z axis, then move the
+		 * whole arm to the side. */
+		compose_palette(&arm(), &[
+			Matrix4::translate(5.0, 0.0, 0.0),
+			Matrix4::translate(0.0, 1.0, 0.0) * bend,
+		], &mut palette);
+
+		/* The shoulder only moves along with the root. */
+		assert_close(apply(&palette[0], [0.0, 0.5, 0.0]), [5.0, 0.5, 0.0]);
+
+		/* Points past the elbow swing around it. */
+		let [x, y, z] = apply(&bend, [0.0, 1.0, 0.0]);
+		assert!(y.abs() < 1e-5);
+		assert_close(apply(&palette[1], [0.0, 1.0, 0.0]), [5.0, 1.0, 0.0]);
+		assert_close(apply(&palette[1], [0.0, 2.0, 0.0]), [5.0 + x, 1.0 + y, z]);
+	}
+}
@@ -0,0 +1,32 @@
+/* Bone palette of the skeleton, with one skinning matrix per joint. */
+#ifdef RC_SKIN_PALETTE_TEXTURE
+uniform highp sampler2D rc_skin_palette;
+
+highp mat4 rc_skin_joint(int joint)
+{
+	return mat4(
+		texelFetch(rc_skin_palette, ivec2(0, joint), 0),
+		texelFetch(rc_skin_palette, ivec2(1, joint), 0),
+		texelFetch(rc_skin_palette, ivec2(2, joint), 0),
+		texelFetch(rc_skin_palette, ivec2(3, joint), 0));
+}
+#else
+layout(std140) uniform rc_skin_palette
+{
+	highp mat4 rc_skin_joints[RC_SKIN_JOINTS];
+};
+
+highp mat4 rc_skin_joint(int joint)
+{
+	return rc_skin_joints[joint];
+}
+#endif
+
+/* Blend the skinning matrices of the joints influencing a vertex. */
+highp mat4 rc_skin_matrix(highp vec4 joints, highp vec4 weights)
+{
+	return rc_skin_joint(int(joints.x)) * weights.x
+		+ rc_skin_joint(int(joints.y)) * weights.y
+		+ rc_skin_joint(int(joints.z)) * weights.z
+		+ rc_skin_joint(int(joints.w)) * weights.w;
+}
@@ -0,0 +1,323 @@
+use gavle::ShaderSource;
+use std::borrow::Cow;
+use std::f32::consts::PI;
+
+/** Name of the binding of the uniform block of the sky shader. */
+pub const SKY_BINDING: &str = "rc_sky";
+
+/** Fragment shader drawing a gradient sky, with a glow around the sun.
+ *
+ * The sky is shaded from its horizon color to its zenith color by how far up
+ * the view direction points, with everything under the horizon taking on a
+ * darker shade of the horizon color. The direction comes in through the
+ * following input, which the vertex stage has to provide:
+ * ```glsl
+ * in highp vec3 vs_sky_direction;
+ * ```
+ *
+ * Directions are in the same space as [`TimeOfDay::sun_direction()`]. The
+ * colors and the sun go into a uniform block bound to [`SKY_BINDING`], whose
+ * contents are laid out by [`SkyUniforms`].
+ *
+ * [`TimeOfDay::sun_direction()`]: TimeOfDay::sun_direction */
+pub const SKY_FRAGMENT_SHADER: ShaderSource<'static> =
+	ShaderSource::Glsl(Cow::Borrowed(include_str!("sky/frag.glsl")));
+
+/** Lowest color temperature of the sun, which it has at the horizon. */
+const HORIZON_TEMPERATURE: f32 = 1900.0;
+/** Highest color temperature of the sun, reached once it is high enough. */
+const ZENITH_TEMPERATURE: f32 = 6500.0;
+/** Elevation past which the sun stops getting any whiter, in radians. */
+const WHITE_ELEVATION: f32 = 0.6;
+
+/** Colors of the sky at the horizon and right overhead. */
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SkyColors {
+	/** Color of the sky at the horizon. */
+	pub horizon: [f32; 3],
+	/** Color of the sky right overhead. */
+	pub zenith: [f32; 3],
+}
+impl SkyColors {
+	/** Colors of the sky at night. */
+	pub const NIGHT: Self = Self {
+		horizon: [0.020, 0.030, 0.060],
+		zenith: [0.005, 0.010, 0.030],
+	};
+	/** Colors of the sky with the sun right at the horizon. */
+	pub const TWILIGHT: Self = Self {
+		horizon: [0.950, 0.500, 0.300],
+		zenith: [0.200, 0.250, 0.450],
+	};
+	/** Colors of the sky with the sun well above the horizon. */
+	pub const DAY: Self = Self {
+		horizon: [0.700, 0.820, 0.950],
+		zenith: [0.200, 0.450, 0.850],
+	};
+
+	/** Colors of the sky with the sun at the given elevation, in radians.
+	 * These go from night, once the sun is far enough below the horizon, to
+	 * twilight, right at the horizon, and then on to day. */
+	pub fn for_elevation(elevation: f32) -> Self {
+		let dusk = smoothstep(-0.2, 0.0, elevation);
+		let day = smoothstep(0.0, 0.4, elevation);
+
+		let mix = |a: [f32; 3], b: [f32; 3], c: [f32; 3]| {
+			let mut color = [0.0; 3];
+			for i in 0..3 {
+				let dusk = lerp(a[i], b[i], dusk);
+				color[i] = lerp(dusk, c[i], day);
+			}
+			color
+		};
+		Self {
+			horizon: mix(Self::NIGHT.horizon, Self::TWILIGHT.horizon, Self::DAY.horizon),
+			zenith: mix(Self::NIGHT.zenith, Self::TWILIGHT.zenith, Self::DAY.zenith),
+		}
+	}
+}
+
+/** Time of day at a given latitude, which the position and color of the sun
+ * and the colors of the sky are worked out from.
+ *
+ * This is a simple analytic model: the sun goes around once a day along the
+ * path it takes at the equinoxes, so its declination is always zero, and the
+ * atmosphere isn't simulated in any way, with the colors following simple
+ * ramps over the elevation of the sun instead.
+ *
+ * Directions are given in a space where `+X` points east, `+Y` points up and
+ * `+Z` points south, so a camera looking down `-Z` faces north and sees the
+ * sun rise on its right. */
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TimeOfDay {
+	/** Seconds since midnight, in the `[0; 86400)` range. */
+	pub seconds_of_day: f32,
+	/** Latitude of the observer, in radians, with positive values going
+	 * north of the equator. */
+	pub latitude: f32,
+}
+impl TimeOfDay {
+	/** Number of seconds in a day. */
+	pub const SECONDS_PER_DAY: f32 = 86400.0;
+
+	/** Creates a new time of day at the given hour, which may have a
+	 * fractional part, and latitude, in radians. */
+	pub fn from_hours(hours: f32, latitude: f32) -> Self {
+		let mut time = Self { seconds_of_day: 0.0, latitude };
+		time.advance(hours * 3600.0);
+		time
+	}
+
+	/** Moves the time of day along by the given number of seconds, which may
+	 * be negative, wrapping around midnight. */
+	pub fn advance(&mut self, seconds: f32) {
+		self.seconds_of_day = (self.seconds_of_day + seconds)
+			.rem_euclid(Self::SECONDS_PER_DAY);
+	}
+
+	/** Angle the sun has gone through since solar noon, in radians. This is
+	 * negative in the morning and positive in the afternoon. */
+	pub fn hour_angle(&self) -> f32 {
+		(self.seconds_of_day / Self::SECONDS_PER_DAY - 0.5) * 2.0 * PI
+	}
+
+	/** Unit vector pointing from the observer towards the sun. Lights cast by
+	 * the sun travel in the opposite direction. */
+	pub fn sun_direction(&self) -> [f32; 3] {
+		let hour = self.hour_angle();
+		let (sin_lat, cos_lat) = self.latitude.sin_cos();
+
+		/* With no declination, the sun goes around the celestial equator. */
+		let east = -hour.sin();
+		let up = cos_lat * hour.cos();
+		let north = -sin_lat * hour.cos();
+
+		[east, up, -north]
+	}
+
+	/** Angle of the sun above the horizon, in radians. This is negative at
+	 * night. */
+	pub fn sun_elevation(&self) -> f32 {
+		self.sun_direction()[1].clamp(-1.0, 1.0).asin()
+	}
+
+	/** Color temperature of the sun, in kelvin, which goes from a warm orange
+	 * at the horizon to white once the sun is high enough. */
+	pub fn sun_temperature(&self) -> f32 {
+		let t = smoothstep(0.0, WHITE_ELEVATION, self.sun_elevation());
+		lerp(HORIZON_TEMPERATURE, ZENITH_TEMPERATURE, t)
+	}
+
+	/** Color of the light of the sun, at full intensity. */
+	pub fn sun_color(&self) -> [f32; 3] {
+		kelvin_to_rgb(self.sun_temperature())
+	}
+
+	/** How strong the light of the sun is, from zero, once it has set, to one,
+	 * once it is clear of the horizon. */
+	pub fn sun_intensity(&self) -> f32 {
+		smoothstep(-0.1, 0.1, self.sun_elevation())
+	}
+
+	/** Colors of the sky at this time of day. */
+	pub fn sky_colors(&self) -> SkyColors {
+		SkyColors::for_elevation(self.sun_elevation())
+	}
+
+	/** Uniform data for [`SKY_FRAGMENT_SHADER`] at this time of day. */
+	pub fn uniforms(&self) -> SkyUniforms {
+		let sky = self.sky_colors();
+		let [r, g, b] = self.sun_color();
+		let [x, y, z] = self.sun_direction();
+		let [hr, hg, hb] = sky.horizon;
+		let [zr, zg, zb] = sky.zenith;
+
+		SkyUniforms {
+			horizon: [hr, hg, hb, 1.0],
+			zenith: [zr, zg, zb, 1.0],
+			sun_direction: [x, y, z, 0.0],
+			sun_color: [r, g, b, self.sun_intensity()],
+		}
+	}
+}
+
+/** Contents of the uniform block of [`SKY_FRAGMENT_SHADER`], laid out to match
+ * its `std140` declaration. */
+#[derive(Debug, Copy, Clone, PartialEq, bytemuck::Zeroable, bytemuck::Pod)]
+#[repr(C)]
+pub struct SkyUniforms {
+	/** Color of the sky at the horizon. The last component is unused. */
+	pub horizon: [f32; 4],
+	/** Color of the sky right overhead. The last component is unused. */
+	pub zenith: [f32; 4],
+	/** Direction towards the sun. The last component is unused. */
+	pub sun_direction: [f32; 4],
+	/** Color of the sun, followed by its intensity. */
+	pub sun_color: [f32; 4],
+}
+
+/** Approximate color of a black body at the given temperature, in kelvin, with
+ * every component in the `[0; 1]` range. This fits curves to the black body
+ * spectrum, and is only meant for temperatures between 1000K and 40000K. */
+pub fn kelvin_to_rgb(kelvin: f32) -> [f32; 3] {
+	let t = kelvin / 100.0;
+
+	let red = if t <= 66.0 {
+		255.0
+	} else {
+		329.698_73 * (t - 60.0).powf(-0.133_204_76)
+	};
+	let green = if t <= 66.0 {
+		99.470_8 * t.ln() - 161.119_57
+	} else {
+		288.122_17 * (t - 60.0).powf(-0.075_514_85)
+	};
+	let blue = if t >= 66.0 {
+		255.0
+	} else if t <= 19.0 {
+		0.0
+	} else {
+		138.517_73 * (t - 10.0).ln() - 305.044_8
+	};
+
+	[
+		(red / 255.0).clamp(0.0, 1.0),
+		(green / 255.0).clamp(0.0, 1.0),
+		(blue / 255.0).clamp(0.0, 1.0),
+	]
+}
+
+/** Linear interpolation between two values. */
+fn lerp(a: f32, b: f32, s: f32) -> f32 {
+	a + (b - a) * s
+}
+
+/** Smooth Hermite step between zero, at `a`, and one, at `b`, as in GLSL. */
+fn smoothstep(a: f32, b: f32, x: f32) -> f32 {
+	let t = ((x - a) / (b - a)).clamp(0.0, 1.0);
+	t * t * (3.0 - 2.0 * t)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const EPSILON: f32 = 1e-4;
+
+	fn close(a: [f32; 3], b: [f32; 3]) -> bool {
+		a.iter().zip(&b).all(|(a, b)| (a - b).abs() < EPSILON)
+	}
+
+	#[test]
+	fn noon_sun_is_overhead_at_the_equator() {
+		let noon = TimeOfDay::from_hours(12.0, 0.0);
+		assert!(close(noon.sun_direction(), [0.0, 1.0, 0.0]));
+		assert!((noon.sun_elevation() - PI / 2.0).abs() < EPSILON);
+
+		/* The sun is white, and as strong as it gets. */
+		let [r, g, b] = noon.sun_color();
+		assert!(r > 0.95 && g > 0.95 && b > 0.95);
+		assert!((noon.sun_intensity() - 1.0).abs() < EPSILON);
+	}
+
+	#[test]
+	fn sun_rises_in_the_east_and_sets_in_the_west() {
+		let sunrise = TimeOfDay::from_hours(6.0, 0.0);
+		let sunset = TimeOfDay::from_hours(18.0, 0.0);
+		let midnight = TimeOfDay::from_hours(0.0, 0.0);
+
+		assert!(close(sunrise.sun_direction(), [1.0, 0.0, 0.0]));
+		assert!(close(sunset.sun_direction(), [-1.0, 0.0, 0.0]));
+		assert!(close(midnight.sun_direction(), [0.0, -1.0, 0.0]));
+		assert!(midnight.sun_intensity() < EPSILON);
+	}
+
+	#[test]
+	fn noon_sun_is_to_the_south_up_north() {
+		let latitude = PI / 4.0;
+		let noon = TimeOfDay::from_hours(12.0, latitude);
+		let [east, up, south] = noon.sun_direction();
+
+		assert!(east.abs() < EPSILON);
+		assert!((up - latitude.cos()).abs() < EPSILON);
+		assert!((south - latitude.sin()).abs() < EPSILON);
+		assert!((noon.sun_elevation() - PI / 4.0).abs() < EPSILON);
+	}
+
+	#[test]
+	fn sunset_is_warm() {
+		let sunset = TimeOfDay::from_hours(18.0, 0.0);
+		assert!(sunset.sun_elevation().abs() < EPSILON);
+		assert!((sunset.sun_temperature() - HORIZON_TEMPERATURE).abs() < 1.0);
+
+		let [r, g, b] = sunset.sun_color();
+		assert!(r > g && g > b);
+		assert!(b < 0.2);
+
+		let sky = sunset.sky_colors();
+		assert!(close(sky.horizon, SkyColors::TWILIGHT.horizon));
+	}
+
+	#[test]
+	fn time_wraps_around_midnight() {
+		let mut time = TimeOfDay::from_hours(23.0, 0.0);
+		time.advance(2.0 * 3600.0);
+		assert!((time.seconds_of_day - 3600.0).abs() < 0.1);
+
+		time.advance(-2.0 * 3600.0);
+		assert!((time.seconds_of_day - 23.0 * 3600.0).abs() < 0.1);
+	}
+
+	#[test]
+	fn kelvin_ramp_matches_known_values() {
+		/* Daylight is close to white. */
+		let [r, g, b] = kelvin_to_rgb(6500.0);
+		assert_eq!(r, 1.0);
+		assert!(g > 0.97 && b > 0.97);
+
+		let [r, g, b] = kelvin_to_rgb(2000.0);
+		assert_eq!(r, 1.0);
+		assert!((g - 0.537).abs() < 0.01);
+		assert!((b - 0.055).abs() < 0.01);
+	}
+}
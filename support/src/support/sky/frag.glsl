@@ -0,0 +1,34 @@
+#version 300 es
+precision highp float;
+
+layout(std140) uniform rc_sky
+{
+/* Color of the sky at the horizon. */
+    vec4 Horizon;
+/* Color of the sky right overhead. */
+    vec4 Zenith;
+/* Direction towards the sun. */
+    vec4 SunDirection;
+/* Color of the sun, and its intensity. */
+    vec4 SunColor;
+};
+
+in highp vec3 vs_sky_direction;
+
+out vec4 color;
+void main()
+{
+    vec3 direction = normalize(vs_sky_direction);
+
+    /* Shade from the horizon up to the zenith, darkening below the horizon. */
+    float height = direction.y;
+    vec3  sky    = mix(Horizon.rgb, Zenith.rgb, sqrt(clamp(height, 0.0, 1.0)));
+    sky *= mix(0.35, 1.0, smoothstep(-0.25, 0.0, height));
+
+    /* Glow around the sun, with a sharp disk in the middle of it. */
+    float facing = max(dot(direction, normalize(SunDirection.xyz)), 0.0);
+    float glow   = pow(facing, 32.0) * 0.5 + smoothstep(0.9995, 0.9998, facing);
+    sky += SunColor.rgb * SunColor.a * glow;
+
+    color = vec4(sky, 1.0);
+}
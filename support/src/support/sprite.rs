@@ -0,0 +1,537 @@
+use crate::support::Matrix4;
+use gavle::*;
+use std::borrow::Cow;
+use std::convert::TryFrom;
+use std::ops::Range;
+use std::rc::Rc;
+
+/** Name of the binding of the sprite texture in the sprite shaders. */
+const SPRITE_TEXTURE_BINDING: &str = "tt_sprite";
+
+/** Name of the binding of the uniform block with the camera matrix. */
+const SPRITE_GLOBALS_BINDING: &str = "rc_sprite_globals";
+
+/** Smallest size of the vertex buffer, in bytes, so that drawing a handful of
+ * sprites doesn't have the buffer grow over and over again. */
+const MIN_SPRITE_BUFFER: u32 = 16 * 1024;
+
+/** A rectangular region of a texture, such as a single frame in a sprite
+ * sheet, which may be drawn as a sprite.
+ *
+ * The texture is shared through an [`Rc`], so that regions may be kept around
+ * and queued up frame after frame without borrowing from anything. */
+#[derive(Debug, Clone)]
+pub struct TextureRegion {
+	/** Texture the region is in. */
+	texture: Rc<Texture>,
+	/** Offset and size of the region, in texels, with rows counted in the
+	 * order they were uploaded in. */
+	texels: [u32; 4],
+}
+impl TextureRegion {
+	/** The region covering the given texture as a whole. */
+	pub fn whole(texture: Rc<Texture>) -> Self {
+		let (width, height) = texture_size(&texture);
+		Self {
+			texture,
+			texels: [0, 0, width, height]
+		}
+	}
+
+	/** The region of the given size, in texels, at the given offset from the
+	 * first texel of the first row of the given texture.
+	 *
+	 * # Panics
+	 * This function panics if the region does not fit in the texture. */
+	pub fn new(texture: Rc<Texture>, x: u32, y: u32, width: u32, height: u32) -> Self {
+		let (texture_width, texture_height) = texture_size(&texture);
+		let fits = x.checked_add(width).map(|x| x <= texture_width).unwrap_or(false)
+			&& y.checked_add(height).map(|y| y <= texture_height).unwrap_or(false);
+		if !fits {
+			panic!("the region of {}x{} texels at ({}, {}) does not fit in a \
+				texture of {}x{} texels", width, height, x, y,
+				texture_width, texture_height)
+		}
+
+		Self {
+			texture,
+			texels: [x, y, width, height]
+		}
+	}
+
+	/** The texture this region is in. */
+	pub fn texture(&self) -> &Rc<Texture> {
+		&self.texture
+	}
+
+	/** Width of this region, in texels. */
+	pub fn width(&self) -> u32 {
+		self.texels[2]
+	}
+
+	/** Height of this region, in texels. */
+	pub fn height(&self) -> u32 {
+		self.texels[3]
+	}
+
+	/** Coordinates of the first and last corners of this region in its
+	 * texture. */
+	fn uv(&self) -> [f32; 4] {
+		let (width, height) = texture_size(&self.texture);
+		let (width, height) = (width.max(1) as f32, height.max(1) as f32);
+		let [x, y, w, h] = self.texels;
+		[
+			x as f32 / width,
+			y as f32 / height,
+			(x + w) as f32 / width,
+			(y + h) as f32 / height
+		]
+	}
+}
+
+/** Width and height of the base level of the given texture. */
+fn texture_size(texture: &Texture) -> (u32, u32) {
+	match texture.extent() {
+		TextureExtent::D1 { length } => (length, 1),
+		TextureExtent::D2 { width, height } => (width, height),
+		TextureExtent::D2Array { width, height, .. } => (width, height),
+		TextureExtent::D3 { width, height, .. } => (width, height),
+	}
+}
+
+/** Layout of a single vertex of the sprite vertex buffer. */
+#[derive(Debug, Copy, Clone, PartialEq, bytemuck::Zeroable, bytemuck::Pod)]
+#[repr(C)]
+struct SpriteVertex {
+	/** Position in the space of the camera. */
+	position: [f32; 2],
+	/** Coordinates in the texture. */
+	uv: [f32; 2],
+	/** Tint of the sprite, with its alpha premultiplied. */
+	color: [u8; 4],
+}
+
+/** Layout of the uniform block of the sprite shaders. */
+#[derive(Debug, Copy, Clone, PartialEq, bytemuck::Zeroable, bytemuck::Pod)]
+#[repr(C)]
+struct SpriteGlobals {
+	view_projection: Matrix4,
+}
+
+/** A sprite queued up to be drawn. */
+#[derive(Debug, Clone)]
+struct QueuedSprite {
+	region: TextureRegion,
+	position: [f32; 2],
+	rotation: f32,
+	scale: [f32; 2],
+	color: [u8; 4],
+}
+
+/** Converts a color with straight alpha to a premultiplied one, with eight
+ * bits for every channel. */
+fn premultiply(color: [f32; 4]) -> [u8; 4] {
+	let alpha = color[3].clamp(0.0, 1.0);
+	let channel = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+	[
+		channel(color[0] * alpha),
+		channel(color[1] * alpha),
+		channel(color[2] * alpha),
+		channel(alpha)
+	]
+}
+
+/** The two triangles of a sprite of the given size, in the space of the
+ * camera, centered at the given position and rotated around it by the given
+ * angle, in radians, from the positive `x` axis towards the positive `y`
+ * axis. */
+fn quad(
+	uv: [f32; 4],
+	size: [f32; 2],
+	position: [f32; 2],
+	rotation: f32,
+	color: [u8; 4]) -> [SpriteVertex; 6] {
+
+	let (sin, cos) = rotation.sin_cos();
+	let (hw, hh) = (size[0] / 2.0, size[1] / 2.0);
+	let corner = |x: f32, y: f32, u: f32, v: f32| SpriteVertex {
+		position: [
+			position[0] + x * cos - y * sin,
+			position[1] + x * sin + y * cos
+		],
+		uv: [u, v],
+		color
+	};
+
+	let [u0, v0, u1, v1] = uv;
+	let first = corner(-hw, -hh, u0, v0);
+	let second = corner(hw, -hh, u1, v0);
+	let third = corner(hw, hh, u1, v1);
+	let fourth = corner(-hw, hh, u0, v1);
+	[first, second, third, first, third, fourth]
+}
+
+/** Splits a list of keys, sorted so that equal keys are next to one another,
+ * into the ranges of sprites sharing a key, in vertices. */
+fn runs(keys: &[u64]) -> Vec<(u64, Range<u32>)> {
+	let mut runs: Vec<(u64, Range<u32>)> = Vec::new();
+	for (index, key) in keys.iter().enumerate() {
+		let start = u32::try_from(index * 6).unwrap();
+		match runs.last_mut() {
+			Some((last, range)) if last == key => range.end = start + 6,
+			_ => runs.push((*key, start..start + 6))
+		}
+	}
+	runs
+}
+
+/** A run of sprites sharing the same texture, drawn in a single draw call. */
+struct SpriteRun {
+	/** Binding group of the texture of the run and the camera. */
+	bind: UniformGroup,
+	/** Range of vertices of the run. */
+	vertices: Range<u32>,
+}
+
+/** Helper for drawing large amounts of textured quads in two dimensions, such
+ * as the sprites, particles and backgrounds of simple 2D content.
+ *
+ * Every frame, sprites get queued up between a call to [`begin()`] and one to
+ * [`end()`], which sorts them by texture, lays them out and uploads them to a
+ * vertex buffer that persists between frames. Then, [`render()`] draws them
+ * with a single draw call for every texture in the batch, all through the same
+ * pipeline.
+ *
+ * Unless a camera matrix is given to [`set_camera()`], positions and sizes
+ * are in physical pixels, from the top left corner of the viewport. Sprites
+ * get drawn in order of their textures, and only sprites sharing a texture get
+ * drawn in the order they were queued in, so sprites that overlap and must
+ * come out in a particular order should come from the same texture, such as
+ * an atlas.
+ *
+ * Blending is done with premultiplied alpha, so the textures of the sprites
+ * must have their alpha premultiplied too. The tint colors given to
+ * [`draw()`] have straight alpha, and are premultiplied by the batch.
+ *
+ * [`begin()`]: Self::begin
+ * [`draw()`]: Self::draw
+ * [`end()`]: Self::end
+ * [`render()`]: Self::render
+ * [`set_camera()`]: Self::set_camera */
+pub struct SpriteBatch {
+	/** Premultiplied alpha blended sprite pipeline. */
+	pipeline: RenderPipeline,
+	/** Uniform buffer holding the camera matrix. */
+	globals: UniformBuffer,
+	/** Vertex buffer holding the sprites of the batch, once there have been
+	 * any. */
+	vertices: Option<VertexBuffer>,
+	/** Runs of sprites sharing a texture prepared for the next draw. */
+	runs: Vec<SpriteRun>,
+	/** Viewport the sprites were prepared for. */
+	viewport: Viewport,
+	/** Camera matrix used in place of the pixel space one, if any. */
+	camera: Option<Matrix4>,
+	/** Sprites queued up since the last call to [`begin()`].
+	 *
+	 * [`begin()`]: Self::begin */
+	queue: Vec<QueuedSprite>,
+	/** Vertices of the last batch, kept around for their storage. */
+	staging: Vec<SpriteVertex>,
+}
+impl SpriteBatch {
+	/** Layout of the sprite vertex buffer. */
+	const LAYOUT: VertexBufferLayout<'static> = VertexBufferLayout {
+		array_stride: 20,
+		attributes: &[
+			VertexAttribute {
+				kind: VertexType::F32,
+				components: VertexComponents::Two,
+				offset: 0,
+				binding: Cow::Borrowed("tt_vert_position"),
+				location: None
+			},
+			VertexAttribute {
+				kind: VertexType::F32,
+				components: VertexComponents::Two,
+				offset: 8,
+				binding: Cow::Borrowed("tt_vert_texture"),
+				location: None
+			},
+			VertexAttribute {
+				kind: VertexType::Unorm8,
+				components: VertexComponents::Four,
+				offset: 16,
+				binding: Cow::Borrowed("tt_vert_color"),
+				location: None
+			},
+		]
+	};
+
+	/** Create a new sprite batch on the given device. */
+	pub fn new(device: &Device) -> Result<Self, SpriteBatchError> {
+		let globals = device.create_uniform_buffer(
+			&BufferDescriptor {
+				size: u32::try_from(std::mem::size_of::<SpriteGlobals>()).unwrap(),
+				profile: BufferProfile::DynamicUpload,
+				contents: BufferContents::F32,
+				..Default::default()
+			})?;
+
+		let vertex = device.create_vertex_shader(
+			ShaderSource::Glsl(Cow::Borrowed(include_str!("sprite/vert.glsl"))))?;
+		let fragment = device.create_fragment_shader(
+			ShaderSource::Glsl(Cow::Borrowed(include_str!("sprite/frag.glsl"))))?;
+
+		let premultiplied = BlendState {
+			src_factor: BlendFactor::One,
+			dst_factor: BlendFactor::OneMinusSrcAlpha,
+			operation: BlendOperation::Add
+		};
+		let pipeline = device.create_render_pipeline(
+			&RenderPipelineDescriptor::new(&vertex, &Self::LAYOUT)
+				.with_fragment_state(FragmentState::new(&fragment)
+					.with_targets(&[ColorTargetState {
+						alpha_blend: premultiplied,
+						color_blend: premultiplied,
+						write_mask: ColorWrite::all(),
+					}]))
+				.with_depth(DepthStencilState::DISABLED))?;
+		pipeline.set_label("sprite batch");
+
+		Ok(Self {
+			pipeline,
+			globals,
+			vertices: None,
+			runs: Vec::new(),
+			viewport: Viewport { x: 0, y: 0, width: 0, height: 0 },
+			camera: None,
+			queue: Vec::new(),
+			staging: Vec::new()
+		})
+	}
+
+	/** Use the given matrix to take sprites to clip space, such as an
+	 * orthographic projection combined with the transformation of a 2D
+	 * camera, or go back to positioning sprites in pixels, if none is given.
+	 * This takes effect with the next call to [`end()`].
+	 *
+	 * [`end()`]: Self::end */
+	pub fn set_camera(&mut self, camera: Option<Matrix4>) {
+		self.camera = camera
+	}
+
+	/** Start a new batch, dropping whatever was queued up since the last call
+	 * to [`end()`].
+	 *
+	 * [`end()`]: Self::end */
+	pub fn begin(&mut self) {
+		self.queue.clear()
+	}
+
+	/** Queue the given region up to be drawn as a sprite centered at the given
+	 * position, rotated around it by the given angle, in radians, and tinted
+	 * with the given color, with straight alpha. At a scale of one, sprites are
+	 * as large as their regions are in texels. */
+	pub fn draw(
+		&mut self,
+		region: &TextureRegion,
+		position: [f32; 2],
+		rotation: f32,
+		scale: [f32; 2],
+		color: [f32; 4]) {
+
+		self.queue.push(QueuedSprite {
+			region: region.clone(),
+			position,
+			rotation,
+			scale,
+			color: premultiply(color)
+		})
+	}
+
+	/** Finish the batch, sorting the queued sprites by texture and uploading
+	 * them for the next call to [`render()`], over the given viewport.
+	 *
+	 * This must be called outside of a render pass, as it needs to write to
+	 * the buffers of the batch.
+	 *
+	 * [`render()`]: Self::render */
+	pub fn end(&mut self, device: &Device, viewport: Viewport) -> Result<(), SpriteBatchError> {
+		self.viewport = viewport;
+		self.runs.clear();
+
+		/* Stable, so that sprites sharing a texture keep their order. */
+		self.queue.sort_by_key(|sprite| sprite.region.texture.id());
+
+		self.staging.clear();
+		for sprite in &self.queue {
+			let size = [
+				sprite.region.width() as f32 * sprite.scale[0],
+				sprite.region.height() as f32 * sprite.scale[1]
+			];
+			self.staging.extend_from_slice(&quad(
+				sprite.region.uv(),
+				size,
+				sprite.position,
+				sprite.rotation,
+				sprite.color));
+		}
+
+		let view_projection = self.camera.unwrap_or_else(|| {
+			Matrix4::orthographic_projection(
+				0.0, viewport.width as f32,
+				0.0, viewport.height as f32,
+				-1.0, 1.0)
+		});
+		let globals = SpriteGlobals {
+			view_projection: view_projection.transpose()
+		};
+		self.globals.write(0, bytemuck::bytes_of(&globals))?;
+
+		let len = buffer_size_of::<SpriteVertex>(self.staging.len())?;
+		let stale = match &self.vertices {
+			Some(vertices) => vertices.len() < len,
+			None => true
+		};
+		if stale && len > 0 {
+			let size = len.checked_next_power_of_two()
+				.unwrap_or(len)
+				.max(MIN_SPRITE_BUFFER);
+			let vertices = device.create_vertex_buffer(
+				&BufferDescriptor {
+					size,
+					profile: BufferProfile::DynamicUpload,
+					contents: BufferContents::Opaque,
+					..Default::default()
+				})?;
+			vertices.set_label("sprite batch");
+			self.vertices = Some(vertices);
+		}
+		if let Some(vertices) = &self.vertices {
+			vertices.write(0, bytemuck::cast_slice(&self.staging[..]))?;
+		}
+
+		let keys = self.queue.iter()
+			.map(|sprite| sprite.region.texture.id())
+			.collect::<Vec<_>>();
+		let mut first = 0;
+		for (_, vertices) in runs(&keys) {
+			let texture = &self.queue[first].region.texture;
+			first += vertices.len() / 6;
+
+			let bind = device.create_uniform_bind_group(
+				&UniformGroupDescriptor {
+					entries: &[
+						UniformGroupEntry {
+							binding: SPRITE_GLOBALS_BINDING.into(),
+							kind: UniformBind::Buffer {
+								buffer: &self.globals,
+								offset: 0,
+								size: None
+							}
+						},
+						UniformGroupEntry {
+							binding: SPRITE_TEXTURE_BINDING.into(),
+							kind: UniformBind::Texture {
+								texture,
+								far: TextureFilter::Linear,
+								near: TextureFilter::Linear,
+								address_mode: AddressMode::ClampToEdge,
+								anisotropy_clamp: None,
+								sampler: None
+							}
+						},
+					]
+				})?;
+			self.runs.push(SpriteRun { bind, vertices });
+		}
+		self.queue.clear();
+
+		Ok(())
+	}
+
+	/** Number of draw calls [`render()`] will issue, which is the number of
+	 * distinct textures in the last batch.
+	 *
+	 * [`render()`]: Self::render */
+	pub fn draw_calls(&self) -> usize {
+		self.runs.len()
+	}
+
+	/** Draw the sprites uploaded by the last call to [`end()`] in the given
+	 * pass, over the viewport they were prepared for.
+	 *
+	 * This switches the pass over to the sprite pipeline, vertex buffer and
+	 * binding groups, which stay set once this returns, so whatever gets drawn
+	 * in the pass afterwards has to set its own again.
+	 *
+	 * [`end()`]: Self::end */
+	pub fn render<'a>(&'a self, pass: &mut RenderPass<'a>) {
+		let vertices = match &self.vertices {
+			Some(vertices) if !self.runs.is_empty() => vertices,
+			_ => return
+		};
+
+		pass.set_pipeline(&self.pipeline);
+		pass.set_viewport(self.viewport)
+			.expect("the viewport must fit in the limits of the context");
+		pass.set_vertex_buffer(vertices);
+		for run in &self.runs {
+			pass.set_bind_group(&run.bind);
+			pass.draw(run.vertices.clone(), 1)
+				.expect("the sprite vertex buffer always holds the batch");
+		}
+	}
+}
+
+/** Errors that may happen while creating or preparing a sprite batch. */
+#[derive(Debug, thiserror::Error)]
+pub enum SpriteBatchError {
+	#[error("could not create sprite buffers: {0}")]
+	Buffer(#[from] BufferError),
+	#[error("could not create sprite shaders: {0}")]
+	Shader(#[from] ShaderError),
+	#[error("could not create sprite pipeline: {0}")]
+	Pipeline(#[from] RenderPipelineError),
+	#[error("could not bind a sprite texture: {0}")]
+	BindGroup(#[from] BindGroupError),
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn quads_are_centered_and_rotated() {
+		let color = premultiply([1.0, 1.0, 1.0, 0.5]);
+		assert_eq!(color, [128, 128, 128, 128]);
+
+		let vertices = quad([0.0, 0.0, 1.0, 1.0], [4.0, 2.0], [10.0, 20.0], 0.0, color);
+		assert_eq!(vertices[0].position, [8.0, 19.0]);
+		assert_eq!(vertices[2].position, [12.0, 21.0]);
+		assert_eq!(vertices[0], vertices[3]);
+		assert_eq!(vertices[2], vertices[4]);
+		assert_eq!(vertices[5].uv, [0.0, 1.0]);
+
+		/* A quarter turn takes the x axis over to the y axis. */
+		let vertices = quad(
+			[0.0, 0.0, 1.0, 1.0],
+			[4.0, 2.0],
+			[0.0, 0.0],
+			std::f32::consts::FRAC_PI_2,
+			color);
+		let [x, y] = vertices[1].position;
+		assert!((x - 1.0).abs() < 1e-5 && (y - 2.0).abs() < 1e-5, "{:?}", [x, y]);
+	}
+
+	#[test]
+	fn runs_follow_textures() {
+		assert!(runs(&[]).is_empty());
+		assert_eq!(runs(&[3, 3, 5, 7, 7, 7]), vec![
+			(3, 0..12),
+			(5, 12..18),
+			(7, 18..36)
+		]);
+	}
+}
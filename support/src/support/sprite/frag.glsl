@@ -0,0 +1,15 @@
+#version 300 es
+precision highp float;
+
+uniform highp sampler2D tt_sprite;
+
+in vec2 vs_uv;
+in vec4 vs_color;
+
+out vec4 color;
+
+/* Both the texture and the tint have their alpha premultiplied already. */
+void main()
+{
+    color = texture(tt_sprite, vs_uv) * vs_color;
+}
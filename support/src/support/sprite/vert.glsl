@@ -0,0 +1,21 @@
+#version 300 es
+precision highp float;
+
+layout(std140) uniform rc_sprite_globals
+{
+    mat4 ViewProjection;
+};
+
+in vec2 tt_vert_position;
+in vec2 tt_vert_texture;
+in vec4 tt_vert_color;
+
+out vec2 vs_uv;
+out vec4 vs_color;
+
+void main()
+{
+    vs_uv    = tt_vert_texture;
+    vs_color = tt_vert_color;
+    gl_Position = ViewProjection * vec4(tt_vert_position, 0.0, 1.0);
+}
@@ -0,0 +1,633 @@
+use gavle::*;
+use std::borrow::Cow;
+use std::convert::TryFrom;
+
+/** Monospace font the glyph atlas gets baked from, which is DejaVu Sans Mono,
+ * distributed under the license next to it. */
+const TEXT_FONT: &[u8] = include_bytes!("text/DejaVuSansMono.ttf");
+
+/** Name of the binding of the glyph atlas in the text shaders. */
+const TEXT_ATLAS_BINDING: &str = "tt_glyphs";
+
+/** Size of the font at a text scale of one, in logical pixels. */
+pub const TEXT_BASE_SIZE: f32 = 16.0;
+
+/** First and last of the characters baked into the atlas, which cover all of
+ * printable ASCII. Any other character gets drawn as `TEXT_FALLBACK`. */
+const TEXT_FIRST: char = ' ';
+const TEXT_LAST: char = '~';
+
+/** Character drawn in place of the ones missing from the atlas. */
+const TEXT_FALLBACK: char = '?';
+
+/** Width of the atlas, in texels. Glyphs get packed into rows as wide as
+ * this, and the atlas grows as tall as it needs to. */
+const ATLAS_WIDTH: u32 = 512;
+
+/** Empty texels left around every glyph in the atlas, so that filtering does
+ * not bleed its neighbors into it. */
+const ATLAS_PADDING: u32 = 1;
+
+/** Smallest size of the vertex buffer, in bytes, so that drawing a few lines
+ * of text doesn't have the buffer grow over and over again. */
+const MIN_TEXT_BUFFER: u32 = 16 * 1024;
+
+/** Placement of a single glyph, both in the atlas and relative to the pen. */
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Glyph {
+	/** Coordinates of the top left and bottom right corners of the glyph in
+	 * the atlas. */
+	pub uv: [f32; 4],
+	/** Width and height of the glyph, in texels of the atlas. */
+	pub size: [f32; 2],
+	/** Offset of the left edge of the glyph from the pen, and of its bottom
+	 * edge above the baseline, in texels of the atlas. */
+	pub offset: [f32; 2],
+	/** How far the pen moves after the glyph, in texels of the atlas. */
+	pub advance: f32,
+}
+
+/** Metrics of the glyphs of a font baked into an atlas, at the size it was
+ * baked at. */
+#[derive(Debug, Clone, PartialEq)]
+pub struct FontMetrics {
+	/** Glyphs of every character from `TEXT_FIRST` to `TEXT_LAST`. */
+	glyphs: Vec<Glyph>,
+	/** Height of the font above the baseline. */
+	ascent: f32,
+	/** Distance between the baselines of two lines. */
+	line_height: f32,
+}
+impl FontMetrics {
+	/** The glyph of the given character, or the fallback glyph, if it is not
+	 * in the atlas. */
+	pub fn glyph(&self, c: char) -> &Glyph {
+		let index = |c: char| (c as usize).wrapping_sub(TEXT_FIRST as usize);
+		self.glyphs.get(index(c))
+			.unwrap_or(&self.glyphs[index(TEXT_FALLBACK)])
+	}
+
+	/** Height of the font above the baseline. */
+	pub fn ascent(&self) -> f32 {
+		self.ascent
+	}
+
+	/** Distance between the baselines of two lines. */
+	pub fn line_height(&self) -> f32 {
+		self.line_height
+	}
+}
+
+/** Glyphs of a font rasterized into an image, before it goes into a texture. */
+struct BakedAtlas {
+	/** Width of the image, in texels. */
+	width: u32,
+	/** Height of the image, in texels. */
+	height: u32,
+	/** Texels of the image in RGBA, white, with the coverage in the alpha
+	 * channel, from the top row to the bottom one. */
+	texels: Vec<u8>,
+	/** Placement of every glyph in the image. */
+	metrics: FontMetrics,
+}
+
+/** Rasterizes the printable ASCII glyphs of the given font at the given size,
+ * in pixels, packing them row by row into an atlas. */
+fn bake(font: &fontdue::Font, size: f32) -> BakedAtlas {
+	let rasterized = (TEXT_FIRST..=TEXT_LAST)
+		.map(|c| font.rasterize(c, size))
+		.collect::<Vec<_>>();
+
+	/* Pack the glyphs into shelves, in order, starting a new shelf whenever a
+	 * glyph doesn't fit in what's left of the current one. */
+	let mut origins = Vec::with_capacity(rasterized.len());
+	let (mut x, mut y, mut shelf) = (ATLAS_PADDING, ATLAS_PADDING, 0);
+	for (metrics, _) in &rasterized {
+		let (width, height) = (metrics.width as u32, metrics.height as u32);
+		if x + width + ATLAS_PADDING > ATLAS_WIDTH {
+			x = ATLAS_PADDING;
+			y += shelf + ATLAS_PADDING;
+			shelf = 0;
+		}
+		origins.push((x, y));
+		x += width + ATLAS_PADDING;
+		shelf = shelf.max(height);
+	}
+	let height = y + shelf + ATLAS_PADDING;
+
+	let mut texels = vec![0; (ATLAS_WIDTH * height * 4) as usize];
+	for texel in texels.chunks_exact_mut(4) {
+		texel[..3].copy_from_slice(&[u8::MAX; 3]);
+	}
+
+	let (width_f, height_f) = (ATLAS_WIDTH as f32, height as f32);
+	let mut glyphs = Vec::with_capacity(rasterized.len());
+	for ((metrics, coverage), (x, y)) in rasterized.iter().zip(origins) {
+		for (row, coverage) in coverage.chunks(metrics.width.max(1)).enumerate() {
+			for (column, coverage) in coverage.iter().enumerate() {
+				let texel = ((y as usize + row) * ATLAS_WIDTH as usize + x as usize + column) * 4;
+				texels[texel + 3] = *coverage;
+			}
+		}
+
+		let (width, height) = (metrics.width as f32, metrics.height as f32);
+		glyphs.push(Glyph {
+			uv: [
+				x as f32 / width_f,
+				y as f32 / height_f,
+				(x as f32 + width) / width_f,
+				(y as f32 + height) / height_f
+			],
+			size: [width, height],
+			offset: [metrics.xmin as f32, metrics.ymin as f32],
+			advance: metrics.advance_width
+		});
+	}
+
+	let (ascent, line_height) = match font.horizontal_line_metrics(size) {
+		Some(line) => (line.ascent, line.new_line_size),
+		None => (size, size * 1.2)
+	};
+
+	BakedAtlas {
+		width: ATLAS_WIDTH,
+		height,
+		texels,
+		metrics: FontMetrics {
+			glyphs,
+			ascent,
+			line_height
+		}
+	}
+}
+
+/** A string queued up to be drawn. */
+#[derive(Debug, Clone, PartialEq)]
+struct QueuedText {
+	/** The string itself. */
+	text: String,
+	/** Position of the top left corner of the first line, in logical pixels
+	 * from the top left corner of the viewport. */
+	position: [f32; 2],
+	/** Size of the text relative to [`TEXT_BASE_SIZE`]. */
+	scale: f32,
+	/** Color of the text, with eight bits for every channel. */
+	color: [u8; 4],
+}
+
+/** Layout of a single vertex of the text vertex buffer. */
+#[derive(Debug, Copy, Clone, PartialEq, bytemuck::Zeroable, bytemuck::Pod)]
+#[repr(C)]
+struct TextVertex {
+	/** Position in normalized device coordinates. */
+	position: [f32; 2],
+	/** Coordinates in the atlas. */
+	uv: [f32; 2],
+	/** Color of the text. */
+	color: [u8; 4],
+}
+
+/** Lays the given text out into quads of two triangles each, in normalized
+ * device coordinates for a viewport of the given size, in physical pixels.
+ *
+ * Positions and sizes in logical pixels get multiplied by the scale factor,
+ * and the glyphs, which were baked for that scale factor, get drawn texel for
+ * texel at a text scale of one. Their corners are snapped to whole pixels, so
+ * that they come out crisp. */
+fn layout(
+	metrics: &FontMetrics,
+	text: &QueuedText,
+	scale_factor: f32,
+	viewport: [f32; 2],
+	vertices: &mut Vec<TextVertex>) {
+
+	let to_ndc = |x: f32, y: f32| [
+		x / viewport[0] * 2.0 - 1.0,
+		1.0 - y / viewport[1] * 2.0
+	];
+
+	let scale = text.scale;
+	let left = text.position[0] * scale_factor;
+	let mut pen = left;
+	let mut baseline = text.position[1] * scale_factor + metrics.ascent * scale;
+	for c in text.text.chars() {
+		if c == '\n' {
+			pen = left;
+			baseline += metrics.line_height * scale;
+			continue
+		}
+
+		let glyph = metrics.glyph(c);
+		if glyph.size[0] > 0.0 && glyph.size[1] > 0.0 {
+			let x0 = (pen + glyph.offset[0] * scale).round();
+			let y1 = (baseline - glyph.offset[1] * scale).round();
+			let x1 = x0 + glyph.size[0] * scale;
+			let y0 = y1 - glyph.size[1] * scale;
+
+			let [u0, v0, u1, v1] = glyph.uv;
+			let corner = |x: f32, y: f32, u: f32, v: f32| TextVertex {
+				position: to_ndc(x, y),
+				uv: [u, v],
+				color: text.color
+			};
+			let (top_left, top_right) = (corner(x0, y0, u0, v0), corner(x1, y0, u1, v0));
+			let (bottom_left, bottom_right) = (corner(x0, y1, u0, v1), corner(x1, y1, u1, v1));
+			vertices.extend_from_slice(&[
+				bottom_left, bottom_right, top_right,
+				bottom_left, top_right, top_left
+			]);
+		}
+		pen += glyph.advance * scale;
+	}
+}
+
+/** Converts a color with floating point channels to eight bits per channel. */
+fn unorm8(color: [f32; 4]) -> [u8; 4] {
+	let channel = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+	[channel(color[0]), channel(color[1]), channel(color[2]), channel(color[3])]
+}
+
+/** Helper for drawing text over the screen, such as frame rate counters and
+ * debugging overlays.
+ *
+ * Strings get queued up with [`queue()`], in logical pixels from the top left
+ * corner of the viewport, laid out and uploaded all at once with
+ * [`prepare()`], and drawn in a single draw call with [`draw()`]. Only
+ * printable ASCII is supported, with every other character drawn as a
+ * question mark, and newlines starting a new line.
+ *
+ * The glyphs get baked for the scale factor of the window, which turns the
+ * logical pixels text gets queued in into physical ones, so that text stays
+ * crisp and keeps its size on high density displays. When the scale factor
+ * changes, it should be passed on to [`set_scale_factor()`].
+ *
+ * [`queue()`]: Self::queue
+ * [`prepare()`]: Self::prepare
+ * [`draw()`]: Self::draw
+ * [`set_scale_factor()`]: Self::set_scale_factor */
+pub struct TextRenderer {
+	/** Font the atlas gets baked from. */
+	font: fontdue::Font,
+	/** Metrics of the glyphs in the atlas. */
+	metrics: FontMetrics,
+	/** Texture holding the atlas. */
+	atlas: Texture,
+	/** Binding group of the atlas. */
+	bind: UniformGroup,
+	/** Alpha blended text pipeline. */
+	pipeline: RenderPipeline,
+	/** Vertex buffer holding the prepared text, once there has been any. */
+	vertices: Option<VertexBuffer>,
+	/** Number of vertices prepared for the next draw. */
+	count: u32,
+	/** Viewport the text was prepared for. */
+	viewport: Viewport,
+	/** Strings queued up for the next call to [`prepare()`].
+	 *
+	 * [`prepare()`]: Self::prepare */
+	queue: Vec<QueuedText>,
+	/** Vertices of the last prepared text, kept around for their storage. */
+	staging: Vec<TextVertex>,
+	/** Ratio between physical and logical pixels. */
+	scale_factor: f32,
+}
+impl TextRenderer {
+	/** Layout of the text vertex buffer. */
+	const LAYOUT: VertexBufferLayout<'static> = VertexBufferLayout {
+		array_stride: 20,
+		attributes: &[
+			VertexAttribute {
+				kind: VertexType::F32,
+				components: VertexComponents::Two,
+				offset: 0,
+				binding: Cow::Borrowed("tt_vert_position"),
+				location: None
+			},
+			VertexAttribute {
+				kind: VertexType::F32,
+				components: VertexComponents::Two,
+				offset: 8,
+				binding: Cow::Borrowed("tt_vert_texture"),
+				location: None
+			},
+			VertexAttribute {
+				kind: VertexType::Unorm8,
+				components: VertexComponents::Four,
+				offset: 16,
+				binding: Cow::Borrowed("tt_vert_color"),
+				location: None
+			},
+		]
+	};
+
+	/** Create a new text renderer on the given device, for a window with the
+	 * given scale factor, as given by `Window::scale_factor()`. */
+	pub fn new(device: &Device, scale_factor: f64) -> Result<Self, TextError> {
+		let font = fontdue::Font::from_bytes(TEXT_FONT, Default::default())
+			.map_err(|what| TextError::Font { what: what.to_owned() })?;
+
+		let scale_factor = Self::check_scale_factor(scale_factor);
+		let (metrics, atlas, bind) = Self::bake_atlas(device, &font, scale_factor)?;
+
+		let vertex = device.create_vertex_shader(
+			ShaderSource::Glsl(Cow::Borrowed(include_str!("text/vert.glsl"))))?;
+		let fragment = device.create_fragment_shader(
+			ShaderSource::Glsl(Cow::Borrowed(include_str!("text/frag.glsl"))))?;
+
+		let pipeline = device.create_render_pipeline(
+			&RenderPipelineDescriptor::new(&vertex, &Self::LAYOUT)
+				.with_fragment_state(FragmentState::new(&fragment)
+					.with_targets(&[ColorTargetState {
+						alpha_blend: BlendState {
+							src_factor: BlendFactor::One,
+							dst_factor: BlendFactor::OneMinusSrcAlpha,
+							operation: BlendOperation::Add
+						},
+						color_blend: BlendState {
+							src_factor: BlendFactor::SrcAlpha,
+							dst_factor: BlendFactor::OneMinusSrcAlpha,
+							operation: BlendOperation::Add
+						},
+						write_mask: ColorWrite::all(),
+					}]))
+				.with_depth(DepthStencilState::DISABLED))?;
+		pipeline.set_label("text");
+
+		Ok(Self {
+			font,
+			metrics,
+			atlas,
+			bind,
+			pipeline,
+			vertices: None,
+			count: 0,
+			viewport: Viewport { x: 0, y: 0, width: 0, height: 0 },
+			queue: Vec::new(),
+			staging: Vec::new(),
+			scale_factor
+		})
+	}
+
+	/** Scale factors that aren't positive get treated as a scale factor of
+	 * one, as nothing could be drawn with them. */
+	fn check_scale_factor(scale_factor: f64) -> f32 {
+		if scale_factor.is_finite() && scale_factor > 0.0 {
+			scale_factor as f32
+		} else {
+			log::warn!(target: "support::text",
+				"Ignoring an invalid scale factor of {}", scale_factor);
+			1.0
+		}
+	}
+
+	/** Bakes an atlas of the given font for the given scale factor into a new
+	 * texture, along with the binding group it goes in. */
+	fn bake_atlas(device: &Device, font: &fontdue::Font, scale_factor: f32)
+		-> Result<(FontMetrics, Texture, UniformGroup), TextError> {
+
+		let baked = bake(font, TEXT_BASE_SIZE * scale_factor);
+		let atlas = device.create_texture_with_data(
+			&TextureDescriptor {
+				extent: TextureExtent::D2 {
+					width: baked.width,
+					height: baked.height
+				},
+				format: TextureFormat::Rgba8Unorm,
+				mip: Mipmap::None,
+				swizzle: None
+			},
+			baked.texels)?;
+		atlas.set_label("text atlas");
+
+		let bind = device.create_uniform_bind_group(
+			&UniformGroupDescriptor {
+				entries: &[
+					UniformGroupEntry {
+						binding: TEXT_ATLAS_BINDING.into(),
+						kind: UniformBind::Texture {
+							texture: &atlas,
+							far: TextureFilter::Linear,
+							near: TextureFilter::Linear,
+							address_mode: AddressMode::ClampToEdge,
+							anisotropy_clamp: None,
+							sampler: None
+						}
+					},
+				]
+			})?;
+
+		Ok((baked.metrics, atlas, bind))
+	}
+
+	/** Ratio between physical and logical pixels the atlas was baked for. */
+	pub fn scale_factor(&self) -> f64 {
+		f64::from(self.scale_factor)
+	}
+
+	/** Bakes the atlas again for the given scale factor, if it changed, as
+	 * happens when a window moves to a display of a different density. */
+	pub fn set_scale_factor(&mut self, device: &Device, scale_factor: f64)
+		-> Result<(), TextError> {
+
+		let scale_factor = Self::check_scale_factor(scale_factor);
+		if scale_factor == self.scale_factor { return Ok(()) }
+
+		let (metrics, atlas, bind) = Self::bake_atlas(device, &self.font, scale_factor)?;
+		self.metrics = metrics;
+		self.atlas = atlas;
+		self.bind = bind;
+		self.scale_factor = scale_factor;
+
+		Ok(())
+	}
+
+	/** Metrics of the font at the current scale factor, in physical pixels,
+	 * for measuring text ahead of queuing it. */
+	pub fn metrics(&self) -> &FontMetrics {
+		&self.metrics
+	}
+
+	/** Queue a string up to be drawn in the next pass, with the top left
+	 * corner of its first line at the given position, in logical pixels from
+	 * the top left corner of the viewport, at the given scale relative to
+	 * [`TEXT_BASE_SIZE`], and in the given color, with straight alpha. */
+	pub fn queue(&mut self, text: &str, x: f32, y: f32, scale: f32, color: [f32; 4]) {
+		self.queue.push(QueuedText {
+			text: text.to_owned(),
+			position: [x, y],
+			scale,
+			color: unorm8(color)
+		})
+	}
+
+	/** Lay out all of the queued strings for the given viewport, in physical
+	 * pixels, and upload them for the next call to [`draw()`]. The queue is
+	 * emptied afterwards, and the vertex buffer only grows when the text no
+	 * longer fits in it.
+	 *
+	 * This must be called outside of a render pass, as it needs to write to
+	 * the vertex buffer.
+	 *
+	 * [`draw()`]: Self::draw */
+	pub fn prepare(&mut self, device: &Device, viewport: Viewport) -> Result<(), TextError> {
+		self.viewport = viewport;
+		self.staging.clear();
+		if viewport.width > 0 && viewport.height > 0 {
+			let size = [viewport.width as f32, viewport.height as f32];
+			for text in &self.queue {
+				layout(&self.metrics, text, self.scale_factor, size, &mut self.staging);
+			}
+		}
+		self.queue.clear();
+
+		let data = bytemuck::cast_slice::<_, u8>(&self.staging[..]);
+		let len = buffer_size_of::<TextVertex>(self.staging.len())?;
+		let stale = match &self.vertices {
+			Some(vertices) => vertices.len() < len,
+			None => true
+		};
+		if stale && len > 0 {
+			let size = len.checked_next_power_of_two()
+				.unwrap_or(len)
+				.max(MIN_TEXT_BUFFER);
+			let vertices = device.create_vertex_buffer(
+				&BufferDescriptor {
+					size,
+					profile: BufferProfile::DynamicUpload,
+					contents: BufferContents::Opaque,
+					..Default::default()
+				})?;
+			vertices.set_label("text");
+			self.vertices = Some(vertices);
+		}
+		if let Some(vertices) = &self.vertices {
+			vertices.write(0, data)?;
+		}
+		self.count = u32::try_from(self.staging.len()).unwrap();
+
+		Ok(())
+	}
+
+	/** Draw the text uploaded by the last call to [`prepare()`] in the given
+	 * pass, over the viewport it was prepared for, in a single draw call.
+	 *
+	 * This switches the pass over to the text pipeline, vertex buffer and
+	 * binding group, which stay set once this returns, so whatever gets drawn
+	 * in the pass afterwards has to set its own again.
+	 *
+	 * [`prepare()`]: Self::prepare */
+	pub fn draw<'a>(&'a self, pass: &mut RenderPass<'a>) {
+		let vertices = match &self.vertices {
+			Some(vertices) if self.count > 0 => vertices,
+			_ => return
+		};
+
+		pass.set_pipeline(&self.pipeline);
+		pass.set_viewport(self.viewport)
+			.expect("the viewport must fit in the limits of the context");
+		pass.set_vertex_buffer(vertices);
+		pass.set_bind_group(&self.bind);
+		pass.draw(0..self.count, 1)
+			.expect("the text vertex buffer always holds the prepared vertices");
+	}
+}
+
+/** Errors that may happen while creating or preparing a text renderer. */
+#[derive(Debug, thiserror::Error)]
+pub enum TextError {
+	#[error("could not load the text font: {what}")]
+	Font {
+		what: String
+	},
+	#[error("could not create text buffers: {0}")]
+	Buffer(#[from] BufferError),
+	#[error("could not create the glyph atlas: {0}")]
+	Texture(#[from] TextureError),
+	#[error("could not create text shaders: {0}")]
+	Shader(#[from] ShaderError),
+	#[error("could not create text pipeline: {0}")]
+	Pipeline(#[from] RenderPipelineError),
+	#[error("could not bind the glyph atlas: {0}")]
+	BindGroup(#[from] BindGroupError),
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/** Metrics where every glyph is a 4x8 box sitting on the baseline, with
+	 * an advance of five texels. */
+	fn metrics() -> FontMetrics {
+		let glyph = Glyph {
+			uv: [0.0, 0.0, 1.0, 1.0],
+			size: [4.0, 8.0],
+			offset: [0.0, 0.0],
+			advance: 5.0
+		};
+		let mut glyphs = vec![glyph; (TEXT_LAST as usize) - (TEXT_FIRST as usize) + 1];
+		glyphs[0].size = [0.0, 0.0];
+		FontMetrics { glyphs, ascent: 10.0, line_height: 12.0 }
+	}
+
+	fn text(text: &str, scale: f32) -> QueuedText {
+		QueuedText {
+			text: text.to_owned(),
+			position: [2.0, 3.0],
+			scale,
+			color: unorm8([1.0, 0.5, 0.0, 1.0])
+		}
+	}
+
+	#[test]
+	fn lays_out_in_physical_pixels() {
+		let mut vertices = Vec::new();
+		layout(&metrics(), &text("a b\nc", 1.0), 2.0, [100.0, 100.0], &mut vertices);
+
+		/* Spaces have no quad, but still move the pen. */
+		assert_eq!(vertices.len(), 3 * 6);
+		assert_eq!(vertices[0].color, [255, 128, 0, 255]);
+
+		/* The bottom left corner of the first glyph sits on the baseline,
+		 * ten pixels below the scaled position. */
+		let ndc = |x: f32, y: f32| [x / 50.0 - 1.0, 1.0 - y / 50.0];
+		assert_eq!(vertices[0].position, ndc(4.0, 16.0));
+		assert_eq!(vertices[2].position, ndc(8.0, 8.0));
+		assert_eq!(vertices[6].position, ndc(14.0, 16.0));
+
+		/* Newlines go back to the left edge, one line further down. */
+		assert_eq!(vertices[12].position, ndc(4.0, 28.0));
+	}
+
+	#[test]
+	fn scales_glyphs_and_falls_back() {
+		let mut metrics = metrics();
+		metrics.glyphs[(TEXT_FALLBACK as usize) - (TEXT_FIRST as usize)].advance = 7.0;
+		assert_eq!(metrics.glyph('é').advance, 7.0);
+		assert_eq!(metrics.glyph('\u{1}').advance, 7.0);
+
+		let mut vertices = Vec::new();
+		layout(&metrics, &text("ab", 2.0), 1.0, [100.0, 100.0], &mut vertices);
+		let ndc = |x: f32, y: f32| [x / 50.0 - 1.0, 1.0 - y / 50.0];
+		assert_eq!(vertices[0].position, ndc(2.0, 23.0));
+		assert_eq!(vertices[2].position, ndc(10.0, 7.0));
+		assert_eq!(vertices[6].position, ndc(12.0, 23.0));
+	}
+
+	#[test]
+	fn bakes_every_printable_character() {
+		let font = fontdue::Font::from_bytes(TEXT_FONT, Default::default()).unwrap();
+		let baked = bake(&font, TEXT_BASE_SIZE);
+
+		assert_eq!(baked.texels.len(), (baked.width * baked.height * 4) as usize);
+		assert_eq!(baked.metrics.glyphs.len(), 95);
+
+		/* The font is monospace, and every glyph fits in the atlas. */
+		let advance = baked.metrics.glyph('M').advance;
+		for c in TEXT_FIRST..=TEXT_LAST {
+			let glyph = baked.metrics.glyph(c);
+			assert_eq!(glyph.advance, advance, "{:?}", c);
+			assert!(glyph.uv.iter().all(|uv| (0.0..=1.0).contains(uv)), "{:?}", c);
+		}
+		assert!(baked.texels.chunks_exact(4).any(|texel| texel[3] > 0));
+	}
+}
@@ -0,0 +1,16 @@
+#version 300 es
+precision highp float;
+
+uniform highp sampler2D tt_glyphs;
+
+in vec2 vs_uv;
+in vec4 vs_color;
+
+out vec4 color;
+
+/* The atlas holds the coverage of the glyphs in its alpha channel. */
+void main()
+{
+    float coverage = texture(tt_glyphs, vs_uv).a;
+    color = vec4(vs_color.rgb, vs_color.a * coverage);
+}
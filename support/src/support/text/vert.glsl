@@ -0,0 +1,17 @@
+#version 300 es
+precision highp float;
+
+in vec2 tt_vert_position;
+in vec2 tt_vert_texture;
+in vec4 tt_vert_color;
+
+out vec2 vs_uv;
+out vec4 vs_color;
+
+/* Positions already come in normalized device coordinates. */
+void main()
+{
+    vs_uv    = tt_vert_texture;
+    vs_color = tt_vert_color;
+    gl_Position = vec4(tt_vert_position, 0.0, 1.0);
+}
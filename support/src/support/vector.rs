@@ -0,0 +1,329 @@
+use crate::support::Matrix4;
+
+/** Three-dimensional vector type.
+ *
+ * This type exposes the arithmetic most of the geometry in game code is made
+ * of, such as dot and cross products, and converts to and from plain arrays,
+ * which is how vectors are stored in vertices.
+ *
+ * Keep in mind that, while this type is marked as a POD structure, the
+ * `std140` GLSL layout aligns `vec3` members to sixteen bytes, so they must be
+ * followed by padding when copied into a device buffer. */
+#[repr(transparent)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd, bytemuck::Pod, bytemuck::Zeroable, serde::Serialize, serde::Deserialize)]
+pub struct Vec3([f32; 3]);
+
+impl Vec3 {
+	/** Creates a new vector with the given components. */
+	pub const fn new(x: f32, y: f32, z: f32) -> Self {
+		Self([x, y, z])
+	}
+
+	/** Creates a new vector with all of its components set to zero. */
+	pub const fn zero() -> Self {
+		Self([0.0; 3])
+	}
+
+	/** Get the contents of this vector as an array. */
+	pub fn as_array(&self) -> &[f32; 3] {
+		&self.0
+	}
+
+	/** The first component of this vector. */
+	pub fn x(&self) -> f32 { self.0[0] }
+
+	/** The second component of this vector. */
+	pub fn y(&self) -> f32 { self.0[1] }
+
+	/** The third component of this vector. */
+	pub fn z(&self) -> f32 { self.0[2] }
+
+	/** Dot product of this vector and the given one. */
+	pub fn dot(&self, rhs: Self) -> f32 {
+		self.0[0] * rhs.0[0] + self.0[1] * rhs.0[1] + self.0[2] * rhs.0[2]
+	}
+
+	/** Cross product of this vector and the given one, which is perpendicular
+	 * to both, following the right hand rule. */
+	pub fn cross(&self, rhs: Self) -> Self {
+		Self([
+			self.0[1] * rhs.0[2] - self.0[2] * rhs.0[1],
+			self.0[2] * rhs.0[0] - self.0[0] * rhs.0[2],
+			self.0[0] * rhs.0[1] - self.0[1] * rhs.0[0],
+		])
+	}
+
+	/** Euclidean length of this vector. */
+	pub fn length(&self) -> f32 {
+		f32::sqrt(self.dot(*self))
+	}
+
+	/** Vector pointing in the same direction as this one, with a length of
+	 * one, or `None` if this vector has no length, and thus no direction. */
+	pub fn normalize(&self) -> Option<Self> {
+		let length = self.length();
+		if length == 0.0 {
+			None
+		} else {
+			Some(*self / length)
+		}
+	}
+
+	/** Extends this vector into a four-dimensional one, with the given value
+	 * as its fourth component. Use `1.0` for points and `0.0` for directions,
+	 * so that only points get translated when transformed by a matrix. */
+	pub fn extend(&self, w: f32) -> Vec4 {
+		Vec4([self.0[0], self.0[1], self.0[2], w])
+	}
+}
+impl From<[f32; 3]> for Vec3 {
+	fn from(array: [f32; 3]) -> Self {
+		Self(array)
+	}
+}
+impl From<Vec3> for [f32; 3] {
+	fn from(vector: Vec3) -> Self {
+		vector.0
+	}
+}
+
+/** Four-dimensional vector type.
+ *
+ * This is mostly useful as a point or a direction in homogeneous coordinates,
+ * to be transformed by a [`Matrix4`].
+ *
+ * The layout of this structure is compatible with both the `std140` and
+ * `std430` GLSL layouts, together with being marked as a POD structure, which
+ * allows it to be copied directly into a device buffer.
+ *
+ * [`Matrix4`]: crate::Matrix4 */
+#[repr(transparent)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd, bytemuck::Pod, bytemuck::Zeroable, serde::Serialize, serde::Deserialize)]
+pub struct Vec4([f32; 4]);
+
+impl Vec4 {
+	/** Creates a new vector with the given components. */
+	pub const fn new(x: f32, y: f32, z: f32, w: f32) -> Self {
+		Self([x, y, z, w])
+	}
+
+	/** Creates a new vector with all of its components set to zero. */
+	pub const fn zero() -> Self {
+		Self([0.0; 4])
+	}
+
+	/** Get the contents of this vector as an array. */
+	pub fn as_array(&self) -> &[f32; 4] {
+		&self.0
+	}
+
+	/** The first component of this vector. */
+	pub fn x(&self) -> f32 { self.0[0] }
+
+	/** The second component of this vector. */
+	pub fn y(&self) -> f32 { self.0[1] }
+
+	/** The third component of this vector. */
+	pub fn z(&self) -> f32 { self.0[2] }
+
+	/** The fourth component of this vector. */
+	pub fn w(&self) -> f32 { self.0[3] }
+
+	/** Dot product of this vector and the given one. */
+	pub fn dot(&self, rhs: Self) -> f32 {
+		self.0.iter().zip(&rhs.0).map(|(a, b)| a * b).sum()
+	}
+
+	/** Euclidean length of this vector. */
+	pub fn length(&self) -> f32 {
+		f32::sqrt(self.dot(*self))
+	}
+
+	/** Vector pointing in the same direction as this one, with a length of
+	 * one, or `None` if this vector has no length, and thus no direction. */
+	pub fn normalize(&self) -> Option<Self> {
+		let length = self.length();
+		if length == 0.0 {
+			None
+		} else {
+			Some(*self / length)
+		}
+	}
+
+	/** Drops the fourth component of this vector. */
+	pub fn truncate(&self) -> Vec3 {
+		Vec3([self.0[0], self.0[1], self.0[2]])
+	}
+
+	/** Divides the first three components of this vector by the fourth one,
+	 * which brings a point in homogeneous coordinates, such as one in clip
+	 * space, back into three-dimensional space. */
+	pub fn project(&self) -> Vec3 {
+		self.truncate() / self.0[3]
+	}
+}
+impl From<[f32; 4]> for Vec4 {
+	fn from(array: [f32; 4]) -> Self {
+		Self(array)
+	}
+}
+impl From<Vec4> for [f32; 4] {
+	fn from(vector: Vec4) -> Self {
+		vector.0
+	}
+}
+
+/** Implements the component-wise arithmetic operators for a vector type. */
+macro_rules! vector_arithmetic {
+	($vector:ident) => {
+		/** Implementation of the standard vector sum functionality. */
+		impl std::ops::Add for $vector {
+			type Output = Self;
+
+			fn add(mut self, rhs: Self) -> Self::Output {
+				self += rhs;
+				self
+			}
+		}
+
+		/** Assigning addition of one vector by another. */
+		impl std::ops::AddAssign for $vector {
+			fn add_assign(&mut self, rhs: Self) {
+				let iter = self.0.iter_mut().zip(&rhs.0);
+				for (i, j) in iter { *i += *j; }
+			}
+		}
+
+		/** Implementation of the standard vector subtraction functionality. */
+		impl std::ops::Sub for $vector {
+			type Output = Self;
+
+			fn sub(mut self, rhs: Self) -> Self::Output {
+				self -= rhs;
+				self
+			}
+		}
+
+		/** Assigning subtraction of one vector by another. */
+		impl std::ops::SubAssign for $vector {
+			fn sub_assign(&mut self, rhs: Self) {
+				let iter = self.0.iter_mut().zip(&rhs.0);
+				for (i, j) in iter { *i -= *j; }
+			}
+		}
+
+		/** Implementation of the negation of a vector. */
+		impl std::ops::Neg for $vector {
+			type Output = Self;
+
+			fn neg(mut self) -> Self::Output {
+				for i in &mut self.0 { *i = -*i; }
+				self
+			}
+		}
+
+		/** Implementation of the multiplication of a vector by a scalar. */
+		impl std::ops::Mul<f32> for $vector {
+			type Output = Self;
+
+			fn mul(mut self, rhs: f32) -> Self::Output {
+				self *= rhs;
+				self
+			}
+		}
+
+		/** Assigning multiplication of a vector by a scalar. */
+		impl std::ops::MulAssign<f32> for $vector {
+			fn mul_assign(&mut self, rhs: f32) {
+				for i in &mut self.0 { *i *= rhs; }
+			}
+		}
+
+		/** Implementation of the division of a vector by a scalar. */
+		impl std::ops::Div<f32> for $vector {
+			type Output = Self;
+
+			fn div(mut self, rhs: f32) -> Self::Output {
+				self /= rhs;
+				self
+			}
+		}
+
+		/** Assigning division of a vector by a scalar. */
+		impl std::ops::DivAssign<f32> for $vector {
+			fn div_assign(&mut self, rhs: f32) {
+				for i in &mut self.0 { *i /= rhs; }
+			}
+		}
+	}
+}
+vector_arithmetic!(Vec3);
+vector_arithmetic!(Vec4);
+
+/** Implementation of the transformation of a vector by a matrix. */
+impl std::ops::Mul<Vec4> for Matrix4 {
+	type Output = Vec4;
+
+	fn mul(self, rhs: Vec4) -> Self::Output {
+		let m = self.as_row_major_array();
+		let row = |i: usize| m[i * 4..i * 4 + 4].iter()
+			.zip(&rhs.0)
+			.map(|(a, b)| a * b)
+			.sum();
+
+		Vec4([row(0), row(1), row(2), row(3)])
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn cross_products_follow_the_right_hand_rule() {
+		let x = Vec3::new(1.0, 0.0, 0.0);
+		let y = Vec3::new(0.0, 1.0, 0.0);
+		let z = Vec3::new(0.0, 0.0, 1.0);
+
+		assert_eq!(x.cross(y), z);
+		assert_eq!(y.cross(z), x);
+		assert_eq!(z.cross(x), y);
+		assert_eq!(y.cross(x), -z);
+		assert_eq!(x.cross(x), Vec3::zero());
+	}
+
+	#[test]
+	fn arithmetic_is_component_wise() {
+		let a = Vec3::new(1.0, 2.0, 3.0);
+		let b = Vec3::new(4.0, -5.0, 6.0);
+
+		assert_eq!(a + b, Vec3::new(5.0, -3.0, 9.0));
+		assert_eq!(a - b, Vec3::new(-3.0, 7.0, -3.0));
+		assert_eq!(a * 2.0, Vec3::new(2.0, 4.0, 6.0));
+		assert_eq!(b / 2.0, Vec3::new(2.0, -2.5, 3.0));
+		assert_eq!(a.dot(b), 12.0);
+		assert_eq!(a.extend(4.0).dot(Vec4::new(1.0, 1.0, 1.0, 1.0)), 10.0);
+	}
+
+	#[test]
+	fn normalized_vectors_have_unit_length() {
+		let a = Vec3::new(3.0, 0.0, 4.0);
+		assert_eq!(a.length(), 5.0);
+		assert_eq!(a.normalize(), Some(Vec3::new(0.6, 0.0, 0.8)));
+		assert_eq!(Vec3::zero().normalize(), None);
+
+		let b = Vec4::new(1.0, 1.0, 1.0, 1.0).normalize().unwrap();
+		assert!((b.length() - 1.0).abs() < 1e-6);
+		assert_eq!(Vec4::zero().normalize(), None);
+	}
+
+	#[test]
+	fn matrices_translate_points_but_not_directions() {
+		let matrix = Matrix4::translate(1.0, 2.0, 3.0) * Matrix4::scale(2.0, 2.0, 2.0);
+		let point = Vec3::new(1.0, 1.0, 1.0);
+
+		assert_eq!(matrix * point.extend(1.0), Vec4::new(3.0, 4.0, 5.0, 1.0));
+		assert_eq!(matrix * point.extend(0.0), Vec4::new(2.0, 2.0, 2.0, 0.0));
+		assert_eq!(Vec4::new(2.0, 4.0, 6.0, 2.0).project(), Vec3::new(1.0, 2.0, 3.0));
+	}
+}
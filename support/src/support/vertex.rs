@@ -1,6 +1,6 @@
 use gavle::*;
 use std::borrow::Cow;
-use crate::support::Matrix4;
+use crate::support::Vec3;
 
 /** Structure containing the data for a vertex in three-dimensional space. */
 #[derive(Debug, Copy, Clone, PartialEq, PartialOrd, bytemuck::Zeroable, bytemuck::Pod, serde::Serialize, serde::Deserialize)]
@@ -28,37 +28,43 @@ impl Vertex {
 				kind: VertexType::F32,
 				components: VertexComponents::Three,
 				offset: 0,
-				binding: Cow::Borrowed("tt_vert_position")
+				binding: Cow::Borrowed("tt_vert_position"),
+				location: None
 			},
 			VertexAttribute {
 				kind: VertexType::F32,
 				components: VertexComponents::Two,
 				offset: 12,
-				binding: Cow::Borrowed("tt_vert_texture")
+				binding: Cow::Borrowed("tt_vert_texture"),
+				location: None
 			},
 			VertexAttribute {
 				kind: VertexType::F32,
 				components: VertexComponents::Three,
 				offset: 20,
-				binding: Cow::Borrowed("tt_vert_color")
+				binding: Cow::Borrowed("tt_vert_color"),
+				location: None
 			},
 			VertexAttribute {
 				kind: VertexType::F32,
 				components: VertexComponents::Three,
 				offset: 32,
-				binding: Cow::Borrowed("tt_vert_normal")
+				binding: Cow::Borrowed("tt_vert_normal"),
+				location: None
 			},
 			VertexAttribute {
 				kind: VertexType::F32,
 				components: VertexComponents::Three,
 				offset: 44,
-				binding: Cow::Borrowed("tt_vert_tangent")
+				binding: Cow::Borrowed("tt_vert_tangent"),
+				location: None
 			},
 			VertexAttribute {
 				kind: VertexType::F32,
 				components: VertexComponents::Three,
 				offset: 56,
-				binding: Cow::Borrowed("tt_vert_bitangent")
+				binding: Cow::Borrowed("tt_vert_bitangent"),
+				location: None
 			},
 		]
 	};
@@ -100,12 +106,10 @@ impl Vertex {
 		bitangent: [f32; 3]) -> Result<Self, InvalidVertex> {
 
 		/* Check whether the NTB vectors form an orthonormal base. */
-		let ntb_determinant = Matrix4::from_row_major_array([
-			normal[0], tangent[0], bitangent[0], 0.0,
-			normal[1], tangent[1], bitangent[1], 0.0,
-			normal[2], tangent[2], bitangent[2], 0.0,
-			      0.0,        0.0,          0.0, 1.0,
-		]).det();
+		let ntb_determinant = ntb_determinant(
+			normal.into(),
+			tangent.into(),
+			bitangent.into());
 
 		/* Tolerate a bit of numerical error. */
 		if f32::round(ntb_determinant.abs() * 100.0) != 100.0 {
@@ -161,12 +165,10 @@ impl Vertex {
 		bitangent: [f32; 3]) -> Result<Self, InvalidVertex> {
 
 		/* Check whether the NTB vectors form an orthonormal base. */
-		let ntb_determinant = Matrix4::from_row_major_array([
-			normal[0], tangent[0], bitangent[0], 0.0,
-			normal[1], tangent[1], bitangent[1], 0.0,
-			normal[2], tangent[2], bitangent[2], 0.0,
-			0.0,        0.0,          0.0, 1.0,
-		]).det();
+		let ntb_determinant = ntb_determinant(
+			normal.into(),
+			tangent.into(),
+			bitangent.into());
 
 		/* Tolerate a bit of numerical error. */
 		if f32::round(ntb_determinant.abs() * 100.0) != 100.0 {
@@ -238,6 +240,13 @@ impl Vertex {
 	}
 }
 
+/** Determinant of the matrix with the given normal, tangent and bitangent
+ * vectors as its columns, which is the triple product of the vectors. Its
+ * absolute value is one for orthonormal bases. */
+fn ntb_determinant(normal: Vec3, tangent: Vec3, bitangent: Vec3) -> f32 {
+	normal.dot(tangent.cross(bitangent))
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum InvalidVertex {
 	#[error("The normal ({normal:?}), tangent ({tangent:?}) and bitangent \
@@ -251,3 +260,22 @@ pub enum InvalidVertex {
 		determinant: f32,
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn ntb_vectors_must_form_an_orthonormal_base() {
+		let (n, t, b) = ([0.0, 0.0, 1.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]);
+
+		assert_eq!(ntb_determinant(n.into(), t.into(), b.into()), 1.0);
+		assert_eq!(ntb_determinant(n.into(), b.into(), t.into()), -1.0);
+		assert!(Vertex::try_new([0.0; 3], [0.0; 2], n, t, b).is_ok());
+		assert!(Vertex::try_new([0.0; 3], [0.0; 2], n, b, t).is_ok());
+
+		/* Vectors that are too short, or that lie on the same plane. */
+		assert!(Vertex::try_new([0.0; 3], [0.0; 2], n, t, [0.0, 0.5, 0.0]).is_err());
+		assert!(Vertex::try_new([0.0; 3], [0.0; 2], n, t, t).is_err());
+	}
+}
@@ -0,0 +1,658 @@
+use gavle::*;
+use std::convert::TryFrom;
+use std::time::Duration;
+
+/** Default number of frames decoded ahead of the one being shown. */
+pub const DEFAULT_DECODE_AHEAD: usize = 4;
+
+/** Function decoding the frame with the given index into tightly packed RGBA
+ * data, with rows going from the bottom of the frame to the top, just like the
+ * data of a texture. On native targets, this gets called from a worker thread,
+ * so it has to be [`Send`]. */
+#[cfg(not(target_arch = "wasm32"))]
+pub type FrameDecoder = Box<dyn FnMut(usize) -> Vec<u8> + Send>;
+
+/** Function decoding the frame with the given index into tightly packed RGBA
+ * data, with rows going from the bottom of the frame to the top, just like the
+ * data of a texture. On the web, this gets called in between frames. */
+#[cfg(target_arch = "wasm32")]
+pub type FrameDecoder = Box<dyn FnMut(usize) -> Vec<u8>>;
+
+/** How a frame sequence carries on once it gets to its last frame. */
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum PlaybackMode {
+	/** Stays at the last frame. */
+	Once,
+	/** Starts over from the first frame. */
+	Loop,
+	/** Plays backwards down to the first frame, then forwards again, without
+	 * showing the frames at either end twice in a row. */
+	PingPong,
+}
+
+/** Where a [`PlaybackClock`] landed after being advanced.
+ *
+ * [`PlaybackClock`]: PlaybackClock */
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct FrameStep {
+	/** Frame that should be shown now. */
+	pub frame: usize,
+	/** Whether this is a different frame than the one shown before. */
+	pub changed: bool,
+	/** Number of frames that were due in between the previous frame and this
+	 * one, and that got skipped, because the clock was advanced by more than
+	 * a single frame at once. */
+	pub skipped: u64,
+}
+
+/** Keeps time for a sequence of frames shown at a constant rate, telling which
+ * frame is due as time goes by.
+ *
+ * Time is kept exactly, in whole frames plus whatever is left over from the
+ * last one, so that no drift builds up over long playbacks. When the clock is
+ * advanced by more than a frame at once, as is the case when rendering falls
+ * behind, it jumps straight to the frame that is due, rather than going
+ * through the ones in between. */
+#[derive(Debug, Clone)]
+pub struct PlaybackClock {
+	/** Number of frames in the sequence. */
+	frames: usize,
+	/** Time each frame is shown for. */
+	frame_time: Duration,
+	/** What happens after the last frame. */
+	mode: PlaybackMode,
+	/** Number of whole frame times elapsed since playback started. */
+	ticks: u64,
+	/** Time elapsed since the start of the current frame time. */
+	remainder: Duration,
+}
+impl PlaybackClock {
+	/** Creates a new clock for a sequence of the given number of frames, shown
+	 * at the given rate, in frames per second, starting at the first frame.
+	 *
+	 * # Panic
+	 * This function panics if there are no frames, or if the frame rate isn't
+	 * a positive, finite number. */
+	pub fn new(frames: usize, frame_rate: f32, mode: PlaybackMode) -> Self {
+		assert!(frames > 0, "frame sequences must have at least one frame");
+		assert!(frame_rate.is_finite() && frame_rate > 0.0,
+			"the frame rate must be a positive number, but it is {}",
+			frame_rate);
+
+		let frame_time = Duration::from_secs_f64(1.0 / f64::from(frame_rate))
+			.max(Duration::from_nanos(1));
+		Self {
+			frames,
+			frame_time,
+			mode,
+			ticks: 0,
+			remainder: Duration::from_secs(0)
+		}
+	}
+
+	/** Number of frames in the sequence. */
+	pub fn frames(&self) -> usize {
+		self.frames
+	}
+
+	/** What happens after the last frame. */
+	pub fn mode(&self) -> PlaybackMode {
+		self.mode
+	}
+
+	/** Frame that is currently due. */
+	pub fn frame(&self) -> usize {
+		self.frame_at(self.ticks)
+	}
+
+	/** Moves the clock forward by the given amount of time. */
+	pub fn advance(&mut self, delta: Duration) -> FrameStep {
+		let frame_time = self.frame_time.as_nanos();
+		let elapsed = self.remainder.as_nanos() + delta.as_nanos();
+		let ticks = u64::try_from(elapsed / frame_time).unwrap_or(u64::MAX);
+		self.remainder = Duration::from_nanos((elapsed % frame_time) as u64);
+
+		let previous = self.ticks;
+		self.ticks = self.ticks.saturating_add(ticks);
+
+		let skipped = match self.mode {
+			PlaybackMode::Once => {
+				let last = self.frames as u64 - 1;
+				u64::min(self.ticks, last)
+					.saturating_sub(u64::min(previous, last))
+					.saturating_sub(1)
+			},
+			PlaybackMode::Loop | PlaybackMode::PingPong =>
+				ticks.saturating_sub(1)
+		};
+		let frame = self.frame();
+		FrameStep {
+			frame,
+			changed: frame != self.frame_at(previous),
+			skipped
+		}
+	}
+
+	/** Up to the given number of frames that come up next after the current
+	 * one, in the order they will be shown in, without repeats. Frames that
+	 * are shown again before all of them have come up are only listed once. */
+	pub fn upcoming(&self, count: usize) -> Vec<usize> {
+		let current = self.frame();
+		let mut upcoming = Vec::with_capacity(count);
+		for ticks in (1..=count as u64).map(|ahead| self.ticks.saturating_add(ahead)) {
+			let frame = self.frame_at(ticks);
+			if frame != current && !upcoming.contains(&frame) {
+				upcoming.push(frame);
+			}
+		}
+
+		upcoming
+	}
+
+	/** Frame that is due after the given number of frame times. */
+	fn frame_at(&self, ticks: u64) -> usize {
+		let frames = self.frames as u64;
+		if frames == 1 { return 0 }
+
+		let frame = match self.mode {
+			PlaybackMode::Once => u64::min(ticks, frames - 1),
+			PlaybackMode::Loop => ticks % frames,
+			PlaybackMode::PingPong => {
+				let period = 2 * (frames - 1);
+				let position = ticks % period;
+				if position < frames { position } else { period - position }
+			}
+		};
+		frame as usize
+	}
+}
+
+/** Where the frames of a [`FrameSequenceTexture`] come from.
+ *
+ * [`FrameSequenceTexture`]: FrameSequenceTexture */
+pub enum FrameSource {
+	/** Frames decoded up front, in the order they are shown in. */
+	Frames(Vec<Vec<u8>>),
+	/** Frames decoded on demand, by the given function. */
+	Decoder {
+		/** Number of frames in the sequence. */
+		frames: usize,
+		/** Function decoding the frames. */
+		decode: FrameDecoder,
+	},
+}
+
+/** Parameters of a new [`FrameSequenceTexture`].
+ *
+ * [`FrameSequenceTexture`]: FrameSequenceTexture */
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct FrameSequenceDescriptor {
+	/** Width of every frame, in texels. */
+	pub width: u32,
+	/** Height of every frame, in texels. */
+	pub height: u32,
+	/** Number of frames shown every second. */
+	pub frame_rate: f32,
+	/** What happens after the last frame. */
+	pub mode: PlaybackMode,
+	/** Number of frames decoded ahead of the one being shown, for sequences
+	 * whose frames are decoded on demand. */
+	pub decode_ahead: usize,
+	/** Largest number of frames decoded ahead of time in a single tick, on the
+	 * web, where there are no worker threads, and where frames get decoded
+	 * in between ticks instead. Ignored on native targets. */
+	pub decode_budget: usize,
+}
+
+/** A texture whose contents get replaced by the frames of a sequence, in order,
+ * at a constant rate, as in a short video.
+ *
+ * Frames are only uploaded when the frame that is due actually changes, and,
+ * when ticks are too far apart to show every frame, the ones in between are
+ * skipped, rather than uploaded only to be replaced right away.
+ *
+ * When frames are decoded on demand, a few of them are decoded ahead of the
+ * one being shown. On native targets, this happens on a worker thread, and a
+ * tick only waits for it when the frame that is due isn't ready yet. On the
+ * web, a limited number of them is decoded in every tick. */
+pub struct FrameSequenceTexture {
+	/** Texture the frames are uploaded to. */
+	texture: Texture,
+	/** Extent of every frame. */
+	extent: TextureExtent,
+	/** Length of every frame, in bytes. */
+	frame_len: usize,
+	/** Clock telling which frame is due. */
+	clock: PlaybackClock,
+	/** Frame currently in the texture. */
+	current: usize,
+	/** Number of frames skipped so far. */
+	skipped: u64,
+	/** Where the frames come from. */
+	frames: Frames,
+	/** Frames already decoded ahead of time, waiting to be shown. */
+	ready: Vec<(usize, Vec<u8>)>,
+	/** Number of frames decoded ahead of the one being shown. */
+	decode_ahead: usize,
+	/** Largest number of frames decoded ahead of time in a single tick. */
+	#[cfg_attr(not(target_arch = "wasm32"), allow(dead_code))]
+	decode_budget: usize,
+}
+impl FrameSequenceTexture {
+	/** Creates a new texture with the first frame of the given sequence in it.
+	 *
+	 * The first frame is always decoded right away, before this returns. */
+	pub fn new(
+		device: &Device,
+		descriptor: &FrameSequenceDescriptor,
+		source: FrameSource) -> Result<Self, FrameSequenceError> {
+
+		let count = match &source {
+			FrameSource::Frames(frames) => frames.len(),
+			FrameSource::Decoder { frames, .. } => *frames
+		};
+		if count == 0 {
+			return Err(FrameSequenceError::Empty)
+		}
+		let frame_rate = descriptor.frame_rate;
+		if !frame_rate.is_finite() || frame_rate <= 0.0 {
+			return Err(FrameSequenceError::InvalidFrameRate { frame_rate })
+		}
+
+		let extent = TextureExtent::D2 {
+			width: descriptor.width,
+			height: descriptor.height
+		};
+		let frame_len = u64::from(descriptor.width)
+			.checked_mul(u64::from(descriptor.height))
+			.and_then(|len| len.checked_mul(4))
+			.unwrap_or(u64::MAX);
+		let frame_len = usize::try_from(frame_len)
+			.map_err(|_| TextureError::TooLarge { computed_bytes: frame_len })?;
+
+		let (frames, first) = match source {
+			FrameSource::Frames(frames) => {
+				for (frame, data) in frames.iter().enumerate() {
+					check_frame(frame, data.len(), frame_len)?;
+				}
+				let first = frames[0].clone();
+				(Frames::Decoded(frames), first)
+			},
+			FrameSource::Decoder { mut decode, .. } => {
+				let first = decode(0);
+				check_frame(0, first.len(), frame_len)?;
+				(Frames::decoder(decode), first)
+			}
+		};
+
+		let texture = device.create_texture_with_data(
+			&TextureDescriptor {
+				extent,
+				format: TextureFormat::Rgba8Unorm,
+				mip: Mipmap::None,
+				swizzle: None
+			},
+			first)?;
+
+		let mut sequence = Self {
+			texture,
+			extent,
+			frame_len,
+			clock: PlaybackClock::new(count, frame_rate, descriptor.mode),
+			current: 0,
+			skipped: 0,
+			frames,
+			ready: Vec::new(),
+			decode_ahead: descriptor.decode_ahead,
+			decode_budget: descriptor.decode_budget
+		};
+		sequence.prefetch()?;
+
+		Ok(sequence)
+	}
+
+	/** Texture the frames get uploaded to. */
+	pub fn texture(&self) -> &Texture {
+		&self.texture
+	}
+
+	/** Frame currently in the texture. */
+	pub fn current_frame(&self) -> usize {
+		self.current
+	}
+
+	/** Number of frames in the sequence. */
+	pub fn frame_count(&self) -> usize {
+		self.clock.frames()
+	}
+
+	/** Number of frames that were skipped so far, because ticks were too far
+	 * apart for them to be shown. */
+	pub fn skipped_frames(&self) -> u64 {
+		self.skipped
+	}
+
+	/** Moves playback forward by the given amount of time, uploading the frame
+	 * that is due, if it isn't the one in the texture already. Returns whether
+	 * a new frame was uploaded.
+	 *
+	 * # Panic
+	 * This function will panic if the pipeline can't be locked mutably, as is
+	 * the case while a render pass is running. */
+	pub fn tick(&mut self, delta: Duration) -> Result<bool, FrameSequenceError> {
+		let step = self.clock.advance(delta);
+		self.skipped += step.skipped;
+		if step.skipped > 0 {
+			log::debug!(target: "support::video",
+				"skipped {} frames to catch up to frame {}",
+				step.skipped,
+				step.frame);
+		}
+
+		if step.changed {
+			self.upload(step.frame)?;
+		}
+		self.prefetch()?;
+
+		Ok(step.changed)
+	}
+
+	/** Uploads the given frame to the texture, decoding it if it isn't ready. */
+	fn upload(&mut self, frame: usize) -> Result<(), FrameSequenceError> {
+		let position = self.ready.iter().position(|(ready, _)| *ready == frame);
+
+		let decoded;
+		let data: &[u8] = match (&mut self.frames, position) {
+			(Frames::Decoded(frames), _) => &frames[frame],
+			(_, Some(position)) => {
+				decoded = self.ready.swap_remove(position).1;
+				&decoded
+			},
+			#[cfg(not(target_arch = "wasm32"))]
+			(Frames::Worker(worker), None) => {
+				decoded = worker.wait_for(frame, &mut self.ready)?;
+				&decoded
+			},
+			#[cfg(target_arch = "wasm32")]
+			(Frames::Inline(decode), None) => {
+				decoded = decode(frame);
+				&decoded
+			}
+		};
+		check_frame(frame, data.len(), self.frame_len)?;
+
+		self.texture.write((0, 0, 0), self.extent, data)?;
+		self.current = frame;
+
+		Ok(())
+	}
+
+	/** Gets the frames coming up next decoded ahead of time, and drops the
+	 * ones that were decoded but won't be coming up any time soon, such as the
+	 * ones that ended up being skipped. */
+	fn prefetch(&mut self) -> Result<(), FrameSequenceError> {
+		let upcoming = self.clock.upcoming(self.decode_ahead);
+		let ready = &mut self.ready;
+		ready.retain(|(frame, _)| upcoming.contains(frame));
+
+		match &mut self.frames {
+			Frames::Decoded(_) => {},
+			#[cfg(not(target_arch = "wasm32"))]
+			Frames::Worker(worker) => {
+				worker.collect(&upcoming, ready);
+				for &frame in &upcoming {
+					if !ready.iter().any(|(ready, _)| *ready == frame) {
+						worker.request(frame)?;
+					}
+				}
+			},
+			#[cfg(target_arch = "wasm32")]
+			Frames::Inline(decode) => {
+				let missing = upcoming.iter()
+					.filter(|frame| !ready.iter().any(|(ready, _)| ready == *frame))
+					.take(self.decode_budget)
+					.copied()
+					.collect::<Vec<_>>();
+				for frame in missing {
+					let data = decode(frame);
+					check_frame(frame, data.len(), self.frame_len)?;
+					ready.push((frame, data));
+				}
+			}
+		}
+
+		Ok(())
+	}
+}
+
+/** Where the frames of a [`FrameSequenceTexture`] are taken from.
+ *
+ * [`FrameSequenceTexture`]: FrameSequenceTexture */
+enum Frames {
+	/** All of the frames, decoded up front. */
+	Decoded(Vec<Vec<u8>>),
+	/** Frames decoded on demand, on a worker thread. */
+	#[cfg(not(target_arch = "wasm32"))]
+	Worker(DecodeWorker),
+	/** Frames decoded on demand, in between ticks. */
+	#[cfg(target_arch = "wasm32")]
+	Inline(FrameDecoder),
+}
+impl Frames {
+	/** Frames decoded on demand by the given function. */
+	#[cfg(not(target_arch = "wasm32"))]
+	fn decoder(decode: FrameDecoder) -> Self {
+		Self::Worker(DecodeWorker::new(decode))
+	}
+
+	/** Frames decoded on demand by the given function. */
+	#[cfg(target_arch = "wasm32")]
+	fn decoder(decode: FrameDecoder) -> Self {
+		Self::Inline(decode)
+	}
+}
+
+/** Thread decoding frames as they get requested from it.
+ *
+ * The thread is left to wind down on its own when this is dropped, which it
+ * does as soon as it is done with the frame it is decoding at that point. */
+#[cfg(not(target_arch = "wasm32"))]
+struct DecodeWorker {
+	/** Indices of the frames to be decoded. */
+	requests: std::sync::mpsc::Sender<usize>,
+	/** Frames decoded by the thread. */
+	decoded: std::sync::mpsc::Receiver<(usize, Vec<u8>)>,
+	/** Frames requested from the thread that haven't been received yet. */
+	pending: Vec<usize>,
+}
+#[cfg(not(target_arch = "wasm32"))]
+impl DecodeWorker {
+	/** Starts a new thread decoding frames with the given function. */
+	fn new(mut decode: FrameDecoder) -> Self {
+		let (requests, incoming) = std::sync::mpsc::channel::<usize>();
+		let (outgoing, decoded) = std::sync::mpsc::channel();
+		std::thread::Builder::new()
+			.name("frame decoder".into())
+			.spawn(move || {
+				for frame in incoming {
+					if outgoing.send((frame, decode(frame))).is_err() {
+						break
+					}
+				}
+			})
+			.expect("could not start the frame decoder thread");
+
+		Self { requests, decoded, pending: Vec::new() }
+	}
+
+	/** Asks for the given frame to be decoded, unless it already was. */
+	fn request(&mut self, frame: usize) -> Result<(), FrameSequenceError> {
+		if self.pending.contains(&frame) { return Ok(()) }
+		self.requests.send(frame)
+			.map_err(|_| FrameSequenceError::DecoderStopped { frame })?;
+		self.pending.push(frame);
+
+		Ok(())
+	}
+
+	/** Takes every frame decoded so far, keeping the ones in the given list
+	 * and dropping the others. */
+	fn collect(&mut self, keep: &[usize], ready: &mut Vec<(usize, Vec<u8>)>) {
+		while let Ok((frame, data)) = self.decoded.try_recv() {
+			self.pending.retain(|pending| *pending != frame);
+			if keep.contains(&frame) {
+				ready.push((frame, data));
+			}
+		}
+	}
+
+	/** Waits for the given frame to be decoded, requesting it if needed, and
+	 * keeping any other frames that come in before it. */
+	fn wait_for(&mut self, frame: usize, ready: &mut Vec<(usize, Vec<u8>)>)
+		-> Result<Vec<u8>, FrameSequenceError> {
+
+		self.request(frame)?;
+		loop {
+			let (decoded, data) = self.decoded.recv()
+				.map_err(|_| FrameSequenceError::DecoderStopped { frame })?;
+			self.pending.retain(|pending| *pending != decoded);
+			if decoded == frame {
+				return Ok(data)
+			}
+			ready.push((decoded, data));
+		}
+	}
+}
+
+/** Checks whether a frame with the given length has the expected length. */
+fn check_frame(frame: usize, len: usize, expected: usize) -> Result<(), FrameSequenceError> {
+	if len != expected {
+		return Err(FrameSequenceError::InvalidFrame { frame, len, expected })
+	}
+	Ok(())
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum FrameSequenceError {
+	#[error("the frame sequence has no frames")]
+	Empty,
+	#[error("the frame rate must be a positive number, but it is {frame_rate}")]
+	InvalidFrameRate {
+		frame_rate: f32
+	},
+	#[error("frame {frame} is {len} bytes long, but the frames of the sequence \
+		are {expected} bytes long")]
+	InvalidFrame {
+		frame: usize,
+		len: usize,
+		expected: usize
+	},
+	#[error("the frame decoder stopped before decoding frame {frame}")]
+	DecoderStopped {
+		frame: usize
+	},
+	#[error("could not create or update the frame sequence texture: {0}")]
+	Texture(#[from] TextureError),
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn millis(millis: u64) -> Duration {
+		Duration::from_millis(millis)
+	}
+
+	fn frames(clock: &mut PlaybackClock, count: usize) -> Vec<usize> {
+		let frame_time = clock.frame_time;
+		(0..count).map(|_| clock.advance(frame_time).frame).collect()
+	}
+
+	#[test]
+	fn frames_change_at_the_frame_rate() {
+		let mut clock = PlaybackClock::new(3, 10.0, PlaybackMode::Loop);
+		assert_eq!(clock.frame(), 0);
+		assert_eq!(clock.advance(millis(60)),
+			FrameStep { frame: 0, changed: false, skipped: 0 });
+		assert_eq!(clock.advance(millis(60)),
+			FrameStep { frame: 1, changed: true, skipped: 0 });
+		assert_eq!(clock.advance(millis(80)),
+			FrameStep { frame: 2, changed: true, skipped: 0 });
+		assert_eq!(clock.advance(millis(100)),
+			FrameStep { frame: 0, changed: true, skipped: 0 });
+	}
+
+	#[test]
+	fn playback_modes() {
+		let mut clock = PlaybackClock::new(4, 1.0, PlaybackMode::Loop);
+		assert_eq!(frames(&mut clock, 6), vec![1, 2, 3, 0, 1, 2]);
+
+		let mut clock = PlaybackClock::new(4, 1.0, PlaybackMode::PingPong);
+		assert_eq!(frames(&mut clock, 8), vec![1, 2, 3, 2, 1, 0, 1, 2]);
+
+		let mut clock = PlaybackClock::new(4, 1.0, PlaybackMode::Once);
+		assert_eq!(frames(&mut clock, 6), vec![1, 2, 3, 3, 3, 3]);
+		assert!(!clock.advance(millis(1000)).changed);
+
+		let mut clock = PlaybackClock::new(1, 1.0, PlaybackMode::PingPong);
+		assert_eq!(frames(&mut clock, 3), vec![0, 0, 0]);
+	}
+
+	#[test]
+	fn falling_behind_skips_frames() {
+		let mut clock = PlaybackClock::new(12, 12.0, PlaybackMode::Loop);
+		assert_eq!(clock.advance(millis(500)),
+			FrameStep { frame: 6, changed: true, skipped: 5 });
+
+		/* A whole loop goes by without the frame changing, but every one of
+		 * the frames in it was still skipped. */
+		assert_eq!(clock.advance(millis(1000)),
+			FrameStep { frame: 6, changed: false, skipped: 11 });
+
+		/* Frames past the end of a sequence that plays once aren't skipped. */
+		let mut clock = PlaybackClock::new(3, 1.0, PlaybackMode::Once);
+		assert_eq!(clock.advance(millis(60_000)),
+			FrameStep { frame: 2, changed: true, skipped: 1 });
+	}
+
+	#[test]
+	fn leftover_time_carries_over() {
+		/* Each frame is shown for 83.3ms, which isn't a whole number of the
+		 * 40ms ticks, so the frame must only change on the third one. */
+		let mut clock = PlaybackClock::new(12, 12.0, PlaybackMode::Loop);
+		assert!(!clock.advance(millis(40)).changed);
+		assert!(!clock.advance(millis(40)).changed);
+		assert!(clock.advance(millis(40)).changed);
+
+		let mut clock = PlaybackClock::new(2, 1000.0, PlaybackMode::Loop);
+		for _ in 0..1000 {
+			clock.advance(Duration::from_micros(999));
+		}
+		assert_eq!(clock.ticks, 999);
+	}
+
+	#[test]
+	fn upcoming_frames() {
+		let mut clock = PlaybackClock::new(4, 1.0, PlaybackMode::Loop);
+		clock.advance(millis(3000));
+		assert_eq!(clock.upcoming(2), vec![0, 1]);
+		assert_eq!(clock.upcoming(8), vec![0, 1, 2]);
+
+		let mut clock = PlaybackClock::new(4, 1.0, PlaybackMode::PingPong);
+		clock.advance(millis(2000));
+		assert_eq!(clock.upcoming(4), vec![3, 1, 0]);
+
+		let mut clock = PlaybackClock::new(4, 1.0, PlaybackMode::Once);
+		clock.advance(millis(2000));
+		assert_eq!(clock.upcoming(4), vec![3]);
+		clock.advance(millis(1000));
+		assert!(clock.upcoming(4).is_empty());
+	}
+
+	#[test]
+	fn frames_must_have_the_right_length() {
+		assert!(check_frame(0, 64, 64).is_ok());
+		assert!(matches!(check_frame(3, 63, 64),
+			Err(FrameSequenceError::InvalidFrame { frame: 3, len: 63, expected: 64 })));
+	}
+}